@@ -116,6 +116,7 @@ fn parse_toml_kv(line: &str) -> Option<(String, String)> {
 }
 
 /// 将 "1.7.9" 解析为 (major, minor, patch)
+#[cfg(target_os = "windows")]
 fn parse_version(version: &str) -> (u64, u64, u64) {
     let mut parts = version.split('.');
     let major = parts.next().and_then(|s| s.parse().ok()).unwrap_or(1);
@@ -0,0 +1,144 @@
+//! 会话计费事件模块
+//!
+//! 订阅事件总线上的会话关闭事件，把会话记录（用户、目标、流量、时长）异步投递到
+//! HTTP 回调和 / 或 spool 文件（JSON Lines），供外部计费 / SIEM 系统近实时消费
+
+use crate::config::AccountingConfig;
+use crate::events::Event;
+use anyhow::Result;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::broadcast;
+use tracing::{debug, warn};
+
+/// 回调请求超时
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 会话计费事件投递器
+#[derive(Debug)]
+pub struct Accounting {
+    dropped: Arc<AtomicU64>,
+}
+
+impl Accounting {
+    /// 按配置启动后台投递任务，消费 `events` 中的会话关闭事件
+    pub fn spawn(config: &AccountingConfig, events: broadcast::Receiver<Event>) -> Result<Self> {
+        let webhook = match config.webhook_url {
+            Some(ref url) => Some(Webhook::new(
+                url,
+                config.webhook_token.clone(),
+                WEBHOOK_TIMEOUT,
+            )?),
+            None => None,
+        };
+        let spool = config.spool_file.as_ref().map(PathBuf::from);
+
+        let dropped = Arc::new(AtomicU64::new(0));
+        tokio::spawn(run_delivery(events, webhook, spool, Arc::clone(&dropped)));
+
+        Ok(Self { dropped })
+    }
+
+    /// 因投递跟不上而丢弃的事件数
+    pub fn dropped_count(&self) -> u64 {
+        self.dropped.load(Ordering::Relaxed)
+    }
+}
+
+/// HTTP 回调目标（POST JSON，可选 Bearer 令牌）
+pub(crate) struct Webhook {
+    client: reqwest::Client,
+    pub(crate) url: String,
+    token: Option<String>,
+}
+
+impl Webhook {
+    pub(crate) fn new(url: &str, token: Option<String>, timeout: Duration) -> Result<Self> {
+        let client = reqwest::Client::builder()
+            .user_agent("VLESS-Rust/1.0")
+            .timeout(timeout)
+            .build()?;
+        Ok(Self {
+            client,
+            url: url.to_string(),
+            token,
+        })
+    }
+
+    pub(crate) async fn post(&self, body: String) -> Result<()> {
+        let mut request = self
+            .client
+            .post(&self.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body);
+        if let Some(ref token) = self.token {
+            request = request.bearer_auth(token);
+        }
+        let response = request.send().await?;
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("HTTP status {}", response.status()));
+        }
+        Ok(())
+    }
+}
+
+/// 后台投递循环：先写 spool 文件，再调用回调
+async fn run_delivery(
+    mut events: broadcast::Receiver<Event>,
+    webhook: Option<Webhook>,
+    spool: Option<PathBuf>,
+    dropped: Arc<AtomicU64>,
+) {
+    loop {
+        let record = match events.recv().await {
+            Ok(Event::SessionClosed(record)) => record,
+            Ok(_) => continue,
+            Err(broadcast::error::RecvError::Lagged(n)) => {
+                dropped.fetch_add(n, Ordering::Relaxed);
+                warn!("Accounting lagged behind, {} events dropped", n);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+
+        let line = match serde_json::to_string(&*record) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("Failed to serialize session event: {}", e);
+                continue;
+            }
+        };
+
+        if let Some(ref path) = spool {
+            if let Err(e) = append_line(path, &line).await {
+                warn!("Failed to write accounting spool {}: {}", path.display(), e);
+            }
+        }
+
+        if let Some(ref webhook) = webhook {
+            match webhook.post(line).await {
+                Ok(()) => debug!("Session event delivered to {}", webhook.url),
+                Err(e) => warn!("Accounting webhook {} failed: {}", webhook.url, e),
+            }
+        }
+    }
+}
+
+/// 向 spool 文件追加一行
+///
+/// 每次重新打开文件，兼容 logrotate 等外部轮转
+pub async fn append_line(path: &Path, line: &str) -> Result<()> {
+    let mut file = tokio::fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(path)
+        .await?;
+    let mut data = Vec::with_capacity(line.len() + 1);
+    data.extend_from_slice(line.as_bytes());
+    data.push(b'\n');
+    file.write_all(&data).await?;
+    Ok(())
+}
@@ -1,26 +1,27 @@
 //! 地址解析工具模块
 //!
-//! 提供统一的地址解析功能，供 TCP 和 WebSocket 模块复用
+//! 提供统一的目标连接功能（经 [`Resolver`] 解析），供 TCP 和 WebSocket 模块复用；
+//! 域名解析出多个地址时按 Happy Eyeballs（RFC 8305）错开并发建连
 
-use crate::config::PerformanceConfig;
-use crate::socket::configure_tcp_socket;
+use crate::config::{DomainStrategy, PerformanceConfig};
+use crate::dns::Resolver;
+use crate::socket::{apply_tcp_mtu_options, configure_tcp_socket};
 use anyhow::{anyhow, Result};
+use futures_util::stream::{FuturesUnordered, StreamExt};
+use socket2::SockRef;
 use std::net::SocketAddr;
-use tokio::net::TcpStream;
+use std::time::{Duration, Instant};
+use tokio::net::{TcpSocket, TcpStream};
 
-/// 解析目标地址
-///
-/// 统一处理域名解析和 IP 地址转换
-///
-/// # Arguments
-/// * `domain` - 域名字节
-/// * `port` - 目标端口
-///
-/// # Returns
-/// * `SocketAddr` - 解析后的地址
-pub async fn resolve_target_address(domain: &[u8], port: u16) -> Result<SocketAddr> {
-    let domain_str = std::str::from_utf8(domain).map_err(|_| anyhow!("Invalid domain encoding"))?;
-    resolve_address(domain_str, port).await
+/// 目标连接各阶段耗时
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ConnectTiming {
+    /// 实际连接的地址
+    pub resolved: Option<SocketAddr>,
+    /// DNS 解析耗时（IP 目标为 0）
+    pub dns: Duration,
+    /// TCP 建连耗时
+    pub connect: Duration,
 }
 
 /// 解析地址字符串
@@ -30,36 +31,12 @@ pub async fn resolve_target_address(domain: &[u8], port: u16) -> Result<SocketAd
 /// * `port` - 目标端口
 ///
 /// # Returns
-/// * `SocketAddr` - 解析后的地址
+/// * `SocketAddr` - 解析后的地址（经默认 [`Resolver`]，取第一个地址）
+#[allow(dead_code)]
 pub async fn resolve_address(addr: &str, port: u16) -> Result<SocketAddr> {
-    let addr_str = format!("{}:{}", addr, port);
-    // 直接使用迭代器的 next()，避免收集所有地址到Vec
-    let mut addrs = tokio::net::lookup_host(&addr_str).await?;
-    addrs
-        .next()
-        .ok_or_else(|| anyhow!("Failed to resolve address: {}", addr))
-}
-
-/// 从协议地址解析目标
-///
-/// 供 TCP/WS 代理使用，统一处理 Address 枚举
-///
-/// # Arguments
-/// * `address` - 协议层地址
-/// * `port` - 目标端口
-///
-/// # Returns
-/// * `SocketAddr` - 解析后的地址
-pub async fn resolve_protocol_address(
-    address: &crate::protocol::Address,
-    port: u16,
-) -> Result<SocketAddr> {
-    use crate::protocol::Address;
-
-    match address {
-        Address::Domain(domain) => resolve_target_address(domain, port).await,
-        _ => address.to_socket_addr(port),
-    }
+    Resolver::default()
+        .resolve(addr, port, DomainStrategy::AsIs)
+        .await
 }
 
 /// 连接到目标服务器
@@ -72,19 +49,111 @@ pub async fn resolve_protocol_address(
 /// * `perf_config` - 性能配置
 ///
 /// # Returns
-/// * `Result<TcpStream>` - 连接成功返回 TCP 流
+/// * `Result<(TcpStream, ConnectTiming)>` - 连接成功返回 TCP 流与各阶段耗时
 pub async fn connect_target(
     address: &crate::protocol::Address,
     port: u16,
     perf_config: &PerformanceConfig,
-) -> Result<TcpStream> {
-    let target_addr = resolve_protocol_address(address, port).await?;
-    let stream = TcpStream::connect(target_addr).await?;
+) -> Result<(TcpStream, ConnectTiming)> {
+    let resolver = Resolver::default();
+    connect_target_checked(address, port, perf_config, &resolver, |_| Ok(())).await
+}
+
+/// 连接到目标服务器，经 `resolver` 解析后先用 `check` 检查各候选地址，跳过未通过的地址；
+/// 全部未通过时返回第一个检查错误，不发起连接
+pub async fn connect_target_checked(
+    address: &crate::protocol::Address,
+    port: u16,
+    perf_config: &PerformanceConfig,
+    resolver: &Resolver,
+    check: impl Fn(SocketAddr) -> Result<()>,
+) -> Result<(TcpStream, ConnectTiming)> {
+    let started = Instant::now();
+    let mut candidates = resolver.resolve_candidates(address, port).await?;
+    let mut rejected = None;
+    candidates.retain(|addr| match check(*addr) {
+        Ok(()) => true,
+        Err(e) => {
+            rejected.get_or_insert(e);
+            false
+        }
+    });
+    if let Some(e) = rejected.filter(|_| candidates.is_empty()) {
+        return Err(e);
+    }
+    let resolved_at = Instant::now();
+
+    let (stream, target_addr) =
+        happy_eyeballs(&candidates, resolver.happy_eyeballs_delay(), perf_config).await?;
+    let timing = ConnectTiming {
+        resolved: Some(target_addr),
+        dns: resolved_at - started,
+        connect: resolved_at.elapsed(),
+    };
+
     configure_tcp_socket(
         &stream,
         perf_config.tcp_recv_buffer,
         perf_config.tcp_send_buffer,
         perf_config.tcp_nodelay,
     )?;
-    Ok(stream)
+    Ok((stream, timing))
+}
+
+/// 按顺序尝试候选地址，返回最先连通的连接
+///
+/// 前一个尝试在 `delay` 内未完成时并发发起下一个，某个尝试失败时立即发起下一个；
+/// 任一连接成功后取消其余尝试。`delay` 为 0 时依次尝试。全部失败时返回最后一个错误
+pub async fn happy_eyeballs(
+    candidates: &[SocketAddr],
+    delay: Duration,
+    perf_config: &PerformanceConfig,
+) -> Result<(TcpStream, SocketAddr)> {
+    let mut pending = candidates.iter().copied();
+    let mut attempts = FuturesUnordered::new();
+    let mut last_error = None;
+    loop {
+        if attempts.is_empty() {
+            match pending.next() {
+                Some(addr) => attempts.push(dial(addr, perf_config)),
+                None => {
+                    return Err(last_error.unwrap_or_else(|| anyhow!("No address to connect to")))
+                }
+            }
+        }
+        let staggered = !delay.is_zero() && pending.len() > 0;
+        tokio::select! {
+            Some((addr, result)) = attempts.next() => match result {
+                Ok(stream) => return Ok((stream, addr)),
+                Err(e) => {
+                    last_error = Some(anyhow!("Failed to connect to {}: {}", addr, e));
+                    if let Some(addr) = pending.next() {
+                        attempts.push(dial(addr, perf_config));
+                    }
+                }
+            },
+            _ = tokio::time::sleep(delay), if staggered => {
+                if let Some(addr) = pending.next() {
+                    attempts.push(dial(addr, perf_config));
+                }
+            }
+        }
+    }
+}
+
+/// 单次建连；MSS 需在连接前设置才会写入握手通告
+async fn dial(
+    addr: SocketAddr,
+    perf_config: &PerformanceConfig,
+) -> (SocketAddr, std::io::Result<TcpStream>) {
+    let result = async {
+        let socket = match addr {
+            SocketAddr::V4(_) => TcpSocket::new_v4()?,
+            SocketAddr::V6(_) => TcpSocket::new_v6()?,
+        };
+        apply_tcp_mtu_options(&SockRef::from(&socket), perf_config);
+        socket.connect(addr).await
+    }
+    .await;
+    (addr, result)
 }
@@ -2,13 +2,28 @@
 //!
 //! 处理 HTTP 请求，提供 VLESS 链接生成和服务器信息展示
 
+use crate::blocklist::Blocklist;
+use crate::capture::{CaptureManager, CaptureRule};
 use crate::config::ProtocolType;
+use crate::config_diff::ReloadPreview;
+use crate::destinations::DestinationTracker;
+use crate::dns::Resolver;
 use crate::http::{
-    build_400_response, build_404_response, build_html_response, build_json_response,
-    parse_http_request,
+    build_400_response, build_404_response, build_503_json_response, build_html_response,
+    build_json_response, extract_header_value, parse_http_request, ApiError, ErrorCode,
 };
+use crate::limiter::SessionLimiter;
+use crate::maintenance::{Maintenance, MaintenanceMode};
+use crate::overhead::OverheadStats;
+use crate::readiness::{AcceptGate, Readiness};
+use crate::route_stats::RouteStats;
+use crate::runtime_stats::{self, ListenerTasks};
+use crate::stall::StallStats;
+use crate::stats::{query_users, UserQuery, UserStats};
+use crate::tr;
+use crate::udp_session::{UdpSessionKey, UdpSessionTable};
 use crate::version::VERSION_INFO;
-use crate::vless_link::{generate_vless_links, VlessLinkConfig};
+use crate::vless_link::{generate_vless_links, UserLink, VlessLinkConfig};
 use anyhow::Result;
 use std::collections::HashMap;
 use std::sync::Arc;
@@ -27,8 +42,378 @@ pub struct ApiConfig {
     pub protocol: ProtocolType,
     /// WebSocket 路径（仅 WebSocket 协议）
     pub ws_path: Option<String>,
+    /// WebSocket Host 请求头（写入链接）
+    pub ws_host: Option<String>,
+    /// TLS SNI（写入链接）
+    pub sni: Option<String>,
     /// 用户邮箱映射（Arc 共享，避免深拷贝）
     pub user_emails: Arc<HashMap<Uuid, Option<Arc<str>>>>,
+    /// 维护说明（维护中或有计划中的维护时显示在信息页顶部）
+    pub maintenance_notice: Option<String>,
+}
+
+/// 管理 API 配置
+pub struct AdminConfig {
+    /// 访问令牌（Authorization: Bearer <token>）
+    pub token: String,
+    /// 目标地址统计器
+    pub destinations: Option<Arc<DestinationTracker>>,
+    /// 用户流量统计
+    pub user_stats: Option<Arc<UserStats>>,
+    /// 全部用户的分享链接
+    pub user_links: Vec<UserLink>,
+    /// 拦截列表（用于数据版本查询与重新加载）
+    pub blocklist: Option<Arc<Blocklist>>,
+    /// 会话抓包（未设置 `api.capture_dir` 时为空）
+    pub capture: Option<Arc<CaptureManager>>,
+    /// 重新加载预演（比较磁盘配置与运行中的配置）
+    pub reload_preview: Option<Arc<ReloadPreview>>,
+    /// 按传输方式统计的协议开销
+    pub overhead: Arc<OverheadStats>,
+    /// 按监听入站统计的连接任务数
+    pub listener_tasks: Arc<ListenerTasks>,
+    /// 目标域名解析器（缓存统计）
+    pub resolver: Arc<Resolver>,
+    /// 按路由统计的会话数与流量
+    pub route_stats: Arc<RouteStats>,
+    /// 新会话准入开关
+    pub gate: Arc<AcceptGate>,
+    /// 维护模式
+    pub maintenance: Arc<Maintenance>,
+}
+
+/// 管理 API 路径前缀
+const ADMIN_PREFIX: &str = "/api/";
+
+/// 是否为管理 API 请求
+pub fn is_admin_request(data: &[u8]) -> bool {
+    parse_http_request(data).is_some_and(|q| q.path.starts_with(ADMIN_PREFIX))
+}
+
+/// 是否为就绪检查请求（`GET /readyz`）
+pub fn is_readyz_request(data: &[u8]) -> bool {
+    parse_http_request(data).is_some_and(|q| q.path == "/readyz")
+}
+
+/// 就绪检查：全部入站正在监听时返回 200，否则返回 503（降级）；
+/// 维护模式与暂停接受新会话时同样返回 503（`maintenance` / `paused`），便于负载均衡摘除
+///
+/// 无需令牌即可获取状态；携带有效令牌时附带各入站的监听地址与失败原因
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_readyz_request(
+    mut stream: TcpStream,
+    data: &[u8],
+    token: &str,
+    readiness: &Readiness,
+    sessions: Option<&SessionLimiter>,
+    udp_sessions: Option<&UdpSessionTable<UdpSessionKey>>,
+    stalls: Option<&StallStats>,
+    gate: Option<&AcceptGate>,
+    maintenance: Option<&Maintenance>,
+) -> Result<()> {
+    let degraded = readiness.is_degraded();
+    let paused = gate.is_some_and(|gate| gate.is_paused());
+    let in_maintenance = maintenance.is_some_and(|maintenance| maintenance.is_active());
+    let status = if in_maintenance {
+        "maintenance"
+    } else if paused {
+        "paused"
+    } else if degraded {
+        "degraded"
+    } else {
+        "ready"
+    };
+    let mut body = serde_json::json!({ "status": status });
+    if authorize(data, token) {
+        body["inbounds"] = serde_json::json!(readiness.inbounds());
+        body["accept_failures"] = serde_json::json!(readiness.accept_failures());
+        body["sessions"] = serde_json::json!(sessions.map(|limiter| limiter.stats()));
+        body["udp_sessions"] = serde_json::json!(udp_sessions.map(|table| table.stats()));
+        body["stalls"] = serde_json::json!(stalls.map(|stalls| stalls.snapshot()));
+        body["gate"] = serde_json::json!(gate.map(|gate| gate.status()));
+        body["maintenance"] =
+            serde_json::json!(maintenance.map(|maintenance| maintenance.status()));
+    }
+    let response = if degraded || paused || in_maintenance {
+        build_503_json_response(&body.to_string())
+    } else {
+        build_json_response(&body.to_string())
+    };
+    stream.write_all(&response).await?;
+    Ok(())
+}
+
+/// 校验 Authorization 请求头（恒定时间比较，避免按耗时猜测令牌）
+pub fn authorize(data: &[u8], token: &str) -> bool {
+    let Some(header) = extract_header_value(data, "Authorization") else {
+        return false;
+    };
+    let Some(provided) = header.strip_prefix("Bearer ") else {
+        return false;
+    };
+    let (a, b) = (provided.trim().as_bytes(), token.as_bytes());
+    a.len() == b.len() && a.iter().zip(b).fold(0u8, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+/// 处理管理 API 请求
+pub async fn handle_admin_request(
+    mut stream: TcpStream,
+    data: &[u8],
+    config: &AdminConfig,
+) -> Result<()> {
+    let response = match admin_response(data, config) {
+        Ok(body) => build_json_response(&body.to_string()),
+        Err(e) => e.to_response(),
+    };
+    stream.write_all(&response).await?;
+    Ok(())
+}
+
+/// 管理 API 路由：成功返回 JSON，失败返回带错误码的 [`ApiError`]
+fn admin_response(data: &[u8], config: &AdminConfig) -> Result<serde_json::Value, ApiError> {
+    if !authorize(data, &config.token) {
+        return Err(ApiError::new(
+            ErrorCode::Unauthorized,
+            tr!("未授权", "Unauthorized"),
+        ));
+    }
+
+    let query = parse_http_request(data)
+        .ok_or_else(|| ApiError::new(ErrorCode::BadRequest, "Invalid HTTP request"))?;
+
+    match query.path.as_str() {
+        "/api/version" => Ok(version_json(config)),
+        "/api/reload" => {
+            if query.method != "POST" {
+                return Err(method_not_allowed(&["POST"]));
+            }
+            if query
+                .params
+                .get("dry_run")
+                .is_some_and(|v| v == "1" || v == "true")
+            {
+                preview_reload(config)
+            } else {
+                Ok(reload_data(config))
+            }
+        }
+        "/api/pause" | "/api/resume" => {
+            if query.method != "POST" {
+                return Err(method_not_allowed(&["POST"]));
+            }
+            Ok(gate_action(config, query.path == "/api/pause"))
+        }
+        "/api/maintenance" => maintenance_action(config, &query.method, &query.params),
+        "/api/capture" | "/api/capture/stop" => {
+            if query.method != "POST" && query.path != "/api/capture" {
+                return Err(method_not_allowed(&["POST"]));
+            }
+            capture_action(config, &query.method, &query.path, &query.params)
+        }
+        "/api/destinations" => Ok(destinations_json(config, query.params.contains_key("all"))),
+        "/api/overhead" => Ok(serde_json::json!({ "transports": config.overhead.snapshot() })),
+        "/api/runtime" => Ok(runtime_json(config)),
+        "/api/stats" => Ok(stats_json(config)),
+        "/api/users" => UserQuery::from_params(&query.params)
+            .map(|user_query| users_json(config, &user_query))
+            .map_err(|e| ApiError::new(ErrorCode::InvalidParameter, e.to_string())),
+        path => user_link_json(config, path)
+            .ok_or_else(|| ApiError::new(ErrorCode::NotFound, tr!("未找到", "Not Found"))),
+    }
+}
+
+/// 请求方法不被支持
+fn method_not_allowed(allowed: &[&str]) -> ApiError {
+    ApiError::new(
+        ErrorCode::MethodNotAllowed,
+        tr!("不支持的请求方法", "Method Not Allowed"),
+    )
+    .with_details(serde_json::json!({ "allowed": allowed }))
+}
+
+/// tokio 运行时指标与各监听入站的连接任务数
+fn runtime_json(config: &AdminConfig) -> serde_json::Value {
+    serde_json::json!(runtime_stats::snapshot(&config.listener_tasks))
+}
+
+/// 解析器缓存统计与按路由 / 路由规则的会话数和流量
+fn stats_json(config: &AdminConfig) -> serde_json::Value {
+    let routing = config.route_stats.snapshot();
+    serde_json::json!({
+        "resolver": config.resolver.stats(),
+        "routes": routing.routes,
+        "rules": routing.rules,
+    })
+}
+
+/// 程序版本与运行时数据版本
+fn version_json(config: &AdminConfig) -> serde_json::Value {
+    serde_json::json!({
+        "product": VERSION_INFO.product_name,
+        "version": VERSION_INFO.version,
+        "data": {
+            "blocklist": config.blocklist.as_ref().map(|list| list.version_info()),
+        },
+    })
+}
+
+/// 触发运行时数据重新加载（后台进行，通过 `/api/version` 查看结果）
+fn reload_data(config: &AdminConfig) -> serde_json::Value {
+    let mut reloading = Vec::new();
+    if let Some(ref list) = config.blocklist {
+        list.request_reload();
+        reloading.push("blocklist");
+        info!("Blocklist reload requested via admin API");
+    }
+    serde_json::json!({ "success": true, "reloading": reloading })
+}
+
+/// 暂停或恢复接受新的代理会话（已有会话与信息页不受影响）
+fn gate_action(config: &AdminConfig, pause: bool) -> serde_json::Value {
+    let changed = if pause {
+        config.gate.pause()
+    } else {
+        config.gate.resume()
+    };
+    if changed {
+        info!(
+            "{} accepting new sessions via admin API",
+            if pause { "Paused" } else { "Resumed" }
+        );
+    }
+    serde_json::json!({
+        "success": true,
+        "paused": config.gate.is_paused(),
+        "changed": changed,
+        "rejected": config.gate.status().rejected,
+    })
+}
+
+/// 维护模式：GET 返回状态，POST `?mode=on|off|scheduled` 切换运行时开关
+fn maintenance_action(
+    config: &AdminConfig,
+    method: &str,
+    params: &HashMap<String, String>,
+) -> Result<serde_json::Value, ApiError> {
+    if method != "POST" {
+        return Ok(serde_json::json!(config.maintenance.status()));
+    }
+    let name = params.get("mode").map(String::as_str).unwrap_or_default();
+    let mode = match name {
+        "on" => MaintenanceMode::On,
+        "off" => MaintenanceMode::Off,
+        "scheduled" => MaintenanceMode::Scheduled,
+        _ => {
+            return Err(ApiError::new(
+                ErrorCode::InvalidParameter,
+                "Invalid mode, expected on, off or scheduled",
+            )
+            .with_details(serde_json::json!({ "parameter": "mode" })))
+        }
+    };
+    let changed = config.maintenance.set_mode(mode);
+    if changed {
+        info!("Maintenance mode set to {} via admin API", name);
+    }
+    let mut body = serde_json::json!(config.maintenance.status());
+    body["success"] = serde_json::json!(true);
+    body["changed"] = serde_json::json!(changed);
+    Ok(body)
+}
+
+/// 重新加载预演：校验磁盘上的配置并返回与运行中配置的差异，不做修改
+fn preview_reload(config: &AdminConfig) -> Result<serde_json::Value, ApiError> {
+    let preview = config
+        .reload_preview
+        .as_ref()
+        .ok_or_else(|| ApiError::new(ErrorCode::FeatureDisabled, "Dry run is not available"))?;
+    let diff = preview
+        .preview()
+        .map_err(|e| ApiError::new(ErrorCode::ConfigInvalid, e.to_string()))?;
+    Ok(serde_json::json!({
+        "success": true,
+        "dry_run": true,
+        "restart_required": !diff.is_empty(),
+        "diff": diff,
+    }))
+}
+
+/// 抓包规则管理：`GET /api/capture` 列出规则，`POST /api/capture` 添加，
+/// `POST /api/capture/stop?id=` 移除
+fn capture_action(
+    config: &AdminConfig,
+    method: &str,
+    path: &str,
+    params: &HashMap<String, String>,
+) -> Result<serde_json::Value, ApiError> {
+    let Some(ref capture) = config.capture else {
+        return Err(ApiError::new(
+            ErrorCode::FeatureDisabled,
+            "Capture is disabled, set api.capture_dir",
+        )
+        .with_details(serde_json::json!({ "setting": "api.capture_dir" })));
+    };
+
+    if path == "/api/capture/stop" {
+        let id = params
+            .get("id")
+            .and_then(|id| id.parse::<u64>().ok())
+            .ok_or_else(|| ApiError::new(ErrorCode::InvalidParameter, "Invalid id"))?;
+        if !capture.stop(id) {
+            return Err(
+                ApiError::new(ErrorCode::NotFound, tr!("未找到", "Not Found"))
+                    .with_details(serde_json::json!({ "id": id })),
+            );
+        }
+        return Ok(serde_json::json!({ "success": true, "stopped": id }));
+    }
+
+    if method == "POST" {
+        let rule = CaptureRule::from_params(params)
+            .map_err(|e| ApiError::new(ErrorCode::InvalidParameter, e.to_string()))?;
+        let rule = capture
+            .start(rule)
+            .map_err(|e| ApiError::new(ErrorCode::CaptureRejected, e.to_string()))?;
+        return Ok(serde_json::json!(rule));
+    }
+    Ok(serde_json::json!({ "rules": capture.rules() }))
+}
+
+/// 目标地址统计：默认仅列出问题目标，`?all=1` 列出全部
+fn destinations_json(config: &AdminConfig, all: bool) -> serde_json::Value {
+    let Some(ref tracker) = config.destinations else {
+        return serde_json::json!({ "tracked": 0, "problem_destinations": [] });
+    };
+
+    let mut body = serde_json::json!({
+        "tracked": tracker.tracked_count(),
+        "problem_destinations": tracker.problems(),
+    });
+    if all {
+        body["destinations"] = serde_json::json!(tracker.reports());
+    }
+    body
+}
+
+/// 用户列表：支持分页、排序、搜索与活跃过滤
+fn users_json(config: &AdminConfig, query: &UserQuery) -> serde_json::Value {
+    let users = config
+        .user_stats
+        .as_ref()
+        .map(|stats| stats.snapshot())
+        .unwrap_or_default();
+    serde_json::json!(query_users(users, query))
+}
+
+/// 单个用户的分享链接：`/api/users/{uuid}/url`，路径不匹配或用户不存在时返回 None
+fn user_link_json(config: &AdminConfig, path: &str) -> Option<serde_json::Value> {
+    let uuid = path
+        .strip_prefix("/api/users/")?
+        .strip_suffix("/url")?
+        .parse::<Uuid>()
+        .ok()?;
+    let link = config.user_links.iter().find(|l| l.uuid == uuid)?;
+    Some(serde_json::json!(link))
 }
 
 /// 处理 HTTP 请求
@@ -88,6 +473,8 @@ async fn handle_link_request(
                 host: config.public_ip.clone(),
                 port: config.port,
                 ws_path: config.ws_path.clone(),
+                ws_host: config.ws_host.clone(),
+                sni: config.sni.clone(),
                 alias: email.to_string(),
             };
 
@@ -102,9 +489,12 @@ async fn handle_link_request(
                             "ws_b64": ws.base64
                         })
                     } else {
-                        serde_json::json!({
-                            "error": "WebSocket link not available"
-                        })
+                        let error = ApiError::new(
+                            ErrorCode::LinkUnavailable,
+                            tr!("WebSocket 链接不可用", "WebSocket link not available"),
+                        );
+                        stream.write_all(&error.to_response()).await?;
+                        return Ok(());
                     }
                 }
                 ProtocolType::Tcp => {
@@ -120,11 +510,8 @@ async fn handle_link_request(
             info!("Served VLESS links for email: {}", email);
         }
         None => {
-            let response_json = serde_json::json!({
-                "error": "User not found"
-            });
-            let response = build_json_response(&response_json.to_string());
-            stream.write_all(&response).await?;
+            let error = ApiError::new(ErrorCode::UserNotFound, tr!("用户不存在", "User not found"));
+            stream.write_all(&error.to_response()).await?;
         }
     }
 
@@ -140,7 +527,8 @@ async fn handle_info_page(stream: &mut TcpStream, config: &ApiConfig) -> Result<
 
     let ws_path_info = if config.protocol == ProtocolType::WebSocket {
         format!(
-            "<tr><td>WS Path</td><td><code>{}</code></td></tr>",
+            "<tr><td>{}</td><td><code>{}</code></td></tr>",
+            tr!("WS 路径", "WS Path"),
             html_escape(config.ws_path.as_deref().unwrap_or("/"))
         )
     } else {
@@ -156,9 +544,15 @@ async fn handle_info_page(stream: &mut TcpStream, config: &ApiConfig) -> Result<
             .replace('\'', "&#x27;")
     }
 
+    let maintenance_banner = config
+        .maintenance_notice
+        .as_deref()
+        .map(|notice| format!(r#"<div class="notice">{}</div>"#, html_escape(notice)))
+        .unwrap_or_default();
+
     let html = format!(
         r#"<!DOCTYPE html>
-<html lang="zh-CN">
+<html lang="{html_lang}">
 <head>
     <meta charset="UTF-8">
     <meta name="viewport" content="width=device-width, initial-scale=1.0">
@@ -181,29 +575,31 @@ async fn handle_info_page(stream: &mut TcpStream, config: &ApiConfig) -> Result<
         .api-url {{ font-family: monospace; color: #4ecdc4; word-break: break-all; }}
         .api-note {{ font-size: 0.75rem; color: #888; margin-top: 8px; }}
         .footer {{ text-align: center; font-size: 0.75rem; color: #666; margin-top: 24px; }}
+        .notice {{ background: rgba(255,193,7,0.15); border: 1px solid rgba(255,193,7,0.5); border-radius: 12px; padding: 16px 20px; margin-bottom: 24px; color: #ffd54f; }}
     </style>
 </head>
 <body>
     <div class="container">
+        {maintenance_banner}
         <div class="card">
             <h1>{}</h1>
             <p class="version">v{}</p>
             <table>
-                <tr><td>Author</td><td>{}</td></tr>
+                <tr><td>{label_author}</td><td>{}</td></tr>
                 <tr><td>IP</td><td><code>{}</code></td></tr>
-                <tr><td>Port</td><td><code>{}</code></td></tr>
-                <tr><td>Protocol</td><td><code>{}</code></td></tr>
+                <tr><td>{label_port}</td><td><code>{}</code></td></tr>
+                <tr><td>{label_protocol}</td><td><code>{}</code></td></tr>
                 {ws_path_info}
             </table>
         </div>
         <div class="card api-section">
-            <p class="api-title">Get VLESS Link</p>
+            <p class="api-title">{label_link}</p>
             <div class="api-box">
                 <p class="api-url">http://{}:{}/?email=your_email</p>
             </div>
-            <p class="api-note">Replace <code>your_email</code> with the email configured in config.json</p>
+            <p class="api-note">{label_note}</p>
         </div>
-        <p class="footer">Powered by {} v{}</p>
+        <p class="footer">{label_powered} {} v{}</p>
     </div>
 </body>
 </html>"#,
@@ -219,6 +615,16 @@ async fn handle_info_page(stream: &mut TcpStream, config: &ApiConfig) -> Result<
         config.port,
         html_escape(VERSION_INFO.product_name),
         VERSION_INFO.version,
+        html_lang = tr!("zh-CN", "en"),
+        label_author = tr!("作者", "Author"),
+        label_port = tr!("端口", "Port"),
+        label_protocol = tr!("协议", "Protocol"),
+        label_link = tr!("获取 VLESS 链接", "Get VLESS Link"),
+        label_note = tr!(
+            "将 <code>your_email</code> 替换为 config.json 中配置的邮箱",
+            "Replace <code>your_email</code> with the email configured in config.json"
+        ),
+        label_powered = tr!("技术支持：", "Powered by"),
     );
 
     let response = build_html_response(&html);
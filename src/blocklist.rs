@@ -0,0 +1,312 @@
+//! 广告 / 追踪域名拦截列表模块
+//!
+//! 支持订阅 hosts 格式或纯域名列表格式的远程拦截列表并定时刷新，
+//! 供 DNS 拦截与 TCP 目标连接共同使用
+
+use crate::config::BlocklistConfig;
+use crate::protocol::{Address, VlessRequest};
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::Notify;
+use tracing::{debug, info, warn};
+use uuid::Uuid;
+
+/// 单个列表下载超时
+const FETCH_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// 单个列表的大小上限（字节），超过时放弃本次下载并保留上次的内容
+pub const MAX_LIST_SIZE: usize = 32 * 1024 * 1024;
+
+/// 最短刷新间隔（秒），避免配置过小导致频繁请求
+const MIN_REFRESH_INTERVAL: u64 = 300;
+
+/// 解析拦截列表文本
+///
+/// 支持三种常见格式：
+/// - hosts 格式：`0.0.0.0 ads.example.com`
+/// - 纯域名列表：`ads.example.com`
+/// - Adblock 域名规则：`||ads.example.com^`
+///
+/// `#` / `!` 开头的行视为注释
+pub fn parse_blocklist(content: &str) -> HashSet<String> {
+    let mut domains = HashSet::new();
+
+    for line in content.lines() {
+        let line = line.trim();
+        if line.is_empty() || line.starts_with('#') || line.starts_with('!') {
+            continue;
+        }
+
+        // 去掉行尾注释
+        let line = line.split('#').next().unwrap_or("").trim();
+
+        let domain = if let Some(rule) = line.strip_prefix("||") {
+            // Adblock 规则只接受纯域名形式
+            match rule.strip_suffix('^') {
+                Some(d) if !d.contains(['/', '*', '$']) => d,
+                _ => continue,
+            }
+        } else {
+            let mut parts = line.split_whitespace();
+            let first = parts.next().unwrap_or("");
+            match parts.next() {
+                // hosts 格式：第一列为 IP
+                Some(host) if first.parse::<std::net::IpAddr>().is_ok() => host,
+                Some(_) => continue,
+                None => first,
+            }
+        };
+
+        let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+        if is_plausible_domain(&domain) {
+            domains.insert(domain);
+        }
+    }
+
+    domains
+}
+
+/// 过滤 hosts 文件中的本地条目与非法域名
+fn is_plausible_domain(domain: &str) -> bool {
+    !domain.is_empty()
+        && domain.contains('.')
+        && !matches!(
+            domain,
+            "localhost" | "localhost.localdomain" | "local" | "broadcasthost"
+        )
+        && domain
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'-' || b == b'.' || b == b'_')
+}
+
+/// 拦截列表
+#[derive(Debug)]
+pub struct Blocklist {
+    /// 配置中直接填写的域名
+    static_domains: HashSet<String>,
+    /// 每个订阅地址最近一次成功下载的域名
+    remote: RwLock<HashMap<String, HashSet<String>>>,
+    /// 合并后的域名集合
+    merged: RwLock<Arc<HashSet<String>>>,
+    /// 不应用拦截列表的用户
+    exempt: HashSet<Uuid>,
+    /// 拦截次数
+    blocked: AtomicU64,
+    /// 数据版本号（每次合并后递增）
+    version: AtomicU64,
+    /// 最近一次合并的时间（Unix 时间戳，秒）
+    updated_at: AtomicU64,
+    /// 立即刷新的请求（API / SIGHUP）
+    reload: Notify,
+}
+
+/// 拦截列表数据版本信息
+#[derive(Debug, Clone, Serialize)]
+pub struct BlocklistVersion {
+    /// 数据版本号，启动时为 1，每次更新递增
+    pub version: u64,
+    /// 最近一次更新时间（Unix 时间戳，秒）
+    pub updated_at: u64,
+    pub domains: usize,
+    /// 已成功加载的订阅地址数
+    pub sources: usize,
+}
+
+impl Blocklist {
+    /// 根据配置创建拦截列表（远程列表需调用 `refresh` 后才生效）
+    pub fn new(config: &BlocklistConfig, exempt: HashSet<Uuid>) -> Self {
+        let static_domains: HashSet<String> = config
+            .domains
+            .iter()
+            .map(|d| d.trim_end_matches('.').to_ascii_lowercase())
+            .collect();
+        let merged = Arc::new(static_domains.clone());
+
+        Self {
+            static_domains,
+            remote: RwLock::new(HashMap::new()),
+            merged: RwLock::new(merged),
+            exempt,
+            blocked: AtomicU64::new(0),
+            version: AtomicU64::new(1),
+            updated_at: AtomicU64::new(unix_now()),
+            reload: Notify::new(),
+        }
+    }
+
+    /// 用户是否应用拦截列表
+    pub fn applies_to(&self, uuid: &Uuid) -> bool {
+        !self.exempt.contains(uuid)
+    }
+
+    /// 域名（或其任一上级域名）是否在列表中
+    pub fn contains(&self, domain: &str) -> bool {
+        let merged = Arc::clone(&self.merged.read().unwrap_or_else(|e| e.into_inner()));
+        let domain = domain.trim_end_matches('.').to_ascii_lowercase();
+
+        let mut candidate = domain.as_str();
+        loop {
+            if merged.contains(candidate) {
+                return true;
+            }
+            match candidate.find('.') {
+                Some(pos) => candidate = &candidate[pos + 1..],
+                None => return false,
+            }
+        }
+    }
+
+    /// 检查并计数：命中时累加拦截次数
+    pub fn check(&self, domain: &str) -> bool {
+        let hit = self.contains(domain);
+        if hit {
+            self.blocked.fetch_add(1, Ordering::Relaxed);
+        }
+        hit
+    }
+
+    /// 累计拦截次数
+    pub fn blocked_count(&self) -> u64 {
+        self.blocked.load(Ordering::Relaxed)
+    }
+
+    /// 当前列表中的域名数量
+    pub fn domain_count(&self) -> usize {
+        self.merged.read().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// 更新某个订阅地址的内容并重新合并
+    pub fn update_source(&self, url: &str, domains: HashSet<String>) {
+        let mut remote = self.remote.write().unwrap_or_else(|e| e.into_inner());
+        remote.insert(url.to_string(), domains);
+
+        let mut merged = self.static_domains.clone();
+        for set in remote.values() {
+            merged.extend(set.iter().cloned());
+        }
+        *self.merged.write().unwrap_or_else(|e| e.into_inner()) = Arc::new(merged);
+        self.version.fetch_add(1, Ordering::Relaxed);
+        self.updated_at.store(unix_now(), Ordering::Relaxed);
+    }
+
+    /// 数据版本信息
+    pub fn version_info(&self) -> BlocklistVersion {
+        BlocklistVersion {
+            version: self.version.load(Ordering::Relaxed),
+            updated_at: self.updated_at.load(Ordering::Relaxed),
+            domains: self.domain_count(),
+            sources: self.remote.read().unwrap_or_else(|e| e.into_inner()).len(),
+        }
+    }
+
+    /// 请求刷新任务立即重新下载订阅（未配置订阅地址时无效果）
+    pub fn request_reload(&self) {
+        self.reload.notify_one();
+    }
+
+    /// 下载并更新全部订阅地址
+    ///
+    /// 下载失败的地址保留上一次成功的内容
+    pub async fn refresh(&self, urls: &[String]) {
+        let client = match reqwest::Client::builder()
+            .user_agent("VLESS-Rust/1.0")
+            .timeout(FETCH_TIMEOUT)
+            .build()
+        {
+            Ok(c) => c,
+            Err(e) => {
+                warn!("Failed to create HTTP client for blocklists: {}", e);
+                return;
+            }
+        };
+
+        for url in urls {
+            match fetch_list(&client, url).await {
+                Ok(domains) => {
+                    debug!("Blocklist {} loaded: {} domains", url, domains.len());
+                    self.update_source(url, domains);
+                }
+                Err(e) => warn!("Failed to update blocklist {}: {}", url, e),
+            }
+        }
+
+        info!("Blocklist refreshed: {} domains", self.domain_count());
+    }
+}
+
+/// 当前 Unix 时间戳（秒）
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
+
+/// 检查 VLESS 请求的目标域名，命中拦截列表时返回错误
+///
+/// 仅对未退出拦截的用户生效；IP 目标不做检查
+pub fn ensure_allowed(blocklist: Option<&Blocklist>, request: &VlessRequest) -> Result<()> {
+    let blocklist = match blocklist {
+        Some(b) if b.applies_to(&request.uuid) => b,
+        _ => return Ok(()),
+    };
+    if let Address::Domain(ref domain) = request.address {
+        let domain = String::from_utf8_lossy(domain);
+        if blocklist.check(&domain) {
+            return Err(anyhow!("Destination blocked by blocklist: {}", domain));
+        }
+    }
+    Ok(())
+}
+
+/// 下载单个拦截列表
+async fn fetch_list(client: &reqwest::Client, url: &str) -> anyhow::Result<HashSet<String>> {
+    let mut response = client.get(url).send().await?;
+    if !response.status().is_success() {
+        return Err(anyhow::anyhow!("HTTP status {}", response.status()));
+    }
+    if let Some(len) = response.content_length() {
+        if len > MAX_LIST_SIZE as u64 {
+            return Err(anyhow!(
+                "List too large: {} bytes (limit {})",
+                len,
+                MAX_LIST_SIZE
+            ));
+        }
+    }
+    // 分块读取，未声明长度的响应同样受上限约束
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await? {
+        if body.len() + chunk.len() > MAX_LIST_SIZE {
+            return Err(anyhow!("List larger than {} bytes", MAX_LIST_SIZE));
+        }
+        body.extend_from_slice(&chunk);
+    }
+    Ok(parse_blocklist(&String::from_utf8_lossy(&body)))
+}
+
+/// 启动定时刷新任务
+///
+/// 立即下载一次，之后按 `refresh_interval` 周期刷新
+pub fn spawn_refresh_task(blocklist: Arc<Blocklist>, config: &BlocklistConfig) {
+    if config.urls.is_empty() {
+        return;
+    }
+
+    let urls = config.urls.clone();
+    let interval = Duration::from_secs(config.refresh_interval.max(MIN_REFRESH_INTERVAL));
+
+    tokio::spawn(async move {
+        loop {
+            blocklist.refresh(&urls).await;
+            tokio::select! {
+                _ = tokio::time::sleep(interval) => {}
+                _ = blocklist.reload.notified() => info!("Blocklist reload requested"),
+            }
+        }
+    });
+}
@@ -0,0 +1,331 @@
+//! 会话抓包调试模块
+//!
+//! 管理员通过管理 API 为指定用户或目标开启抓包，命中的会话把前 N KB 明文侧数据的
+//! 方向、长度与相对时间（可选载荷）写入 JSON Lines 文件，用于协议调试
+
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::PathBuf;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::task::{Context, Poll};
+use std::time::{Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncRead, ReadBuf};
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// 每个会话默认抓取的字节数
+pub const DEFAULT_MAX_KB: usize = 64;
+
+/// 单个会话抓取上限（KB）
+const MAX_KB: usize = 1024;
+
+/// 每条规则默认抓取的会话数
+pub const DEFAULT_SESSIONS: u32 = 10;
+
+/// 每条规则可抓取的会话数上限
+const MAX_SESSIONS: u32 = 1000;
+
+/// 同时生效的规则数上限
+const MAX_RULES: usize = 16;
+
+/// 数据方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Direction {
+    /// 客户端 → 目标
+    Up,
+    /// 目标 → 客户端
+    Down,
+}
+
+/// 抓包规则
+#[derive(Debug, Clone, Serialize)]
+pub struct CaptureRule {
+    pub id: u64,
+    /// 匹配的用户 UUID
+    pub user: Option<Uuid>,
+    /// 匹配的目标子串（不区分大小写）
+    pub dest: Option<String>,
+    /// 每个会话抓取的字节数
+    pub max_bytes: usize,
+    /// 是否记录载荷（Base64）
+    pub payload: bool,
+    /// 剩余可抓取的会话数，归零后规则自动移除
+    pub remaining_sessions: u32,
+}
+
+impl CaptureRule {
+    /// 从查询参数解析（user、dest、max_kb、payload、sessions），至少需要 user 或 dest
+    pub fn from_params(params: &HashMap<String, String>) -> Result<Self> {
+        let user = params
+            .get("user")
+            .map(|u| u.parse::<Uuid>().map_err(|_| anyhow!("Invalid user")))
+            .transpose()?;
+        let dest = params
+            .get("dest")
+            .map(|d| d.trim().to_ascii_lowercase())
+            .filter(|d| !d.is_empty());
+        if user.is_none() && dest.is_none() {
+            return Err(anyhow!("user or dest is required"));
+        }
+
+        let max_kb = match params.get("max_kb") {
+            Some(v) => v
+                .parse::<usize>()
+                .ok()
+                .filter(|&n| n > 0 && n <= MAX_KB)
+                .ok_or_else(|| anyhow!("Invalid max_kb"))?,
+            None => DEFAULT_MAX_KB,
+        };
+        let sessions = match params.get("sessions") {
+            Some(v) => v
+                .parse::<u32>()
+                .ok()
+                .filter(|&n| n > 0 && n <= MAX_SESSIONS)
+                .ok_or_else(|| anyhow!("Invalid sessions"))?,
+            None => DEFAULT_SESSIONS,
+        };
+
+        Ok(Self {
+            id: 0,
+            user,
+            dest,
+            max_bytes: max_kb * 1024,
+            payload: params
+                .get("payload")
+                .is_some_and(|v| v == "1" || v == "true"),
+            remaining_sessions: sessions,
+        })
+    }
+
+    fn matches(&self, uuid: &Uuid, dest: &str) -> bool {
+        self.user.is_none_or(|u| u == *uuid)
+            && self
+                .dest
+                .as_deref()
+                .is_none_or(|d| dest.to_ascii_lowercase().contains(d))
+    }
+}
+
+/// 抓包管理器（所有连接共享）
+#[derive(Debug)]
+pub struct CaptureManager {
+    dir: PathBuf,
+    rules: Mutex<Vec<CaptureRule>>,
+    next_id: AtomicU64,
+    /// 已创建的抓包文件数（用于文件命名）
+    files: AtomicU64,
+}
+
+impl CaptureManager {
+    /// 创建管理器，抓包文件写入 `dir`
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self {
+            dir: dir.into(),
+            rules: Mutex::new(Vec::new()),
+            next_id: AtomicU64::new(1),
+            files: AtomicU64::new(0),
+        }
+    }
+
+    /// 添加规则，返回分配了 ID 的规则
+    pub fn start(&self, mut rule: CaptureRule) -> Result<CaptureRule> {
+        let mut rules = self.rules.lock().unwrap_or_else(|e| e.into_inner());
+        if rules.len() >= MAX_RULES {
+            return Err(anyhow!("Too many capture rules (max {})", MAX_RULES));
+        }
+        std::fs::create_dir_all(&self.dir)
+            .map_err(|e| anyhow!("Cannot create {}: {}", self.dir.display(), e))?;
+
+        rule.id = self.next_id.fetch_add(1, Ordering::Relaxed);
+        rules.push(rule.clone());
+        info!(
+            "Capture rule {} started (user: {:?}, dest: {:?})",
+            rule.id, rule.user, rule.dest
+        );
+        Ok(rule)
+    }
+
+    /// 移除规则，返回是否存在
+    pub fn stop(&self, id: u64) -> bool {
+        let mut rules = self.rules.lock().unwrap_or_else(|e| e.into_inner());
+        let before = rules.len();
+        rules.retain(|r| r.id != id);
+        before != rules.len()
+    }
+
+    /// 当前生效的规则
+    pub fn rules(&self) -> Vec<CaptureRule> {
+        self.rules.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// 为新会话匹配规则并创建抓包文件；无匹配或创建失败时返回 None
+    pub fn open_session(&self, uuid: &Uuid, user: &str, dest: &str) -> Option<SessionCapture> {
+        let rule = {
+            let mut rules = self.rules.lock().unwrap_or_else(|e| e.into_inner());
+            let index = rules.iter().position(|r| r.matches(uuid, dest))?;
+            rules[index].remaining_sessions -= 1;
+            let rule = rules[index].clone();
+            if rule.remaining_sessions == 0 {
+                rules.remove(index);
+            }
+            rule
+        };
+
+        let seq = self.files.fetch_add(1, Ordering::Relaxed) + 1;
+        let path = self
+            .dir
+            .join(format!("capture-{}-{}-{}.jsonl", rule.id, seq, unix_now()));
+        match SessionCapture::create(&path, &rule, uuid, user, dest) {
+            Ok(capture) => {
+                info!(
+                    "Capturing session {} -> {} to {}",
+                    user,
+                    dest,
+                    path.display()
+                );
+                Some(capture)
+            }
+            Err(e) => {
+                warn!("Failed to create capture file {}: {}", path.display(), e);
+                None
+            }
+        }
+    }
+}
+
+/// 单个会话的抓包文件
+#[derive(Debug)]
+struct CaptureFile {
+    writer: BufWriter<File>,
+    started: Instant,
+    /// 剩余可记录的字节数
+    remaining: usize,
+    payload: bool,
+    truncated: bool,
+}
+
+/// 会话抓包句柄（上下行共享）
+#[derive(Debug, Clone)]
+pub struct SessionCapture {
+    file: Arc<Mutex<CaptureFile>>,
+}
+
+impl SessionCapture {
+    fn create(
+        path: &std::path::Path,
+        rule: &CaptureRule,
+        uuid: &Uuid,
+        user: &str,
+        dest: &str,
+    ) -> Result<Self> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        let header = serde_json::json!({
+            "rule": rule.id,
+            "uuid": uuid,
+            "user": user,
+            "dest": dest,
+            "started_at": unix_now(),
+            "max_bytes": rule.max_bytes,
+            "payload": rule.payload,
+        });
+        writeln!(writer, "{}", header)?;
+
+        Ok(Self {
+            file: Arc::new(Mutex::new(CaptureFile {
+                writer,
+                started: Instant::now(),
+                remaining: rule.max_bytes,
+                payload: rule.payload,
+                truncated: false,
+            })),
+        })
+    }
+
+    /// 记录一段数据；超出字节上限后只写一次截断标记
+    pub fn record(&self, direction: Direction, data: &[u8]) {
+        if data.is_empty() {
+            return;
+        }
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        if file.truncated {
+            return;
+        }
+
+        let t_us = file.started.elapsed().as_micros() as u64;
+        let line = if file.remaining == 0 {
+            file.truncated = true;
+            serde_json::json!({ "t_us": t_us, "truncated": true })
+        } else {
+            let take = data.len().min(file.remaining);
+            file.remaining -= take;
+            let mut line = serde_json::json!({
+                "t_us": t_us,
+                "dir": direction,
+                "len": data.len(),
+            });
+            if file.payload {
+                line["payload"] = serde_json::json!(BASE64.encode(&data[..take]));
+            }
+            line
+        };
+
+        if let Err(e) = writeln!(file.writer, "{}", line) {
+            warn!("Capture write failed: {}", e);
+            file.truncated = true;
+        }
+    }
+}
+
+impl Drop for CaptureFile {
+    fn drop(&mut self) {
+        let _ = self.writer.flush();
+    }
+}
+
+/// 读取时记录数据的包装器（未开启抓包时直接透传）
+#[derive(Debug)]
+pub struct CaptureReader<R> {
+    inner: R,
+    capture: Option<SessionCapture>,
+    direction: Direction,
+}
+
+impl<R> CaptureReader<R> {
+    pub fn new(inner: R, capture: Option<SessionCapture>, direction: Direction) -> Self {
+        Self {
+            inner,
+            capture,
+            direction,
+        }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for CaptureReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let before = buf.filled().len();
+        let result = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if let (Poll::Ready(Ok(())), Some(capture)) = (&result, &self.capture) {
+            capture.record(self.direction, &buf.filled()[before..]);
+        }
+        result
+    }
+}
+
+/// 当前 Unix 时间戳（秒）
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
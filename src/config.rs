@@ -1,6 +1,8 @@
+use crate::i18n::Language;
 use anyhow::Result;
 use serde::{Deserialize, Serialize};
-use std::net::SocketAddr;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
 
 /// 协议类型
 #[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
@@ -14,6 +16,55 @@ pub enum ProtocolType {
     WebSocket,
 }
 
+/// 入站协议识别（按连接首部字节区分）
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum InboundProtocol {
+    /// TLS 握手（交给 SNI 分流）
+    Tls,
+    /// 普通 HTTP 请求（信息页、管理 API、伪装站点）
+    Http,
+    /// VLESS：TCP 模式下为明文 VLESS 请求，WS 模式下为 WebSocket 升级
+    Vless,
+}
+
+impl InboundProtocol {
+    /// 默认识别顺序
+    pub const ALL: [Self; 3] = [Self::Tls, Self::Http, Self::Vless];
+}
+
+/// 请求中域名目标的解析方式（取值同 Xray `domainStrategy`）
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+pub enum DomainStrategy {
+    /// 域名原样处理：路由按域名匹配，直连时建连前解析，经上游出站时交给上游解析
+    #[default]
+    AsIs,
+    /// 在路由之前解析为 IP（IPv4 / IPv6 均可），后续按 IP 目标处理
+    #[serde(rename = "UseIP")]
+    UseIp,
+    /// 同 `UseIP`，只使用 IPv4 地址
+    #[serde(rename = "UseIPv4")]
+    UseIpv4,
+    /// 同 `UseIP`，只使用 IPv6 地址
+    #[serde(rename = "UseIPv6")]
+    UseIpv6,
+}
+
+/// 域名解析出多个地址族时的使用策略
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum IpStrategy {
+    /// IPv4 优先，两族地址交替尝试
+    #[default]
+    PreferIpv4,
+    /// IPv6 优先，两族地址交替尝试
+    PreferIpv6,
+    /// 只使用 IPv4 地址
+    Ipv4Only,
+    /// 只使用 IPv6 地址
+    Ipv6Only,
+}
+
 /// 性能优化配置
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct PerformanceConfig {
@@ -41,6 +92,48 @@ pub struct PerformanceConfig {
     /// WebSocket HTTP 头缓冲区大小（字节），默认8KB
     #[serde(default = "default_ws_header_buffer_size")]
     pub ws_header_buffer_size: usize,
+    /// WebSocket 空闲 Ping 间隔（秒），默认 30，0 表示关闭
+    #[serde(default = "default_ws_ping_interval")]
+    pub ws_ping_interval: u64,
+    /// WebSocket 下行小包合并等待时间（毫秒），默认 0 表示不合并
+    #[serde(default)]
+    pub ws_coalesce_ms: u64,
+    /// WebSocket 下行合并阈值（字节），累积到该大小立即发送，默认 16KB
+    #[serde(default = "default_ws_coalesce_bytes")]
+    pub ws_coalesce_bytes: usize,
+    /// UDP 中继本地绑定地址，默认按目标地址族绑定未指定地址（0.0.0.0 / ::）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub udp_bind_address: Option<String>,
+    /// UDP 中继本地端口范围 [起始, 结束]（含），默认由系统随机分配
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub udp_port_range: Option<[u16; 2]>,
+    /// UDP 会话是否为 full-cone（接受任意远端地址的回包，而不只是请求的目标），默认 true
+    #[serde(default = "default_udp_full_cone")]
+    pub udp_full_cone: bool,
+    /// 同时存在的 VLESS UDP 会话上限（全部客户端合计），默认 1024，0 表示不限制
+    #[serde(default = "default_udp_max_sessions")]
+    pub udp_max_sessions: usize,
+    /// UDP 下行重放窗口（每个会话记录的最近数据报数），窗口内重复的数据报被丢弃，默认 0 表示关闭
+    #[serde(default)]
+    pub udp_replay_window: usize,
+    /// TCP 会话停滞检测阈值（秒）：两个方向都无数据超过该时长时记录并计数，默认 0 表示关闭
+    #[serde(default)]
+    pub stall_timeout: u64,
+    /// TCP MSS 上限（`TCP_MAXSEG`，字节），作用于监听接受的连接与出站连接，默认 0 表示使用系统默认
+    #[serde(default)]
+    pub tcp_mss: u32,
+    /// 路径 MTU 发现策略（Linux `IP_MTU_DISCOVER`），默认不修改系统设置
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pmtu_discovery: Option<PmtuDiscovery>,
+    /// 同时处理的连接数上限（全部用户合计），默认 0 表示不限制
+    #[serde(default)]
+    pub max_sessions: usize,
+    /// 达到上限后允许排队等待的连接数，默认 0 表示直接拒绝
+    #[serde(default)]
+    pub session_queue: usize,
+    /// 排队等待的最长时间（毫秒），默认 1000
+    #[serde(default = "default_session_queue_timeout_ms")]
+    pub session_queue_timeout_ms: u64,
 }
 
 fn default_buffer_size() -> usize {
@@ -61,6 +154,12 @@ fn default_udp_timeout() -> u64 {
 fn default_udp_recv_buffer() -> usize {
     64 * 1024
 } // 64KB
+fn default_udp_full_cone() -> bool {
+    true
+}
+fn default_udp_max_sessions() -> usize {
+    1024
+}
 fn default_buffer_pool_size() -> usize {
     // 增加池大小以适应双池设计（小缓冲区池 + 大缓冲区池）
     std::cmp::min(
@@ -71,9 +170,21 @@ fn default_buffer_pool_size() -> usize {
 fn default_ws_header_buffer_size() -> usize {
     8 * 1024
 } // 8KB
+fn default_ws_ping_interval() -> u64 {
+    30
+}
+fn default_ws_coalesce_bytes() -> usize {
+    16 * 1024
+}
+fn default_session_queue_timeout_ms() -> u64 {
+    1000
+}
 fn default_ws_path() -> String {
     "/vless".to_string()
 }
+fn default_detect_public_ip() -> bool {
+    true
+}
 
 impl Default for PerformanceConfig {
     fn default() -> Self {
@@ -86,17 +197,1403 @@ impl Default for PerformanceConfig {
             udp_recv_buffer: default_udp_recv_buffer(),
             buffer_pool_size: default_buffer_pool_size(),
             ws_header_buffer_size: default_ws_header_buffer_size(),
+            ws_ping_interval: default_ws_ping_interval(),
+            ws_coalesce_ms: 0,
+            ws_coalesce_bytes: default_ws_coalesce_bytes(),
+            udp_bind_address: None,
+            udp_port_range: None,
+            udp_full_cone: default_udp_full_cone(),
+            udp_max_sessions: default_udp_max_sessions(),
+            udp_replay_window: 0,
+            stall_timeout: 0,
+            tcp_mss: 0,
+            pmtu_discovery: None,
+            max_sessions: 0,
+            session_queue: 0,
+            session_queue_timeout_ms: default_session_queue_timeout_ms(),
+        }
+    }
+}
+
+/// `TCP_MAXSEG` 的有效范围（Linux 内核限制）
+pub const TCP_MSS_RANGE: std::ops::RangeInclusive<u32> = 88..=32767;
+
+/// `udp_replay_window` 上限：窗口按会话分配，会话数乘以窗口即为哈希的内存占用
+pub const MAX_UDP_REPLAY_WINDOW: usize = 4096;
+
+impl PerformanceConfig {
+    /// 校验 socket 选项与重放窗口取值
+    pub fn validate(&self) -> Result<()> {
+        if self.tcp_mss != 0 && !TCP_MSS_RANGE.contains(&self.tcp_mss) {
+            return Err(anyhow::anyhow!(
+                "performance.tcp_mss must be 0 or between {} and {}: {}",
+                TCP_MSS_RANGE.start(),
+                TCP_MSS_RANGE.end(),
+                self.tcp_mss
+            ));
+        }
+        if self.udp_replay_window > MAX_UDP_REPLAY_WINDOW {
+            return Err(anyhow::anyhow!(
+                "performance.udp_replay_window must be at most {}: {}",
+                MAX_UDP_REPLAY_WINDOW,
+                self.udp_replay_window
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 路径 MTU 发现策略，对应 Linux `IP_PMTUDISC_*`
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum PmtuDiscovery {
+    /// 始终设置 DF 并按 ICMP 更新路径 MTU（内核默认）
+    Do,
+    /// 不设置 DF，允许中途分片，绕过丢弃 ICMP 的黑洞路径
+    Dont,
+    /// 按路由设置决定是否设置 DF
+    Want,
+    /// 设置 DF 但忽略路径 MTU 更新
+    Probe,
+}
+
+/// 性能预设：为缓冲区、缓冲池、监控上限与超时提供一组协调的默认值
+///
+/// 只填充 `performance` / `monitoring` 中未显式设置的字段，显式值始终优先
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case")]
+pub enum Profile {
+    /// 小内存 VPS：缩小缓冲区与监控保留量
+    LowMemory,
+    /// 与内置默认值相同
+    Balanced,
+    /// 高带宽主机：放大缓冲区与缓冲池
+    Throughput,
+}
+
+impl Profile {
+    /// 预设名称
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::LowMemory => "low-memory",
+            Self::Balanced => "balanced",
+            Self::Throughput => "throughput",
+        }
+    }
+
+    /// 预设的默认值（按配置节分组）
+    fn defaults(&self) -> serde_json::Value {
+        match self {
+            Self::LowMemory => serde_json::json!({
+                "performance": {
+                    "buffer_size": 16 * 1024,
+                    "tcp_recv_buffer": 32 * 1024,
+                    "tcp_send_buffer": 32 * 1024,
+                    "udp_recv_buffer": 16 * 1024,
+                    "buffer_pool_size": 8,
+                    "ws_header_buffer_size": 4 * 1024,
+                    "udp_timeout": 20,
+                },
+                "monitoring": {
+                    "destination_window": 20,
+                    "max_destinations": 256,
+                    "destination_sample_every": 4,
+                    "dns_stats_entries": 1000,
+                    "log_history": 200,
+                },
+            }),
+            Self::Balanced => serde_json::json!({}),
+            Self::Throughput => serde_json::json!({
+                "performance": {
+                    "buffer_size": 256 * 1024,
+                    "tcp_recv_buffer": 1024 * 1024,
+                    "tcp_send_buffer": 1024 * 1024,
+                    "udp_recv_buffer": 256 * 1024,
+                    "buffer_pool_size": 256,
+                    "ws_header_buffer_size": 16 * 1024,
+                    "udp_timeout": 60,
+                },
+                "monitoring": {
+                    "destination_window": 100,
+                    "max_destinations": 8192,
+                    "dns_stats_entries": 50_000,
+                    "log_history": 2000,
+                },
+            }),
+        }
+    }
+
+    /// 把预设写入配置 JSON 中缺失的字段，返回填充的（配置节，字段名）
+    fn apply(&self, config: &mut serde_json::Value) -> Vec<(String, String)> {
+        let mut filled = Vec::new();
+        let (Some(config), serde_json::Value::Object(sections)) =
+            (config.as_object_mut(), self.defaults())
+        else {
+            return filled;
+        };
+        for (section, defaults) in sections {
+            let target = config
+                .entry(section.clone())
+                .or_insert_with(|| serde_json::json!({}));
+            let (Some(target), serde_json::Value::Object(defaults)) =
+                (target.as_object_mut(), defaults)
+            else {
+                continue;
+            };
+            for (key, value) in defaults {
+                if !target.contains_key(&key) {
+                    filled.push((section.clone(), key.clone()));
+                    target.insert(key, value);
+                }
+            }
+        }
+        filled
+    }
+}
+
+/// DNS 拦截配置（作用于 UDP 代理到 53 端口的查询）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DnsConfig {
+    /// 是否启用 DNS 拦截，默认 false
+    #[serde(default)]
+    pub enabled: bool,
+    /// 是否缓存上游应答，默认 true
+    #[serde(default = "default_dns_cache")]
+    pub cache: bool,
+    /// 缓存条目上限，默认 1024
+    #[serde(default = "default_dns_cache_size")]
+    pub cache_size: usize,
+    /// 域名覆盖表：域名 → 地址列表
+    #[serde(default, skip_serializing_if = "HashMap::is_empty")]
+    pub hosts: HashMap<String, Vec<IpAddr>>,
+    /// hosts 应答的 TTL（秒），默认 300
+    #[serde(default = "default_dns_hosts_ttl")]
+    pub hosts_ttl: u32,
+    /// 拦截的域名后缀（含子域名），命中时返回 NXDOMAIN
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub block: Vec<String>,
+}
+
+fn default_dns_cache() -> bool {
+    true
+}
+fn default_dns_cache_size() -> usize {
+    1024
+}
+fn default_dns_hosts_ttl() -> u32 {
+    300
+}
+
+impl Default for DnsConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            cache: default_dns_cache(),
+            cache_size: default_dns_cache_size(),
+            hosts: HashMap::new(),
+            hosts_ttl: default_dns_hosts_ttl(),
+            block: Vec::new(),
+        }
+    }
+}
+
+/// 广告 / 追踪域名拦截列表配置
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct BlocklistConfig {
+    /// 订阅地址（hosts 格式或纯域名列表）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub urls: Vec<String>,
+    /// 额外拦截的域名（含子域名）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub domains: Vec<String>,
+    /// 刷新间隔（秒），默认 86400
+    #[serde(default = "default_blocklist_refresh_interval")]
+    pub refresh_interval: u64,
+}
+
+fn default_blocklist_refresh_interval() -> u64 {
+    86400
+}
+
+impl Default for BlocklistConfig {
+    fn default() -> Self {
+        Self {
+            urls: Vec::new(),
+            domains: Vec::new(),
+            refresh_interval: default_blocklist_refresh_interval(),
+        }
+    }
+}
+
+impl BlocklistConfig {
+    /// 是否配置了任何拦截来源
+    pub fn is_enabled(&self) -> bool {
+        !self.urls.is_empty() || !self.domains.is_empty()
+    }
+}
+
+/// 未认证 HTTP 请求的响应方式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum DecoyMode {
+    /// 信息面板与链接查询接口（默认）
+    #[default]
+    Panel,
+    /// 静态站点
+    Static,
+    /// 重定向到真实域名
+    Redirect,
+    /// 返回固定状态码（444 表示直接断开）
+    Reject,
+    /// 反向代理到后端
+    Proxy,
+}
+
+/// 反向代理路由（按路径前缀与可选 Host 匹配）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ProxyRoute {
+    /// 路径前缀，如 /app（匹配 /app 与 /app/...）
+    pub path: String,
+    /// 仅匹配该 Host 请求头
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
+    /// 后端地址，如 127.0.0.1:3000
+    pub backend: String,
+    /// 转发前是否去掉路径前缀，默认 false
+    #[serde(default)]
+    pub strip_prefix: bool,
+}
+
+/// SNI 分流路由
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SniRoute {
+    /// 匹配的 SNI：完整域名，或 `*.example.com` 匹配其任意子域名
+    pub sni: String,
+    /// 后端地址，如 10.0.0.2:443
+    pub backend: String,
+}
+
+/// SNI 分流配置：TLS 连接不解密，按 ClientHello 中的 SNI 原样转发到后端
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct SniProxyConfig {
+    /// 未命中路由且 SNI 不是本服务域名时的后端（如另一台主机上的真实网站）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<String>,
+    /// 按 SNI 的路由，按顺序匹配，优先于 `backend`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub routes: Vec<SniRoute>,
+}
+
+impl SniProxyConfig {
+    /// 是否启用（设置了后端或路由）
+    pub fn is_enabled(&self) -> bool {
+        self.backend.is_some() || !self.routes.is_empty()
+    }
+
+    /// 校验后端地址与路由
+    pub fn validate(&self) -> Result<()> {
+        let backends = self
+            .backend
+            .iter()
+            .chain(self.routes.iter().map(|r| &r.backend));
+        for backend in backends {
+            if backend.rsplit_once(':').is_none_or(|(host, port)| {
+                host.is_empty() || port.parse::<u16>().map_or(true, |p| p == 0)
+            }) {
+                return Err(anyhow::anyhow!(
+                    "sni_proxy backend must be host:port: {}",
+                    backend
+                ));
+            }
+        }
+        if let Some(route) = self.routes.iter().find(|r| r.sni.trim().is_empty()) {
+            return Err(anyhow::anyhow!(
+                "sni_proxy.routes sni is empty for backend {}",
+                route.backend
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 回落规则：握手失败的原始连接转发到的目标
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct FallbackRule {
+    /// 目标地址，如 127.0.0.1:8080
+    pub dest: String,
+    /// 仅匹配该 HTTP 请求路径前缀；不设置时为默认目标
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+}
+
+/// 伪装站点配置
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct DecoyConfig {
+    /// 响应方式，默认 panel
+    #[serde(default)]
+    pub mode: DecoyMode,
+    /// 静态站点根目录（static 模式）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub root: Option<String>,
+    /// 重定向目标，如 https://example.com（redirect 模式，保留原请求路径）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub redirect_url: Option<String>,
+    /// 返回的状态码（reject 模式），默认 400；444 表示不响应直接断开
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>,
+    /// 后端地址，如 127.0.0.1:8080（proxy 模式）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub backend: Option<String>,
+    /// 反向代理路由，优先于 mode 生效（含 WebSocket 升级请求）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub routes: Vec<ProxyRoute>,
+}
+
+impl DecoyConfig {
+    /// 校验当前模式所需的字段
+    pub fn validate(&self) -> Result<()> {
+        let missing = match self.mode {
+            DecoyMode::Static if self.root.is_none() => Some("root"),
+            DecoyMode::Redirect if self.redirect_url.is_none() => Some("redirect_url"),
+            DecoyMode::Proxy if self.backend.is_none() => Some("backend"),
+            _ => None,
+        };
+        if let Some(field) = missing {
+            return Err(anyhow::anyhow!(
+                "decoy.{} is required for decoy mode {:?}",
+                field,
+                self.mode
+            ));
+        }
+        if let Some(status) = self.status {
+            if !(100..=599).contains(&status) {
+                return Err(anyhow::anyhow!("Invalid decoy.status: {}", status));
+            }
+        }
+        for route in &self.routes {
+            if !route.path.starts_with('/') {
+                return Err(anyhow::anyhow!(
+                    "decoy.routes path must start with '/': {}",
+                    route.path
+                ));
+            }
+            if route.backend.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "decoy.routes backend is empty for path {}",
+                    route.path
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 会话计费事件配置
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct AccountingConfig {
+    /// 会话关闭时 POST JSON 的回调地址
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+    /// 回调请求的 Bearer 令牌
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_token: Option<String>,
+    /// 追加写入的 spool 文件（JSON Lines，每行一个会话）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub spool_file: Option<String>,
+}
+
+impl AccountingConfig {
+    /// 是否配置了任何投递目标
+    pub fn is_enabled(&self) -> bool {
+        self.webhook_url.is_some() || self.spool_file.is_some()
+    }
+}
+
+/// NAT-PMP 端口映射配置（家庭网络等 NAT 之后的部署）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct PortMappingConfig {
+    /// 是否在启动时向网关请求端口映射，默认 false
+    #[serde(default)]
+    pub enabled: bool,
+    /// 网关地址（`ip` 或 `ip:port`），默认读取系统默认路由
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub gateway: Option<String>,
+    /// 期望的外部端口，默认与监听端口相同（网关可能分配其他端口）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub external_port: Option<u16>,
+    /// 映射有效期（秒），默认 3600，到期前一半时间自动续期
+    #[serde(default = "default_port_mapping_lifetime")]
+    pub lifetime: u32,
+}
+
+fn default_port_mapping_lifetime() -> u32 {
+    3600
+}
+
+impl Default for PortMappingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            gateway: None,
+            external_port: None,
+            lifetime: default_port_mapping_lifetime(),
+        }
+    }
+}
+
+/// 动态 DNS 服务商
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum DdnsProvider {
+    Cloudflare,
+    Duckdns,
+}
+
+/// 动态 DNS 更新配置（动态 IP 部署）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct DdnsConfig {
+    /// 服务商：cloudflare 或 duckdns，未设置时不启用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub provider: Option<DdnsProvider>,
+    /// 更新的完整域名（同时用于分享链接）
+    #[serde(default)]
+    pub hostname: String,
+    /// API 令牌（Cloudflare API Token / DuckDNS token）
+    #[serde(default)]
+    pub token: String,
+    /// Cloudflare Zone ID
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub zone_id: Option<String>,
+    /// 检查公网 IP 的间隔（秒），默认 300
+    #[serde(default = "default_ddns_interval")]
+    pub interval: u64,
+    /// 自定义 API 地址（兼容服务或测试用）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+}
+
+fn default_ddns_interval() -> u64 {
+    300
+}
+
+impl Default for DdnsConfig {
+    fn default() -> Self {
+        Self {
+            provider: None,
+            hostname: String::new(),
+            token: String::new(),
+            zone_id: None,
+            interval: default_ddns_interval(),
+            endpoint: None,
+        }
+    }
+}
+
+impl DdnsConfig {
+    /// 是否启用
+    pub fn is_enabled(&self) -> bool {
+        self.provider.is_some()
+    }
+}
+
+/// 流量嗅探配置：从 TCP 会话首包的 TLS SNI / HTTP Host 识别域名并覆盖目标（同 Xray `sniffing.destOverride`）
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SniffingConfig {
+    /// 是否启用，默认 false
+    #[serde(default)]
+    pub enabled: bool,
+    /// 识别的协议，默认 `["tls", "http"]`
+    #[serde(default = "default_sniff_protocols")]
+    pub dest_override: Vec<SniffProtocol>,
+    /// 识别出的域名命中这些后缀时不覆盖目标
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub domains_excluded: Vec<String>,
+}
+
+/// 可嗅探的协议
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum SniffProtocol {
+    /// TLS ClientHello 的 SNI
+    Tls,
+    /// HTTP/1.x 请求的 Host 请求头
+    Http,
+}
+
+fn default_sniff_protocols() -> Vec<SniffProtocol> {
+    vec![SniffProtocol::Tls, SniffProtocol::Http]
+}
+
+impl Default for SniffingConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            dest_override: default_sniff_protocols(),
+            domains_excluded: Vec::new(),
+        }
+    }
+}
+
+impl SniffingConfig {
+    /// 校验：启用时至少识别一种协议
+    pub fn validate(&self) -> Result<()> {
+        if self.enabled && self.dest_override.is_empty() {
+            return Err(anyhow::anyhow!(
+                "sniffing.dest_override must not be empty when sniffing is enabled"
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 目标域名解析配置：内置解析器的上游服务器与缓存
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ResolverConfig {
+    /// 上游 DNS 服务器（`IP[:端口]`、`tls://主机[:端口]` 或 `https://主机/路径`），为空时使用系统解析
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub servers: Vec<String>,
+    /// DNS over TLS / HTTPS 额外信任的 CA 证书（PEM），用于自建解析服务
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_file: Option<String>,
+    /// 单次查询超时（秒），默认 5
+    #[serde(default = "default_resolver_timeout")]
+    pub timeout: u64,
+    /// 缓存条目上限，默认 4096，0 表示不缓存
+    #[serde(default = "default_resolver_cache_size")]
+    pub cache_size: usize,
+    /// 解析结果的最长缓存时间（秒），默认 3600
+    #[serde(default = "default_resolver_max_ttl")]
+    pub max_ttl: u32,
+    /// 无地址（NXDOMAIN / 无记录）结果的缓存时间（秒），默认 30
+    #[serde(default = "default_resolver_negative_ttl")]
+    pub negative_ttl: u32,
+    /// 系统解析结果的缓存时间（秒，系统解析不返回 TTL），默认 60
+    #[serde(default = "default_resolver_system_ttl")]
+    pub system_ttl: u32,
+    /// 域名目标的地址族策略，默认 `prefer_ipv4`
+    #[serde(default)]
+    pub ip_strategy: IpStrategy,
+    /// Happy Eyeballs 建连间隔（毫秒）：前一个地址在该时间内未连通时并发尝试下一个，
+    /// 默认 250，0 表示依次尝试（前一个失败后才尝试下一个）
+    #[serde(default = "default_happy_eyeballs_delay")]
+    pub happy_eyeballs_delay_ms: u64,
+}
+
+fn default_resolver_timeout() -> u64 {
+    5
+}
+fn default_resolver_cache_size() -> usize {
+    4096
+}
+fn default_resolver_max_ttl() -> u32 {
+    3600
+}
+fn default_resolver_negative_ttl() -> u32 {
+    30
+}
+fn default_resolver_system_ttl() -> u32 {
+    60
+}
+fn default_happy_eyeballs_delay() -> u64 {
+    250
+}
+
+impl Default for ResolverConfig {
+    fn default() -> Self {
+        Self {
+            servers: Vec::new(),
+            ca_file: None,
+            timeout: default_resolver_timeout(),
+            cache_size: default_resolver_cache_size(),
+            max_ttl: default_resolver_max_ttl(),
+            negative_ttl: default_resolver_negative_ttl(),
+            system_ttl: default_resolver_system_ttl(),
+            ip_strategy: IpStrategy::default(),
+            happy_eyeballs_delay_ms: default_happy_eyeballs_delay(),
+        }
+    }
+}
+
+impl ResolverConfig {
+    /// 解析上游服务器：`IP[:端口]` 为明文 DNS（默认端口 53），
+    /// `tls://主机[:端口]` 为 DNS over TLS（默认端口 853），`https://主机[:端口]/路径` 为 DNS over HTTPS
+    pub fn upstreams(&self) -> Result<Vec<ResolverUpstream>> {
+        self.servers
+            .iter()
+            .map(|server| {
+                let server = server.trim();
+                let invalid = || anyhow::anyhow!("Invalid resolver.servers entry: {}", server);
+                if let Some(rest) = server.strip_prefix("tls://") {
+                    let (host, port) =
+                        split_host_port(rest.trim_end_matches('/'), 853).ok_or_else(invalid)?;
+                    return Ok(ResolverUpstream::Tls { host, port });
+                }
+                if server.starts_with("https://") {
+                    let url = reqwest::Url::parse(server).map_err(|_| invalid())?;
+                    if url.host_str().is_none_or(str::is_empty) {
+                        return Err(invalid());
+                    }
+                    return Ok(ResolverUpstream::Https(url.to_string()));
+                }
+                server
+                    .parse::<SocketAddr>()
+                    .or_else(|_| {
+                        server
+                            .trim_start_matches('[')
+                            .trim_end_matches(']')
+                            .parse::<IpAddr>()
+                            .map(|ip| SocketAddr::new(ip, 53))
+                    })
+                    .map(ResolverUpstream::Udp)
+                    .map_err(|_| invalid())
+            })
+            .collect()
+    }
+
+    /// 校验：上游服务器格式正确，超时不为 0
+    pub fn validate(&self) -> Result<()> {
+        self.upstreams()?;
+        if self.timeout == 0 {
+            return Err(anyhow::anyhow!("resolver.timeout must be greater than 0"));
+        }
+        Ok(())
+    }
+}
+
+/// 解析后的上游 DNS 服务器
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ResolverUpstream {
+    /// 明文 DNS（UDP）
+    Udp(SocketAddr),
+    /// DNS over TLS（RFC 7858），主机名经系统解析
+    Tls { host: String, port: u16 },
+    /// DNS over HTTPS（RFC 8484），完整 URL
+    Https(String),
+}
+
+impl std::fmt::Display for ResolverUpstream {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ResolverUpstream::Udp(addr) => write!(f, "{}", addr),
+            ResolverUpstream::Tls { host, port } if host.contains(':') => {
+                write!(f, "tls://[{}]:{}", host, port)
+            }
+            ResolverUpstream::Tls { host, port } => write!(f, "tls://{}:{}", host, port),
+            ResolverUpstream::Https(url) => write!(f, "{}", url),
+        }
+    }
+}
+
+/// 拆分 `主机[:端口]`（IPv6 需加方括号），省略端口时使用 `default_port`
+fn split_host_port(value: &str, default_port: u16) -> Option<(String, u16)> {
+    if let Ok(addr) = value.parse::<SocketAddr>() {
+        return Some((addr.ip().to_string(), addr.port()));
+    }
+    if let Ok(ip) = value
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+    {
+        return Some((ip.to_string(), default_port));
+    }
+    let (host, port) = match value.rsplit_once(':') {
+        Some((host, port)) => (host, port.parse().ok()?),
+        None => (value, default_port),
+    };
+    let valid = !host.is_empty()
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.');
+    valid.then(|| (host.to_ascii_lowercase(), port))
+}
+
+/// 维护模式下拒绝新会话的方式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum MaintenanceBehavior {
+    /// 直接关闭连接；WebSocket 连接以关闭帧（1013）附带维护说明
+    #[default]
+    Close,
+    /// TCP 入站的 VLESS 连接交给 `fallbacks` 处理（未配置回落时关闭），其余同 `close`
+    Fallback,
+}
+
+/// UTC 时间点
+pub type UtcTime = chrono::DateTime<chrono::Utc>;
+
+/// 维护模式配置：拒绝新的代理会话（已有会话不受影响），并在信息页展示维护说明
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct MaintenanceConfig {
+    /// 启动即进入维护模式，默认 false；运行时可通过管理 API 切换
+    #[serde(default)]
+    pub enabled: bool,
+    /// 计划开始时间（RFC 3339，如 `2026-10-20T02:00:00+08:00`），到点自动进入维护模式
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub starts_at: Option<String>,
+    /// 计划结束时间（RFC 3339），到点自动退出；只设置结束时间时从启动起即处于维护模式
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ends_at: Option<String>,
+    /// 面向用户的维护说明，显示在信息页并作为 WebSocket 关闭原因
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub message: String,
+    /// 拒绝新会话的方式，默认 `close`
+    #[serde(default)]
+    pub behavior: MaintenanceBehavior,
+}
+
+impl MaintenanceConfig {
+    /// 解析计划时间窗口（开始，结束）
+    pub fn window(&self) -> Result<(Option<UtcTime>, Option<UtcTime>)> {
+        let parse = |value: &Option<String>, field: &str| {
+            value
+                .as_deref()
+                .map(|text| {
+                    chrono::DateTime::parse_from_rfc3339(text.trim())
+                        .map(|time| time.with_timezone(&chrono::Utc))
+                        .map_err(|e| {
+                            anyhow::anyhow!("Invalid maintenance.{} {}: {}", field, text, e)
+                        })
+                })
+                .transpose()
+        };
+        Ok((
+            parse(&self.starts_at, "starts_at")?,
+            parse(&self.ends_at, "ends_at")?,
+        ))
+    }
+
+    /// 校验：时间为 RFC 3339 格式，结束时间晚于开始时间
+    pub fn validate(&self) -> Result<()> {
+        if let (Some(starts), Some(ends)) = self.window()? {
+            if ends <= starts {
+                return Err(anyhow::anyhow!(
+                    "maintenance.ends_at must be later than maintenance.starts_at"
+                ));
+            }
         }
+        Ok(())
     }
 }
 
+/// 触发连接钩子的判定结果
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Hash)]
+#[serde(rename_all = "snake_case")]
+pub enum HookVerdict {
+    /// UUID 认证通过，开始代理
+    AuthSuccess,
+    /// UUID 认证失败
+    AuthFailure,
+    /// 认证通过的连接结束
+    Closed,
+}
+
+impl HookVerdict {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            HookVerdict::AuthSuccess => "auth_success",
+            HookVerdict::AuthFailure => "auth_failure",
+            HookVerdict::Closed => "closed",
+        }
+    }
+}
+
+fn default_hook_on() -> Vec<HookVerdict> {
+    vec![HookVerdict::AuthFailure]
+}
+
+fn default_hook_cooldown() -> u64 {
+    60
+}
+
+fn default_hook_max_per_minute() -> u32 {
+    60
+}
+
+fn default_hook_timeout() -> u64 {
+    10
+}
+
+/// 连接钩子：认证成功 / 失败或连接关闭时执行外部命令和 / 或调用 HTTP 回调，
+/// 供 ipset、CrowdSec、fail2ban 等外部防火墙联动
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct HookConfig {
+    /// 触发的判定结果，默认 `["auth_failure"]`
+    #[serde(default = "default_hook_on")]
+    pub on: Vec<HookVerdict>,
+    /// 执行的命令：程序路径与参数，不经过 shell；参数中的 `{ip}`、`{port}`、`{verdict}`、
+    /// `{uuid}`、`{user}` 按事件替换
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exec: Vec<String>,
+    /// 以 POST JSON 通知的回调地址
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_url: Option<String>,
+    /// 回调请求的 Bearer 令牌
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook_token: Option<String>,
+    /// 同一客户端 IP 的同一判定在该时长（秒）内只触发一次，默认 60，0 表示不限制
+    #[serde(default = "default_hook_cooldown")]
+    pub cooldown: u64,
+    /// 每分钟最多触发次数，默认 60，0 表示不限制
+    #[serde(default = "default_hook_max_per_minute")]
+    pub max_per_minute: u32,
+    /// 命令执行与回调请求的超时（秒），默认 10，超时的命令会被终止
+    #[serde(default = "default_hook_timeout")]
+    pub timeout: u64,
+}
+
+fn default_crowdsec_cache_ttl() -> u64 {
+    60
+}
+
+fn default_crowdsec_timeout_ms() -> u64 {
+    500
+}
+
+fn default_crowdsec_alert_threshold() -> u32 {
+    5
+}
+
+fn default_crowdsec_alert_window() -> u64 {
+    60
+}
+
+fn default_crowdsec_scenario() -> String {
+    "vless-rust/auth-failure".to_string()
+}
+
+/// CrowdSec 集成：接受连接时向 LAPI 查询客户端 IP 的决策并断开被封禁的来源，
+/// 并把认证失败作为告警上报
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CrowdSecConfig {
+    /// LAPI 地址，如 `http://127.0.0.1:8080`，未设置时不启用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub lapi_url: Option<String>,
+    /// bouncer API key（`cscli bouncers add` 生成），设置后在接受连接时查询决策
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub bouncer_key: Option<String>,
+    /// 决策缓存时间（秒），默认 60；封禁结果不超过决策剩余时长
+    #[serde(default = "default_crowdsec_cache_ttl")]
+    pub cache_ttl: u64,
+    /// 查询 LAPI 的超时（毫秒），默认 500；超时或出错时放行连接
+    #[serde(default = "default_crowdsec_timeout_ms")]
+    pub timeout_ms: u64,
+    /// machine 标识（`cscli machines add` 生成），与 `password` 一起设置后上报认证失败告警
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub machine_id: Option<String>,
+    /// machine 密码
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+    /// 同一 IP 在 `alert_window` 秒内认证失败达到该次数时上报一条告警，默认 5
+    #[serde(default = "default_crowdsec_alert_threshold")]
+    pub alert_threshold: u32,
+    /// 认证失败计数窗口（秒），默认 60
+    #[serde(default = "default_crowdsec_alert_window")]
+    pub alert_window: u64,
+    /// 告警的场景名，默认 `vless-rust/auth-failure`
+    #[serde(default = "default_crowdsec_scenario")]
+    pub scenario: String,
+}
+
+impl Default for CrowdSecConfig {
+    fn default() -> Self {
+        Self {
+            lapi_url: None,
+            bouncer_key: None,
+            cache_ttl: default_crowdsec_cache_ttl(),
+            timeout_ms: default_crowdsec_timeout_ms(),
+            machine_id: None,
+            password: None,
+            alert_threshold: default_crowdsec_alert_threshold(),
+            alert_window: default_crowdsec_alert_window(),
+            scenario: default_crowdsec_scenario(),
+        }
+    }
+}
+
+impl CrowdSecConfig {
+    /// 是否启用 bouncer（查询决策）
+    pub fn bouncer_enabled(&self) -> bool {
+        self.lapi_url.is_some() && self.bouncer_key.is_some()
+    }
+
+    /// 是否启用告警上报
+    pub fn alerts_enabled(&self) -> bool {
+        self.lapi_url.is_some() && self.machine_id.is_some()
+    }
+
+    /// 校验：设置凭据时必须设置 LAPI 地址，machine 凭据成对出现，超时与阈值大于 0
+    pub fn validate(&self) -> Result<()> {
+        match self.lapi_url {
+            Some(ref url) => {
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    return Err(anyhow::anyhow!(
+                        "crowdsec.lapi_url must start with http:// or https://: {}",
+                        url
+                    ));
+                }
+                if self.bouncer_key.is_none() && self.machine_id.is_none() {
+                    return Err(anyhow::anyhow!(
+                        "crowdsec requires bouncer_key or machine_id"
+                    ));
+                }
+            }
+            None if self.bouncer_key.is_some() || self.machine_id.is_some() => {
+                return Err(anyhow::anyhow!(
+                    "crowdsec.lapi_url is required when bouncer_key or machine_id is set"
+                ));
+            }
+            None => return Ok(()),
+        }
+        if self.machine_id.is_some() != self.password.is_some() {
+            return Err(anyhow::anyhow!(
+                "crowdsec.machine_id and crowdsec.password must be set together"
+            ));
+        }
+        if self.timeout_ms == 0 {
+            return Err(anyhow::anyhow!(
+                "crowdsec.timeout_ms must be greater than 0"
+            ));
+        }
+        if self.alert_threshold == 0 || self.alert_window == 0 {
+            return Err(anyhow::anyhow!(
+                "crowdsec.alert_threshold and crowdsec.alert_window must be greater than 0"
+            ));
+        }
+        if self.scenario.trim().is_empty() {
+            return Err(anyhow::anyhow!("crowdsec.scenario must not be empty"));
+        }
+        Ok(())
+    }
+}
+
+/// 定时重启配置：按计划停止接受新连接、等待现有连接结束后退出，由服务管理器重新拉起
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct RestartConfig {
+    /// cron 表达式（分 时 日 月 周，本地时间），如 `"30 4 * * *"`；也可写 `@hourly` / `@daily` / `@weekly`，未设置时不启用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub schedule: Option<String>,
+    /// 活动连接数不超过该值时才重启，默认 0（仅空闲时）
+    #[serde(default)]
+    pub max_active_connections: usize,
+    /// 到点时连接数超过阈值，在该分钟数内每 30 秒重试，超时则跳过本次，默认 60
+    #[serde(default = "default_restart_defer_minutes")]
+    pub defer_minutes: u64,
+    /// 停止接受新连接后等待现有连接结束的最长时间（秒），默认 30
+    #[serde(default = "default_restart_drain_timeout")]
+    pub drain_timeout: u64,
+    /// 退出码，默认 75；systemd `Restart=on-failure` 只在非 0 退出码时重新拉起
+    #[serde(default = "default_restart_exit_code")]
+    pub exit_code: i32,
+}
+
+fn default_restart_defer_minutes() -> u64 {
+    60
+}
+
+fn default_restart_drain_timeout() -> u64 {
+    30
+}
+
+fn default_restart_exit_code() -> i32 {
+    75
+}
+
+impl Default for RestartConfig {
+    fn default() -> Self {
+        Self {
+            schedule: None,
+            max_active_connections: 0,
+            defer_minutes: default_restart_defer_minutes(),
+            drain_timeout: default_restart_drain_timeout(),
+            exit_code: default_restart_exit_code(),
+        }
+    }
+}
+
+impl RestartConfig {
+    /// 校验计划表达式与退出码
+    pub fn validate(&self) -> Result<()> {
+        if let Some(ref schedule) = self.schedule {
+            crate::restart::Schedule::parse(schedule)
+                .map_err(|e| anyhow::anyhow!("restart.schedule: {}", e))?;
+        }
+        if !(0..=255).contains(&self.exit_code) {
+            return Err(anyhow::anyhow!(
+                "restart.exit_code must be between 0 and 255: {}",
+                self.exit_code
+            ));
+        }
+        Ok(())
+    }
+}
+
+/// 启动横幅格式
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum BannerMode {
+    /// 装饰性文本框
+    #[default]
+    Text,
+    /// 单行 JSON（便于脚本解析）
+    Json,
+    /// 不输出
+    None,
+}
+
+impl BannerMode {
+    /// 解析命令行取值：text、json 或 none
+    pub fn parse(value: &str) -> Option<Self> {
+        match value.to_ascii_lowercase().as_str() {
+            "text" => Some(Self::Text),
+            "json" => Some(Self::Json),
+            "none" => Some(Self::None),
+            _ => None,
+        }
+    }
+}
+
+/// 启动输出配置（横幅与分享链接打印）
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct OutputConfig {
+    /// 启动横幅格式，默认 text（仅非 TUI 模式）
+    #[serde(default)]
+    pub banner: BannerMode,
+    /// 是否在日志中打印分享链接（含 UUID），默认 true
+    #[serde(default = "default_true", skip_serializing_if = "is_true")]
+    pub show_links: bool,
+}
+
+impl Default for OutputConfig {
+    fn default() -> Self {
+        Self {
+            banner: BannerMode::default(),
+            show_links: true,
+        }
+    }
+}
+
+/// 监控数据保留与采样配置（各项均有上限，避免内存无界增长）
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MonitoringConfig {
+    /// 每个目标保留的最近建连样本数，默认 50
+    #[serde(default = "default_destination_window")]
+    pub destination_window: usize,
+    /// 最多跟踪的目标数，默认 2048
+    #[serde(default = "default_max_destinations")]
+    pub max_destinations: usize,
+    /// 每 N 次成功建连记录一次样本（失败总是记录），默认 1
+    #[serde(default = "default_destination_sample_every")]
+    pub destination_sample_every: u32,
+    /// DNS 查询计数最多记录的域名数，默认 10000
+    #[serde(default = "default_dns_stats_entries")]
+    pub dns_stats_entries: usize,
+    /// TUI 保留的日志条数，默认 1000
+    #[serde(default = "default_log_history")]
+    pub log_history: usize,
+    /// 用户统计状态文件：正常退出时保存各用户的流量、连接数与最近活动时间，启动时恢复
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub state_file: Option<String>,
+}
+
+fn default_destination_window() -> usize {
+    50
+}
+
+fn default_max_destinations() -> usize {
+    2048
+}
+
+fn default_destination_sample_every() -> u32 {
+    1
+}
+
+fn default_dns_stats_entries() -> usize {
+    10_000
+}
+
+fn default_log_history() -> usize {
+    1000
+}
+
+impl Default for MonitoringConfig {
+    fn default() -> Self {
+        Self {
+            destination_window: default_destination_window(),
+            max_destinations: default_max_destinations(),
+            destination_sample_every: default_destination_sample_every(),
+            dns_stats_entries: default_dns_stats_entries(),
+            log_history: default_log_history(),
+            state_file: None,
+        }
+    }
+}
+
+impl MonitoringConfig {
+    /// 校验各项是否在允许范围内
+    pub fn validate(&self) -> Result<()> {
+        let limits: [(&str, usize, usize, usize); 5] = [
+            ("destination_window", self.destination_window, 1, 1000),
+            ("max_destinations", self.max_destinations, 1, 65_536),
+            (
+                "destination_sample_every",
+                self.destination_sample_every as usize,
+                1,
+                1000,
+            ),
+            ("dns_stats_entries", self.dns_stats_entries, 0, 100_000),
+            ("log_history", self.log_history, 100, 100_000),
+        ];
+        for (field, value, min, max) in limits {
+            if !(min..=max).contains(&value) {
+                return Err(anyhow::anyhow!(
+                    "monitoring.{} must be between {} and {}: {}",
+                    field,
+                    min,
+                    max,
+                    value
+                ));
+            }
+        }
+        Ok(())
+    }
+}
+
+/// 端口转发的传输类型
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum ForwardNetwork {
+    #[default]
+    Tcp,
+    Udp,
+    /// 同一端口同时转发 TCP 与 UDP
+    Both,
+}
+
+impl ForwardNetwork {
+    /// 是否转发 TCP
+    pub fn tcp(self) -> bool {
+        self != ForwardNetwork::Udp
+    }
+
+    /// 是否转发 UDP
+    pub fn udp(self) -> bool {
+        self != ForwardNetwork::Tcp
+    }
+}
+
+/// 端口转发入站：监听端口收到的原始 TCP / UDP 转发到固定目标（无代理协议）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct ForwardConfig {
+    /// 名称（用于日志与统计，默认使用监听地址）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    /// 监听地址，如 0.0.0.0:8080
+    pub listen: String,
+    /// 目标地址，如 10.0.0.5:80、db.internal:5432
+    pub dest: String,
+    /// 传输类型：tcp、udp 或 both，默认 tcp
+    #[serde(default)]
+    pub network: ForwardNetwork,
+}
+
+/// 出站协议
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum OutboundProtocol {
+    #[default]
+    Vless,
+    /// SOCKS5 代理（CONNECT，可选用户名密码认证）
+    Socks,
+    /// HTTP 代理（CONNECT 隧道）
+    Http,
+}
+
+/// 出站：本地代理入站或服务端目标连接经其转发的上游服务器
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OutboundConfig {
+    /// 出站标识，供本地代理入站与 `server.outbound` 引用
+    pub tag: String,
+    /// 出站协议，默认 vless
+    #[serde(default)]
+    pub protocol: OutboundProtocol,
+    /// 上游服务器地址，如 example.com:443
+    pub server: String,
+    /// 上游服务器上的用户 UUID（仅 vless）
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub uuid: String,
+    /// 代理认证用户名（socks / http，可选）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    /// 代理认证密码（socks / http，可选）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub password: Option<String>,
+}
+
+/// 本地代理入站协议
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum LocalProxyProtocol {
+    /// SOCKS5（仅 CONNECT，无认证）
+    Socks,
+    /// HTTP 代理（CONNECT 与普通请求）
+    Http,
+}
+
+/// 本地代理入站：接收本机应用的 SOCKS / HTTP 代理请求，经出站转发到上游
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct LocalProxyConfig {
+    /// 监听地址，如 127.0.0.1:1080
+    pub listen: String,
+    pub protocol: LocalProxyProtocol,
+    /// 使用的出站标识（默认第一个出站）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outbound: Option<String>,
+}
+
+impl LocalProxyConfig {
+    /// 从出站列表中选出本入站使用的出站（未指定时为第一个出站）
+    pub fn select_outbound<'a>(
+        &self,
+        outbounds: &'a [OutboundConfig],
+    ) -> Option<&'a OutboundConfig> {
+        match self.outbound {
+            Some(ref tag) => outbounds.iter().find(|o| &o.tag == tag),
+            None => outbounds.first(),
+        }
+    }
+}
+
+/// 路由配置
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RoutingConfig {
+    /// 路由规则，按顺序匹配，命中第一条即停止
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub rules: Vec<RoutingRule>,
+    /// MaxMind DB 格式的国家数据库（如 GeoLite2-Country.mmdb），供 `geoip:<国家代码>` 条件使用
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub geoip_file: Option<String>,
+}
+
+/// 路由规则：目标满足任一域名 / IP 条件，且端口与用户条件同时满足时命中；未设置的条件不参与匹配
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct RoutingRule {
+    /// 域名后缀（含域名本身），如 example.com
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub domain_suffix: Vec<String>,
+    /// 域名包含的关键字
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub domain_keyword: Vec<String>,
+    /// 域名正则表达式（小写域名上搜索匹配）
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub domain_regex: Vec<String>,
+    /// 目标 IP 网段，如 10.0.0.0/8；不带前缀长度时为单个地址；
+    /// 也可写 `geoip:<国家代码>`（需 `geoip_file`）与 `geoip:private`
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub ip_cidr: Vec<String>,
+    /// 目标端口，如 "53,443,8000-9000"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<String>,
+    /// 用户 UUID
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub users: Vec<String>,
+    /// 动作：direct、block 或 outbound:<tag>
+    pub action: String,
+}
+
+/// 出站 TLS 规则：命中的目标连接外包一层 TLS（连接仅支持 TLS 的后端）
+#[derive(Debug, Serialize, Deserialize, Clone)]
+pub struct OutboundTlsRule {
+    /// 目标域名（含子域名）或 IP
+    pub dest: String,
+    /// 仅匹配该端口
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub port: Option<u16>,
+    /// TLS SNI 与证书校验名（默认使用目标地址）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sni: Option<String>,
+    /// 是否校验服务器证书，默认 true
+    #[serde(default = "default_true", skip_serializing_if = "is_true")]
+    pub verify: bool,
+    /// 额外信任的 CA 证书文件（PEM），用于内网自签证书
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ca_file: Option<String>,
+    /// ALPN 协议列表，如 ["h2", "http/1.1"]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub alpn: Vec<String>,
+}
+
+/// 管理 API 配置
+#[derive(Debug, Serialize, Deserialize, Clone, Default)]
+pub struct ApiSettings {
+    /// 访问令牌：设置后开放 /api/* 管理接口（Authorization: Bearer <token>）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub token: Option<String>,
+    /// 会话抓包文件目录：设置后可通过管理 API 开启抓包
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub capture_dir: Option<String>,
+}
+
 /// 服务器配置文件格式
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct Config {
     pub server: ServerSettings,
     pub users: Vec<UserConfig>,
+    /// 界面语言：zh 或 en，未设置时按系统区域（LANG 等）检测
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub language: Option<Language>,
+    /// 性能预设：low-memory / balanced / throughput，未设置时使用内置默认值
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub profile: Option<Profile>,
     #[serde(default)]
     pub performance: PerformanceConfig,
+    #[serde(default)]
+    pub dns: DnsConfig,
+    #[serde(default)]
+    pub blocklist: BlocklistConfig,
+    #[serde(default)]
+    pub decoy: DecoyConfig,
+    #[serde(default)]
+    pub sni_proxy: SniProxyConfig,
+    /// 回落规则：握手失败的连接按顺序匹配后原样转发，为空时直接断开
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub fallbacks: Vec<FallbackRule>,
+    #[serde(default)]
+    pub api: ApiSettings,
+    #[serde(default)]
+    pub accounting: AccountingConfig,
+    #[serde(default)]
+    pub port_mapping: PortMappingConfig,
+    #[serde(default)]
+    pub ddns: DdnsConfig,
+    #[serde(default)]
+    pub monitoring: MonitoringConfig,
+    #[serde(default)]
+    pub output: OutputConfig,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outbound_tls: Vec<OutboundTlsRule>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub forwards: Vec<ForwardConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub outbounds: Vec<OutboundConfig>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub local_proxies: Vec<LocalProxyConfig>,
+    #[serde(default)]
+    pub routing: RoutingConfig,
+    #[serde(default)]
+    pub restart: RestartConfig,
+    #[serde(default)]
+    pub sniffing: SniffingConfig,
+    #[serde(default)]
+    pub resolver: ResolverConfig,
+    #[serde(default)]
+    pub maintenance: MaintenanceConfig,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hooks: Vec<HookConfig>,
+    #[serde(default)]
+    pub crowdsec: CrowdSecConfig,
+    /// 由 `profile` 预设填充的字段（配置节，字段名），写回配置文件时省略
+    #[serde(skip)]
+    pub(crate) preset_fields: Vec<(String, String)>,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -109,23 +1606,125 @@ pub struct ServerSettings {
     /// WebSocket 路径（仅 ws 模式使用），默认 "/"
     #[serde(default = "default_ws_path")]
     pub ws_path: String,
+    /// 链接中声明的 WebSocket 早期数据上限（路径追加 `?ed=`），0 表示不声明；
+    /// 服务端始终接受 `Sec-WebSocket-Protocol` 携带的早期数据
+    #[serde(default)]
+    pub ws_early_data: usize,
+    /// 固定公网 IP（设置后跳过公网 IP 探测）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub public_ip: Option<String>,
+    /// 服务域名（优先于 public_ip，用于生成 VLESS 链接）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    /// 是否自动探测公网 IP，默认 true；关闭后链接使用监听地址
+    #[serde(default = "default_detect_public_ip")]
+    pub detect_public_ip: bool,
+    /// WebSocket Host 请求头：设置后校验入站升级请求并写入链接（CDN 前置）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ws_host: Option<String>,
+    /// 链接中的 TLS SNI（TLS 由 CDN 终止时使用）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sni: Option<String>,
+    /// 链接中的端口（CDN 对外端口与监听端口不同时使用）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub link_port: Option<u16>,
+    /// 启动时写入全部用户分享链接的文件（按 0o600 写入）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub links_file: Option<String>,
+    /// 监听端口绑定失败后的重试次数，默认 5
+    #[serde(default = "default_bind_retries")]
+    pub bind_retries: u32,
+    /// 首次重试前的等待毫秒数，之后每次翻倍（上限 10 秒），默认 500
+    #[serde(default = "default_bind_retry_delay_ms")]
+    pub bind_retry_delay_ms: u64,
+    /// 启用的入站协议及识别顺序，默认 `["tls", "http", "vless"]`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub inbound_protocols: Option<Vec<InboundProtocol>>,
+    /// 只接受 TLS 连接（交给 SNI 分流），其他字节立即断开
+    #[serde(default)]
+    pub strict_tls_only: bool,
+    /// 目标 TCP 连接经该出站（`outbounds[].tag`，须为 socks / http）建立，默认直连
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub outbound: Option<String>,
+    /// 拒绝代理到内网、回环、链路本地等保留地址及服务器自身地址，默认 true
+    #[serde(default = "default_true")]
+    pub block_private_destinations: bool,
+    /// 请求中域名目标的解析方式，默认 `AsIs`
+    #[serde(default)]
+    pub domain_strategy: DomainStrategy,
+}
+
+impl ServerSettings {
+    /// 实际启用的入站协议（`strict_tls_only` 优先）
+    pub fn effective_protocols(&self) -> Vec<InboundProtocol> {
+        if self.strict_tls_only {
+            return vec![InboundProtocol::Tls];
+        }
+        self.inbound_protocols
+            .clone()
+            .unwrap_or_else(|| InboundProtocol::ALL.to_vec())
+    }
+}
+
+fn default_bind_retries() -> u32 {
+    5
+}
+
+fn default_bind_retry_delay_ms() -> u64 {
+    500
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 pub struct UserConfig {
     pub uuid: String,
     pub email: Option<String>,
+    /// 是否对该用户应用拦截列表，默认 true
+    #[serde(default = "default_true", skip_serializing_if = "is_true")]
+    pub blocklist: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+fn is_true(value: &bool) -> bool {
+    *value
 }
 
 impl Config {
     /// 从JSON字符串加载配置
+    ///
+    /// 设置 `profile` 时，预设值只填充未显式设置的字段
     pub fn from_json(json: &str) -> Result<Self> {
-        Ok(serde_json::from_str(json)?)
+        let config: Self = serde_json::from_str(json)?;
+        let Some(profile) = config.profile else {
+            return Ok(config);
+        };
+        let mut value: serde_json::Value = serde_json::from_str(json)?;
+        let preset_fields = profile.apply(&mut value);
+        let mut config: Self = serde_json::from_value(value)?;
+        config.preset_fields = preset_fields;
+        Ok(config)
     }
 
     /// 转换为JSON字符串
+    ///
+    /// 由 `profile` 预设填充且未被修改的字段不写出，之后修改 `profile` 仍然生效
     pub fn to_json(&self) -> Result<String> {
-        Ok(serde_json::to_string_pretty(self)?)
+        let Some(profile) = self.profile.filter(|_| !self.preset_fields.is_empty()) else {
+            return Ok(serde_json::to_string_pretty(self)?);
+        };
+        let defaults = profile.defaults();
+        let mut value = serde_json::to_value(self)?;
+        for (section, key) in &self.preset_fields {
+            let Some(target) = value.get_mut(section).and_then(|v| v.as_object_mut()) else {
+                continue;
+            };
+            if target.get(key) == defaults.get(section).and_then(|d| d.get(key)) {
+                target.remove(key);
+            }
+        }
+        Ok(serde_json::to_string_pretty(&value)?)
     }
 
     /// 获取绑定地址
@@ -133,4 +1732,237 @@ impl Config {
         let addr_str = format!("{}:{}", self.server.listen, self.server.port);
         Ok(addr_str.parse()?)
     }
+
+    /// 校验启动时会检查的字段（监听地址、用户、伪装配置、监控上限）
+    pub fn validate(&self) -> Result<()> {
+        self.bind_addr()
+            .map_err(|e| anyhow::anyhow!("Invalid server.listen/port: {}", e))?;
+        self.validate_users()?;
+        self.decoy.validate()?;
+        self.sni_proxy.validate()?;
+        self.validate_fallbacks()?;
+        self.validate_inbound()?;
+        self.validate_outbounds()?;
+        self.validate_routing()?;
+        self.restart.validate()?;
+        self.sniffing.validate()?;
+        self.resolver.validate()?;
+        self.maintenance.validate()?;
+        self.validate_hooks()?;
+        self.crowdsec.validate()?;
+        self.performance.validate()?;
+        self.monitoring.validate()
+    }
+
+    /// 校验回落规则：目标为 host:port，路径以 `/` 开头
+    pub fn validate_fallbacks(&self) -> Result<()> {
+        for (index, rule) in self.fallbacks.iter().enumerate() {
+            if rule.dest.rsplit_once(':').is_none_or(|(host, port)| {
+                host.is_empty() || port.parse::<u16>().map_or(true, |p| p == 0)
+            }) {
+                return Err(anyhow::anyhow!(
+                    "fallbacks[{}].dest must be host:port: {}",
+                    index,
+                    rule.dest
+                ));
+            }
+            if rule.path.as_deref().is_some_and(|p| !p.starts_with('/')) {
+                return Err(anyhow::anyhow!(
+                    "fallbacks[{}].path must start with '/'",
+                    index
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// 校验连接钩子：至少配置命令或回调之一，命令模板只使用已知占位符，超时大于 0
+    pub fn validate_hooks(&self) -> Result<()> {
+        for (index, hook) in self.hooks.iter().enumerate() {
+            if hook.on.is_empty() {
+                return Err(anyhow::anyhow!("hooks[{}].on must not be empty", index));
+            }
+            if hook.exec.is_empty() && hook.webhook_url.is_none() {
+                return Err(anyhow::anyhow!(
+                    "hooks[{}] requires exec or webhook_url",
+                    index
+                ));
+            }
+            if !hook.exec.is_empty() {
+                crate::hooks::CommandTemplate::parse(&hook.exec)
+                    .map_err(|e| anyhow::anyhow!("hooks[{}].exec: {}", index, e))?;
+            }
+            if let Some(ref url) = hook.webhook_url {
+                if !url.starts_with("http://") && !url.starts_with("https://") {
+                    return Err(anyhow::anyhow!(
+                        "hooks[{}].webhook_url must start with http:// or https://: {}",
+                        index,
+                        url
+                    ));
+                }
+            }
+            if hook.timeout == 0 {
+                return Err(anyhow::anyhow!(
+                    "hooks[{}].timeout must be greater than 0",
+                    index
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// 校验入站协议：至少启用一种，只接受 TLS 时必须配置 SNI 分流
+    pub fn validate_inbound(&self) -> Result<()> {
+        let protocols = self.server.effective_protocols();
+        if protocols.is_empty() {
+            return Err(anyhow::anyhow!(
+                "server.inbound_protocols must not be empty"
+            ));
+        }
+        if protocols == [InboundProtocol::Tls] && !self.sni_proxy.is_enabled() {
+            let field = if self.server.strict_tls_only {
+                "server.strict_tls_only"
+            } else {
+                "server.inbound_protocols"
+            };
+            return Err(anyhow::anyhow!(
+                "{} accepts only TLS, which requires sni_proxy (TLS inbound is not supported)",
+                field
+            ));
+        }
+        Ok(())
+    }
+
+    /// 校验出站与本地代理入站：出站标识唯一、UUID 有效，入站与 `server.outbound` 引用的出站存在
+    pub fn validate_outbounds(&self) -> Result<()> {
+        let mut tags = HashMap::new();
+        for (index, outbound) in self.outbounds.iter().enumerate() {
+            if outbound.tag.is_empty() {
+                return Err(anyhow::anyhow!(
+                    "outbounds[{}].tag must not be empty",
+                    index
+                ));
+            }
+            if let Some(first) = tags.insert(outbound.tag.as_str(), index) {
+                return Err(anyhow::anyhow!(
+                    "outbounds[{}].tag: duplicate tag '{}' (same as outbounds[{}])",
+                    index,
+                    outbound.tag,
+                    first
+                ));
+            }
+            if outbound.server.rsplit_once(':').is_none_or(|(host, port)| {
+                host.is_empty() || port.parse::<u16>().map_or(true, |p| p == 0)
+            }) {
+                return Err(anyhow::anyhow!(
+                    "outbounds[{}].server must be host:port: {}",
+                    index,
+                    outbound.server
+                ));
+            }
+            if outbound.protocol == OutboundProtocol::Vless {
+                uuid::Uuid::parse_str(outbound.uuid.trim()).map_err(|_| {
+                    anyhow::anyhow!(
+                        "outbounds[{}].uuid: invalid UUID '{}'",
+                        index,
+                        outbound.uuid
+                    )
+                })?;
+            }
+            if outbound.username.is_some() != outbound.password.is_some() {
+                return Err(anyhow::anyhow!(
+                    "outbounds[{}]: username and password must be set together",
+                    index
+                ));
+            }
+        }
+        if let Some(ref tag) = self.server.outbound {
+            match self.outbounds.iter().find(|o| &o.tag == tag) {
+                None => {
+                    return Err(anyhow::anyhow!(
+                        "server.outbound: unknown outbound '{}'",
+                        tag
+                    ));
+                }
+                Some(outbound) if outbound.protocol == OutboundProtocol::Vless => {
+                    return Err(anyhow::anyhow!(
+                        "server.outbound: outbound '{}' must be socks or http",
+                        tag
+                    ));
+                }
+                Some(_) => {}
+            }
+        }
+        for (index, proxy) in self.local_proxies.iter().enumerate() {
+            proxy.listen.parse::<SocketAddr>().map_err(|_| {
+                anyhow::anyhow!(
+                    "local_proxies[{}].listen: invalid address '{}'",
+                    index,
+                    proxy.listen
+                )
+            })?;
+            match proxy.outbound {
+                Some(ref tag) if !tags.contains_key(tag.as_str()) => {
+                    return Err(anyhow::anyhow!(
+                        "local_proxies[{}].outbound: unknown outbound '{}'",
+                        index,
+                        tag
+                    ));
+                }
+                None if self.outbounds.is_empty() => {
+                    return Err(anyhow::anyhow!(
+                        "local_proxies[{}] requires at least one entry in outbounds",
+                        index
+                    ));
+                }
+                _ => {}
+            }
+        }
+        Ok(())
+    }
+
+    /// 校验路由规则：动作、出站引用、正则、网段、端口与用户 UUID
+    pub fn validate_routing(&self) -> Result<()> {
+        crate::router::Router::new(&self.routing, &self.outbounds).map(|_| ())
+    }
+
+    /// 校验用户列表：UUID 格式错误或重复时报错，错误信息带 JSON 位置（如 `users[2].uuid`）
+    pub fn validate_users(&self) -> Result<()> {
+        let mut seen: HashMap<uuid::Uuid, usize> = HashMap::new();
+        for (index, user) in self.users.iter().enumerate() {
+            let uuid = uuid::Uuid::parse_str(user.uuid.trim()).map_err(|_| {
+                anyhow::anyhow!("users[{}].uuid: invalid UUID '{}'", index, user.uuid)
+            })?;
+            if let Some(first) = seen.insert(uuid, index) {
+                return Err(anyhow::anyhow!(
+                    "users[{}].uuid: duplicate UUID {} (same as users[{}])",
+                    index,
+                    uuid,
+                    first
+                ));
+            }
+        }
+        Ok(())
+    }
+
+    /// 用户列表中的警告：邮箱重复（不区分大小写）时链接查询只能命中其中一个用户
+    pub fn user_warnings(&self) -> Vec<String> {
+        let mut seen: HashMap<String, usize> = HashMap::new();
+        let mut warnings = Vec::new();
+        for (index, user) in self.users.iter().enumerate() {
+            let Some(ref email) = user.email else {
+                continue;
+            };
+            match seen.get(&email.to_lowercase()) {
+                Some(first) => warnings.push(format!(
+                    "users[{}].email: duplicate email '{}' (same as users[{}])",
+                    index, email, first
+                )),
+                None => {
+                    seen.insert(email.to_lowercase(), index);
+                }
+            }
+        }
+        warnings
+    }
 }
@@ -0,0 +1,175 @@
+//! 配置差异模块
+//!
+//! 重新加载预演（dry run）：解析并校验磁盘上的新配置，与运行中的配置比较，
+//! 列出新增 / 删除 / 变更的用户与变更的配置项，不做任何修改
+
+use crate::config::{Config, UserConfig};
+use crate::secrets::{decrypt_config, ConfigKey};
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt::Write as _;
+
+/// 读取、解密并校验配置文件
+pub fn load_config_file(path: &str) -> Result<Config> {
+    let content =
+        std::fs::read_to_string(path).map_err(|e| anyhow!("Cannot read {}: {}", path, e))?;
+    let mut config =
+        Config::from_json(&content).map_err(|e| anyhow!("Invalid config {}: {}", path, e))?;
+    let key = ConfigKey::from_env()?;
+    decrypt_config(&mut config, key.as_ref())?;
+    config.validate()?;
+    Ok(config)
+}
+
+/// 差异中的用户条目
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct UserEntry {
+    pub uuid: String,
+    pub email: Option<String>,
+}
+
+impl From<&UserConfig> for UserEntry {
+    fn from(user: &UserConfig) -> Self {
+        Self {
+            uuid: user.uuid.clone(),
+            email: user.email.clone(),
+        }
+    }
+}
+
+/// 两份配置的差异
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct ConfigDiff {
+    pub users_added: Vec<UserEntry>,
+    pub users_removed: Vec<UserEntry>,
+    /// UUID 相同但邮箱或拦截列表开关变化的用户（取新值）
+    pub users_changed: Vec<UserEntry>,
+    /// 变化的配置项路径（如 `blocklist.domains`），不含取值，避免泄露敏感字段
+    pub changed: Vec<String>,
+    /// 新配置中不阻止加载的问题（如邮箱重复），不计入差异
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+impl ConfigDiff {
+    /// 是否无差异
+    pub fn is_empty(&self) -> bool {
+        self.users_added.is_empty()
+            && self.users_removed.is_empty()
+            && self.users_changed.is_empty()
+            && self.changed.is_empty()
+    }
+
+    /// 终端输出格式
+    pub fn render(&self) -> String {
+        let mut out = String::new();
+        for warning in &self.warnings {
+            let _ = writeln!(out, "! {}", warning);
+        }
+        if self.is_empty() {
+            out.push_str("No changes\n");
+            return out;
+        }
+
+        for (mark, users) in [
+            ("+", &self.users_added),
+            ("-", &self.users_removed),
+            ("~", &self.users_changed),
+        ] {
+            for user in users {
+                let _ = writeln!(
+                    out,
+                    "{} user {} ({})",
+                    mark,
+                    user.email.as_deref().unwrap_or("-"),
+                    user.uuid
+                );
+            }
+        }
+        for path in &self.changed {
+            let _ = writeln!(out, "~ {}", path);
+        }
+        out
+    }
+}
+
+/// 比较运行中配置与新配置
+pub fn diff_configs(running: &Config, new: &Config) -> ConfigDiff {
+    let old_users: BTreeMap<&str, &UserConfig> =
+        running.users.iter().map(|u| (u.uuid.as_str(), u)).collect();
+    let new_users: BTreeMap<&str, &UserConfig> =
+        new.users.iter().map(|u| (u.uuid.as_str(), u)).collect();
+
+    let mut diff = ConfigDiff::default();
+    for (uuid, user) in &new_users {
+        match old_users.get(uuid) {
+            None => diff.users_added.push(UserEntry::from(*user)),
+            Some(old) if old.email != user.email || old.blocklist != user.blocklist => {
+                diff.users_changed.push(UserEntry::from(*user))
+            }
+            Some(_) => {}
+        }
+    }
+    for (uuid, user) in &old_users {
+        if !new_users.contains_key(uuid) {
+            diff.users_removed.push(UserEntry::from(*user));
+        }
+    }
+
+    let mut old_value = serde_json::to_value(running).unwrap_or_default();
+    let mut new_value = serde_json::to_value(new).unwrap_or_default();
+    for value in [&mut old_value, &mut new_value] {
+        if let Some(map) = value.as_object_mut() {
+            map.remove("users");
+        }
+    }
+    collect_changes("", &old_value, &new_value, &mut diff.changed);
+    diff.warnings = new.user_warnings();
+    diff
+}
+
+/// 递归比较对象字段，数组与标量整体比较
+fn collect_changes(prefix: &str, old: &Value, new: &Value, out: &mut Vec<String>) {
+    if let (Value::Object(old_map), Value::Object(new_map)) = (old, new) {
+        let keys: BTreeSet<&String> = old_map.keys().chain(new_map.keys()).collect();
+        for key in keys {
+            let path = if prefix.is_empty() {
+                key.clone()
+            } else {
+                format!("{}.{}", prefix, key)
+            };
+            collect_changes(
+                &path,
+                old_map.get(key).unwrap_or(&Value::Null),
+                new_map.get(key).unwrap_or(&Value::Null),
+                out,
+            );
+        }
+    } else if old != new {
+        out.push(prefix.to_string());
+    }
+}
+
+/// 重新加载预演：记住配置文件路径与运行中的配置
+#[derive(Debug)]
+pub struct ReloadPreview {
+    path: String,
+    running: Config,
+}
+
+impl ReloadPreview {
+    pub fn new(path: impl Into<String>, running: Config) -> Self {
+        Self {
+            path: path.into(),
+            running,
+        }
+    }
+
+    /// 重新读取配置文件并返回与运行中配置的差异
+    pub fn preview(&self) -> Result<ConfigDiff> {
+        let new = load_config_file(&self.path)?;
+        Ok(diff_configs(&self.running, &new))
+    }
+}
@@ -0,0 +1,491 @@
+//! CrowdSec 集成模块
+//!
+//! bouncer：接受连接时向 CrowdSec LAPI 查询客户端 IP 的决策（带本地缓存），被封禁的来源直接断开；
+//! LAPI 不可用时放行连接，安全组件故障不影响服务。
+//! 告警：订阅认证失败事件，同一 IP 在窗口内失败达到阈值时以 machine 身份上报告警，
+//! 是否封禁由 LAPI 的 profiles 决定
+
+use crate::config::{CrowdSecConfig, UtcTime};
+use crate::events::Event;
+use anyhow::Result;
+use chrono::{SecondsFormat, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::sync::broadcast;
+use tracing::{debug, info, warn};
+
+/// 决策缓存条目上限，达到后先清理过期条目，仍超出时清空
+const MAX_CACHE_ENTRIES: usize = 65536;
+
+/// 认证失败计数的 IP 数上限
+const MAX_TRACKED_IPS: usize = 65536;
+
+/// 告警请求（登录与上报）超时
+const ALERT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 单条告警最多携带的事件数
+const MAX_ALERT_EVENTS: usize = 20;
+
+/// 登录令牌未返回有效期时的缓存时间
+const DEFAULT_TOKEN_TTL: Duration = Duration::from_secs(30 * 60);
+
+/// LAPI 返回的决策（只取用到的字段）
+#[derive(Debug, Deserialize)]
+struct Decision {
+    #[serde(rename = "type")]
+    kind: String,
+    #[serde(default)]
+    duration: String,
+}
+
+/// 解析 Go 风格的时长（如 `3h59m58.5s`、`500ms`）；负数视为已过期返回零，超出范围取 `Duration::MAX`，格式错误返回 None
+pub fn parse_go_duration(text: &str) -> Option<Duration> {
+    let text = text.trim();
+    if let Some(rest) = text.strip_prefix('-') {
+        return parse_go_duration(rest).map(|_| Duration::ZERO);
+    }
+    if text.is_empty() {
+        return None;
+    }
+    let mut total = 0f64;
+    let mut rest = text;
+    while !rest.is_empty() {
+        let number_len = rest
+            .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+            .unwrap_or(rest.len());
+        let value: f64 = rest[..number_len].parse().ok()?;
+        rest = &rest[number_len..];
+        let unit_len = rest
+            .find(|c: char| c.is_ascii_digit() || c == '.')
+            .unwrap_or(rest.len());
+        let scale = match &rest[..unit_len] {
+            "h" => 3600.0,
+            "m" => 60.0,
+            "s" => 1.0,
+            "ms" => 1e-3,
+            "us" | "µs" => 1e-6,
+            "ns" => 1e-9,
+            _ => return None,
+        };
+        total += value * scale;
+        rest = &rest[unit_len..];
+    }
+    Some(Duration::try_from_secs_f64(total).unwrap_or(Duration::MAX))
+}
+
+/// bouncer 统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct BouncerStats {
+    /// 检查的连接数
+    pub checked: u64,
+    /// 命中本地缓存的次数
+    pub cache_hits: u64,
+    /// 因封禁决策断开的连接数
+    pub blocked: u64,
+    /// 查询 LAPI 失败（已放行）的次数
+    pub errors: u64,
+}
+
+/// 决策缓存条目
+#[derive(Debug, Clone, Copy)]
+struct CacheEntry {
+    banned: bool,
+    expires: Instant,
+}
+
+/// CrowdSec bouncer：按客户端 IP 查询决策
+#[derive(Debug)]
+pub struct Bouncer {
+    client: reqwest::Client,
+    url: String,
+    key: String,
+    cache_ttl: Duration,
+    cache: Mutex<HashMap<IpAddr, CacheEntry>>,
+    checked: AtomicU64,
+    cache_hits: AtomicU64,
+    blocked: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl Bouncer {
+    /// 按配置创建（需要 `lapi_url` 与 `bouncer_key`）
+    pub fn new(config: &CrowdSecConfig) -> Result<Self> {
+        let (Some(url), Some(key)) = (&config.lapi_url, &config.bouncer_key) else {
+            return Err(anyhow::anyhow!(
+                "crowdsec bouncer requires lapi_url and bouncer_key"
+            ));
+        };
+        let client = reqwest::Client::builder()
+            .user_agent("VLESS-Rust/1.0")
+            .timeout(Duration::from_millis(config.timeout_ms))
+            .build()?;
+        Ok(Self {
+            client,
+            url: url.trim_end_matches('/').to_string(),
+            key: key.clone(),
+            cache_ttl: Duration::from_secs(config.cache_ttl),
+            cache: Mutex::new(HashMap::new()),
+            checked: AtomicU64::new(0),
+            cache_hits: AtomicU64::new(0),
+            blocked: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+        })
+    }
+
+    /// 客户端 IP 是否被封禁；查询失败时放行
+    pub async fn is_banned(&self, ip: IpAddr) -> bool {
+        let ip = ip.to_canonical();
+        self.checked.fetch_add(1, Ordering::Relaxed);
+        let banned = match self.cached(ip, Instant::now()) {
+            Some(banned) => {
+                self.cache_hits.fetch_add(1, Ordering::Relaxed);
+                banned
+            }
+            None => match self.query(ip).await {
+                Ok(ban) => {
+                    self.store(ip, ban, Instant::now());
+                    ban.is_some()
+                }
+                Err(e) => {
+                    let errors = self.errors.fetch_add(1, Ordering::Relaxed) + 1;
+                    // 连续失败时只在开始与每 100 次时告警，避免刷屏
+                    if errors == 1 || errors.is_multiple_of(100) {
+                        warn!(
+                            "CrowdSec LAPI query for {} failed: {} ({} errors so far), allowing",
+                            ip, e, errors
+                        );
+                    } else {
+                        debug!("CrowdSec LAPI query for {} failed: {}, allowing", ip, e);
+                    }
+                    false
+                }
+            },
+        };
+        if banned {
+            self.blocked.fetch_add(1, Ordering::Relaxed);
+        }
+        banned
+    }
+
+    /// 当前统计
+    pub fn stats(&self) -> BouncerStats {
+        BouncerStats {
+            checked: self.checked.load(Ordering::Relaxed),
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            blocked: self.blocked.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+        }
+    }
+
+    /// 查询 LAPI，返回封禁剩余时长（未封禁为 None）
+    async fn query(&self, ip: IpAddr) -> Result<Option<Duration>> {
+        let response = self
+            .client
+            .get(format!("{}/v1/decisions", self.url))
+            .query(&[("ip", ip.to_string())])
+            .header("X-Api-Key", &self.key)
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!("HTTP status {}", response.status()));
+        }
+        let body = response.bytes().await?;
+        // 没有决策时 LAPI 返回 `null`
+        let decisions: Option<Vec<Decision>> = serde_json::from_slice(&body)?;
+        Ok(decisions
+            .unwrap_or_default()
+            .iter()
+            .filter(|decision| decision.kind.eq_ignore_ascii_case("ban"))
+            // 时长无法解析时按仍在封禁处理，缓存时间取 `cache_ttl`
+            .map(|decision| parse_go_duration(&decision.duration).unwrap_or(Duration::MAX))
+            .filter(|remaining| !remaining.is_zero())
+            .max())
+    }
+
+    fn cached(&self, ip: IpAddr, now: Instant) -> Option<bool> {
+        let cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        cache
+            .get(&ip)
+            .filter(|entry| entry.expires > now)
+            .map(|entry| entry.banned)
+    }
+
+    /// 缓存查询结果：封禁结果不超过决策剩余时长，`cache_ttl` 为 0 时不缓存
+    fn store(&self, ip: IpAddr, ban: Option<Duration>, now: Instant) {
+        let ttl = ban.map_or(self.cache_ttl, |remaining| remaining.min(self.cache_ttl));
+        if ttl.is_zero() {
+            return;
+        }
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if cache.len() >= MAX_CACHE_ENTRIES {
+            cache.retain(|_, entry| entry.expires > now);
+            if cache.len() >= MAX_CACHE_ENTRIES {
+                cache.clear();
+            }
+        }
+        cache.insert(
+            ip,
+            CacheEntry {
+                banned: ban.is_some(),
+                expires: now + ttl,
+            },
+        );
+    }
+}
+
+/// 认证失败计数：同一 IP 在窗口内失败达到阈值时取出这些失败的时间
+#[derive(Debug)]
+pub struct FailureTracker {
+    threshold: usize,
+    window: chrono::Duration,
+    failures: HashMap<IpAddr, Vec<UtcTime>>,
+}
+
+impl FailureTracker {
+    pub fn new(threshold: u32, window: Duration) -> Self {
+        Self {
+            threshold: threshold.max(1) as usize,
+            window: chrono::Duration::from_std(window).unwrap_or(chrono::Duration::MAX),
+            failures: HashMap::new(),
+        }
+    }
+
+    /// 记录一次失败，达到阈值时返回窗口内的失败时间并重新计数
+    pub fn record(&mut self, ip: IpAddr, now: UtcTime) -> Option<Vec<UtcTime>> {
+        let window = self.window;
+        if self.failures.len() >= MAX_TRACKED_IPS && !self.failures.contains_key(&ip) {
+            self.failures
+                .retain(|_, times| times.last().is_some_and(|last| now - *last < window));
+            if self.failures.len() >= MAX_TRACKED_IPS {
+                return None;
+            }
+        }
+        let times = self.failures.entry(ip).or_default();
+        times.retain(|time| now - *time < window);
+        times.push(now);
+        if times.len() < self.threshold {
+            return None;
+        }
+        self.failures.remove(&ip)
+    }
+}
+
+/// 生成 LAPI 告警（`POST /v1/alerts` 数组中的一项）；不携带决策，由 LAPI profiles 决定处置
+pub fn build_alert(scenario: &str, ip: IpAddr, failures: &[UtcTime]) -> serde_json::Value {
+    let format = |time: &UtcTime| time.to_rfc3339_opts(SecondsFormat::Secs, true);
+    let ip = ip.to_string();
+    let start = failures.first().map(format).unwrap_or_default();
+    let stop = failures.last().map(format).unwrap_or_default();
+    let events: Vec<serde_json::Value> = failures
+        .iter()
+        .rev()
+        .take(MAX_ALERT_EVENTS)
+        .rev()
+        .map(|time| {
+            serde_json::json!({
+                "timestamp": format(time),
+                "meta": [
+                    { "key": "source_ip", "value": ip },
+                    { "key": "service", "value": "vless" },
+                ],
+            })
+        })
+        .collect();
+    serde_json::json!({
+        "scenario": scenario,
+        "scenario_hash": "",
+        "scenario_version": "",
+        "message": format!("{} failed VLESS authentications from {}", failures.len(), ip),
+        "events_count": failures.len(),
+        "events": events,
+        "start_at": start,
+        "stop_at": stop,
+        "capacity": failures.len(),
+        "leakspeed": "0s",
+        "simulated": false,
+        "source": { "scope": "Ip", "value": ip, "ip": ip },
+    })
+}
+
+/// 以 machine 身份访问 LAPI（登录令牌缓存到过期前）
+struct Machine {
+    client: reqwest::Client,
+    url: String,
+    machine_id: String,
+    password: String,
+    scenario: String,
+    token: Option<(String, Instant)>,
+}
+
+impl Machine {
+    /// 登录并返回令牌
+    async fn login(&mut self) -> Result<String> {
+        let body = serde_json::json!({
+            "machine_id": self.machine_id,
+            "password": self.password,
+            "scenarios": [self.scenario],
+        });
+        let response = self
+            .client
+            .post(format!("{}/v1/watchers/login", self.url))
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(body.to_string())
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(anyhow::anyhow!(
+                "login failed: HTTP status {}",
+                response.status()
+            ));
+        }
+        let body: serde_json::Value = serde_json::from_slice(&response.bytes().await?)?;
+        let token = body["token"]
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("login response has no token"))?
+            .to_string();
+        // 提前一分钟刷新
+        let ttl = body["expire"]
+            .as_str()
+            .and_then(|expire| chrono::DateTime::parse_from_rfc3339(expire).ok())
+            .and_then(|expire| (expire.with_timezone(&Utc) - Utc::now()).to_std().ok())
+            .map_or(DEFAULT_TOKEN_TTL, |ttl| {
+                ttl.saturating_sub(Duration::from_secs(60))
+            });
+        self.token = Some((token.clone(), Instant::now() + ttl));
+        Ok(token)
+    }
+
+    async fn token(&mut self) -> Result<String> {
+        match self.token {
+            Some((ref token, expires)) if expires > Instant::now() => Ok(token.clone()),
+            _ => self.login().await,
+        }
+    }
+
+    /// 上报告警；令牌失效（401）时重新登录一次
+    async fn push(&mut self, alert: &serde_json::Value) -> Result<()> {
+        let body = serde_json::json!([alert]).to_string();
+        for attempt in 0..2 {
+            let token = self.token().await?;
+            let response = self
+                .client
+                .post(format!("{}/v1/alerts", self.url))
+                .header(reqwest::header::CONTENT_TYPE, "application/json")
+                .bearer_auth(token)
+                .body(body.clone())
+                .send()
+                .await?;
+            let status = response.status();
+            if status == reqwest::StatusCode::UNAUTHORIZED && attempt == 0 {
+                self.token = None;
+                continue;
+            }
+            if !status.is_success() {
+                return Err(anyhow::anyhow!("HTTP status {}", status));
+            }
+            return Ok(());
+        }
+        Err(anyhow::anyhow!("unauthorized"))
+    }
+}
+
+/// 认证失败告警上报器
+#[derive(Debug)]
+pub struct AlertReporter {
+    sent: Arc<AtomicU64>,
+    failed: Arc<AtomicU64>,
+}
+
+impl AlertReporter {
+    /// 按配置启动后台任务（需要 `lapi_url`、`machine_id` 与 `password`），消费 `events` 中的认证失败事件
+    pub fn spawn(config: &CrowdSecConfig, events: broadcast::Receiver<Event>) -> Result<Self> {
+        let (Some(url), Some(machine_id), Some(password)) =
+            (&config.lapi_url, &config.machine_id, &config.password)
+        else {
+            return Err(anyhow::anyhow!(
+                "crowdsec alerts require lapi_url, machine_id and password"
+            ));
+        };
+        let client = reqwest::Client::builder()
+            .user_agent("VLESS-Rust/1.0")
+            .timeout(ALERT_TIMEOUT)
+            .build()?;
+        let machine = Machine {
+            client,
+            url: url.trim_end_matches('/').to_string(),
+            machine_id: machine_id.clone(),
+            password: password.clone(),
+            scenario: config.scenario.clone(),
+            token: None,
+        };
+        let tracker = FailureTracker::new(
+            config.alert_threshold,
+            Duration::from_secs(config.alert_window),
+        );
+
+        let sent = Arc::new(AtomicU64::new(0));
+        let failed = Arc::new(AtomicU64::new(0));
+        tokio::spawn(run_alerts(
+            events,
+            machine,
+            tracker,
+            Arc::clone(&sent),
+            Arc::clone(&failed),
+        ));
+        Ok(Self { sent, failed })
+    }
+
+    /// 已上报的告警数
+    pub fn sent_count(&self) -> u64 {
+        self.sent.load(Ordering::Relaxed)
+    }
+
+    /// 上报失败的告警数
+    pub fn failed_count(&self) -> u64 {
+        self.failed.load(Ordering::Relaxed)
+    }
+}
+
+/// 后台上报循环
+async fn run_alerts(
+    mut events: broadcast::Receiver<Event>,
+    mut machine: Machine,
+    mut tracker: FailureTracker,
+    sent: Arc<AtomicU64>,
+    failed: Arc<AtomicU64>,
+) {
+    loop {
+        let client_addr = match events.recv().await {
+            Ok(Event::AuthFailed { client_addr, .. }) => client_addr,
+            Ok(_) => continue,
+            Err(broadcast::error::RecvError::Lagged(n)) => {
+                warn!("CrowdSec alerts lagged behind, {} events dropped", n);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+        let ip = client_addr.ip().to_canonical();
+        let Some(failures) = tracker.record(ip, Utc::now()) else {
+            continue;
+        };
+        let alert = build_alert(&machine.scenario, ip, &failures);
+        match machine.push(&alert).await {
+            Ok(()) => {
+                sent.fetch_add(1, Ordering::Relaxed);
+                info!(
+                    "CrowdSec alert sent for {} ({} authentication failures)",
+                    ip,
+                    failures.len()
+                );
+            }
+            Err(e) => {
+                failed.fetch_add(1, Ordering::Relaxed);
+                warn!("CrowdSec alert for {} failed: {}", ip, e);
+            }
+        }
+    }
+}
@@ -0,0 +1,209 @@
+//! 动态 DNS 更新模块
+//!
+//! 定期探测公网 IP，变化时通过 Cloudflare / DuckDNS API 更新配置的域名（A 记录），
+//! 分享链接使用该域名，适用于家庭宽带等动态 IP 部署
+
+use crate::config::{DdnsConfig, DdnsProvider};
+use crate::public_ip::fetch_public_ip_with_timeout;
+use anyhow::{anyhow, Result};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tracing::{debug, info, warn};
+
+/// Cloudflare API 地址
+const CLOUDFLARE_API: &str = "https://api.cloudflare.com/client/v4";
+
+/// DuckDNS API 地址
+const DUCKDNS_API: &str = "https://www.duckdns.org";
+
+/// DuckDNS 域名后缀
+const DUCKDNS_SUFFIX: &str = ".duckdns.org";
+
+/// 单次 API 请求超时
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 公网 IP 探测超时（秒）
+const DETECT_TIMEOUT_SECS: u64 = 5;
+
+/// 最短检查间隔
+const MIN_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 动态 DNS 更新器
+#[derive(Debug)]
+pub struct DdnsUpdater {
+    provider: DdnsProvider,
+    hostname: String,
+    token: String,
+    zone_id: Option<String>,
+    endpoint: String,
+    interval: Duration,
+    client: reqwest::Client,
+    /// 最近一次成功写入的 IP
+    last_ip: Mutex<Option<String>>,
+}
+
+impl DdnsUpdater {
+    /// 创建更新器（校验必填字段）
+    pub fn new(config: &DdnsConfig) -> Result<Self> {
+        let provider = config
+            .provider
+            .ok_or_else(|| anyhow!("ddns.provider is not set"))?;
+        if config.hostname.is_empty() {
+            return Err(anyhow!("ddns.hostname is required"));
+        }
+        if config.token.is_empty() {
+            return Err(anyhow!("ddns.token is required"));
+        }
+        let endpoint = match provider {
+            DdnsProvider::Cloudflare => {
+                if config.zone_id.is_none() {
+                    return Err(anyhow!("ddns.zone_id is required for cloudflare"));
+                }
+                config.endpoint.as_deref().unwrap_or(CLOUDFLARE_API)
+            }
+            DdnsProvider::Duckdns => {
+                if !config.hostname.ends_with(DUCKDNS_SUFFIX) {
+                    return Err(anyhow!("ddns.hostname must end with {}", DUCKDNS_SUFFIX));
+                }
+                config.endpoint.as_deref().unwrap_or(DUCKDNS_API)
+            }
+        };
+
+        let client = reqwest::Client::builder()
+            .user_agent("VLESS-Rust/1.0")
+            .timeout(REQUEST_TIMEOUT)
+            .build()?;
+
+        Ok(Self {
+            provider,
+            hostname: config.hostname.clone(),
+            token: config.token.clone(),
+            zone_id: config.zone_id.clone(),
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            interval: Duration::from_secs(config.interval).max(MIN_INTERVAL),
+            client,
+            last_ip: Mutex::new(None),
+        })
+    }
+
+    /// 更新的域名
+    pub fn hostname(&self) -> &str {
+        &self.hostname
+    }
+
+    /// 将域名指向 `ip`；与上次成功写入的 IP 相同时跳过，返回是否实际更新
+    pub async fn update(&self, ip: &str) -> Result<bool> {
+        if self
+            .last_ip
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .as_deref()
+            == Some(ip)
+        {
+            return Ok(false);
+        }
+
+        match self.provider {
+            DdnsProvider::Cloudflare => self.update_cloudflare(ip).await?,
+            DdnsProvider::Duckdns => self.update_duckdns(ip).await?,
+        }
+
+        *self.last_ip.lock().unwrap_or_else(|e| e.into_inner()) = Some(ip.to_string());
+        Ok(true)
+    }
+
+    /// Cloudflare：查询 A 记录，存在则覆盖，不存在则创建
+    async fn update_cloudflare(&self, ip: &str) -> Result<()> {
+        let zone_id = self.zone_id.as_deref().unwrap_or_default();
+        let records_url = format!("{}/zones/{}/dns_records", self.endpoint, zone_id);
+
+        let response = self
+            .client
+            .get(&records_url)
+            .query(&[("type", "A"), ("name", self.hostname.as_str())])
+            .bearer_auth(&self.token)
+            .send()
+            .await?;
+        let body = cloudflare_result(response).await?;
+        let record_id = body["result"]
+            .as_array()
+            .and_then(|records| records.first())
+            .and_then(|record| record["id"].as_str())
+            .map(String::from);
+
+        let record = serde_json::json!({
+            "type": "A",
+            "name": self.hostname,
+            "content": ip,
+            "ttl": 1,
+            "proxied": false,
+        })
+        .to_string();
+        let request = match record_id {
+            Some(id) => self.client.put(format!("{}/{}", records_url, id)),
+            None => self.client.post(&records_url),
+        };
+        let response = request
+            .bearer_auth(&self.token)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .body(record)
+            .send()
+            .await?;
+        cloudflare_result(response).await?;
+        Ok(())
+    }
+
+    /// DuckDNS：子域名 + token 的 GET 接口，成功返回 `OK`
+    async fn update_duckdns(&self, ip: &str) -> Result<()> {
+        let subdomain = self
+            .hostname
+            .strip_suffix(DUCKDNS_SUFFIX)
+            .unwrap_or(&self.hostname);
+        let response = self
+            .client
+            .get(format!("{}/update", self.endpoint))
+            .query(&[
+                ("domains", subdomain),
+                ("token", self.token.as_str()),
+                ("ip", ip),
+            ])
+            .send()
+            .await?;
+        let body = response.text().await?;
+        if body.trim() != "OK" {
+            return Err(anyhow!("DuckDNS rejected the update: {}", body.trim()));
+        }
+        Ok(())
+    }
+
+    /// 启动后台任务：按间隔探测公网 IP 并在变化时更新
+    pub fn spawn(self: Arc<Self>) {
+        tokio::spawn(async move {
+            loop {
+                match fetch_public_ip_with_timeout(DETECT_TIMEOUT_SECS).await {
+                    Some(public_ip) => match self.update(&public_ip.ip).await {
+                        Ok(true) => info!("DDNS: {} -> {}", self.hostname, public_ip.ip),
+                        Ok(false) => debug!("DDNS: {} unchanged", self.hostname),
+                        Err(e) => warn!("DDNS update for {} failed: {}", self.hostname, e),
+                    },
+                    None => warn!("DDNS: public IP detection failed, will retry"),
+                }
+                tokio::time::sleep(self.interval).await;
+            }
+        });
+    }
+}
+
+/// 解析 Cloudflare 响应（检查 HTTP 状态与 `success` 字段）
+async fn cloudflare_result(response: reqwest::Response) -> Result<serde_json::Value> {
+    let status = response.status();
+    let body: serde_json::Value = serde_json::from_str(&response.text().await?)
+        .map_err(|e| anyhow!("Invalid Cloudflare response: {}", e))?;
+    if !status.is_success() || body["success"] != true {
+        let message = body["errors"][0]["message"]
+            .as_str()
+            .unwrap_or("unknown error");
+        return Err(anyhow!("Cloudflare API error ({}): {}", status, message));
+    }
+    Ok(body)
+}
@@ -0,0 +1,307 @@
+//! 伪装站点模块
+//!
+//! 决定未认证 HTTP 请求（扫描器、浏览器直接访问）看到的内容：
+//! 静态站点、重定向、固定状态码 / 直接断开，或反向代理到真实后端
+
+use crate::config::{DecoyConfig, DecoyMode, ProxyRoute};
+use crate::http::{extract_header_value, host_matches};
+use anyhow::{anyhow, Result};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tracing::debug;
+
+/// reject 模式默认状态码
+const DEFAULT_REJECT_STATUS: u16 = 400;
+
+/// 约定的“直接断开”状态码（同 nginx 444）
+const STATUS_CLOSE: u16 = 444;
+
+/// 连接后端超时
+const BACKEND_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 解析请求行，返回 (方法, 请求目标)
+pub fn request_line(data: &[u8]) -> Option<(&str, &str)> {
+    let end = data.iter().position(|&b| b == b'\r' || b == b'\n')?;
+    let line = std::str::from_utf8(&data[..end]).ok()?;
+    let mut parts = line.split_whitespace();
+    Some((parts.next()?, parts.next()?))
+}
+
+/// 常用状态码的原因短语
+pub fn reason_phrase(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        301 => "Moved Permanently",
+        302 => "Found",
+        400 => "Bad Request",
+        401 => "Unauthorized",
+        403 => "Forbidden",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        500 => "Internal Server Error",
+        502 => "Bad Gateway",
+        503 => "Service Unavailable",
+        _ => "Unknown",
+    }
+}
+
+/// 根据扩展名推断 Content-Type
+pub fn content_type_for(path: &Path) -> &'static str {
+    let ext = path
+        .extension()
+        .and_then(|e| e.to_str())
+        .map(|e| e.to_ascii_lowercase());
+    match ext.as_deref() {
+        Some("html") | Some("htm") => "text/html; charset=utf-8",
+        Some("css") => "text/css; charset=utf-8",
+        Some("js") | Some("mjs") => "text/javascript; charset=utf-8",
+        Some("json") => "application/json",
+        Some("txt") => "text/plain; charset=utf-8",
+        Some("xml") => "application/xml",
+        Some("svg") => "image/svg+xml",
+        Some("png") => "image/png",
+        Some("jpg") | Some("jpeg") => "image/jpeg",
+        Some("gif") => "image/gif",
+        Some("webp") => "image/webp",
+        Some("ico") => "image/x-icon",
+        Some("woff") => "font/woff",
+        Some("woff2") => "font/woff2",
+        Some("pdf") => "application/pdf",
+        _ => "application/octet-stream",
+    }
+}
+
+/// 构建普通站点风格的响应（不附加面板的安全头，避免暴露特征）
+fn build_response(status: u16, content_type: &str, body: &[u8], head_only: bool) -> Vec<u8> {
+    let mut response = format!(
+        "HTTP/1.1 {} {}\r\n\
+Content-Type: {}\r\n\
+Content-Length: {}\r\n\
+Connection: close\r\n\
+\r\n",
+        status,
+        reason_phrase(status),
+        content_type,
+        body.len()
+    )
+    .into_bytes();
+    if !head_only {
+        response.extend_from_slice(body);
+    }
+    response
+}
+
+/// 构建固定状态码响应
+pub fn build_status_response(status: u16) -> Vec<u8> {
+    let body = format!("{} {}\n", status, reason_phrase(status));
+    build_response(status, "text/plain; charset=utf-8", body.as_bytes(), false)
+}
+
+/// 构建重定向响应（保留原请求路径与查询参数）
+pub fn build_redirect_response(base_url: &str, target: &str) -> Vec<u8> {
+    let location = format!("{}{}", base_url.trim_end_matches('/'), target);
+    format!(
+        "HTTP/1.1 301 Moved Permanently\r\n\
+Location: {}\r\n\
+Content-Length: 0\r\n\
+Connection: close\r\n\
+\r\n",
+        location
+    )
+    .into_bytes()
+}
+
+/// 将请求路径映射到静态站点根目录下的文件
+///
+/// 拒绝路径遍历；目录请求映射到 index.html
+pub fn resolve_static_path(root: &Path, target: &str) -> Option<PathBuf> {
+    let path = target.split(['?', '#']).next().unwrap_or("/");
+    let decoded = urlencoding::decode(path).ok()?;
+    if decoded.contains('\\') || decoded.contains('\0') {
+        return None;
+    }
+
+    let mut resolved = root.to_path_buf();
+    for segment in decoded.split('/') {
+        match segment {
+            "" | "." => continue,
+            ".." => return None,
+            s => resolved.push(s),
+        }
+    }
+
+    if decoded.ends_with('/') || resolved.is_dir() {
+        resolved.push("index.html");
+    }
+    Some(resolved)
+}
+
+/// 路径是否落在前缀之下（/app 匹配 /app、/app/x、/app?x，不匹配 /apple）
+pub fn path_has_prefix(path: &str, prefix: &str) -> bool {
+    let prefix = prefix.trim_end_matches('/');
+    if prefix.is_empty() {
+        return true;
+    }
+    match path.strip_prefix(prefix) {
+        Some(rest) => rest.is_empty() || rest.starts_with(['/', '?']),
+        None => false,
+    }
+}
+
+/// 查找与请求匹配的反向代理路由（按配置顺序，先匹配先生效）
+pub fn match_route<'a>(routes: &'a [ProxyRoute], data: &[u8]) -> Option<&'a ProxyRoute> {
+    let (_, target) = request_line(data)?;
+    let host = extract_header_value(data, "Host");
+    routes.iter().find(|route| {
+        path_has_prefix(target, &route.path)
+            && route
+                .host
+                .as_deref()
+                .is_none_or(|expected| host.as_deref().is_some_and(|h| host_matches(h, expected)))
+    })
+}
+
+/// 去掉请求行中的路径前缀（/app/x → /x，/app → /）
+pub fn strip_request_prefix(data: &[u8], prefix: &str) -> Vec<u8> {
+    let Some((_, target)) = request_line(data) else {
+        return data.to_vec();
+    };
+    let prefix = prefix.trim_end_matches('/');
+    let rest = target.strip_prefix(prefix).unwrap_or(target);
+    let new_target = if rest.starts_with('/') {
+        rest.to_string()
+    } else {
+        format!("/{}", rest)
+    };
+
+    // target 借用自 data，可直接换算出其在原始数据中的偏移
+    let target_start = target.as_ptr() as usize - data.as_ptr() as usize;
+
+    let mut rewritten = Vec::with_capacity(data.len());
+    rewritten.extend_from_slice(&data[..target_start]);
+    rewritten.extend_from_slice(new_target.as_bytes());
+    rewritten.extend_from_slice(&data[target_start + target.len()..]);
+    rewritten
+}
+
+/// 把请求头中的 `Connection` 改为 `close`（同时去掉 `Keep-Alive`）
+///
+/// 路由只按连接上的首个请求选择，后端在首个响应后关闭连接，后续请求只能在新连接上
+/// 重新路由；WebSocket 等升级请求与请求头不完整时原样返回
+pub fn force_connection_close(data: &[u8]) -> Vec<u8> {
+    let Some(end) = data.windows(4).position(|w| w == b"\r\n\r\n") else {
+        return data.to_vec();
+    };
+    let head = &data[..end + 2];
+    if extract_header_value(head, "Upgrade").is_some() {
+        return data.to_vec();
+    }
+
+    let mut rewritten = Vec::with_capacity(data.len() + 19);
+    for (index, line) in head.split_inclusive(|&b| b == b'\n').enumerate() {
+        let name = line.split(|&b| b == b':').next().unwrap_or_default();
+        let name = name.trim_ascii();
+        if index > 0
+            && (name.eq_ignore_ascii_case(b"connection")
+                || name.eq_ignore_ascii_case(b"keep-alive"))
+        {
+            continue;
+        }
+        rewritten.extend_from_slice(line);
+    }
+    rewritten.extend_from_slice(b"Connection: close\r\n");
+    rewritten.extend_from_slice(&data[end + 2..]);
+    rewritten
+}
+
+/// 按路由转发请求
+pub async fn proxy_route(stream: TcpStream, data: &[u8], route: &ProxyRoute) -> Result<()> {
+    debug!("Reverse proxy route {} -> {}", route.path, route.backend);
+    let request = if route.strip_prefix {
+        strip_request_prefix(data, &route.path)
+    } else {
+        data.to_vec()
+    };
+    proxy_to_backend(stream, &force_connection_close(&request), &route.backend).await
+}
+
+/// 按配置响应未认证的 HTTP 请求
+///
+/// panel 模式由调用方处理，这里不会收到
+pub async fn serve(mut stream: TcpStream, data: &[u8], config: &DecoyConfig) -> Result<()> {
+    let (method, target) = request_line(data).unwrap_or(("GET", "/"));
+
+    match config.mode {
+        DecoyMode::Panel => Err(anyhow!("Panel mode is handled by the API module")),
+        DecoyMode::Static => {
+            let root = config.root.as_deref().unwrap_or(".");
+            let response = serve_static(Path::new(root), method, target).await;
+            stream.write_all(&response).await?;
+            Ok(())
+        }
+        DecoyMode::Redirect => {
+            let base = config.redirect_url.as_deref().unwrap_or("/");
+            stream
+                .write_all(&build_redirect_response(base, target))
+                .await?;
+            Ok(())
+        }
+        DecoyMode::Reject => {
+            let status = config.status.unwrap_or(DEFAULT_REJECT_STATUS);
+            if status != STATUS_CLOSE {
+                stream.write_all(&build_status_response(status)).await?;
+            }
+            Ok(())
+        }
+        DecoyMode::Proxy => {
+            let backend = config.backend.as_deref().unwrap_or_default();
+            proxy_to_backend(stream, data, backend).await
+        }
+    }
+}
+
+/// 读取静态文件并构建响应
+async fn serve_static(root: &Path, method: &str, target: &str) -> Vec<u8> {
+    let head_only = method == "HEAD";
+    if method != "GET" && !head_only {
+        return build_status_response(405);
+    }
+
+    if let Some(path) = resolve_static_path(root, target) {
+        if let Ok(body) = tokio::fs::read(&path).await {
+            return build_response(200, content_type_for(&path), &body, head_only);
+        }
+    }
+
+    // 站点自带 404 页面时优先使用
+    let not_found = root.join("404.html");
+    match tokio::fs::read(&not_found).await {
+        Ok(body) => build_response(404, content_type_for(&not_found), &body, head_only),
+        Err(_) => build_status_response(404),
+    }
+}
+
+/// 将连接原样转发到后端（含已读取的请求数据）
+///
+/// 按字节流双向复制，天然支持流式响应体与 WebSocket 升级
+pub async fn proxy_to_backend(mut stream: TcpStream, initial: &[u8], backend: &str) -> Result<()> {
+    let connect = tokio::time::timeout(BACKEND_CONNECT_TIMEOUT, TcpStream::connect(backend)).await;
+    let mut upstream = match connect {
+        Ok(Ok(s)) => s,
+        Ok(Err(e)) => {
+            let _ = stream.write_all(&build_status_response(502)).await;
+            return Err(anyhow!("Failed to connect to backend {}: {}", backend, e));
+        }
+        Err(_) => {
+            let _ = stream.write_all(&build_status_response(502)).await;
+            return Err(anyhow!("Timed out connecting to backend {}", backend));
+        }
+    };
+
+    upstream.write_all(initial).await?;
+    let result = tokio::io::copy_bidirectional(&mut stream, &mut upstream).await;
+    debug!("Reverse proxy to {} finished: {:?}", backend, result);
+    Ok(())
+}
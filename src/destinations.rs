@@ -0,0 +1,210 @@
+//! 目标地址健康统计模块
+//!
+//! 按目标记录最近若干次建连的耗时与失败情况，找出慢或失败率高的目标，
+//! 帮助区分“服务器问题”与“目标站点问题”
+
+use crate::config::MonitoringConfig;
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// 参与判定所需的最少样本数
+const MIN_SAMPLES: usize = 3;
+
+/// 平均建连耗时超过该值视为慢目标（毫秒）
+const SLOW_CONNECT_MS: u64 = 1000;
+
+/// 失败率超过该值视为异常目标
+const FAILURE_RATE_THRESHOLD: f64 = 0.2;
+
+/// 单次建连结果
+#[derive(Debug, Clone, Copy)]
+enum Sample {
+    /// 成功，附带建连耗时（毫秒，含 DNS 解析）
+    Success(u64),
+    /// 失败
+    Failure,
+}
+
+/// 单个目标的滚动统计
+#[derive(Debug)]
+struct DestinationStats {
+    samples: VecDeque<Sample>,
+    last_error: Option<String>,
+    last_seen: Instant,
+}
+
+impl DestinationStats {
+    fn new(window: usize) -> Self {
+        Self {
+            samples: VecDeque::with_capacity(window),
+            last_error: None,
+            last_seen: Instant::now(),
+        }
+    }
+
+    fn push(&mut self, sample: Sample, window: usize) {
+        if self.samples.len() >= window {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+        self.last_seen = Instant::now();
+    }
+
+    fn report(&self, dest: &str) -> DestinationReport {
+        let mut failures = 0;
+        let mut total_ms = 0;
+        let mut max_ms = 0;
+        for sample in &self.samples {
+            match sample {
+                Sample::Success(ms) => {
+                    total_ms += ms;
+                    max_ms = max_ms.max(*ms);
+                }
+                Sample::Failure => failures += 1,
+            }
+        }
+        let successes = self.samples.len() - failures;
+
+        DestinationReport {
+            dest: dest.to_string(),
+            samples: self.samples.len(),
+            failures,
+            failure_rate: failures as f64 / self.samples.len().max(1) as f64,
+            avg_connect_ms: (successes > 0).then(|| total_ms / successes as u64),
+            max_connect_ms: (successes > 0).then_some(max_ms),
+            last_error: self.last_error.clone(),
+            last_seen_secs: self.last_seen.elapsed().as_secs(),
+        }
+    }
+}
+
+/// 目标统计报告
+#[derive(Debug, Clone, Serialize)]
+pub struct DestinationReport {
+    /// 目标（host:port）
+    pub dest: String,
+    /// 窗口内样本数
+    pub samples: usize,
+    /// 窗口内失败次数
+    pub failures: usize,
+    /// 失败率（0~1）
+    pub failure_rate: f64,
+    /// 成功建连的平均耗时（毫秒）
+    pub avg_connect_ms: Option<u64>,
+    /// 成功建连的最大耗时（毫秒）
+    pub max_connect_ms: Option<u64>,
+    /// 最近一次失败原因
+    pub last_error: Option<String>,
+    /// 距最近一次访问的秒数
+    pub last_seen_secs: u64,
+}
+
+impl DestinationReport {
+    /// 是否为问题目标（慢或失败率高）
+    pub fn is_problem(&self) -> bool {
+        self.samples >= MIN_SAMPLES
+            && (self.failure_rate >= FAILURE_RATE_THRESHOLD
+                || self.avg_connect_ms.is_some_and(|ms| ms >= SLOW_CONNECT_MS))
+    }
+}
+
+/// 目标地址统计器（所有连接共享）
+#[derive(Debug)]
+pub struct DestinationTracker {
+    entries: Mutex<HashMap<String, DestinationStats>>,
+    /// 每个目标保留的最近样本数
+    window: usize,
+    /// 最多跟踪的目标数，超出时淘汰最久未访问的目标
+    max_destinations: usize,
+    /// 每 N 次成功建连记录一次
+    sample_every: u64,
+    successes: AtomicU64,
+}
+
+impl Default for DestinationTracker {
+    fn default() -> Self {
+        Self::with_config(&MonitoringConfig::default())
+    }
+}
+
+impl DestinationTracker {
+    /// 按监控配置设置样本窗口、目标数上限与采样间隔
+    pub fn with_config(config: &MonitoringConfig) -> Self {
+        Self {
+            entries: Mutex::new(HashMap::new()),
+            window: config.destination_window.max(1),
+            max_destinations: config.max_destinations.max(1),
+            sample_every: u64::from(config.destination_sample_every.max(1)),
+            successes: AtomicU64::new(0),
+        }
+    }
+
+    /// 记录一次成功建连（按采样间隔抽样）
+    pub fn record_success(&self, dest: &str, connect_time: Duration) {
+        let seq = self.successes.fetch_add(1, Ordering::Relaxed);
+        if !seq.is_multiple_of(self.sample_every) {
+            return;
+        }
+        let ms = connect_time.as_millis().min(u64::MAX as u128) as u64;
+        self.record(dest, Sample::Success(ms), None);
+    }
+
+    /// 记录一次建连失败
+    pub fn record_failure(&self, dest: &str, error: &str) {
+        self.record(dest, Sample::Failure, Some(error));
+    }
+
+    fn record(&self, dest: &str, sample: Sample, error: Option<&str>) {
+        let mut entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+
+        if !entries.contains_key(dest) && entries.len() >= self.max_destinations {
+            if let Some(oldest) = entries
+                .iter()
+                .min_by_key(|(_, stats)| stats.last_seen)
+                .map(|(key, _)| key.clone())
+            {
+                entries.remove(&oldest);
+            }
+        }
+
+        let stats = entries
+            .entry(dest.to_string())
+            .or_insert_with(|| DestinationStats::new(self.window));
+        stats.push(sample, self.window);
+        if let Some(error) = error {
+            stats.last_error = Some(error.to_string());
+        }
+    }
+
+    /// 当前跟踪的目标数
+    pub fn tracked_count(&self) -> usize {
+        self.entries.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    /// 全部目标的报告
+    pub fn reports(&self) -> Vec<DestinationReport> {
+        let entries = self.entries.lock().unwrap_or_else(|e| e.into_inner());
+        entries
+            .iter()
+            .map(|(dest, stats)| stats.report(dest))
+            .collect()
+    }
+
+    /// 问题目标：按失败率、平均耗时降序
+    pub fn problems(&self) -> Vec<DestinationReport> {
+        let mut problems: Vec<_> = self
+            .reports()
+            .into_iter()
+            .filter(DestinationReport::is_problem)
+            .collect();
+        problems.sort_by(|a, b| {
+            b.failure_rate
+                .total_cmp(&a.failure_rate)
+                .then(b.avg_connect_ms.cmp(&a.avg_connect_ms))
+        });
+        problems
+    }
+}
@@ -0,0 +1,1173 @@
+//! DNS 报文处理模块
+//!
+//! 对经 UDP 代理转发到 53 端口的 DNS 查询做本地处理：
+//! hosts 覆盖、域名拦截（返回 NXDOMAIN）、应答缓存以及按域名的查询计数；
+//! 以及服务端连接目标时使用的内置解析器（自定义上游、正负缓存、A/AAAA 并发查询），
+//! 上游可为明文 DNS、DNS over TLS 或 DNS over HTTPS
+
+use crate::blocklist::Blocklist;
+use crate::config::{DnsConfig, DomainStrategy, IpStrategy, ResolverConfig, ResolverUpstream};
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::collections::{HashMap, VecDeque};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpStream, UdpSocket};
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::rustls::ClientConfig;
+use tokio_rustls::TlsConnector;
+use tracing::debug;
+
+/// DNS 服务端口
+pub const DNS_PORT: u16 = 53;
+
+/// A 记录类型
+pub const TYPE_A: u16 = 1;
+/// AAAA 记录类型
+pub const TYPE_AAAA: u16 = 28;
+/// OPT 伪记录类型（EDNS0，TTL 字段不是生存时间）
+const TYPE_OPT: u16 = 41;
+/// IN 类
+const CLASS_IN: u16 = 1;
+
+/// DNS 报文头长度
+const HEADER_LEN: usize = 12;
+/// 响应码：NXDOMAIN
+const RCODE_NXDOMAIN: u8 = 3;
+/// 名称压缩指针最大跳转次数（防止恶意循环）
+const MAX_POINTER_JUMPS: usize = 16;
+/// 无记录应答（NXDOMAIN / NODATA）的缓存时间
+const NEGATIVE_TTL: u32 = 30;
+/// 缓存时间上限
+const MAX_CACHE_TTL: u32 = 3600;
+/// 按域名计数的默认最大条目数，超出后新域名不再单独计数
+const DEFAULT_STATS_ENTRIES: usize = 10_000;
+/// 单个 UDP 会话最多记录的未应答查询数，超出后丢弃最早的记录
+const MAX_PENDING_QUERIES: usize = 256;
+
+/// DNS 查询问题
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DnsQuestion {
+    /// 事务 ID
+    pub id: u16,
+    /// 查询域名（小写，不含结尾的点）
+    pub name: String,
+    /// 查询类型
+    pub qtype: u16,
+    /// 查询类
+    pub qclass: u16,
+    /// 问题段结束位置（报文偏移）
+    end: usize,
+}
+
+/// 解析域名，返回（域名, 名称之后的偏移）
+///
+/// 支持压缩指针；返回的偏移是原始位置上名称字段之后的位置
+fn parse_name(packet: &[u8], mut pos: usize) -> Option<(String, usize)> {
+    let mut labels: Vec<String> = Vec::new();
+    let mut end = None;
+    let mut jumps = 0;
+
+    loop {
+        let len = *packet.get(pos)? as usize;
+        if len == 0 {
+            if end.is_none() {
+                end = Some(pos + 1);
+            }
+            break;
+        }
+
+        if len & 0xC0 == 0xC0 {
+            let low = *packet.get(pos + 1)? as usize;
+            if end.is_none() {
+                end = Some(pos + 2);
+            }
+            jumps += 1;
+            if jumps > MAX_POINTER_JUMPS {
+                return None;
+            }
+            pos = ((len & 0x3F) << 8) | low;
+            continue;
+        }
+
+        if len & 0xC0 != 0 {
+            return None;
+        }
+
+        let label = packet.get(pos + 1..pos + 1 + len)?;
+        labels.push(String::from_utf8_lossy(label).to_ascii_lowercase());
+        pos += 1 + len;
+    }
+
+    Some((labels.join("."), end?))
+}
+
+/// 跳过资源记录名称，返回名称之后的偏移
+fn skip_name(packet: &[u8], pos: usize) -> Option<usize> {
+    parse_name(packet, pos).map(|(_, end)| end)
+}
+
+fn read_u16(packet: &[u8], pos: usize) -> Option<u16> {
+    let bytes = packet.get(pos..pos + 2)?;
+    Some(u16::from_be_bytes([bytes[0], bytes[1]]))
+}
+
+fn read_u32(packet: &[u8], pos: usize) -> Option<u32> {
+    let bytes = packet.get(pos..pos + 4)?;
+    Some(u32::from_be_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+}
+
+/// 解析 DNS 报文的第一个问题
+///
+/// 查询与应答都可以解析，调用者按需检查 QR 位
+pub fn parse_question(packet: &[u8]) -> Option<DnsQuestion> {
+    if packet.len() < HEADER_LEN {
+        return None;
+    }
+
+    let id = read_u16(packet, 0)?;
+    let qdcount = read_u16(packet, 4)?;
+    if qdcount == 0 {
+        return None;
+    }
+
+    let (name, pos) = parse_name(packet, HEADER_LEN)?;
+    let qtype = read_u16(packet, pos)?;
+    let qclass = read_u16(packet, pos + 2)?;
+
+    Some(DnsQuestion {
+        id,
+        name,
+        qtype,
+        qclass,
+        end: pos + 4,
+    })
+}
+
+/// 是否为查询报文（QR = 0）
+pub fn is_query(packet: &[u8]) -> bool {
+    packet.len() >= HEADER_LEN && packet[2] & 0x80 == 0
+}
+
+/// 读取应答中所有资源记录的 TTL（偏移, 值），跳过 OPT 伪记录
+fn record_ttls(packet: &[u8]) -> Option<Vec<(usize, u32)>> {
+    let qdcount = read_u16(packet, 4)? as usize;
+    let rrcount = read_u16(packet, 6)? as usize
+        + read_u16(packet, 8)? as usize
+        + read_u16(packet, 10)? as usize;
+
+    let mut pos = HEADER_LEN;
+    for _ in 0..qdcount {
+        pos = skip_name(packet, pos)? + 4;
+    }
+
+    let mut ttls = Vec::with_capacity(rrcount);
+    for _ in 0..rrcount {
+        pos = skip_name(packet, pos)?;
+        let rtype = read_u16(packet, pos)?;
+        let ttl = read_u32(packet, pos + 4)?;
+        let rdlength = read_u16(packet, pos + 8)? as usize;
+        if rtype != TYPE_OPT {
+            ttls.push((pos + 4, ttl));
+        }
+        pos += 10 + rdlength;
+        if pos > packet.len() {
+            return None;
+        }
+    }
+
+    Some(ttls)
+}
+
+/// 构建应答报文头 + 问题段
+fn response_header(query: &[u8], question: &DnsQuestion, rcode: u8, ancount: u16) -> Vec<u8> {
+    let mut out = Vec::with_capacity(question.end + 32);
+    out.extend_from_slice(&query[0..2]);
+    // QR=1，保留 Opcode 与 RD，RA=1
+    out.push(0x80 | (query[2] & 0x79));
+    out.push(0x80 | (rcode & 0x0F));
+    out.extend_from_slice(&1u16.to_be_bytes());
+    out.extend_from_slice(&ancount.to_be_bytes());
+    out.extend_from_slice(&0u16.to_be_bytes());
+    out.extend_from_slice(&0u16.to_be_bytes());
+    out.extend_from_slice(&query[HEADER_LEN..question.end]);
+    out
+}
+
+/// 构建 NXDOMAIN 应答
+pub fn build_nxdomain(query: &[u8], question: &DnsQuestion) -> Vec<u8> {
+    response_header(query, question, RCODE_NXDOMAIN, 0)
+}
+
+/// 构建 hosts 覆盖应答
+///
+/// 只返回与查询类型匹配的地址（A → IPv4，AAAA → IPv6），没有匹配时返回空应答（NODATA）
+pub fn build_address_answer(
+    query: &[u8],
+    question: &DnsQuestion,
+    addrs: &[IpAddr],
+    ttl: u32,
+) -> Vec<u8> {
+    let matching: Vec<&IpAddr> = addrs
+        .iter()
+        .filter(|ip| match question.qtype {
+            TYPE_A => ip.is_ipv4(),
+            TYPE_AAAA => ip.is_ipv6(),
+            _ => false,
+        })
+        .collect();
+
+    let mut out = response_header(query, question, 0, matching.len() as u16);
+    for ip in matching {
+        // 名称使用指向问题段的压缩指针
+        out.extend_from_slice(&[0xC0, HEADER_LEN as u8]);
+        out.extend_from_slice(&question.qtype.to_be_bytes());
+        out.extend_from_slice(&CLASS_IN.to_be_bytes());
+        out.extend_from_slice(&ttl.to_be_bytes());
+        match ip {
+            IpAddr::V4(v4) => {
+                out.extend_from_slice(&4u16.to_be_bytes());
+                out.extend_from_slice(&v4.octets());
+            }
+            IpAddr::V6(v6) => {
+                out.extend_from_slice(&16u16.to_be_bytes());
+                out.extend_from_slice(&v6.octets());
+            }
+        }
+    }
+    out
+}
+
+/// 检查域名是否命中后缀列表（完全相等或为其子域名）
+pub fn domain_matches_suffix(domain: &str, suffix: &str) -> bool {
+    let suffix = suffix.trim_end_matches('.');
+    domain == suffix
+        || (domain.len() > suffix.len()
+            && domain.ends_with(suffix)
+            && domain.as_bytes()[domain.len() - suffix.len() - 1] == b'.')
+}
+
+/// 缓存的应答
+#[derive(Debug)]
+struct CachedAnswer {
+    packet: Vec<u8>,
+    ttl_offsets: Vec<(usize, u32)>,
+    inserted: Instant,
+    expires: Instant,
+}
+
+/// 查询处理结果
+#[derive(Debug, PartialEq, Eq)]
+pub enum DnsAction {
+    /// 本地直接应答
+    Reply(Vec<u8>),
+    /// 转发给上游
+    Forward,
+}
+
+/// 缓存键：（上游服务器，域名，查询类型）
+type CacheKey = (String, String, u16);
+
+/// DNS 拦截器
+///
+/// 所有 UDP 会话共享同一个实例（缓存与计数跨会话生效）；缓存按上游服务器区分，
+/// 应答只经 [`DnsSession`] 校验后写入
+#[derive(Debug)]
+pub struct DnsInterceptor {
+    hosts: HashMap<String, Vec<IpAddr>>,
+    hosts_ttl: u32,
+    block: Vec<String>,
+    cache_size: usize,
+    cache: Mutex<HashMap<CacheKey, CachedAnswer>>,
+    stats: Mutex<HashMap<String, u64>>,
+    stats_limit: usize,
+}
+
+impl DnsInterceptor {
+    /// 根据配置创建拦截器
+    pub fn new(config: &DnsConfig) -> Self {
+        let hosts = config
+            .hosts
+            .iter()
+            .map(|(name, addrs)| {
+                (
+                    name.trim_end_matches('.').to_ascii_lowercase(),
+                    addrs.clone(),
+                )
+            })
+            .collect();
+        let block = config
+            .block
+            .iter()
+            .map(|d| d.trim_end_matches('.').to_ascii_lowercase())
+            .collect();
+
+        Self {
+            hosts,
+            hosts_ttl: config.hosts_ttl,
+            block,
+            cache_size: if config.cache { config.cache_size } else { 0 },
+            cache: Mutex::new(HashMap::new()),
+            stats: Mutex::new(HashMap::new()),
+            stats_limit: DEFAULT_STATS_ENTRIES,
+        }
+    }
+
+    /// 设置查询计数最多记录的域名数（0 表示不计数）
+    pub fn with_stats_limit(mut self, limit: usize) -> Self {
+        self.stats_limit = limit;
+        self
+    }
+
+    /// 域名是否在拦截列表中
+    pub fn is_blocked(&self, domain: &str) -> bool {
+        self.block.iter().any(|b| domain_matches_suffix(domain, b))
+    }
+
+    /// 处理客户端发往 `upstream` 的 DNS 查询
+    ///
+    /// `blocklist` 为订阅拦截列表（用户退出拦截时传 None）。
+    /// 非法或无法识别的报文一律转发，保持透明
+    pub fn handle_query(
+        &self,
+        packet: &[u8],
+        upstream: &str,
+        blocklist: Option<&Blocklist>,
+    ) -> DnsAction {
+        if !is_query(packet) {
+            return DnsAction::Forward;
+        }
+        let question = match parse_question(packet) {
+            Some(q) => q,
+            None => return DnsAction::Forward,
+        };
+
+        self.record_query(&question.name);
+
+        if self.is_blocked(&question.name) {
+            debug!("DNS query blocked: {}", question.name);
+            return DnsAction::Reply(build_nxdomain(packet, &question));
+        }
+
+        if blocklist.is_some_and(|b| b.check(&question.name)) {
+            debug!("DNS query blocked by blocklist: {}", question.name);
+            return DnsAction::Reply(build_nxdomain(packet, &question));
+        }
+
+        if let Some(addrs) = self.hosts.get(&question.name) {
+            debug!("DNS query answered from hosts: {}", question.name);
+            return DnsAction::Reply(build_address_answer(
+                packet,
+                &question,
+                addrs,
+                self.hosts_ttl,
+            ));
+        }
+
+        match self.cached_answer(upstream, &question) {
+            Some(reply) => {
+                debug!("DNS cache hit: {} (type {})", question.name, question.qtype);
+                DnsAction::Reply(reply)
+            }
+            None => DnsAction::Forward,
+        }
+    }
+
+    /// 记录 `upstream` 返回的应答到缓存（调用方已确认应答对应本会话转发的查询）
+    fn store_response(&self, upstream: &str, packet: &[u8]) {
+        if self.cache_size == 0 || packet.len() < HEADER_LEN || is_query(packet) {
+            return;
+        }
+        // 截断应答不缓存
+        if packet[2] & 0x02 != 0 {
+            return;
+        }
+        let rcode = packet[3] & 0x0F;
+        if rcode != 0 && rcode != RCODE_NXDOMAIN {
+            return;
+        }
+
+        let question = match parse_question(packet) {
+            Some(q) => q,
+            None => return,
+        };
+        let ttl_offsets = match record_ttls(packet) {
+            Some(t) => t,
+            None => return,
+        };
+
+        let ttl = ttl_offsets
+            .iter()
+            .map(|(_, ttl)| *ttl)
+            .min()
+            .unwrap_or(NEGATIVE_TTL)
+            .min(MAX_CACHE_TTL);
+        if ttl == 0 {
+            return;
+        }
+
+        let now = Instant::now();
+        let entry = CachedAnswer {
+            packet: packet.to_vec(),
+            ttl_offsets,
+            inserted: now,
+            expires: now + Duration::from_secs(ttl as u64),
+        };
+
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if cache.len() >= self.cache_size {
+            cache.retain(|_, v| v.expires > now);
+            if cache.len() >= self.cache_size {
+                if let Some(key) = cache.keys().next().cloned() {
+                    cache.remove(&key);
+                }
+            }
+        }
+        cache.insert((upstream.to_string(), question.name, question.qtype), entry);
+    }
+
+    /// 从缓存中取出应答，重写事务 ID 并扣减已经过的 TTL
+    fn cached_answer(&self, upstream: &str, question: &DnsQuestion) -> Option<Vec<u8>> {
+        if self.cache_size == 0 {
+            return None;
+        }
+
+        let now = Instant::now();
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        let key = (upstream.to_string(), question.name.clone(), question.qtype);
+        let entry = cache.get(&key)?;
+
+        if entry.expires <= now {
+            cache.remove(&key);
+            return None;
+        }
+
+        let elapsed = now.duration_since(entry.inserted).as_secs() as u32;
+        let mut reply = entry.packet.clone();
+        reply[0..2].copy_from_slice(&question.id.to_be_bytes());
+        for (offset, ttl) in &entry.ttl_offsets {
+            let remaining = ttl.saturating_sub(elapsed);
+            reply[*offset..*offset + 4].copy_from_slice(&remaining.to_be_bytes());
+        }
+        Some(reply)
+    }
+
+    fn record_query(&self, name: &str) {
+        let mut stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        if let Some(count) = stats.get_mut(name) {
+            *count += 1;
+        } else if stats.len() < self.stats_limit {
+            stats.insert(name.to_string(), 1);
+        }
+    }
+
+    /// 查询次数最多的域名（降序）
+    pub fn top_queries(&self, limit: usize) -> Vec<(String, u64)> {
+        let stats = self.stats.lock().unwrap_or_else(|e| e.into_inner());
+        let mut entries: Vec<(String, u64)> = stats.iter().map(|(k, v)| (k.clone(), *v)).collect();
+        entries.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        entries.truncate(limit);
+        entries
+    }
+
+    /// 当前缓存条目数
+    pub fn cache_len(&self) -> usize {
+        self.cache.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+}
+
+/// 单个 UDP 会话的 DNS 处理
+///
+/// 记录本会话已转发、尚未应答的查询（事务 ID、域名、类型）；只有来自会话目标、
+/// 且与未应答查询匹配的应答才写入共享缓存，防止伪造应答污染其他用户的缓存
+#[derive(Debug)]
+pub struct DnsSession {
+    interceptor: Arc<DnsInterceptor>,
+    upstream: String,
+    pending: Mutex<VecDeque<(u16, String, u16)>>,
+}
+
+impl DnsSession {
+    /// `upstream` 为会话目标（如 `8.8.8.8:53`），作为缓存键的一部分
+    pub fn new(interceptor: Arc<DnsInterceptor>, upstream: impl Into<String>) -> Self {
+        Self {
+            interceptor,
+            upstream: upstream.into(),
+            pending: Mutex::default(),
+        }
+    }
+
+    /// 处理客户端发出的查询；需要转发时记录为未应答查询
+    pub fn handle_query(&self, packet: &[u8], blocklist: Option<&Blocklist>) -> DnsAction {
+        let action = self
+            .interceptor
+            .handle_query(packet, &self.upstream, blocklist);
+        if action == DnsAction::Forward && is_query(packet) {
+            if let Some(question) = parse_question(packet) {
+                let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+                if pending.len() >= MAX_PENDING_QUERIES {
+                    pending.pop_front();
+                }
+                pending.push_back((question.id, question.name, question.qtype));
+            }
+        }
+        action
+    }
+
+    /// 处理会话收到的数据报：`from_target` 为来源是否为会话目标
+    ///
+    /// 与未应答查询匹配的应答写入缓存，其他数据报只转发不缓存
+    pub fn handle_response(&self, packet: &[u8], from_target: bool) {
+        if !from_target || packet.len() < HEADER_LEN || is_query(packet) {
+            return;
+        }
+        let Some(question) = parse_question(packet) else {
+            return;
+        };
+        {
+            let mut pending = self.pending.lock().unwrap_or_else(|e| e.into_inner());
+            let Some(index) = pending.iter().position(|(id, name, qtype)| {
+                *id == question.id && *name == question.name && *qtype == question.qtype
+            }) else {
+                debug!(
+                    "Ignoring unsolicited DNS response for {} from {}",
+                    question.name, self.upstream
+                );
+                return;
+            };
+            pending.remove(index);
+        }
+        self.interceptor.store_response(&self.upstream, packet);
+    }
+}
+
+/// 构建递归查询报文（RD=1，单个问题）
+fn build_query(id: u16, name: &str, qtype: u16) -> Vec<u8> {
+    let mut out = Vec::with_capacity(HEADER_LEN + name.len() + 6);
+    out.extend_from_slice(&id.to_be_bytes());
+    out.extend_from_slice(&[0x01, 0x00]);
+    out.extend_from_slice(&1u16.to_be_bytes());
+    out.extend_from_slice(&[0; 6]);
+    for label in name.split('.').filter(|l| !l.is_empty()) {
+        out.push(label.len() as u8);
+        out.extend_from_slice(label.as_bytes());
+    }
+    out.push(0);
+    out.extend_from_slice(&qtype.to_be_bytes());
+    out.extend_from_slice(&CLASS_IN.to_be_bytes());
+    out
+}
+
+/// 读取应答中与查询类型匹配的地址记录，返回（地址列表，最小 TTL）
+///
+/// NXDOMAIN 与无记录应答返回空列表；其他错误响应码返回 None
+fn answer_addresses(packet: &[u8], qtype: u16) -> Option<(Vec<IpAddr>, Option<u32>)> {
+    let rcode = packet.get(3)? & 0x0F;
+    if rcode == RCODE_NXDOMAIN {
+        return Some((Vec::new(), None));
+    }
+    if rcode != 0 {
+        return None;
+    }
+
+    let qdcount = read_u16(packet, 4)? as usize;
+    let ancount = read_u16(packet, 6)? as usize;
+    let mut pos = HEADER_LEN;
+    for _ in 0..qdcount {
+        pos = skip_name(packet, pos)? + 4;
+    }
+
+    let mut addrs = Vec::new();
+    let mut min_ttl: Option<u32> = None;
+    for _ in 0..ancount {
+        pos = skip_name(packet, pos)?;
+        let rtype = read_u16(packet, pos)?;
+        let ttl = read_u32(packet, pos + 4)?;
+        let rdlength = read_u16(packet, pos + 8)? as usize;
+        let rdata = packet.get(pos + 10..pos + 10 + rdlength)?;
+        // CNAME 等其他记录只参与 TTL 计算
+        min_ttl = Some(min_ttl.map_or(ttl, |t| t.min(ttl)));
+        match (rtype, rdlength) {
+            (TYPE_A, 4) if qtype == TYPE_A => {
+                addrs.push(IpAddr::V4(Ipv4Addr::new(
+                    rdata[0], rdata[1], rdata[2], rdata[3],
+                )));
+            }
+            (TYPE_AAAA, 16) if qtype == TYPE_AAAA => {
+                let mut octets = [0u8; 16];
+                octets.copy_from_slice(rdata);
+                addrs.push(IpAddr::V6(Ipv6Addr::from(octets)));
+            }
+            _ => {}
+        }
+        pos += 10 + rdlength;
+    }
+    Some((addrs, min_ttl))
+}
+
+/// 按域名解析方式从地址列表中选择目标：`AsIs` 与 `UseIP` 取第一个地址，
+/// `UseIPv4` / `UseIPv6` 只取对应族的地址
+pub fn select_address(
+    domain: &str,
+    addrs: &[IpAddr],
+    port: u16,
+    strategy: DomainStrategy,
+) -> Result<SocketAddr> {
+    let ip = match strategy {
+        DomainStrategy::AsIs | DomainStrategy::UseIp => addrs
+            .first()
+            .ok_or_else(|| anyhow!("Failed to resolve address: {}", domain))?,
+        DomainStrategy::UseIpv4 => addrs
+            .iter()
+            .find(|ip| ip.is_ipv4())
+            .ok_or_else(|| anyhow!("No IPv4 address for {}", domain))?,
+        DomainStrategy::UseIpv6 => addrs
+            .iter()
+            .find(|ip| ip.is_ipv6())
+            .ok_or_else(|| anyhow!("No IPv6 address for {}", domain))?,
+    };
+    Ok(SocketAddr::new(*ip, port))
+}
+
+/// 按地址族策略排序并过滤地址：`prefer_*` 时优先的地址族在前、两族交替（RFC 8305 第 4 节），
+/// `*_only` 时只保留该地址族；同族地址保持原有顺序
+pub fn order_addresses(addrs: &[IpAddr], strategy: IpStrategy) -> Vec<IpAddr> {
+    let (v4, v6): (Vec<IpAddr>, Vec<IpAddr>) = addrs.iter().partition(|ip| ip.is_ipv4());
+    let (first, second) = match strategy {
+        IpStrategy::PreferIpv4 => (v4, v6),
+        IpStrategy::PreferIpv6 => (v6, v4),
+        IpStrategy::Ipv4Only => return v4,
+        IpStrategy::Ipv6Only => return v6,
+    };
+    let mut ordered = Vec::with_capacity(addrs.len());
+    let mut second = second.into_iter();
+    for ip in first {
+        ordered.push(ip);
+        ordered.extend(second.next());
+    }
+    ordered.extend(second);
+    ordered
+}
+
+/// 缓存的解析结果（地址为空表示无地址）
+#[derive(Debug)]
+struct CachedLookup {
+    addrs: Arc<[IpAddr]>,
+    expires: Instant,
+}
+
+/// 解析器缓存统计
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct ResolverStats {
+    /// 当前缓存条目数
+    pub entries: usize,
+    /// 命中缓存的查询数（含无地址结果）
+    pub hits: u64,
+    /// 命中无地址缓存的查询数
+    pub negative_hits: u64,
+    /// 未命中缓存、向上游或系统查询的次数
+    pub misses: u64,
+    /// 查询失败次数（超时、网络错误、上游错误响应，不缓存）
+    pub errors: u64,
+}
+
+/// 目标域名解析器
+///
+/// 所有会话共享同一个实例；未配置上游时使用系统解析并按 `system_ttl` 缓存
+#[derive(Debug)]
+pub struct Resolver {
+    servers: Vec<ResolverUpstream>,
+    /// 配置了 DoT / DoH 上游时创建
+    secure: Option<SecureUpstreams>,
+    ip_strategy: IpStrategy,
+    happy_eyeballs_delay: Duration,
+    timeout: Duration,
+    cache_size: usize,
+    max_ttl: u32,
+    negative_ttl: u32,
+    system_ttl: u32,
+    cache: Mutex<HashMap<String, CachedLookup>>,
+    hits: AtomicU64,
+    negative_hits: AtomicU64,
+    misses: AtomicU64,
+    errors: AtomicU64,
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new(&ResolverConfig::default()).expect("default resolver config is valid")
+    }
+}
+
+impl Resolver {
+    /// 根据配置创建解析器
+    pub fn new(config: &ResolverConfig) -> Result<Self> {
+        let servers = config.upstreams()?;
+        let secure = if servers
+            .iter()
+            .any(|server| !matches!(server, ResolverUpstream::Udp(_)))
+        {
+            Some(SecureUpstreams::new(config.ca_file.as_deref())?)
+        } else {
+            None
+        };
+        Ok(Self {
+            servers,
+            secure,
+            ip_strategy: config.ip_strategy,
+            happy_eyeballs_delay: Duration::from_millis(config.happy_eyeballs_delay_ms),
+            timeout: Duration::from_secs(config.timeout),
+            cache_size: config.cache_size,
+            max_ttl: config.max_ttl,
+            negative_ttl: config.negative_ttl,
+            system_ttl: config.system_ttl,
+            cache: Mutex::new(HashMap::new()),
+            hits: AtomicU64::new(0),
+            negative_hits: AtomicU64::new(0),
+            misses: AtomicU64::new(0),
+            errors: AtomicU64::new(0),
+        })
+    }
+
+    /// 解析域名的全部地址（IPv4 在前）；IP 字面量直接返回
+    pub async fn lookup(&self, domain: &str) -> Result<Arc<[IpAddr]>> {
+        if let Ok(ip) = domain
+            .trim_matches(|c| c == '[' || c == ']')
+            .parse::<IpAddr>()
+        {
+            return Ok(Arc::from([ip]));
+        }
+        let name = domain.trim_end_matches('.').to_ascii_lowercase();
+
+        if let Some(addrs) = self.cached(&name) {
+            self.hits.fetch_add(1, Ordering::Relaxed);
+            if addrs.is_empty() {
+                self.negative_hits.fetch_add(1, Ordering::Relaxed);
+                return Err(anyhow!("Failed to resolve address: {}", domain));
+            }
+            return Ok(addrs);
+        }
+        self.misses.fetch_add(1, Ordering::Relaxed);
+
+        let result = if self.servers.is_empty() {
+            lookup_system(&name).await.map(|addrs| {
+                let ttl = if addrs.is_empty() {
+                    self.negative_ttl
+                } else {
+                    self.system_ttl
+                };
+                (addrs, ttl)
+            })
+        } else {
+            self.lookup_upstream(&name).await
+        };
+        let (addrs, ttl) = match result {
+            Ok(found) => found,
+            Err(e) => {
+                self.errors.fetch_add(1, Ordering::Relaxed);
+                return Err(e);
+            }
+        };
+
+        let addrs: Arc<[IpAddr]> = Arc::from(addrs);
+        self.store(name, Arc::clone(&addrs), ttl.min(self.max_ttl));
+        if addrs.is_empty() {
+            return Err(anyhow!("Failed to resolve address: {}", domain));
+        }
+        Ok(addrs)
+    }
+
+    /// 按域名解析方式解析域名目标（`AsIs` / `UseIP` 取地址族策略排序后的第一个地址）
+    pub async fn resolve(
+        &self,
+        domain: &str,
+        port: u16,
+        strategy: DomainStrategy,
+    ) -> Result<SocketAddr> {
+        let addrs = self.ordered(domain).await?;
+        select_address(domain, &addrs, port, strategy)
+    }
+
+    /// 解析协议层地址：IP 目标直接转换，域名目标按 `AsIs` 取第一个地址
+    pub async fn resolve_address(
+        &self,
+        address: &crate::protocol::Address,
+        port: u16,
+    ) -> Result<SocketAddr> {
+        match address {
+            crate::protocol::Address::Domain(domain) => {
+                let domain =
+                    std::str::from_utf8(domain).map_err(|_| anyhow!("Invalid domain encoding"))?;
+                self.resolve(domain, port, DomainStrategy::AsIs).await
+            }
+            _ => address.to_socket_addr(port),
+        }
+    }
+
+    /// 解析协议层地址的全部候选地址（按地址族策略排序），供 Happy Eyeballs 建连依次尝试
+    pub async fn resolve_candidates(
+        &self,
+        address: &crate::protocol::Address,
+        port: u16,
+    ) -> Result<Vec<SocketAddr>> {
+        match address {
+            crate::protocol::Address::Domain(domain) => {
+                let domain =
+                    std::str::from_utf8(domain).map_err(|_| anyhow!("Invalid domain encoding"))?;
+                let addrs = self.ordered(domain).await?;
+                Ok(addrs
+                    .into_iter()
+                    .map(|ip| SocketAddr::new(ip, port))
+                    .collect())
+            }
+            _ => Ok(vec![address.to_socket_addr(port)?]),
+        }
+    }
+
+    /// Happy Eyeballs 建连间隔（0 表示依次尝试）
+    pub fn happy_eyeballs_delay(&self) -> Duration {
+        self.happy_eyeballs_delay
+    }
+
+    /// 解析并按地址族策略排序；IP 字面量不受策略限制
+    async fn ordered(&self, domain: &str) -> Result<Vec<IpAddr>> {
+        let addrs = self.lookup(domain).await?;
+        if domain
+            .trim_matches(|c| c == '[' || c == ']')
+            .parse::<IpAddr>()
+            .is_ok()
+        {
+            return Ok(addrs.to_vec());
+        }
+        let ordered = order_addresses(&addrs, self.ip_strategy);
+        if ordered.is_empty() {
+            let family = match self.ip_strategy {
+                IpStrategy::Ipv6Only => "IPv6",
+                _ => "IPv4",
+            };
+            return Err(anyhow!(
+                "No {} address for {} (resolver.ip_strategy)",
+                family,
+                domain
+            ));
+        }
+        Ok(ordered)
+    }
+
+    /// 缓存统计
+    pub fn stats(&self) -> ResolverStats {
+        ResolverStats {
+            entries: self.cache.lock().unwrap_or_else(|e| e.into_inner()).len(),
+            hits: self.hits.load(Ordering::Relaxed),
+            negative_hits: self.negative_hits.load(Ordering::Relaxed),
+            misses: self.misses.load(Ordering::Relaxed),
+            errors: self.errors.load(Ordering::Relaxed),
+        }
+    }
+
+    fn cached(&self, name: &str) -> Option<Arc<[IpAddr]>> {
+        if self.cache_size == 0 {
+            return None;
+        }
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        let entry = cache.get(name)?;
+        if entry.expires <= Instant::now() {
+            cache.remove(name);
+            return None;
+        }
+        Some(Arc::clone(&entry.addrs))
+    }
+
+    fn store(&self, name: String, addrs: Arc<[IpAddr]>, ttl: u32) {
+        if self.cache_size == 0 || ttl == 0 {
+            return;
+        }
+        let now = Instant::now();
+        let mut cache = self.cache.lock().unwrap_or_else(|e| e.into_inner());
+        if cache.len() >= self.cache_size && !cache.contains_key(&name) {
+            cache.retain(|_, v| v.expires > now);
+            if cache.len() >= self.cache_size {
+                if let Some(key) = cache.keys().next().cloned() {
+                    cache.remove(&key);
+                }
+            }
+        }
+        cache.insert(
+            name,
+            CachedLookup {
+                addrs,
+                expires: now + Duration::from_secs(ttl as u64),
+            },
+        );
+    }
+
+    /// 向上游并发查询 A 与 AAAA，返回（地址，缓存时间）；两种查询都失败时返回错误
+    async fn lookup_upstream(&self, name: &str) -> Result<(Vec<IpAddr>, u32)> {
+        let (v4, v6) = tokio::join!(self.query(name, TYPE_A), self.query(name, TYPE_AAAA));
+        let (v4, v6) = match (v4, v6) {
+            (Err(e), Err(_)) => return Err(e),
+            (v4, v6) => (v4.unwrap_or_default(), v6.unwrap_or_default()),
+        };
+        let ttl = [v4.1, v6.1]
+            .into_iter()
+            .flatten()
+            .min()
+            .unwrap_or(self.negative_ttl);
+        let mut addrs = v4.0;
+        addrs.extend(v6.0);
+        let ttl = if addrs.is_empty() {
+            self.negative_ttl
+        } else {
+            ttl
+        };
+        Ok((addrs, ttl))
+    }
+
+    /// 按顺序向上游服务器发送单个查询，返回第一个有效应答中的地址
+    async fn query(&self, name: &str, qtype: u16) -> Result<(Vec<IpAddr>, Option<u32>)> {
+        let mut last_error = anyhow!("No resolver servers configured");
+        for server in &self.servers {
+            let answer = async {
+                match (server, &self.secure) {
+                    (ResolverUpstream::Udp(addr), _) => query_server(*addr, name, qtype).await,
+                    (ResolverUpstream::Tls { host, port }, Some(secure)) => {
+                        secure.query_tls(host, *port, name, qtype).await
+                    }
+                    (ResolverUpstream::Https(url), Some(secure)) => {
+                        secure.query_https(url, name, qtype).await
+                    }
+                    _ => Err(anyhow!("Encrypted DNS transport is not initialized")),
+                }
+            };
+            match tokio::time::timeout(self.timeout, answer).await {
+                Ok(Ok(answer)) => return Ok(answer),
+                Ok(Err(e)) => last_error = e,
+                Err(_) => last_error = anyhow!("DNS query to {} timed out", server),
+            }
+            debug!(
+                "DNS query for {} (type {}) via {} failed: {}",
+                name, qtype, server, last_error
+            );
+        }
+        Err(last_error)
+    }
+}
+
+/// 向单个上游服务器查询，忽略事务 ID 或问题不匹配的报文
+async fn query_server(
+    server: SocketAddr,
+    name: &str,
+    qtype: u16,
+) -> Result<(Vec<IpAddr>, Option<u32>)> {
+    let bind: SocketAddr = match server {
+        SocketAddr::V4(_) => (Ipv4Addr::UNSPECIFIED, 0).into(),
+        SocketAddr::V6(_) => (Ipv6Addr::UNSPECIFIED, 0).into(),
+    };
+    let socket = UdpSocket::bind(bind).await?;
+    socket.connect(server).await?;
+    let id = uuid::Uuid::new_v4().as_u128() as u16;
+    socket.send(&build_query(id, name, qtype)).await?;
+
+    let mut buf = [0u8; 1500];
+    loop {
+        let n = socket.recv(&mut buf).await?;
+        let packet = &buf[..n];
+        if is_answer_to(packet, id, name, qtype) {
+            return upstream_answer(packet, qtype, &server, name);
+        }
+    }
+}
+
+/// 报文是否为对应查询（事务 ID 与问题一致）的应答
+fn is_answer_to(packet: &[u8], id: u16, name: &str, qtype: u16) -> bool {
+    !is_query(packet)
+        && parse_question(packet).is_some_and(|q| q.id == id && q.qtype == qtype && q.name == name)
+}
+
+/// 提取上游应答中的地址，错误响应码转换为错误
+fn upstream_answer(
+    packet: &[u8],
+    qtype: u16,
+    server: &dyn std::fmt::Display,
+    name: &str,
+) -> Result<(Vec<IpAddr>, Option<u32>)> {
+    answer_addresses(packet, qtype).ok_or_else(|| {
+        anyhow!(
+            "DNS server {} returned error code {} for {}",
+            server,
+            packet[3] & 0x0F,
+            name
+        )
+    })
+}
+
+/// DNS over TLS 连接
+type DotStream = TlsStream<TcpStream>;
+
+/// DNS over TLS 每个上游保留的空闲连接数上限
+const DOT_IDLE_CONNECTIONS: usize = 4;
+
+/// DNS over TLS / HTTPS 上游的连接
+///
+/// DoT 查询复用空闲连接（RFC 7858 长度前缀格式），复用的连接失败时改用新连接重试一次；
+/// DoH 使用 POST `application/dns-message`（RFC 8484），连接由 HTTP 客户端复用
+struct SecureUpstreams {
+    tls: TlsConnector,
+    https: reqwest::Client,
+    idle: Mutex<HashMap<(String, u16), Vec<DotStream>>>,
+}
+
+impl std::fmt::Debug for SecureUpstreams {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("SecureUpstreams").finish_non_exhaustive()
+    }
+}
+
+impl SecureUpstreams {
+    /// 在内置根证书之外信任 `ca_file` 中的证书
+    fn new(ca_file: Option<&str>) -> Result<Self> {
+        let roots = crate::outbound_tls::root_store(ca_file, "resolver.ca_file")?;
+        let config = ClientConfig::builder_with_provider(Arc::new(ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+
+        let mut https = reqwest::Client::builder();
+        if let Some(path) = ca_file {
+            let pem = std::fs::read(path)
+                .map_err(|e| anyhow!("Cannot read resolver.ca_file {}: {}", path, e))?;
+            for cert in reqwest::Certificate::from_pem_bundle(&pem)
+                .map_err(|e| anyhow!("Invalid certificate in {}: {}", path, e))?
+            {
+                https = https.add_root_certificate(cert);
+            }
+        }
+        Ok(Self {
+            tls: TlsConnector::from(Arc::new(config)),
+            https: https.build()?,
+            idle: Mutex::new(HashMap::new()),
+        })
+    }
+
+    /// DNS over TLS 查询
+    async fn query_tls(
+        &self,
+        host: &str,
+        port: u16,
+        name: &str,
+        qtype: u16,
+    ) -> Result<(Vec<IpAddr>, Option<u32>)> {
+        let key = (host.to_string(), port);
+        let id = uuid::Uuid::new_v4().as_u128() as u16;
+        let query = build_query(id, name, qtype);
+        let server = format!("tls://{}:{}", host, port);
+
+        let idle = self
+            .idle
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get_mut(&key)
+            .and_then(Vec::pop);
+        let reused = match idle {
+            Some(mut stream) => match exchange_tcp(&mut stream, &query, id).await {
+                Ok(packet) => Some((stream, packet)),
+                Err(e) => {
+                    debug!("Idle DNS over TLS connection to {} failed: {}", server, e);
+                    None
+                }
+            },
+            None => None,
+        };
+        let (stream, packet) = match reused {
+            Some(exchanged) => exchanged,
+            None => {
+                let tcp = TcpStream::connect((host, port)).await?;
+                let server_name = ServerName::try_from(host.to_string())
+                    .map_err(|_| anyhow!("Invalid DNS over TLS server name: {}", host))?;
+                let mut stream = self.tls.connect(server_name, tcp).await?;
+                let packet = exchange_tcp(&mut stream, &query, id).await?;
+                (stream, packet)
+            }
+        };
+
+        let mut idle = self.idle.lock().unwrap_or_else(|e| e.into_inner());
+        let streams = idle.entry(key).or_default();
+        if streams.len() < DOT_IDLE_CONNECTIONS {
+            streams.push(stream);
+        }
+        drop(idle);
+
+        if !is_answer_to(&packet, id, name, qtype) {
+            return Err(anyhow!("Unexpected DNS response from {}", server));
+        }
+        upstream_answer(&packet, qtype, &server, name)
+    }
+
+    /// DNS over HTTPS 查询（事务 ID 固定为 0，便于 HTTP 缓存）
+    async fn query_https(
+        &self,
+        url: &str,
+        name: &str,
+        qtype: u16,
+    ) -> Result<(Vec<IpAddr>, Option<u32>)> {
+        let response = self
+            .https
+            .post(url)
+            .header(reqwest::header::CONTENT_TYPE, "application/dns-message")
+            .header(reqwest::header::ACCEPT, "application/dns-message")
+            .body(build_query(0, name, qtype))
+            .send()
+            .await?;
+        if !response.status().is_success() {
+            return Err(anyhow!(
+                "DNS server {} returned HTTP {}",
+                url,
+                response.status()
+            ));
+        }
+        let packet = response.bytes().await?;
+        if !is_answer_to(&packet, 0, name, qtype) {
+            return Err(anyhow!("Unexpected DNS response from {}", url));
+        }
+        upstream_answer(&packet, qtype, &url, name)
+    }
+}
+
+/// 在 TCP 格式（2 字节长度前缀）的连接上发送查询并读取事务 ID 一致的应答
+async fn exchange_tcp(stream: &mut DotStream, query: &[u8], id: u16) -> std::io::Result<Vec<u8>> {
+    let mut framed = Vec::with_capacity(query.len() + 2);
+    framed.extend_from_slice(&(query.len() as u16).to_be_bytes());
+    framed.extend_from_slice(query);
+    stream.write_all(&framed).await?;
+    stream.flush().await?;
+    loop {
+        let len = stream.read_u16().await? as usize;
+        let mut packet = vec![0u8; len];
+        stream.read_exact(&mut packet).await?;
+        if read_u16(&packet, 0) == Some(id) {
+            return Ok(packet);
+        }
+    }
+}
+
+/// 系统解析（`getaddrinfo`），IPv4 地址排在前面
+///
+/// 系统解析无法区分域名不存在与临时故障，失败一律作为错误返回、不缓存
+async fn lookup_system(name: &str) -> Result<Vec<IpAddr>> {
+    let addrs = tokio::net::lookup_host((name, 0))
+        .await
+        .map_err(|e| anyhow!("Failed to resolve address: {}: {}", name, e))?;
+    let mut ips: Vec<IpAddr> = Vec::new();
+    for addr in addrs {
+        if !ips.contains(&addr.ip()) {
+            ips.push(addr.ip());
+        }
+    }
+    ips.sort_by_key(|ip| ip.is_ipv6());
+    Ok(ips)
+}
@@ -0,0 +1,434 @@
+//! 自检诊断模块
+//!
+//! `vless doctor` 子命令：检查端口可用性、系统限制、时钟偏差与公网 IP 探测，
+//! 并针对每一项失败给出可执行的修复建议
+
+use crate::config::Config;
+use crate::config::ServerSettings;
+use crate::public_ip::resolve_public_host;
+use crate::secrets::{decrypt_config, ConfigKey};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::{TcpListener, TcpStream};
+
+/// 推荐的最小文件描述符上限
+const RECOMMENDED_NOFILE: u64 = 65535;
+
+/// 推荐的最小 somaxconn
+const RECOMMENDED_SOMAXCONN: u64 = 4096;
+
+/// 可接受的最大时钟偏差（秒）
+const MAX_CLOCK_SKEW_SECS: i64 = 90;
+
+/// 时钟校验使用的 HTTPS 端点（只读取 Date 响应头）
+const CLOCK_CHECK_URL: &str = "https://www.cloudflare.com";
+
+/// 检查结果状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CheckStatus {
+    Pass,
+    Warn,
+    Fail,
+    Skip,
+}
+
+/// 单项检查结果
+#[derive(Debug, Clone)]
+pub struct CheckResult {
+    /// 检查项名称
+    pub name: &'static str,
+    /// 检查状态
+    pub status: CheckStatus,
+    /// 检查详情
+    pub detail: String,
+    /// 修复建议（仅 Warn / Fail 时提供）
+    pub fix: Option<String>,
+}
+
+impl CheckResult {
+    fn pass(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Pass,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+
+    fn warn(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Warn,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn fail(name: &'static str, detail: impl Into<String>, fix: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Fail,
+            detail: detail.into(),
+            fix: Some(fix.into()),
+        }
+    }
+
+    fn skip(name: &'static str, detail: impl Into<String>) -> Self {
+        Self {
+            name,
+            status: CheckStatus::Skip,
+            detail: detail.into(),
+            fix: None,
+        }
+    }
+}
+
+/// 检查配置文件能否加载
+pub fn check_config(config_path: &str) -> (CheckResult, Option<Config>) {
+    const NAME: &str = "Config";
+
+    let content = match std::fs::read_to_string(config_path) {
+        Ok(c) => c,
+        Err(e) => {
+            return (
+                CheckResult::fail(
+                    NAME,
+                    format!("Cannot read {}: {}", config_path, e),
+                    "Run `vless` once to launch the configuration wizard, or pass the config path: `vless doctor /path/to/config.json`",
+                ),
+                None,
+            );
+        }
+    };
+
+    match Config::from_json(&content) {
+        Ok(mut config) => {
+            if let Err(e) =
+                ConfigKey::from_env().and_then(|key| decrypt_config(&mut config, key.as_ref()))
+            {
+                return (
+                    CheckResult::fail(
+                        NAME,
+                        format!("Cannot decrypt {}: {}", config_path, e),
+                        "Export VLESS_CONFIG_KEY (or VLESS_CONFIG_KEY_FILE) with the key used by `vless encrypt-config`",
+                    ),
+                    None,
+                );
+            }
+            if config.users.is_empty() {
+                let result = CheckResult::warn(
+                    NAME,
+                    format!("{} loaded, but no users are configured", config_path),
+                    "Add at least one entry to `users` so clients can authenticate",
+                );
+                (result, Some(config))
+            } else {
+                let result = CheckResult::pass(
+                    NAME,
+                    format!("{} loaded ({} users)", config_path, config.users.len()),
+                );
+                (result, Some(config))
+            }
+        }
+        Err(e) => (
+            CheckResult::fail(
+                NAME,
+                format!("Invalid config {}: {}", config_path, e),
+                "Fix the JSON syntax/fields reported above (see docs/spec.md for the schema)",
+            ),
+            None,
+        ),
+    }
+}
+
+/// 检查监听端口是否可绑定，并验证本机可以连入
+pub async fn check_port(addr: SocketAddr) -> CheckResult {
+    const NAME: &str = "Port";
+
+    let listener = match TcpListener::bind(addr).await {
+        Ok(l) => l,
+        Err(e) => {
+            let fix = match e.kind() {
+                std::io::ErrorKind::AddrInUse => format!(
+                    "Port {} is already in use. Stop the other process (`ss -ltnp 'sport = :{}'`) or change `server.port`",
+                    addr.port(),
+                    addr.port()
+                ),
+                std::io::ErrorKind::PermissionDenied => format!(
+                    "Ports below 1024 need privileges. Run as root, grant `setcap cap_net_bind_service=+ep <binary>`, or use a port >= 1024 (current: {})",
+                    addr.port()
+                ),
+                std::io::ErrorKind::AddrNotAvailable => format!(
+                    "Address {} is not assigned to this host. Use 0.0.0.0 or an address shown by `ip addr`",
+                    addr.ip()
+                ),
+                _ => "Check `server.listen` / `server.port` in the config".to_string(),
+            };
+            return CheckResult::fail(NAME, format!("Cannot bind {}: {}", addr, e), fix);
+        }
+    };
+
+    // 使用实际绑定地址（端口为 0 时由系统分配）
+    let local_addr = match listener.local_addr() {
+        Ok(a) => a,
+        Err(e) => {
+            return CheckResult::fail(
+                NAME,
+                format!("Bound {} but cannot read local address: {}", addr, e),
+                "Check `server.listen` / `server.port` in the config",
+            );
+        }
+    };
+
+    // 未指定地址时通过回环地址自连
+    let connect_addr = if local_addr.ip().is_unspecified() {
+        let loopback = if local_addr.is_ipv4() {
+            std::net::IpAddr::from([127, 0, 0, 1])
+        } else {
+            std::net::IpAddr::from(std::net::Ipv6Addr::LOCALHOST)
+        };
+        SocketAddr::new(loopback, local_addr.port())
+    } else {
+        local_addr
+    };
+
+    let connect = tokio::time::timeout(Duration::from_secs(3), TcpStream::connect(connect_addr));
+    match connect.await {
+        Ok(Ok(_)) => CheckResult::pass(
+            NAME,
+            format!(
+                "{} is free and accepts local connections; if clients still get \"connection refused\", open the port in the firewall / cloud security group",
+                addr
+            ),
+        ),
+        Ok(Err(e)) => CheckResult::warn(
+            NAME,
+            format!("Bound {} but local connect failed: {}", addr, e),
+            "A local firewall may be dropping connections; check iptables/nftables rules",
+        ),
+        Err(_) => CheckResult::warn(
+            NAME,
+            format!("Bound {} but local connect timed out", addr),
+            "A local firewall may be dropping connections; check iptables/nftables rules",
+        ),
+    }
+}
+
+/// TLS 证书检查（当前版本未内置 TLS）
+pub fn check_tls() -> CheckResult {
+    CheckResult::skip(
+        "TLS",
+        "TLS is not built in; certificate validity and SNI checks are skipped (put a TLS-terminating proxy in front if needed)",
+    )
+}
+
+/// 检查文件描述符上限
+#[cfg(unix)]
+pub fn check_nofile_limit() -> CheckResult {
+    const NAME: &str = "nofile";
+
+    let mut limit = libc::rlimit {
+        rlim_cur: 0,
+        rlim_max: 0,
+    };
+    // SAFETY: getrlimit 仅写入传入的结构体
+    let ret = unsafe { libc::getrlimit(libc::RLIMIT_NOFILE, &mut limit) };
+    if ret != 0 {
+        return CheckResult::skip(NAME, "Unable to query RLIMIT_NOFILE");
+    }
+
+    let soft = limit.rlim_cur;
+    if soft < RECOMMENDED_NOFILE as libc::rlim_t {
+        CheckResult::warn(
+            NAME,
+            format!(
+                "Open file limit is {} (each proxied connection uses 2 descriptors)",
+                soft
+            ),
+            format!(
+                "Raise it with `ulimit -n {n}` or `LimitNOFILE={n}` in the systemd unit",
+                n = RECOMMENDED_NOFILE
+            ),
+        )
+    } else {
+        CheckResult::pass(NAME, format!("Open file limit is {}", soft))
+    }
+}
+
+/// 检查文件描述符上限（非 Unix 平台跳过）
+#[cfg(not(unix))]
+pub fn check_nofile_limit() -> CheckResult {
+    CheckResult::skip("nofile", "Not applicable on this platform")
+}
+
+/// 检查监听队列上限
+pub fn check_somaxconn() -> CheckResult {
+    const NAME: &str = "somaxconn";
+
+    match std::fs::read_to_string("/proc/sys/net/core/somaxconn") {
+        Ok(content) => match content.trim().parse::<u64>() {
+            Ok(value) => evaluate_somaxconn(value),
+            Err(_) => CheckResult::skip(NAME, "Unable to parse /proc/sys/net/core/somaxconn"),
+        },
+        Err(_) => CheckResult::skip(NAME, "Not available on this platform"),
+    }
+}
+
+/// 根据 somaxconn 值给出结论
+pub fn evaluate_somaxconn(value: u64) -> CheckResult {
+    const NAME: &str = "somaxconn";
+
+    if value < RECOMMENDED_SOMAXCONN {
+        CheckResult::warn(
+            NAME,
+            format!("net.core.somaxconn is {}", value),
+            format!(
+                "Bursts of new connections may be dropped; run `sysctl -w net.core.somaxconn={}`",
+                RECOMMENDED_SOMAXCONN
+            ),
+        )
+    } else {
+        CheckResult::pass(NAME, format!("net.core.somaxconn is {}", value))
+    }
+}
+
+/// 根据时钟偏差（秒，本地 - 远端）给出结论
+pub fn evaluate_clock_skew(skew_secs: i64) -> CheckResult {
+    const NAME: &str = "Clock";
+
+    if skew_secs.abs() > MAX_CLOCK_SKEW_SECS {
+        CheckResult::warn(
+            NAME,
+            format!("Local clock is off by {}s", skew_secs),
+            "Enable time sync (`timedatectl set-ntp true` or chrony); TLS clients reject peers with skewed clocks",
+        )
+    } else {
+        CheckResult::pass(NAME, format!("Local clock skew is {}s", skew_secs))
+    }
+}
+
+/// 通过 HTTPS 响应的 Date 头检查时钟偏差
+pub async fn check_clock_skew() -> CheckResult {
+    const NAME: &str = "Clock";
+
+    let client = match reqwest::Client::builder()
+        .user_agent("VLESS-Rust/1.0")
+        .timeout(Duration::from_secs(5))
+        .build()
+    {
+        Ok(c) => c,
+        Err(e) => return CheckResult::skip(NAME, format!("Failed to create HTTP client: {}", e)),
+    };
+
+    let response = match client.head(CLOCK_CHECK_URL).send().await {
+        Ok(r) => r,
+        Err(e) => {
+            return CheckResult::skip(NAME, format!("Cannot reach {}: {}", CLOCK_CHECK_URL, e));
+        }
+    };
+
+    let remote = response
+        .headers()
+        .get(reqwest::header::DATE)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| chrono::DateTime::parse_from_rfc2822(v).ok());
+
+    match remote {
+        Some(remote) => {
+            let skew = chrono::Utc::now().timestamp() - remote.timestamp();
+            evaluate_clock_skew(skew)
+        }
+        None => CheckResult::skip(NAME, "Remote server did not return a usable Date header"),
+    }
+}
+
+/// 检查公网 IP 探测
+pub async fn check_public_ip(settings: Option<&ServerSettings>) -> CheckResult {
+    const NAME: &str = "Public IP";
+
+    if let Some(settings) = settings {
+        if settings.domain.is_none() && settings.public_ip.is_none() && !settings.detect_public_ip
+        {
+            return CheckResult::skip(
+                NAME,
+                "Detection disabled (server.detect_public_ip = false); share links use the listen address",
+            );
+        }
+    }
+
+    let result = match settings {
+        Some(settings) => resolve_public_host(settings, 5).await,
+        None => crate::public_ip::fetch_public_ip_with_timeout(5).await,
+    };
+
+    match result {
+        Some(ip) => CheckResult::pass(NAME, format!("{} (from {})", ip.ip, ip.source)),
+        None => CheckResult::warn(
+            NAME,
+            "Public IP detection failed; share links will use the listen address",
+            "Check outbound HTTPS connectivity / DNS, or set `server.public_ip` / `server.domain` to skip detection",
+        ),
+    }
+}
+
+/// 执行全部检查
+pub async fn run_checks(config_path: &str) -> Vec<CheckResult> {
+    let mut results = Vec::new();
+
+    let (config_result, config) = check_config(config_path);
+    results.push(config_result);
+
+    match config.as_ref().map(|c| c.bind_addr()) {
+        Some(Ok(addr)) => results.push(check_port(addr).await),
+        Some(Err(e)) => results.push(CheckResult::fail(
+            "Port",
+            format!("Invalid listen address: {}", e),
+            "`server.listen` must be an IP address such as 0.0.0.0 or ::",
+        )),
+        None => results.push(CheckResult::skip("Port", "Skipped (config not loaded)")),
+    }
+
+    results.push(check_tls());
+    results.push(check_nofile_limit());
+    results.push(check_somaxconn());
+    results.push(check_clock_skew().await);
+    results.push(check_public_ip(config.as_ref().map(|c| &c.server)).await);
+
+    results
+}
+
+/// 打印诊断报告
+///
+/// # Returns
+/// * `bool` - 没有 Fail 项时返回 true
+pub fn print_report(results: &[CheckResult]) -> bool {
+    println!("VLESS doctor report");
+    println!("===================");
+
+    for result in results {
+        let mark = match result.status {
+            CheckStatus::Pass => "[ OK ]",
+            CheckStatus::Warn => "[WARN]",
+            CheckStatus::Fail => "[FAIL]",
+            CheckStatus::Skip => "[SKIP]",
+        };
+        println!("{} {:<10} {}", mark, result.name, result.detail);
+        if let Some(ref fix) = result.fix {
+            println!("       {:<10} fix: {}", "", fix);
+        }
+    }
+
+    let failed = results
+        .iter()
+        .filter(|r| r.status == CheckStatus::Fail)
+        .count();
+    let warned = results
+        .iter()
+        .filter(|r| r.status == CheckStatus::Warn)
+        .count();
+
+    println!();
+    println!("{} failed, {} warnings", failed, warned);
+
+    failed == 0
+}
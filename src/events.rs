@@ -0,0 +1,118 @@
+//! 内部事件总线模块
+//!
+//! 代理连接在关键节点发布事件，计费投递等子系统各自订阅，
+//! 代理路径不再直接依赖具体的消费方
+
+use crate::session::SessionRecord;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+use uuid::Uuid;
+
+/// 事件缓冲容量，订阅方处理过慢时丢弃最旧的事件
+const EVENT_CAPACITY: usize = 4096;
+
+/// 服务器事件
+#[derive(Debug, Clone)]
+pub enum Event {
+    /// 认证通过，开始代理
+    ConnectionOpened {
+        client_addr: SocketAddr,
+        uuid: Uuid,
+        user: String,
+        network: &'static str,
+        dest: String,
+    },
+    /// 连接结束（与 `ConnectionOpened` 成对出现）
+    ConnectionClosed {
+        client_addr: SocketAddr,
+        uuid: Uuid,
+        user: String,
+    },
+    /// 会话关闭（携带会话记录；建连失败的连接没有会话记录）
+    SessionClosed(Arc<SessionRecord>),
+    /// UUID 认证失败
+    AuthFailed { client_addr: SocketAddr, uuid: Uuid },
+    /// 目标被拦截列表或路由规则拦截
+    DestinationBlocked { user: String, dest: String },
+}
+
+/// 事件总线（克隆仅增加引用计数，所有克隆共享同一通道）
+#[derive(Debug, Clone)]
+pub struct EventBus {
+    sender: broadcast::Sender<Event>,
+}
+
+impl Default for EventBus {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        let (sender, _) = broadcast::channel(EVENT_CAPACITY);
+        Self { sender }
+    }
+
+    /// 发布事件（无订阅方时直接丢弃）
+    pub fn publish(&self, event: Event) {
+        let _ = self.sender.send(event);
+    }
+
+    /// 订阅后续事件
+    pub fn subscribe(&self) -> broadcast::Receiver<Event> {
+        self.sender.subscribe()
+    }
+
+    /// 当前订阅方数量
+    pub fn subscriber_count(&self) -> usize {
+        self.sender.receiver_count()
+    }
+}
+
+/// 启动审计日志订阅方
+///
+/// 以 `audit` 为 target 输出连接建立、认证失败与拦截事件，便于单独过滤
+/// （会话关闭已由会话日志覆盖，这里不重复输出）
+pub fn spawn_audit_log(mut events: broadcast::Receiver<Event>) {
+    tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(Event::ConnectionOpened {
+                    client_addr,
+                    user,
+                    network,
+                    dest,
+                    ..
+                }) => {
+                    info!(
+                        target: "audit",
+                        client = %client_addr,
+                        user = %user,
+                        network,
+                        dest = %dest,
+                        "Connection opened"
+                    );
+                }
+                Ok(Event::AuthFailed { client_addr, uuid }) => {
+                    warn!(
+                        target: "audit",
+                        client = %client_addr,
+                        uuid = %uuid,
+                        "Authentication failed"
+                    );
+                }
+                Ok(Event::DestinationBlocked { user, dest }) => {
+                    info!(target: "audit", user = %user, dest = %dest, "Destination blocked");
+                }
+                Ok(Event::ConnectionClosed { .. }) | Ok(Event::SessionClosed(_)) => {}
+                Err(broadcast::error::RecvError::Lagged(n)) => {
+                    warn!(target: "audit", "Audit log lagged behind, {} events dropped", n);
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
@@ -0,0 +1,72 @@
+//! 回落模块
+//!
+//! 首部既不是已启用的协议、VLESS 请求无法解析或 UUID 无效时，不直接断开，
+//! 而是把原始字节流（含已读取的数据）透明转发到本地 Web 服务（Xray 风格 fallbacks），
+//! 主动探测看到的是正常网站
+
+use crate::config::FallbackRule;
+use crate::decoy::{path_has_prefix, request_line};
+use anyhow::{anyhow, Result};
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::TcpStream;
+use tracing::debug;
+
+/// 连接回落目标超时
+const DEST_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 回落规则集合
+#[derive(Debug)]
+pub struct Fallback {
+    rules: Vec<FallbackRule>,
+}
+
+impl Fallback {
+    pub fn new(rules: Vec<FallbackRule>) -> Self {
+        Self { rules }
+    }
+
+    /// 按首部数据选择回落目标：设置了 `path` 的规则按 HTTP 请求路径前缀匹配，
+    /// 其余规则作为默认目标；均按配置顺序先匹配先生效
+    pub fn select(&self, data: &[u8]) -> Option<&str> {
+        let target = request_line(data).map(|(_, target)| target);
+        self.rules
+            .iter()
+            .find(|rule| match (rule.path.as_deref(), target) {
+                (Some(path), Some(target)) => path_has_prefix(target, path),
+                (Some(_), None) => false,
+                (None, _) => true,
+            })
+            .map(|rule| rule.dest.as_str())
+    }
+
+    /// 把连接转发到回落目标；`initial` 为已从连接读取的数据，
+    /// 仅 peek 未读取的数据随双向复制一并发出
+    ///
+    /// 未匹配任何规则或目标不可达时直接关闭连接（不返回任何响应）
+    pub async fn serve(&self, mut stream: TcpStream, initial: &[u8]) -> Result<()> {
+        let mut head = [0u8; 1024];
+        let data = if initial.is_empty() {
+            let n = stream.peek(&mut head).await?;
+            &head[..n]
+        } else {
+            initial
+        };
+        let Some(dest) = self.select(data) else {
+            debug!("No fallback matches, closing connection");
+            return Ok(());
+        };
+
+        let connect = tokio::time::timeout(DEST_CONNECT_TIMEOUT, TcpStream::connect(dest)).await;
+        let mut upstream = match connect {
+            Ok(Ok(s)) => s,
+            Ok(Err(e)) => return Err(anyhow!("Failed to connect to fallback {}: {}", dest, e)),
+            Err(_) => return Err(anyhow!("Timed out connecting to fallback {}", dest)),
+        };
+
+        upstream.write_all(initial).await?;
+        let result = tokio::io::copy_bidirectional(&mut stream, &mut upstream).await;
+        debug!("Fallback to {} finished: {:?}", dest, result);
+        Ok(())
+    }
+}
@@ -0,0 +1,460 @@
+//! 端口转发入站模块
+//!
+//! 类似 dokodemo-door：监听端口收到的原始 TCP 连接 / UDP 数据报直接转发到固定目标，
+//! 与 VLESS 会话共用出站连接、出站 TLS、目标统计、会话记录与事件
+
+use crate::address::ConnectTiming;
+use crate::capture::{CaptureReader, Direction};
+use crate::config::{ForwardConfig, PerformanceConfig};
+use crate::protocol::{Address, Command, VlessRequest};
+use crate::route_stats::{SessionRoute, ROUTE_DIRECT};
+use crate::runtime_stats::TaskCounter;
+use crate::session::{
+    copy_counted, copy_with_ttfb, format_destination, SessionRecord, SessionServices,
+};
+use crate::socket::{
+    apply_tcp_mtu_options, bind_udp_socket, configure_tcp_socket, set_pmtu_discovery,
+};
+use crate::stall::ActivityReader;
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use socket2::SockRef;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// 每个转发端口同时存在的 UDP 会话上限
+const MAX_UDP_SESSIONS: usize = 1024;
+
+/// 解析目标地址（`host:port`、`1.2.3.4:port` 或 `[v6]:port`）
+pub fn parse_dest(dest: &str) -> Result<(Address, u16)> {
+    if let Ok(addr) = dest.parse::<SocketAddr>() {
+        let address = match addr {
+            SocketAddr::V4(v4) => Address::Ipv4(*v4.ip()),
+            SocketAddr::V6(v6) => Address::Ipv6(*v6.ip()),
+        };
+        return Ok((address, addr.port()));
+    }
+
+    let (host, port) = dest
+        .rsplit_once(':')
+        .ok_or_else(|| anyhow!("Forward dest must be host:port: {}", dest))?;
+    let port = port
+        .parse::<u16>()
+        .ok()
+        .filter(|&p| p > 0)
+        .ok_or_else(|| anyhow!("Invalid forward dest port: {}", dest))?;
+    if host.is_empty() || host.contains(':') || host.len() > 255 {
+        return Err(anyhow!("Invalid forward dest host: {}", dest));
+    }
+    Ok((Address::Domain(Bytes::from(host.to_string())), port))
+}
+
+/// 转发目标（TCP / UDP 共享）
+#[derive(Debug)]
+struct ForwardTarget {
+    name: String,
+    /// 统计用标识（每个转发入站一个，与用户 UUID 区分）
+    uuid: Uuid,
+    address: Address,
+    port: u16,
+    services: SessionServices,
+    perf_config: PerformanceConfig,
+    /// 连接任务计数（TCP 连接与 UDP 会话）
+    tasks: Arc<TaskCounter>,
+}
+
+impl ForwardTarget {
+    /// 构造与 VLESS 请求等价的会话描述（用于连接事件）
+    fn request(&self, command: Command) -> VlessRequest {
+        VlessRequest {
+            version: 0,
+            uuid: self.uuid,
+            addons_length: 0,
+            addons: Bytes::new(),
+            command,
+            port: self.port,
+            address: self.address.clone(),
+        }
+    }
+
+    fn dest(&self) -> String {
+        format_destination(&self.address, self.port)
+    }
+
+    /// 会话记录与统计中的用户标识
+    fn label(&self) -> String {
+        format!("forward:{}", self.name)
+    }
+}
+
+/// 已绑定的端口转发入站
+#[derive(Debug)]
+pub struct Forwarder {
+    target: Arc<ForwardTarget>,
+    tcp: Option<TcpListener>,
+    udp: Option<UdpSocket>,
+}
+
+impl Forwarder {
+    /// 解析配置并绑定监听端口
+    pub async fn bind(
+        config: &ForwardConfig,
+        services: SessionServices,
+        perf_config: PerformanceConfig,
+    ) -> Result<Self> {
+        let listen: SocketAddr = config
+            .listen
+            .parse()
+            .map_err(|_| anyhow!("Invalid forward listen address: {}", config.listen))?;
+        let (address, port) = parse_dest(&config.dest)?;
+
+        let tcp = if config.network.tcp() {
+            let listener = TcpListener::bind(listen).await?;
+            apply_tcp_mtu_options(&SockRef::from(&listener), &perf_config);
+            Some(listener)
+        } else {
+            None
+        };
+        // TCP 端口为 0 时让 UDP 使用同一个随机端口
+        let udp_listen = tcp
+            .as_ref()
+            .map(|l| l.local_addr())
+            .transpose()?
+            .unwrap_or(listen);
+        let udp = if config.network.udp() {
+            let socket = UdpSocket::bind(udp_listen).await?;
+            set_pmtu_discovery(&SockRef::from(&socket), perf_config.pmtu_discovery);
+            Some(socket)
+        } else {
+            None
+        };
+
+        let name = config.name.clone().unwrap_or_else(|| config.listen.clone());
+        let tasks = services
+            .listener_tasks
+            .register(&format!("forward:{}", name));
+        Ok(Self {
+            target: Arc::new(ForwardTarget {
+                name,
+                uuid: Uuid::new_v4(),
+                address,
+                port,
+                services,
+                perf_config,
+                tasks,
+            }),
+            tcp,
+            udp,
+        })
+    }
+
+    /// 名称
+    pub fn name(&self) -> &str {
+        &self.target.name
+    }
+
+    /// 会话记录与统计中的用户标识（`forward:<名称>`）
+    pub fn label(&self) -> String {
+        self.target.label()
+    }
+
+    /// 统计用标识
+    pub fn uuid(&self) -> Uuid {
+        self.target.uuid
+    }
+
+    /// 目标地址
+    pub fn dest(&self) -> String {
+        self.target.dest()
+    }
+
+    /// TCP 监听地址
+    pub fn tcp_addr(&self) -> Option<SocketAddr> {
+        self.tcp.as_ref().and_then(|l| l.local_addr().ok())
+    }
+
+    /// UDP 监听地址
+    pub fn udp_addr(&self) -> Option<SocketAddr> {
+        self.udp.as_ref().and_then(|s| s.local_addr().ok())
+    }
+
+    /// 启动转发任务（中止返回的任务即停止监听）
+    pub fn spawn(self) -> Vec<JoinHandle<()>> {
+        let mut tasks = Vec::new();
+        if let Some(listener) = self.tcp {
+            tasks.push(tokio::spawn(run_tcp(listener, Arc::clone(&self.target))));
+        }
+        if let Some(socket) = self.udp {
+            tasks.push(tokio::spawn(run_udp(
+                Arc::new(socket),
+                Arc::clone(&self.target),
+            )));
+        }
+        tasks
+    }
+}
+
+/// TCP 接收循环
+async fn run_tcp(listener: TcpListener, target: Arc<ForwardTarget>) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, client_addr)) => {
+                if !target.services.admit() {
+                    debug!(
+                        "Forward {} paused or in maintenance, closing {}",
+                        target.name, client_addr
+                    );
+                    continue;
+                }
+                let target = Arc::clone(&target);
+                let task = target.tasks.enter();
+                tokio::spawn(async move {
+                    let _task = task;
+                    if target.services.is_banned(client_addr).await {
+                        debug!(
+                            "Forward {} CrowdSec decision bans {}, closing",
+                            target.name, client_addr
+                        );
+                        return;
+                    }
+                    if let Err(e) = forward_tcp(stream, client_addr, &target).await {
+                        debug!("Forward {} from {} failed: {}", target.name, client_addr, e);
+                    }
+                });
+            }
+            Err(e) => warn!("Forward {} accept failed: {}", target.name, e),
+        }
+    }
+}
+
+/// 转发单个 TCP 连接
+async fn forward_tcp(
+    client_stream: TcpStream,
+    client_addr: SocketAddr,
+    target: &ForwardTarget,
+) -> Result<()> {
+    let services = &target.services;
+    let perf_config = &target.perf_config;
+    configure_tcp_socket(
+        &client_stream,
+        perf_config.tcp_recv_buffer,
+        perf_config.tcp_send_buffer,
+        perf_config.tcp_nodelay,
+    )?;
+    let _connection = services.connection_opened(client_addr, &target.request(Command::Tcp), None);
+
+    let started = Instant::now();
+    let dest = target.dest();
+    let connected = services
+        .connect_target(&target.address, target.port, perf_config)
+        .await;
+    services.record_connect(&dest, &connected);
+    let (target_stream, timing) = connected?;
+    let connected_at = Instant::now();
+    debug!(
+        "Forward {}: {} -> {}",
+        target.name,
+        client_addr,
+        target_stream.peer_addr()?
+    );
+
+    // 转发目标不经路由规则，只经 `server.outbound`
+    let route = services.outbound.as_ref().map_or(ROUTE_DIRECT, |o| o.tag());
+    let mut record = SessionRecord::new(target.uuid, target.label(), "tcp", dest, &timing, started)
+        .with_route(SessionRoute::new(route, None));
+    let capture = services.open_capture(&record);
+
+    let (activity, _stall) = services.watch_stall(&record, perf_config);
+    let (client_read, mut client_write) = client_stream.into_split();
+    let (target_read, mut target_write) = target_stream.into_split();
+    let client_read = CaptureReader::new(client_read, capture.clone(), Direction::Up);
+    let target_read = CaptureReader::new(target_read, capture, Direction::Down);
+    let mut client_read = ActivityReader::new(client_read, Arc::clone(&activity));
+    let mut target_read = ActivityReader::new(target_read, activity);
+
+    let client_to_target =
+        tokio::spawn(async move { copy_counted(&mut client_read, &mut target_write).await.0 });
+    let target_to_client = tokio::spawn(async move {
+        let (ttfb, bytes, _) =
+            copy_with_ttfb(&mut target_read, &mut client_write, connected_at).await;
+        (ttfb, bytes)
+    });
+
+    let (up, down) = tokio::join!(client_to_target, target_to_client);
+    let (ttfb, bytes_down) = down.unwrap_or((None, 0));
+    record.bytes_up = up.unwrap_or(0);
+    record.bytes_down = bytes_down;
+    record.set_ttfb(ttfb);
+    services.finish_session(&mut record);
+    Ok(())
+}
+
+/// UDP 会话（按客户端地址区分）
+#[derive(Debug)]
+struct UdpSession {
+    socket: Arc<UdpSocket>,
+    target_addr: SocketAddr,
+    bytes_up: Arc<AtomicU64>,
+}
+
+type UdpSessions = Arc<Mutex<HashMap<SocketAddr, Arc<UdpSession>>>>;
+
+/// UDP 接收循环：按客户端地址建立会话，目标应答原路返回
+async fn run_udp(listener: Arc<UdpSocket>, target: Arc<ForwardTarget>) {
+    let sessions: UdpSessions = Arc::default();
+    let mut buffer = vec![0u8; 64 * 1024];
+
+    loop {
+        let (n, client_addr) = match listener.recv_from(&mut buffer).await {
+            Ok(received) => received,
+            Err(e) => {
+                debug!("Forward {} UDP receive failed: {}", target.name, e);
+                continue;
+            }
+        };
+
+        let existing = sessions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .get(&client_addr)
+            .cloned();
+        let session = match existing {
+            Some(session) => session,
+            None => {
+                if !target.services.admit() {
+                    debug!(
+                        "Forward {} paused or in maintenance, dropping {}",
+                        target.name, client_addr
+                    );
+                    continue;
+                }
+                if sessions.lock().unwrap_or_else(|e| e.into_inner()).len() >= MAX_UDP_SESSIONS {
+                    debug!("Forward {} UDP session limit reached", target.name);
+                    continue;
+                }
+                match open_udp_session(&listener, &sessions, &target, client_addr).await {
+                    Ok(session) => session,
+                    Err(e) => {
+                        debug!("Forward {} UDP session failed: {}", target.name, e);
+                        continue;
+                    }
+                }
+            }
+        };
+
+        match session
+            .socket
+            .send_to(&buffer[..n], session.target_addr)
+            .await
+        {
+            Ok(_) => {
+                session.bytes_up.fetch_add(n as u64, Ordering::Relaxed);
+            }
+            Err(e) => debug!("Forward {} UDP send failed: {}", target.name, e),
+        }
+    }
+}
+
+/// 建立 UDP 会话并启动应答转发任务（空闲超过 `udp_timeout` 后结束）
+async fn open_udp_session(
+    listener: &Arc<UdpSocket>,
+    sessions: &UdpSessions,
+    target: &Arc<ForwardTarget>,
+    client_addr: SocketAddr,
+) -> Result<Arc<UdpSession>> {
+    let started = Instant::now();
+    let target_addr = target
+        .services
+        .resolver
+        .resolve_address(&target.address, target.port)
+        .await?;
+    let timing = ConnectTiming {
+        resolved: Some(target_addr),
+        dns: started.elapsed(),
+        ..Default::default()
+    };
+    let socket = Arc::new(bind_udp_socket(&target.perf_config, target_addr).await?);
+    let session = Arc::new(UdpSession {
+        socket: Arc::clone(&socket),
+        target_addr,
+        bytes_up: Arc::new(AtomicU64::new(0)),
+    });
+    sessions
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(client_addr, Arc::clone(&session));
+
+    let mut record = SessionRecord::new(
+        target.uuid,
+        target.label(),
+        "udp",
+        target.dest(),
+        &timing,
+        started,
+    )
+    .with_route(SessionRoute::new(ROUTE_DIRECT, None));
+    let connection =
+        target
+            .services
+            .connection_opened(client_addr, &target.request(Command::Udp), None);
+    let listener = Arc::clone(listener);
+    let sessions = Arc::clone(sessions);
+    let target = Arc::clone(target);
+    let bytes_up = Arc::clone(&session.bytes_up);
+    let task = target.tasks.enter();
+
+    tokio::spawn(async move {
+        let _task = task;
+        let idle = Duration::from_secs(target.perf_config.udp_timeout.max(1));
+        let mut buffer = vec![0u8; 64 * 1024];
+        let mut ttfb = None;
+        let mut bytes_down = 0u64;
+        let mut seen_up = 0u64;
+
+        loop {
+            match tokio::time::timeout(idle, socket.recv_from(&mut buffer)).await {
+                Ok(Ok((n, src))) => {
+                    if src != target_addr {
+                        continue;
+                    }
+                    ttfb.get_or_insert_with(|| started.elapsed());
+                    if listener.send_to(&buffer[..n], client_addr).await.is_err() {
+                        break;
+                    }
+                    bytes_down += n as u64;
+                }
+                Ok(Err(e)) => {
+                    debug!("Forward UDP receive failed: {}", e);
+                    break;
+                }
+                Err(_) => {
+                    // 期间仍有上行数据时继续等待
+                    let up = bytes_up.load(Ordering::Relaxed);
+                    if up == seen_up {
+                        break;
+                    }
+                    seen_up = up;
+                }
+            }
+        }
+
+        sessions
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&client_addr);
+        record.bytes_up = bytes_up.load(Ordering::Relaxed);
+        record.bytes_down = bytes_down;
+        record.set_ttfb(ttfb);
+        target.services.finish_session(&mut record);
+        drop(connection);
+    });
+
+    Ok(session)
+}
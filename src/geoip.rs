@@ -0,0 +1,310 @@
+//! GeoIP 模块
+//!
+//! 读取 MaxMind DB 格式（`GeoLite2-Country.mmdb` 等）的国家数据库，按 IP 查询国家代码，
+//! 供路由规则的 `geoip:<国家代码>` 条件使用；`geoip:private` 为内置的保留地址段，无需数据库。
+//! 数据库整体读入内存，查询时遍历搜索树并解码命中的数据记录
+
+use anyhow::{anyhow, Result};
+use serde_json::{Map, Value};
+use std::net::IpAddr;
+use std::path::Path;
+
+/// 元数据起始标记
+const METADATA_MARKER: &[u8] = b"\xAB\xCD\xEFMaxMind.com";
+
+/// 搜索树与数据区之间的 16 字节分隔
+const DATA_SECTION_SEPARATOR: usize = 16;
+
+/// 数据解码的最大嵌套深度
+const MAX_DEPTH: usize = 32;
+
+/// MaxMind DB 国家数据库
+#[derive(Debug)]
+pub struct GeoIpDb {
+    data: Vec<u8>,
+    node_count: usize,
+    record_size: usize,
+    ip_version: u16,
+    /// 搜索树字节数
+    tree_size: usize,
+    /// IPv4 地址在 IPv6 树中的起始节点（跳过前 96 个 0 位）
+    ipv4_start: usize,
+    database_type: String,
+}
+
+impl GeoIpDb {
+    /// 读取数据库文件
+    pub fn open(path: &Path) -> Result<Self> {
+        let data = std::fs::read(path)
+            .map_err(|e| anyhow!("Failed to read GeoIP database {}: {}", path.display(), e))?;
+        Self::from_bytes(data)
+            .map_err(|e| anyhow!("Invalid GeoIP database {}: {}", path.display(), e))
+    }
+
+    /// 从内存中的数据库内容创建
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self> {
+        let marker = data
+            .windows(METADATA_MARKER.len())
+            .rposition(|w| w == METADATA_MARKER)
+            .ok_or_else(|| anyhow!("metadata marker not found"))?;
+        let metadata_start = marker + METADATA_MARKER.len();
+        let (metadata, _) = Decoder::new(&data[metadata_start..]).decode(0, 0)?;
+        let field = |name: &str| {
+            metadata
+                .get(name)
+                .and_then(Value::as_u64)
+                .ok_or_else(|| anyhow!("metadata field {} is missing", name))
+        };
+        let node_count = field("node_count")? as usize;
+        let record_size = field("record_size")? as usize;
+        let ip_version = field("ip_version")? as u16;
+        if !matches!(record_size, 24 | 28 | 32) {
+            return Err(anyhow!("unsupported record size {}", record_size));
+        }
+        if !matches!(ip_version, 4 | 6) {
+            return Err(anyhow!("unsupported IP version {}", ip_version));
+        }
+        let tree_size = node_count * record_size / 4;
+        if tree_size + DATA_SECTION_SEPARATOR > marker {
+            return Err(anyhow!("search tree exceeds file size"));
+        }
+
+        let mut db = Self {
+            data,
+            node_count,
+            record_size,
+            ip_version,
+            tree_size,
+            ipv4_start: 0,
+            database_type: metadata
+                .get("database_type")
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string(),
+        };
+        if ip_version == 6 {
+            let mut node = 0;
+            for _ in 0..96 {
+                if node >= node_count {
+                    break;
+                }
+                node = db.read_record(node, 0);
+            }
+            db.ipv4_start = node;
+        }
+        Ok(db)
+    }
+
+    /// 数据库类型，如 `GeoLite2-Country`
+    pub fn database_type(&self) -> &str {
+        &self.database_type
+    }
+
+    /// 查询 IP 对应的数据记录；数据库中没有该地址时返回 None
+    pub fn lookup(&self, ip: IpAddr) -> Result<Option<Value>> {
+        let (bits, bit_count, mut node) = match ip.to_canonical() {
+            IpAddr::V4(v4) => {
+                let start = if self.ip_version == 6 {
+                    self.ipv4_start
+                } else {
+                    0
+                };
+                ((u32::from(v4) as u128) << 96, 32, start)
+            }
+            IpAddr::V6(_) if self.ip_version == 4 => return Ok(None),
+            IpAddr::V6(v6) => (u128::from(v6), 128, 0),
+        };
+        for i in 0..bit_count {
+            if node >= self.node_count {
+                break;
+            }
+            node = self.read_record(node, ((bits >> (127 - i)) & 1) as usize);
+        }
+        if node <= self.node_count {
+            return Ok(None);
+        }
+        let offset = (node - self.node_count)
+            .checked_sub(DATA_SECTION_SEPARATOR)
+            .ok_or_else(|| anyhow!("invalid data pointer {}", node))?;
+        let data_section = &self.data[self.tree_size + DATA_SECTION_SEPARATOR..];
+        let (value, _) = Decoder::new(data_section).decode(offset, 0)?;
+        Ok(Some(value))
+    }
+
+    /// 查询 IP 所属国家的小写 ISO 代码（无国家时取注册国家）
+    pub fn country(&self, ip: IpAddr) -> Option<String> {
+        let record = self.lookup(ip).ok()??;
+        ["country", "registered_country"]
+            .iter()
+            .find_map(|key| record.get(key)?.get("iso_code")?.as_str())
+            .map(|code| code.to_ascii_lowercase())
+    }
+
+    /// 读取节点的左（`bit = 0`）或右（`bit = 1`）记录
+    fn read_record(&self, node: usize, bit: usize) -> usize {
+        let base = node * self.record_size / 4;
+        let bytes = |start: usize, len: usize| {
+            self.data[base + start..base + start + len]
+                .iter()
+                .fold(0usize, |acc, &b| (acc << 8) | b as usize)
+        };
+        match (self.record_size, bit) {
+            (24, 0) => bytes(0, 3),
+            (24, _) => bytes(3, 3),
+            (28, 0) => ((self.data[base + 3] as usize & 0xf0) << 20) | bytes(0, 3),
+            (28, _) => ((self.data[base + 3] as usize & 0x0f) << 24) | bytes(4, 3),
+            (_, 0) => bytes(0, 4),
+            _ => bytes(4, 4),
+        }
+    }
+}
+
+/// 数据区解码器（指针相对于 `buf` 起始位置）
+struct Decoder<'a> {
+    buf: &'a [u8],
+}
+
+impl<'a> Decoder<'a> {
+    fn new(buf: &'a [u8]) -> Self {
+        Self { buf }
+    }
+
+    fn bytes(&self, offset: usize, len: usize) -> Result<&'a [u8]> {
+        self.buf
+            .get(offset..offset + len)
+            .ok_or_else(|| anyhow!("data offset {} out of range", offset))
+    }
+
+    fn uint(&self, offset: usize, len: usize) -> Result<u128> {
+        Ok(self
+            .bytes(offset, len)?
+            .iter()
+            .fold(0u128, |acc, &b| (acc << 8) | b as u128))
+    }
+
+    /// 解码 `offset` 处的值，返回（值，下一个值的偏移）
+    fn decode(&self, offset: usize, depth: usize) -> Result<(Value, usize)> {
+        if depth > MAX_DEPTH {
+            return Err(anyhow!("data nested too deeply"));
+        }
+        let control = self.bytes(offset, 1)?[0];
+        let mut offset = offset + 1;
+        let mut kind = control >> 5;
+        if kind == 0 {
+            kind = 7u8.saturating_add(self.bytes(offset, 1)?[0]);
+            offset += 1;
+        }
+
+        if kind == 1 {
+            // 指针：解码目标值，但后续数据紧接在指针之后
+            let size = ((control >> 3) & 0x3) as usize;
+            let high = (control & 0x7) as usize;
+            let pointer = match size {
+                0 => (high << 8) | self.uint(offset, 1)? as usize,
+                1 => ((high << 16) | self.uint(offset, 2)? as usize) + 2048,
+                2 => ((high << 24) | self.uint(offset, 3)? as usize) + 526_336,
+                _ => self.uint(offset, 4)? as usize,
+            };
+            let (value, _) = self.decode(pointer, depth + 1)?;
+            return Ok((value, offset + size + 1));
+        }
+
+        let mut size = (control & 0x1f) as usize;
+        match size {
+            29 => {
+                size = 29 + self.uint(offset, 1)? as usize;
+                offset += 1;
+            }
+            30 => {
+                size = 285 + self.uint(offset, 2)? as usize;
+                offset += 2;
+            }
+            31 => {
+                size = 65_821 + self.uint(offset, 3)? as usize;
+                offset += 3;
+            }
+            _ => {}
+        }
+
+        let value = match kind {
+            2 => {
+                let text = std::str::from_utf8(self.bytes(offset, size)?)
+                    .map_err(|_| anyhow!("invalid UTF-8 string at offset {}", offset))?;
+                Value::from(text)
+            }
+            3 => Value::from(f64::from_be_bytes(
+                self.bytes(offset, 8)?.try_into().unwrap_or_default(),
+            )),
+            15 => Value::from(f32::from_be_bytes(
+                self.bytes(offset, 4)?.try_into().unwrap_or_default(),
+            )),
+            4 => Value::from(self.bytes(offset, size)?.to_vec()),
+            5 | 6 | 9 => Value::from(self.uint(offset, size)? as u64),
+            10 => Value::from(self.uint(offset, size)?.to_string()),
+            8 => Value::from(self.uint(offset, size)? as u32 as i32),
+            14 => return Ok((Value::Bool(size != 0), offset)),
+            7 => {
+                let mut map = Map::new();
+                for _ in 0..size {
+                    let (key, next) = self.decode(offset, depth + 1)?;
+                    let (value, next) = self.decode(next, depth + 1)?;
+                    let key = key
+                        .as_str()
+                        .ok_or_else(|| anyhow!("map key is not a string"))?;
+                    map.insert(key.to_string(), value);
+                    offset = next;
+                }
+                return Ok((Value::Object(map), offset));
+            }
+            11 => {
+                let mut items = Vec::with_capacity(size.min(256));
+                for _ in 0..size {
+                    let (value, next) = self.decode(offset, depth + 1)?;
+                    items.push(value);
+                    offset = next;
+                }
+                return Ok((Value::Array(items), offset));
+            }
+            _ => return Err(anyhow!("unsupported data type {}", kind)),
+        };
+        let end = match kind {
+            3 => offset + 8,
+            15 => offset + 4,
+            _ => offset + size,
+        };
+        Ok((value, end))
+    }
+}
+
+/// 是否为私有或保留地址（`geoip:private`）
+///
+/// 包括 RFC 1918 私有网段、回环、链路本地、运营商级 NAT、文档与基准测试网段、组播与保留地址，
+/// 以及 IPv6 的唯一本地、链路本地与组播地址
+pub fn is_private(ip: IpAddr) -> bool {
+    match ip.to_canonical() {
+        IpAddr::V4(v4) => {
+            let [a, b, c, _] = v4.octets();
+            v4.is_private()
+                || v4.is_loopback()
+                || v4.is_link_local()
+                || v4.is_unspecified()
+                || v4.is_broadcast()
+                || v4.is_documentation()
+                || a == 0
+                || a >= 224
+                || (a == 100 && (64..128).contains(&b))
+                || (a == 192 && b == 0 && c == 0)
+                || (a == 192 && b == 88 && c == 99)
+                || (a == 198 && (18..20).contains(&b))
+        }
+        IpAddr::V6(v6) => {
+            let first = v6.segments()[0];
+            v6.is_loopback()
+                || v6.is_unspecified()
+                || v6.is_multicast()
+                || (first & 0xfe00) == 0xfc00
+                || (first & 0xffc0) == 0xfe80
+                || (first == 0x2001 && v6.segments()[1] == 0x0db8)
+        }
+    }
+}
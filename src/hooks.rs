@@ -0,0 +1,477 @@
+//! 连接钩子模块
+//!
+//! 订阅事件总线上的认证成功 / 失败与连接关闭事件，按 `hooks` 配置执行外部命令和 / 或调用
+//! HTTP 回调，把客户端 IP 与判定结果交给 ipset、CrowdSec、fail2ban 等外部工具处理。
+//! 命令不经过 shell，占位符只在单个参数内替换，客户端数据不会被解释为命令或拆分为多个参数
+
+use crate::accounting::Webhook;
+use crate::config::{HookConfig, HookVerdict};
+use crate::events::Event;
+use anyhow::Result;
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::process::Stdio;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::{broadcast, Semaphore};
+use tracing::{debug, warn};
+use uuid::Uuid;
+
+/// 命令模板可用的占位符
+pub const PLACEHOLDERS: &[&str] = &["ip", "port", "verdict", "uuid", "user"];
+
+/// 每个钩子同时执行的命令 / 回调上限，超出时丢弃事件
+const MAX_CONCURRENT: usize = 16;
+
+/// 冷却记录达到该数量时清理过期条目
+const MAX_COOLDOWN_ENTRIES: usize = 4096;
+
+/// 每分钟触发次数的统计窗口
+const RATE_WINDOW: Duration = Duration::from_secs(60);
+
+/// 交给钩子的连接事件（回调请求体）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct HookEvent {
+    pub verdict: HookVerdict,
+    /// 客户端 IP（IPv4 映射的 IPv6 地址还原为 IPv4）
+    pub ip: IpAddr,
+    pub port: u16,
+    pub uuid: Uuid,
+    /// 用户（邮箱或 UUID；认证失败时为客户端发送的 UUID）
+    pub user: String,
+    /// 事件时间（Unix 时间戳，秒）
+    pub timestamp: u64,
+}
+
+impl HookEvent {
+    pub fn new(verdict: HookVerdict, client_addr: SocketAddr, uuid: Uuid, user: String) -> Self {
+        Self {
+            verdict,
+            ip: client_addr.ip().to_canonical(),
+            port: client_addr.port(),
+            uuid,
+            user,
+            timestamp: unix_now(),
+        }
+    }
+
+    /// 从总线事件提取（与钩子无关的事件返回 None）
+    pub fn from_event(event: &Event) -> Option<Self> {
+        match event {
+            Event::ConnectionOpened {
+                client_addr,
+                uuid,
+                user,
+                ..
+            } => Some(Self::new(
+                HookVerdict::AuthSuccess,
+                *client_addr,
+                *uuid,
+                user.clone(),
+            )),
+            Event::AuthFailed { client_addr, uuid } => Some(Self::new(
+                HookVerdict::AuthFailure,
+                *client_addr,
+                *uuid,
+                uuid.to_string(),
+            )),
+            Event::ConnectionClosed {
+                client_addr,
+                uuid,
+                user,
+            } => Some(Self::new(
+                HookVerdict::Closed,
+                *client_addr,
+                *uuid,
+                user.clone(),
+            )),
+            Event::SessionClosed(_) | Event::DestinationBlocked { .. } => None,
+        }
+    }
+
+    /// 占位符对应的值
+    fn field(&self, name: &str) -> String {
+        match name {
+            "ip" => self.ip.to_string(),
+            "port" => self.port.to_string(),
+            "verdict" => self.verdict.as_str().to_string(),
+            "uuid" => self.uuid.to_string(),
+            _ => self.user.clone(),
+        }
+    }
+}
+
+/// 命令参数片段
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Segment {
+    Text(String),
+    Field(&'static str),
+}
+
+/// 命令模板：程序路径固定，参数中的占位符按事件替换
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandTemplate {
+    program: String,
+    args: Vec<Vec<Segment>>,
+}
+
+impl CommandTemplate {
+    /// 解析命令模板：`{名称}`（小写字母与下划线）为占位符，必须是已知占位符，其余花括号原样保留；
+    /// 程序路径不能包含占位符
+    pub fn parse(exec: &[String]) -> Result<Self> {
+        let (program, args) = exec
+            .split_first()
+            .ok_or_else(|| anyhow::anyhow!("command must not be empty"))?;
+        if program.trim().is_empty() {
+            return Err(anyhow::anyhow!("program must not be empty"));
+        }
+        if parse_segments(program)?
+            .iter()
+            .any(|segment| matches!(segment, Segment::Field(_)))
+        {
+            return Err(anyhow::anyhow!(
+                "program must not contain placeholders: {}",
+                program
+            ));
+        }
+        let args = args
+            .iter()
+            .map(|arg| parse_segments(arg))
+            .collect::<Result<_>>()?;
+        Ok(Self {
+            program: program.clone(),
+            args,
+        })
+    }
+
+    pub fn program(&self) -> &str {
+        &self.program
+    }
+
+    /// 按事件展开参数（模板中的每个参数对应一个实际参数）
+    pub fn args(&self, event: &HookEvent) -> Vec<String> {
+        self.args
+            .iter()
+            .map(|segments| {
+                segments
+                    .iter()
+                    .map(|segment| match segment {
+                        Segment::Text(text) => text.clone(),
+                        Segment::Field(name) => event.field(name),
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// 把参数拆分为文本与占位符
+fn parse_segments(text: &str) -> Result<Vec<Segment>> {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('{') {
+        let after = &rest[start + 1..];
+        let name_len = after
+            .find(|c: char| !(c.is_ascii_lowercase() || c == '_'))
+            .unwrap_or(after.len());
+        if name_len == 0 || !after[name_len..].starts_with('}') {
+            literal.push_str(&rest[..=start]);
+            rest = after;
+            continue;
+        }
+        let name = &after[..name_len];
+        let field = PLACEHOLDERS
+            .iter()
+            .find(|placeholder| **placeholder == name)
+            .ok_or_else(|| {
+                let known: Vec<String> =
+                    PLACEHOLDERS.iter().map(|p| format!("{{{}}}", p)).collect();
+                anyhow::anyhow!(
+                    "unknown placeholder {{{}}}, expected one of {}",
+                    name,
+                    known.join(", ")
+                )
+            })?;
+        literal.push_str(&rest[..start]);
+        if !literal.is_empty() {
+            segments.push(Segment::Text(std::mem::take(&mut literal)));
+        }
+        segments.push(Segment::Field(field));
+        rest = &after[name_len + 1..];
+    }
+    literal.push_str(rest);
+    if !literal.is_empty() {
+        segments.push(Segment::Text(literal));
+    }
+    Ok(segments)
+}
+
+/// 触发频率限制：同一客户端 IP 的同一判定在冷却期内只触发一次，并限制每分钟总次数
+#[derive(Debug)]
+pub struct RateLimiter {
+    cooldown: Duration,
+    max_per_minute: u32,
+    recent: HashMap<(HookVerdict, IpAddr), Instant>,
+    window_start: Option<Instant>,
+    window_count: u32,
+}
+
+impl RateLimiter {
+    /// `cooldown` 为零或 `max_per_minute` 为 0 时不做对应限制
+    pub fn new(cooldown: Duration, max_per_minute: u32) -> Self {
+        Self {
+            cooldown,
+            max_per_minute,
+            recent: HashMap::new(),
+            window_start: None,
+            window_count: 0,
+        }
+    }
+
+    /// 是否允许在 `now` 触发，允许时计入冷却与次数
+    pub fn check(&mut self, verdict: HookVerdict, ip: IpAddr, now: Instant) -> bool {
+        let cooling = !self.cooldown.is_zero();
+        if cooling
+            && self
+                .recent
+                .get(&(verdict, ip))
+                .is_some_and(|last| now.duration_since(*last) < self.cooldown)
+        {
+            return false;
+        }
+        if self.max_per_minute > 0 {
+            match self.window_start {
+                Some(start) if now.duration_since(start) < RATE_WINDOW => {
+                    if self.window_count >= self.max_per_minute {
+                        return false;
+                    }
+                }
+                _ => {
+                    self.window_start = Some(now);
+                    self.window_count = 0;
+                }
+            }
+            self.window_count += 1;
+        }
+        if cooling {
+            if self.recent.len() >= MAX_COOLDOWN_ENTRIES {
+                let cooldown = self.cooldown;
+                self.recent
+                    .retain(|_, last| now.duration_since(*last) < cooldown);
+            }
+            self.recent.insert((verdict, ip), now);
+        }
+        true
+    }
+}
+
+/// 单个钩子的动作
+struct HookAction {
+    index: usize,
+    command: Option<CommandTemplate>,
+    webhook: Option<Webhook>,
+    timeout: Duration,
+}
+
+impl HookAction {
+    /// 执行命令与回调，全部成功时返回 true
+    async fn run(&self, event: HookEvent) -> bool {
+        let mut ok = true;
+        if let Some(ref command) = self.command {
+            match run_command(command, &event, self.timeout).await {
+                Ok(()) => debug!(
+                    "Hook {} ran {} for {} {}",
+                    self.index,
+                    command.program(),
+                    event.verdict.as_str(),
+                    event.ip
+                ),
+                Err(e) => {
+                    warn!(
+                        "Hook {} command {} failed: {}",
+                        self.index,
+                        command.program(),
+                        e
+                    );
+                    ok = false;
+                }
+            }
+        }
+        if let Some(ref webhook) = self.webhook {
+            let result = match serde_json::to_string(&event) {
+                Ok(body) => webhook.post(body).await,
+                Err(e) => Err(e.into()),
+            };
+            match result {
+                Ok(()) => debug!("Hook {} event delivered to {}", self.index, webhook.url),
+                Err(e) => {
+                    warn!("Hook {} webhook {} failed: {}", self.index, webhook.url, e);
+                    ok = false;
+                }
+            }
+        }
+        ok
+    }
+}
+
+/// 执行命令（不经过 shell），超时后终止进程
+async fn run_command(
+    command: &CommandTemplate,
+    event: &HookEvent,
+    timeout: Duration,
+) -> Result<()> {
+    let mut child = tokio::process::Command::new(command.program())
+        .args(command.args(event))
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .kill_on_drop(true)
+        .spawn()?;
+    let status = tokio::time::timeout(timeout, child.wait())
+        .await
+        .map_err(|_| anyhow::anyhow!("timed out after {}s, killed", timeout.as_secs()))??;
+    if !status.success() {
+        return Err(anyhow::anyhow!("exited with {}", status));
+    }
+    Ok(())
+}
+
+/// 单个钩子的运行状态
+struct HookState {
+    on: Vec<HookVerdict>,
+    limiter: RateLimiter,
+    permits: Arc<Semaphore>,
+    action: Arc<HookAction>,
+}
+
+/// 钩子触发计数
+#[derive(Debug, Default)]
+struct HookCounters {
+    triggered: AtomicU64,
+    suppressed: AtomicU64,
+    failed: AtomicU64,
+}
+
+/// 连接钩子
+#[derive(Debug)]
+pub struct Hooks {
+    counters: Arc<HookCounters>,
+}
+
+impl Hooks {
+    /// 按配置启动后台任务，消费 `events` 中的认证与连接关闭事件
+    pub fn spawn(configs: &[HookConfig], events: broadcast::Receiver<Event>) -> Result<Self> {
+        let mut hooks = Vec::with_capacity(configs.len());
+        for (index, config) in configs.iter().enumerate() {
+            let command = if config.exec.is_empty() {
+                None
+            } else {
+                Some(CommandTemplate::parse(&config.exec)?)
+            };
+            let timeout = Duration::from_secs(config.timeout);
+            let webhook = config
+                .webhook_url
+                .as_deref()
+                .map(|url| Webhook::new(url, config.webhook_token.clone(), timeout))
+                .transpose()?;
+            hooks.push(HookState {
+                on: config.on.clone(),
+                limiter: RateLimiter::new(
+                    Duration::from_secs(config.cooldown),
+                    config.max_per_minute,
+                ),
+                permits: Arc::new(Semaphore::new(MAX_CONCURRENT)),
+                action: Arc::new(HookAction {
+                    index,
+                    command,
+                    webhook,
+                    timeout,
+                }),
+            });
+        }
+
+        let counters = Arc::new(HookCounters::default());
+        tokio::spawn(run_hooks(events, hooks, Arc::clone(&counters)));
+        Ok(Self { counters })
+    }
+
+    /// 已触发的次数
+    pub fn triggered_count(&self) -> u64 {
+        self.counters.triggered.load(Ordering::Relaxed)
+    }
+
+    /// 因频率限制、并发上限或处理跟不上而未触发的次数
+    pub fn suppressed_count(&self) -> u64 {
+        self.counters.suppressed.load(Ordering::Relaxed)
+    }
+
+    /// 命令或回调失败的次数
+    pub fn failed_count(&self) -> u64 {
+        self.counters.failed.load(Ordering::Relaxed)
+    }
+}
+
+/// 后台分发循环：按判定与频率限制选出钩子，在独立任务中执行
+async fn run_hooks(
+    mut events: broadcast::Receiver<Event>,
+    mut hooks: Vec<HookState>,
+    counters: Arc<HookCounters>,
+) {
+    loop {
+        let event = match events.recv().await {
+            Ok(event) => match HookEvent::from_event(&event) {
+                Some(event) => event,
+                None => continue,
+            },
+            Err(broadcast::error::RecvError::Lagged(n)) => {
+                counters.suppressed.fetch_add(n, Ordering::Relaxed);
+                warn!("Hooks lagged behind, {} events dropped", n);
+                continue;
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        };
+
+        let now = Instant::now();
+        for hook in hooks.iter_mut() {
+            if !hook.on.contains(&event.verdict) {
+                continue;
+            }
+            let permit = if hook.limiter.check(event.verdict, event.ip, now) {
+                Arc::clone(&hook.permits).try_acquire_owned().ok()
+            } else {
+                None
+            };
+            let Some(permit) = permit else {
+                counters.suppressed.fetch_add(1, Ordering::Relaxed);
+                debug!(
+                    "Hook {} suppressed {} for {}",
+                    hook.action.index,
+                    event.verdict.as_str(),
+                    event.ip
+                );
+                continue;
+            };
+            counters.triggered.fetch_add(1, Ordering::Relaxed);
+            let action = Arc::clone(&hook.action);
+            let counters = Arc::clone(&counters);
+            let event = event.clone();
+            tokio::spawn(async move {
+                if !action.run(event).await {
+                    counters.failed.fetch_add(1, Ordering::Relaxed);
+                }
+                drop(permit);
+            });
+        }
+    }
+}
+
+/// 当前 Unix 时间戳（秒）
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or(0)
+}
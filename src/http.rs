@@ -2,6 +2,7 @@
 //!
 //! 用于区分 HTTP 请求和 VLESS 协议请求，并构建 HTTP 响应
 
+use crate::tr;
 use std::collections::HashMap;
 
 /// 检测数据是否为 HTTP 请求（支持 HTTP/1.x 和 HTTP/2）
@@ -30,6 +31,8 @@ pub fn is_http_request(data: &[u8]) -> bool {
 /// HTTP 查询参数
 #[derive(Debug, Clone)]
 pub struct HttpQuery {
+    /// 请求方法（GET、POST 等）
+    pub method: String,
     /// 请求路径
     pub path: String,
     /// 查询参数
@@ -79,6 +82,7 @@ pub fn parse_http_request(data: &[u8]) -> Option<HttpQuery> {
     }
 
     Some(HttpQuery {
+        method: parts[0].to_string(),
         path: path.to_string(),
         params,
     })
@@ -109,22 +113,134 @@ pub fn build_json_response(json: &str) -> Vec<u8> {
     build_response(200, "OK", "application/json; charset=utf-8", json)
 }
 
+/// 构建 503 JSON 响应（服务降级）
+pub fn build_503_json_response(json: &str) -> Vec<u8> {
+    build_response(
+        503,
+        "Service Unavailable",
+        "application/json; charset=utf-8",
+        json,
+    )
+}
+
 /// 构建 HTML 响应
 #[allow(dead_code)]
 pub fn build_html_response(html: &str) -> Vec<u8> {
     build_response(200, "OK", "text/html; charset=utf-8", html)
 }
 
+/// API 错误码（稳定的机器可读标识，不随界面语言变化）
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorCode {
+    /// 请求无法解析
+    BadRequest,
+    /// 令牌缺失或错误
+    Unauthorized,
+    /// 路径或资源不存在
+    NotFound,
+    /// 请求方法不被该接口支持
+    MethodNotAllowed,
+    /// 查询参数无效
+    InvalidParameter,
+    /// 功能未启用（缺少对应配置）
+    FeatureDisabled,
+    /// 磁盘上的配置无效
+    ConfigInvalid,
+    /// 抓包规则被拒绝（数量上限、目录不可写）
+    CaptureRejected,
+    /// 按邮箱查询时用户不存在
+    UserNotFound,
+    /// 当前传输方式没有对应链接
+    LinkUnavailable,
+}
+
+impl ErrorCode {
+    /// 错误码字符串
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::BadRequest => "bad_request",
+            Self::Unauthorized => "unauthorized",
+            Self::NotFound => "not_found",
+            Self::MethodNotAllowed => "method_not_allowed",
+            Self::InvalidParameter => "invalid_parameter",
+            Self::FeatureDisabled => "feature_disabled",
+            Self::ConfigInvalid => "config_invalid",
+            Self::CaptureRejected => "capture_rejected",
+            Self::UserNotFound => "user_not_found",
+            Self::LinkUnavailable => "link_unavailable",
+        }
+    }
+
+    /// 对应的 HTTP 状态码与原因短语
+    pub fn status(&self) -> (u16, &'static str) {
+        match self {
+            Self::Unauthorized => (401, "Unauthorized"),
+            Self::NotFound | Self::UserNotFound | Self::LinkUnavailable => (404, "Not Found"),
+            Self::MethodNotAllowed => (405, "Method Not Allowed"),
+            Self::CaptureRejected => (409, "Conflict"),
+            Self::BadRequest
+            | Self::InvalidParameter
+            | Self::FeatureDisabled
+            | Self::ConfigInvalid => (400, "Bad Request"),
+        }
+    }
+}
+
+/// API 错误：响应体为 `{"success": false, "code", "message", "details"}`
+#[derive(Debug, Clone)]
+pub struct ApiError {
+    pub code: ErrorCode,
+    /// 人类可读的说明（固定错误按界面语言输出）
+    pub message: String,
+    /// 附加信息，没有时为 null
+    pub details: serde_json::Value,
+}
+
+impl ApiError {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Self {
+            code,
+            message: message.into(),
+            details: serde_json::Value::Null,
+        }
+    }
+
+    /// 附加结构化信息
+    pub fn with_details(mut self, details: serde_json::Value) -> Self {
+        self.details = details;
+        self
+    }
+
+    /// 响应体 JSON
+    pub fn body(&self) -> serde_json::Value {
+        serde_json::json!({
+            "success": false,
+            "code": self.code.as_str(),
+            "message": self.message,
+            "details": self.details,
+        })
+    }
+
+    /// 完整 HTTP 响应
+    pub fn to_response(&self) -> Vec<u8> {
+        let (status, status_text) = self.code.status();
+        build_response(
+            status,
+            status_text,
+            "application/json; charset=utf-8",
+            &self.body().to_string(),
+        )
+    }
+}
+
 /// 构建 404 响应
 pub fn build_404_response() -> Vec<u8> {
-    let body = r#"{"success":false,"error":"Not Found"}"#;
-    build_response(404, "Not Found", "application/json; charset=utf-8", body)
+    ApiError::new(ErrorCode::NotFound, tr!("未找到", "Not Found")).to_response()
 }
 
 /// 构建 400 响应
 pub fn build_400_response(error: &str) -> Vec<u8> {
-    let body = format!(r#"{{"success":false,"error":"{}"}}"#, error);
-    build_response(400, "Bad Request", "application/json; charset=utf-8", &body)
+    ApiError::new(ErrorCode::BadRequest, error).to_response()
 }
 
 /// 从 HTTP 请求数据中提取请求路径
@@ -178,6 +294,20 @@ pub fn extract_header_value(headers: &[u8], header_name: &str) -> Option<String>
     None
 }
 
+/// 判断 Host 请求头是否与期望的主机名一致
+///
+/// 忽略大小写与端口部分，支持 `[::1]:8080` 形式的 IPv6 地址
+pub fn host_matches(host_header: &str, expected: &str) -> bool {
+    let host = host_header.trim();
+    let host = if let Some(rest) = host.strip_prefix('[') {
+        rest.split(']').next().unwrap_or("")
+    } else {
+        host.rsplit_once(':').map_or(host, |(h, _)| h)
+    };
+    host.trim_end_matches('.')
+        .eq_ignore_ascii_case(expected.trim_end_matches('.'))
+}
+
 /// 验证 HTTP 请求头的基本安全性
 ///
 /// 检查 Content-Length 是否过大
@@ -0,0 +1,82 @@
+//! 界面语言模块
+//!
+//! 配置向导、启动横幅、信息页与 API 固定错误信息按当前语言输出；
+//! 运行日志保持英文，便于检索
+
+use serde::{Deserialize, Serialize};
+use std::sync::atomic::{AtomicU8, Ordering};
+
+/// 界面语言
+#[derive(Debug, Serialize, Deserialize, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Language {
+    /// 简体中文
+    Zh = 1,
+    /// English
+    En = 0,
+}
+
+/// 当前语言（0 = En，1 = Zh），未初始化时为英文
+static CURRENT: AtomicU8 = AtomicU8::new(0);
+
+impl Language {
+    /// 解析语言标识：`zh`、`zh_CN.UTF-8`、`en`、`en_US` 等
+    pub fn parse(value: &str) -> Option<Self> {
+        let value = value.trim().to_ascii_lowercase();
+        if value.starts_with("zh") {
+            Some(Self::Zh)
+        } else if value.starts_with("en") {
+            Some(Self::En)
+        } else {
+            None
+        }
+    }
+
+    /// 按优先级确定语言：`VLESS_LANG` > 配置 `language` > `LC_ALL` / `LC_MESSAGES` / `LANG`
+    ///
+    /// 系统区域为 zh* 时使用中文，其余已设置的区域（含 `C`）使用英文；
+    /// 均未设置时 Windows 使用中文，其他平台使用英文
+    pub fn detect(configured: Option<Language>) -> Self {
+        if let Some(lang) = std::env::var("VLESS_LANG")
+            .ok()
+            .and_then(|v| Self::parse(&v))
+        {
+            return lang;
+        }
+        if let Some(lang) = configured {
+            return lang;
+        }
+        let locale = ["LC_ALL", "LC_MESSAGES", "LANG"]
+            .iter()
+            .filter_map(|name| std::env::var(name).ok())
+            .find(|value| !value.is_empty());
+        match locale {
+            Some(locale) => Self::parse(&locale).unwrap_or(Self::En),
+            None if cfg!(windows) => Self::Zh,
+            None => Self::En,
+        }
+    }
+}
+
+/// 设置当前语言
+pub fn set_language(lang: Language) {
+    CURRENT.store(lang as u8, Ordering::Relaxed);
+}
+
+/// 当前是否为中文
+pub fn is_zh() -> bool {
+    CURRENT.load(Ordering::Relaxed) == Language::Zh as u8
+}
+
+/// 按当前语言选择文本：`tr!("中文", "English")`，
+/// 可带格式参数：`tr!("共 {} 个用户", "{} users", n)`
+#[macro_export]
+macro_rules! tr {
+    ($zh:literal, $en:literal $(, $arg:expr)* $(,)?) => {
+        if $crate::i18n::is_zh() {
+            format!($zh $(, $arg)*)
+        } else {
+            format!($en $(, $arg)*)
+        }
+    };
+}
@@ -2,21 +2,59 @@
 //!
 //! 提供 VLESS 协议服务器核心功能
 
+pub mod accounting;
 pub mod address;
 pub mod api;
 pub mod atomic_write;
+pub mod blocklist;
+pub mod capture;
 pub mod config;
+pub mod config_diff;
+pub mod crowdsec;
+pub mod ddns;
+pub mod decoy;
+pub mod destinations;
+pub mod dns;
+pub mod doctor;
+pub mod events;
+pub mod fallback;
+pub mod forward;
+pub mod geoip;
+pub mod hooks;
 pub mod http;
+pub mod i18n;
+pub mod limiter;
+pub mod local_proxy;
+pub mod maintenance;
+pub mod mux;
+pub mod outbound;
+pub mod outbound_tls;
+pub mod overhead;
+pub mod port_mapping;
 pub mod protocol;
 pub mod public_ip;
+pub mod readiness;
+pub mod restart;
+pub mod route_stats;
+pub mod router;
+pub mod runtime_stats;
+pub mod secrets;
 pub mod server;
+pub mod session;
+pub mod sni_proxy;
+pub mod sniffer;
+pub mod socks;
 pub mod socket;
+pub mod stall;
+pub mod stats;
 pub mod tcp;
 pub mod tui;
+pub mod udp_session;
 pub mod version;
 pub mod vless_link;
 pub mod wizard;
 pub mod ws;
+pub mod xray_import;
 
 // service 模块仅在二进制目标中可用
 // 注意：main.rs 中的模块声明会覆盖这里
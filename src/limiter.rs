@@ -0,0 +1,85 @@
+//! 全局会话并发限制模块
+//!
+//! 限制同时处理的连接数（与单个用户无关）：超过上限的连接进入有限长度的
+//! 等待队列，队列已满或等待超时时直接关闭（服务繁忙），负载突增时内存占用可控
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+
+/// 会话并发统计
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionLimitStats {
+    /// 正在处理的会话数
+    pub active: usize,
+    /// 并发上限
+    pub max: usize,
+    /// 正在排队的连接数
+    pub waiting: usize,
+    /// 因服务繁忙被拒绝的连接总数
+    pub rejected: u64,
+}
+
+/// 全局会话并发限制
+#[derive(Debug)]
+pub struct SessionLimiter {
+    semaphore: Arc<Semaphore>,
+    max: usize,
+    queue: usize,
+    timeout: Duration,
+    waiting: AtomicUsize,
+    rejected: AtomicU64,
+}
+
+impl SessionLimiter {
+    /// `max` 为并发上限，`queue` 为等待队列长度（0 表示不排队），`timeout` 为排队上限时间
+    pub fn new(max: usize, queue: usize, timeout: Duration) -> Self {
+        Self {
+            semaphore: Arc::new(Semaphore::new(max)),
+            max,
+            queue,
+            timeout,
+            waiting: AtomicUsize::new(0),
+            rejected: AtomicU64::new(0),
+        }
+    }
+
+    /// 获取会话许可，持有期间计入并发数；服务繁忙时返回 None
+    pub async fn acquire(&self) -> Option<OwnedSemaphorePermit> {
+        if let Ok(permit) = Arc::clone(&self.semaphore).try_acquire_owned() {
+            return Some(permit);
+        }
+        // 先占用队列位置，避免并发连接同时越过队列上限
+        let queued = self
+            .waiting
+            .fetch_update(Ordering::AcqRel, Ordering::Acquire, |n| {
+                (n < self.queue).then_some(n + 1)
+            })
+            .is_ok();
+        let permit = if queued {
+            let result =
+                tokio::time::timeout(self.timeout, Arc::clone(&self.semaphore).acquire_owned())
+                    .await;
+            self.waiting.fetch_sub(1, Ordering::AcqRel);
+            result.ok().and_then(Result::ok)
+        } else {
+            None
+        };
+        if permit.is_none() {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+        }
+        permit
+    }
+
+    /// 当前统计
+    pub fn stats(&self) -> SessionLimitStats {
+        SessionLimitStats {
+            active: self.max - self.semaphore.available_permits(),
+            max: self.max,
+            waiting: self.waiting.load(Ordering::Relaxed),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
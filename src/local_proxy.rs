@@ -0,0 +1,278 @@
+//! 本地代理入站模块（客户端模式）
+//!
+//! 在本机监听 SOCKS5 或 HTTP 代理端口，把应用的连接请求经出站（VLESS / SOCKS5 / HTTP 上游）转发。
+//! SOCKS5 只支持无认证的 CONNECT；HTTP 支持 CONNECT 隧道与绝对 URI 的普通请求
+//! （请求行改写为相对路径后转发，同一连接上的后续请求发往同一目标）
+
+use crate::config::{LocalProxyConfig, LocalProxyProtocol, PerformanceConfig};
+use crate::forward::parse_dest;
+use crate::outbound::Outbound;
+use crate::runtime_stats::TaskCounter;
+use crate::socks;
+use anyhow::{anyhow, Result};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+/// HTTP 代理请求头上限
+const MAX_HTTP_HEADER: usize = 8192;
+
+/// 已绑定的本地代理入站
+#[derive(Debug)]
+pub struct LocalProxy {
+    protocol: LocalProxyProtocol,
+    listener: TcpListener,
+    outbound: Arc<Outbound>,
+    perf_config: PerformanceConfig,
+    tasks: Arc<TaskCounter>,
+}
+
+impl LocalProxy {
+    /// 绑定监听端口
+    pub async fn bind(
+        config: &LocalProxyConfig,
+        outbound: Arc<Outbound>,
+        perf_config: PerformanceConfig,
+    ) -> Result<Self> {
+        let listen: SocketAddr = config
+            .listen
+            .parse()
+            .map_err(|_| anyhow!("Invalid local proxy listen address: {}", config.listen))?;
+        let listener = TcpListener::bind(listen).await?;
+        Ok(Self {
+            protocol: config.protocol,
+            listener,
+            outbound,
+            perf_config,
+            tasks: Arc::default(),
+        })
+    }
+
+    /// 设置连接任务计数器（供 `/api/runtime`）
+    pub fn with_task_counter(mut self, tasks: Arc<TaskCounter>) -> Self {
+        self.tasks = tasks;
+        self
+    }
+
+    /// 监听地址
+    pub fn local_addr(&self) -> Option<SocketAddr> {
+        self.listener.local_addr().ok()
+    }
+
+    /// 启动接收任务（中止返回的任务即停止监听）
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move {
+            let perf_config = Arc::new(self.perf_config);
+            loop {
+                let (stream, client_addr) = match self.listener.accept().await {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        warn!("Local proxy accept failed: {}", e);
+                        continue;
+                    }
+                };
+                let outbound = Arc::clone(&self.outbound);
+                let perf_config = Arc::clone(&perf_config);
+                let protocol = self.protocol;
+                let task = self.tasks.enter();
+                tokio::spawn(async move {
+                    let _task = task;
+                    let result = match protocol {
+                        LocalProxyProtocol::Socks => {
+                            handle_socks(stream, &outbound, &perf_config).await
+                        }
+                        LocalProxyProtocol::Http => {
+                            handle_http(stream, &outbound, &perf_config).await
+                        }
+                    };
+                    if let Err(e) = result {
+                        debug!("Local proxy request from {} failed: {}", client_addr, e);
+                    }
+                });
+            }
+        })
+    }
+}
+
+/// SOCKS5 应答（绑定地址固定为 0.0.0.0:0）
+async fn socks_reply(stream: &mut TcpStream, rep: u8) -> Result<()> {
+    stream
+        .write_all(&[socks::VERSION, rep, 0, socks::ATYP_IPV4, 0, 0, 0, 0, 0, 0])
+        .await?;
+    Ok(())
+}
+
+/// 处理 SOCKS5 连接
+async fn handle_socks(
+    mut stream: TcpStream,
+    outbound: &Outbound,
+    perf_config: &PerformanceConfig,
+) -> Result<()> {
+    // 方法协商
+    let mut header = [0u8; 2];
+    stream.read_exact(&mut header).await?;
+    if header[0] != socks::VERSION {
+        return Err(anyhow!("Unsupported SOCKS version: {}", header[0]));
+    }
+    let mut methods = vec![0u8; header[1] as usize];
+    stream.read_exact(&mut methods).await?;
+    if !methods.contains(&socks::NO_AUTH) {
+        stream
+            .write_all(&[socks::VERSION, socks::NO_ACCEPTABLE])
+            .await?;
+        return Err(anyhow!("SOCKS client does not offer no-auth method"));
+    }
+    stream.write_all(&[socks::VERSION, socks::NO_AUTH]).await?;
+
+    // 请求：VER CMD RSV ATYP DST.ADDR DST.PORT
+    let mut request = [0u8; 4];
+    stream.read_exact(&mut request).await?;
+    let Some((address, port)) = socks::read_address(&mut stream, request[3]).await? else {
+        socks_reply(&mut stream, socks::REP_ATYP_UNSUPPORTED).await?;
+        return Err(anyhow!("Unsupported SOCKS address type: {}", request[3]));
+    };
+    if request[1] != socks::CMD_CONNECT {
+        socks_reply(&mut stream, socks::REP_CMD_UNSUPPORTED).await?;
+        return Err(anyhow!("Unsupported SOCKS command: {}", request[1]));
+    }
+
+    let upstream = match outbound.connect(&address, port, &[], perf_config).await {
+        Ok(upstream) => upstream,
+        Err(e) => {
+            socks_reply(&mut stream, socks::REP_FAILURE).await?;
+            return Err(e);
+        }
+    };
+    socks_reply(&mut stream, socks::REP_SUCCESS).await?;
+    outbound.relay(stream, upstream).await;
+    Ok(())
+}
+
+/// 读取 HTTP 请求头，返回（请求头，请求头之后已读到的数据）
+async fn read_http_head(stream: &mut TcpStream) -> Result<(Vec<u8>, Vec<u8>)> {
+    let mut buf = Vec::with_capacity(1024);
+    let mut chunk = [0u8; 1024];
+    loop {
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Err(anyhow!("Connection closed before HTTP request header"));
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if let Some(end) = buf.windows(4).position(|w| w == b"\r\n\r\n") {
+            let rest = buf.split_off(end + 4);
+            return Ok((buf, rest));
+        }
+        if buf.len() > MAX_HTTP_HEADER {
+            return Err(anyhow!("HTTP proxy request header too large"));
+        }
+    }
+}
+
+/// 把绝对 URI 的请求改写为发往源站的请求：请求行使用相对路径，去掉 `Proxy-*` 头
+///
+/// 返回（目标 `host:port`，改写后的请求头）
+pub fn rewrite_http_request(head: &[u8]) -> Result<(String, Vec<u8>)> {
+    let text = std::str::from_utf8(head).map_err(|_| anyhow!("Invalid HTTP request header"))?;
+    let (request_line, headers) = text
+        .split_once("\r\n")
+        .ok_or_else(|| anyhow!("Invalid HTTP request header"))?;
+    let mut parts = request_line.split(' ');
+    let (Some(method), Some(uri), Some(version)) = (parts.next(), parts.next(), parts.next())
+    else {
+        return Err(anyhow!("Invalid HTTP request line: {}", request_line));
+    };
+    let rest = uri
+        .strip_prefix("http://")
+        .ok_or_else(|| anyhow!("HTTP proxy requires an absolute http:// URI: {}", uri))?;
+    let (authority, path) = match rest.find('/') {
+        Some(index) => rest.split_at(index),
+        None => (rest, "/"),
+    };
+    if authority.is_empty() {
+        return Err(anyhow!("HTTP proxy URI has no host: {}", uri));
+    }
+    let dest = if authority
+        .rsplit_once(':')
+        .is_some_and(|(_, port)| !port.is_empty() && port.bytes().all(|b| b.is_ascii_digit()))
+    {
+        authority.to_string()
+    } else {
+        format!("{}:80", authority)
+    };
+
+    let mut rewritten = format!("{} {} {}\r\n", method, path, version);
+    for line in headers.split("\r\n") {
+        let is_proxy_header = line
+            .split_once(':')
+            .is_some_and(|(name, _)| name.trim().to_ascii_lowercase().starts_with("proxy-"));
+        if !is_proxy_header && !line.is_empty() {
+            rewritten.push_str(line);
+            rewritten.push_str("\r\n");
+        }
+    }
+    rewritten.push_str("\r\n");
+    Ok((dest, rewritten.into_bytes()))
+}
+
+/// 处理 HTTP 代理连接
+async fn handle_http(
+    mut stream: TcpStream,
+    outbound: &Outbound,
+    perf_config: &PerformanceConfig,
+) -> Result<()> {
+    let (head, rest) = read_http_head(&mut stream).await?;
+
+    if head.starts_with(b"CONNECT ") {
+        let authority = head[8..]
+            .split(|&b| b == b' ')
+            .next()
+            .and_then(|a| std::str::from_utf8(a).ok())
+            .unwrap_or_default()
+            .to_string();
+        let (address, port) = parse_dest(&authority)?;
+        let upstream = match outbound.connect(&address, port, &rest, perf_config).await {
+            Ok(upstream) => upstream,
+            Err(e) => {
+                stream
+                    .write_all(b"HTTP/1.1 502 Bad Gateway\r\nContent-Length: 0\r\n\r\n")
+                    .await?;
+                return Err(e);
+            }
+        };
+        stream
+            .write_all(b"HTTP/1.1 200 Connection Established\r\n\r\n")
+            .await?;
+        outbound.relay(stream, upstream).await;
+        return Ok(());
+    }
+
+    let (dest, mut request) = match rewrite_http_request(&head) {
+        Ok(rewritten) => rewritten,
+        Err(e) => {
+            stream
+                .write_all(b"HTTP/1.1 400 Bad Request\r\nContent-Length: 0\r\n\r\n")
+                .await?;
+            return Err(e);
+        }
+    };
+    debug!("HTTP proxy request to {}", dest);
+    let (address, port) = parse_dest(&dest)?;
+    request.extend_from_slice(&rest);
+    let upstream = match outbound
+        .connect(&address, port, &request, perf_config)
+        .await
+    {
+        Ok(upstream) => upstream,
+        Err(e) => {
+            stream
+                .write_all(b"HTTP/1.1 502 Bad Gateway\r\nContent-Length: 0\r\n\r\n")
+                .await?;
+            return Err(e);
+        }
+    };
+    outbound.relay(stream, upstream).await;
+    Ok(())
+}
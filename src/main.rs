@@ -1,32 +1,72 @@
+mod accounting;
 mod address;
 mod api;
 mod atomic_write;
+mod blocklist;
+mod capture;
 mod config;
+mod config_diff;
+mod crowdsec;
+mod ddns;
+mod decoy;
+mod destinations;
+mod dns;
+mod doctor;
+mod events;
+mod fallback;
+mod forward;
+mod geoip;
+mod hooks;
 mod http;
+mod i18n;
+mod limiter;
+mod local_proxy;
+mod maintenance;
+mod mux;
+mod outbound;
+mod outbound_tls;
+mod overhead;
+mod port_mapping;
 mod protocol;
 mod public_ip;
+mod readiness;
+mod restart;
+mod route_stats;
+mod router;
+mod runtime_stats;
+mod secrets;
 mod server;
 mod service;
+mod session;
+mod sni_proxy;
+mod sniffer;
+mod socks;
 mod socket;
+mod stall;
+mod stats;
 mod tcp;
 mod tui;
+mod udp_session;
 mod version;
 mod vless_link;
 mod wizard;
 mod ws;
+mod xray_import;
 
 use crate::config::Config;
-use crate::server::{ServerConfig, VlessServer};
+use crate::server::{DestinationPolicy, ServerConfig, VlessServer};
 
 use anyhow::Result;
 
 use std::env;
 use std::sync::mpsc;
+use std::sync::Arc;
 use std::thread;
 
+#[cfg(not(unix))]
 use tokio::signal;
 
-use tracing::{error, info};
+use tracing::{error, info, warn};
 use tracing_subscriber::util::SubscriberInitExt;
 
 // 使用 mimalloc 作为全局内存分配器，提升内存分配性能
@@ -62,22 +102,179 @@ async fn main() -> Result<()> {
         }
     }
 
+    // 检查 doctor 子命令（自检诊断）
+    if args.get(1).map(String::as_str) == Some("doctor") {
+        let config_path = args
+            .iter()
+            .skip(2)
+            .find(|p| !p.starts_with("--"))
+            .cloned()
+            .unwrap_or_else(|| "config.json".to_string());
+        let results = doctor::run_checks(&config_path).await;
+        if !doctor::print_report(&results) {
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    // 检查 gen-key 子命令（生成配置加密密钥）
+    if args.get(1).map(String::as_str) == Some("gen-key") {
+        println!("{}", secrets::ConfigKey::generate()?.to_base64());
+        return Ok(());
+    }
+
+    // 检查 encrypt-config 子命令（加密配置敏感字段 / 轮换密钥）
+    if args.get(1).map(String::as_str) == Some("encrypt-config") {
+        let new_key_file = args
+            .iter()
+            .position(|a| a == "--new-key-file")
+            .and_then(|i| args.get(i + 1))
+            .cloned();
+        let config_path = args
+            .iter()
+            .skip(2)
+            .filter(|p| Some(*p) != new_key_file.as_ref())
+            .find(|p| !p.starts_with("--"))
+            .cloned()
+            .unwrap_or_else(|| "config.json".to_string());
+        match secrets::encrypt_config_file(&config_path, new_key_file.as_deref()) {
+            Ok(count) => {
+                println!("Encrypted {} values in {}", count, config_path);
+                return Ok(());
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // 检查 import-xray 子命令（从 Xray / v2ray 配置与统计导出迁移）
+    if args.get(1).map(String::as_str) == Some("import-xray") {
+        let stats = flag_value(&args, "--stats");
+        let output = flag_value(&args, "--output");
+        let state_file = flag_value(&args, "--state-file");
+        let flag_values = [&stats, &output, &state_file];
+        let Some(xray_config) = args
+            .iter()
+            .skip(2)
+            .filter(|p| !flag_values.iter().any(|v| v.as_ref() == Some(*p)))
+            .find(|p| !p.starts_with("--"))
+            .cloned()
+        else {
+            eprintln!(
+                "Usage: vless import-xray <xray-config.json> [--stats <stats.json>] [--output <config.json>] [--state-file <path>] [--force]"
+            );
+            std::process::exit(1);
+        };
+        let options = xray_import::ImportOptions {
+            xray_config,
+            stats,
+            output: output.unwrap_or_else(|| "config.json".to_string()),
+            state_file,
+            force: args.iter().any(|a| a == "--force"),
+        };
+        match xray_import::import_files(&options) {
+            Ok(report) => {
+                for warning in &report.warnings {
+                    eprintln!("Warning: {}", warning);
+                }
+                println!(
+                    "Imported {} users from {} VLESS inbounds into {}",
+                    report.users, report.inbounds, options.output
+                );
+                if let Some((users, path)) = report.stats {
+                    println!("Seeded traffic counters for {} users in {}", users, path);
+                }
+                return Ok(());
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    // --dry-run <新配置>：校验新配置并输出与当前配置的差异，不启动服务
+    let dry_run = flag_value(&args, "--dry-run");
+
+    // --banner <text|json|none>：覆盖配置中的横幅格式
+    let banner = flag_value(&args, "--banner");
+    let banner_mode = match banner {
+        Some(ref value) => match config::BannerMode::parse(value) {
+            Some(mode) => Some(mode),
+            None => {
+                eprintln!("Error: --banner expects text, json or none, got {}", value);
+                std::process::exit(1);
+            }
+        },
+        None => None,
+    };
+
+    // --print-info json：输出启动信息报告后退出，不启动服务
+    let print_info = flag_value(&args, "--print-info");
+    if print_info.as_deref().is_some_and(|format| format != "json") {
+        eprintln!("Error: --print-info only supports json");
+        std::process::exit(1);
+    }
+
     // 读取配置文件路径（跳过 args[0]，它是可执行文件路径）
+    let flag_values = [&dry_run, &banner, &print_info];
     let config_path = args
         .iter()
         .skip(1) // 跳过 args[0]（可执行文件路径）
+        .filter(|p| !flag_values.iter().any(|v| v.as_ref() == Some(*p)))
         .find(|p| !p.starts_with("--"))
         .cloned()
         .unwrap_or_else(|| "config.json".to_string());
 
+    if let Some(new_path) = dry_run {
+        let diff = config_diff::load_config_file(&config_path).and_then(|running| {
+            let new = config_diff::load_config_file(&new_path)?;
+            Ok(config_diff::diff_configs(&running, &new))
+        });
+        match diff {
+            Ok(diff) => {
+                print!("{}", diff.render());
+                return Ok(());
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
+    if print_info.is_some() {
+        match print_startup_report(&config_path).await {
+            Ok(report) => {
+                println!("{}", report);
+                return Ok(());
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                std::process::exit(1);
+            }
+        }
+    }
+
     // 检查是否禁用 TUI（可通过 --no-tui 参数或环境变量）
     let use_tui = !args.iter().any(|a| a == "--no-tui") && env::var("DISABLE_TUI").is_err();
 
+    // 界面语言：向导使用环境检测结果，加载配置后按 `language` 重新确定
+    i18n::set_language(i18n::Language::detect(None));
+
     // 加载配置（不输出日志）
-    let (config, config_messages) = match std::fs::read_to_string(&config_path) {
+    let (mut config, config_messages) = match std::fs::read_to_string(&config_path) {
         Ok(content) => {
-            let config = Config::from_json(&content)?;
-            (config, vec![format!("Loading config from {}", config_path)])
+            let mut config = Config::from_json(&content)?;
+            let mut messages = vec![format!("Loading config from {}", config_path)];
+            let key = secrets::ConfigKey::from_env()?;
+            let decrypted = secrets::decrypt_config(&mut config, key.as_ref())?;
+            if decrypted > 0 {
+                messages.push(format!("Decrypted {} encrypted config values", decrypted));
+            }
+            (config, messages)
         }
         Err(_) => {
             let mut messages = vec![
@@ -99,20 +296,42 @@ async fn main() -> Result<()> {
             #[cfg(unix)]
             {
                 // 权限已在原子写入时设置，这里只记录日志
-                messages.push(format!("Config file permissions set to 600 (rw-------)"));
+                messages.push("Config file permissions set to 600 (rw-------)".to_string());
             }
 
             messages.push(format!("Config saved to {}", config_path));
             (config, messages)
         }
     };
+    config.performance.validate()?;
+    config.monitoring.validate()?;
+    config.restart.validate()?;
+    config.sniffing.validate()?;
+    config.resolver.validate()?;
+    config.maintenance.validate()?;
+    config.validate_hooks()?;
+    config.crowdsec.validate()?;
+    config
+        .validate_users()
+        .map_err(|e| anyhow::anyhow!("Invalid config {}: {}", config_path, e))?;
+    i18n::set_language(i18n::Language::detect(config.language));
+    if let Some(mode) = banner_mode {
+        config.output.banner = mode;
+    }
+    if args.iter().any(|a| a == "--hide-links") {
+        config.output.show_links = false;
+    }
 
-    // 获取公网 IP（用于生成 VLESS 链接）
-    let public_ip = match public_ip::fetch_public_ip_with_timeout(5).await {
+    // 获取公网 IP（用于生成 VLESS 链接），配置中指定时跳过探测
+    let public_ip = match public_ip::resolve_public_host(&config.server, 5).await {
         Some(ip) => {
             eprintln!("Public IP detected: {} (from {})", ip.ip, ip.source);
             Some(ip.ip)
         }
+        None if !config.server.detect_public_ip => {
+            eprintln!("Public IP detection disabled, VLESS links will use listen address");
+            None
+        }
         None => {
             eprintln!("Warning: Failed to detect public IP, VLESS links will use listen address");
             None
@@ -140,6 +359,9 @@ async fn main() -> Result<()> {
         public_ip: public_ip.clone(),
     };
 
+    // 记住配置路径与加载时的配置，供 /api/reload?dry_run=true 比较
+    let reload_preview = Arc::new(config_diff::ReloadPreview::new(&config_path, config.clone()));
+
     if use_tui {
         let (log_tx, log_rx) = mpsc::channel();
 
@@ -150,24 +372,33 @@ async fn main() -> Result<()> {
         // 在当前 Runtime 上 spawn 服务器任务，避免创建第二个 Runtime
         let config_clone = config.clone();
         let public_ip_clone = public_ip.clone();
+        let log_history = config.monitoring.log_history;
         let server_handle = tokio::spawn(async move {
-            let _ = run_server(config_clone, Some(shutdown_rx), public_ip_clone).await;
+            let _ = run_server(
+                config_clone,
+                Some(shutdown_rx),
+                public_ip_clone,
+                reload_preview,
+            )
+            .await;
         });
 
         // TUI 在独立线程运行（阻塞式终端 I/O），错误转 String 以满足 Send 约束
         let tui_handle = thread::spawn(move || {
-            run_tui_with_channel(log_rx, &server_status).map_err(|e| e.to_string())
+            run_tui_with_channel(log_rx, &server_status, log_history).map_err(|e| e.to_string())
         });
 
-        let result = tui_handle.join().map_err(|_| anyhow::anyhow!("TUI thread panicked"))?;
+        let result = tui_handle
+            .join()
+            .map_err(|_| anyhow::anyhow!("TUI thread panicked"))?;
 
         let _ = shutdown_tx.send(true);
         let _ = server_handle.await;
 
         result.map_err(|e| anyhow::anyhow!("{}", e))
     } else {
-        // 传统模式：直接打印横幅
-        version::print_banner_with_status(&server_status);
+        // 传统模式：按配置打印横幅
+        version::print_banner(&server_status, config.output.banner);
 
         // 初始化日志（在横幅之后）
         tracing_subscriber::fmt::init();
@@ -180,35 +411,452 @@ async fn main() -> Result<()> {
         info!("Server configuration loaded:");
         info!("  Listen: {}:{}", config.server.listen, config.server.port);
         info!("  Protocol: {:?}", config.server.protocol);
+        if let Some(profile) = config.profile {
+            info!("  Profile: {}", profile.as_str());
+        }
         if config.server.protocol == config::ProtocolType::WebSocket {
             info!("  WS Path: {}", config.server.ws_path);
+            if let Some(ref host) = config.server.ws_host {
+                info!("  WS Host: {}", host);
+            }
         }
         info!("  Users: {}", config.users.len());
 
-        run_server(config, None, public_ip).await
+        run_server(config, None, public_ip, reload_preview).await
     }
 }
 
+/// 读取带参数的命令行选项值（`--flag value`）
+fn flag_value(args: &[String], flag: &str) -> Option<String> {
+    args.iter()
+        .position(|a| a == flag)
+        .and_then(|i| args.get(i + 1))
+        .cloned()
+}
+
+/// 生成启动信息报告（`--print-info json`）
+///
+/// 链接地址与启动时一致：DDNS 域名 / `server.domain` > 公网 IP > 监听地址；
+/// NAT 端口映射需要联系网关，报告中只使用 `server.link_port`
+async fn print_startup_report(config_path: &str) -> Result<String> {
+    let config = config_diff::load_config_file(config_path)?;
+    let public_host = if config.ddns.is_enabled() {
+        Some(
+            config
+                .server
+                .domain
+                .clone()
+                .unwrap_or_else(|| config.ddns.hostname.clone()),
+        )
+    } else {
+        public_ip::resolve_public_host(&config.server, 5)
+            .await
+            .map(|ip| ip.ip)
+    };
+
+    let mut server_config = server::ServerConfig::new(
+        config.bind_addr()?,
+        config.server.protocol,
+        config.server.ws_path.clone(),
+        public_host,
+        config.server.port,
+    )
+    .with_fronting(
+        config.server.ws_host.clone(),
+        config.server.sni.clone(),
+        config.server.link_port,
+    )
+    .with_ws_early_data(config.server.ws_early_data);
+    for user in &config.users {
+        if let Ok(uuid) = uuid::Uuid::parse_str(&user.uuid) {
+            server_config.add_user_with_email(uuid, user.email.clone());
+        }
+    }
+
+    let mut listen = vec![format!("{}:{}", config.server.listen, config.server.port)];
+    listen.extend(config.forwards.iter().map(|f| f.listen.clone()));
+    listen.extend(config.local_proxies.iter().map(|p| p.listen.clone()));
+    Ok(version::startup_report(
+        &config,
+        listen,
+        &server_config.panel_url(),
+        &server_config.user_links(),
+    )
+    .to_string())
+}
+
+/// SIGHUP 触发拦截列表重新下载
+#[cfg(unix)]
+fn spawn_reload_on_sighup(list: Arc<blocklist::Blocklist>) -> Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut sighup = signal(SignalKind::hangup())
+        .map_err(|e| anyhow::anyhow!("Failed to register SIGHUP handler: {}", e))?;
+    tokio::spawn(async move {
+        while sighup.recv().await.is_some() {
+            info!("Received SIGHUP, reloading blocklists...");
+            list.request_reload();
+        }
+    });
+    Ok(())
+}
+
+/// SIGUSR2 切换是否接受新的代理会话（已有会话不受影响）
+#[cfg(unix)]
+fn spawn_pause_on_sigusr2(gate: Arc<readiness::AcceptGate>) -> Result<()> {
+    use tokio::signal::unix::{signal, SignalKind};
+    let mut sigusr2 = signal(SignalKind::user_defined2())
+        .map_err(|e| anyhow::anyhow!("Failed to register SIGUSR2 handler: {}", e))?;
+    tokio::spawn(async move {
+        while sigusr2.recv().await.is_some() {
+            if gate.toggle() {
+                info!("Received SIGUSR2, paused accepting new sessions");
+            } else {
+                info!("Received SIGUSR2, resumed accepting new sessions");
+            }
+        }
+    });
+    Ok(())
+}
+
 /// 运行服务器
 async fn run_server(
     config: Config,
     mut shutdown_rx: Option<tokio::sync::watch::Receiver<bool>>,
     public_ip: Option<String>,
+    reload_preview: Arc<config_diff::ReloadPreview>,
 ) -> Result<()> {
     let bind_addr = config.bind_addr()?;
     let port = config.server.port;
+    for warning in config.user_warnings() {
+        warn!("{}", warning);
+    }
+
+    // 动态 DNS：后台保持域名指向公网 IP，链接使用该域名（server.domain 优先）
+    let public_ip = if config.ddns.is_enabled() {
+        let updater = Arc::new(ddns::DdnsUpdater::new(&config.ddns)?);
+        info!("  DDNS enabled for {}", updater.hostname());
+        let host = config
+            .server
+            .domain
+            .clone()
+            .unwrap_or_else(|| updater.hostname().to_string());
+        updater.spawn();
+        Some(host)
+    } else {
+        public_ip
+    };
 
+    config.validate_inbound()?;
+    config.validate_fallbacks()?;
+    config.validate_outbounds()?;
+    config.validate_routing()?;
+    let inbound_protocols = config.server.effective_protocols();
+    let bind_retry =
+        readiness::BindRetry::new(config.server.bind_retries, config.server.bind_retry_delay_ms);
+    let readiness = Arc::new(readiness::Readiness::default());
+    let destination_policy = config
+        .server
+        .block_private_destinations
+        .then(|| DestinationPolicy::new(bind_addr, public_ip.as_deref()));
     let mut server_config = ServerConfig::new(
         bind_addr,
         config.server.protocol,
         config.server.ws_path,
         public_ip,
         port,
+    )
+    .with_bind_retry(bind_retry.clone())
+    .with_readiness(Arc::clone(&readiness))
+    .with_ws_early_data(config.server.ws_early_data);
+    if inbound_protocols != config::InboundProtocol::ALL {
+        info!(
+            "  Inbound protocols: {}",
+            serde_json::to_string(&inbound_protocols).unwrap_or_default()
+        );
+    }
+    server_config = server_config.with_inbound_protocols(inbound_protocols);
+    if config.sniffing.enabled {
+        info!(
+            "  Sniffing enabled: {}",
+            serde_json::to_string(&config.sniffing.dest_override).unwrap_or_default()
+        );
+        server_config =
+            server_config.with_sniffer(Arc::new(sniffer::Sniffer::new(&config.sniffing)));
+    }
+    let resolver = Arc::new(dns::Resolver::new(&config.resolver)?);
+    if !config.resolver.servers.is_empty() {
+        info!("  Resolver servers: {}", config.resolver.servers.join(", "));
+    }
+    server_config = server_config.with_resolver(resolver);
+    let maintenance = Arc::new(maintenance::Maintenance::new(&config.maintenance)?);
+    if config.maintenance.enabled {
+        info!("  Maintenance mode: on");
+    } else if config.maintenance.starts_at.is_some() || config.maintenance.ends_at.is_some() {
+        info!(
+            "  Maintenance window: {} - {}",
+            config.maintenance.starts_at.as_deref().unwrap_or("now"),
+            config.maintenance.ends_at.as_deref().unwrap_or("until disabled")
+        );
+    }
+    server_config = server_config.with_maintenance(maintenance);
+    if config.server.domain_strategy != config::DomainStrategy::AsIs {
+        info!(
+            "  Domain strategy: {}",
+            serde_json::to_string(&config.server.domain_strategy).unwrap_or_default()
+        );
+        server_config = server_config.with_domain_strategy(config.server.domain_strategy);
+    }
+    match destination_policy {
+        Some(policy) => server_config = server_config.with_destination_policy(policy),
+        None => {
+            warn!("  Private destination blocking disabled (server.block_private_destinations)")
+        }
+    }
+    if config.performance.max_sessions > 0 {
+        server_config = server_config.with_session_limiter(Arc::new(limiter::SessionLimiter::new(
+            config.performance.max_sessions,
+            config.performance.session_queue,
+            std::time::Duration::from_millis(config.performance.session_queue_timeout_ms),
+        )));
+        info!(
+            "  Session limit: {} (queue {}, timeout {}ms)",
+            config.performance.max_sessions,
+            config.performance.session_queue,
+            config.performance.session_queue_timeout_ms
+        );
+    }
+    server_config = server_config.with_udp_sessions(Arc::new(
+        udp_session::UdpSessionTable::new(config.performance.udp_max_sessions),
+    ));
+    if !config.performance.udp_full_cone {
+        info!("  UDP full-cone disabled: replies only accepted from the requested target");
+    }
+
+    // NAT 之后的部署：向网关请求端口映射，外部端口写入分享链接（link_port 优先）
+    let port_mapper = if config.port_mapping.enabled {
+        match port_mapping::PortMapper::start(&config.port_mapping, port).await {
+            Ok(mapper) => {
+                info!(
+                    "  Port mapping: external port {} -> {} (gateway {})",
+                    mapper.external_port(),
+                    port,
+                    mapper.gateway()
+                );
+                Some(mapper)
+            }
+            Err(e) => {
+                error!("Port mapping failed: {}", e);
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    server_config = server_config.with_fronting(
+        config.server.ws_host.clone(),
+        config.server.sni.clone(),
+        config
+            .server
+            .link_port
+            .or(port_mapper.as_ref().map(|m| m.external_port())),
     );
 
+    config.decoy.validate()?;
+    server_config = server_config.with_decoy(config.decoy.clone());
+
+    if !config.fallbacks.is_empty() {
+        server_config = server_config.with_fallback(Arc::new(fallback::Fallback::new(
+            config.fallbacks.clone(),
+        )));
+        info!(
+            "  Fallback enabled: {} rule(s), default {}",
+            config.fallbacks.len(),
+            config
+                .fallbacks
+                .iter()
+                .find(|r| r.path.is_none())
+                .map_or("none", |r| r.dest.as_str())
+        );
+    }
+
+    if config.sni_proxy.is_enabled() {
+        config.sni_proxy.validate()?;
+        let own_domains = [
+            &config.server.domain,
+            &config.server.sni,
+            &config.server.ws_host,
+        ]
+        .into_iter()
+        .flatten()
+        .cloned()
+        .collect();
+        server_config = server_config.with_sni_proxy(Arc::new(sni_proxy::SniRouter::new(
+            &config.sni_proxy,
+            own_domains,
+        )));
+        info!(
+            "  SNI passthrough enabled: {} route(s), default backend {}",
+            config.sni_proxy.routes.len(),
+            config.sni_proxy.backend.as_deref().unwrap_or("none")
+        );
+    }
+
+    let destinations = Arc::new(destinations::DestinationTracker::with_config(
+        &config.monitoring,
+    ));
+    server_config = server_config
+        .with_destinations(Arc::clone(&destinations))
+        .with_api_token(config.api.token.clone())
+        .with_reload_preview(reload_preview);
+    if config.api.token.is_some() {
+        info!("  Admin API enabled at /api/");
+        if let Some(ref dir) = config.api.capture_dir {
+            server_config = server_config.with_capture(Arc::new(capture::CaptureManager::new(dir)));
+            info!("  Session capture available (files in {})", dir);
+        }
+    }
+
+    if !config.outbound_tls.is_empty() {
+        let outbound_tls = outbound_tls::OutboundTls::new(&config.outbound_tls)?;
+        server_config = server_config.with_outbound_tls(Arc::new(outbound_tls));
+        info!(
+            "  Outbound TLS enabled ({} rules)",
+            config.outbound_tls.len()
+        );
+    }
+
+    // 上游代理串联：目标 TCP 连接经 SOCKS5 / HTTP 出站建立（UDP 仍直连）
+    if let Some(outbound) = config
+        .server
+        .outbound
+        .as_ref()
+        .and_then(|tag| config.outbounds.iter().find(|o| &o.tag == tag))
+    {
+        let outbound = outbound::Outbound::from_config(outbound)?;
+        info!(
+            "  Target connections via outbound {} ({:?} {})",
+            outbound.tag(),
+            outbound.protocol(),
+            outbound.server()
+        );
+        server_config = server_config.with_outbound(Arc::new(outbound));
+    }
+
+    if !config.routing.rules.is_empty() {
+        let router = router::Router::new(&config.routing, &config.outbounds)?;
+        info!("  Routing enabled ({} rules)", config.routing.rules.len());
+        if let Some(db) = router.geoip() {
+            info!("  GeoIP database: {}", db.database_type());
+        }
+        server_config = server_config.with_router(Arc::new(router));
+    }
+
+    let dns_interceptor = config
+        .dns
+        .enabled
+        .then(|| {
+            Arc::new(
+                dns::DnsInterceptor::new(&config.dns)
+                    .with_stats_limit(config.monitoring.dns_stats_entries),
+            )
+        });
+    if let Some(ref dns) = dns_interceptor {
+        server_config = server_config.with_dns(Arc::clone(dns));
+        info!(
+            "  DNS interception enabled ({} hosts, {} blocked suffixes)",
+            config.dns.hosts.len(),
+            config.dns.block.len()
+        );
+    }
+
+    let blocklist = config.blocklist.is_enabled().then(|| {
+        let exempt = config
+            .users
+            .iter()
+            .filter(|u| !u.blocklist)
+            .filter_map(|u| uuid::Uuid::parse_str(&u.uuid).ok())
+            .collect();
+        Arc::new(blocklist::Blocklist::new(&config.blocklist, exempt))
+    });
+    if let Some(ref list) = blocklist {
+        server_config = server_config.with_blocklist(Arc::clone(list));
+        blocklist::spawn_refresh_task(Arc::clone(list), &config.blocklist);
+        #[cfg(unix)]
+        spawn_reload_on_sighup(Arc::clone(list))?;
+        info!(
+            "  Blocklist enabled ({} subscriptions, {} static domains)",
+            config.blocklist.urls.len(),
+            config.blocklist.domains.len()
+        );
+    }
+
+    let events = events::EventBus::new();
+    server_config = server_config.with_events(events.clone());
+    events::spawn_audit_log(events.subscribe());
+
+    let accounting = if config.accounting.is_enabled() {
+        let accounting = accounting::Accounting::spawn(&config.accounting, events.subscribe())?;
+        info!(
+            "  Session accounting enabled (webhook: {}, spool: {})",
+            config.accounting.webhook_url.as_deref().unwrap_or("none"),
+            config.accounting.spool_file.as_deref().unwrap_or("none")
+        );
+        Some(accounting)
+    } else {
+        None
+    };
+
+    let hooks = if config.hooks.is_empty() {
+        None
+    } else {
+        let hooks = hooks::Hooks::spawn(&config.hooks, events.subscribe())?;
+        for (index, hook) in config.hooks.iter().enumerate() {
+            let on: Vec<&str> = hook.on.iter().map(|verdict| verdict.as_str()).collect();
+            info!(
+                "  Hook {} on {}: exec {}, webhook {}",
+                index,
+                on.join(", "),
+                hook.exec.first().map(String::as_str).unwrap_or("none"),
+                hook.webhook_url.as_deref().unwrap_or("none")
+            );
+        }
+        Some(hooks)
+    };
+
+    let bouncer = if config.crowdsec.bouncer_enabled() {
+        let bouncer = Arc::new(crowdsec::Bouncer::new(&config.crowdsec)?);
+        info!(
+            "  CrowdSec bouncer enabled (LAPI: {}, cache: {}s)",
+            config.crowdsec.lapi_url.as_deref().unwrap_or_default(),
+            config.crowdsec.cache_ttl
+        );
+        server_config = server_config.with_crowdsec(Arc::clone(&bouncer));
+        Some(bouncer)
+    } else {
+        None
+    };
+    let crowdsec_alerts = if config.crowdsec.alerts_enabled() {
+        let alerts = crowdsec::AlertReporter::spawn(&config.crowdsec, events.subscribe())?;
+        info!(
+            "  CrowdSec alerts enabled (scenario: {}, {} failures in {}s)",
+            config.crowdsec.scenario,
+            config.crowdsec.alert_threshold,
+            config.crowdsec.alert_window
+        );
+        Some(alerts)
+    } else {
+        None
+    };
+
+    let user_stats = Arc::new(stats::UserStats::new());
+    user_stats.spawn_collector(events.subscribe());
+    server_config = server_config.with_user_stats(Arc::clone(&user_stats));
+
     for user in &config.users {
         if let Ok(uuid) = uuid::Uuid::parse_str(&user.uuid) {
             let email = user.email.clone();
+            user_stats.register(uuid, email.clone());
             server_config.add_user_with_email(uuid, email.clone());
             info!(
                 "  Added user: {} ({})",
@@ -218,8 +866,145 @@ async fn run_server(
         }
     }
 
+    // 端口转发：与 VLESS 会话共用出站与统计，按转发名称计入流量
+    // 绑定重试后仍失败的转发不影响其他入站，通过 /readyz 报告降级
+    let mut forward_tasks = Vec::new();
+    for forward in &config.forwards {
+        let name = format!(
+            "forward:{}",
+            forward.name.as_deref().unwrap_or(&forward.listen)
+        );
+        let bound = readiness::bind_with_retry(&name, &bind_retry, || {
+            forward::Forwarder::bind(
+                forward,
+                server_config.services.clone(),
+                config.performance.clone(),
+            )
+        })
+        .await;
+        let forwarder = match bound {
+            Ok(forwarder) => forwarder,
+            Err(e) if readiness::is_io_error(&e) => {
+                error!("{:#}, continuing without it", e);
+                readiness.set_failed(&name, &forward.listen, &format!("{:#}", e));
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        readiness.set_ready(&name, &forward.listen);
+        user_stats.register(forwarder.uuid(), Some(forwarder.label()));
+        let listen = forwarder
+            .tcp_addr()
+            .or(forwarder.udp_addr())
+            .map(|addr| addr.to_string())
+            .unwrap_or_default();
+        info!(
+            "  Forward {} ({} {:?}) -> {}",
+            forwarder.name(),
+            listen,
+            forward.network,
+            forwarder.dest()
+        );
+        forward_tasks.extend(forwarder.spawn());
+    }
+
+    // 本地代理入站（客户端模式）：经出站把本机应用的连接转发到上游 VLESS 服务器
+    for proxy in &config.local_proxies {
+        let Some(outbound) = proxy.select_outbound(&config.outbounds) else {
+            continue;
+        };
+        let outbound = Arc::new(outbound::Outbound::from_config(outbound)?);
+        let name = format!("local_proxy:{}", proxy.listen);
+        let bound = readiness::bind_with_retry(&name, &bind_retry, || {
+            local_proxy::LocalProxy::bind(proxy, Arc::clone(&outbound), config.performance.clone())
+        })
+        .await;
+        let local = match bound {
+            Ok(local) => local,
+            Err(e) if readiness::is_io_error(&e) => {
+                error!("{:#}, continuing without it", e);
+                readiness.set_failed(&name, &proxy.listen, &format!("{:#}", e));
+                continue;
+            }
+            Err(e) => return Err(e),
+        };
+        readiness.set_ready(&name, &proxy.listen);
+        let local = local.with_task_counter(server_config.services.listener_tasks.register(&name));
+        info!(
+            "  Local {:?} proxy {} -> outbound {} ({})",
+            proxy.protocol,
+            local
+                .local_addr()
+                .map(|addr| addr.to_string())
+                .unwrap_or_default(),
+            outbound.tag(),
+            outbound.server()
+        );
+        forward_tasks.push(local.spawn());
+    }
+
+    // 恢复上次正常退出时保存的用户统计（须在登记全部用户与转发之后）
+    if let Some(ref path) = config.monitoring.state_file {
+        match stats::load_state(std::path::Path::new(path)) {
+            Ok(Some(saved)) => info!(
+                "  Restored stats for {} users from {} (saved {}s ago)",
+                user_stats.restore(&saved),
+                path,
+                saved.age_secs()
+            ),
+            Ok(None) => {}
+            Err(e) => warn!("{}, starting with empty stats", e),
+        }
+    }
+
+    let links = server_config.user_links();
+    if config.output.show_links {
+        info!("  Share links:");
+        for link in &links {
+            info!(
+                "    {} ({}): {}",
+                link.email.as_deref().unwrap_or("no email"),
+                link.transport,
+                link.vless
+            );
+        }
+    } else {
+        info!("  Share links: {} (hidden)", links.len());
+    }
+    if let Some(ref path) = config.server.links_file {
+        let content = vless_link::format_links_file(&links);
+        match atomic_write::atomic_write_file_with_perms(
+            std::path::Path::new(path),
+            &content,
+            0o600,
+        ) {
+            Ok(()) => info!("  Share links written to {}", path),
+            Err(e) => error!("Failed to write share links to {}: {}", path, e),
+        }
+    }
+
     let performance_config = config.performance.clone();
     let (shutdown_tx, _) = tokio::sync::broadcast::channel::<()>(1);
+    let listener_tasks = Arc::clone(&server_config.services.listener_tasks);
+    let route_stats = Arc::clone(&server_config.services.route_stats);
+    #[cfg(unix)]
+    spawn_pause_on_sigusr2(Arc::clone(&server_config.services.gate))?;
+
+    // 定时重启：TUI 模式为交互运行，不自动退出
+    let restart_schedule = match config.restart.schedule {
+        Some(_) if shutdown_rx.is_some() => {
+            warn!("Scheduled restarts are disabled in TUI mode");
+            None
+        }
+        Some(ref expr) => {
+            info!(
+                "  Scheduled restart: {} (max {} active connections, exit code {})",
+                expr, config.restart.max_active_connections, config.restart.exit_code
+            );
+            Some(restart::Schedule::parse(expr)?)
+        }
+        None => None,
+    };
 
     let server =
         VlessServer::new(server_config, performance_config).with_shutdown(shutdown_tx.clone());
@@ -255,6 +1040,15 @@ async fn run_server(
         }
     };
 
+    let scheduled_restart = async {
+        match restart_schedule {
+            Some(ref schedule) => {
+                restart::wait_for_restart(schedule, &config.restart, &listener_tasks).await
+            }
+            None => std::future::pending::<()>().await,
+        }
+    };
+
     let flag_check = async {
         if let Some(ref mut rx) = shutdown_rx {
             rx.changed().await.ok();
@@ -264,6 +1058,7 @@ async fn run_server(
         }
     };
 
+    let mut restarting = false;
     tokio::select! {
         result = server.run() => {
             if let Err(e) = result {
@@ -279,9 +1074,136 @@ async fn run_server(
             info!("Shutting down server...");
             let _ = shutdown_tx.send(());
         }
+        _ = scheduled_restart => {
+            info!("Restarting server on schedule, draining connections...");
+            let _ = shutdown_tx.send(());
+            restarting = true;
+        }
+    }
+
+    for task in forward_tasks {
+        task.abort();
+    }
+
+    if restarting {
+        let remaining = restart::drain(
+            &listener_tasks,
+            std::time::Duration::from_secs(config.restart.drain_timeout),
+        )
+        .await;
+        if remaining > 0 {
+            warn!(
+                "Drain timed out after {}s, closing {} remaining connections",
+                config.restart.drain_timeout, remaining
+            );
+        } else {
+            info!("All connections drained");
+        }
+    }
+
+    if let Some(mapper) = port_mapper {
+        mapper.release().await;
+    }
+
+    if let Some(ref path) = config.monitoring.state_file {
+        match user_stats.save(std::path::Path::new(path)) {
+            Ok(()) => info!("User stats saved to {}", path),
+            Err(e) => error!("Failed to save user stats to {}: {}", path, e),
+        }
+    }
+
+    let routes = route_stats.snapshot().routes;
+    if !routes.is_empty() {
+        let summary = routes
+            .iter()
+            .map(|r| {
+                format!(
+                    "{} ({} sessions, up {} B, down {} B)",
+                    r.route, r.counters.sessions, r.counters.bytes_up, r.counters.bytes_down
+                )
+            })
+            .collect::<Vec<_>>();
+        info!("Route traffic: {}", summary.join(", "));
+    }
+
+    if let Some(dns) = dns_interceptor {
+        let top = dns
+            .top_queries(10)
+            .into_iter()
+            .map(|(name, count)| format!("{} ({})", name, count))
+            .collect::<Vec<_>>();
+        info!(
+            "DNS cache entries: {}, top queried domains: {}",
+            dns.cache_len(),
+            if top.is_empty() {
+                "none".to_string()
+            } else {
+                top.join(", ")
+            }
+        );
+    }
+
+    if let Some(list) = blocklist {
+        info!(
+            "Blocklist: {} domains, {} requests blocked",
+            list.domain_count(),
+            list.blocked_count()
+        );
+    }
+
+    if let Some(accounting) = accounting {
+        let dropped = accounting.dropped_count();
+        if dropped > 0 {
+            info!("Accounting: {} session events dropped", dropped);
+        }
+    }
+
+    if let Some(bouncer) = bouncer {
+        let stats = bouncer.stats();
+        info!(
+            "CrowdSec: {} connections checked, {} blocked, {} LAPI errors",
+            stats.checked, stats.blocked, stats.errors
+        );
+    }
+
+    if let Some(alerts) = crowdsec_alerts {
+        info!(
+            "CrowdSec: {} alerts sent, {} failed",
+            alerts.sent_count(),
+            alerts.failed_count()
+        );
+    }
+
+    if let Some(hooks) = hooks {
+        info!(
+            "Hooks: {} triggered, {} suppressed, {} failed",
+            hooks.triggered_count(),
+            hooks.suppressed_count(),
+            hooks.failed_count()
+        );
+    }
+
+    let problems = destinations.problems();
+    if !problems.is_empty() {
+        info!(
+            "Problem destinations: {}",
+            problems
+                .iter()
+                .take(10)
+                .map(|r| r.dest.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
     }
 
     info!("Server stopped");
+    if restarting {
+        info!(
+            "Exiting with code {} for scheduled restart",
+            config.restart.exit_code
+        );
+        std::process::exit(config.restart.exit_code);
+    }
     Ok(())
 }
 
@@ -301,6 +1223,7 @@ fn init_tui_logging(log_tx: mpsc::Sender<tui::LogEntry>) {
 fn run_tui_with_channel(
     log_rx: mpsc::Receiver<tui::LogEntry>,
     status_info: &version::ServerStatusInfo,
+    log_history: usize,
 ) -> Result<(), Box<dyn std::error::Error>> {
     use ratatui::{
         backend::CrosstermBackend,
@@ -319,8 +1242,6 @@ fn run_tui_with_channel(
     };
     use std::time::Duration;
 
-    const MAX_LOG_ENTRIES: usize = 1000;
-
     enable_raw_mode()?;
     let mut stdout = std::io::stdout();
     execute!(stdout, EnterAlternateScreen)?;
@@ -339,7 +1260,7 @@ fn run_tui_with_channel(
         // 接收新日志
         while let Ok(entry) = log_rx.try_recv() {
             log_entries.push(entry);
-            if log_entries.len() > MAX_LOG_ENTRIES {
+            if log_entries.len() > log_history {
                 log_entries.remove(0);
             }
             // 如果启用自动滚动，调整偏移量以显示最新日志
@@ -0,0 +1,211 @@
+//! 维护模式模块
+//!
+//! 手动开启（配置或管理 API）或处于计划时间窗口内时拒绝新的代理会话，已有会话不受影响；
+//! 信息页展示维护说明（计划中的维护提前展示时间窗口），WebSocket 客户端收到附带说明的关闭帧
+
+use crate::config::{MaintenanceBehavior, MaintenanceConfig};
+use crate::tr;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicU8, Ordering};
+use tracing::info;
+
+/// WebSocket 关闭帧原因的最大字节数（控制帧载荷 125 字节减去 2 字节状态码）
+const MAX_CLOSE_REASON: usize = 123;
+
+/// 运行时的维护模式开关
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MaintenanceMode {
+    /// 按计划时间窗口
+    Scheduled,
+    /// 手动开启
+    On,
+    /// 手动关闭（计划时间窗口不生效）
+    Off,
+}
+
+impl MaintenanceMode {
+    fn from_u8(value: u8) -> Self {
+        match value {
+            1 => MaintenanceMode::On,
+            2 => MaintenanceMode::Off,
+            _ => MaintenanceMode::Scheduled,
+        }
+    }
+
+    fn as_u8(self) -> u8 {
+        match self {
+            MaintenanceMode::Scheduled => 0,
+            MaintenanceMode::On => 1,
+            MaintenanceMode::Off => 2,
+        }
+    }
+}
+
+/// 维护模式状态（管理 API 输出）
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MaintenanceStatus {
+    /// 当前是否处于维护模式
+    pub active: bool,
+    pub mode: MaintenanceMode,
+    /// 计划开始时间（RFC 3339，UTC）
+    pub starts_at: Option<String>,
+    /// 计划结束时间（RFC 3339，UTC）
+    pub ends_at: Option<String>,
+    pub message: String,
+    pub behavior: MaintenanceBehavior,
+    /// 启动以来因维护而拒绝的连接数
+    pub rejected: u64,
+}
+
+/// 维护模式（所有会话共享）
+#[derive(Debug, Default)]
+pub struct Maintenance {
+    mode: AtomicU8,
+    starts_at: Option<DateTime<Utc>>,
+    ends_at: Option<DateTime<Utc>>,
+    message: String,
+    behavior: MaintenanceBehavior,
+    rejected: AtomicU64,
+    /// 上次准入检查时的状态，用于记录进入与退出日志
+    active: AtomicBool,
+}
+
+impl Maintenance {
+    /// 根据配置创建；`enabled` 为 true 时以手动开启状态启动
+    pub fn new(config: &MaintenanceConfig) -> Result<Self> {
+        let (starts_at, ends_at) = config.window()?;
+        let mode = if config.enabled {
+            MaintenanceMode::On
+        } else {
+            MaintenanceMode::Scheduled
+        };
+        Ok(Self {
+            mode: AtomicU8::new(mode.as_u8()),
+            starts_at,
+            ends_at,
+            message: config.message.trim().to_string(),
+            behavior: config.behavior,
+            rejected: AtomicU64::new(0),
+            active: AtomicBool::new(false),
+        })
+    }
+
+    /// 切换运行时开关，返回状态是否改变
+    pub fn set_mode(&self, mode: MaintenanceMode) -> bool {
+        self.mode.swap(mode.as_u8(), Ordering::Relaxed) != mode.as_u8()
+    }
+
+    pub fn mode(&self) -> MaintenanceMode {
+        MaintenanceMode::from_u8(self.mode.load(Ordering::Relaxed))
+    }
+
+    /// 当前是否处于维护模式
+    pub fn is_active(&self) -> bool {
+        self.is_active_at(Utc::now())
+    }
+
+    /// 指定时刻是否处于维护模式
+    pub fn is_active_at(&self, now: DateTime<Utc>) -> bool {
+        match self.mode() {
+            MaintenanceMode::On => true,
+            MaintenanceMode::Off => false,
+            MaintenanceMode::Scheduled => self.in_window(now),
+        }
+    }
+
+    /// 是否处于计划时间窗口（未设置任何时间时不处于窗口）
+    fn in_window(&self, now: DateTime<Utc>) -> bool {
+        if self.starts_at.is_none() && self.ends_at.is_none() {
+            return false;
+        }
+        self.starts_at.is_none_or(|starts| now >= starts)
+            && self.ends_at.is_none_or(|ends| now < ends)
+    }
+
+    /// 检查是否接受新会话；维护中时计入拒绝数并返回 false
+    pub fn admit(&self) -> bool {
+        let active = self.is_active();
+        if self.active.swap(active, Ordering::Relaxed) != active {
+            if active {
+                info!("Maintenance mode started, rejecting new sessions");
+            } else {
+                info!("Maintenance mode ended, accepting new sessions");
+            }
+        }
+        if active {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+        }
+        !active
+    }
+
+    pub fn behavior(&self) -> MaintenanceBehavior {
+        self.behavior
+    }
+
+    /// 信息页展示的维护说明：维护中时为说明文字，计划中的维护附带时间窗口，其余为空
+    pub fn notice(&self) -> Option<String> {
+        self.notice_at(Utc::now())
+    }
+
+    /// 指定时刻的维护说明
+    pub fn notice_at(&self, now: DateTime<Utc>) -> Option<String> {
+        let format = |time: DateTime<Utc>| time.format("%Y-%m-%d %H:%M UTC").to_string();
+        let mut notice = if self.is_active_at(now) {
+            let mut notice = tr!("服务维护中", "Under maintenance");
+            if let Some(ends) = self
+                .ends_at
+                .filter(|_| self.mode() == MaintenanceMode::Scheduled)
+            {
+                notice.push_str(&tr!(
+                    "，预计结束：{}",
+                    ", expected to end at {}",
+                    format(ends)
+                ));
+            }
+            notice
+        } else {
+            let starts = self
+                .starts_at
+                .filter(|starts| *starts > now && self.mode() == MaintenanceMode::Scheduled)?;
+            let mut notice = tr!("计划维护：{}", "Scheduled maintenance: {}", format(starts));
+            if let Some(ends) = self.ends_at {
+                notice.push_str(&format!(" - {}", format(ends)));
+            }
+            notice
+        };
+        if !self.message.is_empty() {
+            notice.push_str(&format!(" — {}", self.message));
+        }
+        Some(notice)
+    }
+
+    /// WebSocket 关闭帧原因：维护说明（未设置时为默认文字），按字符边界截断到 123 字节
+    pub fn close_reason(&self) -> String {
+        let reason = if self.message.is_empty() {
+            "Under maintenance"
+        } else {
+            &self.message
+        };
+        let mut end = reason.len().min(MAX_CLOSE_REASON);
+        while !reason.is_char_boundary(end) {
+            end -= 1;
+        }
+        reason[..end].to_string()
+    }
+
+    /// 当前状态
+    pub fn status(&self) -> MaintenanceStatus {
+        MaintenanceStatus {
+            active: self.is_active(),
+            mode: self.mode(),
+            starts_at: self.starts_at.map(|time| time.to_rfc3339()),
+            ends_at: self.ends_at.map(|time| time.to_rfc3339()),
+            message: self.message.clone(),
+            behavior: self.behavior,
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
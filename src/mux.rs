@@ -0,0 +1,656 @@
+//! Mux 多路复用模块
+//!
+//! 实现 Xray 的 Mux.Cool 协议（VLESS 命令 3）：在一条客户端连接上承载多个子连接。
+//! TCP 子连接经内存管道交给 `handle_tcp_proxy` 处理；UDP 子连接每帧即一个数据报，
+//! 为保留数据报边界按帧直接收发
+
+use crate::blocklist::{ensure_allowed, Blocklist};
+use crate::config::PerformanceConfig;
+use crate::dns::{DnsAction, DnsSession, DNS_PORT};
+use crate::events::Event;
+use crate::overhead::Transport;
+use crate::protocol::{Address, Command, VlessRequest};
+use crate::router::RouteMatch;
+use crate::session::{format_destination, user_label, SessionRecord, SessionServices};
+use crate::tcp::handle_tcp_proxy;
+use crate::udp_session::UdpSessionKey;
+use anyhow::{anyhow, Result};
+use bytes::{Buf, BufMut, Bytes, BytesMut};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Instant;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt, DuplexStream, WriteHalf};
+use tokio::sync::{mpsc, Notify};
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// 帧选项：携带数据
+const OPTION_DATA: u8 = 0x01;
+
+/// 单条连接上同时存在的子连接上限
+pub const MAX_SUB_SESSIONS: usize = 128;
+
+/// TCP 子连接内存管道的缓冲区大小（不小于单帧数据上限）
+const PIPE_BUFFER: usize = 64 * 1024;
+
+/// 下行读取缓冲区大小（单帧数据长度）
+const FRAME_DATA: usize = 16 * 1024;
+
+/// 待写回客户端的帧队列长度
+const FRAME_QUEUE: usize = 64;
+
+/// 单个子连接待转发的上行帧队列长度
+const INPUT_QUEUE: usize = 64;
+
+/// 子连接状态
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SessionStatus {
+    New = 1,
+    Keep = 2,
+    End = 3,
+    KeepAlive = 4,
+}
+
+impl TryFrom<u8> for SessionStatus {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            1 => Ok(SessionStatus::New),
+            2 => Ok(SessionStatus::Keep),
+            3 => Ok(SessionStatus::End),
+            4 => Ok(SessionStatus::KeepAlive),
+            _ => Err(anyhow!("Invalid mux session status: {}", value)),
+        }
+    }
+}
+
+/// 子连接传输类型
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum MuxNetwork {
+    Tcp = 1,
+    Udp = 2,
+}
+
+impl TryFrom<u8> for MuxNetwork {
+    type Error = anyhow::Error;
+
+    fn try_from(value: u8) -> Result<Self> {
+        match value {
+            1 => Ok(MuxNetwork::Tcp),
+            2 => Ok(MuxNetwork::Udp),
+            _ => Err(anyhow!("Invalid mux network: {}", value)),
+        }
+    }
+}
+
+/// 子连接目标
+#[derive(Debug, Clone, PartialEq)]
+pub struct MuxTarget {
+    pub network: MuxNetwork,
+    pub address: Address,
+    pub port: u16,
+}
+
+/// Mux.Cool 帧
+///
+/// 格式：元数据长度（2 字节）| 子连接 ID（2）| 状态（1）| 选项（1）|
+/// [传输类型（1）| 端口（2）| 地址] | [数据长度（2）| 数据]
+#[derive(Debug, Clone, PartialEq)]
+pub struct MuxFrame {
+    pub id: u16,
+    pub status: SessionStatus,
+    /// 目标（New 帧必有；UDP 的 Keep 帧可携带数据报来源）
+    pub target: Option<MuxTarget>,
+    pub data: Option<Bytes>,
+}
+
+impl MuxFrame {
+    /// 下行数据帧
+    pub fn keep(id: u16, target: Option<MuxTarget>, data: Bytes) -> Self {
+        Self {
+            id,
+            status: SessionStatus::Keep,
+            target,
+            data: Some(data),
+        }
+    }
+
+    /// 关闭子连接帧
+    pub fn end(id: u16) -> Self {
+        Self {
+            id,
+            status: SessionStatus::End,
+            target: None,
+            data: None,
+        }
+    }
+
+    /// 读取一帧；连接在帧边界关闭时返回 None
+    pub async fn read_from<R: AsyncRead + Unpin>(reader: &mut R) -> Result<Option<Self>> {
+        let meta_len = match reader.read_u16().await {
+            Ok(len) => len as usize,
+            Err(e) if e.kind() == std::io::ErrorKind::UnexpectedEof => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        if meta_len < 4 {
+            return Err(anyhow!("Invalid mux metadata length: {}", meta_len));
+        }
+        let mut meta = vec![0u8; meta_len];
+        reader.read_exact(&mut meta).await?;
+        let mut meta = Bytes::from(meta);
+
+        let id = meta.get_u16();
+        let status = SessionStatus::try_from(meta.get_u8())?;
+        let option = meta.get_u8();
+        let target = if meta.has_remaining() {
+            if meta.remaining() < 3 {
+                return Err(anyhow!("Truncated mux target"));
+            }
+            let network = MuxNetwork::try_from(meta.get_u8())?;
+            let port = meta.get_u16();
+            let address = Address::decode(&mut meta)?;
+            Some(MuxTarget {
+                network,
+                address,
+                port,
+            })
+        } else {
+            None
+        };
+        if status == SessionStatus::New && target.is_none() {
+            return Err(anyhow!("Mux new frame without target"));
+        }
+
+        let data = if option & OPTION_DATA != 0 {
+            let len = reader.read_u16().await? as usize;
+            let mut data = vec![0u8; len];
+            reader.read_exact(&mut data).await?;
+            Some(Bytes::from(data))
+        } else {
+            None
+        };
+
+        Ok(Some(Self {
+            id,
+            status,
+            target,
+            data,
+        }))
+    }
+
+    /// 编码为字节；目标域名过长时返回错误
+    pub fn encode(&self) -> Result<Bytes> {
+        let data_len = self.data.as_ref().map_or(0, |d| d.len());
+        let mut buf = BytesMut::with_capacity(2 + 4 + 4 + 256 + 2 + data_len);
+        buf.put_u16(0); // 元数据长度，写完后回填
+        buf.put_u16(self.id);
+        buf.put_u8(self.status as u8);
+        buf.put_u8(if self.data.is_some() { OPTION_DATA } else { 0 });
+        if let Some(ref target) = self.target {
+            buf.put_u8(target.network as u8);
+            buf.put_u16(target.port);
+            target.address.encode(&mut buf)?;
+        }
+        let meta_len = (buf.len() - 2) as u16;
+        buf[..2].copy_from_slice(&meta_len.to_be_bytes());
+        if let Some(ref data) = self.data {
+            buf.put_u16(data.len() as u16);
+            buf.put_slice(data);
+        }
+        Ok(buf.freeze())
+    }
+}
+
+/// 子连接
+///
+/// 上行帧经有界队列交给子连接任务，目标建连在任务中进行：
+/// TCP 队列满时解复用循环等待（Mux.Cool 没有单独的流控，以此形成背压），UDP 队列满时丢弃数据报
+struct SubSession {
+    /// New 帧中的目标；UDP 子连接的 Keep 帧不得改为其他目标
+    target: MuxTarget,
+    input: mpsc::Sender<Bytes>,
+    /// 通知 TCP 下行转发停止（客户端已关闭子连接）
+    closed: Arc<Notify>,
+    task: JoinHandle<()>,
+}
+
+impl SubSession {
+    /// 客户端关闭子连接
+    fn close(self) {
+        if self.target.network == MuxNetwork::Tcp {
+            self.closed.notify_one();
+        }
+    }
+}
+
+/// 子连接共享的连接级上下文
+struct MuxContext {
+    client_addr: SocketAddr,
+    request: VlessRequest,
+    perf_config: PerformanceConfig,
+    services: SessionServices,
+    blocklist: Option<Arc<Blocklist>>,
+    user_email: Option<Arc<str>>,
+    frames: mpsc::Sender<Bytes>,
+}
+
+/// 处理 Mux 连接：解复用客户端帧，按子连接建立目标连接并把下行数据封帧写回
+#[allow(clippy::too_many_arguments)]
+pub async fn handle_mux<S>(
+    client_stream: S,
+    client_addr: SocketAddr,
+    request: VlessRequest,
+    initial_data: Bytes,
+    perf_config: PerformanceConfig,
+    services: &SessionServices,
+    blocklist: Option<Arc<Blocklist>>,
+    user_email: Option<Arc<str>>,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let (client_read, client_write) = tokio::io::split(client_stream);
+    let mut client_read = AsyncReadExt::chain(std::io::Cursor::new(initial_data), client_read);
+    let (frames, frames_rx) = mpsc::channel(FRAME_QUEUE);
+    let writer = tokio::spawn(write_frames(client_write, frames_rx));
+
+    let ctx = Arc::new(MuxContext {
+        client_addr,
+        request,
+        perf_config,
+        services: services.clone(),
+        blocklist,
+        user_email,
+        frames,
+    });
+    let mut sessions: HashMap<u16, SubSession> = HashMap::new();
+
+    info!("Mux connection established from {}", client_addr);
+
+    let result = loop {
+        let mut frame = match MuxFrame::read_from(&mut client_read).await {
+            Ok(Some(frame)) => frame,
+            Ok(None) => break Ok(()),
+            Err(e) => break Err(e),
+        };
+        match frame.status {
+            SessionStatus::New => {
+                if let Some(old) = sessions.remove(&frame.id) {
+                    old.close();
+                }
+                sessions.retain(|_, s| !s.task.is_finished());
+                if sessions.len() >= MAX_SUB_SESSIONS {
+                    warn!(
+                        "Mux sub-connection limit ({}) reached for {}",
+                        MAX_SUB_SESSIONS, client_addr
+                    );
+                    send_end(&ctx.frames, frame.id).await;
+                    continue;
+                }
+                let Some(target) = frame.target.clone() else {
+                    continue;
+                };
+                let id = frame.id;
+                let (input, input_rx) = mpsc::channel(INPUT_QUEUE);
+                // UDP 的首个数据报与之后的数据报一样经队列发送
+                if target.network == MuxNetwork::Udp {
+                    if let Some(data) = frame.data.take() {
+                        let _ = input.try_send(data);
+                    }
+                }
+                let closed = Arc::new(Notify::new());
+                let task = tokio::spawn(run_session(
+                    Arc::clone(&ctx),
+                    frame,
+                    input_rx,
+                    Arc::clone(&closed),
+                ));
+                sessions.insert(
+                    id,
+                    SubSession {
+                        target,
+                        input,
+                        closed,
+                        task,
+                    },
+                );
+            }
+            SessionStatus::Keep => {
+                let Some(data) = frame.data else {
+                    continue;
+                };
+                let delivered = match sessions.get(&frame.id) {
+                    Some(session) if session.target.network == MuxNetwork::Tcp => {
+                        session.input.send(data).await.is_ok()
+                    }
+                    // UDP 子连接只发往 New 帧中的目标，携带其他目标的 Keep 帧结束该子连接
+                    Some(session)
+                        if frame.target.as_ref().is_some_and(|t| *t != session.target) =>
+                    {
+                        debug!(
+                            "Mux UDP sub-connection {} changed target, closing",
+                            frame.id
+                        );
+                        false
+                    }
+                    Some(session) => match session.input.try_send(data) {
+                        Ok(()) => true,
+                        Err(mpsc::error::TrySendError::Full(_)) => {
+                            debug!("Mux UDP sub-connection {} queue full, dropping", frame.id);
+                            true
+                        }
+                        Err(mpsc::error::TrySendError::Closed(_)) => false,
+                    },
+                    None => false,
+                };
+                if !delivered {
+                    if let Some(session) = sessions.remove(&frame.id) {
+                        session.close();
+                    }
+                    send_end(&ctx.frames, frame.id).await;
+                }
+            }
+            SessionStatus::End => {
+                if let Some(session) = sessions.remove(&frame.id) {
+                    session.close();
+                }
+            }
+            SessionStatus::KeepAlive => {}
+        }
+    };
+
+    // 客户端连接关闭：结束全部子连接，待下行转发停止后关闭写端
+    for (_, session) in sessions.drain() {
+        session.close();
+    }
+    drop(ctx);
+    match result {
+        Ok(()) => {
+            let _ = writer.await;
+            debug!("Mux connection closed");
+        }
+        Err(_) => writer.abort(),
+    }
+    result
+}
+
+/// 子连接任务：检查目标（拦截列表、域名解析、路由）后转发；被拦截或目标无效时回复 End
+async fn run_session(
+    ctx: Arc<MuxContext>,
+    frame: MuxFrame,
+    input: mpsc::Receiver<Bytes>,
+    closed: Arc<Notify>,
+) {
+    let id = frame.id;
+    let Some(target) = frame.target else {
+        return;
+    };
+    let mut request = VlessRequest {
+        command: match target.network {
+            MuxNetwork::Tcp => Command::Tcp,
+            MuxNetwork::Udp => Command::Udp,
+        },
+        address: target.address,
+        port: target.port,
+        ..ctx.request.clone()
+    };
+
+    if let Some(ref data) = frame.data {
+        ctx.services.sniff_destination(&mut request, data);
+    }
+    let allowed = ensure_allowed(ctx.blocklist.as_deref(), &request);
+    if allowed.is_ok() {
+        if let Err(e) = ctx.services.apply_domain_strategy(&mut request).await {
+            debug!("Mux sub-connection {} failed: {}", id, e);
+            send_end(&ctx.frames, id).await;
+            return;
+        }
+    }
+    let route = ctx.services.route(&request);
+    if let Err(e) = allowed
+        .and_then(|_| ctx.services.ensure_routable(&request, &route))
+        .and_then(|_| ctx.services.ensure_destination(&request))
+    {
+        info!("{} (user {}, mux)", e, request.uuid);
+        ctx.services.record_blocked(&route);
+        ctx.services.events.publish(Event::DestinationBlocked {
+            user: request.uuid.to_string(),
+            dest: format_destination(&request.address, request.port),
+        });
+        send_end(&ctx.frames, id).await;
+        return;
+    }
+    debug!(
+        "Mux sub-connection {} -> {}",
+        id,
+        format_destination(&request.address, request.port)
+    );
+
+    match target.network {
+        MuxNetwork::Tcp => {
+            let (local, remote) = tokio::io::duplex(PIPE_BUFFER);
+            let (down, up) = tokio::io::split(local);
+            let feed = tokio::spawn(pump_up(input, up));
+            let pump = pump_down(id, down, ctx.frames.clone(), closed);
+            let proxy = handle_tcp_proxy(
+                remote,
+                ctx.client_addr,
+                request,
+                route,
+                frame.data.unwrap_or_default(),
+                ctx.perf_config.clone(),
+                &ctx.services,
+                ctx.user_email.clone(),
+            );
+            let (result, ()) = tokio::join!(proxy, pump);
+            feed.abort();
+            if let Err(e) = result {
+                debug!("Mux sub-connection {} failed: {}", id, e);
+            }
+        }
+        MuxNetwork::Udp => {
+            let result = proxy_udp(
+                (ctx.client_addr, id),
+                request,
+                route,
+                input,
+                &ctx.frames,
+                ctx.perf_config.clone(),
+                &ctx.services,
+                ctx.blocklist.clone(),
+                ctx.user_email.clone(),
+            )
+            .await;
+            if let Err(e) = result {
+                debug!("Mux UDP sub-connection {} failed: {}", id, e);
+            }
+            send_end(&ctx.frames, id).await;
+        }
+    }
+}
+
+/// 把帧依次写回客户端
+async fn write_frames<W: AsyncWrite + Unpin>(mut writer: W, mut frames: mpsc::Receiver<Bytes>) {
+    while let Some(frame) = frames.recv().await {
+        if writer.write_all(&frame).await.is_err() {
+            return;
+        }
+    }
+    let _ = writer.shutdown().await;
+}
+
+/// TCP 子连接上行：把队列中的帧数据写入内存管道；客户端关闭子连接时关闭写端
+async fn pump_up(mut input: mpsc::Receiver<Bytes>, mut up: WriteHalf<DuplexStream>) {
+    while let Some(data) = input.recv().await {
+        if up.write_all(&data).await.is_err() {
+            return;
+        }
+    }
+    let _ = up.shutdown().await;
+}
+
+async fn send_end(frames: &mpsc::Sender<Bytes>, id: u16) {
+    send_frame(frames, MuxFrame::end(id)).await;
+}
+
+/// 编码并发送一帧；写端已关闭或编码失败时返回 false
+async fn send_frame(frames: &mpsc::Sender<Bytes>, frame: MuxFrame) -> bool {
+    match frame.encode() {
+        Ok(frame) => frames.send(frame).await.is_ok(),
+        Err(e) => {
+            debug!("Failed to encode Mux frame {}: {}", frame.id, e);
+            false
+        }
+    }
+}
+
+/// TCP 子连接下行：读取管道数据封装为 Keep 帧；目标关闭时发送 End
+async fn pump_down<R: AsyncRead + Unpin>(
+    id: u16,
+    mut down: R,
+    frames: mpsc::Sender<Bytes>,
+    closed: Arc<Notify>,
+) {
+    let mut buffer = vec![0u8; FRAME_DATA];
+    loop {
+        let n = tokio::select! {
+            read = down.read(&mut buffer) => match read {
+                Ok(0) | Err(_) => break,
+                Ok(n) => n,
+            },
+            // 客户端已关闭子连接，无需回复 End
+            _ = closed.notified() => return,
+        };
+        let frame = MuxFrame::keep(id, None, Bytes::copy_from_slice(&buffer[..n]));
+        if !send_frame(&frames, frame).await {
+            return;
+        }
+    }
+    send_end(&frames, id).await;
+}
+
+/// UDP 子连接：每个上行帧发送一个数据报，每个回包封装为一个带实际来源地址的 Keep 帧
+#[allow(clippy::too_many_arguments)]
+async fn proxy_udp(
+    key: UdpSessionKey,
+    request: VlessRequest,
+    route: RouteMatch,
+    mut datagrams: mpsc::Receiver<Bytes>,
+    frames: &mpsc::Sender<Bytes>,
+    perf_config: PerformanceConfig,
+    services: &SessionServices,
+    blocklist: Option<Arc<Blocklist>>,
+    user_email: Option<Arc<str>>,
+) -> Result<()> {
+    let destination = format_destination(&request.address, request.port);
+    let dns = services
+        .dns
+        .clone()
+        .filter(|_| request.port == DNS_PORT)
+        .map(|dns| DnsSession::new(dns, destination.clone()));
+
+    if !services.udp_sessions.has_capacity() {
+        return Err(anyhow!(
+            "UDP session limit reached ({})",
+            services.udp_sessions.max()
+        ));
+    }
+    let started = Instant::now();
+    let (session, timing) = services.open_udp(&request, &route, &perf_config).await?;
+    let mut record = SessionRecord::new(
+        request.uuid,
+        user_label(&request.uuid, user_email.as_ref()),
+        "udp",
+        destination,
+        &timing,
+        started,
+    )
+    .with_route(services.session_route(&request, &route));
+    let session = services.udp_sessions.insert(key, session)?;
+    let id = key.1;
+    let target = MuxTarget {
+        network: MuxNetwork::Udp,
+        address: request.address.clone(),
+        port: request.port,
+    };
+
+    let mut buffer = vec![0u8; FRAME_DATA];
+    let sent_at = Instant::now();
+    let mut ttfb = None;
+
+    loop {
+        tokio::select! {
+            datagram = datagrams.recv() => {
+                let Some(datagram) = datagram else {
+                    break;
+                };
+                session.touch();
+                if let Some(ref dns) = dns {
+                    if let DnsAction::Reply(reply) =
+                        dns.handle_query(&datagram, blocklist.as_deref())
+                    {
+                        let frame = MuxFrame::keep(id, Some(target.clone()), Bytes::from(reply));
+                        if !send_frame(frames, frame).await {
+                            break;
+                        }
+                        continue;
+                    }
+                }
+                if let Err(e) = session.send(&datagram).await {
+                    warn!("Failed to send UDP packet: {}", e);
+                    break;
+                }
+                record.bytes_up += datagram.len() as u64;
+            }
+            received = session.recv_from(&mut buffer) => {
+                let (n, src) = match received {
+                    Ok(Some(received)) => received,
+                    Ok(None) => {
+                        debug!("Mux UDP sub-connection {} idle timeout", id);
+                        break;
+                    }
+                    Err(e) => {
+                        warn!("Error receiving UDP packet: {}", e);
+                        break;
+                    }
+                };
+                if let Some(ref dns) = dns {
+                    dns.handle_response(&buffer[..n], session.is_target(src));
+                }
+                ttfb.get_or_insert_with(|| sent_at.elapsed());
+                // full-cone 下来自其他远端的回包以其实际地址作为来源
+                let source = if session.is_target(src) {
+                    target.clone()
+                } else {
+                    MuxTarget {
+                        network: MuxNetwork::Udp,
+                        address: Address::from(src.ip()),
+                        port: src.port(),
+                    }
+                };
+                let frame =
+                    MuxFrame::keep(id, Some(source), Bytes::copy_from_slice(&buffer[..n]));
+                if !send_frame(frames, frame).await {
+                    break;
+                }
+                record.bytes_down += n as u64;
+            }
+        }
+    }
+
+    if session.replay_dropped() > 0 {
+        debug!(
+            "UDP session dropped {} replayed packet(s)",
+            session.replay_dropped()
+        );
+    }
+    services.udp_sessions.remove(&key, &session);
+    record.set_ttfb(ttfb);
+    services
+        .overhead
+        .record_session(Transport::Tcp, (record.bytes_up, record.bytes_down), (0, 0));
+    services.finish_session(&mut record);
+    Ok(())
+}
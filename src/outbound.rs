@@ -0,0 +1,289 @@
+//! 出站模块
+//!
+//! 经上游服务器建立到目标的 TCP 连接，支持三种上游：
+//! - VLESS：发送请求头（可携带首包数据），下行读取时先剥离服务端的响应头，再与本地连接双向转发。
+//!   响应头可能随首个下行数据一起到达，因此上行不等待响应头
+//! - SOCKS5：完成握手（可选用户名密码认证）与 CONNECT 后得到透明的字节流
+//! - HTTP：发送 `CONNECT` 请求，收到 2xx 应答后得到透明的字节流
+//!
+//! SOCKS5 / HTTP 出站可通过 `server.outbound` 用于服务端的目标连接（串联到上一跳代理）；
+//! SOCKS5 出站还可经 UDP ASSOCIATE 中继 UDP 会话，HTTP 与 VLESS 出站不支持 UDP
+
+use crate::address::{connect_target, ConnectTiming};
+use crate::config::{OutboundConfig, OutboundProtocol, PerformanceConfig};
+use crate::forward::parse_dest;
+use crate::protocol::{Address, Command, VlessRequest, VlessResponse};
+use crate::session::{copy_counted, format_destination};
+use crate::socks;
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use bytes::Bytes;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::debug;
+use uuid::Uuid;
+
+/// HTTP CONNECT 应答头上限
+const MAX_CONNECT_RESPONSE: usize = 8192;
+
+/// 与上游握手（SOCKS5 / HTTP CONNECT 应答、VLESS 响应头）的超时
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 出站
+#[derive(Debug, Clone)]
+pub struct Outbound {
+    tag: String,
+    protocol: OutboundProtocol,
+    server: String,
+    address: Address,
+    port: u16,
+    /// VLESS 用户 UUID（其他协议为 nil）
+    uuid: Uuid,
+    /// 代理认证（用户名，密码）
+    credentials: Option<(String, String)>,
+}
+
+impl Outbound {
+    pub fn from_config(config: &OutboundConfig) -> Result<Self> {
+        let (address, port) = parse_dest(&config.server)?;
+        let uuid = match config.protocol {
+            OutboundProtocol::Vless => Uuid::parse_str(config.uuid.trim())
+                .map_err(|_| anyhow!("Invalid outbound UUID: {}", config.uuid))?,
+            OutboundProtocol::Socks | OutboundProtocol::Http => Uuid::nil(),
+        };
+        Ok(Self {
+            tag: config.tag.clone(),
+            protocol: config.protocol,
+            server: config.server.clone(),
+            address,
+            port,
+            uuid,
+            credentials: config.username.clone().zip(config.password.clone()),
+        })
+    }
+
+    /// 出站标识
+    pub fn tag(&self) -> &str {
+        &self.tag
+    }
+
+    /// 出站协议
+    pub fn protocol(&self) -> OutboundProtocol {
+        self.protocol
+    }
+
+    /// 上游服务器地址
+    pub fn server(&self) -> &str {
+        &self.server
+    }
+
+    /// 经上游建立到目标的连接，`initial_data` 在握手后立即发出
+    ///
+    /// VLESS 上游的响应头留在流中，由 [`Outbound::relay`] 剥离
+    pub async fn connect(
+        &self,
+        address: &Address,
+        port: u16,
+        initial_data: &[u8],
+        perf_config: &PerformanceConfig,
+    ) -> Result<TcpStream> {
+        let (stream, _) = self.dial(address, port, initial_data, perf_config).await?;
+        Ok(stream)
+    }
+
+    /// 同 [`Outbound::connect`]，并返回建连耗时（DNS 为解析上游地址的耗时，连接含握手）
+    pub async fn dial(
+        &self,
+        address: &Address,
+        port: u16,
+        initial_data: &[u8],
+        perf_config: &PerformanceConfig,
+    ) -> Result<(TcpStream, ConnectTiming)> {
+        let (mut stream, mut timing) = connect_target(&self.address, self.port, perf_config)
+            .await
+            .map_err(|e| anyhow!("Failed to connect to outbound {}: {}", self.tag, e))?;
+        let started = Instant::now();
+        let credentials = self
+            .credentials
+            .as_ref()
+            .map(|(user, pass)| (user.as_str(), pass.as_str()));
+        let handshake = async {
+            match self.protocol {
+                OutboundProtocol::Vless => {
+                    let request = VlessRequest::new(self.uuid, Command::Tcp, address.clone(), port);
+                    let mut header = request.encode()?.to_vec();
+                    header.extend_from_slice(initial_data);
+                    stream.write_all(&header).await?;
+                }
+                OutboundProtocol::Socks => {
+                    socks::connect(&mut stream, address, port, credentials)
+                        .await
+                        .map_err(|e| anyhow!("Outbound {}: {}", self.tag, e))?;
+                }
+                OutboundProtocol::Http => {
+                    http_connect(&mut stream, address, port, credentials)
+                        .await
+                        .map_err(|e| anyhow!("Outbound {}: {}", self.tag, e))?;
+                }
+            }
+            if self.protocol != OutboundProtocol::Vless && !initial_data.is_empty() {
+                stream.write_all(initial_data).await?;
+            }
+            Ok::<_, anyhow::Error>(())
+        };
+        tokio::time::timeout(HANDSHAKE_TIMEOUT, handshake)
+            .await
+            .map_err(|_| anyhow!("Outbound {}: handshake timed out", self.tag))??;
+        timing.connect += started.elapsed();
+        debug!(
+            "Outbound {} connected to {} via {}",
+            self.tag,
+            format_destination(address, port),
+            self.server
+        );
+        Ok((stream, timing))
+    }
+
+    /// 经上游建立 UDP 中继（仅 SOCKS5 出站支持），返回中继与建连耗时
+    ///
+    /// 应答的中继地址为未指定地址或域名时，改用控制连接的对端 IP
+    pub async fn udp_associate(
+        &self,
+        perf_config: &PerformanceConfig,
+    ) -> Result<(UdpRelay, ConnectTiming)> {
+        if self.protocol != OutboundProtocol::Socks {
+            return Err(anyhow!("Outbound {} does not support UDP", self.tag));
+        }
+        let (mut control, mut timing) = connect_target(&self.address, self.port, perf_config)
+            .await
+            .map_err(|e| anyhow!("Failed to connect to outbound {}: {}", self.tag, e))?;
+        let started = Instant::now();
+        let credentials = self
+            .credentials
+            .as_ref()
+            .map(|(user, pass)| (user.as_str(), pass.as_str()));
+        let (address, port) = tokio::time::timeout(
+            HANDSHAKE_TIMEOUT,
+            socks::udp_associate(&mut control, credentials),
+        )
+        .await
+        .map_err(|_| anyhow!("Outbound {}: handshake timed out", self.tag))?
+        .map_err(|e| anyhow!("Outbound {}: {}", self.tag, e))?;
+        let relay = match address.to_socket_addr(port) {
+            Ok(relay) if !relay.ip().is_unspecified() => relay,
+            _ => SocketAddr::new(control.peer_addr()?.ip(), port),
+        };
+        timing.connect += started.elapsed();
+        debug!("Outbound {} UDP relay at {}", self.tag, relay);
+        Ok((
+            UdpRelay {
+                relay,
+                _control: control,
+            },
+            timing,
+        ))
+    }
+
+    /// 本地连接与上游连接双向转发，返回（上行字节数，下行字节数）
+    ///
+    /// VLESS 上游的下行先剥离响应头；任一方向结束后关闭对端写入
+    pub async fn relay<C>(&self, client: C, upstream: TcpStream) -> (u64, u64)
+    where
+        C: AsyncRead + AsyncWrite + Send + 'static,
+    {
+        let strip_response = self.protocol == OutboundProtocol::Vless;
+        let (mut client_read, mut client_write) = tokio::io::split(client);
+        let (mut upstream_read, mut upstream_write) = upstream.into_split();
+
+        let uplink = tokio::spawn(async move {
+            let (bytes, _) = copy_counted(&mut client_read, &mut upstream_write).await;
+            let _ = upstream_write.shutdown().await;
+            bytes
+        });
+        let downlink = tokio::spawn(async move {
+            if strip_response {
+                let header =
+                    tokio::time::timeout(HANDSHAKE_TIMEOUT, read_response(&mut upstream_read))
+                        .await
+                        .unwrap_or_else(|_| Err(anyhow!("timed out")));
+                if let Err(e) = header {
+                    debug!("Outbound response header failed: {}", e);
+                    let _ = client_write.shutdown().await;
+                    return 0;
+                }
+            }
+            let (bytes, _) = copy_counted(&mut upstream_read, &mut client_write).await;
+            let _ = client_write.shutdown().await;
+            bytes
+        });
+
+        let (up, down) = tokio::join!(uplink, downlink);
+        (up.unwrap_or(0), down.unwrap_or(0))
+    }
+}
+
+/// 经 SOCKS5 UDP ASSOCIATE 建立的 UDP 中继
+#[derive(Debug)]
+pub struct UdpRelay {
+    /// 中继地址：数据报加 SOCKS5 UDP 头部后发往该地址
+    relay: SocketAddr,
+    /// 控制连接：关闭即结束关联，会话期间保持打开
+    _control: TcpStream,
+}
+
+impl UdpRelay {
+    /// 中继地址
+    pub fn relay_addr(&self) -> SocketAddr {
+        self.relay
+    }
+}
+
+/// 读取并解析服务端响应头
+pub async fn read_response<R: AsyncRead + Unpin>(reader: &mut R) -> Result<VlessResponse> {
+    let mut header = [0u8; 2];
+    reader.read_exact(&mut header).await?;
+    let mut buf = vec![0u8; 2 + header[1] as usize];
+    buf[..2].copy_from_slice(&header);
+    reader.read_exact(&mut buf[2..]).await?;
+    let (response, _) = VlessResponse::decode(Bytes::from(buf))?;
+    Ok(response)
+}
+
+/// 作为客户端发送 HTTP `CONNECT` 请求并等待 2xx 应答
+///
+/// 逐字节读取应答头，避免把隧道中的后续数据读入缓冲区
+async fn http_connect<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    address: &Address,
+    port: u16,
+    credentials: Option<(&str, &str)>,
+) -> Result<()> {
+    let authority = format_destination(address, port);
+    let mut request = format!("CONNECT {} HTTP/1.1\r\nHost: {}\r\n", authority, authority);
+    if let Some((username, password)) = credentials {
+        let token = BASE64.encode(format!("{}:{}", username, password));
+        request.push_str(&format!("Proxy-Authorization: Basic {}\r\n", token));
+    }
+    request.push_str("\r\n");
+    stream.write_all(request.as_bytes()).await?;
+
+    let mut response = Vec::with_capacity(128);
+    while !response.ends_with(b"\r\n\r\n") {
+        if response.len() >= MAX_CONNECT_RESPONSE {
+            return Err(anyhow!("HTTP CONNECT response header too large"));
+        }
+        response.push(stream.read_u8().await?);
+    }
+    let status_line = response
+        .split(|&b| b == b'\r')
+        .next()
+        .map(String::from_utf8_lossy)
+        .unwrap_or_default();
+    let status = status_line.split(' ').nth(1).unwrap_or_default();
+    if !status.starts_with('2') {
+        return Err(anyhow!("HTTP CONNECT failed: {}", status_line));
+    }
+    Ok(())
+}
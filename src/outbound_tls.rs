@@ -0,0 +1,342 @@
+//! 出站 TLS 模块
+//!
+//! 按 `outbound_tls` 规则为目标连接发起 TLS（可配置 SNI、证书校验与 ALPN），
+//! 用于连接仅支持 TLS 的后端；客户端看到的仍是明文字节流
+
+use crate::config::OutboundTlsRule;
+use crate::dns::domain_matches_suffix;
+use crate::protocol::Address;
+use anyhow::{anyhow, Result};
+use std::io;
+use std::net::{IpAddr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::io::{AsyncRead, AsyncWrite, ReadBuf, ReadHalf, WriteHalf};
+use tokio::net::tcp::{OwnedReadHalf, OwnedWriteHalf};
+use tokio::net::TcpStream;
+use tokio_rustls::client::TlsStream;
+use tokio_rustls::rustls::client::danger::{
+    HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier,
+};
+use tokio_rustls::rustls::crypto::{
+    ring, verify_tls12_signature, verify_tls13_signature, CryptoProvider,
+};
+use tokio_rustls::rustls::pki_types::pem::PemObject;
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use tokio_rustls::rustls::{ClientConfig, DigitallySignedStruct, RootCertStore, SignatureScheme};
+use tokio_rustls::TlsConnector;
+
+/// TLS 握手超时
+const HANDSHAKE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 编译后的规则
+struct CompiledRule {
+    dest: String,
+    port: Option<u16>,
+    sni: Option<String>,
+    connector: TlsConnector,
+}
+
+/// 出站 TLS 规则集（所有连接共享）
+pub struct OutboundTls {
+    rules: Vec<CompiledRule>,
+}
+
+impl std::fmt::Debug for OutboundTls {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("OutboundTls")
+            .field("rules", &self.rules.len())
+            .finish()
+    }
+}
+
+impl OutboundTls {
+    /// 按配置构建规则（读取 CA 文件并创建 TLS 客户端配置）
+    pub fn new(rules: &[OutboundTlsRule]) -> Result<Self> {
+        let rules = rules
+            .iter()
+            .map(|rule| {
+                let dest = rule
+                    .dest
+                    .trim()
+                    .trim_matches(|c| c == '[' || c == ']')
+                    .to_ascii_lowercase();
+                if dest.is_empty() {
+                    return Err(anyhow!("outbound_tls.dest is required"));
+                }
+                if let Some(ref sni) = rule.sni {
+                    ServerName::try_from(sni.as_str())
+                        .map_err(|_| anyhow!("Invalid outbound_tls.sni: {}", sni))?;
+                }
+                Ok(CompiledRule {
+                    dest,
+                    port: rule.port,
+                    sni: rule.sni.clone(),
+                    connector: TlsConnector::from(Arc::new(client_config(rule)?)),
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self { rules })
+    }
+
+    fn find(&self, address: &Address, port: u16) -> Option<&CompiledRule> {
+        let host = host_string(address);
+        self.rules.iter().find(|rule| {
+            rule.port.is_none_or(|p| p == port)
+                && match address {
+                    Address::Domain(_) => domain_matches_suffix(&host, &rule.dest),
+                    _ => host == rule.dest,
+                }
+        })
+    }
+
+    /// 命中规则时在已建立的连接上完成 TLS 握手，否则原样返回
+    pub async fn wrap(
+        &self,
+        stream: TcpStream,
+        address: &Address,
+        port: u16,
+    ) -> Result<TargetStream> {
+        let Some(rule) = self.find(address, port) else {
+            return Ok(TargetStream::Plain(stream));
+        };
+
+        let server_name = match rule.sni {
+            Some(ref sni) => ServerName::try_from(sni.clone())?,
+            None => match address {
+                Address::Ipv4(ip) => ServerName::from(IpAddr::V4(*ip)),
+                Address::Ipv6(ip) => ServerName::from(IpAddr::V6(*ip)),
+                Address::Domain(_) => ServerName::try_from(host_string(address))
+                    .map_err(|_| anyhow!("Invalid target name for TLS"))?,
+            },
+        };
+        let tls = tokio::time::timeout(
+            HANDSHAKE_TIMEOUT,
+            rule.connector.connect(server_name, stream),
+        )
+        .await
+        .map_err(|_| anyhow!("Outbound TLS handshake timed out"))?
+        .map_err(|e| anyhow!("Outbound TLS handshake failed: {}", e))?;
+        Ok(TargetStream::Tls(Box::new(tls)))
+    }
+}
+
+/// 目标地址的主机部分（域名小写）
+fn host_string(address: &Address) -> String {
+    match address {
+        Address::Ipv4(ip) => ip.to_string(),
+        Address::Ipv6(ip) => ip.to_string(),
+        Address::Domain(domain) => String::from_utf8_lossy(domain).to_ascii_lowercase(),
+    }
+}
+
+/// 内置根证书加上 `ca_file`（PEM，可含多个证书）中的证书；`setting` 为报错时的配置项名称
+pub(crate) fn root_store(ca_file: Option<&str>, setting: &str) -> Result<RootCertStore> {
+    let mut roots = RootCertStore {
+        roots: webpki_roots::TLS_SERVER_ROOTS.to_vec(),
+    };
+    if let Some(path) = ca_file {
+        for cert in CertificateDer::pem_file_iter(path)
+            .map_err(|e| anyhow!("Cannot read {} {}: {}", setting, path, e))?
+        {
+            let cert = cert.map_err(|e| anyhow!("Invalid certificate in {}: {}", path, e))?;
+            roots.add(cert)?;
+        }
+    }
+    Ok(roots)
+}
+
+/// 构建单条规则的 TLS 客户端配置
+fn client_config(rule: &OutboundTlsRule) -> Result<ClientConfig> {
+    let provider = Arc::new(ring::default_provider());
+    let builder = ClientConfig::builder_with_provider(Arc::clone(&provider))
+        .with_safe_default_protocol_versions()?;
+
+    let mut config = if rule.verify {
+        let roots = root_store(rule.ca_file.as_deref(), "outbound_tls.ca_file")?;
+        builder.with_root_certificates(roots).with_no_client_auth()
+    } else {
+        builder
+            .dangerous()
+            .with_custom_certificate_verifier(Arc::new(NoVerifier(provider)))
+            .with_no_client_auth()
+    };
+    config.alpn_protocols = rule.alpn.iter().map(|p| p.as_bytes().to_vec()).collect();
+    Ok(config)
+}
+
+/// 不校验证书链与主机名（仍校验握手签名），对应 `verify: false`
+#[derive(Debug)]
+struct NoVerifier(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for NoVerifier {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> std::result::Result<ServerCertVerified, tokio_rustls::rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> std::result::Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+/// 目标连接：明文 TCP 或出站 TLS
+#[derive(Debug)]
+pub enum TargetStream {
+    Plain(TcpStream),
+    Tls(Box<TlsStream<TcpStream>>),
+}
+
+impl TargetStream {
+    /// 目标地址
+    pub fn peer_addr(&self) -> io::Result<SocketAddr> {
+        match self {
+            Self::Plain(stream) => stream.peer_addr(),
+            Self::Tls(stream) => stream.get_ref().0.peer_addr(),
+        }
+    }
+
+    /// 拆分读写两半（明文连接仍使用无锁的 `into_split`）
+    pub fn into_split(self) -> (TargetReadHalf, TargetWriteHalf) {
+        match self {
+            Self::Plain(stream) => {
+                let (read, write) = stream.into_split();
+                (TargetReadHalf::Plain(read), TargetWriteHalf::Plain(write))
+            }
+            Self::Tls(stream) => {
+                let (read, write) = tokio::io::split(*stream);
+                (TargetReadHalf::Tls(read), TargetWriteHalf::Tls(write))
+            }
+        }
+    }
+}
+
+/// 目标连接读半部
+#[derive(Debug)]
+pub enum TargetReadHalf {
+    Plain(OwnedReadHalf),
+    Tls(ReadHalf<TlsStream<TcpStream>>),
+}
+
+/// 目标连接写半部
+#[derive(Debug)]
+pub enum TargetWriteHalf {
+    Plain(OwnedWriteHalf),
+    Tls(WriteHalf<TlsStream<TcpStream>>),
+}
+
+impl AsyncRead for TargetStream {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(s) => Pin::new(s).poll_read(cx, buf),
+            Self::Tls(s) => Pin::new(s.as_mut()).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for TargetStream {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::Plain(s) => Pin::new(s).poll_write(cx, buf),
+            Self::Tls(s) => Pin::new(s.as_mut()).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(s) => Pin::new(s).poll_flush(cx),
+            Self::Tls(s) => Pin::new(s.as_mut()).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(s) => Pin::new(s).poll_shutdown(cx),
+            Self::Tls(s) => Pin::new(s.as_mut()).poll_shutdown(cx),
+        }
+    }
+}
+
+impl AsyncRead for TargetReadHalf {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(s) => Pin::new(s).poll_read(cx, buf),
+            Self::Tls(s) => Pin::new(s).poll_read(cx, buf),
+        }
+    }
+}
+
+impl AsyncWrite for TargetWriteHalf {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<io::Result<usize>> {
+        match self.get_mut() {
+            Self::Plain(s) => Pin::new(s).poll_write(cx, buf),
+            Self::Tls(s) => Pin::new(s).poll_write(cx, buf),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(s) => Pin::new(s).poll_flush(cx),
+            Self::Tls(s) => Pin::new(s).poll_flush(cx),
+        }
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<io::Result<()>> {
+        match self.get_mut() {
+            Self::Plain(s) => Pin::new(s).poll_shutdown(cx),
+            Self::Tls(s) => Pin::new(s).poll_shutdown(cx),
+        }
+    }
+}
@@ -0,0 +1,128 @@
+//! 协议开销统计模块
+//!
+//! 按传输方式分别累计负载字节与协议开销字节（VLESS 请求 / 响应头、
+//! WebSocket 握手与帧头），用于比较不同传输方式的传输效率
+
+use serde::Serialize;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+/// 入站传输方式
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transport {
+    /// VLESS over TCP
+    Tcp,
+    /// VLESS over WebSocket
+    Ws,
+}
+
+impl Transport {
+    /// 传输方式名称（API 输出）
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Tcp => "tcp",
+            Self::Ws => "ws",
+        }
+    }
+}
+
+/// 单个传输方式的累计计数
+#[derive(Debug, Default)]
+struct Counters {
+    sessions: AtomicU64,
+    payload_up: AtomicU64,
+    payload_down: AtomicU64,
+    overhead_up: AtomicU64,
+    overhead_down: AtomicU64,
+}
+
+/// 单个传输方式的开销摘要
+#[derive(Debug, Clone, Serialize)]
+pub struct TransportOverhead {
+    /// 传输方式（tcp / ws）
+    pub transport: &'static str,
+    /// 已结束的会话数
+    pub sessions: u64,
+    /// 上行负载字节数（客户端 → 目标）
+    pub payload_up: u64,
+    /// 下行负载字节数（目标 → 客户端）
+    pub payload_down: u64,
+    /// 上行协议开销字节数
+    pub overhead_up: u64,
+    /// 下行协议开销字节数
+    pub overhead_down: u64,
+    /// 负载占线路总字节的比例（无流量时为 1.0）
+    pub efficiency: f64,
+}
+
+/// 协议开销统计（所有会话共享）
+#[derive(Debug, Default)]
+pub struct OverheadStats {
+    tcp: Counters,
+    ws: Counters,
+}
+
+impl OverheadStats {
+    fn counters(&self, transport: Transport) -> &Counters {
+        match transport {
+            Transport::Tcp => &self.tcp,
+            Transport::Ws => &self.ws,
+        }
+    }
+
+    /// 累计协议开销字节（握手、协议头等，可在会话中多次调用）
+    pub fn record_overhead(&self, transport: Transport, up: u64, down: u64) {
+        let counters = self.counters(transport);
+        counters.overhead_up.fetch_add(up, Ordering::Relaxed);
+        counters.overhead_down.fetch_add(down, Ordering::Relaxed);
+    }
+
+    /// 会话结束时累计负载字节与会话内的开销字节
+    pub fn record_session(&self, transport: Transport, payload: (u64, u64), overhead: (u64, u64)) {
+        let counters = self.counters(transport);
+        counters.sessions.fetch_add(1, Ordering::Relaxed);
+        counters.payload_up.fetch_add(payload.0, Ordering::Relaxed);
+        counters
+            .payload_down
+            .fetch_add(payload.1, Ordering::Relaxed);
+        self.record_overhead(transport, overhead.0, overhead.1);
+    }
+
+    /// 各传输方式的统计快照
+    pub fn snapshot(&self) -> Vec<TransportOverhead> {
+        [Transport::Tcp, Transport::Ws]
+            .into_iter()
+            .map(|transport| {
+                let counters = self.counters(transport);
+                let payload_up = counters.payload_up.load(Ordering::Relaxed);
+                let payload_down = counters.payload_down.load(Ordering::Relaxed);
+                let overhead_up = counters.overhead_up.load(Ordering::Relaxed);
+                let overhead_down = counters.overhead_down.load(Ordering::Relaxed);
+                let payload = payload_up + payload_down;
+                let total = payload + overhead_up + overhead_down;
+                TransportOverhead {
+                    transport: transport.as_str(),
+                    sessions: counters.sessions.load(Ordering::Relaxed),
+                    payload_up,
+                    payload_down,
+                    overhead_up,
+                    overhead_down,
+                    efficiency: if total == 0 {
+                        1.0
+                    } else {
+                        payload as f64 / total as f64
+                    },
+                }
+            })
+            .collect()
+    }
+}
+
+/// WebSocket 帧头长度：2 字节基础头 + 扩展长度（2 / 8 字节）+ 掩码（客户端帧 4 字节）
+pub fn ws_frame_header_len(payload_len: usize, masked: bool) -> u64 {
+    let extended = match payload_len {
+        0..=125 => 0,
+        126..=65535 => 2,
+        _ => 8,
+    };
+    2 + extended + if masked { 4 } else { 0 }
+}
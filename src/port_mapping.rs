@@ -0,0 +1,234 @@
+//! NAT-PMP 端口映射模块
+//!
+//! 家庭网络等 NAT 之后的部署：启动时向网关请求 TCP 端口映射（RFC 6886）并定期续期，
+//! 映射到的外部端口写入分享链接，服务停止时删除映射
+
+use crate::config::PortMappingConfig;
+use anyhow::{anyhow, Result};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::task::JoinHandle;
+use tracing::{debug, info, warn};
+
+/// NAT-PMP 服务端口
+pub const NAT_PMP_PORT: u16 = 5351;
+
+/// TCP 映射请求的操作码
+const OPCODE_MAP_TCP: u8 = 2;
+
+/// 响应操作码偏移
+const OPCODE_RESPONSE: u8 = 128;
+
+/// 首次重传间隔（每次翻倍）
+const INITIAL_RETRY: Duration = Duration::from_millis(250);
+
+/// 最大尝试次数
+const MAX_ATTEMPTS: u32 = 4;
+
+/// 最短续期间隔
+const MIN_RENEW_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 网关返回的映射结果
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PortMapping {
+    pub internal_port: u16,
+    pub external_port: u16,
+    /// 网关实际给予的有效期（秒）
+    pub lifetime: u32,
+}
+
+/// 编码 TCP 映射请求（`lifetime` 为 0 时表示删除映射）
+pub fn encode_request(internal_port: u16, external_port: u16, lifetime: u32) -> [u8; 12] {
+    let mut buf = [0u8; 12];
+    buf[1] = OPCODE_MAP_TCP;
+    buf[4..6].copy_from_slice(&internal_port.to_be_bytes());
+    buf[6..8].copy_from_slice(&external_port.to_be_bytes());
+    buf[8..12].copy_from_slice(&lifetime.to_be_bytes());
+    buf
+}
+
+/// 解析 TCP 映射响应
+pub fn parse_response(data: &[u8]) -> Result<PortMapping> {
+    if data.len() < 16 {
+        return Err(anyhow!("NAT-PMP response too short ({} bytes)", data.len()));
+    }
+    if data[0] != 0 || data[1] != OPCODE_RESPONSE + OPCODE_MAP_TCP {
+        return Err(anyhow!(
+            "Unexpected NAT-PMP response (version {}, opcode {})",
+            data[0],
+            data[1]
+        ));
+    }
+
+    let result = u16::from_be_bytes([data[2], data[3]]);
+    if result != 0 {
+        let reason = match result {
+            1 => "unsupported version",
+            2 => "not authorized or refused",
+            3 => "gateway network failure",
+            4 => "out of resources",
+            5 => "unsupported opcode",
+            _ => "unknown error",
+        };
+        return Err(anyhow!("NAT-PMP gateway error {}: {}", result, reason));
+    }
+
+    Ok(PortMapping {
+        internal_port: u16::from_be_bytes([data[8], data[9]]),
+        external_port: u16::from_be_bytes([data[10], data[11]]),
+        lifetime: u32::from_be_bytes([data[12], data[13], data[14], data[15]]),
+    })
+}
+
+/// 从 `/proc/net/route` 内容中解析默认网关
+pub fn parse_default_gateway(route_table: &str) -> Option<Ipv4Addr> {
+    route_table.lines().skip(1).find_map(|line| {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 3 || fields[1] != "00000000" {
+            return None;
+        }
+        // 十六进制、小端序
+        let raw = u32::from_str_radix(fields[2], 16).ok()?;
+        let gateway = Ipv4Addr::from(raw.to_le_bytes());
+        (!gateway.is_unspecified()).then_some(gateway)
+    })
+}
+
+/// 确定网关地址：配置优先，否则读取系统默认路由（仅 Linux）
+pub fn gateway_addr(config: &PortMappingConfig) -> Result<SocketAddr> {
+    if let Some(ref gateway) = config.gateway {
+        if let Ok(addr) = gateway.parse::<SocketAddr>() {
+            return Ok(addr);
+        }
+        let ip = gateway
+            .parse::<IpAddr>()
+            .map_err(|_| anyhow!("Invalid port_mapping.gateway: {}", gateway))?;
+        return Ok(SocketAddr::new(ip, NAT_PMP_PORT));
+    }
+
+    let route_table = std::fs::read_to_string("/proc/net/route")
+        .map_err(|_| anyhow!("Cannot detect default gateway, set port_mapping.gateway"))?;
+    let gateway = parse_default_gateway(&route_table)
+        .ok_or_else(|| anyhow!("No default gateway found, set port_mapping.gateway"))?;
+    Ok(SocketAddr::new(IpAddr::V4(gateway), NAT_PMP_PORT))
+}
+
+/// 向网关发送映射请求（按 RFC 6886 指数退避重传）
+pub async fn request_mapping(
+    gateway: SocketAddr,
+    internal_port: u16,
+    external_port: u16,
+    lifetime: u32,
+) -> Result<PortMapping> {
+    let bind_addr: SocketAddr = if gateway.is_ipv4() {
+        "0.0.0.0:0".parse()?
+    } else {
+        "[::]:0".parse()?
+    };
+    let socket = UdpSocket::bind(bind_addr).await?;
+    socket.connect(gateway).await?;
+
+    let request = encode_request(internal_port, external_port, lifetime);
+    let mut buf = [0u8; 64];
+    let mut wait = INITIAL_RETRY;
+    for attempt in 1..=MAX_ATTEMPTS {
+        socket.send(&request).await?;
+        match tokio::time::timeout(wait, socket.recv(&mut buf)).await {
+            Ok(Ok(n)) => return parse_response(&buf[..n]),
+            Ok(Err(e)) => return Err(anyhow!("NAT-PMP request to {} failed: {}", gateway, e)),
+            Err(_) => debug!("NAT-PMP attempt {} to {} timed out", attempt, gateway),
+        }
+        wait *= 2;
+    }
+    Err(anyhow!("NAT-PMP gateway {} did not respond", gateway))
+}
+
+/// 已建立的端口映射（后台自动续期）
+#[derive(Debug)]
+pub struct PortMapper {
+    gateway: SocketAddr,
+    mapping: PortMapping,
+    renew_task: JoinHandle<()>,
+}
+
+impl PortMapper {
+    /// 请求映射并启动续期任务
+    pub async fn start(config: &PortMappingConfig, internal_port: u16) -> Result<Self> {
+        let gateway = gateway_addr(config)?;
+        let external_port = config.external_port.unwrap_or(internal_port);
+        let mapping =
+            request_mapping(gateway, internal_port, external_port, config.lifetime).await?;
+        if mapping.external_port != external_port {
+            warn!(
+                "Gateway mapped external port {} instead of requested {}",
+                mapping.external_port, external_port
+            );
+        }
+
+        let renew_task = tokio::spawn(renew_loop(gateway, mapping, config.lifetime));
+        Ok(Self {
+            gateway,
+            mapping,
+            renew_task,
+        })
+    }
+
+    /// 网关分配的外部端口
+    pub fn external_port(&self) -> u16 {
+        self.mapping.external_port
+    }
+
+    /// 网关地址
+    pub fn gateway(&self) -> SocketAddr {
+        self.gateway
+    }
+
+    /// 停止续期并删除映射
+    pub async fn release(self) {
+        self.renew_task.abort();
+        match request_mapping(self.gateway, self.mapping.internal_port, 0, 0).await {
+            Ok(_) => info!("Port mapping removed from gateway {}", self.gateway),
+            Err(e) => warn!("Failed to remove port mapping: {}", e),
+        }
+    }
+}
+
+/// 在有效期过半时续期
+async fn renew_loop(gateway: SocketAddr, mut mapping: PortMapping, lifetime: u32) {
+    let mut interval = renew_interval(&mapping);
+    loop {
+        tokio::time::sleep(interval).await;
+
+        match request_mapping(
+            gateway,
+            mapping.internal_port,
+            mapping.external_port,
+            lifetime,
+        )
+        .await
+        {
+            Ok(renewed) => {
+                if renewed.external_port != mapping.external_port {
+                    warn!(
+                        "Gateway changed external port from {} to {}, share links are stale",
+                        mapping.external_port, renewed.external_port
+                    );
+                }
+                debug!("Port mapping renewed for {}s", renewed.lifetime);
+                mapping = renewed;
+                interval = renew_interval(&mapping);
+            }
+            Err(e) => {
+                // 失败后尽快重试，避免映射过期
+                warn!("Failed to renew port mapping: {}", e);
+                interval = MIN_RENEW_INTERVAL;
+            }
+        }
+    }
+}
+
+/// 续期间隔：有效期的一半，不短于 30 秒
+fn renew_interval(mapping: &PortMapping) -> Duration {
+    Duration::from_secs(u64::from(mapping.lifetime / 2)).max(MIN_RENEW_INTERVAL)
+}
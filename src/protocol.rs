@@ -1,7 +1,7 @@
 use anyhow::{anyhow, Result};
 use bytes::{Buf, BufMut, Bytes, BytesMut};
 use futures_util::SinkExt;
-use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use tokio::io::AsyncWriteExt;
 use tokio_tungstenite::tungstenite::Message;
 use tracing::{debug, warn};
@@ -61,6 +61,15 @@ pub enum Address {
     Ipv6(Ipv6Addr),
 }
 
+impl From<IpAddr> for Address {
+    fn from(ip: IpAddr) -> Self {
+        match ip {
+            IpAddr::V4(ip) => Address::Ipv4(ip),
+            IpAddr::V6(ip) => Address::Ipv6(ip),
+        }
+    }
+}
+
 impl Address {
     pub fn decode(buf: &mut Bytes) -> Result<Self> {
         if buf.is_empty() {
@@ -108,6 +117,28 @@ impl Address {
             Address::Domain(_) => Err(anyhow!("Cannot convert domain to socket address directly")),
         }
     }
+
+    /// 编码为 VLESS 地址格式（地址类型 + 地址），与 `decode` 互逆；域名超过 255 字节时返回错误
+    pub fn encode(&self, buf: &mut BytesMut) -> Result<()> {
+        match self {
+            Address::Ipv4(addr) => {
+                buf.put_u8(AddressType::Ipv4 as u8);
+                buf.put_slice(&addr.octets());
+            }
+            Address::Domain(domain) => {
+                let len = u8::try_from(domain.len())
+                    .map_err(|_| anyhow!("Domain name too long: {} bytes", domain.len()))?;
+                buf.put_u8(AddressType::Domain as u8);
+                buf.put_u8(len);
+                buf.put_slice(domain);
+            }
+            Address::Ipv6(addr) => {
+                buf.put_u8(AddressType::Ipv6 as u8);
+                buf.put_slice(&addr.octets());
+            }
+        }
+        Ok(())
+    }
 }
 
 /// VLESS请求
@@ -185,6 +216,32 @@ impl VlessRequest {
 
         Ok((request, buf))
     }
+
+    /// 构造客户端请求（正式版本，无附加数据）
+    pub fn new(uuid: Uuid, command: Command, address: Address, port: u16) -> Self {
+        Self {
+            version: VLESS_VERSION_RELEASE,
+            uuid,
+            addons_length: 0,
+            addons: Bytes::new(),
+            command,
+            port,
+            address,
+        }
+    }
+
+    /// 编码请求头（客户端模式使用），与 `decode` 互逆；目标域名过长时返回错误
+    pub fn encode(&self) -> Result<Bytes> {
+        let mut buf = BytesMut::with_capacity(22 + self.addons.len() + 256);
+        buf.put_u8(self.version);
+        buf.put_slice(self.uuid.as_bytes());
+        buf.put_u8(self.addons.len() as u8);
+        buf.put_slice(&self.addons);
+        buf.put_u8(self.command as u8);
+        buf.put_u16(self.port);
+        self.address.encode(&mut buf)?;
+        Ok(buf.freeze())
+    }
 }
 
 /// VLESS响应
@@ -204,8 +261,13 @@ impl VlessResponse {
         }
     }
 
+    /// 编码后的字节数（版本 + 附加信息长度 + 附加信息）
+    pub fn encoded_len(&self) -> usize {
+        2 + self.addons.len()
+    }
+
     pub fn encode(&self) -> Bytes {
-        let mut buf = BytesMut::with_capacity(2 + self.addons.len());
+        let mut buf = BytesMut::with_capacity(self.encoded_len());
         buf.put_u8(self.version);
         buf.put_u8(self.addons_length);
         if !self.addons.is_empty() {
@@ -213,6 +275,52 @@ impl VlessResponse {
         }
         buf.freeze()
     }
+
+    /// 解析服务端响应头，返回响应与其后的数据
+    pub fn decode(mut buf: Bytes) -> Result<(Self, Bytes)> {
+        if buf.len() < 2 {
+            return Err(anyhow!("Buffer too short for VLESS response"));
+        }
+        let version = buf.get_u8();
+        if version != VLESS_VERSION_BETA && version != VLESS_VERSION_RELEASE {
+            return Err(anyhow!("Unsupported VLESS version: {}", version));
+        }
+        let addons_length = buf.get_u8();
+        if buf.len() < addons_length as usize {
+            return Err(anyhow!("Invalid addons length"));
+        }
+        let addons = buf.split_to(addons_length as usize);
+        let response = VlessResponse {
+            version,
+            addons_length,
+            addons,
+        };
+        Ok((response, buf))
+    }
+}
+
+/// UDP over TCP 每个包的长度前缀字节数
+pub const UDP_LENGTH_PREFIX: usize = 2;
+
+/// 编码 UDP over TCP 包：2 字节大端长度 + 载荷（载荷不超过 65535 字节）
+pub fn encode_udp_packet(payload: &[u8]) -> Bytes {
+    let mut buf = BytesMut::with_capacity(UDP_LENGTH_PREFIX + payload.len());
+    buf.put_u16(payload.len() as u16);
+    buf.put_slice(payload);
+    buf.freeze()
+}
+
+/// 从缓冲区取出一个完整的 UDP over TCP 包；数据不足时返回 None，等待后续读取
+pub fn take_udp_packet(buf: &mut BytesMut) -> Option<Bytes> {
+    if buf.len() < UDP_LENGTH_PREFIX {
+        return None;
+    }
+    let len = u16::from_be_bytes([buf[0], buf[1]]) as usize;
+    if buf.len() < UDP_LENGTH_PREFIX + len {
+        return None;
+    }
+    buf.advance(UDP_LENGTH_PREFIX);
+    Some(buf.split_to(len).freeze())
 }
 
 /// 验证 VLESS 请求的用户身份
@@ -2,6 +2,7 @@
 //!
 //! 使用多个 API 接口并发获取公网 IP，返回首个成功结果
 
+use crate::config::ServerSettings;
 use std::time::Duration;
 use tokio::sync::mpsc;
 use tracing::{debug, warn};
@@ -110,3 +111,34 @@ pub async fn fetch_public_ip_with_timeout(timeout_secs: u64) -> Option<PublicIp>
         }
     }
 }
+
+/// 根据配置确定链接使用的公网地址
+///
+/// 优先级：`server.domain` > `server.public_ip` > 自动探测；
+/// `server.detect_public_ip = false` 时不访问任何外部服务，返回 None（使用监听地址）
+///
+/// # Arguments
+/// * `settings` - 服务器配置段
+/// * `timeout_secs` - 自动探测超时时间（秒）
+pub async fn resolve_public_host(settings: &ServerSettings, timeout_secs: u64) -> Option<PublicIp> {
+    if let Some(ref domain) = settings.domain {
+        return Some(PublicIp {
+            ip: domain.clone(),
+            source: "server.domain".to_string(),
+        });
+    }
+
+    if let Some(ref ip) = settings.public_ip {
+        return Some(PublicIp {
+            ip: ip.clone(),
+            source: "server.public_ip".to_string(),
+        });
+    }
+
+    if !settings.detect_public_ip {
+        debug!("Public IP detection disabled by config");
+        return None;
+    }
+
+    fetch_public_ip_with_timeout(timeout_secs).await
+}
@@ -0,0 +1,259 @@
+//! 入站就绪状态模块
+//!
+//! 监听端口被短暂占用时（如重启竞争）按退避重试绑定；端口转发入站重试后
+//! 仍失败时不影响其他入站，记录为降级状态并通过 `/readyz` 报告。
+//! accept 持续失败（文件描述符耗尽等）时暂停接受连接并按退避重试；
+//! 运营者可通过管理 API 或 SIGUSR2 暂停接受新的代理会话（已有会话与信息页不受影响）
+
+use anyhow::Result;
+use serde::Serialize;
+use std::future::Future;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::Duration;
+use tracing::warn;
+
+/// 重试间隔上限
+const MAX_RETRY_DELAY: Duration = Duration::from_secs(10);
+
+/// accept 暂停时间下限与上限
+const MIN_ACCEPT_PAUSE: Duration = Duration::from_millis(5);
+const MAX_ACCEPT_PAUSE: Duration = Duration::from_secs(1);
+
+/// 非资源耗尽类 accept 错误连续出现多少次后开始暂停（避免未知错误空转）
+const TRANSIENT_ACCEPT_ERRORS: u64 = 8;
+
+/// 表示资源耗尽的 accept 错误码
+#[cfg(unix)]
+const EXHAUSTED_ERRORS: &[i32] = &[libc::EMFILE, libc::ENFILE, libc::ENOBUFS, libc::ENOMEM];
+#[cfg(windows)]
+const EXHAUSTED_ERRORS: &[i32] = &[10024, 10055]; // WSAEMFILE, WSAENOBUFS
+#[cfg(not(any(unix, windows)))]
+const EXHAUSTED_ERRORS: &[i32] = &[];
+
+/// 绑定重试策略：首次失败后等待 `delay`，之后每次翻倍（上限 10 秒）
+#[derive(Debug, Clone)]
+pub struct BindRetry {
+    /// 首次绑定失败后的最大重试次数，0 表示不重试
+    pub retries: u32,
+    /// 首次重试前的等待时间
+    pub delay: Duration,
+}
+
+impl BindRetry {
+    pub fn new(retries: u32, delay_ms: u64) -> Self {
+        Self {
+            retries,
+            delay: Duration::from_millis(delay_ms),
+        }
+    }
+}
+
+impl Default for BindRetry {
+    fn default() -> Self {
+        Self::new(5, 500)
+    }
+}
+
+/// 按重试策略执行绑定，`what` 用于日志与错误信息
+pub async fn bind_with_retry<T, F, Fut>(what: &str, retry: &BindRetry, mut bind: F) -> Result<T>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut delay = retry.delay;
+    let mut attempt = 0;
+    loop {
+        match bind().await {
+            Ok(bound) => return Ok(bound),
+            // 只重试 IO 错误（端口占用、地址暂不可用等），配置错误直接返回
+            Err(e) if attempt < retry.retries && is_io_error(&e) => {
+                attempt += 1;
+                warn!(
+                    "Failed to bind {}: {}, retrying in {:?} ({}/{})",
+                    what, e, delay, attempt, retry.retries
+                );
+                tokio::time::sleep(delay).await;
+                delay = (delay * 2).min(MAX_RETRY_DELAY);
+            }
+            Err(e) if attempt == 0 => return Err(e.context(format!("Failed to bind {}", what))),
+            Err(e) => {
+                return Err(e.context(format!("Failed to bind {} after {} retries", what, attempt)))
+            }
+        }
+    }
+}
+
+/// 错误是否来自 IO（可重试；配置错误不重试）
+pub fn is_io_error(error: &anyhow::Error) -> bool {
+    error.downcast_ref::<std::io::Error>().is_some()
+}
+
+/// accept 错误是否为资源耗尽（文件描述符、内核缓冲区或内存不足）
+pub fn is_resource_exhausted(error: &std::io::Error) -> bool {
+    error
+        .raw_os_error()
+        .is_some_and(|code| EXHAUSTED_ERRORS.contains(&code))
+}
+
+/// accept 失败退避：资源耗尽时立即暂停接受连接，其他错误连续出现
+/// 多次后才暂停；暂停时间从 5ms 起翻倍（上限 1 秒），成功接受连接后复位
+#[derive(Debug, Default)]
+pub struct AcceptBackoff {
+    pause: Duration,
+    streak: u64,
+}
+
+impl AcceptBackoff {
+    /// 记录一次失败，返回需要暂停的时间
+    pub fn on_error(&mut self, error: &std::io::Error) -> Option<Duration> {
+        self.streak += 1;
+        if !is_resource_exhausted(error) && self.streak < TRANSIENT_ACCEPT_ERRORS {
+            return None;
+        }
+        self.pause = if self.pause.is_zero() {
+            MIN_ACCEPT_PAUSE
+        } else {
+            (self.pause * 2).min(MAX_ACCEPT_PAUSE)
+        };
+        Some(self.pause)
+    }
+
+    /// 记录一次成功，返回此前连续失败的次数
+    pub fn on_success(&mut self) -> u64 {
+        self.pause = Duration::ZERO;
+        std::mem::take(&mut self.streak)
+    }
+
+    /// 当前连续失败次数
+    pub fn streak(&self) -> u64 {
+        self.streak
+    }
+}
+
+/// 单个入站的状态
+#[derive(Debug, Clone, Serialize)]
+pub struct InboundStatus {
+    /// 入站名称（`main` 或 `forward:<name>`）
+    pub name: String,
+    /// 监听地址
+    pub listen: String,
+    /// 是否正在监听
+    pub ready: bool,
+    /// 绑定失败原因
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// 各入站的就绪状态（所有连接共享）
+#[derive(Debug, Default)]
+pub struct Readiness {
+    inbounds: Mutex<Vec<InboundStatus>>,
+    accept_failures: AtomicU64,
+}
+
+impl Readiness {
+    fn record(&self, status: InboundStatus) {
+        let mut inbounds = self.inbounds.lock().unwrap_or_else(|e| e.into_inner());
+        inbounds.retain(|s| s.name != status.name);
+        inbounds.push(status);
+    }
+
+    /// 记录入站已开始监听
+    pub fn set_ready(&self, name: &str, listen: &str) {
+        self.record(InboundStatus {
+            name: name.to_string(),
+            listen: listen.to_string(),
+            ready: true,
+            error: None,
+        });
+    }
+
+    /// 记录入站绑定失败（服务继续运行，状态为降级）
+    pub fn set_failed(&self, name: &str, listen: &str, error: &str) {
+        self.record(InboundStatus {
+            name: name.to_string(),
+            listen: listen.to_string(),
+            ready: false,
+            error: Some(error.to_string()),
+        });
+    }
+
+    /// 是否存在未能监听的入站
+    pub fn is_degraded(&self) -> bool {
+        self.inbounds().iter().any(|s| !s.ready)
+    }
+
+    /// 累计一次 accept 失败
+    pub fn record_accept_failure(&self) {
+        self.accept_failures.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// 启动以来 accept 失败的总次数
+    pub fn accept_failures(&self) -> u64 {
+        self.accept_failures.load(Ordering::Relaxed)
+    }
+
+    /// 按记录顺序返回全部入站状态
+    pub fn inbounds(&self) -> Vec<InboundStatus> {
+        self.inbounds
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .clone()
+    }
+}
+
+/// 新代理会话的准入开关：暂停时拒绝新的 VLESS 连接与端口转发会话，已建立的会话继续运行
+#[derive(Debug, Default)]
+pub struct AcceptGate {
+    paused: AtomicBool,
+    rejected: AtomicU64,
+}
+
+/// 准入开关状态
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct GateStatus {
+    /// 是否已暂停接受新会话
+    pub paused: bool,
+    /// 启动以来因暂停而拒绝的连接数
+    pub rejected: u64,
+}
+
+impl AcceptGate {
+    /// 暂停接受新会话，返回状态是否改变
+    pub fn pause(&self) -> bool {
+        !self.paused.swap(true, Ordering::Relaxed)
+    }
+
+    /// 恢复接受新会话，返回状态是否改变
+    pub fn resume(&self) -> bool {
+        self.paused.swap(false, Ordering::Relaxed)
+    }
+
+    /// 切换暂停状态，返回切换后是否暂停
+    pub fn toggle(&self) -> bool {
+        !self.paused.fetch_xor(true, Ordering::Relaxed)
+    }
+
+    /// 是否已暂停
+    pub fn is_paused(&self) -> bool {
+        self.paused.load(Ordering::Relaxed)
+    }
+
+    /// 检查是否接受新会话；暂停时计入拒绝数并返回 false
+    pub fn admit(&self) -> bool {
+        if self.is_paused() {
+            self.rejected.fetch_add(1, Ordering::Relaxed);
+            return false;
+        }
+        true
+    }
+
+    /// 当前状态
+    pub fn status(&self) -> GateStatus {
+        GateStatus {
+            paused: self.is_paused(),
+            rejected: self.rejected.load(Ordering::Relaxed),
+        }
+    }
+}
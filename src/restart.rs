@@ -0,0 +1,219 @@
+//! 定时重启模块
+//!
+//! 按 cron 计划（本地时间）在活动连接数不超过阈值时触发重启：主流程停止接受新连接、
+//! 等待现有连接结束后以配置的退出码退出，由 systemd 等服务管理器重新拉起。
+//! 到点时连接数超过阈值则定期重试，超过推迟时限后跳过本次
+
+use crate::config::RestartConfig;
+use crate::runtime_stats::ListenerTasks;
+use anyhow::{anyhow, Result};
+use chrono::{Datelike, Duration as ChronoDuration, Local, NaiveDateTime, TimeZone, Timelike};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{debug, info, warn};
+
+/// 连接数超过阈值时的重试间隔
+const RECHECK_INTERVAL: Duration = Duration::from_secs(30);
+
+/// 排空期间检查连接数的间隔
+const DRAIN_POLL_INTERVAL: Duration = Duration::from_millis(200);
+
+/// 查找下一次触发时间的最大范围（约 4 年，覆盖 2 月 29 日）
+const MAX_SEARCH_DAYS: i64 = 366 * 4 + 1;
+
+/// cron 字段：允许的取值位图
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Field {
+    bits: u64,
+    /// 是否以 `*` 开头（日与周同时受限时按“或”匹配）
+    any: bool,
+}
+
+impl Field {
+    fn contains(&self, value: u32) -> bool {
+        self.bits & (1 << value) != 0
+    }
+
+    /// 解析逗号分隔的列表，元素为 `*`、`n`、`a-b`，可带步长 `/s`
+    fn parse(text: &str, name: &str, min: u32, max: u32) -> Result<Self> {
+        let mut bits = 0u64;
+        for part in text.split(',') {
+            let (range, step) = match part.split_once('/') {
+                Some((range, step)) => {
+                    let step: u32 = step
+                        .parse()
+                        .ok()
+                        .filter(|&s| s > 0)
+                        .ok_or_else(|| anyhow!("invalid step in {} field: {}", name, part))?;
+                    (range, step)
+                }
+                None => (part, 1),
+            };
+            let value = |v: &str| {
+                v.parse::<u32>()
+                    .ok()
+                    .filter(|v| (min..=max).contains(v))
+                    .ok_or_else(|| {
+                        anyhow!("{} must be between {} and {}: {}", name, min, max, part)
+                    })
+            };
+            let (start, end) = match range {
+                "*" => (min, max),
+                _ => match range.split_once('-') {
+                    Some((a, b)) => (value(a)?, value(b)?),
+                    // 带步长的单个值表示从该值到最大值
+                    None if step > 1 => (value(range)?, max),
+                    None => {
+                        let v = value(range)?;
+                        (v, v)
+                    }
+                },
+            };
+            if start > end {
+                return Err(anyhow!("invalid range in {} field: {}", name, part));
+            }
+            for v in (start..=end).step_by(step as usize) {
+                bits |= 1 << v;
+            }
+        }
+        Ok(Self {
+            bits,
+            any: text.starts_with('*'),
+        })
+    }
+}
+
+/// cron 计划：分 时 日 月 周（0 与 7 均为周日）
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    minute: Field,
+    hour: Field,
+    day: Field,
+    month: Field,
+    weekday: Field,
+}
+
+impl Schedule {
+    /// 解析 5 字段 cron 表达式或 `@hourly` / `@daily` / `@weekly`
+    pub fn parse(expr: &str) -> Result<Self> {
+        let expr = match expr.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            other => other,
+        };
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(anyhow!(
+                "expected 5 fields (minute hour day month weekday), got {}",
+                fields.len()
+            ));
+        };
+        let mut weekday = Field::parse(weekday, "weekday", 0, 7)?;
+        if weekday.contains(7) {
+            weekday.bits = (weekday.bits | 1) & !(1 << 7);
+        }
+        Ok(Self {
+            minute: Field::parse(minute, "minute", 0, 59)?,
+            hour: Field::parse(hour, "hour", 0, 23)?,
+            day: Field::parse(day, "day", 1, 31)?,
+            month: Field::parse(month, "month", 1, 12)?,
+            weekday,
+        })
+    }
+
+    /// 日期是否满足日、月、周字段（日与周都受限时满足其一即可）
+    fn matches_date(&self, time: &NaiveDateTime) -> bool {
+        if !self.month.contains(time.month()) {
+            return false;
+        }
+        let day = self.day.contains(time.day());
+        let weekday = self.weekday.contains(time.weekday().num_days_from_sunday());
+        match (self.day.any, self.weekday.any) {
+            (false, false) => day || weekday,
+            _ => day && weekday,
+        }
+    }
+
+    /// `after` 之后（不含）的下一次触发时间，计划永不触发（如 2 月 30 日）时返回 None
+    pub fn next_after(&self, after: NaiveDateTime) -> Option<NaiveDateTime> {
+        let start = after.with_second(0)?.with_nanosecond(0)? + ChronoDuration::minutes(1);
+        let mut date = start.date();
+        let last = date + ChronoDuration::days(MAX_SEARCH_DAYS);
+        while date <= last {
+            let midnight = date.and_hms_opt(0, 0, 0)?;
+            if self.matches_date(&midnight) {
+                for hour in (0..24).filter(|&h| self.hour.contains(h)) {
+                    for minute in (0..60).filter(|&m| self.minute.contains(m)) {
+                        let time = date.and_hms_opt(hour, minute, 0)?;
+                        if time >= start {
+                            return Some(time);
+                        }
+                    }
+                }
+            }
+            date = date.succ_opt()?;
+        }
+        None
+    }
+}
+
+/// 等待下一次满足条件的计划重启（连接数不超过阈值）后返回；计划永不触发时一直等待
+pub async fn wait_for_restart(schedule: &Schedule, config: &RestartConfig, tasks: &ListenerTasks) {
+    let mut after = Local::now().naive_local();
+    loop {
+        let Some(next) = schedule.next_after(after) else {
+            warn!("Restart schedule never fires, scheduled restarts disabled");
+            std::future::pending::<()>().await;
+            return;
+        };
+        after = next;
+        // 夏令时跳过的时刻不存在，直接找下一次
+        let Some(at) = Local.from_local_datetime(&next).earliest() else {
+            continue;
+        };
+        info!(
+            "Next scheduled restart at {}",
+            at.format("%Y-%m-%d %H:%M %z")
+        );
+        let wait = (at - Local::now()).to_std().unwrap_or_default();
+        tokio::time::sleep(wait).await;
+
+        let deadline = Instant::now() + Duration::from_secs(config.defer_minutes * 60);
+        loop {
+            let active = tasks.active_total();
+            if active <= config.max_active_connections {
+                info!(
+                    "Scheduled restart triggered with {} active connections",
+                    active
+                );
+                return;
+            }
+            if Instant::now() >= deadline {
+                warn!(
+                    "Skipping scheduled restart: {} active connections above threshold {} for {} minutes",
+                    active, config.max_active_connections, config.defer_minutes
+                );
+                break;
+            }
+            debug!(
+                "Deferring scheduled restart: {} active connections (threshold {})",
+                active, config.max_active_connections
+            );
+            tokio::time::sleep(RECHECK_INTERVAL).await;
+        }
+        after = after.max(Local::now().naive_local());
+    }
+}
+
+/// 等待所有连接任务结束，返回超时后仍在运行的任务数
+pub async fn drain(tasks: &ListenerTasks, timeout: Duration) -> usize {
+    let deadline = Instant::now() + timeout;
+    loop {
+        let active = tasks.active_total();
+        if active == 0 || Instant::now() >= deadline {
+            return active;
+        }
+        tokio::time::sleep(DRAIN_POLL_INTERVAL).await;
+    }
+}
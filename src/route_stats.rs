@@ -0,0 +1,137 @@
+//! 路由流量统计模块
+//!
+//! 按会话实际经过的路由（`direct`、`block` 或出站标识）与命中的路由规则累计会话数和流量，
+//! 用于确认各路由承载的流量以及规则是否按预期生效
+
+use serde::Serialize;
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+/// 直连路由标识
+pub const ROUTE_DIRECT: &str = "direct";
+/// 拦截路由标识（路由规则、拦截列表与目标地址策略拒绝的会话）
+pub const ROUTE_BLOCK: &str = "block";
+
+/// 会话经过的路由
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize)]
+pub struct SessionRoute {
+    /// 路由标识：`direct`、`block` 或出站标识
+    pub route: String,
+    /// 命中的路由规则序号（未命中规则时为空）
+    pub rule: Option<usize>,
+}
+
+impl SessionRoute {
+    pub fn new(route: &str, rule: Option<usize>) -> Self {
+        Self {
+            route: route.to_string(),
+            rule,
+        }
+    }
+}
+
+/// 单个路由（或规则）的累计计数
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct RouteCounters {
+    /// 会话数（拦截的会话只计数，不产生流量）
+    pub sessions: u64,
+    /// 上行字节数（客户端 → 目标）
+    pub bytes_up: u64,
+    /// 下行字节数（目标 → 客户端）
+    pub bytes_down: u64,
+}
+
+impl RouteCounters {
+    fn add(&mut self, other: &RouteCounters) {
+        self.sessions += other.sessions;
+        self.bytes_up += other.bytes_up;
+        self.bytes_down += other.bytes_down;
+    }
+}
+
+/// 按路由汇总的统计
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RouteSummary {
+    pub route: String,
+    #[serde(flatten)]
+    pub counters: RouteCounters,
+}
+
+/// 按路由规则的统计
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RuleSummary {
+    /// 规则序号（`routing.rules` 中的位置，从 0 开始）
+    pub rule: usize,
+    pub route: String,
+    #[serde(flatten)]
+    pub counters: RouteCounters,
+}
+
+/// 路由统计快照
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct RouteSnapshot {
+    /// 按路由汇总，按总流量降序
+    pub routes: Vec<RouteSummary>,
+    /// 按命中的路由规则，按规则序号升序
+    pub rules: Vec<RuleSummary>,
+}
+
+/// 路由流量统计（所有会话共享）
+#[derive(Debug, Default)]
+pub struct RouteStats {
+    counters: Mutex<HashMap<SessionRoute, RouteCounters>>,
+}
+
+impl RouteStats {
+    /// 会话结束时累计流量
+    pub fn record_session(&self, route: &SessionRoute, bytes_up: u64, bytes_down: u64) {
+        let mut counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        counters
+            .entry(route.clone())
+            .or_default()
+            .add(&RouteCounters {
+                sessions: 1,
+                bytes_up,
+                bytes_down,
+            });
+    }
+
+    /// 记录一个被拒绝的会话，`rule` 为拦截它的路由规则（拦截列表与目标地址策略为空）
+    pub fn record_blocked(&self, rule: Option<usize>) {
+        self.record_session(&SessionRoute::new(ROUTE_BLOCK, rule), 0, 0);
+    }
+
+    /// 统计快照
+    pub fn snapshot(&self) -> RouteSnapshot {
+        let counters = self.counters.lock().unwrap_or_else(|e| e.into_inner());
+        let mut routes: HashMap<&str, RouteCounters> = HashMap::new();
+        let mut rules: Vec<RuleSummary> = Vec::new();
+        for (key, value) in counters.iter() {
+            routes.entry(&key.route).or_default().add(value);
+            if let Some(rule) = key.rule {
+                rules.push(RuleSummary {
+                    rule,
+                    route: key.route.to_string(),
+                    counters: *value,
+                });
+            }
+        }
+
+        let mut routes: Vec<RouteSummary> = routes
+            .into_iter()
+            .map(|(route, counters)| RouteSummary {
+                route: route.to_string(),
+                counters,
+            })
+            .collect();
+        routes.sort_by(|a, b| {
+            let total = |c: &RouteCounters| c.bytes_up + c.bytes_down;
+            total(&b.counters)
+                .cmp(&total(&a.counters))
+                .then_with(|| b.counters.sessions.cmp(&a.counters.sessions))
+                .then_with(|| a.route.cmp(&b.route))
+        });
+        rules.sort_by(|a, b| a.rule.cmp(&b.rule).then_with(|| a.route.cmp(&b.route)));
+        RouteSnapshot { routes, rules }
+    }
+}
@@ -0,0 +1,703 @@
+//! 路由模块
+//!
+//! 连接目标前按 `routing.rules` 为会话选择动作：直连、拦截或经指定出站。
+//! 规则按顺序匹配，命中第一条即停止；未命中时使用默认行为（`server.outbound` 或直连）。
+//! 域名条件只匹配域名目标，IP 条件（网段、`geoip:`）只匹配 IP 目标，路由本身不解析域名
+
+use crate::config::{OutboundConfig, OutboundProtocol, RoutingConfig};
+use crate::dns::domain_matches_suffix;
+use crate::geoip::{self, GeoIpDb};
+use crate::outbound::Outbound;
+use crate::protocol::Address;
+use anyhow::{anyhow, Result};
+use std::cell::OnceCell;
+use std::collections::{HashMap, HashSet};
+use std::net::IpAddr;
+use std::ops::RangeInclusive;
+use std::path::Path;
+use std::sync::Arc;
+use uuid::Uuid;
+
+/// 正则量词的重复次数上限
+const MAX_REPEAT: usize = 1000;
+/// 正则编译后的指令数上限（计数量词按次数展开）
+const MAX_PROGRAM: usize = 20_000;
+
+/// 路由匹配结果：命中时为（规则序号，动作），未命中任何规则时为 None
+pub type RouteMatch = Option<(usize, RouteAction)>;
+
+/// 路由动作
+#[derive(Debug, Clone)]
+pub enum RouteAction {
+    /// 直连目标（不经 `server.outbound`）
+    Direct,
+    /// 拦截
+    Block,
+    /// 经指定的 SOCKS5 / HTTP 出站连接
+    Outbound(Arc<Outbound>),
+}
+
+/// IP 条件
+#[derive(Debug)]
+enum IpMatcher {
+    Cidr(Cidr),
+    /// `geoip:private`
+    Private,
+    /// `geoip:<国家代码>`（小写）
+    Country(String),
+}
+
+impl IpMatcher {
+    fn matches(&self, ip: IpAddr, context: &MatchContext) -> bool {
+        match self {
+            Self::Cidr(cidr) => cidr.contains(ip),
+            Self::Private => geoip::is_private(ip),
+            Self::Country(code) => context.country(ip) == Some(code.as_str()),
+        }
+    }
+}
+
+/// 单次路由查询的上下文：目标 IP 的国家只在需要时查询一次
+struct MatchContext<'a> {
+    geoip: Option<&'a GeoIpDb>,
+    country: OnceCell<Option<String>>,
+}
+
+impl MatchContext<'_> {
+    fn country(&self, ip: IpAddr) -> Option<&str> {
+        self.country
+            .get_or_init(|| self.geoip?.country(ip))
+            .as_deref()
+    }
+}
+
+/// 编译后的规则
+#[derive(Debug)]
+struct CompiledRule {
+    domain_suffix: Vec<String>,
+    domain_keyword: Vec<String>,
+    domain_regex: Vec<Regex>,
+    ip_cidr: Vec<IpMatcher>,
+    ports: Vec<RangeInclusive<u16>>,
+    users: HashSet<Uuid>,
+    action: RouteAction,
+}
+
+impl CompiledRule {
+    fn has_destination(&self) -> bool {
+        !(self.domain_suffix.is_empty()
+            && self.domain_keyword.is_empty()
+            && self.domain_regex.is_empty()
+            && self.ip_cidr.is_empty())
+    }
+
+    fn matches_destination(&self, address: &Address, context: &MatchContext) -> bool {
+        match address {
+            Address::Domain(domain) => {
+                let domain = String::from_utf8_lossy(domain)
+                    .trim_end_matches('.')
+                    .to_ascii_lowercase();
+                self.domain_suffix
+                    .iter()
+                    .any(|suffix| domain_matches_suffix(&domain, suffix))
+                    || self.domain_keyword.iter().any(|k| domain.contains(k))
+                    || self.domain_regex.iter().any(|r| r.is_match(&domain))
+            }
+            Address::Ipv4(ip) => self
+                .ip_cidr
+                .iter()
+                .any(|m| m.matches(IpAddr::V4(*ip), context)),
+            Address::Ipv6(ip) => self
+                .ip_cidr
+                .iter()
+                .any(|m| m.matches(IpAddr::V6(*ip), context)),
+        }
+    }
+
+    fn matches(&self, uuid: &Uuid, address: &Address, port: u16, context: &MatchContext) -> bool {
+        (!self.has_destination() || self.matches_destination(address, context))
+            && (self.ports.is_empty() || self.ports.iter().any(|r| r.contains(&port)))
+            && (self.users.is_empty() || self.users.contains(uuid))
+    }
+}
+
+/// 路由规则集（所有连接共享）
+#[derive(Debug, Default)]
+pub struct Router {
+    rules: Vec<CompiledRule>,
+    geoip: Option<GeoIpDb>,
+}
+
+impl Router {
+    /// 编译路由规则；`outbound:<tag>` 须引用 `outbounds` 中的 SOCKS5 / HTTP 出站，
+    /// 设置 `geoip_file` 时读取 GeoIP 数据库
+    pub fn new(config: &RoutingConfig, outbounds: &[OutboundConfig]) -> Result<Self> {
+        let geoip = match config.geoip_file {
+            Some(ref path) => Some(
+                GeoIpDb::open(Path::new(path)).map_err(|e| anyhow!("routing.geoip_file: {}", e))?,
+            ),
+            None => None,
+        };
+        let mut resolved: HashMap<&str, Arc<Outbound>> = HashMap::new();
+        let mut rules = Vec::with_capacity(config.rules.len());
+        for (index, rule) in config.rules.iter().enumerate() {
+            let field = |name: &str| format!("routing.rules[{}].{}", index, name);
+
+            let action = match rule.action.trim() {
+                "direct" => RouteAction::Direct,
+                "block" => RouteAction::Block,
+                action => {
+                    let Some(tag) = action.strip_prefix("outbound:") else {
+                        return Err(anyhow!(
+                            "{}: unknown action '{}' (expected direct, block or outbound:<tag>)",
+                            field("action"),
+                            action
+                        ));
+                    };
+                    let outbound = match outbounds.iter().find(|o| o.tag == tag) {
+                        None => {
+                            return Err(anyhow!("{}: unknown outbound '{}'", field("action"), tag))
+                        }
+                        Some(o) if o.protocol == OutboundProtocol::Vless => {
+                            return Err(anyhow!(
+                                "{}: outbound '{}' must be socks or http",
+                                field("action"),
+                                tag
+                            ))
+                        }
+                        Some(o) => o,
+                    };
+                    let outbound = match resolved.get(outbound.tag.as_str()) {
+                        Some(existing) => Arc::clone(existing),
+                        None => {
+                            let created = Arc::new(Outbound::from_config(outbound)?);
+                            resolved.insert(outbound.tag.as_str(), Arc::clone(&created));
+                            created
+                        }
+                    };
+                    RouteAction::Outbound(outbound)
+                }
+            };
+
+            let domain_regex = rule
+                .domain_regex
+                .iter()
+                .map(|pattern| {
+                    Regex::new(pattern).map_err(|e| {
+                        anyhow!(
+                            "{}: invalid regex '{}': {}",
+                            field("domain_regex"),
+                            pattern,
+                            e
+                        )
+                    })
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let ip_cidr = rule
+                .ip_cidr
+                .iter()
+                .map(|entry| match entry.trim().strip_prefix("geoip:") {
+                    Some("private") => Ok(IpMatcher::Private),
+                    Some(_) if geoip.is_none() => Err(anyhow!(
+                        "{}: {} requires routing.geoip_file",
+                        field("ip_cidr"),
+                        entry
+                    )),
+                    Some(code)
+                        if code.len() == 2 && code.bytes().all(|b| b.is_ascii_alphabetic()) =>
+                    {
+                        Ok(IpMatcher::Country(code.to_ascii_lowercase()))
+                    }
+                    Some(_) => Err(anyhow!(
+                        "{}: invalid country code '{}'",
+                        field("ip_cidr"),
+                        entry
+                    )),
+                    None => Cidr::parse(entry)
+                        .map(IpMatcher::Cidr)
+                        .ok_or_else(|| anyhow!("{}: invalid CIDR '{}'", field("ip_cidr"), entry)),
+                })
+                .collect::<Result<Vec<_>>>()?;
+            let ports = match rule.port {
+                Some(ref spec) => parse_ports(spec)
+                    .ok_or_else(|| anyhow!("{}: invalid port list '{}'", field("port"), spec))?,
+                None => Vec::new(),
+            };
+            let users = rule
+                .users
+                .iter()
+                .map(|uuid| {
+                    Uuid::parse_str(uuid.trim())
+                        .map_err(|_| anyhow!("{}: invalid UUID '{}'", field("users"), uuid))
+                })
+                .collect::<Result<HashSet<_>>>()?;
+
+            rules.push(CompiledRule {
+                domain_suffix: normalize_domains(&rule.domain_suffix),
+                domain_keyword: normalize_domains(&rule.domain_keyword),
+                domain_regex,
+                ip_cidr,
+                ports,
+                users,
+                action,
+            });
+        }
+        Ok(Self { rules, geoip })
+    }
+
+    /// 已加载的 GeoIP 数据库
+    pub fn geoip(&self) -> Option<&GeoIpDb> {
+        self.geoip.as_ref()
+    }
+
+    /// 查找第一条命中的规则，返回（规则序号，动作）
+    pub fn route(
+        &self,
+        uuid: &Uuid,
+        address: &Address,
+        port: u16,
+    ) -> Option<(usize, &RouteAction)> {
+        let context = MatchContext {
+            geoip: self.geoip.as_ref(),
+            country: OnceCell::new(),
+        };
+        self.rules
+            .iter()
+            .enumerate()
+            .find(|(_, rule)| rule.matches(uuid, address, port, &context))
+            .map(|(index, rule)| (index, &rule.action))
+    }
+}
+
+fn normalize_domains(domains: &[String]) -> Vec<String> {
+    domains
+        .iter()
+        .map(|d| d.trim().trim_end_matches('.').to_ascii_lowercase())
+        .filter(|d| !d.is_empty())
+        .collect()
+}
+
+/// 解析端口列表，如 `53,443,8000-9000`
+fn parse_ports(spec: &str) -> Option<Vec<RangeInclusive<u16>>> {
+    spec.split(',')
+        .map(|part| {
+            let part = part.trim();
+            let (start, end) = part.split_once('-').unwrap_or((part, part));
+            let start = start.trim().parse::<u16>().ok()?;
+            let end = end.trim().parse::<u16>().ok()?;
+            (start <= end).then_some(start..=end)
+        })
+        .collect()
+}
+
+/// IP 网段
+#[derive(Debug)]
+struct Cidr {
+    network: IpAddr,
+    prefix: u32,
+}
+
+impl Cidr {
+    fn parse(value: &str) -> Option<Self> {
+        let value = value.trim();
+        let (network, prefix) = match value.split_once('/') {
+            Some((ip, prefix)) => (ip.parse::<IpAddr>().ok()?, Some(prefix.parse().ok()?)),
+            None => (value.parse::<IpAddr>().ok()?, None),
+        };
+        let width = address_width(network);
+        let prefix = prefix.unwrap_or(width);
+        (prefix <= width).then_some(Self { network, prefix })
+    }
+
+    fn contains(&self, ip: IpAddr) -> bool {
+        // IPv4 映射的 IPv6 地址按 IPv4 匹配
+        let ip = ip.to_canonical();
+        let width = address_width(ip);
+        if width != address_width(self.network) {
+            return false;
+        }
+        let shift = width - self.prefix;
+        let bits = |ip: IpAddr| match ip {
+            IpAddr::V4(v4) => u32::from(v4) as u128,
+            IpAddr::V6(v6) => u128::from(v6),
+        };
+        self.prefix == 0 || bits(ip) >> shift == bits(self.network) >> shift
+    }
+}
+
+fn address_width(ip: IpAddr) -> u32 {
+    if ip.is_ipv4() {
+        32
+    } else {
+        128
+    }
+}
+
+/// 正则表达式（编译为 NFA 指令后按 Thompson 方式模拟，在域名上搜索匹配）
+///
+/// 支持字面量、`.`、`^`、`$`、字符类（`[a-z]`、`[^0-9]`、`\d`、`\w`、`\s`）、
+/// 分组（含 `(?:...)`）与 `|`、量词 `*`、`+`、`?`、`{n}`、`{n,}`、`{n,m}`；
+/// 不支持反向引用与环视。匹配时间与输入长度和指令数的乘积成正比，不会因嵌套量词指数回溯
+#[derive(Debug)]
+struct Regex {
+    program: Vec<Inst>,
+}
+
+type Sequence = Vec<Piece>;
+
+#[derive(Debug)]
+struct Piece {
+    node: Node,
+    min: usize,
+    max: usize,
+}
+
+#[derive(Debug)]
+enum Node {
+    Char(char),
+    Any,
+    Class {
+        ranges: Vec<(char, char)>,
+        negated: bool,
+    },
+    Start,
+    End,
+    Group(Vec<Sequence>),
+}
+
+/// NFA 指令
+#[derive(Debug)]
+enum Inst {
+    Char(char),
+    Any,
+    Class(Arc<[(char, char)]>, bool),
+    Start,
+    End,
+    /// 同时尝试两个分支
+    Split(usize, usize),
+    Jump(usize),
+    Match,
+}
+
+impl Regex {
+    fn new(pattern: &str) -> std::result::Result<Self, String> {
+        let mut parser = Parser {
+            chars: pattern.chars().collect(),
+            pos: 0,
+        };
+        let alternatives = parser.alternation()?;
+        if parser.pos < parser.chars.len() {
+            return Err("unmatched ')'".to_string());
+        }
+        let mut program = Vec::new();
+        compile_alternation(&alternatives, &mut program)?;
+        program.push(Inst::Match);
+        Ok(Self { program })
+    }
+
+    fn is_match(&self, text: &str) -> bool {
+        let input: Vec<char> = text.chars().collect();
+        let mut current = Threads::new(self.program.len());
+        let mut next = Threads::new(self.program.len());
+        for pos in 0..=input.len() {
+            // 每个位置都从头开始一个线程，即在输入中搜索
+            if self.add_thread(&mut current, 0, pos, input.len()) {
+                return true;
+            }
+            let Some(&c) = input.get(pos) else {
+                break;
+            };
+            for index in 0..current.list.len() {
+                let pc = current.list[index];
+                let matched = match self.program[pc] {
+                    Inst::Char(expected) => c == expected,
+                    Inst::Any => true,
+                    Inst::Class(ref ranges, negated) => {
+                        ranges.iter().any(|&(lo, hi)| (lo..=hi).contains(&c)) != negated
+                    }
+                    _ => false,
+                };
+                if matched && self.add_thread(&mut next, pc + 1, pos + 1, input.len()) {
+                    return true;
+                }
+            }
+            std::mem::swap(&mut current, &mut next);
+            next.clear();
+        }
+        false
+    }
+
+    /// 沿空转移加入线程（每条指令在同一位置只加入一次），到达 `Match` 时返回 true
+    fn add_thread(&self, threads: &mut Threads, pc: usize, pos: usize, len: usize) -> bool {
+        let mut stack = vec![pc];
+        while let Some(pc) = stack.pop() {
+            if std::mem::replace(&mut threads.seen[pc], true) {
+                continue;
+            }
+            match self.program[pc] {
+                Inst::Match => return true,
+                Inst::Jump(target) => stack.push(target),
+                Inst::Split(first, second) => {
+                    stack.push(second);
+                    stack.push(first);
+                }
+                Inst::Start if pos == 0 => stack.push(pc + 1),
+                Inst::End if pos == len => stack.push(pc + 1),
+                Inst::Start | Inst::End => {}
+                _ => threads.list.push(pc),
+            }
+        }
+        false
+    }
+}
+
+/// 当前位置的线程集合
+struct Threads {
+    list: Vec<usize>,
+    seen: Vec<bool>,
+}
+
+impl Threads {
+    fn new(len: usize) -> Self {
+        Self {
+            list: Vec::new(),
+            seen: vec![false; len],
+        }
+    }
+
+    fn clear(&mut self) {
+        self.list.clear();
+        self.seen.fill(false);
+    }
+}
+
+fn emit(program: &mut Vec<Inst>, inst: Inst) -> std::result::Result<usize, String> {
+    if program.len() >= MAX_PROGRAM {
+        return Err("pattern too large".to_string());
+    }
+    program.push(inst);
+    Ok(program.len() - 1)
+}
+
+fn compile_alternation(
+    alternatives: &[Sequence],
+    program: &mut Vec<Inst>,
+) -> std::result::Result<(), String> {
+    let mut jumps = Vec::new();
+    for (index, sequence) in alternatives.iter().enumerate() {
+        if index + 1 == alternatives.len() {
+            compile_sequence(sequence, program)?;
+            break;
+        }
+        let split = emit(program, Inst::Split(0, 0))?;
+        compile_sequence(sequence, program)?;
+        jumps.push(emit(program, Inst::Jump(0))?);
+        program[split] = Inst::Split(split + 1, program.len());
+    }
+    let end = program.len();
+    for jump in jumps {
+        program[jump] = Inst::Jump(end);
+    }
+    Ok(())
+}
+
+fn compile_sequence(
+    sequence: &[Piece],
+    program: &mut Vec<Inst>,
+) -> std::result::Result<(), String> {
+    for piece in sequence {
+        for _ in 0..piece.min {
+            compile_node(&piece.node, program)?;
+        }
+        if piece.max == usize::MAX {
+            let split = emit(program, Inst::Split(0, 0))?;
+            compile_node(&piece.node, program)?;
+            emit(program, Inst::Jump(split))?;
+            program[split] = Inst::Split(split + 1, program.len());
+        } else {
+            for _ in piece.min..piece.max {
+                let split = emit(program, Inst::Split(0, 0))?;
+                compile_node(&piece.node, program)?;
+                program[split] = Inst::Split(split + 1, program.len());
+            }
+        }
+    }
+    Ok(())
+}
+
+fn compile_node(node: &Node, program: &mut Vec<Inst>) -> std::result::Result<(), String> {
+    let inst = match node {
+        Node::Char(c) => Inst::Char(*c),
+        Node::Any => Inst::Any,
+        Node::Class { ranges, negated } => Inst::Class(ranges.as_slice().into(), *negated),
+        Node::Start => Inst::Start,
+        Node::End => Inst::End,
+        Node::Group(alternatives) => return compile_alternation(alternatives, program),
+    };
+    emit(program, inst).map(|_| ())
+}
+
+/// `\d`、`\w`、`\s` 及其取反形式对应的字符范围
+fn class_escape(c: char) -> Option<(Vec<(char, char)>, bool)> {
+    let ranges = match c.to_ascii_lowercase() {
+        'd' => vec![('0', '9')],
+        'w' => vec![('a', 'z'), ('A', 'Z'), ('0', '9'), ('_', '_')],
+        's' => vec![(' ', ' '), ('\t', '\r')],
+        _ => return None,
+    };
+    Some((ranges, c.is_ascii_uppercase()))
+}
+
+struct Parser {
+    chars: Vec<char>,
+    pos: usize,
+}
+
+type ParseResult<T> = std::result::Result<T, String>;
+
+impl Parser {
+    fn peek(&self) -> Option<char> {
+        self.chars.get(self.pos).copied()
+    }
+
+    fn bump(&mut self) -> Option<char> {
+        let c = self.peek();
+        if c.is_some() {
+            self.pos += 1;
+        }
+        c
+    }
+
+    fn eat(&mut self, c: char) -> bool {
+        let matched = self.peek() == Some(c);
+        if matched {
+            self.pos += 1;
+        }
+        matched
+    }
+
+    fn alternation(&mut self) -> ParseResult<Vec<Sequence>> {
+        let mut alternatives = vec![self.sequence()?];
+        while self.eat('|') {
+            alternatives.push(self.sequence()?);
+        }
+        Ok(alternatives)
+    }
+
+    fn sequence(&mut self) -> ParseResult<Sequence> {
+        let mut sequence = Vec::new();
+        while let Some(c) = self.bump() {
+            let node = match c {
+                '|' | ')' => {
+                    self.pos -= 1;
+                    break;
+                }
+                '.' => Node::Any,
+                '^' => Node::Start,
+                '$' => Node::End,
+                '(' => {
+                    if self.chars[self.pos..].starts_with(&['?', ':']) {
+                        self.pos += 2;
+                    }
+                    let alternatives = self.alternation()?;
+                    if !self.eat(')') {
+                        return Err("missing ')'".to_string());
+                    }
+                    Node::Group(alternatives)
+                }
+                '[' => self.class()?,
+                '\\' => {
+                    let escaped = self.bump().ok_or("trailing '\\'")?;
+                    match class_escape(escaped) {
+                        Some((ranges, negated)) => Node::Class { ranges, negated },
+                        None if escaped.is_ascii_alphanumeric() => {
+                            return Err(format!("unsupported escape '\\{}'", escaped));
+                        }
+                        None => Node::Char(escaped),
+                    }
+                }
+                '*' | '+' | '?' | '{' => return Err(format!("nothing to repeat before '{}'", c)),
+                c => Node::Char(c),
+            };
+            let (min, max) = self.quantifier()?;
+            sequence.push(Piece { node, min, max });
+        }
+        Ok(sequence)
+    }
+
+    fn class(&mut self) -> ParseResult<Node> {
+        let negated = self.eat('^');
+        let mut ranges = Vec::new();
+        let mut first = true;
+        loop {
+            let c = self.bump().ok_or("missing ']'")?;
+            if c == ']' && !first {
+                break;
+            }
+            first = false;
+            let lo = if c == '\\' {
+                let escaped = self.bump().ok_or("missing ']'")?;
+                match class_escape(escaped) {
+                    Some((class, false)) => {
+                        ranges.extend(class);
+                        continue;
+                    }
+                    Some(_) => return Err(format!("unsupported escape '\\{}' in class", escaped)),
+                    None => escaped,
+                }
+            } else {
+                c
+            };
+            // 位于末尾的 `-` 为字面量
+            if self.peek() == Some('-') && self.chars.get(self.pos + 1).is_some_and(|&n| n != ']') {
+                self.pos += 1;
+                let mut hi = self.bump().ok_or("missing ']'")?;
+                if hi == '\\' {
+                    hi = self.bump().ok_or("missing ']'")?;
+                }
+                if hi < lo {
+                    return Err(format!("invalid range {}-{}", lo, hi));
+                }
+                ranges.push((lo, hi));
+            } else {
+                ranges.push((lo, lo));
+            }
+        }
+        Ok(Node::Class { ranges, negated })
+    }
+
+    fn quantifier(&mut self) -> ParseResult<(usize, usize)> {
+        let bounds = match self.peek() {
+            Some('*') => (0, usize::MAX),
+            Some('+') => (1, usize::MAX),
+            Some('?') => (0, 1),
+            Some('{') => {
+                self.pos += 1;
+                let bounds = self.counted()?;
+                // 非贪婪标记不影响是否匹配
+                self.eat('?');
+                return Ok(bounds);
+            }
+            _ => return Ok((1, 1)),
+        };
+        self.pos += 1;
+        self.eat('?');
+        Ok(bounds)
+    }
+
+    fn counted(&mut self) -> ParseResult<(usize, usize)> {
+        let end = self.chars[self.pos..]
+            .iter()
+            .position(|&c| c == '}')
+            .ok_or("missing '}'")?;
+        let body: String = self.chars[self.pos..self.pos + end].iter().collect();
+        self.pos += end + 1;
+        let parse = |s: &str| s.trim().parse::<usize>().ok().filter(|&n| n <= MAX_REPEAT);
+        let bounds = match body.split_once(',') {
+            None => parse(&body).map(|n| (n, n)),
+            Some((min, max)) if max.trim().is_empty() => parse(min).map(|n| (n, usize::MAX)),
+            Some((min, max)) => parse(min).zip(parse(max)).filter(|(min, max)| min <= max),
+        };
+        bounds.ok_or_else(|| format!("invalid repetition {{{}}}", body))
+    }
+}
@@ -0,0 +1,125 @@
+//! 运行时指标模块
+//!
+//! 汇总 tokio 运行时指标（工作线程数、存活任务数、全局队列深度、各工作线程的忙碌时长与休眠次数）
+//! 与按监听入站统计的连接任务数，供 `/api/runtime` 区分执行器饱和与网络瓶颈。
+//! 任务轮询耗时与工作线程本地队列深度需要 `tokio_unstable` 编译选项，不提供
+
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicU64, AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex};
+
+/// 单个监听入站的连接任务计数
+#[derive(Debug, Default)]
+pub struct TaskCounter {
+    active: AtomicUsize,
+    total: AtomicU64,
+}
+
+impl TaskCounter {
+    /// 记录一个任务开始，返回的守卫析构时计为结束
+    pub fn enter(self: &Arc<Self>) -> TaskGuard {
+        self.active.fetch_add(1, Ordering::Relaxed);
+        self.total.fetch_add(1, Ordering::Relaxed);
+        TaskGuard(Arc::clone(self))
+    }
+
+    /// 当前运行中的任务数
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::Relaxed)
+    }
+}
+
+/// 任务计数守卫
+#[derive(Debug)]
+pub struct TaskGuard(Arc<TaskCounter>);
+
+impl Drop for TaskGuard {
+    fn drop(&mut self) {
+        self.0.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// 单个监听入站的任务统计
+#[derive(Debug, Clone, Serialize)]
+pub struct ListenerTaskStats {
+    /// 入站名称（与 `/readyz` 的 `inbounds[].name` 一致）
+    pub name: String,
+    /// 运行中的连接任务数
+    pub active: usize,
+    /// 启动以来的连接任务总数
+    pub total: u64,
+}
+
+/// 按监听入站登记的任务计数
+#[derive(Debug, Default)]
+pub struct ListenerTasks {
+    listeners: Mutex<BTreeMap<String, Arc<TaskCounter>>>,
+}
+
+impl ListenerTasks {
+    /// 取得入站的计数器（同名入站共用一个）
+    pub fn register(&self, name: &str) -> Arc<TaskCounter> {
+        let mut listeners = self.listeners.lock().unwrap_or_else(|e| e.into_inner());
+        Arc::clone(listeners.entry(name.to_string()).or_default())
+    }
+
+    /// 所有入站运行中的连接任务总数
+    pub fn active_total(&self) -> usize {
+        let listeners = self.listeners.lock().unwrap_or_else(|e| e.into_inner());
+        listeners.values().map(|counter| counter.active()).sum()
+    }
+
+    /// 各入站的任务统计（按名称排序）
+    pub fn snapshot(&self) -> Vec<ListenerTaskStats> {
+        let listeners = self.listeners.lock().unwrap_or_else(|e| e.into_inner());
+        listeners
+            .iter()
+            .map(|(name, counter)| ListenerTaskStats {
+                name: name.clone(),
+                active: counter.active(),
+                total: counter.total.load(Ordering::Relaxed),
+            })
+            .collect()
+    }
+}
+
+/// 单个工作线程的指标
+#[derive(Debug, Clone, Serialize)]
+pub struct WorkerStats {
+    /// 累计忙碌时长（毫秒）
+    pub busy_ms: u64,
+    /// 累计休眠次数
+    pub park_count: u64,
+}
+
+/// 运行时指标快照
+#[derive(Debug, Clone, Serialize)]
+pub struct RuntimeSnapshot {
+    /// 工作线程数
+    pub workers: usize,
+    /// 存活任务数（含后台任务）
+    pub alive_tasks: usize,
+    /// 全局队列中等待调度的任务数
+    pub global_queue_depth: usize,
+    pub worker_stats: Vec<WorkerStats>,
+    pub listeners: Vec<ListenerTaskStats>,
+}
+
+/// 采集当前运行时的指标（须在 tokio 运行时内调用）
+pub fn snapshot(tasks: &ListenerTasks) -> RuntimeSnapshot {
+    let metrics = tokio::runtime::Handle::current().metrics();
+    let workers = metrics.num_workers();
+    RuntimeSnapshot {
+        workers,
+        alive_tasks: metrics.num_alive_tasks(),
+        global_queue_depth: metrics.global_queue_depth(),
+        worker_stats: (0..workers)
+            .map(|worker| WorkerStats {
+                busy_ms: metrics.worker_total_busy_duration(worker).as_millis() as u64,
+                park_count: metrics.worker_park_count(worker),
+            })
+            .collect(),
+        listeners: tasks.snapshot(),
+    }
+}
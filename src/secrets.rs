@@ -0,0 +1,208 @@
+//! 配置文件敏感字段加密模块
+//!
+//! 用户 UUID、管理 API 令牌、回调令牌与 DDNS 令牌可以以 `enc:v1:<base64>` 形式存放在 config.json 中，
+//! 启动时使用环境变量提供的密钥（AES-256-GCM）解密，配置备份泄露时不会直接暴露全部用户
+
+use crate::atomic_write::atomic_write_file_with_perms;
+use crate::config::Config;
+use anyhow::{anyhow, Result};
+use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use ring::aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN};
+use ring::rand::{SecureRandom, SystemRandom};
+
+/// 密文前缀（带版本号，便于将来更换算法）
+pub const ENC_PREFIX: &str = "enc:v1:";
+
+/// 密钥环境变量（Base64 编码的 32 字节密钥）
+pub const KEY_ENV: &str = "VLESS_CONFIG_KEY";
+
+/// 密钥文件环境变量（文件内容为 Base64 编码的密钥）
+pub const KEY_FILE_ENV: &str = "VLESS_CONFIG_KEY_FILE";
+
+/// 密钥长度（字节）
+const KEY_LEN: usize = 32;
+
+/// 配置加密密钥
+#[derive(Clone)]
+pub struct ConfigKey {
+    bytes: [u8; KEY_LEN],
+}
+
+impl std::fmt::Debug for ConfigKey {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str("ConfigKey(..)")
+    }
+}
+
+impl ConfigKey {
+    /// 生成随机密钥
+    pub fn generate() -> Result<Self> {
+        let mut bytes = [0u8; KEY_LEN];
+        SystemRandom::new()
+            .fill(&mut bytes)
+            .map_err(|_| anyhow!("Failed to generate random key"))?;
+        Ok(Self { bytes })
+    }
+
+    /// 从 Base64 字符串解析密钥
+    pub fn from_base64(encoded: &str) -> Result<Self> {
+        let decoded = BASE64
+            .decode(encoded.trim())
+            .map_err(|e| anyhow!("Invalid config key encoding: {}", e))?;
+        let bytes: [u8; KEY_LEN] = decoded
+            .try_into()
+            .map_err(|_| anyhow!("Config key must be {} bytes", KEY_LEN))?;
+        Ok(Self { bytes })
+    }
+
+    /// 编码为 Base64 字符串
+    pub fn to_base64(&self) -> String {
+        BASE64.encode(self.bytes)
+    }
+
+    /// 从文件读取密钥
+    pub fn from_file(path: &str) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read config key file {}: {}", path, e))?;
+        Self::from_base64(&content)
+    }
+
+    /// 从环境变量加载密钥（`VLESS_CONFIG_KEY` 优先于 `VLESS_CONFIG_KEY_FILE`）
+    pub fn from_env() -> Result<Option<Self>> {
+        if let Ok(encoded) = std::env::var(KEY_ENV) {
+            return Self::from_base64(&encoded).map(Some);
+        }
+        if let Ok(path) = std::env::var(KEY_FILE_ENV) {
+            return Self::from_file(&path).map(Some);
+        }
+        Ok(None)
+    }
+
+    fn aead_key(&self) -> LessSafeKey {
+        // 长度已在构造时保证，这里不会失败
+        LessSafeKey::new(UnboundKey::new(&AES_256_GCM, &self.bytes).expect("valid key length"))
+    }
+
+    /// 加密字符串，返回 `enc:v1:<base64(nonce || ciphertext || tag)>`
+    pub fn encrypt_value(&self, plaintext: &str) -> Result<String> {
+        let mut nonce = [0u8; NONCE_LEN];
+        SystemRandom::new()
+            .fill(&mut nonce)
+            .map_err(|_| anyhow!("Failed to generate nonce"))?;
+
+        let mut data = plaintext.as_bytes().to_vec();
+        self.aead_key()
+            .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+            .map_err(|_| anyhow!("Encryption failed"))?;
+
+        let mut sealed = nonce.to_vec();
+        sealed.extend_from_slice(&data);
+        Ok(format!("{}{}", ENC_PREFIX, BASE64.encode(sealed)))
+    }
+
+    /// 解密 `enc:v1:` 形式的字符串
+    pub fn decrypt_value(&self, value: &str) -> Result<String> {
+        let encoded = value
+            .strip_prefix(ENC_PREFIX)
+            .ok_or_else(|| anyhow!("Value is not encrypted"))?;
+        let sealed = BASE64
+            .decode(encoded)
+            .map_err(|e| anyhow!("Invalid encrypted value: {}", e))?;
+        if sealed.len() < NONCE_LEN {
+            return Err(anyhow!("Invalid encrypted value: too short"));
+        }
+
+        let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+        let nonce = Nonce::try_assume_unique_for_key(nonce)
+            .map_err(|_| anyhow!("Invalid encrypted value: bad nonce"))?;
+        let mut data = ciphertext.to_vec();
+        let plaintext = self
+            .aead_key()
+            .open_in_place(nonce, Aad::empty(), &mut data)
+            .map_err(|_| anyhow!("Decryption failed: wrong key or corrupted value"))?;
+        String::from_utf8(plaintext.to_vec())
+            .map_err(|_| anyhow!("Decrypted value is not valid UTF-8"))
+    }
+}
+
+/// 是否为加密值
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENC_PREFIX)
+}
+
+/// 遍历配置中的敏感字段
+fn secret_fields(config: &mut Config) -> Vec<&mut String> {
+    let mut fields: Vec<&mut String> = config.users.iter_mut().map(|u| &mut u.uuid).collect();
+    if let Some(ref mut token) = config.api.token {
+        fields.push(token);
+    }
+    if let Some(ref mut token) = config.accounting.webhook_token {
+        fields.push(token);
+    }
+    if !config.ddns.token.is_empty() {
+        fields.push(&mut config.ddns.token);
+    }
+    fields
+}
+
+/// 解密配置中的敏感字段，返回解密的字段数
+///
+/// 配置中存在加密字段但未提供密钥时返回错误
+pub fn decrypt_config(config: &mut Config, key: Option<&ConfigKey>) -> Result<usize> {
+    let mut count = 0;
+    for field in secret_fields(config) {
+        if !is_encrypted(field) {
+            continue;
+        }
+        let key = key.ok_or_else(|| {
+            anyhow!(
+                "Config contains encrypted values but no key is set ({} or {})",
+                KEY_ENV,
+                KEY_FILE_ENV
+            )
+        })?;
+        *field = key.decrypt_value(field)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// 加密配置中的敏感字段（已加密的字段先用 `old_key` 解密，用于轮换密钥），返回加密的字段数
+pub fn encrypt_config(
+    config: &mut Config,
+    old_key: Option<&ConfigKey>,
+    new_key: &ConfigKey,
+) -> Result<usize> {
+    decrypt_config(config, old_key)?;
+    let mut count = 0;
+    for field in secret_fields(config) {
+        *field = new_key.encrypt_value(field)?;
+        count += 1;
+    }
+    Ok(count)
+}
+
+/// `encrypt-config` 子命令：加密（或用新密钥重新加密）配置文件中的敏感字段
+///
+/// 当前密钥取自环境变量；`new_key_file` 为空时使用当前密钥加密
+pub fn encrypt_config_file(config_path: &str, new_key_file: Option<&str>) -> Result<usize> {
+    let content = std::fs::read_to_string(config_path)
+        .map_err(|e| anyhow!("Failed to read {}: {}", config_path, e))?;
+    let mut config = Config::from_json(&content)?;
+
+    let current = ConfigKey::from_env()?;
+    let new_key = match new_key_file {
+        Some(path) => ConfigKey::from_file(path)?,
+        None => current.clone().ok_or_else(|| {
+            anyhow!(
+                "No key set: export {} or {} (generate one with `vless gen-key`)",
+                KEY_ENV,
+                KEY_FILE_ENV
+            )
+        })?,
+    };
+
+    let count = encrypt_config(&mut config, current.as_ref(), &new_key)?;
+    atomic_write_file_with_perms(std::path::Path::new(config_path), &config.to_json()?, 0o600)?;
+    Ok(count)
+}
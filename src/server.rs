@@ -2,20 +2,50 @@
 //!
 //! 负责服务器启动和连接调度，具体协议处理委托给子模块
 
-use crate::api::{self, ApiConfig};
-use crate::config::{PerformanceConfig, ProtocolType};
+use crate::api::{self, AdminConfig, ApiConfig};
+use crate::blocklist::Blocklist;
+use crate::capture::CaptureManager;
+use crate::config::{
+    DecoyConfig, DecoyMode, DomainStrategy, InboundProtocol, MaintenanceBehavior,
+    PerformanceConfig, ProtocolType,
+};
+use crate::config_diff::ReloadPreview;
+use crate::crowdsec::Bouncer;
+use crate::decoy;
+use crate::destinations::DestinationTracker;
+use crate::dns::{DnsInterceptor, Resolver};
+use crate::events::EventBus;
+use crate::fallback::Fallback;
+use crate::geoip::is_private;
 use crate::http::is_http_request;
+use crate::limiter::SessionLimiter;
+use crate::maintenance::Maintenance;
+use crate::outbound::Outbound;
+use crate::outbound_tls::OutboundTls;
+use crate::readiness::{
+    bind_with_retry, is_resource_exhausted, AcceptBackoff, BindRetry, Readiness,
+};
+use crate::router::Router;
+use crate::session::{user_label, SessionServices};
+use crate::sni_proxy::{is_tls_handshake, SniRouter};
+use crate::sniffer::Sniffer;
+use crate::socket::apply_tcp_mtu_options;
+use crate::stats::UserStats;
 use crate::tcp;
+use crate::udp_session::{UdpSessionKey, UdpSessionTable};
+use crate::vless_link::{generate_user_link, UserLink, VlessLinkConfig};
 use crate::ws::{self, is_websocket_upgrade, WsConnectionResult};
 use anyhow::Result;
 use bytes::Bytes;
+use socket2::SockRef;
 use std::collections::{HashMap, HashSet};
-use std::net::SocketAddr;
+use std::net::{IpAddr, SocketAddr};
 use std::sync::Arc;
 use tokio::io::AsyncReadExt;
 use tokio::net::TcpListener;
 use tokio::net::TcpStream;
-use tracing::{debug, error, info};
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tracing::{debug, error, info, warn};
 use uuid::Uuid;
 
 /// 用户邮箱映射类型别名
@@ -45,6 +75,61 @@ pub fn detect_protocol(data: &[u8]) -> ProtocolHint {
     }
 }
 
+/// 按启用协议的顺序识别连接首部，未命中任何已启用协议时返回 None
+///
+/// WS 模式下 VLESS 经 WebSocket 升级到达，首部与 HTTP 请求相同，
+/// 升级与普通请求在读取完整请求头后再区分
+pub fn classify_inbound(
+    data: &[u8],
+    protocols: &[InboundProtocol],
+    mode: ProtocolType,
+) -> Option<InboundProtocol> {
+    protocols.iter().copied().find(|protocol| match protocol {
+        InboundProtocol::Tls => is_tls_handshake(data),
+        InboundProtocol::Http => is_http_request(data),
+        InboundProtocol::Vless => match mode {
+            ProtocolType::Tcp => data.first() == Some(&0),
+            ProtocolType::WebSocket => is_http_request(data),
+        },
+    })
+}
+
+/// 目标地址策略：拒绝代理到内网、回环、链路本地等保留地址以及服务器自身地址，
+/// 避免代理被用于访问服务器所在网络的内部服务
+#[derive(Debug, Clone, Default)]
+pub struct DestinationPolicy {
+    /// 服务器自身地址（监听地址与公网 IP）
+    own_addresses: Vec<IpAddr>,
+}
+
+impl DestinationPolicy {
+    /// 按监听地址与公网 IP 创建策略（未指定地址与域名被忽略）
+    pub fn new(bind_addr: SocketAddr, public_ip: Option<&str>) -> Self {
+        let own_addresses = std::iter::once(bind_addr.ip())
+            .chain(public_ip.and_then(|ip| ip.parse().ok()))
+            .filter(|ip: &IpAddr| !ip.is_unspecified())
+            .map(|ip| ip.to_canonical())
+            .collect();
+        Self { own_addresses }
+    }
+
+    /// 目标 IP 是否被拒绝
+    pub fn is_blocked(&self, ip: IpAddr) -> bool {
+        is_private(ip) || self.own_addresses.contains(&ip.to_canonical())
+    }
+
+    /// 检查解析后的目标地址，被拒绝时返回错误
+    pub fn check(&self, target: SocketAddr) -> Result<()> {
+        if self.is_blocked(target.ip()) {
+            return Err(anyhow::anyhow!(
+                "Destination blocked by private address policy: {}",
+                target
+            ));
+        }
+        Ok(())
+    }
+}
+
 /// VLESS 服务器配置
 #[derive(Debug, Clone)]
 pub struct ServerConfig {
@@ -54,6 +139,8 @@ pub struct ServerConfig {
     pub protocol: ProtocolType,
     /// WebSocket 路径
     pub ws_path: String,
+    /// 链接中声明的 WebSocket 早期数据上限（字节，0 表示不声明）
+    pub ws_early_data: usize,
     /// 有效用户 UUID 集合
     pub users: HashSet<Uuid>,
     /// 用户邮箱映射（Arc 共享，避免每次 HTTP 请求深拷贝）
@@ -62,6 +149,32 @@ pub struct ServerConfig {
     pub public_ip: Option<String>,
     /// 服务端口
     pub port: u16,
+    /// 会话共享服务（DNS 拦截、拦截列表、目标统计）
+    pub services: SessionServices,
+    /// WebSocket Host 请求头（校验入站并写入链接）
+    pub ws_host: Option<String>,
+    /// 链接中的 TLS SNI
+    pub sni: Option<String>,
+    /// 链接中的端口（默认使用服务端口）
+    pub link_port: Option<u16>,
+    /// 未认证 HTTP 请求的伪装方式
+    pub decoy: DecoyConfig,
+    /// 管理 API 访问令牌（未设置时不开放 /api/*）
+    pub api_token: Option<String>,
+    /// 用户流量统计（供管理 API 查询）
+    pub user_stats: Option<Arc<UserStats>>,
+    /// 重新加载预演（供 `POST /api/reload?dry_run=true`）
+    pub reload_preview: Option<Arc<ReloadPreview>>,
+    /// SNI 分流（TLS 连接按 SNI 原样转发到其他后端）
+    pub sni_proxy: Option<Arc<SniRouter>>,
+    /// 监听端口绑定重试策略
+    pub bind_retry: BindRetry,
+    /// 入站就绪状态（供 `/readyz`）
+    pub readiness: Arc<Readiness>,
+    /// 全局会话并发限制（未设置时不限制）
+    pub session_limiter: Option<Arc<SessionLimiter>>,
+    /// 启用的入站协议及识别顺序
+    pub inbound_protocols: Vec<InboundProtocol>,
 }
 
 impl ServerConfig {
@@ -77,19 +190,261 @@ impl ServerConfig {
             bind_addr,
             protocol,
             ws_path,
+            ws_early_data: 0,
             users: HashSet::new(),
             user_emails: Arc::new(HashMap::new()),
             public_ip,
             port,
+            services: SessionServices::default(),
+            ws_host: None,
+            sni: None,
+            link_port: None,
+            decoy: DecoyConfig::default(),
+            api_token: None,
+            user_stats: None,
+            reload_preview: None,
+            sni_proxy: None,
+            bind_retry: BindRetry::default(),
+            readiness: Arc::new(Readiness::default()),
+            session_limiter: None,
+            inbound_protocols: InboundProtocol::ALL.to_vec(),
         }
     }
 
+    /// 设置 DNS 拦截器
+    pub fn with_dns(mut self, dns: Arc<DnsInterceptor>) -> Self {
+        self.services.dns = Some(dns);
+        self
+    }
+
+    /// 设置目标域名解析器
+    pub fn with_resolver(mut self, resolver: Arc<Resolver>) -> Self {
+        self.services.resolver = resolver;
+        self
+    }
+
+    /// 设置维护模式
+    pub fn with_maintenance(mut self, maintenance: Arc<Maintenance>) -> Self {
+        self.services.maintenance = maintenance;
+        self
+    }
+
+    /// 设置 CrowdSec bouncer（主监听与端口转发共用）
+    pub fn with_crowdsec(mut self, bouncer: Arc<Bouncer>) -> Self {
+        self.services.bouncer = Some(bouncer);
+        self
+    }
+
+    /// 设置回落目标
+    pub fn with_fallback(mut self, fallback: Arc<Fallback>) -> Self {
+        self.services.fallback = Some(fallback);
+        self
+    }
+
+    /// 设置 VLESS UDP 会话表（决定 UDP 会话上限）
+    pub fn with_udp_sessions(mut self, sessions: Arc<UdpSessionTable<UdpSessionKey>>) -> Self {
+        self.services.udp_sessions = sessions;
+        self
+    }
+
+    /// 设置目标地址策略（拒绝内网与服务器自身地址）
+    pub fn with_destination_policy(mut self, policy: DestinationPolicy) -> Self {
+        self.services.destination_policy = Some(Arc::new(policy));
+        self
+    }
+
+    /// 设置请求中域名目标的解析方式
+    pub fn with_domain_strategy(mut self, strategy: DomainStrategy) -> Self {
+        self.services.domain_strategy = strategy;
+        self
+    }
+
+    /// 设置流量嗅探
+    pub fn with_sniffer(mut self, sniffer: Arc<Sniffer>) -> Self {
+        self.services.sniffer = Some(sniffer);
+        self
+    }
+
+    /// 设置拦截列表
+    pub fn with_blocklist(mut self, blocklist: Arc<Blocklist>) -> Self {
+        self.services.blocklist = Some(blocklist);
+        self
+    }
+
+    /// 设置目标地址统计器
+    pub fn with_destinations(mut self, destinations: Arc<DestinationTracker>) -> Self {
+        self.services.destinations = Some(destinations);
+        self
+    }
+
+    /// 设置 CDN 前置参数：Host 请求头、SNI 与链接端口
+    pub fn with_fronting(
+        mut self,
+        ws_host: Option<String>,
+        sni: Option<String>,
+        link_port: Option<u16>,
+    ) -> Self {
+        self.ws_host = ws_host;
+        self.sni = sni;
+        self.link_port = link_port;
+        self
+    }
+
+    /// 设置链接中声明的 WebSocket 早期数据上限
+    pub fn with_ws_early_data(mut self, max_bytes: usize) -> Self {
+        self.ws_early_data = max_bytes;
+        self
+    }
+
+    /// 设置伪装站点
+    pub fn with_decoy(mut self, decoy: DecoyConfig) -> Self {
+        self.decoy = decoy;
+        self
+    }
+
+    /// 设置 SNI 分流
+    pub fn with_sni_proxy(mut self, router: Arc<SniRouter>) -> Self {
+        self.sni_proxy = Some(router);
+        self
+    }
+
+    /// 设置监听端口绑定重试策略
+    pub fn with_bind_retry(mut self, retry: BindRetry) -> Self {
+        self.bind_retry = retry;
+        self
+    }
+
+    /// 设置入站就绪状态（与端口转发共享）
+    pub fn with_readiness(mut self, readiness: Arc<Readiness>) -> Self {
+        self.readiness = readiness;
+        self
+    }
+
+    /// 设置全局会话并发限制
+    pub fn with_session_limiter(mut self, limiter: Arc<SessionLimiter>) -> Self {
+        self.session_limiter = Some(limiter);
+        self
+    }
+
+    /// 设置启用的入站协议及识别顺序
+    pub fn with_inbound_protocols(mut self, protocols: Vec<InboundProtocol>) -> Self {
+        self.inbound_protocols = protocols;
+        self
+    }
+
+    /// 启用了指定入站协议
+    fn accepts(&self, protocol: InboundProtocol) -> bool {
+        self.inbound_protocols.contains(&protocol)
+    }
+
+    /// 设置事件总线（与订阅方共享同一通道）
+    pub fn with_events(mut self, events: EventBus) -> Self {
+        self.services.events = events;
+        self
+    }
+
+    /// 设置出站 TLS 规则
+    pub fn with_outbound_tls(mut self, outbound_tls: Arc<OutboundTls>) -> Self {
+        self.services.outbound_tls = Some(outbound_tls);
+        self
+    }
+
+    /// 设置上游代理出站（目标 TCP 连接经其建立）
+    pub fn with_outbound(mut self, outbound: Arc<Outbound>) -> Self {
+        self.services.outbound = Some(outbound);
+        self
+    }
+
+    /// 设置路由规则
+    pub fn with_router(mut self, router: Arc<Router>) -> Self {
+        self.services.router = Some(router);
+        self
+    }
+
+    /// 设置会话抓包管理器
+    pub fn with_capture(mut self, capture: Arc<CaptureManager>) -> Self {
+        self.services.capture = Some(capture);
+        self
+    }
+
+    /// 设置用户流量统计
+    pub fn with_user_stats(mut self, stats: Arc<UserStats>) -> Self {
+        self.user_stats = Some(stats);
+        self
+    }
+
+    /// 设置重新加载预演（配置文件路径与运行中的配置）
+    pub fn with_reload_preview(mut self, preview: Arc<ReloadPreview>) -> Self {
+        self.reload_preview = Some(preview);
+        self
+    }
+
+    /// 设置管理 API 访问令牌
+    pub fn with_api_token(mut self, token: Option<String>) -> Self {
+        self.api_token = token;
+        self
+    }
+
     /// 添加用户（带邮箱）
     pub fn add_user_with_email(&mut self, uuid: Uuid, email: Option<String>) {
         self.users.insert(uuid);
         let email_arc = email.map(|e| Arc::from(e.as_str()));
         Arc::make_mut(&mut self.user_emails).insert(uuid, email_arc);
     }
+
+    /// 链接中使用的地址（未获取公网 IP 时使用监听地址）
+    fn link_host(&self) -> String {
+        self.public_ip
+            .clone()
+            .unwrap_or_else(|| self.bind_addr.ip().to_string())
+    }
+
+    /// 链接中使用的 WebSocket 路径（仅 WebSocket 协议）
+    fn link_ws_path(&self) -> Option<String> {
+        (self.protocol == ProtocolType::WebSocket).then(|| match self.ws_early_data {
+            0 => self.ws_path.clone(),
+            max => format!("{}?ed={}", self.ws_path, max),
+        })
+    }
+
+    /// 信息页地址（与分享链接使用相同的地址与端口，TLS 由 CDN 终止时为 https）
+    pub fn panel_url(&self) -> String {
+        let scheme = if self.sni.is_some() { "https" } else { "http" };
+        let host = self.ws_host.clone().unwrap_or_else(|| self.link_host());
+        let host = if host.contains(':') {
+            format!("[{}]", host)
+        } else {
+            host
+        };
+        format!(
+            "{}://{}:{}/",
+            scheme,
+            host,
+            self.link_port.unwrap_or(self.port)
+        )
+    }
+
+    /// 生成全部用户的分享链接（按邮箱、UUID 排序）
+    pub fn user_links(&self) -> Vec<UserLink> {
+        let mut links: Vec<UserLink> = self
+            .user_emails
+            .iter()
+            .map(|(uuid, email)| {
+                let link_config = VlessLinkConfig {
+                    uuid: *uuid,
+                    host: self.link_host(),
+                    port: self.link_port.unwrap_or(self.port),
+                    ws_path: self.link_ws_path(),
+                    ws_host: self.ws_host.clone(),
+                    sni: self.sni.clone(),
+                    alias: user_label(uuid, email.as_ref()),
+                };
+                generate_user_link(&link_config, email.as_deref().map(String::from))
+            })
+            .collect();
+        links.sort_by(|a, b| (&a.email, a.uuid).cmp(&(&b.email, b.uuid)));
+        links
+    }
 }
 
 /// VLESS 服务器
@@ -117,11 +472,24 @@ impl VlessServer {
 
     /// 启动服务器
     pub async fn run(&self) -> Result<()> {
-        let listener = TcpListener::bind(self.config.bind_addr).await?;
-        info!("VLESS server listening on {}", self.config.bind_addr);
+        let bind_addr = self.config.bind_addr;
+        let listener = bind_with_retry(
+            &format!("VLESS listener {}", bind_addr),
+            &self.config.bind_retry,
+            || async { Ok(TcpListener::bind(bind_addr).await?) },
+        )
+        .await?;
+        // 监听 socket 上的 MSS / 路径 MTU 选项由接受的连接继承
+        apply_tcp_mtu_options(&SockRef::from(&listener), &self.performance_config);
+        self.config
+            .readiness
+            .set_ready("main", &bind_addr.to_string());
+        info!("VLESS server listening on {}", bind_addr);
 
         // 如果有关闭信号，监听它
         let mut shutdown_rx = self.shutdown.as_ref().map(|s| s.subscribe());
+        let mut backoff = AcceptBackoff::default();
+        let tasks = self.config.services.listener_tasks.register("main");
 
         loop {
             // 使用 tokio::select! 来监听关闭信号
@@ -139,9 +507,41 @@ impl VlessServer {
 
             match accept_result {
                 Some(Ok((stream, addr))) => {
+                    let failures = backoff.on_success();
+                    if failures > 0 {
+                        info!(
+                            "Accepting connections again after {} failed accepts",
+                            failures
+                        );
+                    }
                     let config = Arc::clone(&self.config);
                     let performance_config = self.performance_config.clone();
+                    let task = tasks.enter();
                     tokio::spawn(async move {
+                        let _task = task;
+                        if config.services.is_banned(addr).await {
+                            debug!("CrowdSec decision bans {}, closing connection", addr);
+                            return;
+                        }
+                        // 全局并发限制：队列已满或排队超时时直接关闭（服务繁忙）
+                        let _permit = match config.session_limiter {
+                            Some(ref limiter) => match limiter.acquire().await {
+                                Some(permit) => Some(permit),
+                                None => {
+                                    let rejected = limiter.stats().rejected;
+                                    if rejected % 1000 == 1 {
+                                        warn!(
+                                            "Server busy, rejecting connection from {} ({} rejected so far)",
+                                            addr, rejected
+                                        );
+                                    } else {
+                                        debug!("Server busy, rejecting connection from {}", addr);
+                                    }
+                                    return;
+                                }
+                            },
+                            None => None,
+                        };
                         if let Err(e) =
                             Self::handle_connection(stream, addr, config, performance_config).await
                         {
@@ -150,7 +550,25 @@ impl VlessServer {
                     });
                 }
                 Some(Err(e)) => {
-                    error!("Failed to accept connection: {}", e);
+                    self.config.readiness.record_accept_failure();
+                    let pause = backoff.on_error(&e);
+                    // 连续失败时只在开始与每 100 次时告警，避免刷屏
+                    let streak = backoff.streak();
+                    if streak == 1 || streak % 100 == 0 {
+                        if is_resource_exhausted(&e) {
+                            warn!(
+                                "Failed to accept connection: {} ({} in a row, check the open file limit), pausing accepts",
+                                e, streak
+                            );
+                        } else {
+                            error!("Failed to accept connection: {} ({} in a row)", e, streak);
+                        }
+                    } else {
+                        debug!("Failed to accept connection: {}", e);
+                    }
+                    if let Some(pause) = pause {
+                        tokio::time::sleep(pause).await;
+                    }
                 }
                 None => break, // shutdown signal received
             }
@@ -169,6 +587,48 @@ impl VlessServer {
     ) -> Result<()> {
         debug!("New connection from {}", client_addr);
 
+        // 按首部字节识别协议，未启用的协议立即断开
+        let mut head = [0u8; 8];
+        let n = stream.peek(&mut head).await?;
+        if n == 0 {
+            return Err(anyhow::anyhow!(
+                "Connection closed by client (addr: {})",
+                client_addr
+            ));
+        }
+        let protocol = classify_inbound(&head[..n], &config.inbound_protocols, config.protocol);
+        match protocol {
+            // SNI 分流：TLS 握手不属于明文 VLESS / WS / HTTP，按 SNI 转发
+            Some(InboundProtocol::Tls) => {
+                if let Some(ref router) = config.sni_proxy {
+                    return router.handle(stream).await;
+                }
+                if let Some(ref fallback) = config.services.fallback {
+                    return fallback.serve(stream, &[]).await;
+                }
+                debug!(
+                    "TLS connection from {} closed: sni_proxy is not configured",
+                    client_addr
+                );
+                return Ok(());
+            }
+            Some(_) => {}
+            None => {
+                if let Some(ref fallback) = config.services.fallback {
+                    debug!(
+                        "Connection from {} matches no enabled inbound protocol, falling back",
+                        client_addr
+                    );
+                    return fallback.serve(stream, &[]).await;
+                }
+                debug!(
+                    "Connection from {} matches no enabled inbound protocol, closing",
+                    client_addr
+                );
+                return Ok(());
+            }
+        }
+
         // 根据协议类型分发处理
         match config.protocol {
             ProtocolType::WebSocket => {
@@ -207,6 +667,23 @@ impl VlessServer {
                 Self::handle_http_request(stream, header_bytes, &config).await
             }
             ProtocolHint::VlessConnection => {
+                if !config.services.gate.admit() {
+                    debug!("Paused, closing VLESS connection from {}", client_addr);
+                    return Ok(());
+                }
+                if !config.services.maintenance.admit() {
+                    if config.services.maintenance.behavior() == MaintenanceBehavior::Fallback {
+                        if let Some(ref fallback) = config.services.fallback {
+                            debug!("Maintenance mode, falling back {}", client_addr);
+                            return fallback.serve(stream, &[]).await;
+                        }
+                    }
+                    debug!(
+                        "Maintenance mode, closing VLESS connection from {}",
+                        client_addr
+                    );
+                    return Ok(());
+                }
                 // 通过 Arc 共享，避免每连接深拷贝整个 HashSet/HashMap
                 let config_ref = Arc::clone(&config);
 
@@ -215,6 +692,7 @@ impl VlessServer {
                     client_addr,
                     performance_config,
                     &config_ref.users,
+                    config_ref.services.clone(),
                     |uuid| {
                         let config_ref = Arc::clone(&config_ref);
                         async move { config_ref.user_emails.get(&uuid).and_then(|e| e.clone()) }
@@ -232,21 +710,48 @@ impl VlessServer {
         config: Arc<ServerConfig>,
         performance_config: PerformanceConfig,
     ) -> Result<()> {
-        let result =
-            ws::detect_ws_connection(stream, &config.ws_path, performance_config.clone()).await?;
+        let result = ws::detect_ws_connection(
+            stream,
+            &config.ws_path,
+            config.ws_host.as_deref(),
+            performance_config.clone(),
+        )
+        .await?;
 
         match result {
-            WsConnectionResult::UpgradeSuccess(ws_stream, first_message) => {
+            WsConnectionResult::UpgradeSuccess(..) if !config.accepts(InboundProtocol::Vless) => {
+                debug!("VLESS is disabled, closing WebSocket from {}", client_addr);
+                Ok(())
+            }
+            WsConnectionResult::HttpRequest(..) if !config.accepts(InboundProtocol::Http) => {
+                debug!("HTTP is disabled, closing connection from {}", client_addr);
+                Ok(())
+            }
+            WsConnectionResult::UpgradeSuccess(..) if !config.services.gate.admit() => {
+                debug!("Paused, closing WebSocket from {}", client_addr);
+                Ok(())
+            }
+            WsConnectionResult::UpgradeSuccess(mut ws_stream, ..)
+                if !config.services.maintenance.admit() =>
+            {
+                debug!("Maintenance mode, closing WebSocket from {}", client_addr);
+                let reason = config.services.maintenance.close_reason();
+                ws::close_with_reason(&mut ws_stream, CloseCode::Again, reason).await;
+                Ok(())
+            }
+            WsConnectionResult::UpgradeSuccess(ws_stream, first_message, overhead) => {
                 // 通过 Arc 共享，避免每连接深拷贝
                 let config_ref = Arc::clone(&config);
 
                 ws::handle_ws_vless(
                     ws_stream,
                     first_message,
+                    overhead,
                     &config_ref.users,
                     |uuid| config_ref.user_emails.get(uuid).and_then(|e| e.clone()),
                     performance_config,
                     client_addr,
+                    &config_ref.services,
                 )
                 .await
             }
@@ -262,20 +767,59 @@ impl VlessServer {
         data: Bytes,
         config: &ServerConfig,
     ) -> Result<()> {
+        if let Some(route) = decoy::match_route(&config.decoy.routes, &data) {
+            return decoy::proxy_route(stream, &data, route).await;
+        }
+
+        if let Some(ref token) = config.api_token {
+            if api::is_readyz_request(&data) {
+                return api::handle_readyz_request(
+                    stream,
+                    &data,
+                    token,
+                    &config.readiness,
+                    config.session_limiter.as_deref(),
+                    Some(&config.services.udp_sessions),
+                    Some(&config.services.stalls),
+                    Some(&config.services.gate),
+                    Some(&config.services.maintenance),
+                )
+                .await;
+            }
+            if api::is_admin_request(&data) {
+                let admin_config = AdminConfig {
+                    token: token.clone(),
+                    destinations: config.services.destinations.clone(),
+                    user_stats: config.user_stats.clone(),
+                    user_links: config.user_links(),
+                    blocklist: config.services.blocklist.clone(),
+                    capture: config.services.capture.clone(),
+                    reload_preview: config.reload_preview.clone(),
+                    overhead: Arc::clone(&config.services.overhead),
+                    listener_tasks: Arc::clone(&config.services.listener_tasks),
+                    resolver: Arc::clone(&config.services.resolver),
+                    route_stats: Arc::clone(&config.services.route_stats),
+                    gate: Arc::clone(&config.services.gate),
+                    maintenance: Arc::clone(&config.services.maintenance),
+                };
+                return api::handle_admin_request(stream, &data, &admin_config).await;
+            }
+        }
+
+        if config.decoy.mode != DecoyMode::Panel {
+            return decoy::serve(stream, &data, &config.decoy).await;
+        }
+
         let api_config = ApiConfig {
-            public_ip: config
-                .public_ip
-                .clone()
-                .unwrap_or_else(|| config.bind_addr.ip().to_string()),
-            port: config.port,
+            public_ip: config.link_host(),
+            port: config.link_port.unwrap_or(config.port),
             protocol: config.protocol,
-            ws_path: if config.protocol == ProtocolType::WebSocket {
-                Some(config.ws_path.clone())
-            } else {
-                None
-            },
+            ws_path: config.link_ws_path(),
+            ws_host: config.ws_host.clone(),
+            sni: config.sni.clone(),
             // Arc::clone 只增加引用计数，不复制 HashMap 数据
             user_emails: Arc::clone(&config.user_emails),
+            maintenance_notice: config.services.maintenance.notice(),
         };
 
         api::handle_http_request(stream, &data, &api_config).await
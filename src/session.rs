@@ -0,0 +1,665 @@
+//! 会话记录模块
+//!
+//! 为每个代理会话收集结构化字段（用户、目标、解析地址、各阶段耗时、流量），
+//! 在会话关闭时统一输出，便于仅凭日志排查慢连接
+
+use crate::address::{connect_target_checked, ConnectTiming};
+use crate::blocklist::Blocklist;
+use crate::capture::{CaptureManager, SessionCapture};
+use crate::config::{DomainStrategy, OutboundProtocol, PerformanceConfig};
+use crate::crowdsec::Bouncer;
+use crate::destinations::DestinationTracker;
+use crate::dns::{DnsInterceptor, Resolver};
+use crate::events::{Event, EventBus};
+use crate::fallback::Fallback;
+use crate::maintenance::Maintenance;
+use crate::outbound::Outbound;
+use crate::outbound_tls::{OutboundTls, TargetStream};
+use crate::overhead::OverheadStats;
+use crate::protocol::{Address, Command, VlessRequest};
+use crate::readiness::AcceptGate;
+use crate::route_stats::{RouteStats, SessionRoute, ROUTE_BLOCK, ROUTE_DIRECT};
+use crate::router::{RouteAction, RouteMatch, Router};
+use crate::runtime_stats::ListenerTasks;
+use crate::server::DestinationPolicy;
+use crate::sniffer::Sniffer;
+use crate::socket::bind_udp_socket;
+use crate::stall::{self, Activity, StallStats, StallWatch};
+use crate::udp_session::{UdpSession, UdpSessionKey, UdpSessionTable};
+use bytes::Bytes;
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tracing::{debug, info};
+use uuid::Uuid;
+
+/// 会话共享服务
+///
+/// 由服务器在启动时按配置创建，所有连接共享（克隆仅增加引用计数）
+#[derive(Debug, Clone, Default)]
+pub struct SessionServices {
+    /// DNS 拦截器（UDP 代理到 53 端口时使用）
+    pub dns: Option<Arc<DnsInterceptor>>,
+    /// 广告 / 追踪域名拦截列表
+    pub blocklist: Option<Arc<Blocklist>>,
+    /// 目标地址健康统计
+    pub destinations: Option<Arc<DestinationTracker>>,
+    /// 内部事件总线
+    pub events: EventBus,
+    /// 会话抓包（管理 API 开启规则后生效）
+    pub capture: Option<Arc<CaptureManager>>,
+    /// 出站 TLS 规则（命中的目标连接外包 TLS）
+    pub outbound_tls: Option<Arc<OutboundTls>>,
+    /// 按传输方式统计的协议开销
+    pub overhead: Arc<OverheadStats>,
+    /// 握手失败时的回落目标
+    pub fallback: Option<Arc<Fallback>>,
+    /// VLESS UDP 会话表（`Command::Udp` 与 Mux UDP 子连接）
+    pub udp_sessions: Arc<UdpSessionTable<UdpSessionKey>>,
+    /// TCP 会话停滞统计
+    pub stalls: Arc<StallStats>,
+    /// 按监听入站统计的连接任务数
+    pub listener_tasks: Arc<ListenerTasks>,
+    /// 上游代理出站（`server.outbound`，设置后目标 TCP 连接经其建立）
+    pub outbound: Option<Arc<Outbound>>,
+    /// 路由规则（`routing.rules`）
+    pub router: Option<Arc<Router>>,
+    /// 目标地址策略（`server.block_private_destinations`，拒绝内网与服务器自身地址）
+    pub destination_policy: Option<Arc<DestinationPolicy>>,
+    /// 请求中域名目标的解析方式（`server.domain_strategy`）
+    pub domain_strategy: DomainStrategy,
+    /// 流量嗅探（`sniffing`，识别首包域名覆盖目标）
+    pub sniffer: Option<Arc<Sniffer>>,
+    /// 目标域名解析器（`resolver`，带缓存）
+    pub resolver: Arc<Resolver>,
+    /// 按路由与路由规则统计的会话数与流量
+    pub route_stats: Arc<RouteStats>,
+    /// 新会话准入开关（`/api/pause`、`/api/resume` 与 SIGUSR2）
+    pub gate: Arc<AcceptGate>,
+    /// 维护模式（`maintenance`）
+    pub maintenance: Arc<Maintenance>,
+    /// CrowdSec bouncer（`crowdsec.bouncer_key`，未设置时不检查）
+    pub bouncer: Option<Arc<Bouncer>>,
+}
+
+impl SessionServices {
+    /// 是否接受新的代理会话：暂停或维护模式时拒绝（计入各自的拒绝数）
+    pub fn admit(&self) -> bool {
+        self.gate.admit() && self.maintenance.admit()
+    }
+
+    /// 客户端 IP 是否被 CrowdSec 决策封禁（未启用 bouncer 时为 false）
+    pub async fn is_banned(&self, client_addr: SocketAddr) -> bool {
+        match self.bouncer {
+            Some(ref bouncer) => bouncer.is_banned(client_addr.ip()).await,
+            None => false,
+        }
+    }
+
+    /// 查找请求命中的路由规则
+    ///
+    /// 每个请求在确定最终目标后只匹配一次，结果传给之后的检查、连接与会话记录
+    pub fn route(&self, request: &VlessRequest) -> RouteMatch {
+        let (index, action) =
+            self.router
+                .as_ref()?
+                .route(&request.uuid, &request.address, request.port)?;
+        Some((index, action.clone()))
+    }
+
+    /// 按路由结果检查目标，命中 `block` 规则时返回错误
+    pub fn ensure_routable(
+        &self,
+        request: &VlessRequest,
+        route: &RouteMatch,
+    ) -> anyhow::Result<()> {
+        match route {
+            Some((index, RouteAction::Block)) => Err(anyhow::anyhow!(
+                "Destination blocked by routing rule {}: {}",
+                index,
+                format_destination(&request.address, request.port)
+            )),
+            _ => Ok(()),
+        }
+    }
+
+    /// 会话经过的路由：命中规则时为规则动作，否则为 `server.outbound`（未设置时直连）；
+    /// UDP 会话经不支持 UDP 的出站时按直连计
+    pub fn session_route(&self, request: &VlessRequest, route: &RouteMatch) -> SessionRoute {
+        let (rule, outbound) = match *route {
+            Some((index, RouteAction::Outbound(ref outbound))) => (Some(index), Some(outbound)),
+            Some((index, RouteAction::Direct)) => (Some(index), None),
+            Some((index, RouteAction::Block)) => {
+                return SessionRoute::new(ROUTE_BLOCK, Some(index))
+            }
+            None => (None, self.outbound.as_ref()),
+        };
+        let outbound = outbound.filter(|outbound| {
+            request.command != Command::Udp || outbound.protocol() == OutboundProtocol::Socks
+        });
+        SessionRoute::new(outbound.map_or(ROUTE_DIRECT, |o| o.tag()), rule)
+    }
+
+    /// 记录被拒绝的请求（拦截列表、路由规则或目标地址策略），计入 `block` 路由
+    pub fn record_blocked(&self, route: &RouteMatch) {
+        let rule = match *route {
+            Some((index, RouteAction::Block)) => Some(index),
+            _ => None,
+        };
+        self.route_stats.record_blocked(rule);
+    }
+
+    /// 嗅探 TCP 请求首包中的域名并覆盖目标；未启用嗅探、UDP 请求或未识别出域名时不变
+    ///
+    /// 在拦截列表检查之前调用，识别出的域名同样参与拦截与路由
+    pub fn sniff_destination(&self, request: &mut VlessRequest, payload: &[u8]) {
+        let Some(ref sniffer) = self.sniffer else {
+            return;
+        };
+        if request.command != Command::Tcp {
+            return;
+        }
+        let Some((protocol, domain)) = sniffer.sniff(payload) else {
+            return;
+        };
+        debug!(
+            "Sniffed {:?} domain {} for {}",
+            protocol,
+            domain,
+            format_destination(&request.address, request.port)
+        );
+        request.address = Address::Domain(Bytes::from(domain));
+    }
+
+    /// 按 `domain_strategy` 在路由前把域名目标解析为 IP（`AsIs` 时不变）
+    ///
+    /// 在拦截列表检查之后调用：拦截列表只匹配域名
+    pub async fn apply_domain_strategy(&self, request: &mut VlessRequest) -> anyhow::Result<()> {
+        let Address::Domain(ref domain) = request.address else {
+            return Ok(());
+        };
+        if self.domain_strategy == DomainStrategy::AsIs {
+            return Ok(());
+        }
+        let domain_str =
+            std::str::from_utf8(domain).map_err(|_| anyhow::anyhow!("Invalid domain encoding"))?;
+        let resolved = self
+            .resolver
+            .resolve(domain_str, request.port, self.domain_strategy)
+            .await?;
+        debug!(
+            "Resolved {} to {} ({:?})",
+            String::from_utf8_lossy(domain),
+            resolved.ip(),
+            self.domain_strategy
+        );
+        request.address = Address::from(resolved.ip());
+        Ok(())
+    }
+
+    /// 按目标地址策略检查 IP 目标，域名目标在解析后检查
+    pub fn ensure_destination(&self, request: &VlessRequest) -> anyhow::Result<()> {
+        let Some(ref policy) = self.destination_policy else {
+            return Ok(());
+        };
+        match request.address {
+            Address::Domain(_) => Ok(()),
+            _ => policy.check(request.address.to_socket_addr(request.port)?),
+        }
+    }
+
+    /// 解析 UDP 会话的目标地址并按目标地址策略检查
+    pub async fn resolve_udp_target(
+        &self,
+        address: &Address,
+        port: u16,
+    ) -> anyhow::Result<SocketAddr> {
+        let target = self.resolver.resolve_address(address, port).await?;
+        if let Some(ref policy) = self.destination_policy {
+            policy.check(target)?;
+        }
+        Ok(target)
+    }
+
+    /// 按路由规则选择 UDP 会话的出站：`direct` 与未设置出站时直连；
+    /// HTTP 出站无法承载 UDP，回退为直连
+    pub fn udp_outbound<'a>(
+        &'a self,
+        request: &VlessRequest,
+        route: &'a RouteMatch,
+    ) -> Option<&'a Arc<Outbound>> {
+        let outbound = match route {
+            Some((_, RouteAction::Outbound(outbound))) => outbound,
+            Some(_) => return None,
+            None => self.outbound.as_ref()?,
+        };
+        if outbound.protocol() != OutboundProtocol::Socks {
+            debug!(
+                "Outbound {} does not support UDP, sending {} directly",
+                outbound.tag(),
+                format_destination(&request.address, request.port)
+            );
+            return None;
+        }
+        Some(outbound)
+    }
+
+    /// 建立 UDP 会话：经 SOCKS5 出站时通过 UDP ASSOCIATE 中继（域名由上游解析），
+    /// 否则解析目标并按目标地址策略检查后直连
+    pub async fn open_udp(
+        &self,
+        request: &VlessRequest,
+        route: &RouteMatch,
+        perf_config: &PerformanceConfig,
+    ) -> anyhow::Result<(UdpSession, ConnectTiming)> {
+        let idle = Duration::from_secs(perf_config.udp_timeout);
+        let (session, timing) = match self.udp_outbound(request, route) {
+            Some(outbound) => {
+                let (relay, mut timing) = outbound.udp_associate(perf_config).await?;
+                let socket = bind_udp_socket(perf_config, relay.relay_addr()).await?;
+                timing.resolved = request.address.to_socket_addr(request.port).ok();
+                let session = UdpSession::via_relay(
+                    socket,
+                    relay,
+                    &request.address,
+                    request.port,
+                    perf_config.udp_full_cone,
+                    idle,
+                );
+                (session, timing)
+            }
+            None => {
+                let started = Instant::now();
+                let target = self
+                    .resolve_udp_target(&request.address, request.port)
+                    .await?;
+                let timing = ConnectTiming {
+                    resolved: Some(target),
+                    dns: started.elapsed(),
+                    ..Default::default()
+                };
+                let socket = bind_udp_socket(perf_config, target).await?;
+                let session = UdpSession::new(socket, target, perf_config.udp_full_cone, idle);
+                (session, timing)
+            }
+        };
+        Ok((
+            session.with_replay_window(perf_config.udp_replay_window),
+            timing,
+        ))
+    }
+
+    /// 按路由规则连接请求的目标：`direct` 不经 `server.outbound`，`outbound:<tag>` 经指定出站，
+    /// 未命中规则时同 [`SessionServices::connect_target`]
+    pub async fn connect_request(
+        &self,
+        request: &VlessRequest,
+        route: &RouteMatch,
+        perf_config: &PerformanceConfig,
+    ) -> anyhow::Result<(TargetStream, ConnectTiming)> {
+        let outbound = match route {
+            None => self.outbound.as_ref(),
+            Some((index, action)) => {
+                debug!(
+                    "Routing rule {} matched {}: {:?}",
+                    index,
+                    format_destination(&request.address, request.port),
+                    action
+                );
+                match action {
+                    RouteAction::Direct => None,
+                    RouteAction::Outbound(outbound) => Some(outbound),
+                    RouteAction::Block => {
+                        return Err(anyhow::anyhow!(
+                            "Destination blocked by routing rule {}",
+                            index
+                        ))
+                    }
+                }
+            }
+        };
+        let policy = self.destination_policy.as_deref();
+        self.connect_via(
+            outbound,
+            policy,
+            &request.address,
+            request.port,
+            perf_config,
+        )
+        .await
+    }
+
+    /// 连接目标；设置上游代理出站时经其建立，命中出站 TLS 规则时完成握手（握手耗时计入建连耗时）
+    ///
+    /// 用于端口转发等由运营者配置的目标，不按目标地址策略检查
+    pub async fn connect_target(
+        &self,
+        address: &Address,
+        port: u16,
+        perf_config: &PerformanceConfig,
+    ) -> anyhow::Result<(TargetStream, ConnectTiming)> {
+        self.connect_via(self.outbound.as_ref(), None, address, port, perf_config)
+            .await
+    }
+
+    async fn connect_via(
+        &self,
+        outbound: Option<&Arc<Outbound>>,
+        policy: Option<&DestinationPolicy>,
+        address: &Address,
+        port: u16,
+        perf_config: &PerformanceConfig,
+    ) -> anyhow::Result<(TargetStream, ConnectTiming)> {
+        let (stream, mut timing) = match outbound {
+            Some(outbound) => outbound.dial(address, port, &[], perf_config).await?,
+            // 直连时在解析后、建连前检查实际地址，防止域名解析到内网
+            None => {
+                connect_target_checked(address, port, perf_config, &self.resolver, |target| {
+                    match policy {
+                        Some(policy) => policy.check(target),
+                        None => Ok(()),
+                    }
+                })
+                .await?
+            }
+        };
+        let Some(ref tls) = self.outbound_tls else {
+            return Ok((TargetStream::Plain(stream), timing));
+        };
+        let started = Instant::now();
+        let stream = tls.wrap(stream, address, port).await?;
+        timing.connect += started.elapsed();
+        Ok((stream, timing))
+    }
+
+    /// 记录目标建连结果（未启用目标统计时忽略）
+    pub fn record_connect<T>(&self, dest: &str, result: &anyhow::Result<(T, ConnectTiming)>) {
+        let Some(ref destinations) = self.destinations else {
+            return;
+        };
+        match result {
+            Ok((_, timing)) => destinations.record_success(dest, timing.dns + timing.connect),
+            Err(e) => destinations.record_failure(dest, &e.to_string()),
+        }
+    }
+
+    /// 发布连接建立事件
+    ///
+    /// 返回的守卫在连接结束（含出错提前返回）时发布 `ConnectionClosed`，
+    /// 保证订阅方看到的建立 / 关闭事件成对出现
+    pub fn connection_opened(
+        &self,
+        client_addr: SocketAddr,
+        request: &VlessRequest,
+        user_email: Option<&Arc<str>>,
+    ) -> ConnectionGuard {
+        self.events.publish(Event::ConnectionOpened {
+            client_addr,
+            uuid: request.uuid,
+            user: user_label(&request.uuid, user_email),
+            network: match request.command {
+                Command::Tcp => "tcp",
+                Command::Udp => "udp",
+                Command::Mux => "mux",
+            },
+            dest: format_destination(&request.address, request.port),
+        });
+        ConnectionGuard {
+            events: self.events.clone(),
+            client_addr,
+            uuid: request.uuid,
+            user: user_label(&request.uuid, user_email),
+        }
+    }
+
+    /// 握手失败：配置了回落时把连接（含已读取的 `data`）转发到回落目标，否则返回原错误
+    pub async fn fallback_or(
+        &self,
+        stream: TcpStream,
+        data: &[u8],
+        error: anyhow::Error,
+    ) -> anyhow::Result<()> {
+        let Some(ref fallback) = self.fallback else {
+            return Err(error);
+        };
+        debug!("{}, falling back", error);
+        fallback.serve(stream, data).await
+    }
+
+    /// 按抓包规则为会话打开抓包文件（未启用或未命中时返回 None）
+    pub fn open_capture(&self, record: &SessionRecord) -> Option<SessionCapture> {
+        self.capture
+            .as_ref()?
+            .open_session(&record.uuid, &record.user, &record.dest)
+    }
+
+    /// 为会话启动停滞检测，返回两个方向共享的活动记录（未设置 `stall_timeout` 时不检测）
+    pub fn watch_stall(
+        &self,
+        record: &SessionRecord,
+        perf_config: &PerformanceConfig,
+    ) -> (Arc<Activity>, Option<StallWatch>) {
+        let activity = Arc::new(Activity::default());
+        let watch = stall::watch(
+            Arc::clone(&activity),
+            Duration::from_secs(perf_config.stall_timeout),
+            Arc::clone(&self.stalls),
+            record.id,
+            record.dest.clone(),
+        );
+        (activity, watch)
+    }
+
+    /// 结束会话：输出日志并发布会话关闭事件
+    pub fn finish_session(&self, record: &mut SessionRecord) {
+        record.finish();
+        if let Some(ref route) = record.route {
+            self.route_stats
+                .record_session(route, record.bytes_up, record.bytes_down);
+        }
+        if self.events.subscriber_count() > 0 {
+            self.events
+                .publish(Event::SessionClosed(Arc::new(record.clone())));
+        }
+    }
+}
+
+/// 连接守卫：析构时发布连接关闭事件
+#[derive(Debug)]
+pub struct ConnectionGuard {
+    events: EventBus,
+    client_addr: SocketAddr,
+    uuid: Uuid,
+    user: String,
+}
+
+impl Drop for ConnectionGuard {
+    fn drop(&mut self) {
+        self.events.publish(Event::ConnectionClosed {
+            client_addr: self.client_addr,
+            uuid: self.uuid,
+            user: std::mem::take(&mut self.user),
+        });
+    }
+}
+
+/// 格式化目标地址（域名:端口 / IPv4:端口 / [IPv6]:端口）
+pub fn format_destination(address: &Address, port: u16) -> String {
+    match address {
+        Address::Ipv4(ip) => format!("{}:{}", ip, port),
+        Address::Ipv6(ip) => format!("[{}]:{}", ip, port),
+        Address::Domain(domain) => format!("{}:{}", String::from_utf8_lossy(domain), port),
+    }
+}
+
+/// 用户标识：优先使用邮箱，否则使用 UUID
+pub fn user_label(uuid: &Uuid, email: Option<&Arc<str>>) -> String {
+    match email {
+        Some(email) => email.to_string(),
+        None => uuid.to_string(),
+    }
+}
+
+/// 毫秒数（饱和转换）
+fn millis(duration: Duration) -> u64 {
+    duration.as_millis().min(u64::MAX as u128) as u64
+}
+
+/// 下一个会话 ID
+static NEXT_SESSION_ID: AtomicU64 = AtomicU64::new(1);
+
+/// 会话记录
+#[derive(Debug, Clone, Serialize)]
+pub struct SessionRecord {
+    /// 会话 ID（进程内递增，用于关联同一会话的日志）
+    pub id: u64,
+    /// 用户 UUID
+    pub uuid: Uuid,
+    /// 用户（邮箱或 UUID）
+    pub user: String,
+    /// 传输类型：tcp / udp
+    pub network: &'static str,
+    /// 目标地址（客户端请求的原始形式）
+    pub dest: String,
+    /// 实际连接的 IP 地址
+    pub resolved_ip: Option<String>,
+    /// DNS 解析耗时（毫秒）
+    pub dns_ms: u64,
+    /// TCP 建连耗时（毫秒，UDP 为 0）
+    pub connect_ms: u64,
+    /// 建连完成到收到目标首字节的耗时（毫秒）
+    pub ttfb_ms: Option<u64>,
+    /// 上行字节数（客户端 → 目标）
+    pub bytes_up: u64,
+    /// 下行字节数（目标 → 客户端）
+    pub bytes_down: u64,
+    /// 会话总时长（毫秒）
+    pub duration_ms: u64,
+    /// 会话关闭时间（Unix 时间戳，秒）
+    pub closed_at: u64,
+    /// 经过的路由与命中的路由规则（输出为 `route` / `rule` 字段）
+    #[serde(flatten)]
+    pub route: Option<SessionRoute>,
+    #[serde(skip)]
+    started: Instant,
+}
+
+impl SessionRecord {
+    /// 创建会话记录，`started` 为会话开始时间（用于计算总时长）
+    pub fn new(
+        uuid: Uuid,
+        user: String,
+        network: &'static str,
+        dest: String,
+        timing: &ConnectTiming,
+        started: Instant,
+    ) -> Self {
+        Self {
+            id: NEXT_SESSION_ID.fetch_add(1, Ordering::Relaxed),
+            uuid,
+            user,
+            network,
+            dest,
+            resolved_ip: timing.resolved.map(|addr| addr.ip().to_string()),
+            dns_ms: millis(timing.dns),
+            connect_ms: millis(timing.connect),
+            ttfb_ms: None,
+            bytes_up: 0,
+            bytes_down: 0,
+            duration_ms: 0,
+            closed_at: 0,
+            route: None,
+            started,
+        }
+    }
+
+    /// 设置会话经过的路由
+    pub fn with_route(mut self, route: SessionRoute) -> Self {
+        self.route = Some(route);
+        self
+    }
+
+    /// 记录首字节耗时
+    pub fn set_ttfb(&mut self, ttfb: Option<Duration>) {
+        self.ttfb_ms = ttfb.map(millis);
+    }
+
+    /// 结束会话并输出结构化日志
+    pub fn finish(&mut self) {
+        self.duration_ms = millis(self.started.elapsed());
+        self.closed_at = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_secs())
+            .unwrap_or(0);
+        info!(
+            id = self.id,
+            user = %self.user,
+            network = self.network,
+            dest = %self.dest,
+            route = self.route.as_ref().map_or("-", |r| &*r.route),
+            resolved_ip = self.resolved_ip.as_deref().unwrap_or("-"),
+            dns_ms = self.dns_ms,
+            connect_ms = self.connect_ms,
+            ttfb_ms = self.ttfb_ms,
+            bytes_up = self.bytes_up,
+            bytes_down = self.bytes_down,
+            duration_ms = self.duration_ms,
+            "Session closed"
+        );
+    }
+}
+
+/// 复制数据直到读端结束或出错，返回（已写入的字节数，结束结果）
+///
+/// 与 `tokio::io::copy` 不同，出错（如连接被重置）时仍返回出错前已写入的字节数
+pub async fn copy_counted<R, W>(reader: &mut R, writer: &mut W) -> (u64, std::io::Result<()>)
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut buffer = vec![0u8; 16 * 1024];
+    let mut total = 0u64;
+    loop {
+        let n = match reader.read(&mut buffer).await {
+            Ok(0) => return (total, writer.flush().await),
+            Ok(n) => n,
+            Err(e) => return (total, Err(e)),
+        };
+        if let Err(e) = writer.write_all(&buffer[..n]).await {
+            return (total, Err(e));
+        }
+        total += n as u64;
+    }
+}
+
+/// 复制数据并记录首字节到达时间
+///
+/// 返回 (首字节耗时，复制的字节数，结束结果)；耗时从 `since` 开始计算，
+/// 出错时字节数为出错前已写入的部分
+pub async fn copy_with_ttfb<R, W>(
+    reader: &mut R,
+    writer: &mut W,
+    since: Instant,
+) -> (Option<Duration>, u64, std::io::Result<()>)
+where
+    R: AsyncRead + Unpin,
+    W: AsyncWrite + Unpin,
+{
+    let mut first = [0u8; 16 * 1024];
+    let n = match reader.read(&mut first).await {
+        Ok(0) => return (None, 0, Ok(())),
+        Ok(n) => n,
+        Err(e) => return (None, 0, Err(e)),
+    };
+    let ttfb = since.elapsed();
+    if let Err(e) = writer.write_all(&first[..n]).await {
+        return (Some(ttfb), 0, Err(e));
+    }
+
+    let (rest, result) = copy_counted(reader, writer).await;
+    (Some(ttfb), n as u64 + rest, result)
+}
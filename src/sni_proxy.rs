@@ -0,0 +1,225 @@
+//! SNI 分流模块
+//!
+//! 与已有 TLS 服务共用端口：TLS 连接不解密，按 ClientHello 中的 SNI
+//! 原样转发到其他后端；明文 VLESS / WebSocket / HTTP 连接照常处理
+
+use crate::config::{SniProxyConfig, SniRoute};
+use crate::http::host_matches;
+use anyhow::{anyhow, Result};
+use std::time::Duration;
+use tokio::net::TcpStream;
+use tracing::{debug, info};
+
+/// TLS 记录头长度
+const RECORD_HEADER_LEN: usize = 5;
+
+/// ClientHello 最大读取长度（单条 TLS 记录上限 16KB）
+const MAX_CLIENT_HELLO: usize = RECORD_HEADER_LEN + 16 * 1024;
+
+/// 等待完整 ClientHello 的超时
+const CLIENT_HELLO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// 连接后端超时
+const BACKEND_CONNECT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// 是否为 TLS 握手记录（ContentType 22 + 主版本 3）
+pub fn is_tls_handshake(data: &[u8]) -> bool {
+    data.len() >= 3 && data[0] == 0x16 && data[1] == 0x03
+}
+
+/// 顺序读取 ClientHello 字段的游标
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Option<&'a [u8]> {
+        if self.data.len() < n {
+            return None;
+        }
+        let (head, rest) = self.data.split_at(n);
+        self.data = rest;
+        Some(head)
+    }
+
+    fn u8(&mut self) -> Option<usize> {
+        self.take(1).map(|b| b[0] as usize)
+    }
+
+    fn u16(&mut self) -> Option<usize> {
+        self.take(2)
+            .map(|b| u16::from_be_bytes([b[0], b[1]]) as usize)
+    }
+
+    /// 读取带长度前缀的字段
+    fn vec8(&mut self) -> Option<&'a [u8]> {
+        let len = self.u8()?;
+        self.take(len)
+    }
+
+    fn vec16(&mut self) -> Option<&'a [u8]> {
+        let len = self.u16()?;
+        self.take(len)
+    }
+}
+
+/// 从 TLS 记录中解析 ClientHello 的 SNI（server_name 扩展）
+///
+/// 数据不完整、不是 ClientHello 或不含 SNI 时返回 None
+pub fn parse_sni(data: &[u8]) -> Option<String> {
+    if !is_tls_handshake(data) {
+        return None;
+    }
+    let mut record = Reader {
+        data: &data[RECORD_HEADER_LEN.min(data.len())..],
+    };
+    // Handshake：类型 1 = ClientHello，长度 24 位
+    if record.u8()? != 1 {
+        return None;
+    }
+    record.take(3)?;
+    record.take(2 + 32)?; // client_version + random
+    record.vec8()?; // session_id
+    record.vec16()?; // cipher_suites
+    record.vec8()?; // compression_methods
+    let mut extensions = Reader {
+        data: record.vec16()?,
+    };
+    while !extensions.data.is_empty() {
+        let ext_type = extensions.u16()?;
+        let body = extensions.vec16()?;
+        if ext_type != 0 {
+            continue;
+        }
+        let mut list = Reader { data: body };
+        let mut names = Reader {
+            data: list.vec16()?,
+        };
+        while !names.data.is_empty() {
+            let name_type = names.u8()?;
+            let name = names.vec16()?;
+            if name_type == 0 {
+                return std::str::from_utf8(name)
+                    .ok()
+                    .map(|n| n.to_ascii_lowercase());
+            }
+        }
+        return None;
+    }
+    None
+}
+
+/// SNI 是否匹配路由规则（完整域名或 `*.` 通配子域名，不区分大小写）
+fn sni_matches(sni: &str, pattern: &str) -> bool {
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => {
+            sni.len() > suffix.len() + 1 && {
+                let (head, tail) = sni.split_at(sni.len() - suffix.len());
+                head.ends_with('.') && tail.eq_ignore_ascii_case(suffix)
+            }
+        }
+        None => host_matches(sni, pattern),
+    }
+}
+
+/// SNI 分流器
+#[derive(Debug, Clone)]
+pub struct SniRouter {
+    routes: Vec<SniRoute>,
+    backend: Option<String>,
+    /// 本服务域名（命中时不转发）
+    own_domains: Vec<String>,
+}
+
+impl SniRouter {
+    /// 按配置创建；`own_domains` 为本服务的域名（`server.domain`、`sni`、`ws_host`）
+    pub fn new(config: &SniProxyConfig, own_domains: Vec<String>) -> Self {
+        Self {
+            routes: config.routes.clone(),
+            backend: config.backend.clone(),
+            own_domains,
+        }
+    }
+
+    /// 选择后端：路由优先，其次是非本服务域名的默认后端
+    ///
+    /// 返回 None 表示不转发（SNI 为本服务域名或未配置默认后端）
+    pub fn route(&self, sni: Option<&str>) -> Option<&str> {
+        if let Some(sni) = sni {
+            if let Some(route) = self.routes.iter().find(|r| sni_matches(sni, &r.sni)) {
+                return Some(&route.backend);
+            }
+            if self.own_domains.iter().any(|d| host_matches(sni, d)) {
+                return None;
+            }
+        }
+        self.backend.as_deref()
+    }
+
+    /// 处理 TLS 连接：读取 ClientHello 后按 SNI 原样转发
+    ///
+    /// 未命中后端时关闭连接（入站未实现 TLS 终止）
+    pub async fn handle(&self, mut stream: TcpStream) -> Result<()> {
+        let client_hello = peek_client_hello(&stream).await?;
+        let sni = parse_sni(&client_hello);
+        let Some(backend) = self.route(sni.as_deref()) else {
+            info!(
+                "TLS connection for SNI {} has no passthrough backend, closing (TLS inbound is not supported)",
+                sni.as_deref().unwrap_or("-")
+            );
+            return Ok(());
+        };
+
+        let connect =
+            tokio::time::timeout(BACKEND_CONNECT_TIMEOUT, TcpStream::connect(backend)).await;
+        let mut upstream = match connect {
+            Ok(Ok(s)) => s,
+            Ok(Err(e)) => {
+                return Err(anyhow!(
+                    "Failed to connect to SNI backend {}: {}",
+                    backend,
+                    e
+                ))
+            }
+            Err(_) => return Err(anyhow!("Timed out connecting to SNI backend {}", backend)),
+        };
+        debug!(
+            "SNI passthrough {} -> {}",
+            sni.as_deref().unwrap_or("-"),
+            backend
+        );
+
+        // ClientHello 仅被 peek，随双向复制一并发往后端
+        let result = tokio::io::copy_bidirectional(&mut stream, &mut upstream).await;
+        debug!("SNI passthrough to {} finished: {:?}", backend, result);
+        Ok(())
+    }
+}
+
+/// peek 完整的首条 TLS 记录（ClientHello 可能跨多个 TCP 分段）
+async fn peek_client_hello(stream: &TcpStream) -> Result<Vec<u8>> {
+    let mut buf = vec![0u8; MAX_CLIENT_HELLO];
+    let read = async {
+        loop {
+            let n = stream.peek(&mut buf).await?;
+            if n == 0 {
+                return Err(anyhow!("Connection closed before ClientHello"));
+            }
+            let wanted = if n >= RECORD_HEADER_LEN {
+                RECORD_HEADER_LEN + u16::from_be_bytes([buf[3], buf[4]]) as usize
+            } else {
+                RECORD_HEADER_LEN
+            };
+            if n >= wanted.min(MAX_CLIENT_HELLO) {
+                return Ok(n);
+            }
+            // peek 不消费数据，稍等后续分段到达
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+    };
+    let n = tokio::time::timeout(CLIENT_HELLO_TIMEOUT, read)
+        .await
+        .map_err(|_| anyhow!("Timed out waiting for ClientHello"))??;
+    buf.truncate(n);
+    Ok(buf)
+}
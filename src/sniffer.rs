@@ -0,0 +1,89 @@
+//! 流量嗅探模块
+//!
+//! 检查 TCP 会话随请求头到达的首包：TLS ClientHello 取 SNI，HTTP/1.x 请求取 Host 请求头，
+//! 识别出的域名替换请求目标（端口不变），使客户端以 IP 发起的连接也能按域名拦截与路由。
+//! 只检查首包中已有的数据，不额外等待客户端发送
+
+use crate::config::{SniffProtocol, SniffingConfig};
+use crate::dns::domain_matches_suffix;
+use crate::http::{extract_header_value, is_http_request};
+use crate::sni_proxy::parse_sni;
+use std::net::IpAddr;
+
+/// 域名最大长度
+const MAX_DOMAIN_LEN: usize = 253;
+
+/// 流量嗅探器
+#[derive(Debug, Clone)]
+pub struct Sniffer {
+    protocols: Vec<SniffProtocol>,
+    /// 不覆盖目标的域名后缀（小写，无末尾的点）
+    excluded: Vec<String>,
+}
+
+impl Sniffer {
+    pub fn new(config: &SniffingConfig) -> Self {
+        Self {
+            protocols: config.dest_override.clone(),
+            excluded: config
+                .domains_excluded
+                .iter()
+                .map(|d| d.trim().trim_end_matches('.').to_ascii_lowercase())
+                .filter(|d| !d.is_empty())
+                .collect(),
+        }
+    }
+
+    /// 按配置顺序识别首包中的域名，返回（协议，小写域名）；命中排除列表时返回 None
+    pub fn sniff(&self, data: &[u8]) -> Option<(SniffProtocol, String)> {
+        let (protocol, domain) = self.protocols.iter().find_map(|&protocol| {
+            let domain = match protocol {
+                SniffProtocol::Tls => parse_sni(data),
+                SniffProtocol::Http => sniff_http_host(data),
+            };
+            domain
+                .map(|d| d.trim_end_matches('.').to_ascii_lowercase())
+                .filter(|d| is_domain(d))
+                .map(|d| (protocol, d))
+        })?;
+        if self
+            .excluded
+            .iter()
+            .any(|suffix| domain_matches_suffix(&domain, suffix))
+        {
+            return None;
+        }
+        Some((protocol, domain))
+    }
+}
+
+/// 从 HTTP/1.x 请求头中取 Host（去掉端口），HTTP/2 前言与 `CONNECT` 请求不识别
+pub fn sniff_http_host(data: &[u8]) -> Option<String> {
+    if !is_http_request(data) || data.starts_with(b"PRI") || data.starts_with(b"CONNECT") {
+        return None;
+    }
+    let end = data
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .unwrap_or(data.len());
+    let host = extract_header_value(&data[..end], "Host")?;
+    // 方括号为 IPv6 字面量，不是域名
+    if host.starts_with('[') {
+        return None;
+    }
+    let host = match host.rsplit_once(':') {
+        Some((name, port)) if port.parse::<u16>().is_ok() => name,
+        _ => host.as_str(),
+    };
+    Some(host.to_string())
+}
+
+/// 是否为可用作目标的域名（排除 IP 字面量与非法字符）
+fn is_domain(name: &str) -> bool {
+    !name.is_empty()
+        && name.len() <= MAX_DOMAIN_LEN
+        && name.parse::<IpAddr>().is_err()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || matches!(b, b'-' | b'.' | b'_'))
+}
@@ -1,12 +1,14 @@
-//! TCP Socket 配置模块
+//! Socket 配置模块
 //!
-//! 提供 TCP socket 参数配置功能
+//! 提供 TCP socket 参数配置、MSS / 路径 MTU 选项与 UDP 中继 socket 绑定功能
 
-use anyhow::Result;
-use socket2::{SockRef, TcpKeepalive};
+use crate::config::{PerformanceConfig, PmtuDiscovery};
+use anyhow::{anyhow, Result};
+use socket2::{SockRef, Socket, TcpKeepalive};
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
 use std::time::Duration;
-use tokio::net::TcpStream;
-use tracing::debug;
+use tokio::net::{TcpStream, UdpSocket};
+use tracing::{debug, warn};
 
 /// TCP Keepalive 参数：60s 空闲后开始探测，每 10s 一次，最多 3 次
 const KEEPALIVE_IDLE: Duration = Duration::from_secs(60);
@@ -69,3 +71,152 @@ pub fn configure_tcp_socket(
 
     Ok(())
 }
+
+/// 设置 TCP MSS 上限（`TCP_MAXSEG`）；`mss` 为 0 时不修改
+///
+/// 在监听 socket 上设置时由之后接受的连接继承，在连接前设置时同时影响握手中通告的 MSS
+pub fn set_tcp_mss(socket: &Socket, mss: u32) {
+    if mss == 0 {
+        return;
+    }
+    #[cfg(unix)]
+    match socket.set_mss(mss) {
+        Ok(()) => debug!("Set TCP MSS to {}", mss),
+        Err(e) => warn!("Failed to set TCP MSS to {}: {}", mss, e),
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = socket;
+        warn!("tcp_mss is not supported on this platform, ignoring");
+    }
+}
+
+/// 设置路径 MTU 发现策略（Linux `IP_MTU_DISCOVER` / `IPV6_MTU_DISCOVER`）；`mode` 为 None 时不修改
+pub fn set_pmtu_discovery(socket: &Socket, mode: Option<PmtuDiscovery>) {
+    let Some(mode) = mode else {
+        return;
+    };
+    #[cfg(any(target_os = "linux", target_os = "android"))]
+    {
+        use std::os::fd::AsRawFd;
+
+        let (v4, v6) = match mode {
+            PmtuDiscovery::Do => (libc::IP_PMTUDISC_DO, libc::IPV6_PMTUDISC_DO),
+            PmtuDiscovery::Dont => (libc::IP_PMTUDISC_DONT, libc::IPV6_PMTUDISC_DONT),
+            PmtuDiscovery::Want => (libc::IP_PMTUDISC_WANT, libc::IPV6_PMTUDISC_WANT),
+            PmtuDiscovery::Probe => (libc::IP_PMTUDISC_PROBE, libc::IPV6_PMTUDISC_PROBE),
+        };
+        let set = |level: libc::c_int, name: libc::c_int, value: libc::c_int| {
+            // SAFETY: 借用期间 fd 有效，value 为 c_int 并传入其大小
+            let ret = unsafe {
+                libc::setsockopt(
+                    socket.as_raw_fd(),
+                    level,
+                    name,
+                    &value as *const libc::c_int as *const libc::c_void,
+                    std::mem::size_of::<libc::c_int>() as libc::socklen_t,
+                )
+            };
+            if ret == 0 {
+                Ok(())
+            } else {
+                Err(std::io::Error::last_os_error())
+            }
+        };
+        let ipv6 = socket
+            .local_addr()
+            .map(|addr| addr.is_ipv6())
+            .unwrap_or(false);
+        // IPv6 socket 同时设置 IPv4 选项，覆盖双栈 socket 上的 IPv4 映射流量（失败可忽略）
+        let result = if ipv6 {
+            let _ = set(libc::IPPROTO_IP, libc::IP_MTU_DISCOVER, v4);
+            set(libc::IPPROTO_IPV6, libc::IPV6_MTU_DISCOVER, v6)
+        } else {
+            set(libc::IPPROTO_IP, libc::IP_MTU_DISCOVER, v4)
+        };
+        match result {
+            Ok(()) => debug!("Set path MTU discovery to {:?}", mode),
+            Err(e) => warn!("Failed to set path MTU discovery to {:?}: {}", mode, e),
+        }
+    }
+    #[cfg(not(any(target_os = "linux", target_os = "android")))]
+    {
+        let _ = socket;
+        warn!(
+            "pmtu_discovery {:?} is only supported on Linux, ignoring",
+            mode
+        );
+    }
+}
+
+/// 应用 TCP socket 的 MSS 与路径 MTU 选项（监听 socket 或连接前的出站 socket）
+pub fn apply_tcp_mtu_options(socket: &Socket, perf_config: &PerformanceConfig) {
+    set_tcp_mss(socket, perf_config.tcp_mss);
+    set_pmtu_discovery(socket, perf_config.pmtu_discovery);
+}
+
+/// 计算 UDP 中继的本地绑定 IP
+///
+/// 配置了 `udp_bind_address` 时使用配置值，否则按目标地址族选择未指定地址
+pub fn udp_bind_ip(perf_config: &PerformanceConfig, target: SocketAddr) -> Result<IpAddr> {
+    match perf_config.udp_bind_address {
+        Some(ref addr) => addr
+            .parse::<IpAddr>()
+            .map_err(|_| anyhow!("Invalid udp_bind_address: {}", addr)),
+        None => Ok(if target.is_ipv6() {
+            IpAddr::V6(Ipv6Addr::UNSPECIFIED)
+        } else {
+            IpAddr::V4(Ipv4Addr::UNSPECIFIED)
+        }),
+    }
+}
+
+/// 绑定 UDP 中继 socket
+///
+/// 按 `udp_bind_address` / `udp_port_range` 绑定本地地址，
+/// 端口范围内从随机起点依次尝试，全部被占用时返回错误
+///
+/// # Arguments
+/// * `perf_config` - 性能配置
+/// * `target` - UDP 目标地址（用于选择地址族）
+pub async fn bind_udp_socket(
+    perf_config: &PerformanceConfig,
+    target: SocketAddr,
+) -> Result<UdpSocket> {
+    let ip = udp_bind_ip(perf_config, target)?;
+
+    let [start, end] = match perf_config.udp_port_range {
+        Some(range) => range,
+        None => {
+            let socket = UdpSocket::bind(SocketAddr::new(ip, 0)).await?;
+            set_pmtu_discovery(&SockRef::from(&socket), perf_config.pmtu_discovery);
+            return Ok(socket);
+        }
+    };
+
+    if start == 0 || start > end {
+        return Err(anyhow!("Invalid udp_port_range: [{}, {}]", start, end));
+    }
+
+    // 从随机偏移开始，避免并发会话总是争抢范围内的第一个端口
+    let span = (end - start) as u32 + 1;
+    let offset = uuid::Uuid::new_v4().as_u128() as u32 % span;
+
+    for i in 0..span {
+        let port = start + ((offset + i) % span) as u16;
+        match UdpSocket::bind(SocketAddr::new(ip, port)).await {
+            Ok(socket) => {
+                set_pmtu_discovery(&SockRef::from(&socket), perf_config.pmtu_discovery);
+                return Ok(socket);
+            }
+            Err(e) => debug!("UDP port {} unavailable: {}", port, e),
+        }
+    }
+
+    Err(anyhow!(
+        "No free UDP port in range [{}, {}] on {}",
+        start,
+        end,
+        ip
+    ))
+}
@@ -0,0 +1,209 @@
+//! SOCKS5 协议模块（RFC 1928 / RFC 1929）
+//!
+//! 常量与地址编解码供本地代理入站（服务端一侧）与上游代理出站（客户端一侧）共用；
+//! 出站一侧还支持 UDP ASSOCIATE 与 UDP 中继数据报的封装
+
+use crate::protocol::Address;
+use anyhow::{anyhow, Result};
+use bytes::Bytes;
+use std::net::{Ipv4Addr, Ipv6Addr};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+pub const VERSION: u8 = 5;
+pub const NO_AUTH: u8 = 0x00;
+pub const USERNAME_PASSWORD: u8 = 0x02;
+pub const NO_ACCEPTABLE: u8 = 0xff;
+/// 用户名密码子协商版本（RFC 1929）
+pub const AUTH_VERSION: u8 = 0x01;
+pub const CMD_CONNECT: u8 = 0x01;
+pub const CMD_UDP_ASSOCIATE: u8 = 0x03;
+pub const ATYP_IPV4: u8 = 0x01;
+pub const ATYP_DOMAIN: u8 = 0x03;
+pub const ATYP_IPV6: u8 = 0x04;
+pub const REP_SUCCESS: u8 = 0x00;
+pub const REP_FAILURE: u8 = 0x01;
+pub const REP_CMD_UNSUPPORTED: u8 = 0x07;
+pub const REP_ATYP_UNSUPPORTED: u8 = 0x08;
+
+/// 编码 SOCKS5 地址（ATYP + 地址 + 端口）
+pub fn encode_address(address: &Address, port: u16, buf: &mut Vec<u8>) {
+    match address {
+        Address::Ipv4(addr) => {
+            buf.push(ATYP_IPV4);
+            buf.extend_from_slice(&addr.octets());
+        }
+        Address::Domain(domain) => {
+            buf.push(ATYP_DOMAIN);
+            buf.push(domain.len() as u8);
+            buf.extend_from_slice(domain);
+        }
+        Address::Ipv6(addr) => {
+            buf.push(ATYP_IPV6);
+            buf.extend_from_slice(&addr.octets());
+        }
+    }
+    buf.extend_from_slice(&port.to_be_bytes());
+}
+
+/// 按已读到的 ATYP 读取地址与端口；不支持的地址类型返回 None（未读取后续字节）
+pub async fn read_address<R: AsyncRead + Unpin>(
+    reader: &mut R,
+    atyp: u8,
+) -> Result<Option<(Address, u16)>> {
+    let address = match atyp {
+        ATYP_IPV4 => {
+            let mut octets = [0u8; 4];
+            reader.read_exact(&mut octets).await?;
+            Address::Ipv4(Ipv4Addr::from(octets))
+        }
+        ATYP_DOMAIN => {
+            let len = reader.read_u8().await? as usize;
+            let mut domain = vec![0u8; len];
+            reader.read_exact(&mut domain).await?;
+            Address::Domain(Bytes::from(domain))
+        }
+        ATYP_IPV6 => {
+            let mut octets = [0u8; 16];
+            reader.read_exact(&mut octets).await?;
+            Address::Ipv6(Ipv6Addr::from(octets))
+        }
+        _ => return Ok(None),
+    };
+    let port = reader.read_u16().await?;
+    Ok(Some((address, port)))
+}
+
+/// 作为客户端完成 SOCKS5 握手并请求 CONNECT；`credentials` 为（用户名，密码）
+pub async fn connect<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    address: &Address,
+    port: u16,
+    credentials: Option<(&str, &str)>,
+) -> Result<()> {
+    handshake(stream, credentials).await?;
+    // 绑定地址对转发无用，丢弃
+    request(stream, CMD_CONNECT, address, port).await?;
+    Ok(())
+}
+
+/// 作为客户端完成 SOCKS5 握手并请求 UDP ASSOCIATE，返回中继地址（BND.ADDR，BND.PORT）
+///
+/// 请求中的客户端地址填 `0.0.0.0:0`，由服务端接受任意来源；关联在 `stream` 关闭时结束
+pub async fn udp_associate<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    credentials: Option<(&str, &str)>,
+) -> Result<(Address, u16)> {
+    handshake(stream, credentials).await?;
+    request(
+        stream,
+        CMD_UDP_ASSOCIATE,
+        &Address::Ipv4(Ipv4Addr::UNSPECIFIED),
+        0,
+    )
+    .await
+}
+
+/// 方法协商与可选的用户名密码认证
+async fn handshake<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    credentials: Option<(&str, &str)>,
+) -> Result<()> {
+    let greeting: &[u8] = match credentials {
+        Some(_) => &[VERSION, 2, NO_AUTH, USERNAME_PASSWORD],
+        None => &[VERSION, 1, NO_AUTH],
+    };
+    stream.write_all(greeting).await?;
+    let mut choice = [0u8; 2];
+    stream.read_exact(&mut choice).await?;
+    if choice[0] != VERSION {
+        return Err(anyhow!("Invalid SOCKS5 server version: {}", choice[0]));
+    }
+    match (choice[1], credentials) {
+        (NO_AUTH, _) => Ok(()),
+        (USERNAME_PASSWORD, Some((username, password))) => {
+            let mut auth = vec![AUTH_VERSION, username.len() as u8];
+            auth.extend_from_slice(username.as_bytes());
+            auth.push(password.len() as u8);
+            auth.extend_from_slice(password.as_bytes());
+            stream.write_all(&auth).await?;
+            let mut status = [0u8; 2];
+            stream.read_exact(&mut status).await?;
+            if status[1] != 0 {
+                return Err(anyhow!("SOCKS5 authentication failed"));
+            }
+            Ok(())
+        }
+        (method, _) => Err(anyhow!(
+            "SOCKS5 server rejected authentication methods (selected {:#04x})",
+            method
+        )),
+    }
+}
+
+/// 发送请求并读取应答，返回应答中的绑定地址
+async fn request<S: AsyncRead + AsyncWrite + Unpin>(
+    stream: &mut S,
+    cmd: u8,
+    address: &Address,
+    port: u16,
+) -> Result<(Address, u16)> {
+    let mut request = vec![VERSION, cmd, 0];
+    encode_address(address, port, &mut request);
+    stream.write_all(&request).await?;
+    let mut reply = [0u8; 4];
+    stream.read_exact(&mut reply).await?;
+    if reply[1] != REP_SUCCESS {
+        let name = match cmd {
+            CMD_UDP_ASSOCIATE => "UDP ASSOCIATE",
+            _ => "CONNECT",
+        };
+        return Err(anyhow!(
+            "SOCKS5 {} failed with reply {:#04x}",
+            name,
+            reply[1]
+        ));
+    }
+    read_address(stream, reply[3])
+        .await?
+        .ok_or_else(|| anyhow!("Invalid SOCKS5 reply address type: {}", reply[3]))
+}
+
+/// 编码 UDP 中继数据报头部（RSV + FRAG + 目标地址）
+pub fn encode_udp_header(address: &Address, port: u16) -> Vec<u8> {
+    let mut header = vec![0, 0, 0];
+    encode_address(address, port, &mut header);
+    header
+}
+
+/// 解析 UDP 中继数据报，返回（来源地址，端口，载荷偏移）；分片数据报不支持
+pub fn decode_udp_header(packet: &[u8]) -> Result<(Address, u16, usize)> {
+    let [_, _, frag, atyp, rest @ ..] = packet else {
+        return Err(anyhow!("SOCKS5 UDP packet too short"));
+    };
+    if *frag != 0 {
+        return Err(anyhow!("Fragmented SOCKS5 UDP packet not supported"));
+    }
+    let (address, len) = match *atyp {
+        ATYP_IPV4 if rest.len() >= 4 => (
+            Address::Ipv4(Ipv4Addr::from(<[u8; 4]>::try_from(&rest[..4])?)),
+            4,
+        ),
+        ATYP_IPV6 if rest.len() >= 16 => (
+            Address::Ipv6(Ipv6Addr::from(<[u8; 16]>::try_from(&rest[..16])?)),
+            16,
+        ),
+        ATYP_DOMAIN if !rest.is_empty() && rest.len() > rest[0] as usize => {
+            let len = rest[0] as usize;
+            (
+                Address::Domain(Bytes::copy_from_slice(&rest[1..=len])),
+                len + 1,
+            )
+        }
+        _ => return Err(anyhow!("Invalid SOCKS5 UDP packet address")),
+    };
+    let port = rest
+        .get(len..len + 2)
+        .map(|p| u16::from_be_bytes([p[0], p[1]]))
+        .ok_or_else(|| anyhow!("SOCKS5 UDP packet too short"))?;
+    Ok((address, port, 4 + len + 2))
+}
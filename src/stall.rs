@@ -0,0 +1,206 @@
+//! 会话停滞检测模块
+//!
+//! 客户端与目标连接都未关闭、但两个方向在 `stall_timeout` 秒内都没有任何数据时判定为停滞，
+//! 与正常关闭和 UDP 空闲超时分开统计：按连接 ID 记录日志并计数，恢复传输后再记一次，
+//! 用于排查 MTU 黑洞等路径问题。检测只记录，不关闭连接
+
+use serde::Serialize;
+use std::pin::Pin;
+use std::sync::atomic::{AtomicBool, AtomicU64, AtomicUsize, Ordering};
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, ReadBuf};
+use tokio::task::JoinHandle;
+use tracing::info;
+
+/// 停滞状态下的复查间隔上限
+const RECHECK_INTERVAL: Duration = Duration::from_secs(1);
+
+/// 停滞统计快照
+#[derive(Debug, Clone, Serialize)]
+pub struct StallSnapshot {
+    /// 当前处于停滞状态的会话数
+    pub stalled: usize,
+    /// 启动以来检测到的停滞次数
+    pub stalled_total: u64,
+    /// 其中已恢复传输的次数
+    pub recovered_total: u64,
+}
+
+/// 全局停滞统计
+#[derive(Debug, Default)]
+pub struct StallStats {
+    stalled: AtomicUsize,
+    stalled_total: AtomicU64,
+    recovered_total: AtomicU64,
+}
+
+impl StallStats {
+    pub fn snapshot(&self) -> StallSnapshot {
+        StallSnapshot {
+            stalled: self.stalled.load(Ordering::Relaxed),
+            stalled_total: self.stalled_total.load(Ordering::Relaxed),
+            recovered_total: self.recovered_total.load(Ordering::Relaxed),
+        }
+    }
+}
+
+/// 会话的最近活动时间（两个方向共享）
+#[derive(Debug)]
+pub struct Activity {
+    started: Instant,
+    /// 最近活动时间（相对 `started` 的毫秒数）
+    last: AtomicU64,
+    /// 任一方向已结束（此后不再判定停滞）
+    closed: AtomicBool,
+}
+
+impl Default for Activity {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            last: AtomicU64::new(0),
+            closed: AtomicBool::new(false),
+        }
+    }
+}
+
+impl Activity {
+    /// 记录一次数据传输
+    pub fn touch(&self) {
+        let elapsed = self.started.elapsed().as_millis() as u64;
+        self.last.fetch_max(elapsed, Ordering::Relaxed);
+    }
+
+    /// 标记任一方向已结束
+    pub fn close(&self) {
+        self.closed.store(true, Ordering::Relaxed);
+    }
+
+    /// 距最近一次活动的时间
+    pub fn idle_for(&self) -> Duration {
+        let last = Duration::from_millis(self.last.load(Ordering::Relaxed));
+        self.started.elapsed().saturating_sub(last)
+    }
+
+    fn is_closed(&self) -> bool {
+        self.closed.load(Ordering::Relaxed)
+    }
+}
+
+/// 读取时记录活动的包装器：读到数据时刷新活动时间，读到 EOF 或出错时标记结束
+#[derive(Debug)]
+pub struct ActivityReader<R> {
+    inner: R,
+    activity: Arc<Activity>,
+}
+
+impl<R> ActivityReader<R> {
+    pub fn new(inner: R, activity: Arc<Activity>) -> Self {
+        Self { inner, activity }
+    }
+}
+
+impl<R: AsyncRead + Unpin> AsyncRead for ActivityReader<R> {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let before = buf.filled().len();
+        let result = Pin::new(&mut self.inner).poll_read(cx, buf);
+        match &result {
+            Poll::Ready(Ok(())) if buf.filled().len() > before => self.activity.touch(),
+            Poll::Ready(_) => self.activity.close(),
+            Poll::Pending => {}
+        }
+        result
+    }
+}
+
+/// 停滞检测任务，析构时停止
+#[derive(Debug)]
+pub struct StallWatch {
+    task: JoinHandle<()>,
+}
+
+impl Drop for StallWatch {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+/// 会话停滞状态：析构（会话结束）时仍处于停滞的从当前计数中扣除
+struct StallState {
+    stats: Arc<StallStats>,
+    stalled: bool,
+}
+
+impl StallState {
+    fn enter(&mut self) {
+        self.stalled = true;
+        self.stats.stalled.fetch_add(1, Ordering::Relaxed);
+        self.stats.stalled_total.fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn leave(&mut self) {
+        self.stalled = false;
+        self.stats.stalled.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+impl Drop for StallState {
+    fn drop(&mut self) {
+        if self.stalled {
+            self.leave();
+        }
+    }
+}
+
+/// 启动停滞检测；`timeout` 为零时不检测
+///
+/// `id` 与 `dest` 仅用于日志
+pub fn watch(
+    activity: Arc<Activity>,
+    timeout: Duration,
+    stats: Arc<StallStats>,
+    id: u64,
+    dest: String,
+) -> Option<StallWatch> {
+    if timeout.is_zero() {
+        return None;
+    }
+    let task = tokio::spawn(async move {
+        let mut state = StallState {
+            stats,
+            stalled: false,
+        };
+        loop {
+            if activity.is_closed() {
+                return;
+            }
+            let idle = activity.idle_for();
+            if idle < timeout {
+                if state.stalled {
+                    state.leave();
+                    state.stats.recovered_total.fetch_add(1, Ordering::Relaxed);
+                    info!(id, dest = %dest, "Session recovered from stall");
+                }
+                tokio::time::sleep(timeout - idle).await;
+                continue;
+            }
+            if !state.stalled {
+                state.enter();
+                info!(
+                    id,
+                    dest = %dest,
+                    idle_secs = idle.as_secs(),
+                    "Session stalled: no data in either direction"
+                );
+            }
+            tokio::time::sleep(RECHECK_INTERVAL.min(timeout / 4)).await;
+        }
+    });
+    Some(StallWatch { task })
+}
@@ -0,0 +1,391 @@
+//! 用户流量统计模块
+//!
+//! 订阅事件总线，按用户累计连接数与上下行流量，供管理 API 分页查询。
+//! 配置 `monitoring.state_file` 时，正常退出前保存各用户的计数，启动后恢复
+
+use crate::atomic_write::atomic_write_file_with_perms;
+use crate::events::Event;
+use anyhow::{anyhow, Result};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+use tokio::sync::broadcast;
+use tracing::warn;
+use uuid::Uuid;
+
+/// 默认每页条数
+const DEFAULT_PER_PAGE: usize = 50;
+
+/// 每页条数上限
+const MAX_PER_PAGE: usize = 500;
+
+/// 状态文件格式版本
+const STATE_VERSION: u32 = 1;
+
+/// 单个用户的累计计数
+#[derive(Debug, Default)]
+struct UserCounters {
+    email: Option<String>,
+    bytes_up: u64,
+    bytes_down: u64,
+    connections: u64,
+    active: u64,
+    /// 最近一次连接时间（墙钟时间，重启后仍可恢复）
+    last_seen: Option<SystemTime>,
+}
+
+/// 用户统计摘要
+#[derive(Debug, Clone, Serialize)]
+pub struct UserSummary {
+    pub uuid: Uuid,
+    pub email: Option<String>,
+    /// 上行字节数（客户端 → 目标）
+    pub bytes_up: u64,
+    /// 下行字节数（目标 → 客户端）
+    pub bytes_down: u64,
+    /// 累计连接数
+    pub connections: u64,
+    /// 当前活跃连接数
+    pub active_connections: u64,
+    /// 距最近一次连接的秒数（从未连接时为空）
+    pub last_seen_secs: Option<u64>,
+}
+
+impl UserSummary {
+    /// 上下行合计流量
+    pub fn total_bytes(&self) -> u64 {
+        self.bytes_up.saturating_add(self.bytes_down)
+    }
+}
+
+/// 用户统计（所有连接共享）
+#[derive(Debug, Default)]
+pub struct UserStats {
+    users: Mutex<HashMap<Uuid, UserCounters>>,
+}
+
+impl UserStats {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// 登记配置中的用户（未产生流量的用户也会出现在列表中）
+    pub fn register(&self, uuid: Uuid, email: Option<String>) {
+        let mut users = self.users.lock().unwrap_or_else(|e| e.into_inner());
+        users.entry(uuid).or_default().email = email;
+    }
+
+    /// 按事件更新计数
+    pub fn apply(&self, event: &Event) {
+        let mut users = self.users.lock().unwrap_or_else(|e| e.into_inner());
+        match event {
+            Event::ConnectionOpened { uuid, .. } => {
+                let counters = users.entry(*uuid).or_default();
+                counters.connections += 1;
+                counters.active += 1;
+                counters.last_seen = Some(SystemTime::now());
+            }
+            Event::ConnectionClosed { uuid, .. } => {
+                let counters = users.entry(*uuid).or_default();
+                counters.active = counters.active.saturating_sub(1);
+            }
+            Event::SessionClosed(record) => {
+                let counters = users.entry(record.uuid).or_default();
+                counters.bytes_up = counters.bytes_up.saturating_add(record.bytes_up);
+                counters.bytes_down = counters.bytes_down.saturating_add(record.bytes_down);
+            }
+            Event::AuthFailed { .. } | Event::DestinationBlocked { .. } => {}
+        }
+    }
+
+    /// 全部用户的统计摘要
+    pub fn snapshot(&self) -> Vec<UserSummary> {
+        let users = self.users.lock().unwrap_or_else(|e| e.into_inner());
+        users
+            .iter()
+            .map(|(uuid, c)| UserSummary {
+                uuid: *uuid,
+                email: c.email.clone(),
+                bytes_up: c.bytes_up,
+                bytes_down: c.bytes_down,
+                connections: c.connections,
+                active_connections: c.active,
+                last_seen_secs: c
+                    .last_seen
+                    .map(|t| t.elapsed().map_or(0, |age| age.as_secs())),
+            })
+            .collect()
+    }
+
+    /// 导出各用户的累计计数（不含活跃连接数）
+    pub fn export(&self) -> SavedStats {
+        let users = self.users.lock().unwrap_or_else(|e| e.into_inner());
+        SavedStats::new(
+            users
+                .iter()
+                .map(|(uuid, c)| SavedUser {
+                    uuid: *uuid,
+                    bytes_up: c.bytes_up,
+                    bytes_down: c.bytes_down,
+                    connections: c.connections,
+                    last_seen: c.last_seen.map(unix_secs),
+                })
+                .collect(),
+        )
+    }
+
+    /// 把保存的计数累加到已登记的用户上（配置中已删除的用户被忽略），返回恢复的用户数
+    ///
+    /// 最近活动时间按墙钟时间恢复，停机期间计入间隔；晚于当前时间的记录（时钟回拨）按当前时间处理
+    pub fn restore(&self, saved: &SavedStats) -> usize {
+        let now = SystemTime::now();
+        let mut users = self.users.lock().unwrap_or_else(|e| e.into_inner());
+        let mut restored = 0;
+        for user in &saved.users {
+            let Some(counters) = users.get_mut(&user.uuid) else {
+                continue;
+            };
+            counters.bytes_up = counters.bytes_up.saturating_add(user.bytes_up);
+            counters.bytes_down = counters.bytes_down.saturating_add(user.bytes_down);
+            counters.connections = counters.connections.saturating_add(user.connections);
+            let last_seen = user
+                .last_seen
+                .map(|secs| (UNIX_EPOCH + Duration::from_secs(secs)).min(now));
+            counters.last_seen = counters.last_seen.max(last_seen);
+            restored += 1;
+        }
+        restored
+    }
+
+    /// 把当前计数原子写入状态文件
+    pub fn save(&self, path: &Path) -> Result<()> {
+        let content = serde_json::to_string(&self.export())?;
+        atomic_write_file_with_perms(path, &content, 0o600)
+    }
+
+    /// 启动事件订阅任务
+    pub fn spawn_collector(self: &Arc<Self>, mut events: broadcast::Receiver<Event>) {
+        let stats = Arc::clone(self);
+        tokio::spawn(async move {
+            loop {
+                match events.recv().await {
+                    Ok(event) => stats.apply(&event),
+                    Err(broadcast::error::RecvError::Lagged(n)) => {
+                        warn!("User stats lagged behind, {} events dropped", n);
+                    }
+                    Err(broadcast::error::RecvError::Closed) => break,
+                }
+            }
+        });
+    }
+}
+
+/// 单个用户的保存计数
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedUser {
+    pub uuid: Uuid,
+    pub bytes_up: u64,
+    pub bytes_down: u64,
+    pub connections: u64,
+    /// 最近一次连接时间（Unix 时间戳，秒）
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_seen: Option<u64>,
+}
+
+/// 用户统计状态文件内容
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SavedStats {
+    pub version: u32,
+    /// 保存时间（Unix 时间戳，秒）
+    pub saved_at: u64,
+    pub users: Vec<SavedUser>,
+}
+
+impl SavedStats {
+    /// 以当前时间为保存时间创建状态（如从其他服务导入的计数）
+    pub fn new(users: Vec<SavedUser>) -> Self {
+        Self {
+            version: STATE_VERSION,
+            saved_at: unix_secs(SystemTime::now()),
+            users,
+        }
+    }
+
+    /// 距保存时的秒数（时钟回拨时为 0）
+    pub fn age_secs(&self) -> u64 {
+        unix_secs(SystemTime::now()).saturating_sub(self.saved_at)
+    }
+}
+
+/// 读取状态文件；文件不存在时返回 `None`
+pub fn load_state(path: &Path) -> Result<Option<SavedStats>> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(anyhow!("Failed to read {}: {}", path.display(), e)),
+    };
+    let saved: SavedStats = serde_json::from_str(&content)
+        .map_err(|e| anyhow!("Invalid stats state file {}: {}", path.display(), e))?;
+    if saved.version != STATE_VERSION {
+        return Err(anyhow!(
+            "Unsupported stats state file version {} in {}",
+            saved.version,
+            path.display()
+        ));
+    }
+    Ok(Some(saved))
+}
+
+fn unix_secs(time: SystemTime) -> u64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs())
+}
+
+/// 排序字段
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SortKey {
+    /// 上下行合计流量
+    Traffic,
+    /// 累计连接数
+    Connections,
+    /// 当前活跃连接数
+    Active,
+    /// 邮箱
+    Email,
+}
+
+/// 用户列表查询条件
+#[derive(Debug, Clone)]
+pub struct UserQuery {
+    /// 页码（从 1 开始）
+    pub page: usize,
+    pub per_page: usize,
+    pub sort: SortKey,
+    pub descending: bool,
+    /// 按邮箱或 UUID 子串搜索（不区分大小写）
+    pub search: Option<String>,
+    /// 仅列出有活跃连接的用户
+    pub active_only: bool,
+}
+
+impl Default for UserQuery {
+    fn default() -> Self {
+        Self {
+            page: 1,
+            per_page: DEFAULT_PER_PAGE,
+            sort: SortKey::Traffic,
+            descending: true,
+            search: None,
+            active_only: false,
+        }
+    }
+}
+
+impl UserQuery {
+    /// 从查询参数解析（page、per_page、sort、order、search、active）
+    pub fn from_params(params: &HashMap<String, String>) -> Result<Self> {
+        let mut query = Self::default();
+
+        if let Some(page) = params.get("page") {
+            query.page = page
+                .parse::<usize>()
+                .ok()
+                .filter(|&p| p > 0)
+                .ok_or_else(|| anyhow!("Invalid page"))?;
+        }
+        if let Some(per_page) = params.get("per_page") {
+            query.per_page = per_page
+                .parse::<usize>()
+                .ok()
+                .filter(|&n| n > 0)
+                .ok_or_else(|| anyhow!("Invalid per_page"))?
+                .min(MAX_PER_PAGE);
+        }
+        if let Some(sort) = params.get("sort") {
+            query.sort = match sort.as_str() {
+                "traffic" => SortKey::Traffic,
+                "connections" => SortKey::Connections,
+                "active" => SortKey::Active,
+                "email" => SortKey::Email,
+                _ => return Err(anyhow!("Invalid sort")),
+            };
+            // 邮箱默认升序，其余默认降序
+            query.descending = query.sort != SortKey::Email;
+        }
+        if let Some(order) = params.get("order") {
+            query.descending = match order.as_str() {
+                "asc" => false,
+                "desc" => true,
+                _ => return Err(anyhow!("Invalid order")),
+            };
+        }
+        query.search = params
+            .get("search")
+            .map(|s| s.trim().to_ascii_lowercase())
+            .filter(|s| !s.is_empty());
+        query.active_only = params
+            .get("active")
+            .is_some_and(|v| v == "1" || v == "true");
+
+        Ok(query)
+    }
+}
+
+/// 分页结果
+#[derive(Debug, Clone, Serialize)]
+pub struct UserPage {
+    /// 过滤后的总条数
+    pub total: usize,
+    pub page: usize,
+    pub per_page: usize,
+    pub users: Vec<UserSummary>,
+}
+
+/// 过滤、排序并分页
+pub fn query_users(mut users: Vec<UserSummary>, query: &UserQuery) -> UserPage {
+    users.retain(|u| {
+        (!query.active_only || u.active_connections > 0)
+            && query.search.as_deref().is_none_or(|needle| {
+                u.uuid.to_string().contains(needle)
+                    || u.email
+                        .as_deref()
+                        .is_some_and(|e| e.to_ascii_lowercase().contains(needle))
+            })
+    });
+
+    users.sort_by(|a, b| {
+        let ordering = match query.sort {
+            SortKey::Traffic => a.total_bytes().cmp(&b.total_bytes()),
+            SortKey::Connections => a.connections.cmp(&b.connections),
+            SortKey::Active => a.active_connections.cmp(&b.active_connections),
+            SortKey::Email => a.email.cmp(&b.email),
+        };
+        let ordering = if query.descending {
+            ordering.reverse()
+        } else {
+            ordering
+        };
+        // 相同值按 UUID 排序，保证翻页稳定
+        match ordering {
+            Ordering::Equal => a.uuid.cmp(&b.uuid),
+            other => other,
+        }
+    });
+
+    let total = users.len();
+    let users = users
+        .into_iter()
+        .skip((query.page - 1).saturating_mul(query.per_page))
+        .take(query.per_page)
+        .collect();
+
+    UserPage {
+        total,
+        page: query.page,
+        per_page: query.per_page,
+        users,
+    }
+}
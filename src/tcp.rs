@@ -2,18 +2,30 @@
 //!
 //! 处理原始 TCP 连接上的 VLESS 协议请求
 
-use crate::address::{connect_target, resolve_protocol_address};
+use crate::blocklist::{ensure_allowed, Blocklist};
+use crate::capture::{CaptureReader, Direction};
 use crate::config::PerformanceConfig;
+use crate::dns::{DnsAction, DnsSession, DNS_PORT};
+use crate::events::Event;
+use crate::mux::handle_mux;
+use crate::overhead::Transport;
 use crate::protocol::{
-    authenticate_request, Command, VlessRequest, VlessResponse, VlessResponseSender,
+    authenticate_request, encode_udp_packet, take_udp_packet, Command, VlessRequest, VlessResponse,
+    VlessResponseSender, UDP_LENGTH_PREFIX,
+};
+use crate::router::RouteMatch;
+use crate::session::{
+    copy_counted, copy_with_ttfb, format_destination, user_label, SessionRecord, SessionServices,
 };
 use crate::socket::configure_tcp_socket;
+use crate::stall::ActivityReader;
 use anyhow::{anyhow, Result};
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use std::net::SocketAddr;
 use std::sync::Arc;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::{TcpStream, UdpSocket};
+use std::time::Instant;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+use tokio::net::TcpStream;
 use tracing::{debug, info, warn};
 
 /// 处理 TCP 协议连接
@@ -24,12 +36,14 @@ use tracing::{debug, info, warn};
 /// * `config` - 服务器配置引用
 /// * `performance_config` - 性能配置
 /// * `users` - 有效用户 UUID 集合
+/// * `services` - 会话共享服务（DNS 拦截、拦截列表、目标统计）
 /// * `authenticate` - 认证函数，返回用户邮箱
 pub async fn handle_tcp_connection<F, Fut>(
     mut stream: TcpStream,
     client_addr: SocketAddr,
     performance_config: PerformanceConfig,
     users: &std::collections::HashSet<uuid::Uuid>,
+    services: SessionServices,
     authenticate: F,
 ) -> Result<()>
 where
@@ -58,50 +72,132 @@ where
     // 从栈缓冲区创建 Bytes（一次拷贝，避免堆分配）
     let header_bytes = Bytes::copy_from_slice(&small_buf[..n]);
 
-    // 解析 VLESS 请求
-    let (request, remaining_data) = VlessRequest::decode(header_bytes)?;
+    // 解析 VLESS 请求；失败时按配置回落
+    let (mut request, remaining_data) = match VlessRequest::decode(header_bytes) {
+        Ok(decoded) => decoded,
+        Err(e) => return services.fallback_or(stream, &small_buf[..n], e).await,
+    };
+    let request_header_len = (n - remaining_data.len()) as u64;
 
     debug!("Parsed VLESS request: {:?}", request);
 
     // 验证用户 UUID
-    authenticate_request(&request, users, client_addr)?;
+    if let Err(e) = authenticate_request(&request, users, client_addr) {
+        services.events.publish(Event::AuthFailed {
+            client_addr,
+            uuid: request.uuid,
+        });
+        return services.fallback_or(stream, &small_buf[..n], e).await;
+    }
     info!("Authenticated user {} from {}", request.uuid, client_addr);
 
+    services.sniff_destination(&mut request, &remaining_data);
+    // 用户退出拦截时不再向下传递列表
+    let blocklist = services
+        .blocklist
+        .clone()
+        .filter(|b| b.applies_to(&request.uuid));
+    let allowed = ensure_allowed(blocklist.as_deref(), &request);
+    if allowed.is_ok() {
+        services.apply_domain_strategy(&mut request).await?;
+    }
+    let route = services.route(&request);
+    if let Err(e) = allowed
+        .and_then(|_| services.ensure_routable(&request, &route))
+        .and_then(|_| services.ensure_destination(&request))
+    {
+        info!("{} (user {})", e, request.uuid);
+        services.record_blocked(&route);
+        services.events.publish(Event::DestinationBlocked {
+            user: request.uuid.to_string(),
+            dest: format_destination(&request.address, request.port),
+        });
+        return Ok(());
+    }
+
     let response = VlessResponse::new_with_version(request.version);
     stream.send_response(&response).await?;
+    services.overhead.record_overhead(
+        Transport::Tcp,
+        request_header_len,
+        response.encoded_len() as u64,
+    );
 
     let user_email = authenticate(request.uuid).await;
+    let _connection = services.connection_opened(client_addr, &request, user_email.as_ref());
 
     // 根据命令类型处理连接
     match request.command {
         Command::Tcp => {
             handle_tcp_proxy(
                 stream,
+                client_addr,
                 request,
+                route,
                 remaining_data,
                 performance_config,
+                &services,
+                user_email,
+            )
+            .await
+        }
+        Command::Udp => {
+            handle_udp_proxy(
+                stream,
+                client_addr,
+                request,
+                route,
+                remaining_data,
+                performance_config,
+                &services,
+                blocklist,
                 user_email,
             )
             .await
         }
-        Command::Udp => handle_udp_proxy(stream, request, performance_config, user_email).await,
         Command::Mux => {
-            warn!("Mux command not implemented yet");
-            Err(anyhow!("Mux not supported"))
+            handle_mux(
+                stream,
+                client_addr,
+                request,
+                remaining_data,
+                performance_config,
+                &services,
+                blocklist,
+                user_email,
+            )
+            .await
         }
     }
 }
 
 /// 处理 TCP 代理
-async fn handle_tcp_proxy(
-    client_stream: TcpStream,
+///
+/// `client_stream` 为客户端侧数据流（原始 TCP 连接或 Mux 子连接的内存管道），
+/// `route` 为请求的路由匹配结果
+#[allow(clippy::too_many_arguments)]
+pub(crate) async fn handle_tcp_proxy<S>(
+    client_stream: S,
+    client_addr: SocketAddr,
     request: VlessRequest,
+    route: RouteMatch,
     initial_data: Bytes,
     perf_config: PerformanceConfig,
-    _user_email: Option<Arc<str>>,
-) -> Result<()> {
-    let mut target_stream = connect_target(&request.address, request.port, &perf_config).await?;
+    services: &SessionServices,
+    user_email: Option<Arc<str>>,
+) -> Result<()>
+where
+    S: AsyncRead + AsyncWrite + Send + 'static,
+{
+    let started = Instant::now();
+    let dest = format_destination(&request.address, request.port);
+    let connected = services
+        .connect_request(&request, &route, &perf_config)
+        .await;
+    services.record_connect(&dest, &connected);
+    let (mut target_stream, timing) = connected?;
     let target_addr = target_stream.peer_addr()?;
+    let connected_at = Instant::now();
 
     debug!("Connected to target: {}", target_addr);
 
@@ -112,124 +208,235 @@ async fn handle_tcp_proxy(
 
     info!(
         "Established proxy connection: {} -> {}",
-        client_stream.peer_addr()?,
-        target_addr
+        client_addr, target_addr
     );
 
-    let (mut client_read, mut client_write) = client_stream.into_split();
-    let (mut target_read, mut target_write) = target_stream.into_split();
+    let mut record = SessionRecord::new(
+        request.uuid,
+        user_label(&request.uuid, user_email.as_ref()),
+        "tcp",
+        dest,
+        &timing,
+        started,
+    )
+    .with_route(services.session_route(&request, &route));
+
+    let capture = services.open_capture(&record);
+    if let Some(ref capture) = capture {
+        capture.record(Direction::Up, &initial_data);
+    }
+
+    let (activity, _stall) = services.watch_stall(&record, &perf_config);
+    let (client_read, mut client_write) = tokio::io::split(client_stream);
+    let (target_read, mut target_write) = target_stream.into_split();
+    let client_read = CaptureReader::new(client_read, capture.clone(), Direction::Up);
+    let target_read = CaptureReader::new(target_read, capture, Direction::Down);
+    let mut client_read = ActivityReader::new(client_read, Arc::clone(&activity));
+    let mut target_read = ActivityReader::new(target_read, activity);
 
     let client_to_target = tokio::spawn(async move {
-        let result = tokio::io::copy(&mut client_read, &mut target_write).await;
-        debug!("Client to target copy finished: {:?}", result);
+        let (bytes, result) = copy_counted(&mut client_read, &mut target_write).await;
+        debug!(
+            "Client to target copy finished: {} bytes, {:?}",
+            bytes, result
+        );
+        bytes
     });
 
     let target_to_client = tokio::spawn(async move {
-        let result = tokio::io::copy(&mut target_read, &mut client_write).await;
-        debug!("Target to client copy finished: {:?}", result);
+        let (ttfb, bytes, result) =
+            copy_with_ttfb(&mut target_read, &mut client_write, connected_at).await;
+        debug!(
+            "Target to client copy finished: {} bytes, {:?}",
+            bytes, result
+        );
+        // 目标关闭后半关闭客户端写端（Mux 子连接据此发送 End）
+        let _ = client_write.shutdown().await;
+        (ttfb, bytes)
     });
 
-    let _ = tokio::join!(client_to_target, target_to_client);
+    let (up, down) = tokio::join!(client_to_target, target_to_client);
+    let (ttfb, bytes_down) = down.unwrap_or((None, 0));
+    record.bytes_up = initial_len as u64 + up.unwrap_or(0);
+    record.bytes_down = bytes_down;
+    record.set_ttfb(ttfb);
+    services
+        .overhead
+        .record_session(Transport::Tcp, (record.bytes_up, record.bytes_down), (0, 0));
+    services.finish_session(&mut record);
 
     debug!("Proxy connection closed");
     Ok(())
 }
 
 /// 处理 UDP 代理（UDP over TCP 机制）
+///
+/// 两个方向上每个 UDP 包都带 2 字节大端长度前缀；`initial_data` 为请求头后随附的首批数据。
+/// 会话登记在 `services.udp_sessions` 中，full-cone 时任意远端地址的回包都转给客户端
+#[allow(clippy::too_many_arguments)]
 async fn handle_udp_proxy(
     client_stream: TcpStream,
+    client_addr: SocketAddr,
     request: VlessRequest,
+    route: RouteMatch,
+    initial_data: Bytes,
     perf_config: PerformanceConfig,
-    _user_email: Option<Arc<str>>,
+    services: &SessionServices,
+    blocklist: Option<Arc<Blocklist>>,
+    user_email: Option<Arc<str>>,
 ) -> Result<()> {
-    // 解析目标地址
-    let target_addr = resolve_protocol_address(&request.address, request.port).await?;
-
-    info!(
-        "Establishing UDP proxy: {:?} -> {}",
-        client_stream.peer_addr(),
-        target_addr
-    );
-
-    // 绑定本地 UDP socket（随机端口）
-    let udp_socket = Arc::new(UdpSocket::bind("0.0.0.0:0").await?);
-    let local_addr = udp_socket.local_addr()?;
-    debug!("UDP socket bound to {}", local_addr);
-
-    let udp_timeout = perf_config.udp_timeout;
-
-    // 分离 TCP 流
-    let (mut client_read, mut client_write) = client_stream.into_split();
-
-    // 任务1：客户端 → 目标（读取 TCP 数据，发送 UDP 包）
-    let udp_socket_c2t = Arc::clone(&udp_socket);
+    let destination = format_destination(&request.address, request.port);
+    // 仅对发往 53 端口的 UDP 会话启用 DNS 拦截
+    let dns: Option<Arc<DnsSession>> = services
+        .dns
+        .clone()
+        .filter(|_| request.port == DNS_PORT)
+        .map(|dns| Arc::new(DnsSession::new(dns, destination.clone())));
+
+    // 会话表已满时不再建立新会话
+    let key = (client_addr, 0);
+    if !services.udp_sessions.has_capacity() {
+        return Err(anyhow!(
+            "UDP session limit reached ({})",
+            services.udp_sessions.max()
+        ));
+    }
+    // 解析目标并绑定本地 UDP socket（按配置的地址 / 端口范围），经 SOCKS5 出站时建立中继
+    let started = Instant::now();
+    let (session, timing) = services.open_udp(&request, &route, &perf_config).await?;
+    let mut record = SessionRecord::new(
+        request.uuid,
+        user_label(&request.uuid, user_email.as_ref()),
+        "udp",
+        destination.clone(),
+        &timing,
+        started,
+    )
+    .with_route(services.session_route(&request, &route));
+
+    info!("Establishing UDP proxy: {} -> {}", client_addr, destination);
+    debug!("UDP socket bound to {}", session.socket().local_addr()?);
+    let session = services.udp_sessions.insert(key, session)?;
+
+    // 分离 TCP 流；写半部由两个任务共享（DNS 本地应答直接写回客户端）
+    let (mut client_read, client_write) = client_stream.into_split();
+    let client_write = Arc::new(tokio::sync::Mutex::new(client_write));
+
+    // 任务1：客户端 → 目标（按长度前缀拆包，逐个发送 UDP 包）
+    let session_c2t = Arc::clone(&session);
+    let dns_c2t = dns.clone();
+    let client_write_c2t = Arc::clone(&client_write);
 
     let client_to_target = tokio::spawn(async move {
-        let mut buffer = [0u8; 16 * 1024]; // 16KB，覆盖大多数 UDP 载荷
-        let timeout_duration = std::time::Duration::from_secs(udp_timeout);
-
-        loop {
-            let timeout_result =
-                tokio::time::timeout(timeout_duration, client_read.read(&mut buffer)).await;
+        let mut pending = BytesMut::from(&initial_data[..]);
+        let (mut bytes_up, mut packets_up) = (0u64, 0u64);
+
+        'session: loop {
+            while let Some(packet) = take_udp_packet(&mut pending) {
+                packets_up += 1;
+                session_c2t.touch();
+                if let Some(ref dns) = dns_c2t {
+                    if let DnsAction::Reply(reply) = dns.handle_query(&packet, blocklist.as_deref())
+                    {
+                        let reply = encode_udp_packet(&reply);
+                        if client_write_c2t
+                            .lock()
+                            .await
+                            .write_all(&reply)
+                            .await
+                            .is_err()
+                        {
+                            break 'session;
+                        }
+                        continue;
+                    }
+                }
+                if let Err(e) = session_c2t.send(&packet).await {
+                    warn!("Failed to send UDP packet: {}", e);
+                    break 'session;
+                }
+                bytes_up += packet.len() as u64;
+            }
 
-            match timeout_result {
+            // 空闲计时按会话两个方向的最近活动计算
+            let Some(wait) = session_c2t.remaining() else {
+                debug!("UDP session idle timeout");
+                break;
+            };
+            pending.reserve(16 * 1024); // 16KB，覆盖大多数 UDP 载荷
+            match tokio::time::timeout(wait, client_read.read_buf(&mut pending)).await {
                 Ok(Ok(0)) => {
                     debug!("Client closed connection");
                     break;
                 }
-                Ok(Ok(n)) => {
-                    if let Err(e) = udp_socket_c2t.send_to(&buffer[..n], target_addr).await {
-                        warn!("Failed to send UDP packet: {}", e);
-                        break;
-                    }
-                }
+                Ok(Ok(_)) => {}
                 Ok(Err(e)) => {
                     warn!("Error reading from client: {}", e);
                     break;
                 }
-                Err(_) => {
-                    debug!("UDP session timeout after {}s of inactivity", udp_timeout);
-                    break;
-                }
+                Err(_) => {}
             }
         }
+        (bytes_up, packets_up)
     });
 
-    // 任务2：目标 → 客户端（接收 UDP 包，写入 TCP 流）
-    let udp_socket_t2c = Arc::clone(&udp_socket);
+    // 任务2：目标 → 客户端（接收 UDP 包，加长度前缀写入 TCP 流）
+    let session_t2c = Arc::clone(&session);
 
+    let sent_at = Instant::now();
     let target_to_client = tokio::spawn(async move {
         let mut buffer = [0u8; 16 * 1024]; // 16KB，覆盖大多数 UDP 载荷
-        let timeout_duration = std::time::Duration::from_secs(udp_timeout);
+        let mut ttfb = None;
+        let (mut bytes_down, mut packets_down) = (0u64, 0u64);
 
         loop {
-            let timeout_result =
-                tokio::time::timeout(timeout_duration, udp_socket_t2c.recv_from(&mut buffer)).await;
-
-            match timeout_result {
-                Ok(Ok((n, src))) => {
-                    if src != target_addr {
-                        debug!("Ignoring UDP packet from unexpected source: {}", src);
-                        continue;
-                    }
-                    if client_write.write_all(&buffer[..n]).await.is_err() {
-                        break;
-                    }
-                }
-                Ok(Err(e)) => {
-                    warn!("Error receiving UDP packet: {}", e);
+            let (n, src) = match session_t2c.recv_from(&mut buffer).await {
+                Ok(Some(received)) => received,
+                Ok(None) => {
+                    debug!("UDP session idle timeout");
                     break;
                 }
-                Err(_) => {
-                    debug!("UDP receive timeout after {}s of inactivity", udp_timeout);
+                Err(e) => {
+                    warn!("Error receiving UDP packet: {}", e);
                     break;
                 }
+            };
+            if let Some(ref dns) = dns {
+                dns.handle_response(&buffer[..n], session_t2c.is_target(src));
+            }
+            ttfb.get_or_insert_with(|| sent_at.elapsed());
+            let packet = encode_udp_packet(&buffer[..n]);
+            if client_write.lock().await.write_all(&packet).await.is_err() {
+                break;
             }
+            bytes_down += n as u64;
+            packets_down += 1;
         }
+        (ttfb, bytes_down, packets_down)
     });
 
     // 等待两个任务完成
-    let _ = tokio::join!(client_to_target, target_to_client);
+    let (up, down) = tokio::join!(client_to_target, target_to_client);
+    if session.replay_dropped() > 0 {
+        debug!(
+            "UDP session dropped {} replayed packet(s)",
+            session.replay_dropped()
+        );
+    }
+    services.udp_sessions.remove(&key, &session);
+    let (bytes_up, packets_up) = up.unwrap_or((0, 0));
+    let (ttfb, bytes_down, packets_down) = down.unwrap_or((None, 0, 0));
+    record.bytes_up = bytes_up;
+    record.bytes_down = bytes_down;
+    record.set_ttfb(ttfb);
+    // 长度前缀计为协议开销
+    let prefix = UDP_LENGTH_PREFIX as u64;
+    services.overhead.record_session(
+        Transport::Tcp,
+        (record.bytes_up, record.bytes_down),
+        (packets_up * prefix, packets_down * prefix),
+    );
+    services.finish_session(&mut record);
 
     debug!("UDP proxy session closed");
     Ok(())
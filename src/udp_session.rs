@@ -0,0 +1,356 @@
+//! UDP 会话管理模块
+//!
+//! 按客户端记录 UDP 中继会话：每个会话持有独立的本地 socket，空闲计时按上下行
+//! 任一方向的最近活动计算；full-cone 模式下接受任意远端地址发往该 socket 的数据报，
+//! 而不只是会话的首个目标。会话表达到上限时拒绝新会话
+//!
+//! 经 SOCKS5 出站的会话把数据报加上 UDP 中继头部后发往上游中继地址，回包剥离头部后
+//! 以其中的来源地址参与过滤；目标为域名时由上游解析、无法按目标过滤，
+//! 即使关闭 full-cone 也接受任意来源
+//!
+//! 可选的重放窗口记录每个会话最近收到的下行数据报（来源地址 + 内容的带密钥哈希），
+//! 窗口内重复的数据报视为重放直接丢弃，不写入客户端连接
+
+use crate::outbound::UdpRelay;
+use crate::protocol::Address;
+use crate::socks;
+use anyhow::{anyhow, Result};
+use serde::Serialize;
+use std::collections::hash_map::RandomState;
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::hash::{BuildHasher, Hash};
+use std::net::SocketAddr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tracing::debug;
+
+/// VLESS UDP 会话键：客户端连接地址与子连接 ID（`Command::Udp` 固定为 0，Mux 为子连接 ID）
+pub type UdpSessionKey = (SocketAddr, u16);
+
+/// UDP 会话表统计
+#[derive(Debug, Clone, Serialize)]
+pub struct UdpSessionStats {
+    /// 当前会话数
+    pub active: usize,
+    /// 会话数上限，0 表示不限制
+    pub max: usize,
+    /// 因重放检测丢弃的下行数据报总数
+    pub replay_dropped: u64,
+}
+
+/// 下行重放窗口：保存最近 `capacity` 个数据报的哈希
+///
+/// `order` 记录先后顺序用于淘汰，`seen` 用于常数时间查重
+#[derive(Debug)]
+struct ReplayWindow {
+    /// 每个会话独立的随机密钥，外部无法构造哈希碰撞
+    keys: RandomState,
+    order: VecDeque<u64>,
+    seen: HashSet<u64>,
+    capacity: usize,
+}
+
+impl ReplayWindow {
+    fn new(capacity: usize) -> Self {
+        Self {
+            keys: RandomState::new(),
+            order: VecDeque::new(),
+            seen: HashSet::new(),
+            capacity,
+        }
+    }
+
+    /// 记录数据报；窗口内已出现过时返回 false
+    fn check(&mut self, src: SocketAddr, data: &[u8]) -> bool {
+        let digest = self.keys.hash_one((src, data));
+        if !self.seen.insert(digest) {
+            return false;
+        }
+        if self.order.len() == self.capacity {
+            if let Some(oldest) = self.order.pop_front() {
+                self.seen.remove(&oldest);
+            }
+        }
+        self.order.push_back(digest);
+        true
+    }
+}
+
+/// 数据报的发送方式
+#[derive(Debug)]
+enum Destination {
+    /// 直接发往目标
+    Direct(SocketAddr),
+    /// 经上游 SOCKS5 中继：`header` 为目标的 UDP 中继头部，`target` 为 IP 目标（域名时为 None）
+    Relay {
+        relay: UdpRelay,
+        header: Vec<u8>,
+        target: Option<SocketAddr>,
+    },
+}
+
+/// 单个 UDP 中继会话
+#[derive(Debug)]
+pub struct UdpSession {
+    socket: UdpSocket,
+    destination: Destination,
+    full_cone: bool,
+    idle: Duration,
+    created: Instant,
+    /// 最近活动时间（相对 `created` 的毫秒数）
+    last_active: AtomicU64,
+    replay: Option<Mutex<ReplayWindow>>,
+    replay_dropped: AtomicU64,
+}
+
+impl UdpSession {
+    /// `target` 为客户端请求的目标，`idle` 为空闲超时
+    pub fn new(socket: UdpSocket, target: SocketAddr, full_cone: bool, idle: Duration) -> Self {
+        Self::with_destination(socket, Destination::Direct(target), full_cone, idle)
+    }
+
+    /// 经上游 SOCKS5 中继的会话，`socket` 用于与中继地址收发
+    pub fn via_relay(
+        socket: UdpSocket,
+        relay: UdpRelay,
+        address: &Address,
+        port: u16,
+        full_cone: bool,
+        idle: Duration,
+    ) -> Self {
+        let destination = Destination::Relay {
+            relay,
+            header: socks::encode_udp_header(address, port),
+            target: address.to_socket_addr(port).ok(),
+        };
+        Self::with_destination(socket, destination, full_cone, idle)
+    }
+
+    fn with_destination(
+        socket: UdpSocket,
+        destination: Destination,
+        full_cone: bool,
+        idle: Duration,
+    ) -> Self {
+        Self {
+            socket,
+            destination,
+            full_cone,
+            idle,
+            created: Instant::now(),
+            last_active: AtomicU64::new(0),
+            replay: None,
+            replay_dropped: AtomicU64::new(0),
+        }
+    }
+
+    /// 启用下行重放窗口，`window` 为记录的最近数据报数，0 表示关闭
+    pub fn with_replay_window(mut self, window: usize) -> Self {
+        self.replay = (window > 0).then(|| Mutex::new(ReplayWindow::new(window)));
+        self
+    }
+
+    pub fn socket(&self) -> &UdpSocket {
+        &self.socket
+    }
+
+    /// `src` 是否为会话目标（经中继的域名目标无法比较，视为目标）
+    pub fn is_target(&self, src: SocketAddr) -> bool {
+        match self.destination {
+            Destination::Direct(target) => src == target,
+            Destination::Relay { target, .. } => target.is_none_or(|target| src == target),
+        }
+    }
+
+    /// 是否接受来自 `src` 的数据报：full-cone 时接受任意来源，否则只接受目标地址
+    pub fn accepts(&self, src: SocketAddr) -> bool {
+        self.full_cone || self.is_target(src)
+    }
+
+    /// 向目标发送一个数据报（经中继时加 UDP 中继头部）
+    pub async fn send(&self, data: &[u8]) -> std::io::Result<()> {
+        match self.destination {
+            Destination::Direct(target) => self.socket.send_to(data, target).await?,
+            Destination::Relay {
+                ref relay,
+                ref header,
+                ..
+            } => {
+                let mut packet = Vec::with_capacity(header.len() + data.len());
+                packet.extend_from_slice(header);
+                packet.extend_from_slice(data);
+                self.socket.send_to(&packet, relay.relay_addr()).await?
+            }
+        };
+        Ok(())
+    }
+
+    /// 经中继时剥离回包的 UDP 中继头部，返回（载荷长度，实际来源）；无效数据报返回 None
+    fn unwrap_relayed(
+        &self,
+        buf: &mut [u8],
+        n: usize,
+        src: SocketAddr,
+    ) -> Option<(usize, SocketAddr)> {
+        let Destination::Relay { ref relay, .. } = self.destination else {
+            return Some((n, src));
+        };
+        if src != relay.relay_addr() {
+            debug!("Ignoring UDP packet from non-relay source: {}", src);
+            return None;
+        }
+        let (address, port, offset) = match socks::decode_udp_header(&buf[..n]) {
+            Ok(header) => header,
+            Err(e) => {
+                debug!("Ignoring invalid relayed UDP packet: {}", e);
+                return None;
+            }
+        };
+        let src = address.to_socket_addr(port).ok()?;
+        buf.copy_within(offset..n, 0);
+        Some((n - offset, src))
+    }
+
+    /// 记录一次活动（上行或下行），重置空闲计时
+    pub fn touch(&self) {
+        let elapsed = self.created.elapsed().as_millis() as u64;
+        self.last_active.fetch_max(elapsed, Ordering::Relaxed);
+    }
+
+    /// 距空闲超时的剩余时间，已超时返回 None
+    pub fn remaining(&self) -> Option<Duration> {
+        let last = Duration::from_millis(self.last_active.load(Ordering::Relaxed));
+        let idle_for = self.created.elapsed().saturating_sub(last);
+        self.idle
+            .checked_sub(idle_for)
+            .filter(|remaining| !remaining.is_zero())
+    }
+
+    /// 本会话因重放检测丢弃的数据报数
+    pub fn replay_dropped(&self) -> u64 {
+        self.replay_dropped.load(Ordering::Relaxed)
+    }
+
+    /// 数据报是否为重放（未启用重放窗口时始终为 false）
+    fn is_replay(&self, src: SocketAddr, data: &[u8]) -> bool {
+        let Some(ref replay) = self.replay else {
+            return false;
+        };
+        let fresh = replay
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .check(src, data);
+        if !fresh {
+            self.replay_dropped.fetch_add(1, Ordering::Relaxed);
+        }
+        !fresh
+    }
+
+    /// 接收下一个可接受来源的数据报（重放的数据报被丢弃）；会话空闲超时返回 None
+    pub async fn recv_from(&self, buf: &mut [u8]) -> std::io::Result<Option<(usize, SocketAddr)>> {
+        loop {
+            let Some(wait) = self.remaining() else {
+                return Ok(None);
+            };
+            // 超时后重新计算剩余时间：期间有上行活动时继续等待
+            let Ok(received) = tokio::time::timeout(wait, self.socket.recv_from(buf)).await else {
+                continue;
+            };
+            let (n, src) = received?;
+            let Some((n, src)) = self.unwrap_relayed(buf, n, src) else {
+                continue;
+            };
+            if !self.accepts(src) {
+                debug!("Ignoring UDP packet from unexpected source: {}", src);
+                continue;
+            }
+            if self.is_replay(src, &buf[..n]) {
+                debug!("Dropping replayed UDP packet from {}", src);
+                continue;
+            }
+            self.touch();
+            return Ok(Some((n, src)));
+        }
+    }
+}
+
+/// UDP 会话表
+#[derive(Debug)]
+pub struct UdpSessionTable<K> {
+    sessions: Mutex<HashMap<K, Arc<UdpSession>>>,
+    max: usize,
+    /// 已关闭会话的重放丢弃数累计
+    replay_dropped: AtomicU64,
+}
+
+impl<K> Default for UdpSessionTable<K> {
+    fn default() -> Self {
+        Self {
+            sessions: Mutex::default(),
+            max: 0,
+            replay_dropped: AtomicU64::new(0),
+        }
+    }
+}
+
+impl<K: Eq + Hash> UdpSessionTable<K> {
+    /// `max` 为会话数上限，0 表示不限制
+    pub fn new(max: usize) -> Self {
+        Self {
+            sessions: Mutex::default(),
+            max,
+            replay_dropped: AtomicU64::new(0),
+        }
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, HashMap<K, Arc<UdpSession>>> {
+        self.sessions.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// 是否还能建立新会话（在绑定 socket 前检查，避免无谓的绑定）
+    pub fn has_capacity(&self) -> bool {
+        self.max == 0 || self.lock().len() < self.max
+    }
+
+    /// 登记会话；同一键已有会话时替换，达到上限时返回错误
+    pub fn insert(&self, key: K, session: UdpSession) -> Result<Arc<UdpSession>> {
+        let mut sessions = self.lock();
+        if self.max > 0 && sessions.len() >= self.max && !sessions.contains_key(&key) {
+            return Err(anyhow!("UDP session limit reached ({})", self.max));
+        }
+        let session = Arc::new(session);
+        sessions.insert(key, Arc::clone(&session));
+        Ok(session)
+    }
+
+    /// 移除会话（仅当表中仍是同一会话时，避免误删后来建立的会话）
+    pub fn remove(&self, key: &K, session: &Arc<UdpSession>) {
+        let mut sessions = self.lock();
+        if sessions
+            .get(key)
+            .is_some_and(|current| Arc::ptr_eq(current, session))
+        {
+            sessions.remove(key);
+            self.replay_dropped
+                .fetch_add(session.replay_dropped(), Ordering::Relaxed);
+        }
+    }
+
+    /// 会话数上限，0 表示不限制
+    pub fn max(&self) -> usize {
+        self.max
+    }
+
+    /// 当前统计
+    pub fn stats(&self) -> UdpSessionStats {
+        let sessions = self.lock();
+        let live: u64 = sessions.values().map(|s| s.replay_dropped()).sum();
+        UdpSessionStats {
+            active: sessions.len(),
+            max: self.max,
+            replay_dropped: self.replay_dropped.load(Ordering::Relaxed) + live,
+        }
+    }
+}
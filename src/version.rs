@@ -1,7 +1,9 @@
 // 编译时生成的版本信息（使用 include! 直接包含文件内容）
 include!("version_info.rs");
 
-use crate::config::ProtocolType;
+use crate::config::{BannerMode, Config, ProtocolType};
+use crate::tr;
+use crate::vless_link::UserLink;
 
 /// 服务器状态信息
 #[derive(Clone)]
@@ -80,25 +82,33 @@ pub fn print_banner_with_status(status_info: &ServerStatusInfo) {
 
     // 公网 IP 行（如果可用）
     if let Some(ref public_ip) = status_info.public_ip {
-        status_lines.push(format!("[█] Public IP: {}", public_ip));
+        status_lines.push(tr!("[█] 公网 IP: {}", "[█] Public IP: {}", public_ip));
     }
 
     // 监听地址行
-    status_lines.push(format!("[█] Listening on {}", status_info.listen_addr));
+    status_lines.push(tr!(
+        "[█] 监听地址: {}",
+        "[█] Listening on {}",
+        status_info.listen_addr
+    ));
 
     // 协议类型行
-    status_lines.push(format!("[█] Protocol: {}", protocol_str));
+    status_lines.push(tr!("[█] 协议: {}", "[█] Protocol: {}", protocol_str));
 
     // WebSocket 路径行（如果适用，显示在 Protocol 下方）
     if let Some(ref ws_path) = status_info.ws_path {
-        status_lines.push(format!("[█] WS Path: {}", ws_path));
+        status_lines.push(tr!("[█] WS 路径: {}", "[█] WS Path: {}", ws_path));
     }
 
     // 用户数量行
-    status_lines.push(format!("[█] Users: {}", status_info.user_count));
+    status_lines.push(tr!(
+        "[█] 用户数: {}",
+        "[█] Users: {}",
+        status_info.user_count
+    ));
 
     // 缓冲区大小行
-    status_lines.push(format!("[█] Buffer: {}", buffer_str));
+    status_lines.push(tr!("[█] 缓冲区: {}", "[█] Buffer: {}", buffer_str));
 
     // 确定框的宽度（根据最长行）
     let mut max_width = product_line.len();
@@ -133,7 +143,69 @@ pub fn print_banner_with_status(status_info: &ServerStatusInfo) {
     println!();
 }
 
-/// 右填充字符串到指定宽度
+/// 启动信息 JSON（不含用户凭据）
+pub fn banner_json(status_info: &ServerStatusInfo) -> serde_json::Value {
+    serde_json::json!({
+        "product": VERSION_INFO.product_name,
+        "version": VERSION_INFO.version,
+        "public_ip": status_info.public_ip,
+        "listen": status_info.listen_addr,
+        "protocol": protocol_string(status_info.protocol),
+        "ws_path": status_info.ws_path,
+        "users": status_info.user_count,
+        "buffer_size": status_info.buffer_size,
+    })
+}
+
+/// 启动信息报告（`--print-info json`），供自动化部署解析
+///
+/// 包含用户分享链接；未实现 TLS 入站，`tls_fingerprint` 始终为 `null`
+pub fn startup_report(
+    config: &Config,
+    listen: Vec<String>,
+    panel_url: &str,
+    links: &[UserLink],
+) -> serde_json::Value {
+    serde_json::json!({
+        "product": VERSION_INFO.product_name,
+        "version": VERSION_INFO.version,
+        "listen": listen,
+        "protocol": protocol_string(config.server.protocol),
+        "ws_path": (config.server.protocol == ProtocolType::WebSocket)
+            .then(|| config.server.ws_path.clone()),
+        "panel_url": panel_url,
+        "tls_fingerprint": serde_json::Value::Null,
+        "users": links,
+    })
+}
+
+/// 按格式输出启动横幅
+pub fn print_banner(status_info: &ServerStatusInfo, mode: BannerMode) {
+    match mode {
+        BannerMode::Text => print_banner_with_status(status_info),
+        BannerMode::Json => println!("{}", banner_json(status_info)),
+        BannerMode::None => {}
+    }
+}
+
+/// 右填充字符串到指定显示宽度（中文等全角字符占两列）
 fn pad_right(s: &str, width: usize) -> String {
-    format!("{:<width$}", s, width = width)
+    let padding = width.saturating_sub(display_width(s));
+    format!("{}{}", s, " ".repeat(padding))
+}
+
+/// 字符串在终端中的显示宽度
+fn display_width(s: &str) -> usize {
+    s.chars()
+        .map(|c| match c as u32 {
+            0x1100..=0x115F
+            | 0x2E80..=0xA4CF
+            | 0xAC00..=0xD7A3
+            | 0xF900..=0xFAFF
+            | 0xFE30..=0xFE4F
+            | 0xFF00..=0xFF60
+            | 0xFFE0..=0xFFE6 => 2,
+            _ => 1,
+        })
+        .sum()
 }
@@ -17,6 +17,10 @@ pub struct VlessLinkConfig {
     pub port: u16,
     /// WebSocket 路径（如果支持 WebSocket）
     pub ws_path: Option<String>,
+    /// WebSocket Host 请求头（CDN 前置时与连接地址不同）
+    pub ws_host: Option<String>,
+    /// TLS SNI（设置后 WebSocket 链接使用 security=tls，TLS 由 CDN 终止）
+    pub sni: Option<String>,
     /// 用户标识（email 或其他）
     pub alias: String,
 }
@@ -62,11 +66,21 @@ pub fn generate_vless_links(config: &VlessLinkConfig) -> VlessLinks {
     );
 
     // 生成 WebSocket 链接（如果有 ws_path）
+    // 连接地址、Host 请求头与 SNI 可分别配置，用于 CDN 前置
     let ws_link = config.ws_path.as_ref().map(|ws_path| {
         let path_encoded = urlencoding::encode(ws_path);
+        let security = match config.sni {
+            Some(ref sni) => format!("security=tls&sni={}", urlencoding::encode(sni)),
+            None => "security=none".to_string(),
+        };
+        let host_param = config
+            .ws_host
+            .as_ref()
+            .map(|h| format!("&host={}", urlencoding::encode(h)))
+            .unwrap_or_default();
         format!(
-            "vless://{}@{}:{}?encryption=none&security=none&type=ws&path={}#{}",
-            uuid_str, config.host, config.port, path_encoded, alias_encoded
+            "vless://{}@{}:{}?encryption=none&{}&type=ws{}&path={}#{}",
+            uuid_str, config.host, config.port, security, host_param, path_encoded, alias_encoded
         )
     });
 
@@ -88,3 +102,47 @@ pub fn generate_vless_links(config: &VlessLinkConfig) -> VlessLinks {
 fn encode_link(link: &str) -> String {
     BASE64.encode(link.as_bytes())
 }
+
+/// 用户分享链接（对应服务器当前的传输方式）
+#[derive(Debug, Clone, Serialize)]
+pub struct UserLink {
+    pub uuid: Uuid,
+    pub email: Option<String>,
+    /// 传输方式：`tcp` 或 `ws`
+    pub transport: &'static str,
+    /// 原始 VLESS 链接
+    pub vless: String,
+    /// Base64 编码的链接
+    pub base64: String,
+}
+
+/// 生成单个用户的分享链接
+///
+/// 设置了 `ws_path` 时生成 WebSocket 链接，否则生成 TCP 链接
+pub fn generate_user_link(config: &VlessLinkConfig, email: Option<String>) -> UserLink {
+    let links = generate_vless_links(config);
+    let (transport, link) = match links.ws {
+        Some(ws) => ("ws", ws),
+        None => ("tcp", links.tcp),
+    };
+    UserLink {
+        uuid: config.uuid,
+        email,
+        transport,
+        vless: link.vless,
+        base64: link.base64,
+    }
+}
+
+/// 生成链接文件内容：每个用户一行注释加一行链接
+pub fn format_links_file(links: &[UserLink]) -> String {
+    let mut out = String::new();
+    for link in links {
+        let label = link.email.clone().unwrap_or_else(|| link.uuid.to_string());
+        out.push_str(&format!(
+            "# {} ({})\n{}\n",
+            label, link.transport, link.vless
+        ));
+    }
+    out
+}
@@ -1,5 +1,6 @@
 use crate::config::{Config, ProtocolType, ServerSettings, UserConfig};
-use anyhow::Result;
+use crate::tr;
+use anyhow::{anyhow, Result};
 use std::io::{self, Write};
 use uuid::Uuid;
 
@@ -10,10 +11,17 @@ impl ConfigWizard {
     /// 启动配置向导
     pub fn run() -> Result<Config> {
         println!("\n╔════════════════════════════════════════════════════════════╗");
-        println!("║         VLESS Server - 首次配置向导                        ║");
-        println!("║                                                            ║");
-        println!("║  欢迎使用 VLESS 服务器！                                   ║");
-        println!("║  这个向导将帮助您完成基本配置。                            ║");
+        if crate::i18n::is_zh() {
+            println!("║         VLESS Server - 首次配置向导                        ║");
+            println!("║                                                            ║");
+            println!("║  欢迎使用 VLESS 服务器！                                   ║");
+            println!("║  这个向导将帮助您完成基本配置。                            ║");
+        } else {
+            println!("║         VLESS Server - First-run setup                     ║");
+            println!("║                                                            ║");
+            println!("║  Welcome to VLESS Server!                                  ║");
+            println!("║  This wizard walks you through the basic settings.         ║");
+        }
         println!("╚════════════════════════════════════════════════════════════╝\n");
 
         // 配置服务器监听地址
@@ -25,10 +33,17 @@ impl ConfigWizard {
         // 配置协议类型
         let (protocol, ws_path) = Self::prompt_protocol()?;
 
-        // 配置用户
-        let users = Self::prompt_users()?;
+        // 导入已有用户，再按需添加
+        let imported = Self::prompt_import()?;
+        let users = Self::prompt_users(imported)?;
 
-        println!("\n✓ 配置完成！正在生成配置文件...\n");
+        println!(
+            "{}",
+            tr!(
+                "\n✓ 配置完成！正在生成配置文件...\n",
+                "\n✓ Configuration complete! Writing config file...\n"
+            )
+        );
 
         // 创建配置
         let config = Config {
@@ -37,9 +52,49 @@ impl ConfigWizard {
                 port,
                 protocol,
                 ws_path,
+                ws_early_data: 0,
+                public_ip: None,
+                domain: None,
+                detect_public_ip: true,
+                ws_host: None,
+                sni: None,
+                link_port: None,
+                links_file: None,
+                bind_retries: 5,
+                bind_retry_delay_ms: 500,
+                inbound_protocols: None,
+                strict_tls_only: false,
+                outbound: None,
+                block_private_destinations: true,
+                domain_strategy: Default::default(),
             },
             users,
+            language: None,
+            profile: None,
             performance: Default::default(),
+            dns: Default::default(),
+            blocklist: Default::default(),
+            decoy: Default::default(),
+            sni_proxy: Default::default(),
+            fallbacks: Vec::new(),
+            api: Default::default(),
+            accounting: Default::default(),
+            port_mapping: Default::default(),
+            ddns: Default::default(),
+            monitoring: Default::default(),
+            output: Default::default(),
+            outbound_tls: Vec::new(),
+            forwards: Vec::new(),
+            outbounds: Vec::new(),
+            local_proxies: Vec::new(),
+            routing: Default::default(),
+            restart: Default::default(),
+            sniffing: Default::default(),
+            resolver: Default::default(),
+            maintenance: Default::default(),
+            hooks: Vec::new(),
+            crowdsec: Default::default(),
+            preset_fields: Vec::new(),
         };
 
         Ok(config)
@@ -47,14 +102,44 @@ impl ConfigWizard {
 
     /// 提示输入监听地址
     fn prompt_listen_address() -> Result<String> {
-        println!("【服务器监听地址】");
-        println!("  监听地址决定了服务器接受连接的网络接口。");
-        println!("  • 0.0.0.0  - 监听所有网络接口（推荐）");
-        println!("  • 127.0.0.1 - 仅本地访问");
-        println!("  • 特定IP   - 仅指定网卡");
+        println!("{}", tr!("【服务器监听地址】", "[Listen address]"));
+        println!(
+            "{}",
+            tr!(
+                "  监听地址决定了服务器接受连接的网络接口。",
+                "  The network interface the server accepts connections on."
+            )
+        );
+        println!(
+            "{}",
+            tr!(
+                "  • 0.0.0.0  - 监听所有网络接口（推荐）",
+                "  • 0.0.0.0   - all interfaces (recommended)"
+            )
+        );
+        println!(
+            "{}",
+            tr!(
+                "  • 127.0.0.1 - 仅本地访问",
+                "  • 127.0.0.1 - local access only"
+            )
+        );
+        println!(
+            "{}",
+            tr!(
+                "  • 特定IP   - 仅指定网卡",
+                "  • other IP  - that interface only"
+            )
+        );
 
         loop {
-            print!("  请输入监听地址 [默认: 0.0.0.0]: ");
+            print!(
+                "{}",
+                tr!(
+                    "  请输入监听地址 [默认: 0.0.0.0]: ",
+                    "  Listen address [default: 0.0.0.0]: "
+                )
+            );
             io::stdout().flush()?;
 
             let mut input = String::new();
@@ -70,18 +155,42 @@ impl ConfigWizard {
                 return Ok(input.to_string());
             }
 
-            println!("  ⚠ 无效的IP地址格式，请重新输入");
+            println!(
+                "{}",
+                tr!(
+                    "  ⚠ 无效的IP地址格式，请重新输入",
+                    "  ⚠ Invalid IP address, please try again"
+                )
+            );
         }
     }
 
     /// 提示输入端口
     fn prompt_port() -> Result<u16> {
-        println!("\n【服务器监听端口】");
-        println!("  监听端口用于接受 VLESS 连接和 HTTP 监控请求。");
-        println!("  常用端口：443 (HTTPS)、8443 (备用HTTPS)");
+        println!("{}", tr!("\n【服务器监听端口】", "\n[Listen port]"));
+        println!(
+            "{}",
+            tr!(
+                "  监听端口用于接受 VLESS 连接和 HTTP 监控请求。",
+                "  The port for VLESS connections and HTTP requests."
+            )
+        );
+        println!(
+            "{}",
+            tr!(
+                "  常用端口：443 (HTTPS)、8443 (备用HTTPS)",
+                "  Common ports: 443 (HTTPS), 8443 (alternative HTTPS)"
+            )
+        );
 
         loop {
-            print!("  请输入端口 [1-65535，默认: 443]: ");
+            print!(
+                "{}",
+                tr!(
+                    "  请输入端口 [1-65535，默认: 443]: ",
+                    "  Port [1-65535, default: 443]: "
+                )
+            );
             io::stdout().flush()?;
 
             let mut input = String::new();
@@ -94,20 +203,50 @@ impl ConfigWizard {
 
             match input.parse::<u16>() {
                 Ok(port) if port > 0 => return Ok(port),
-                _ => println!("  ⚠ 无效的端口号，请输入 1-65535 之间的数字"),
+                _ => println!(
+                    "{}",
+                    tr!(
+                        "  ⚠ 无效的端口号，请输入 1-65535 之间的数字",
+                        "  ⚠ Invalid port, enter a number between 1 and 65535"
+                    )
+                ),
             }
         }
     }
 
     /// 提示配置协议类型
     fn prompt_protocol() -> Result<(ProtocolType, String)> {
-        println!("\n【传输协议类型】");
-        println!("  选择服务器使用的传输协议类型。");
-        println!("  • TCP - 原始 VLESS over TCP（推荐）");
-        println!("  • WS  - VLESS over WebSocket（可穿透防火墙）");
+        println!("{}", tr!("\n【传输协议类型】", "\n[Transport]"));
+        println!(
+            "{}",
+            tr!(
+                "  选择服务器使用的传输协议类型。",
+                "  Choose the transport the server uses."
+            )
+        );
+        println!(
+            "{}",
+            tr!(
+                "  • TCP - 原始 VLESS over TCP（推荐）",
+                "  • TCP - plain VLESS over TCP (recommended)"
+            )
+        );
+        println!(
+            "{}",
+            tr!(
+                "  • WS  - VLESS over WebSocket（可穿透防火墙）",
+                "  • WS  - VLESS over WebSocket (passes firewalls and CDNs)"
+            )
+        );
 
         loop {
-            print!("  请选择协议类型 [1]TCP / [2]WS [默认: 1]: ");
+            print!(
+                "{}",
+                tr!(
+                    "  请选择协议类型 [1]TCP / [2]WS [默认: 1]: ",
+                    "  Transport [1]TCP / [2]WS [default: 1]: "
+                )
+            );
             io::stdout().flush()?;
 
             let mut input = String::new();
@@ -120,19 +259,43 @@ impl ConfigWizard {
                 let ws_path = Self::prompt_ws_path()?;
                 return Ok((ProtocolType::WebSocket, ws_path));
             } else {
-                println!("  ⚠ 无效选择，请输入 1 或 2");
+                println!(
+                    "{}",
+                    tr!(
+                        "  ⚠ 无效选择，请输入 1 或 2",
+                        "  ⚠ Invalid choice, enter 1 or 2"
+                    )
+                );
             }
         }
     }
 
     /// 提示配置 WebSocket 路径
     fn prompt_ws_path() -> Result<String> {
-        println!("\n【WebSocket 路径】");
-        println!("  WebSocket 路径用于客户端连接识别。");
-        println!("  示例：/vless, /ws, /proxy");
+        println!("{}", tr!("\n【WebSocket 路径】", "\n[WebSocket path]"));
+        println!(
+            "{}",
+            tr!(
+                "  WebSocket 路径用于客户端连接识别。",
+                "  The path clients use for the WebSocket connection."
+            )
+        );
+        println!(
+            "{}",
+            tr!(
+                "  示例：/vless, /ws, /proxy",
+                "  Examples: /vless, /ws, /proxy"
+            )
+        );
 
         loop {
-            print!("  请输入 WebSocket 路径 [默认: /vless]: ");
+            print!(
+                "{}",
+                tr!(
+                    "  请输入 WebSocket 路径 [默认: /vless]: ",
+                    "  WebSocket path [default: /vless]: "
+                )
+            );
             io::stdout().flush()?;
 
             let mut input = String::new();
@@ -147,12 +310,21 @@ impl ConfigWizard {
 
             // 验证路径格式
             if !path.starts_with('/') {
-                println!("  ⚠ 路径必须以 / 开头");
+                println!(
+                    "{}",
+                    tr!("  ⚠ 路径必须以 / 开头", "  ⚠ The path must start with /")
+                );
                 continue;
             }
 
             if path.contains("..") || path.contains('\\') {
-                println!("  ⚠ 路径包含非法字符");
+                println!(
+                    "{}",
+                    tr!(
+                        "  ⚠ 路径包含非法字符",
+                        "  ⚠ The path contains invalid characters"
+                    )
+                );
                 continue;
             }
 
@@ -160,22 +332,121 @@ impl ConfigWizard {
         }
     }
 
-    /// 提示配置用户
-    fn prompt_users() -> Result<Vec<UserConfig>> {
-        println!("\n【用户配置】");
-        println!("  VLESS 协议使用 UUID 作为用户认证凭据。");
-        println!("  每个用户需要唯一的 UUID 和可选的邮箱地址。\n");
+    /// 提示从文件导入已有用户
+    fn prompt_import() -> Result<Vec<UserConfig>> {
+        println!("{}", tr!("\n【导入用户】", "\n[Import users]"));
+        println!(
+            "{}",
+            tr!(
+                "  可从以下文件导入已有用户（迁移时无需重新创建）：",
+                "  Existing users can be imported from (no need to recreate them when migrating):"
+            )
+        );
+        println!("{}", tr!("  • 旧的 config.json", "  • an old config.json"));
+        println!(
+            "{}",
+            tr!(
+                "  • vless:// 链接列表（每行一个）",
+                "  • a list of vless:// links (one per line)"
+            )
+        );
+        println!(
+            "{}",
+            tr!(
+                "  • CSV（每行 uuid,email）",
+                "  • a CSV file (uuid,email per line)"
+            )
+        );
 
-        let mut users = Vec::new();
+        loop {
+            print!(
+                "{}",
+                tr!(
+                    "  请输入文件路径 [留空跳过]: ",
+                    "  File path [leave empty to skip]: "
+                )
+            );
+            io::stdout().flush()?;
+
+            let mut input = String::new();
+            io::stdin().read_line(&mut input)?;
+            let path = input.trim();
+            if path.is_empty() {
+                return Ok(Vec::new());
+            }
+
+            let imported = std::fs::read_to_string(path)
+                .map_err(|e| anyhow!("Cannot read {}: {}", path, e))
+                .and_then(|content| parse_user_import(&content));
+            match imported {
+                Ok(users) if !users.is_empty() => {
+                    println!(
+                        "{}",
+                        tr!("  ✓ 已导入 {} 个用户", "  ✓ Imported {} users", users.len())
+                    );
+                    return Ok(users);
+                }
+                Ok(_) => println!(
+                    "{}",
+                    tr!("  ⚠ 文件中没有找到用户", "  ⚠ No users found in the file")
+                ),
+                Err(e) => println!("{}", tr!("  ⚠ 导入失败: {}", "  ⚠ Import failed: {}", e)),
+            }
+        }
+    }
+
+    /// 提示配置用户（已导入用户时可以跳过添加）
+    fn prompt_users(mut users: Vec<UserConfig>) -> Result<Vec<UserConfig>> {
+        println!("{}", tr!("\n【用户配置】", "\n[Users]"));
+        println!(
+            "{}",
+            tr!(
+                "  VLESS 协议使用 UUID 作为用户认证凭据。",
+                "  VLESS authenticates users by UUID."
+            )
+        );
+        println!(
+            "{}",
+            tr!(
+                "  每个用户需要唯一的 UUID 和可选的邮箱地址。\n",
+                "  Each user needs a unique UUID and an optional email.\n"
+            )
+        );
+
+        if !users.is_empty() {
+            print!(
+                "{}",
+                tr!(
+                    "已导入 {} 个用户，是否继续添加用户？[y/N]: ",
+                    "{} users imported. Add more users? [y/N]: ",
+                    users.len()
+                )
+            );
+            io::stdout().flush()?;
+
+            let mut input = String::new();
+            io::stdin().read_line(&mut input)?;
+            let input = input.trim().to_lowercase();
+            if input != "y" && input != "yes" {
+                return Ok(users);
+            }
+            println!();
+        }
 
         loop {
             let user = Self::prompt_user(&users)?;
             users.push(user);
 
-            println!("\n当前用户数: {}", users.len());
+            println!(
+                "{}",
+                tr!("\n当前用户数: {}", "\nUsers so far: {}", users.len())
+            );
 
             if !users.is_empty() {
-                print!("是否继续添加用户？[y/N]: ");
+                print!(
+                    "{}",
+                    tr!("是否继续添加用户？[y/N]: ", "Add another user? [y/N]: ")
+                );
                 io::stdout().flush()?;
 
                 let mut input = String::new();
@@ -197,17 +468,44 @@ impl ConfigWizard {
     fn prompt_user(existing_users: &[UserConfig]) -> Result<UserConfig> {
         loop {
             println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━");
-            println!("添加用户 #{}", existing_users.len() + 1);
+            println!(
+                "{}",
+                tr!("添加用户 #{}", "Add user #{}", existing_users.len() + 1)
+            );
             println!("━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━━\n");
 
             // UUID 配置
-            println!("【用户 UUID】");
-            println!("  UUID 是用户的唯一认证凭据，必须保密。");
-            println!("  • 自动生成 - 系统随机生成安全的 UUID（推荐）");
-            println!("  • 手动输入 - 使用自定义 UUID（8-4-4-4-12 格式）");
+            println!("{}", tr!("【用户 UUID】", "[User UUID]"));
+            println!(
+                "{}",
+                tr!(
+                    "  UUID 是用户的唯一认证凭据，必须保密。",
+                    "  The UUID is the user's credential and must be kept secret."
+                )
+            );
+            println!(
+                "{}",
+                tr!(
+                    "  • 自动生成 - 系统随机生成安全的 UUID（推荐）",
+                    "  • Auto   - generate a random UUID (recommended)"
+                )
+            );
+            println!(
+                "{}",
+                tr!(
+                    "  • 手动输入 - 使用自定义 UUID（8-4-4-4-12 格式）",
+                    "  • Manual - enter your own UUID (8-4-4-4-12 format)"
+                )
+            );
 
             let uuid = loop {
-                print!("  选择 [A]自动生成 / [M]手动输入 [默认: A]: ");
+                print!(
+                    "{}",
+                    tr!(
+                        "  选择 [A]自动生成 / [M]手动输入 [默认: A]: ",
+                        "  Choose [A]uto / [M]anual [default: A]: "
+                    )
+                );
                 io::stdout().flush()?;
 
                 let mut input = String::new();
@@ -216,10 +514,13 @@ impl ConfigWizard {
 
                 if input.is_empty() || input == "a" || input == "auto" {
                     let new_uuid = Uuid::new_v4();
-                    println!("  ✓ 已生成 UUID: {}", new_uuid);
+                    println!(
+                        "{}",
+                        tr!("  ✓ 已生成 UUID: {}", "  ✓ Generated UUID: {}", new_uuid)
+                    );
                     break new_uuid.to_string();
                 } else if input == "m" || input == "manual" {
-                    print!("  请输入 UUID: ");
+                    print!("{}", tr!("  请输入 UUID: ", "  UUID: "));
                     io::stdout().flush()?;
 
                     let mut uuid_input = String::new();
@@ -230,22 +531,51 @@ impl ConfigWizard {
                         Ok(uuid) => break uuid.to_string(),
                         Err(_) => {
                             println!(
-                                "  ⚠ 无效的 UUID 格式，示例: 550e8400-e29b-41d4-a716-446655440000"
+                                "{}",
+                                tr!(
+                                    "  ⚠ 无效的 UUID 格式，示例: 550e8400-e29b-41d4-a716-446655440000",
+                                    "  ⚠ Invalid UUID, example: 550e8400-e29b-41d4-a716-446655440000"
+                                )
                             );
                         }
                     }
                 } else {
-                    println!("  ⚠ 无效选择，请输入 A 或 M");
+                    println!(
+                        "{}",
+                        tr!(
+                            "  ⚠ 无效选择，请输入 A 或 M",
+                            "  ⚠ Invalid choice, enter A or M"
+                        )
+                    );
                 }
             };
 
             // 邮箱配置
             let default_email = format!("user{}@a.com", existing_users.len() + 1);
-            println!("\n【用户邮箱】");
-            println!("  邮箱地址用于标识用户，方便管理。");
-            println!("  可以在客户端显示，帮助识别连接。");
-
-            print!("  请输入邮箱地址 [默认: {}]: ", default_email);
+            println!("{}", tr!("\n【用户邮箱】", "\n[User email]"));
+            println!(
+                "{}",
+                tr!(
+                    "  邮箱地址用于标识用户，方便管理。",
+                    "  The email identifies the user for management."
+                )
+            );
+            println!(
+                "{}",
+                tr!(
+                    "  可以在客户端显示，帮助识别连接。",
+                    "  It is shown in clients to tell connections apart."
+                )
+            );
+
+            print!(
+                "{}",
+                tr!(
+                    "  请输入邮箱地址 [默认: {}]: ",
+                    "  Email [default: {}]: ",
+                    default_email
+                )
+            );
             io::stdout().flush()?;
 
             let mut input = String::new();
@@ -257,33 +587,129 @@ impl ConfigWizard {
             } else {
                 // 验证邮箱格式（基本格式检查）
                 if !is_valid_email_format(input) {
-                    println!("  ⚠ 邮箱格式不正确，但仍然接受");
+                    println!(
+                        "{}",
+                        tr!(
+                            "  ⚠ 邮箱格式不正确，但仍然接受",
+                            "  ⚠ The email looks malformed, accepting it anyway"
+                        )
+                    );
                 }
                 input.to_string()
             };
 
             // 验证 UUID 唯一性
             if let Some(existing) = existing_users.iter().find(|u| u.uuid == uuid) {
+                let name = existing
+                    .email
+                    .clone()
+                    .unwrap_or_else(|| tr!("未命名", "unnamed"));
                 println!(
-                    "  ✗ 错误：UUID 与现有用户重复: {}",
-                    existing.email.as_deref().unwrap_or("未命名")
+                    "{}",
+                    tr!(
+                        "  ✗ 错误：UUID 与现有用户重复: {}",
+                        "  ✗ Error: UUID already used by {}",
+                        name
+                    )
+                );
+                println!(
+                    "{}",
+                    tr!(
+                        "  请重新输入不同的 UUID\n",
+                        "  Please enter a different UUID\n"
+                    )
                 );
-                println!("  请重新输入不同的 UUID\n");
                 continue; // 重新开始循环，而不是递归
             }
 
-            println!("\n✓ 用户配置完成");
+            println!("{}", tr!("\n✓ 用户配置完成", "\n✓ User added"));
             println!("  UUID: {}", uuid);
             println!("  Email: {}", email);
 
             return Ok(UserConfig {
                 uuid,
                 email: Some(email),
+                blocklist: true,
             });
         }
     }
 }
 
+/// 解析导入的用户列表，按内容识别格式：
+/// - JSON：本程序的 config.json（读取 `users`）
+/// - `vless://` 链接：每行一个，UUID 取自用户名部分，邮箱取自 `#` 后的备注
+/// - CSV：每行 `uuid,email`（邮箱可省略，允许 `uuid,email` 表头）
+///
+/// 空行与 `#` 开头的行被忽略；重复 UUID 只保留第一个
+pub fn parse_user_import(content: &str) -> Result<Vec<UserConfig>> {
+    let users = if content.trim_start().starts_with('{') {
+        Config::from_json(content)
+            .map_err(|e| anyhow!("Invalid config.json: {}", e))?
+            .users
+            .into_iter()
+            .map(|user| {
+                Uuid::parse_str(&user.uuid)
+                    .map_err(|_| anyhow!("Invalid UUID in config.json: {}", user.uuid))?;
+                Ok(user)
+            })
+            .collect::<Result<Vec<_>>>()?
+    } else {
+        let mut users = Vec::new();
+        for (index, line) in content.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (uuid, email) = match line.strip_prefix("vless://") {
+                Some(link) => parse_vless_link(link),
+                None => {
+                    let mut fields = line.splitn(2, ',');
+                    let uuid = fields.next().unwrap_or_default().trim();
+                    if index == 0 && uuid.eq_ignore_ascii_case("uuid") {
+                        continue;
+                    }
+                    let email = fields
+                        .next()
+                        .map(|e| e.trim().trim_matches('"').to_string())
+                        .filter(|e| !e.is_empty());
+                    (uuid.to_string(), email)
+                }
+            };
+            let uuid = Uuid::parse_str(&uuid)
+                .map_err(|_| anyhow!("Line {}: invalid UUID: {}", index + 1, uuid))?;
+            users.push(UserConfig {
+                uuid: uuid.to_string(),
+                email,
+                blocklist: true,
+            });
+        }
+        users
+    };
+
+    let mut seen = std::collections::HashSet::new();
+    Ok(users
+        .into_iter()
+        .filter(|user| seen.insert(user.uuid.to_ascii_lowercase()))
+        .collect())
+}
+
+/// 拆出 `vless://` 链接中的 UUID 与备注（URL 解码）
+fn parse_vless_link(link: &str) -> (String, Option<String>) {
+    let (rest, alias) = match link.split_once('#') {
+        Some((rest, alias)) => (rest, Some(alias)),
+        None => (link, None),
+    };
+    let uuid = rest.split('@').next().unwrap_or_default().to_string();
+    let email = alias
+        .map(|a| {
+            urlencoding::decode(a)
+                .map(|s| s.into_owned())
+                .unwrap_or_else(|_| a.to_string())
+        })
+        .filter(|a| !a.is_empty());
+    (uuid, email)
+}
+
 /// 验证邮箱格式（基本检查）
 ///
 /// 检查规则：
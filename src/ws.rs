@@ -2,29 +2,69 @@
 //!
 //! 处理 WebSocket 连接上的 VLESS 协议请求
 
-use crate::address::connect_target;
+use crate::blocklist::ensure_allowed;
+use crate::capture::Direction;
 use crate::config::PerformanceConfig;
-use crate::http::{extract_header_value, extract_http_path, validate_http_headers};
+use crate::events::Event;
+use crate::http::{extract_header_value, extract_http_path, host_matches, validate_http_headers};
+use crate::overhead::{ws_frame_header_len, Transport};
 use crate::protocol::{
     authenticate_request, Command, VlessRequest, VlessResponse, VlessResponseSender,
+    VLESS_VERSION_BETA, VLESS_VERSION_RELEASE,
 };
+use crate::router::RouteMatch;
+use crate::session::{format_destination, user_label, SessionRecord, SessionServices};
 use crate::socket::configure_tcp_socket;
+use crate::stall::ActivityReader;
 use anyhow::{anyhow, Result};
-use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
+use base64::engine::general_purpose::{STANDARD as BASE64, URL_SAFE_NO_PAD};
+use base64::Engine as _;
 use bytes::Bytes;
 use futures_util::stream::{SplitSink, SplitStream};
 use futures_util::{SinkExt, StreamExt};
 use sha1_smol::Sha1;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Instant;
 use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpStream;
+use tokio_tungstenite::tungstenite::protocol::frame::coding::CloseCode;
+use tokio_tungstenite::tungstenite::protocol::CloseFrame;
 use tokio_tungstenite::tungstenite::Message;
 use tracing::{debug, info, warn};
 
 /// WebSocket 握手密钥常量
 const WEBSOCKET_GUID: &str = "258EAFA5-E914-47DA-95CA-C5AB0DC85B11";
 
+/// 请求路径是否为配置的 WebSocket 路径
+///
+/// 未去掉早期数据参数的客户端会把 `?ed=2048` 一并发送，同样视为匹配
+pub fn ws_path_matches(path: &str, expected: &str) -> bool {
+    path == expected
+        || path
+            .strip_prefix(expected)
+            .is_some_and(|rest| rest.starts_with("?ed="))
+}
+
+/// 解析 `Sec-WebSocket-Protocol` 中的早期数据（Xray / sing-box 约定的 URL 安全 Base64）
+///
+/// 仅在请求路径带 `?ed=` 参数，或解码结果以 VLESS 版本号与 UUID 开头时视为早期数据；
+/// 其他取值（如 `chat` 这类恰好是合法 Base64 的子协议名）返回 None
+pub fn decode_early_data(value: &str, path: &str) -> Option<Bytes> {
+    let data = URL_SAFE_NO_PAD
+        .decode(value.trim().trim_end_matches('='))
+        .ok()?;
+    if data.is_empty() {
+        return None;
+    }
+    let has_ed = path
+        .split_once('?')
+        .is_some_and(|(_, query)| query.split('&').any(|param| param.starts_with("ed=")));
+    let vless_header =
+        data.len() > 16 && matches!(data[0], VLESS_VERSION_BETA | VLESS_VERSION_RELEASE);
+    (has_ed || vless_header).then(|| Bytes::from(data))
+}
+
 /// 检测 HTTP 请求是否是 WebSocket 升级请求
 pub fn is_websocket_upgrade(data: &[u8]) -> bool {
     let text = match std::str::from_utf8(data) {
@@ -59,11 +99,19 @@ pub fn is_websocket_upgrade(data: &[u8]) -> bool {
 }
 
 /// 验证并处理 WebSocket 升级请求，手动完成握手
+///
+/// 返回 WebSocket 流、`Sec-WebSocket-Protocol` 携带的早期数据，
+/// 以及握手消耗的（上行，下行）字节数（早期数据不计入开销）
 async fn process_ws_handshake(
     mut stream: TcpStream,
     expected_path: &str,
+    expected_host: Option<&str>,
     header_buffer_size: usize,
-) -> Result<tokio_tungstenite::WebSocketStream<TcpStream>> {
+) -> Result<(
+    tokio_tungstenite::WebSocketStream<TcpStream>,
+    Option<Bytes>,
+    (u64, u64),
+)> {
     let mut header_buf = Vec::new();
     let mut temp_buf = [0u8; 1024];
 
@@ -85,7 +133,7 @@ async fn process_ws_handshake(
     }
 
     let path = extract_http_path(&header_buf).ok_or_else(|| anyhow!("Invalid HTTP request"))?;
-    if path != expected_path {
+    if !ws_path_matches(&path, expected_path) {
         warn!(
             "WebSocket path mismatch: expected '{}', got '{}'",
             expected_path, path
@@ -93,6 +141,18 @@ async fn process_ws_handshake(
         return Err(anyhow!("Invalid WebSocket path: {}", path));
     }
 
+    // 配置了 Host 时校验请求头，拒绝直连 IP 的扫描请求
+    if let Some(expected_host) = expected_host {
+        let host = extract_header_value(&header_buf, "Host").unwrap_or_default();
+        if !host_matches(&host, expected_host) {
+            warn!(
+                "WebSocket host mismatch: expected '{}', got '{}'",
+                expected_host, host
+            );
+            return Err(anyhow!("Invalid WebSocket host: {}", host));
+        }
+    }
+
     if let Some(error) = validate_http_headers(&header_buf) {
         return Err(anyhow!("{}", error));
     }
@@ -105,13 +165,24 @@ async fn process_ws_handshake(
     sha1.update(WEBSOCKET_GUID.as_bytes());
     let accept_key = BASE64.encode(sha1.digest().bytes());
 
+    // 早期数据：首段载荷随升级请求发送，省去一次往返；需原样回显该请求头
+    let protocol = extract_header_value(&header_buf, "Sec-WebSocket-Protocol");
+    let early_data = protocol
+        .as_deref()
+        .and_then(|value| decode_early_data(value, &path));
+    let protocol_line = match (&protocol, &early_data) {
+        (Some(protocol), Some(_)) => format!("Sec-WebSocket-Protocol: {}\r\n", protocol),
+        _ => String::new(),
+    };
+
     let response = format!(
         "HTTP/1.1 101 Switching Protocols\r\n\
         Upgrade: websocket\r\n\
         Connection: Upgrade\r\n\
         Sec-WebSocket-Accept: {}\r\n\
+        {}\
         \r\n",
-        accept_key
+        accept_key, protocol_line
     );
 
     stream.write_all(response.as_bytes()).await?;
@@ -125,21 +196,37 @@ async fn process_ws_handshake(
 
     info!("WebSocket handshake completed for path: {}", path);
 
-    Ok(ws_stream)
+    let early_len = early_data.as_ref().map_or(0, |d| d.len());
+    let overhead = ((header_buf.len() - early_len) as u64, response.len() as u64);
+    Ok((ws_stream, early_data, overhead))
 }
 
 /// 处理 WebSocket 升级请求（已确认是 WS 升级，直接握手）
+///
+/// 返回 WebSocket 流、首条消息，以及握手与首帧的（上行，下行）开销字节数
 pub async fn handle_ws_upgrade(
     stream: TcpStream,
     ws_path: &str,
+    ws_host: Option<&str>,
     header_buffer_size: usize,
-) -> Result<(tokio_tungstenite::WebSocketStream<TcpStream>, Bytes)> {
+) -> Result<(
+    tokio_tungstenite::WebSocketStream<TcpStream>,
+    Bytes,
+    (u64, u64),
+)> {
     // detect_ws_connection 已验证是 WS 升级请求，直接握手，无需再 peek
-    let mut ws_stream = process_ws_handshake(stream, ws_path, header_buffer_size).await?;
+    let (mut ws_stream, early_data, (mut overhead_up, overhead_down)) =
+        process_ws_handshake(stream, ws_path, ws_host, header_buffer_size).await?;
+
+    if let Some(data) = early_data {
+        debug!("Received WebSocket early data: {} bytes", data.len());
+        return Ok((ws_stream, data, (overhead_up, overhead_down)));
+    }
 
     let first_message = match ws_stream.next().await {
         Some(Ok(Message::Binary(data))) => {
             debug!("Received first WebSocket message: {} bytes", data.len());
+            overhead_up += ws_frame_header_len(data.len(), true);
             Bytes::from(data)
         }
         Some(Ok(Message::Text(text))) => match BASE64.decode(&text) {
@@ -148,6 +235,9 @@ pub async fn handle_ws_upgrade(
                     "Received first WebSocket message (Base64): {} bytes",
                     data.len()
                 );
+                // Base64 膨胀部分同样计为开销
+                overhead_up +=
+                    ws_frame_header_len(text.len(), true) + (text.len() - data.len()) as u64;
                 Bytes::from(data)
             }
             Err(_) => return Err(anyhow!("First WebSocket message must be binary")),
@@ -160,24 +250,44 @@ pub async fn handle_ws_upgrade(
         }
     };
 
-    Ok((ws_stream, first_message))
+    Ok((ws_stream, first_message, (overhead_up, overhead_down)))
 }
 
 /// WebSocket 连接处理结果
 #[allow(clippy::large_enum_variant)]
 pub enum WsConnectionResult {
-    /// WebSocket 升级成功，返回流和首条消息
-    UpgradeSuccess(tokio_tungstenite::WebSocketStream<TcpStream>, Bytes),
+    /// WebSocket 升级成功，返回流、首条消息与握手开销（上行，下行）
+    UpgradeSuccess(
+        tokio_tungstenite::WebSocketStream<TcpStream>,
+        Bytes,
+        (u64, u64),
+    ),
     /// 普通 HTTP 请求，返回流和已读取的数据
     HttpRequest(TcpStream, Bytes),
 }
 
+/// 发送带状态码与原因的关闭帧后关闭连接（不等待对端确认）
+pub async fn close_with_reason(
+    ws_stream: &mut tokio_tungstenite::WebSocketStream<TcpStream>,
+    code: CloseCode,
+    reason: String,
+) {
+    let frame = CloseFrame {
+        code,
+        reason: reason.into(),
+    };
+    if let Err(e) = ws_stream.send(Message::Close(Some(frame))).await {
+        debug!("Failed to send WebSocket close frame: {}", e);
+    }
+}
+
 /// 检测并处理 WebSocket 连接
 ///
 /// 返回连接类型，由调用者决定后续处理
 pub async fn detect_ws_connection(
     stream: TcpStream,
     ws_path: &str,
+    ws_host: Option<&str>,
     performance_config: PerformanceConfig,
 ) -> Result<WsConnectionResult> {
     use crate::http::is_http_request;
@@ -201,15 +311,26 @@ pub async fn detect_ws_connection(
     // 检测是否是 HTTP 请求
     if is_http_request(&peek_buf[..n]) {
         // 检测是否是 WebSocket 升级请求
-        if is_websocket_upgrade(&peek_buf[..n]) {
+        // 路径不匹配的升级请求交给 HTTP 处理（反向代理路由 / 伪装站点）
+        let path_matches =
+            extract_http_path(&peek_buf[..n]).is_some_and(|p| ws_path_matches(&p, ws_path));
+        if path_matches && is_websocket_upgrade(&peek_buf[..n]) {
             debug!("WebSocket upgrade request detected");
-            let (ws_stream, first_message) =
-                handle_ws_upgrade(stream, ws_path, performance_config.ws_header_buffer_size)
-                    .await?;
-            return Ok(WsConnectionResult::UpgradeSuccess(ws_stream, first_message));
+            let (ws_stream, first_message, overhead) = handle_ws_upgrade(
+                stream,
+                ws_path,
+                ws_host,
+                performance_config.ws_header_buffer_size,
+            )
+            .await?;
+            return Ok(WsConnectionResult::UpgradeSuccess(
+                ws_stream,
+                first_message,
+                overhead,
+            ));
         } else {
             // 普通 HTTP 请求：使用栈上固定缓冲区，避免堆分配
-            debug!("Plain HTTP request detected (not WS upgrade)");
+            debug!("Plain HTTP request detected (not WS upgrade on configured path)");
             let mut stream = stream;
             let mut http_buf = [0u8; 8192];
             let read_n = stream.read(&mut http_buf).await?;
@@ -225,32 +346,71 @@ pub async fn detect_ws_connection(
 }
 
 /// 处理已验证的 WebSocket VLESS 连接
+///
+/// `handshake_overhead` 为握手阶段的（上行，下行）开销字节数，与 VLESS 头一并计入统计
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_ws_vless(
     ws_stream: tokio_tungstenite::WebSocketStream<TcpStream>,
     first_message: Bytes,
+    handshake_overhead: (u64, u64),
     users: &std::collections::HashSet<uuid::Uuid>,
     get_user_email: impl Fn(&uuid::Uuid) -> Option<Arc<str>>,
     performance_config: PerformanceConfig,
     client_addr: SocketAddr,
+    services: &SessionServices,
 ) -> Result<()> {
     // 解析 VLESS 请求
-    let (request, remaining_data) = VlessRequest::decode(first_message)?;
+    let message_len = first_message.len();
+    let (mut request, remaining_data) = VlessRequest::decode(first_message)?;
+    let request_header_len = (message_len - remaining_data.len()) as u64;
 
     debug!("Parsed VLESS request from WS: {:?}", request);
 
     // 验证用户 UUID
-    authenticate_request(&request, users, client_addr)?;
+    if let Err(e) = authenticate_request(&request, users, client_addr) {
+        services.events.publish(Event::AuthFailed {
+            client_addr,
+            uuid: request.uuid,
+        });
+        return Err(e);
+    }
     info!(
         "Authenticated user {} from {} (WS)",
         request.uuid, client_addr
     );
 
+    services.sniff_destination(&mut request, &remaining_data);
+    let allowed = ensure_allowed(services.blocklist.as_deref(), &request);
+    if allowed.is_ok() {
+        services.apply_domain_strategy(&mut request).await?;
+    }
+    let route = services.route(&request);
+    if let Err(e) = allowed
+        .and_then(|_| services.ensure_routable(&request, &route))
+        .and_then(|_| services.ensure_destination(&request))
+    {
+        info!("{} (user {})", e, request.uuid);
+        services.record_blocked(&route);
+        services.events.publish(Event::DestinationBlocked {
+            user: request.uuid.to_string(),
+            dest: format_destination(&request.address, request.port),
+        });
+        return Ok(());
+    }
+
     let response = VlessResponse::new_with_version(request.version);
     let (mut ws_sender, ws_receiver) = ws_stream.split();
 
     ws_sender.send_response(&response).await?;
+    let response_len = response.encoded_len();
+    services.overhead.record_overhead(
+        Transport::Ws,
+        handshake_overhead.0 + request_header_len,
+        handshake_overhead.1 + ws_frame_header_len(response_len, false) + response_len as u64,
+    );
 
     let user_email = get_user_email(&request.uuid);
+    let _connection = services.connection_opened(client_addr, &request, user_email.as_ref());
 
     // 根据命令类型分发处理
     match request.command {
@@ -259,8 +419,10 @@ pub async fn handle_ws_vless(
                 ws_sender,
                 ws_receiver,
                 request,
+                route,
                 remaining_data,
                 performance_config,
+                services,
                 user_email,
                 client_addr,
             )
@@ -278,13 +440,16 @@ pub async fn handle_ws_vless(
 }
 
 /// 处理 WebSocket 代理连接
+#[allow(clippy::too_many_arguments)]
 pub async fn handle_ws_proxy(
     mut ws_sender: SplitSink<tokio_tungstenite::WebSocketStream<TcpStream>, Message>,
     mut ws_receiver: SplitStream<tokio_tungstenite::WebSocketStream<TcpStream>>,
     request: VlessRequest,
+    route: RouteMatch,
     initial_data: Bytes,
     perf_config: PerformanceConfig,
-    _user_email: Option<Arc<str>>,
+    services: &SessionServices,
+    user_email: Option<Arc<str>>,
     client_addr: SocketAddr,
 ) -> Result<()> {
     info!(
@@ -292,8 +457,15 @@ pub async fn handle_ws_proxy(
         request.uuid, client_addr
     );
 
-    let mut target_stream = connect_target(&request.address, request.port, &perf_config).await?;
+    let started = Instant::now();
+    let dest = format_destination(&request.address, request.port);
+    let connected = services
+        .connect_request(&request, &route, &perf_config)
+        .await;
+    services.record_connect(&dest, &connected);
+    let (mut target_stream, timing) = connected?;
     let target_addr = target_stream.peer_addr()?;
+    let connected_at = Instant::now();
 
     debug!("Connected to target: {}", target_addr);
 
@@ -306,22 +478,59 @@ pub async fn handle_ws_proxy(
         client_addr, target_addr
     );
 
-    let (mut target_read, mut target_write) = target_stream.into_split();
+    let mut record = SessionRecord::new(
+        request.uuid,
+        user_label(&request.uuid, user_email.as_ref()),
+        "tcp",
+        dest,
+        &timing,
+        started,
+    )
+    .with_route(services.session_route(&request, &route));
+    record.bytes_up = initial_data.len() as u64;
+
+    let capture = services.open_capture(&record);
+    if let Some(ref capture) = capture {
+        capture.record(Direction::Up, &initial_data);
+    }
+    let up_capture = capture.clone();
+
+    let (activity, _stall) = services.watch_stall(&record, &perf_config);
+    let up_activity = Arc::clone(&activity);
+    let (target_read, mut target_write) = target_stream.into_split();
+    let mut target_read = ActivityReader::new(target_read, activity);
 
+    // 帧头开销按每条消息一帧估算（客户端帧带掩码）
     let ws_to_target = tokio::spawn(async move {
+        let mut bytes_up = 0u64;
+        let mut overhead_up = 0u64;
         loop {
             match ws_receiver.next().await {
                 Some(Ok(Message::Binary(data))) => {
-                    if target_write.write_all(&data).await.is_err() {
+                    up_activity.touch();
+                    overhead_up += ws_frame_header_len(data.len(), true);
+                    if let Some(ref capture) = up_capture {
+                        capture.record(Direction::Up, &data);
+                    }
+                    if let Err(e) = target_write.write_all(&data).await {
+                        debug!("Failed to write to target: {}", e);
                         break;
                     }
+                    bytes_up += data.len() as u64;
                 }
                 Some(Ok(Message::Text(text))) => {
                     match BASE64.decode(&text) {
                         Ok(data) => {
+                            up_activity.touch();
+                            overhead_up += ws_frame_header_len(text.len(), true)
+                                + (text.len() - data.len()) as u64;
+                            if let Some(ref capture) = up_capture {
+                                capture.record(Direction::Up, &data);
+                            }
                             if target_write.write_all(&data).await.is_err() {
                                 break;
                             }
+                            bytes_up += data.len() as u64;
                         }
                         Err(_) => {
                             warn!("Received non-binary Text message that is not valid Base64, skipping");
@@ -336,38 +545,105 @@ pub async fn handle_ws_proxy(
                     warn!("WebSocket error: {}", e);
                     break;
                 }
-                _ => {}
+                Some(Ok(message)) => {
+                    // Ping / Pong 等控制帧整体计为开销
+                    overhead_up += ws_frame_header_len(message.len(), true) + message.len() as u64;
+                }
             }
         }
         debug!("WebSocket receive loop ended");
+        up_activity.close();
         let _ = target_write.shutdown().await;
+        (bytes_up, overhead_up)
     });
 
+    // 空闲保活：CDN（如 Cloudflare）会断开约 100 秒无数据的 WebSocket
+    let ping_interval = perf_config.ws_ping_interval;
+    // 小包合并：累积到阈值或首个未发送字节等待超时后再发一帧，0 表示逐次发送
+    let coalesce_delay = std::time::Duration::from_millis(perf_config.ws_coalesce_ms);
+
     let target_to_ws = tokio::spawn(async move {
         let mut buffer = vec![0u8; 64 * 1024]; // 64KB，与 TCP 模式对齐
+        let coalesce_bytes = if coalesce_delay.is_zero() {
+            0
+        } else {
+            perf_config.ws_coalesce_bytes.clamp(1, buffer.len())
+        };
+        let period = std::time::Duration::from_secs(ping_interval.max(1));
+        let mut ping_timer = tokio::time::interval_at(tokio::time::Instant::now() + period, period);
+        let mut ttfb = None;
+        let mut bytes_down = 0u64;
+        let mut overhead_down = 0u64;
+        let mut pending = 0usize;
+        let mut flush_at: Option<tokio::time::Instant> = None;
 
         loop {
-            match target_read.read(&mut buffer).await {
-                Ok(0) => {
+            let read = tokio::select! {
+                read = target_read.read(&mut buffer[pending..]) => Some(read),
+                _ = tokio::time::sleep_until(flush_at.unwrap_or_else(tokio::time::Instant::now)),
+                    if flush_at.is_some() => None,
+                _ = ping_timer.tick(), if ping_interval > 0 => {
+                    if ws_sender.send(Message::Ping(Vec::new())).await.is_err() {
+                        break;
+                    }
+                    overhead_down += ws_frame_header_len(0, false);
+                    continue;
+                }
+            };
+
+            let closed = match read {
+                Some(Ok(0)) => {
                     debug!("Target connection closed");
-                    break;
+                    true
                 }
-                Ok(n) => {
-                    // 使用 Bytes::copy_from_slice 避免 to_vec() 的额外分配语义混淆；
-                    // 注意 tungstenite Message::Binary 接受 Vec<u8>，此处仍需一次拷贝，
-                    // 但语义更清晰，且 buffer 可继续复用
-                    let payload = buffer[..n].to_vec();
-                    if ws_sender.send(Message::Binary(payload)).await.is_err() {
-                        break;
+                Some(Ok(n)) => {
+                    ttfb.get_or_insert_with(|| connected_at.elapsed());
+                    if let Some(ref capture) = capture {
+                        capture.record(Direction::Down, &buffer[pending..pending + n]);
                     }
+                    pending += n;
+                    false
                 }
-                Err(_) => break,
+                Some(Err(_)) => true,
+                // 合并等待超时
+                None => false,
+            };
+
+            if pending > 0 && (closed || read.is_none() || pending >= coalesce_bytes) {
+                // tungstenite Message::Binary 接受 Vec<u8>，此处需一次拷贝，buffer 可继续复用
+                let payload = buffer[..pending].to_vec();
+                if ws_sender.send(Message::Binary(payload)).await.is_err() {
+                    break;
+                }
+                bytes_down += pending as u64;
+                overhead_down += ws_frame_header_len(pending, false);
+                pending = 0;
+                flush_at = None;
+                // 有数据下行时推迟下一次 Ping
+                ping_timer.reset();
+            } else if pending > 0 && flush_at.is_none() {
+                flush_at = Some(tokio::time::Instant::now() + coalesce_delay);
+            }
+            if closed {
+                break;
             }
         }
         let _ = ws_sender.send(Message::Close(None)).await;
+        (ttfb, bytes_down, overhead_down)
     });
 
-    let _ = tokio::join!(ws_to_target, target_to_ws);
+    let (up, down) = tokio::join!(ws_to_target, target_to_ws);
+    let (bytes_up, overhead_up) = up.unwrap_or((0, 0));
+    let (ttfb, bytes_down, overhead_down) = down.unwrap_or((None, 0, 0));
+    record.bytes_up += bytes_up;
+    record.bytes_down = bytes_down;
+    record.set_ttfb(ttfb);
+    services.overhead.record_session(
+        Transport::Ws,
+        (record.bytes_up, record.bytes_down),
+        (overhead_up, overhead_down),
+    );
+    services.finish_session(&mut record);
 
     debug!("WebSocket proxy session closed");
     Ok(())
@@ -0,0 +1,426 @@
+//! Xray / v2ray 迁移导入模块
+//!
+//! `vless import-xray` 读取 Xray 服务端配置中的 VLESS 入站，生成本服务的配置（监听地址、传输、用户与回落），
+//! 并可读取 `xray api statsquery` 导出的统计，把按邮箱记录的上下行流量写入 `monitoring.state_file`，
+//! 启动时恢复到对应用户。本服务没有流量配额与有效期，Xray 侧的这类设置不导入
+
+use crate::atomic_write::atomic_write_file_with_perms;
+use crate::config::Config;
+use crate::stats::{SavedStats, SavedUser};
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::collections::{HashMap, HashSet};
+use std::path::{Path, PathBuf};
+use uuid::Uuid;
+
+/// 未指定时统计状态文件的文件名（与生成的配置同目录）
+const DEFAULT_STATE_FILE: &str = "stats-state.json";
+
+/// Xray 允许的非 UUID 客户端 ID 最大长度
+const MAX_CUSTOM_ID_LEN: usize = 30;
+
+/// 配置导入结果
+#[derive(Debug)]
+pub struct ImportedConfig {
+    pub config: Config,
+    /// 导入的 VLESS 入站数
+    pub inbounds: usize,
+    /// 无法等价转换而忽略的设置
+    pub warnings: Vec<String>,
+}
+
+/// 统计导入结果
+#[derive(Debug)]
+pub struct ImportedStats {
+    pub stats: SavedStats,
+    pub warnings: Vec<String>,
+}
+
+/// `import-xray` 子命令参数
+#[derive(Debug, Default)]
+pub struct ImportOptions {
+    /// Xray 配置文件
+    pub xray_config: String,
+    /// `xray api statsquery` 导出的 JSON
+    pub stats: Option<String>,
+    /// 生成的配置文件
+    pub output: String,
+    /// 统计状态文件，未指定时为配置同目录下的 `stats-state.json`
+    pub state_file: Option<String>,
+    /// 覆盖已存在的文件
+    pub force: bool,
+}
+
+/// `import-xray` 子命令结果
+#[derive(Debug)]
+pub struct ImportReport {
+    pub users: usize,
+    pub inbounds: usize,
+    /// 写入统计的用户数与状态文件路径
+    pub stats: Option<(usize, String)>,
+    pub warnings: Vec<String>,
+}
+
+/// 把 Xray 配置转换为本服务配置
+///
+/// 第一个 VLESS 入站决定监听地址与传输，所有 VLESS 入站的客户端合并为用户（按 UUID 去重）
+pub fn import_config(content: &str) -> Result<ImportedConfig> {
+    let root: Value =
+        serde_json::from_str(content).map_err(|e| anyhow!("Invalid Xray config: {}", e))?;
+    let inbounds = root
+        .get("inbounds")
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow!("Xray config has no inbounds"))?;
+
+    let mut warnings = Vec::new();
+    let mut vless = Vec::new();
+    for (index, inbound) in inbounds.iter().enumerate() {
+        let name = match inbound.get("tag").and_then(Value::as_str) {
+            Some(tag) => format!("inbound '{}'", tag),
+            None => format!("inbounds[{}]", index),
+        };
+        match inbound.get("protocol").and_then(Value::as_str) {
+            Some("vless") => vless.push((name, inbound)),
+            protocol => warnings.push(format!(
+                "Skipping {} (protocol {})",
+                name,
+                protocol.unwrap_or("unknown")
+            )),
+        }
+    }
+    let Some((first_name, first)) = vless.first() else {
+        return Err(anyhow!("No VLESS inbound found in Xray config"));
+    };
+    if vless.len() > 1 {
+        warnings.push(format!(
+            "Listen address and transport are taken from {}; users of all {} VLESS inbounds are merged",
+            first_name,
+            vless.len()
+        ));
+    }
+
+    let server = server_settings(first_name, first, &mut warnings)?;
+    let fallbacks = fallbacks(first_name, first, &mut warnings);
+    let mut users = Vec::new();
+    let mut seen = HashSet::new();
+    for (name, inbound) in &vless {
+        let clients = inbound
+            .pointer("/settings/clients")
+            .and_then(Value::as_array)
+            .map(Vec::as_slice)
+            .unwrap_or_default();
+        let mut flows = HashSet::new();
+        for client in clients {
+            let id = client
+                .get("id")
+                .and_then(Value::as_str)
+                .ok_or_else(|| anyhow!("{}: client without id", name))?;
+            let uuid =
+                client_uuid(id).ok_or_else(|| anyhow!("{}: invalid client id '{}'", name, id))?;
+            let email = client
+                .get("email")
+                .and_then(Value::as_str)
+                .filter(|e| !e.is_empty());
+            if Uuid::parse_str(id).is_err() {
+                warnings.push(format!(
+                    "{}: client id '{}' mapped to UUID {}",
+                    name, id, uuid
+                ));
+            }
+            if let Some(flow) = client.get("flow").and_then(Value::as_str) {
+                if !flow.is_empty() && flows.insert(flow) {
+                    warnings.push(format!(
+                        "{}: flow '{}' is not supported, remove it from client links",
+                        name, flow
+                    ));
+                }
+            }
+            if !seen.insert(uuid) {
+                warnings.push(format!("{}: duplicate client {} skipped", name, uuid));
+                continue;
+            }
+            users.push(json!({ "uuid": uuid.to_string(), "email": email }));
+        }
+    }
+    if users.is_empty() {
+        return Err(anyhow!("No VLESS clients found in Xray config"));
+    }
+
+    let value = json!({ "server": server, "users": users, "fallbacks": fallbacks });
+    let config = Config::from_json(&value.to_string())?;
+    config.validate()?;
+    Ok(ImportedConfig {
+        config,
+        inbounds: vless.len(),
+        warnings,
+    })
+}
+
+/// 监听地址、端口与传输
+fn server_settings(name: &str, inbound: &Value, warnings: &mut Vec<String>) -> Result<Value> {
+    let listen = match inbound.get("listen").and_then(Value::as_str) {
+        Some(listen) if listen.starts_with('/') || listen.starts_with('@') => {
+            warnings.push(format!(
+                "{} listens on Unix socket {}, using 0.0.0.0",
+                name, listen
+            ));
+            "0.0.0.0"
+        }
+        Some(listen) if !listen.is_empty() => listen,
+        _ => "0.0.0.0",
+    };
+    let port = match inbound.get("port") {
+        Some(Value::Number(port)) => port.as_u64().and_then(|p| u16::try_from(p).ok()),
+        Some(Value::String(port)) => port.trim().parse::<u16>().ok(),
+        _ => None,
+    }
+    .filter(|&p| p != 0)
+    .ok_or_else(|| {
+        anyhow!(
+            "{}: unsupported port {}",
+            name,
+            inbound.get("port").unwrap_or(&Value::Null)
+        )
+    })?;
+
+    let stream = inbound.get("streamSettings").unwrap_or(&Value::Null);
+    let mut server = json!({ "listen": listen, "port": port });
+    match stream
+        .get("network")
+        .and_then(Value::as_str)
+        .unwrap_or("tcp")
+    {
+        "tcp" | "raw" => {
+            let header = ["/tcpSettings/header/type", "/rawSettings/header/type"]
+                .iter()
+                .find_map(|p| stream.pointer(p).and_then(Value::as_str));
+            if header.is_some_and(|h| h != "none") {
+                warnings.push(format!(
+                    "{}: TCP header obfuscation is not supported and was dropped",
+                    name
+                ));
+            }
+        }
+        "ws" => {
+            let ws = stream.get("wsSettings").unwrap_or(&Value::Null);
+            server["protocol"] = json!("ws");
+            server["ws_path"] = json!(ws.get("path").and_then(Value::as_str).unwrap_or("/"));
+            let host = ws
+                .get("host")
+                .or_else(|| ws.pointer("/headers/Host"))
+                .and_then(Value::as_str)
+                .filter(|h| !h.is_empty());
+            if let Some(host) = host {
+                server["ws_host"] = json!(host);
+            }
+        }
+        network => {
+            return Err(anyhow!(
+                "{}: transport '{}' is not supported (only tcp and ws)",
+                name,
+                network
+            ));
+        }
+    }
+    if let Some(security) = stream
+        .get("security")
+        .and_then(Value::as_str)
+        .filter(|s| *s != "none")
+    {
+        warnings.push(format!(
+            "{}: security '{}' is not supported, the imported server accepts plain connections (terminate TLS in front of it)",
+            name, security
+        ));
+    }
+    Ok(server)
+}
+
+/// VLESS 回落：端口与 `host:port` 目标可直接使用，Unix socket 与 PROXY protocol 不支持
+fn fallbacks(name: &str, inbound: &Value, warnings: &mut Vec<String>) -> Vec<Value> {
+    let entries = inbound
+        .pointer("/settings/fallbacks")
+        .and_then(Value::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let mut rules = Vec::new();
+    for entry in entries {
+        let dest = match entry.get("dest") {
+            Some(Value::Number(port)) => Some(format!("127.0.0.1:{}", port)),
+            Some(Value::String(dest)) if dest.parse::<u16>().is_ok() => {
+                Some(format!("127.0.0.1:{}", dest))
+            }
+            Some(Value::String(dest)) if dest.contains(':') && !dest.starts_with('/') => {
+                Some(dest.clone())
+            }
+            _ => None,
+        };
+        let Some(dest) = dest else {
+            warnings.push(format!(
+                "{}: fallback to {} skipped (only TCP destinations are supported)",
+                name,
+                entry.get("dest").unwrap_or(&Value::Null)
+            ));
+            continue;
+        };
+        if entry.get("xver").and_then(Value::as_u64).unwrap_or(0) > 0 {
+            warnings.push(format!(
+                "{}: PROXY protocol (xver) for fallback {} is not supported",
+                name, dest
+            ));
+        }
+        if entry.get("name").is_some() || entry.get("alpn").is_some() {
+            warnings.push(format!(
+                "{}: SNI / ALPN conditions of fallback {} were dropped",
+                name, dest
+            ));
+        }
+        let path = entry
+            .get("path")
+            .and_then(Value::as_str)
+            .filter(|p| !p.is_empty());
+        rules.push(json!({ "dest": dest, "path": path }));
+    }
+    rules
+}
+
+/// 解析客户端 ID：标准 UUID，或按 Xray 规则把 1–30 字节的字符串映射为 UUIDv5（命名空间为全零 UUID）
+pub fn client_uuid(id: &str) -> Option<Uuid> {
+    if let Ok(uuid) = Uuid::parse_str(id) {
+        return Some(uuid);
+    }
+    if id.is_empty() || id.len() > MAX_CUSTOM_ID_LEN {
+        return None;
+    }
+    let mut hasher = sha1_smol::Sha1::new();
+    hasher.update(Uuid::nil().as_bytes());
+    hasher.update(id.as_bytes());
+    let digest = hasher.digest().bytes();
+    let mut bytes = [0u8; 16];
+    bytes.copy_from_slice(&digest[..16]);
+    bytes[6] = (bytes[6] & 0x0f) | 0x50;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    Some(Uuid::from_bytes(bytes))
+}
+
+/// 读取 `xray api statsquery` 的 JSON 输出，按邮箱把 `user>>>邮箱>>>traffic>>>uplink/downlink` 计数对应到用户
+pub fn import_stats(content: &str, config: &Config) -> Result<ImportedStats> {
+    let root: Value =
+        serde_json::from_str(content).map_err(|e| anyhow!("Invalid Xray stats export: {}", e))?;
+    let entries = root
+        .get("stat")
+        .or(Some(&root))
+        .and_then(Value::as_array)
+        .ok_or_else(|| anyhow!("Xray stats export has no \"stat\" array"))?;
+
+    // Xray 的统计名称中邮箱不区分大小写
+    let mut by_email = HashMap::new();
+    for user in &config.users {
+        if let (Some(email), Ok(uuid)) = (&user.email, Uuid::parse_str(&user.uuid)) {
+            by_email.insert(email.to_lowercase(), uuid);
+        }
+    }
+
+    let mut warnings = Vec::new();
+    let mut traffic: HashMap<Uuid, (u64, u64)> = HashMap::new();
+    let mut unknown = HashSet::new();
+    for entry in entries {
+        let Some(name) = entry.get("name").and_then(Value::as_str) else {
+            continue;
+        };
+        let parts: Vec<&str> = name.split(">>>").collect();
+        let ["user", email, "traffic", direction] = parts[..] else {
+            continue;
+        };
+        let value = match entry.get("value") {
+            Some(Value::Number(n)) => n.as_u64(),
+            Some(Value::String(s)) => s.parse().ok(),
+            None | Some(Value::Null) => Some(0),
+            _ => None,
+        }
+        .ok_or_else(|| anyhow!("Invalid value for stat {}", name))?;
+        let Some(uuid) = by_email.get(&email.to_lowercase()) else {
+            if unknown.insert(email.to_string()) {
+                warnings.push(format!("Traffic of unknown user '{}' skipped", email));
+            }
+            continue;
+        };
+        let counters = traffic.entry(*uuid).or_default();
+        match direction {
+            "uplink" => counters.0 = counters.0.saturating_add(value),
+            "downlink" => counters.1 = counters.1.saturating_add(value),
+            _ => {}
+        }
+    }
+    let without_email = config.users.iter().filter(|u| u.email.is_none()).count();
+    if without_email > 0 {
+        warnings.push(format!(
+            "{} users have no email and cannot be matched to traffic stats",
+            without_email
+        ));
+    }
+
+    let mut users: Vec<SavedUser> = traffic
+        .into_iter()
+        .map(|(uuid, (bytes_up, bytes_down))| SavedUser {
+            uuid,
+            bytes_up,
+            bytes_down,
+            connections: 0,
+            last_seen: None,
+        })
+        .collect();
+    users.sort_by_key(|u| u.uuid);
+    Ok(ImportedStats {
+        stats: SavedStats::new(users),
+        warnings,
+    })
+}
+
+/// `import-xray` 子命令：生成配置文件（与统计状态文件），不覆盖已存在的文件，除非指定 `force`
+pub fn import_files(options: &ImportOptions) -> Result<ImportReport> {
+    let read = |path: &str| {
+        std::fs::read_to_string(path).map_err(|e| anyhow!("Failed to read {}: {}", path, e))
+    };
+    let ensure_new = |path: &Path| {
+        if path.exists() && !options.force {
+            return Err(anyhow!(
+                "{} already exists (use --force to overwrite)",
+                path.display()
+            ));
+        }
+        Ok(())
+    };
+
+    let imported = import_config(&read(&options.xray_config)?)?;
+    let mut config = imported.config;
+    let mut warnings = imported.warnings;
+    let output = Path::new(&options.output);
+    ensure_new(output)?;
+
+    let mut stats = None;
+    if let Some(ref stats_path) = options.stats {
+        let seeded = import_stats(&read(stats_path)?, &config)?;
+        warnings.extend(seeded.warnings);
+        let state_file = match options.state_file {
+            Some(ref path) => PathBuf::from(path),
+            None => output
+                .parent()
+                .unwrap_or(Path::new(""))
+                .join(DEFAULT_STATE_FILE),
+        };
+        ensure_new(&state_file)?;
+        let content = serde_json::to_string(&seeded.stats)?;
+        atomic_write_file_with_perms(&state_file, &content, 0o600)?;
+        let state_file = state_file.display().to_string();
+        config.monitoring.state_file = Some(state_file.clone());
+        stats = Some((seeded.stats.users.len(), state_file));
+    }
+
+    atomic_write_file_with_perms(output, &config.to_json()?, 0o600)?;
+    Ok(ImportReport {
+        users: config.users.len(),
+        inbounds: imported.inbounds,
+        stats,
+        warnings,
+    })
+}
@@ -0,0 +1,165 @@
+//! 会话计费事件测试
+
+use std::time::{Duration, Instant};
+use tempfile::TempDir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use uuid::Uuid;
+use vless_rust::accounting::{append_line, Accounting};
+use vless_rust::address::ConnectTiming;
+use vless_rust::config::{AccountingConfig, Config};
+use vless_rust::events::{Event, EventBus};
+use vless_rust::session::{SessionRecord, SessionServices};
+
+/// 通过会话服务结束一个会话（发布会话关闭事件）
+fn close_session(events: &EventBus, user: &str) {
+    let mut record = SessionRecord::new(
+        Uuid::nil(),
+        user.to_string(),
+        "tcp",
+        "example.com:443".to_string(),
+        &ConnectTiming::default(),
+        Instant::now(),
+    );
+    record.bytes_up = 100;
+    record.bytes_down = 2000;
+    let services = SessionServices {
+        events: events.clone(),
+        ..Default::default()
+    };
+    services.finish_session(&mut record);
+}
+
+/// 等待文件出现指定行数
+async fn wait_for_lines(path: &std::path::Path, count: usize) -> Vec<String> {
+    for _ in 0..50 {
+        if let Ok(text) = tokio::fs::read_to_string(path).await {
+            let lines: Vec<String> = text.lines().map(String::from).collect();
+            if lines.len() >= count {
+                return lines;
+            }
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("spool file did not receive {} lines", count);
+}
+
+// ============================================================================
+// 配置
+// ============================================================================
+
+#[test]
+fn test_accounting_config_parse() {
+    let config = Config::from_json(
+        r#"{
+            "server": {"listen": "0.0.0.0", "port": 443},
+            "users": [],
+            "accounting": {
+                "webhook_url": "https://billing.example.com/events",
+                "webhook_token": "t0ken",
+                "spool_file": "/var/lib/vless/sessions.jsonl"
+            }
+        }"#,
+    )
+    .unwrap();
+    assert!(config.accounting.is_enabled());
+    assert_eq!(config.accounting.webhook_token.as_deref(), Some("t0ken"));
+
+    assert!(!AccountingConfig::default().is_enabled());
+}
+
+// ============================================================================
+// 投递
+// ============================================================================
+
+#[tokio::test]
+async fn test_append_line() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("spool.jsonl");
+    append_line(&path, "{\"a\":1}").await.unwrap();
+    append_line(&path, "{\"a\":2}").await.unwrap();
+    let text = tokio::fs::read_to_string(&path).await.unwrap();
+    assert_eq!(text, "{\"a\":1}\n{\"a\":2}\n");
+}
+
+#[tokio::test]
+async fn test_spool_receives_session_events() {
+    let dir = TempDir::new().unwrap();
+    let path = dir.path().join("sessions.jsonl");
+    let events = EventBus::new();
+    let accounting = Accounting::spawn(
+        &AccountingConfig {
+            spool_file: Some(path.to_string_lossy().into_owned()),
+            ..Default::default()
+        },
+        events.subscribe(),
+    )
+    .unwrap();
+
+    // 非会话关闭事件应被忽略
+    events.publish(Event::DestinationBlocked {
+        user: "alice@example.com".to_string(),
+        dest: "ads.example:443".to_string(),
+    });
+    close_session(&events, "alice@example.com");
+    close_session(&events, "bob@example.com");
+
+    let lines = wait_for_lines(&path, 2).await;
+    assert_eq!(lines.len(), 2);
+    let first: serde_json::Value = serde_json::from_str(&lines[0]).unwrap();
+    assert_eq!(first["user"], "alice@example.com");
+    assert_eq!(first["dest"], "example.com:443");
+    assert_eq!(first["bytes_up"], 100);
+    assert_eq!(first["bytes_down"], 2000);
+    assert!(first["closed_at"].as_u64().unwrap() > 0);
+    assert_eq!(accounting.dropped_count(), 0);
+}
+
+#[tokio::test]
+async fn test_webhook_receives_session_event() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0u8; 4096];
+        // 读到请求体的结束括号为止
+        while !request.ends_with(b"}") {
+            let n = stream.read(&mut buf).await.unwrap();
+            if n == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..n]);
+        }
+        stream
+            .write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        String::from_utf8(request).unwrap()
+    });
+
+    let events = EventBus::new();
+    let _accounting = Accounting::spawn(
+        &AccountingConfig {
+            webhook_url: Some(format!("http://{}/events", addr)),
+            webhook_token: Some("t0ken".to_string()),
+            ..Default::default()
+        },
+        events.subscribe(),
+    )
+    .unwrap();
+    close_session(&events, "alice@example.com");
+
+    let request = tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(request.starts_with("POST /events HTTP/1.1"));
+    assert!(request
+        .to_ascii_lowercase()
+        .contains("authorization: bearer t0ken"));
+    let body = request.split("\r\n\r\n").nth(1).unwrap();
+    let event: serde_json::Value = serde_json::from_str(body).unwrap();
+    assert_eq!(event["user"], "alice@example.com");
+    assert_eq!(event["network"], "tcp");
+}
@@ -0,0 +1,276 @@
+//! 拦截列表模块测试
+
+use bytes::Bytes;
+use std::collections::HashSet;
+use std::net::Ipv4Addr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use uuid::Uuid;
+use vless_rust::blocklist::{
+    ensure_allowed, parse_blocklist, spawn_refresh_task, Blocklist, MAX_LIST_SIZE,
+};
+use vless_rust::config::{BlocklistConfig, Config, DnsConfig};
+use vless_rust::dns::{DnsAction, DnsInterceptor, TYPE_A};
+use vless_rust::protocol::{Address, Command, VlessRequest};
+
+fn config_with_domains(domains: &[&str]) -> BlocklistConfig {
+    BlocklistConfig {
+        domains: domains.iter().map(|d| d.to_string()).collect(),
+        ..Default::default()
+    }
+}
+
+fn request(uuid: Uuid, address: Address) -> VlessRequest {
+    VlessRequest {
+        version: 0,
+        uuid,
+        addons_length: 0,
+        addons: Bytes::new(),
+        command: Command::Tcp,
+        port: 443,
+        address,
+    }
+}
+
+/// 构建一个简单的 DNS 查询报文
+fn build_query(name: &str) -> Vec<u8> {
+    let mut packet = vec![0x12, 0x34, 0x01, 0x00, 0, 1, 0, 0, 0, 0, 0, 0];
+    for label in name.split('.') {
+        packet.push(label.len() as u8);
+        packet.extend_from_slice(label.as_bytes());
+    }
+    packet.push(0);
+    packet.extend_from_slice(&TYPE_A.to_be_bytes());
+    packet.extend_from_slice(&1u16.to_be_bytes());
+    packet
+}
+
+// ============================================================================
+// 列表解析
+// ============================================================================
+
+#[test]
+fn test_parse_hosts_format() {
+    let content = "# comment\n127.0.0.1 localhost\n0.0.0.0 ads.example.com\n0.0.0.0 Tracker.Example.NET # inline\n";
+    let domains = parse_blocklist(content);
+    assert_eq!(domains.len(), 2);
+    assert!(domains.contains("ads.example.com"));
+    assert!(domains.contains("tracker.example.net"));
+}
+
+#[test]
+fn test_parse_domain_list_and_adblock_rules() {
+    let content =
+        "! title\nads.example.com\n||metrics.example.org^\n||example.org/path^\nnot a domain\n";
+    let domains = parse_blocklist(content);
+    let expected: HashSet<String> = ["ads.example.com", "metrics.example.org"]
+        .iter()
+        .map(|s| s.to_string())
+        .collect();
+    assert_eq!(domains, expected);
+}
+
+// ============================================================================
+// 匹配与计数
+// ============================================================================
+
+#[test]
+fn test_contains_matches_subdomains() {
+    let list = Blocklist::new(&config_with_domains(&["ads.example.com"]), HashSet::new());
+    assert!(list.contains("ads.example.com"));
+    assert!(list.contains("cdn.ads.example.com."));
+    assert!(!list.contains("example.com"));
+    assert!(!list.contains("badads.example.com"));
+}
+
+#[test]
+fn test_update_source_merges_with_static_domains() {
+    let list = Blocklist::new(&config_with_domains(&["a.com"]), HashSet::new());
+    list.update_source("https://list", parse_blocklist("b.com\n"));
+    assert_eq!(list.domain_count(), 2);
+
+    // 同一地址再次更新时替换旧内容
+    list.update_source("https://list", parse_blocklist("c.com\n"));
+    assert!(list.contains("a.com"));
+    assert!(!list.contains("b.com"));
+    assert!(list.contains("c.com"));
+}
+
+#[test]
+fn test_version_increments_on_update() {
+    let list = Blocklist::new(&config_with_domains(&["a.com"]), HashSet::new());
+    let initial = list.version_info();
+    assert_eq!(initial.version, 1);
+    assert_eq!(initial.domains, 1);
+    assert_eq!(initial.sources, 0);
+    assert!(initial.updated_at > 0);
+
+    list.update_source("https://list", parse_blocklist("b.com\nc.com\n"));
+    let updated = list.version_info();
+    assert_eq!(updated.version, 2);
+    assert_eq!(updated.domains, 3);
+    assert_eq!(updated.sources, 1);
+}
+
+#[tokio::test]
+async fn test_reload_request_refreshes_immediately() {
+    // 每次请求返回不同的列表内容
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hosts", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        for i in 0.. {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf).await.unwrap();
+            let body = format!("v{}.example\n", i);
+            let reply = format!(
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                body.len(),
+                body
+            );
+            stream.write_all(reply.as_bytes()).await.unwrap();
+        }
+    });
+
+    let config = BlocklistConfig {
+        urls: vec![url],
+        ..Default::default()
+    };
+    let list = Arc::new(Blocklist::new(&config, HashSet::new()));
+    spawn_refresh_task(Arc::clone(&list), &config);
+
+    wait_for(|| list.contains("v0.example")).await;
+    list.request_reload();
+    wait_for(|| list.contains("v1.example")).await;
+    assert!(!list.contains("v0.example"));
+    assert_eq!(list.version_info().version, 3);
+}
+
+#[tokio::test]
+async fn test_oversized_list_is_rejected() {
+    // 第一次声明超限的 Content-Length，第二次不声明长度、持续发送超过上限的数据
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}/hosts", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        for declared in [true, false] {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf).await.unwrap();
+            let line = b"big.example\n";
+            if declared {
+                let head = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Length: {}\r\nConnection: close\r\n\r\n",
+                    MAX_LIST_SIZE + 1
+                );
+                let _ = stream.write_all(head.as_bytes()).await;
+                let _ = stream.write_all(line).await;
+            } else {
+                let head = "HTTP/1.1 200 OK\r\nConnection: close\r\n\r\n";
+                let _ = stream.write_all(head.as_bytes()).await;
+                let chunk = line.repeat(64 * 1024 / line.len());
+                let mut sent = 0;
+                while sent <= MAX_LIST_SIZE {
+                    if stream.write_all(&chunk).await.is_err() {
+                        break;
+                    }
+                    sent += chunk.len();
+                }
+            }
+        }
+    });
+
+    let list = Blocklist::new(&BlocklistConfig::default(), HashSet::new());
+    list.refresh(std::slice::from_ref(&url)).await;
+    assert!(!list.contains("big.example"));
+    list.refresh(&[url]).await;
+    assert!(!list.contains("big.example"));
+    assert_eq!(list.domain_count(), 0);
+}
+
+/// 等待条件成立（最多 5 秒）
+async fn wait_for(condition: impl Fn() -> bool) {
+    for _ in 0..250 {
+        if condition() {
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    panic!("condition not met in time");
+}
+
+#[test]
+fn test_ensure_allowed_counts_and_respects_opt_out() {
+    let opted_out = Uuid::new_v4();
+    let list = Blocklist::new(
+        &config_with_domains(&["ads.example.com"]),
+        HashSet::from([opted_out]),
+    );
+    let domain = Address::Domain(Bytes::from_static(b"x.ads.example.com"));
+
+    assert!(ensure_allowed(Some(&list), &request(Uuid::new_v4(), domain.clone())).is_err());
+    assert!(ensure_allowed(Some(&list), &request(opted_out, domain)).is_ok());
+    assert!(ensure_allowed(
+        Some(&list),
+        &request(Uuid::new_v4(), Address::Ipv4(Ipv4Addr::LOCALHOST))
+    )
+    .is_ok());
+    assert!(ensure_allowed(
+        None,
+        &request(Uuid::new_v4(), Address::Ipv4(Ipv4Addr::LOCALHOST))
+    )
+    .is_ok());
+    assert_eq!(list.blocked_count(), 1);
+}
+
+#[test]
+fn test_dns_query_blocked_by_blocklist() {
+    let list = Blocklist::new(&config_with_domains(&["ads.example.com"]), HashSet::new());
+    let dns = DnsInterceptor::new(&DnsConfig {
+        enabled: true,
+        ..Default::default()
+    });
+    let query = build_query("ads.example.com");
+
+    assert_eq!(
+        dns.handle_query(&query, "8.8.8.8:53", None),
+        DnsAction::Forward
+    );
+    let DnsAction::Reply(reply) = dns.handle_query(&query, "8.8.8.8:53", Some(&list)) else {
+        panic!("expected NXDOMAIN reply");
+    };
+    assert_eq!(reply[3] & 0x0F, 3);
+    assert_eq!(list.blocked_count(), 1);
+}
+
+// ============================================================================
+// 配置
+// ============================================================================
+
+#[test]
+fn test_config_blocklist_section_and_user_opt_out() {
+    let json = r#"{
+        "server": {"listen": "0.0.0.0", "port": 443},
+        "users": [
+            {"uuid": "550e8400-e29b-41d4-a716-446655440000", "email": null},
+            {"uuid": "550e8400-e29b-41d4-a716-446655440001", "email": null, "blocklist": false}
+        ],
+        "blocklist": {"urls": ["https://example.com/hosts"]}
+    }"#;
+    let config = Config::from_json(json).unwrap();
+    assert!(config.blocklist.is_enabled());
+    assert_eq!(config.blocklist.refresh_interval, 86400);
+    assert!(config.users[0].blocklist);
+    assert!(!config.users[1].blocklist);
+
+    // 默认值不写回配置文件
+    let serialized = config.to_json().unwrap();
+    assert_eq!(serialized.matches("\"blocklist\": false").count(), 1);
+    assert!(
+        !Config::from_json(r#"{"server": {"listen": "0.0.0.0", "port": 443}, "users": []}"#)
+            .unwrap()
+            .blocklist
+            .is_enabled()
+    );
+}
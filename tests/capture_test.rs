@@ -0,0 +1,262 @@
+//! 会话抓包测试
+
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use uuid::Uuid;
+use vless_rust::capture::{
+    CaptureManager, CaptureReader, CaptureRule, Direction, DEFAULT_MAX_KB, DEFAULT_SESSIONS,
+};
+use vless_rust::config::PerformanceConfig;
+use vless_rust::session::SessionServices;
+use vless_rust::tcp::handle_tcp_connection;
+
+fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+/// 读取目录下唯一的抓包文件（每行一个 JSON）
+fn read_capture(dir: &Path) -> Vec<serde_json::Value> {
+    let files: Vec<_> = std::fs::read_dir(dir)
+        .unwrap()
+        .map(|e| e.unwrap().path())
+        .collect();
+    assert_eq!(files.len(), 1, "expected one capture file: {:?}", files);
+    std::fs::read_to_string(&files[0])
+        .unwrap()
+        .lines()
+        .map(|l| serde_json::from_str(l).unwrap())
+        .collect()
+}
+
+// ============================================================================
+// 规则
+// ============================================================================
+
+#[test]
+fn test_rule_from_params() {
+    let user = Uuid::new_v4();
+    let rule = CaptureRule::from_params(&params(&[("user", &user.to_string())])).unwrap();
+    assert_eq!(rule.user, Some(user));
+    assert_eq!(rule.max_bytes, DEFAULT_MAX_KB * 1024);
+    assert_eq!(rule.remaining_sessions, DEFAULT_SESSIONS);
+    assert!(!rule.payload);
+
+    let rule = CaptureRule::from_params(&params(&[
+        ("dest", "Example.COM"),
+        ("max_kb", "4"),
+        ("sessions", "2"),
+        ("payload", "true"),
+    ]))
+    .unwrap();
+    assert_eq!(rule.dest.as_deref(), Some("example.com"));
+    assert_eq!(rule.max_bytes, 4096);
+    assert_eq!(rule.remaining_sessions, 2);
+    assert!(rule.payload);
+}
+
+#[test]
+fn test_rule_from_params_rejects_invalid() {
+    assert!(CaptureRule::from_params(&params(&[])).is_err());
+    assert!(CaptureRule::from_params(&params(&[("user", "nope")])).is_err());
+    assert!(CaptureRule::from_params(&params(&[("dest", "a"), ("max_kb", "0")])).is_err());
+    assert!(CaptureRule::from_params(&params(&[("dest", "a"), ("max_kb", "99999")])).is_err());
+    assert!(CaptureRule::from_params(&params(&[("dest", "a"), ("sessions", "x")])).is_err());
+}
+
+#[test]
+fn test_rule_matching_and_expiry() {
+    let dir = tempfile::tempdir().unwrap();
+    let manager = CaptureManager::new(dir.path());
+    let alice = Uuid::new_v4();
+
+    let rule = manager
+        .start(
+            CaptureRule::from_params(&params(&[
+                ("user", &alice.to_string()),
+                ("dest", "example.com"),
+                ("sessions", "1"),
+            ]))
+            .unwrap(),
+        )
+        .unwrap();
+    assert_eq!(manager.rules().len(), 1);
+
+    // 用户或目标不匹配
+    assert!(manager
+        .open_session(&Uuid::new_v4(), "bob", "example.com:443")
+        .is_none());
+    assert!(manager
+        .open_session(&alice, "alice", "other.org:443")
+        .is_none());
+
+    // 命中后会话数耗尽，规则自动移除
+    assert!(manager
+        .open_session(&alice, "alice", "WWW.EXAMPLE.COM:443")
+        .is_some());
+    assert!(manager.rules().is_empty());
+    assert!(!manager.stop(rule.id));
+}
+
+#[test]
+fn test_stop_rule() {
+    let dir = tempfile::tempdir().unwrap();
+    let manager = CaptureManager::new(dir.path());
+    let rule = manager
+        .start(CaptureRule::from_params(&params(&[("dest", "example.com")])).unwrap())
+        .unwrap();
+    assert!(manager.stop(rule.id));
+    assert!(manager.rules().is_empty());
+    assert!(manager
+        .open_session(&Uuid::new_v4(), "alice", "example.com:443")
+        .is_none());
+}
+
+// ============================================================================
+// 抓包文件
+// ============================================================================
+
+#[test]
+fn test_session_capture_truncates_at_budget() {
+    let dir = tempfile::tempdir().unwrap();
+    let manager = CaptureManager::new(dir.path());
+    manager
+        .start(
+            CaptureRule::from_params(&params(&[
+                ("dest", "example.com"),
+                ("max_kb", "1"),
+                ("payload", "1"),
+            ]))
+            .unwrap(),
+        )
+        .unwrap();
+
+    let capture = manager
+        .open_session(&Uuid::new_v4(), "alice", "example.com:443")
+        .unwrap();
+    capture.record(Direction::Up, b"hello");
+    capture.record(Direction::Down, &[0u8; 2000]);
+    capture.record(Direction::Up, b"late");
+    capture.record(Direction::Up, b"later");
+    drop(capture);
+
+    let lines = read_capture(dir.path());
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0]["user"], "alice");
+    assert_eq!(lines[0]["dest"], "example.com:443");
+    assert_eq!(lines[0]["max_bytes"], 1024);
+
+    assert_eq!(lines[1]["dir"], "up");
+    assert_eq!(lines[1]["len"], 5);
+    assert_eq!(lines[1]["payload"], "aGVsbG8=");
+
+    // 长度为原始长度，载荷截断到剩余预算
+    assert_eq!(lines[2]["dir"], "down");
+    assert_eq!(lines[2]["len"], 2000);
+    assert_eq!(lines[2]["payload"].as_str().unwrap().len(), 1360);
+
+    assert_eq!(lines[3]["truncated"], true);
+}
+
+#[tokio::test]
+async fn test_capture_reader_records_reads() {
+    let dir = tempfile::tempdir().unwrap();
+    let manager = CaptureManager::new(dir.path());
+    manager
+        .start(CaptureRule::from_params(&params(&[("dest", "example.com")])).unwrap())
+        .unwrap();
+    let capture = manager.open_session(&Uuid::new_v4(), "alice", "example.com:80");
+
+    let mut reader = CaptureReader::new(&b"GET / HTTP/1.1\r\n"[..], capture, Direction::Up);
+    let mut buf = Vec::new();
+    reader.read_to_end(&mut buf).await.unwrap();
+    drop(reader);
+
+    let lines = read_capture(dir.path());
+    assert_eq!(lines.len(), 2);
+    assert_eq!(lines[1]["len"], 16);
+    // 未开启载荷记录
+    assert!(lines[1].get("payload").is_none());
+}
+
+// ============================================================================
+// 代理路径
+// ============================================================================
+
+#[tokio::test]
+async fn test_tcp_proxy_session_is_captured() {
+    let dir = tempfile::tempdir().unwrap();
+    let manager = std::sync::Arc::new(CaptureManager::new(dir.path()));
+    manager
+        .start(
+            CaptureRule::from_params(&params(&[("dest", "127.0.0.1"), ("payload", "1")])).unwrap(),
+        )
+        .unwrap();
+
+    // 回显目标
+    let target = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let target_port = target.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (mut stream, _) = target.accept().await.unwrap();
+        let mut buf = [0u8; 64];
+        let n = stream.read(&mut buf).await.unwrap();
+        stream.write_all(&buf[..n]).await.unwrap();
+    });
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let user = Uuid::new_v4();
+    let services = SessionServices {
+        capture: Some(manager),
+        ..Default::default()
+    };
+    let server = tokio::spawn(async move {
+        let (stream, client_addr): (TcpStream, SocketAddr) = listener.accept().await.unwrap();
+        let users = HashSet::from([user]);
+        let _ = handle_tcp_connection(
+            stream,
+            client_addr,
+            PerformanceConfig::default(),
+            &users,
+            services,
+            |_| async { None },
+        )
+        .await;
+    });
+
+    let mut request = vec![0u8];
+    request.extend_from_slice(user.as_bytes());
+    request.push(0); // addons length
+    request.push(1); // TCP
+    request.extend_from_slice(&target_port.to_be_bytes());
+    request.push(1); // IPv4
+    request.extend_from_slice(&[127, 0, 0, 1]);
+    request.extend_from_slice(b"ping");
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client.write_all(&request).await.unwrap();
+    let mut response = Vec::new();
+    tokio::time::timeout(Duration::from_secs(5), client.read_to_end(&mut response))
+        .await
+        .unwrap()
+        .unwrap();
+    drop(client);
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let lines = read_capture(dir.path());
+    let records: Vec<_> = lines[1..]
+        .iter()
+        .map(|l| (l["dir"].as_str().unwrap(), l["payload"].as_str().unwrap()))
+        .collect();
+    assert!(records.contains(&("up", "cGluZw==")));
+    assert!(records.contains(&("down", "cGluZw==")));
+}
@@ -0,0 +1,177 @@
+//! 配置差异（重新加载预演）测试
+
+use std::io::Write;
+use vless_rust::config::Config;
+use vless_rust::config_diff::{diff_configs, load_config_file, ReloadPreview, UserEntry};
+
+const ALICE: &str = "11111111-1111-1111-1111-111111111111";
+const BOB: &str = "22222222-2222-2222-2222-222222222222";
+const CAROL: &str = "33333333-3333-3333-3333-333333333333";
+
+fn base_config() -> Config {
+    Config::from_json(&format!(
+        r#"{{
+            "server": {{"listen": "0.0.0.0", "port": 443}},
+            "users": [
+                {{"uuid": "{}", "email": "alice"}},
+                {{"uuid": "{}", "email": "bob"}}
+            ],
+            "blocklist": {{"domains": ["ads.example"]}},
+            "api": {{"token": "old-token"}}
+        }}"#,
+        ALICE, BOB
+    ))
+    .unwrap()
+}
+
+fn write_config(json: &str) -> tempfile::NamedTempFile {
+    let mut file = tempfile::NamedTempFile::new().unwrap();
+    file.write_all(json.as_bytes()).unwrap();
+    file
+}
+
+// ============================================================================
+// 差异计算
+// ============================================================================
+
+#[test]
+fn test_identical_configs_have_no_diff() {
+    let diff = diff_configs(&base_config(), &base_config());
+    assert!(diff.is_empty());
+    assert_eq!(diff.render(), "No changes\n");
+}
+
+#[test]
+fn test_user_changes() {
+    let mut new = base_config();
+    new.users.retain(|u| u.uuid != BOB);
+    new.users[0].email = Some("alice@example.com".to_string());
+    new.users.push(vless_rust::config::UserConfig {
+        uuid: CAROL.to_string(),
+        email: Some("carol".to_string()),
+        blocklist: false,
+    });
+
+    let diff = diff_configs(&base_config(), &new);
+    assert_eq!(
+        diff.users_added,
+        vec![UserEntry {
+            uuid: CAROL.to_string(),
+            email: Some("carol".to_string()),
+        }]
+    );
+    assert_eq!(diff.users_removed[0].uuid, BOB);
+    assert_eq!(
+        diff.users_changed[0].email.as_deref(),
+        Some("alice@example.com")
+    );
+    assert!(diff.changed.is_empty());
+
+    let rendered = diff.render();
+    assert!(rendered.contains(&format!("+ user carol ({})", CAROL)));
+    assert!(rendered.contains(&format!("- user bob ({})", BOB)));
+}
+
+#[test]
+fn test_setting_changes_list_paths_without_values() {
+    let mut new = base_config();
+    new.blocklist.domains.push("tracker.example".to_string());
+    new.api.token = Some("new-token".to_string());
+    new.performance.buffer_size *= 2;
+
+    let diff = diff_configs(&base_config(), &new);
+    assert_eq!(
+        diff.changed,
+        vec!["api.token", "blocklist.domains", "performance.buffer_size"]
+    );
+    let rendered = diff.render();
+    assert!(rendered.contains("~ api.token"));
+    assert!(!rendered.contains("new-token"));
+}
+
+#[test]
+fn test_added_section_is_reported() {
+    let mut new = base_config();
+    new.server.domain = Some("vpn.example.com".to_string());
+    let diff = diff_configs(&base_config(), &new);
+    assert_eq!(diff.changed, vec!["server.domain"]);
+}
+
+// ============================================================================
+// 加载与校验
+// ============================================================================
+
+#[test]
+fn test_load_config_file_validates() {
+    let file = write_config(r#"{"server": {"listen": "0.0.0.0", "port": 443}, "users": []}"#);
+    assert!(load_config_file(file.path().to_str().unwrap()).is_ok());
+
+    let file = write_config(r#"{"server": {"listen": "not-an-ip", "port": 443}, "users": []}"#);
+    let err = load_config_file(file.path().to_str().unwrap()).unwrap_err();
+    assert!(err.to_string().contains("server.listen"));
+
+    let file = write_config(
+        r#"{"server": {"listen": "0.0.0.0", "port": 443}, "users": [], "decoy": {"mode": "redirect"}}"#,
+    );
+    let err = load_config_file(file.path().to_str().unwrap()).unwrap_err();
+    assert!(err.to_string().contains("redirect_url"));
+
+    let file = write_config("{ not json");
+    assert!(load_config_file(file.path().to_str().unwrap()).is_err());
+    assert!(load_config_file("/nonexistent/config.json").is_err());
+}
+
+#[test]
+fn test_validate_users_reports_location() {
+    let mut config = base_config();
+    assert!(config.validate_users().is_ok());
+
+    config.users[1].uuid = "not-a-uuid".to_string();
+    let err = config.validate_users().unwrap_err().to_string();
+    assert!(err.starts_with("users[1].uuid: invalid UUID"), "{}", err);
+
+    // 大小写不同的同一 UUID 也视为重复
+    config.users[1].uuid = ALICE.to_uppercase();
+    let err = config.validate().unwrap_err().to_string();
+    assert!(err.contains("users[1].uuid: duplicate UUID"), "{}", err);
+    assert!(err.contains("users[0]"), "{}", err);
+}
+
+#[test]
+fn test_duplicate_email_is_warning() {
+    let mut new = base_config();
+    new.users[1].email = Some("ALICE".to_string());
+    assert!(new.validate().is_ok());
+    assert_eq!(
+        new.user_warnings(),
+        vec!["users[1].email: duplicate email 'ALICE' (same as users[0])".to_string()]
+    );
+
+    let diff = diff_configs(&base_config(), &new);
+    assert_eq!(diff.warnings.len(), 1);
+    assert!(diff.render().starts_with("! users[1].email"));
+    assert!(base_config().user_warnings().is_empty());
+}
+
+#[test]
+fn test_load_config_file_rejects_duplicate_uuid() {
+    let file = write_config(&format!(
+        r#"{{"server": {{"listen": "0.0.0.0", "port": 443}},
+            "users": [{{"uuid": "{0}"}}, {{"uuid": "{1}"}}, {{"uuid": "{0}"}}]}}"#,
+        ALICE, BOB
+    ));
+    let err = load_config_file(file.path().to_str().unwrap()).unwrap_err();
+    assert!(err.to_string().contains("users[2].uuid"), "{}", err);
+}
+
+#[test]
+fn test_reload_preview_reads_file_each_time() {
+    let file = write_config(&base_config().to_json().unwrap());
+    let preview = ReloadPreview::new(file.path().to_str().unwrap(), base_config());
+    assert!(preview.preview().unwrap().is_empty());
+
+    let mut new = base_config();
+    new.users.clear();
+    std::fs::write(file.path(), new.to_json().unwrap()).unwrap();
+    assert_eq!(preview.preview().unwrap().users_removed.len(), 2);
+}
@@ -0,0 +1,95 @@
+//! 配置加载测试
+
+use vless_rust::config::{Config, PerformanceConfig, Profile};
+
+fn config_with(extra: &str) -> Config {
+    Config::from_json(&format!(
+        r#"{{"server": {{"listen": "0.0.0.0", "port": 443}}, "users": []{}}}"#,
+        extra
+    ))
+    .unwrap()
+}
+
+// ============================================================================
+// 性能预设
+// ============================================================================
+
+#[test]
+fn test_no_profile_uses_builtin_defaults() {
+    let config = config_with("");
+    let defaults = PerformanceConfig::default();
+    assert!(config.profile.is_none());
+    assert_eq!(config.performance.buffer_size, defaults.buffer_size);
+    assert_eq!(
+        config.performance.buffer_pool_size,
+        defaults.buffer_pool_size
+    );
+
+    let balanced = config_with(r#", "profile": "balanced""#);
+    assert_eq!(balanced.profile, Some(Profile::Balanced));
+    assert_eq!(
+        balanced.performance.tcp_recv_buffer,
+        defaults.tcp_recv_buffer
+    );
+    assert_eq!(balanced.monitoring.max_destinations, 2048);
+}
+
+#[test]
+fn test_low_memory_profile() {
+    let config = config_with(r#", "profile": "low-memory""#);
+    assert_eq!(config.performance.buffer_size, 16 * 1024);
+    assert_eq!(config.performance.tcp_send_buffer, 32 * 1024);
+    assert_eq!(config.performance.buffer_pool_size, 8);
+    assert_eq!(config.monitoring.max_destinations, 256);
+    assert_eq!(config.monitoring.log_history, 200);
+    // 未纳入预设的字段保持内置默认值
+    assert!(config.performance.tcp_nodelay);
+    assert_eq!(config.performance.ws_ping_interval, 30);
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn test_explicit_values_override_profile() {
+    let config = config_with(
+        r#", "profile": "throughput",
+            "performance": {"buffer_size": 4096}"#,
+    );
+    assert_eq!(config.performance.buffer_size, 4096);
+    assert_eq!(config.performance.tcp_recv_buffer, 1024 * 1024);
+    assert_eq!(config.monitoring.max_destinations, 8192);
+    assert!(config.validate().is_ok());
+}
+
+#[test]
+fn test_profile_round_trip_and_errors() {
+    let config = config_with(r#", "profile": "throughput""#);
+    let reloaded = Config::from_json(&config.to_json().unwrap()).unwrap();
+    assert_eq!(reloaded.profile, Some(Profile::Throughput));
+    assert_eq!(reloaded.performance.buffer_size, 256 * 1024);
+    assert_eq!(Profile::LowMemory.as_str(), "low-memory");
+
+    let err = Config::from_json(
+        r#"{"server": {"listen": "0.0.0.0", "port": 443}, "users": [], "profile": "turbo"}"#,
+    )
+    .unwrap_err();
+    assert!(err.to_string().contains("turbo"));
+}
+
+#[test]
+fn test_rewrite_keeps_profile_effective() {
+    let mut config = config_with(
+        r#", "profile": "throughput",
+            "performance": {"buffer_size": 4096}"#,
+    );
+    config.monitoring.log_history = 123;
+    let json = config.to_json().unwrap();
+    // 预设值不写回配置文件，显式值与修改过的值保留
+    assert!(!json.contains("tcp_recv_buffer"));
+    assert!(json.contains("\"buffer_size\": 4096"));
+    assert!(json.contains("\"log_history\": 123"));
+
+    let switched = Config::from_json(&json.replace("throughput", "low-memory")).unwrap();
+    assert_eq!(switched.performance.tcp_recv_buffer, 32 * 1024);
+    assert_eq!(switched.performance.buffer_size, 4096);
+    assert_eq!(switched.monitoring.log_history, 123);
+}
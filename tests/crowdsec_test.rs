@@ -0,0 +1,359 @@
+//! CrowdSec 集成测试：配置校验、决策查询与缓存、认证失败计数与告警上报
+
+use chrono::{DateTime, Utc};
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use uuid::Uuid;
+use vless_rust::config::{Config, CrowdSecConfig};
+use vless_rust::crowdsec::{
+    build_alert, parse_go_duration, AlertReporter, Bouncer, FailureTracker,
+};
+use vless_rust::events::{Event, EventBus};
+use vless_rust::session::SessionServices;
+
+/// 收到的请求：（请求行，小写请求头，请求体）
+type Requests = Arc<Mutex<Vec<(String, String, String)>>>;
+
+/// 启动假的 LAPI：按请求行返回（状态码，响应体），记录收到的请求
+async fn fake_lapi(
+    respond: impl Fn(&str) -> (u16, String) + Send + Sync + 'static,
+) -> (String, Requests) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let url = format!("http://{}", listener.local_addr().unwrap());
+    let requests: Requests = Arc::default();
+    let recorded = Arc::clone(&requests);
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = listener.accept().await {
+            let mut request = Vec::new();
+            let mut buf = [0u8; 4096];
+            let (head, body) = loop {
+                let n = stream.read(&mut buf).await.unwrap();
+                if n == 0 {
+                    break (String::new(), String::new());
+                }
+                request.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&request).to_string();
+                let Some((head, body)) = text.split_once("\r\n\r\n") else {
+                    continue;
+                };
+                let length = head
+                    .to_ascii_lowercase()
+                    .lines()
+                    .find_map(|line| line.strip_prefix("content-length: ")?.parse().ok())
+                    .unwrap_or(0usize);
+                if body.len() >= length {
+                    break (head.to_string(), body.to_string());
+                }
+            };
+            let line = head.lines().next().unwrap_or_default().to_string();
+            let (status, response) = respond(&line);
+            recorded
+                .lock()
+                .unwrap()
+                .push((line, head.to_ascii_lowercase(), body));
+            let reply = format!(
+                "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                response.len(),
+                response
+            );
+            let _ = stream.write_all(reply.as_bytes()).await;
+        }
+    });
+    (url, requests)
+}
+
+fn bouncer_config(url: &str) -> CrowdSecConfig {
+    CrowdSecConfig {
+        lapi_url: Some(url.to_string()),
+        bouncer_key: Some("b0uncer".to_string()),
+        ..Default::default()
+    }
+}
+
+fn ip(text: &str) -> IpAddr {
+    text.parse().unwrap()
+}
+
+fn time(text: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(text)
+        .unwrap()
+        .with_timezone(&Utc)
+}
+
+// ============================================================================
+// 配置
+// ============================================================================
+
+#[test]
+fn test_crowdsec_config() {
+    let parse = |crowdsec: &str| {
+        Config::from_json(&format!(
+            r#"{{"server": {{"listen": "0.0.0.0", "port": 443}}, "users": [], "crowdsec": {}}}"#,
+            crowdsec
+        ))
+        .unwrap()
+    };
+
+    let config = parse("{}");
+    assert!(!config.crowdsec.bouncer_enabled());
+    assert!(!config.crowdsec.alerts_enabled());
+    assert_eq!(config.crowdsec.cache_ttl, 60);
+    assert_eq!(config.crowdsec.timeout_ms, 500);
+    assert_eq!(config.crowdsec.alert_threshold, 5);
+    assert_eq!(config.crowdsec.scenario, "vless-rust/auth-failure");
+    assert!(config.validate().is_ok());
+
+    let config = parse(
+        r#"{"lapi_url": "http://127.0.0.1:8080", "bouncer_key": "k", "machine_id": "vless", "password": "p"}"#,
+    );
+    assert!(config.crowdsec.bouncer_enabled());
+    assert!(config.crowdsec.alerts_enabled());
+    assert!(config.validate().is_ok());
+
+    let error = |crowdsec: &str| parse(crowdsec).validate().unwrap_err().to_string();
+    assert!(error(r#"{"bouncer_key": "k"}"#).contains("crowdsec.lapi_url is required"));
+    assert!(
+        error(r#"{"lapi_url": "127.0.0.1:8080", "bouncer_key": "k"}"#)
+            .contains("crowdsec.lapi_url must start with")
+    );
+    assert!(error(r#"{"lapi_url": "http://127.0.0.1:8080"}"#)
+        .contains("requires bouncer_key or machine_id"));
+    assert!(
+        error(r#"{"lapi_url": "http://127.0.0.1:8080", "machine_id": "vless"}"#)
+            .contains("crowdsec.password")
+    );
+    assert!(
+        error(r#"{"lapi_url": "http://127.0.0.1:8080", "bouncer_key": "k", "timeout_ms": 0}"#)
+            .contains("crowdsec.timeout_ms")
+    );
+}
+
+#[test]
+fn test_parse_go_duration() {
+    assert_eq!(
+        parse_go_duration("3h59m58s"),
+        Some(Duration::from_secs(3 * 3600 + 59 * 60 + 58))
+    );
+    assert_eq!(
+        parse_go_duration("1m30.5s"),
+        Some(Duration::from_millis(90_500))
+    );
+    assert_eq!(parse_go_duration("500ms"), Some(Duration::from_millis(500)));
+    // 负数表示决策已过期
+    assert_eq!(parse_go_duration("-2m3s"), Some(Duration::ZERO));
+    assert_eq!(parse_go_duration(""), None);
+    assert_eq!(parse_go_duration("4d"), None);
+    assert_eq!(parse_go_duration("h"), None);
+    // 超长数字来自网络输入，按最长时长处理而不是 panic
+    let huge = format!("{}h", "9".repeat(400));
+    assert_eq!(parse_go_duration(&huge), Some(Duration::MAX));
+    assert_eq!(parse_go_duration("999999999999999999999h"), Some(Duration::MAX));
+}
+
+// ============================================================================
+// 决策查询
+// ============================================================================
+
+#[tokio::test]
+async fn test_bouncer_queries_and_caches_decisions() {
+    let (url, requests) = fake_lapi(|line| {
+        let body = if line.contains("ip=203.0.113.7") {
+            r#"[{"id": 1, "origin": "crowdsec", "type": "ban", "scope": "Ip", "value": "203.0.113.7", "duration": "3h59m", "scenario": "crowdsecurity/ssh-bf"}]"#
+        } else if line.contains("ip=203.0.113.8") {
+            r#"[{"id": 2, "type": "ban", "scope": "Ip", "value": "203.0.113.8", "duration": "-5s"}]"#
+        } else if line.contains("ip=203.0.113.9") {
+            r#"[{"id": 3, "type": "captcha", "scope": "Ip", "value": "203.0.113.9", "duration": "1h"}]"#
+        } else {
+            "null"
+        };
+        (200, body.to_string())
+    })
+    .await;
+    let bouncer = Bouncer::new(&bouncer_config(&url)).unwrap();
+
+    assert!(bouncer.is_banned(ip("203.0.113.7")).await);
+    // IPv4 映射地址按 IPv4 查询并共用缓存
+    assert!(bouncer.is_banned(ip("::ffff:203.0.113.7")).await);
+    assert!(!bouncer.is_banned(ip("198.51.100.1")).await);
+    assert!(!bouncer.is_banned(ip("198.51.100.1")).await);
+    // 已过期的封禁与非封禁类决策放行
+    assert!(!bouncer.is_banned(ip("203.0.113.8")).await);
+    assert!(!bouncer.is_banned(ip("203.0.113.9")).await);
+
+    let requests = requests.lock().unwrap().clone();
+    assert_eq!(requests.len(), 4);
+    assert!(requests[0]
+        .0
+        .starts_with("GET /v1/decisions?ip=203.0.113.7 HTTP/1.1"));
+    assert!(requests[0].1.contains("x-api-key: b0uncer"));
+
+    let stats = bouncer.stats();
+    assert_eq!(stats.checked, 6);
+    assert_eq!(stats.cache_hits, 2);
+    assert_eq!(stats.blocked, 2);
+    assert_eq!(stats.errors, 0);
+}
+
+#[tokio::test]
+async fn test_bouncer_fails_open() {
+    let (url, requests) =
+        fake_lapi(|_| (403, r#"{"message": "access forbidden"}"#.to_string())).await;
+    let bouncer = Bouncer::new(&bouncer_config(&url)).unwrap();
+    assert!(!bouncer.is_banned(ip("203.0.113.7")).await);
+    // 出错的结果不缓存
+    assert!(!bouncer.is_banned(ip("203.0.113.7")).await);
+    assert_eq!(requests.lock().unwrap().len(), 2);
+    assert_eq!(bouncer.stats().errors, 2);
+
+    // LAPI 不可达
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let closed = format!("http://{}", listener.local_addr().unwrap());
+    drop(listener);
+    let bouncer = Bouncer::new(&bouncer_config(&closed)).unwrap();
+    assert!(!bouncer.is_banned(ip("203.0.113.7")).await);
+    assert_eq!(bouncer.stats().errors, 1);
+}
+
+#[tokio::test]
+async fn test_session_services_is_banned() {
+    let (url, _) = fake_lapi(|line| {
+        let body = if line.contains("ip=127.0.0.1") {
+            r#"[{"type": "ban", "duration": "10m"}]"#
+        } else {
+            "null"
+        };
+        (200, body.to_string())
+    })
+    .await;
+    let client: SocketAddr = "127.0.0.1:40000".parse().unwrap();
+
+    assert!(!SessionServices::default().is_banned(client).await);
+    let services = SessionServices {
+        bouncer: Some(Arc::new(Bouncer::new(&bouncer_config(&url)).unwrap())),
+        ..Default::default()
+    };
+    assert!(services.is_banned(client).await);
+    assert!(!services.is_banned("127.0.0.2:40000".parse().unwrap()).await);
+}
+
+// ============================================================================
+// 告警
+// ============================================================================
+
+#[test]
+fn test_failure_tracker() {
+    let mut tracker = FailureTracker::new(3, Duration::from_secs(60));
+    let attacker = ip("203.0.113.7");
+
+    assert!(tracker
+        .record(attacker, time("2026-10-15T00:00:00Z"))
+        .is_none());
+    // 超出窗口的失败不计入
+    assert!(tracker
+        .record(attacker, time("2026-10-15T00:01:00Z"))
+        .is_none());
+    assert!(tracker
+        .record(attacker, time("2026-10-15T00:01:10Z"))
+        .is_none());
+    assert!(tracker
+        .record(ip("203.0.113.8"), time("2026-10-15T00:01:15Z"))
+        .is_none());
+    let failures = tracker
+        .record(attacker, time("2026-10-15T00:01:20Z"))
+        .unwrap();
+    assert_eq!(
+        failures,
+        vec![
+            time("2026-10-15T00:01:00Z"),
+            time("2026-10-15T00:01:10Z"),
+            time("2026-10-15T00:01:20Z"),
+        ]
+    );
+    // 上报后重新计数
+    assert!(tracker
+        .record(attacker, time("2026-10-15T00:01:21Z"))
+        .is_none());
+}
+
+#[test]
+fn test_build_alert() {
+    let failures = vec![time("2026-10-15T00:01:00Z"), time("2026-10-15T00:01:20Z")];
+    let alert = build_alert("vless-rust/auth-failure", ip("203.0.113.7"), &failures);
+    assert_eq!(alert["scenario"], "vless-rust/auth-failure");
+    assert_eq!(alert["events_count"], 2);
+    assert_eq!(alert["start_at"], "2026-10-15T00:01:00Z");
+    assert_eq!(alert["stop_at"], "2026-10-15T00:01:20Z");
+    assert_eq!(alert["simulated"], false);
+    assert_eq!(alert["source"]["scope"], "Ip");
+    assert_eq!(alert["source"]["value"], "203.0.113.7");
+    assert_eq!(alert["events"].as_array().unwrap().len(), 2);
+    assert_eq!(alert["events"][0]["meta"][0]["key"], "source_ip");
+    assert_eq!(alert["events"][0]["meta"][0]["value"], "203.0.113.7");
+    // 不携带决策，由 LAPI profiles 决定处置
+    assert!(alert.get("decisions").is_none());
+}
+
+#[tokio::test]
+async fn test_alert_reporter_pushes_auth_failures() {
+    let (url, requests) = fake_lapi(|line| {
+        if line.starts_with("POST /v1/watchers/login") {
+            (
+                200,
+                r#"{"code": 200, "expire": "2099-01-01T00:00:00Z", "token": "jwt-t0ken"}"#
+                    .to_string(),
+            )
+        } else {
+            (201, r#"["1"]"#.to_string())
+        }
+    })
+    .await;
+    let config = CrowdSecConfig {
+        lapi_url: Some(url),
+        machine_id: Some("vless".to_string()),
+        password: Some("s3cret".to_string()),
+        alert_threshold: 2,
+        ..Default::default()
+    };
+    let events = EventBus::new();
+    let alerts = AlertReporter::spawn(&config, events.subscribe()).unwrap();
+
+    let fail = |addr: &str| {
+        events.publish(Event::AuthFailed {
+            client_addr: addr.parse().unwrap(),
+            uuid: Uuid::nil(),
+        })
+    };
+    fail("203.0.113.7:1000");
+    fail("198.51.100.1:1000");
+    fail("203.0.113.7:1001");
+    fail("203.0.113.7:1002");
+    fail("203.0.113.7:1003");
+
+    for _ in 0..100 {
+        if alerts.sent_count() == 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(alerts.sent_count(), 2);
+    assert_eq!(alerts.failed_count(), 0);
+
+    let requests = requests.lock().unwrap().clone();
+    // 令牌缓存，只登录一次
+    assert_eq!(requests.len(), 3);
+    assert!(requests[0].0.starts_with("POST /v1/watchers/login"));
+    let login: serde_json::Value = serde_json::from_str(&requests[0].2).unwrap();
+    assert_eq!(login["machine_id"], "vless");
+    assert_eq!(login["password"], "s3cret");
+    assert_eq!(login["scenarios"][0], "vless-rust/auth-failure");
+
+    assert!(requests[1].0.starts_with("POST /v1/alerts"));
+    assert!(requests[1].1.contains("authorization: bearer jwt-t0ken"));
+    let pushed: serde_json::Value = serde_json::from_str(&requests[1].2).unwrap();
+    assert_eq!(pushed[0]["source"]["ip"], "203.0.113.7");
+    assert_eq!(pushed[0]["events_count"], 2);
+}
@@ -0,0 +1,228 @@
+//! 动态 DNS 更新测试
+
+use std::sync::{Arc, Mutex};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use vless_rust::config::{Config, DdnsConfig, DdnsProvider};
+use vless_rust::ddns::DdnsUpdater;
+
+/// 本地模拟 API：按请求行返回响应体，记录收到的请求（请求行与请求体）
+async fn fake_api(
+    respond: fn(&str) -> (u16, String),
+) -> (String, Arc<Mutex<Vec<(String, String)>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let requests = Arc::new(Mutex::new(Vec::new()));
+    let log = Arc::clone(&requests);
+
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut data = Vec::new();
+            let mut buf = [0u8; 4096];
+            // 读完请求头与 Content-Length 指定的请求体
+            loop {
+                let n = stream.read(&mut buf).await.unwrap();
+                data.extend_from_slice(&buf[..n]);
+                let text = String::from_utf8_lossy(&data).to_string();
+                if let Some(end) = text.find("\r\n\r\n") {
+                    let length = text
+                        .lines()
+                        .find_map(|l| {
+                            l.to_ascii_lowercase()
+                                .strip_prefix("content-length: ")
+                                .map(String::from)
+                        })
+                        .and_then(|v| v.trim().parse::<usize>().ok())
+                        .unwrap_or(0);
+                    if data.len() >= end + 4 + length || n == 0 {
+                        break;
+                    }
+                }
+            }
+
+            let text = String::from_utf8_lossy(&data).to_string();
+            let request_line = text.lines().next().unwrap_or_default().to_string();
+            let body = text
+                .split("\r\n\r\n")
+                .nth(1)
+                .unwrap_or_default()
+                .to_string();
+            let (status, response) = respond(&request_line);
+            log.lock().unwrap().push((request_line, body));
+
+            let reply = format!(
+                "HTTP/1.1 {} X\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+                status,
+                response.len(),
+                response
+            );
+            stream.write_all(reply.as_bytes()).await.unwrap();
+        }
+    });
+
+    (format!("http://{}", addr), requests)
+}
+
+fn duckdns_config(endpoint: String) -> DdnsConfig {
+    DdnsConfig {
+        provider: Some(DdnsProvider::Duckdns),
+        hostname: "myhome.duckdns.org".to_string(),
+        token: "duck-token".to_string(),
+        endpoint: Some(endpoint),
+        ..Default::default()
+    }
+}
+
+fn cloudflare_config(endpoint: String) -> DdnsConfig {
+    DdnsConfig {
+        provider: Some(DdnsProvider::Cloudflare),
+        hostname: "vpn.example.com".to_string(),
+        token: "cf-token".to_string(),
+        zone_id: Some("zone123".to_string()),
+        endpoint: Some(endpoint),
+        ..Default::default()
+    }
+}
+
+// ============================================================================
+// 配置
+// ============================================================================
+
+#[test]
+fn test_ddns_config_parse() {
+    let config = Config::from_json(
+        r#"{
+            "server": {"listen": "0.0.0.0", "port": 443},
+            "users": [],
+            "ddns": {
+                "provider": "cloudflare",
+                "hostname": "vpn.example.com",
+                "token": "cf-token",
+                "zone_id": "zone123"
+            }
+        }"#,
+    )
+    .unwrap();
+    assert!(config.ddns.is_enabled());
+    assert_eq!(config.ddns.provider, Some(DdnsProvider::Cloudflare));
+    assert_eq!(config.ddns.interval, 300);
+    assert!(!DdnsConfig::default().is_enabled());
+}
+
+#[test]
+fn test_updater_validates_config() {
+    let endpoint = "http://127.0.0.1:1".to_string();
+
+    let mut config = cloudflare_config(endpoint.clone());
+    config.zone_id = None;
+    assert!(DdnsUpdater::new(&config).is_err());
+
+    let mut config = duckdns_config(endpoint.clone());
+    config.hostname = "myhome.example.com".to_string();
+    assert!(DdnsUpdater::new(&config).is_err());
+
+    let mut config = duckdns_config(endpoint.clone());
+    config.token.clear();
+    assert!(DdnsUpdater::new(&config).is_err());
+
+    assert!(DdnsUpdater::new(&DdnsConfig::default()).is_err());
+    assert!(DdnsUpdater::new(&duckdns_config(endpoint)).is_ok());
+}
+
+// ============================================================================
+// DuckDNS
+// ============================================================================
+
+#[tokio::test]
+async fn test_duckdns_update() {
+    let (endpoint, requests) = fake_api(|_| (200, "OK".to_string())).await;
+    let updater = DdnsUpdater::new(&duckdns_config(endpoint)).unwrap();
+
+    assert!(updater.update("203.0.113.7").await.unwrap());
+    // 相同 IP 不重复请求
+    assert!(!updater.update("203.0.113.7").await.unwrap());
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 1);
+    let line = &requests[0].0;
+    assert!(line.starts_with("GET /update?"));
+    assert!(line.contains("domains=myhome&"));
+    assert!(line.contains("token=duck-token"));
+    assert!(line.contains("ip=203.0.113.7"));
+}
+
+#[tokio::test]
+async fn test_duckdns_rejection_is_error() {
+    let (endpoint, _) = fake_api(|_| (200, "KO".to_string())).await;
+    let updater = DdnsUpdater::new(&duckdns_config(endpoint)).unwrap();
+    assert!(updater.update("203.0.113.7").await.is_err());
+    // 失败后不记录，下次仍会重试
+    assert!(updater.update("203.0.113.7").await.is_err());
+}
+
+// ============================================================================
+// Cloudflare
+// ============================================================================
+
+#[tokio::test]
+async fn test_cloudflare_updates_existing_record() {
+    let (endpoint, requests) = fake_api(|line| {
+        if line.starts_with("GET") {
+            (
+                200,
+                r#"{"success":true,"result":[{"id":"rec1"}]}"#.to_string(),
+            )
+        } else {
+            (200, r#"{"success":true,"result":{}}"#.to_string())
+        }
+    })
+    .await;
+    let updater = DdnsUpdater::new(&cloudflare_config(endpoint)).unwrap();
+    assert!(updater.update("203.0.113.7").await.unwrap());
+
+    let requests = requests.lock().unwrap();
+    assert_eq!(requests.len(), 2);
+    assert!(requests[0]
+        .0
+        .starts_with("GET /zones/zone123/dns_records?type=A&name=vpn.example.com"));
+    assert!(requests[1]
+        .0
+        .starts_with("PUT /zones/zone123/dns_records/rec1 "));
+    let record: serde_json::Value = serde_json::from_str(&requests[1].1).unwrap();
+    assert_eq!(record["content"], "203.0.113.7");
+    assert_eq!(record["name"], "vpn.example.com");
+}
+
+#[tokio::test]
+async fn test_cloudflare_creates_missing_record() {
+    let (endpoint, requests) = fake_api(|line| {
+        if line.starts_with("GET") {
+            (200, r#"{"success":true,"result":[]}"#.to_string())
+        } else {
+            (200, r#"{"success":true,"result":{}}"#.to_string())
+        }
+    })
+    .await;
+    let updater = DdnsUpdater::new(&cloudflare_config(endpoint)).unwrap();
+    updater.update("203.0.113.7").await.unwrap();
+
+    let requests = requests.lock().unwrap();
+    assert!(requests[1]
+        .0
+        .starts_with("POST /zones/zone123/dns_records "));
+}
+
+#[tokio::test]
+async fn test_cloudflare_api_error() {
+    let (endpoint, _) = fake_api(|_| {
+        (
+            403,
+            r#"{"success":false,"errors":[{"message":"Authentication error"}]}"#.to_string(),
+        )
+    })
+    .await;
+    let updater = DdnsUpdater::new(&cloudflare_config(endpoint)).unwrap();
+    let err = updater.update("203.0.113.7").await.unwrap_err();
+    assert!(err.to_string().contains("Authentication error"));
+}
@@ -0,0 +1,365 @@
+//! 伪装站点模块测试
+
+use std::path::Path;
+use tempfile::TempDir;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use vless_rust::config::{Config, DecoyConfig, DecoyMode, ProxyRoute};
+use vless_rust::decoy::{
+    build_redirect_response, build_status_response, content_type_for, force_connection_close,
+    match_route, path_has_prefix, proxy_route, request_line, resolve_static_path, serve,
+    strip_request_prefix,
+};
+
+fn route(path: &str, host: Option<&str>, backend: &str) -> ProxyRoute {
+    ProxyRoute {
+        path: path.to_string(),
+        host: host.map(String::from),
+        backend: backend.to_string(),
+        strip_prefix: false,
+    }
+}
+
+/// 启动一次性服务端：接受连接后用给定配置处理请求，返回客户端收到的全部数据
+async fn roundtrip(config: DecoyConfig, request: &'static [u8]) -> Vec<u8> {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 1024];
+        let n = stream.read(&mut buf).await.unwrap();
+        let _ = serve(stream, &buf[..n], &config).await;
+    });
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client.write_all(request).await.unwrap();
+    let mut response = Vec::new();
+    client.read_to_end(&mut response).await.unwrap();
+    response
+}
+
+// ============================================================================
+// 请求解析与响应构建
+// ============================================================================
+
+#[test]
+fn test_request_line() {
+    assert_eq!(
+        request_line(b"GET /a?b=1 HTTP/1.1\r\nHost: x\r\n\r\n"),
+        Some(("GET", "/a?b=1"))
+    );
+    assert_eq!(request_line(b"GET\r\n"), None);
+    assert_eq!(request_line(b"no newline"), None);
+}
+
+#[test]
+fn test_build_responses() {
+    let redirect =
+        String::from_utf8(build_redirect_response("https://example.com/", "/p?q=1")).unwrap();
+    assert!(redirect.starts_with("HTTP/1.1 301 Moved Permanently\r\n"));
+    assert!(redirect.contains("Location: https://example.com/p?q=1\r\n"));
+
+    let status = String::from_utf8(build_status_response(403)).unwrap();
+    assert!(status.starts_with("HTTP/1.1 403 Forbidden\r\n"));
+    // 不携带面板特有的安全头
+    assert!(!status.contains("Content-Security-Policy"));
+}
+
+#[test]
+fn test_content_type_for() {
+    assert_eq!(
+        content_type_for(Path::new("a/index.HTML")),
+        "text/html; charset=utf-8"
+    );
+    assert_eq!(content_type_for(Path::new("logo.png")), "image/png");
+    assert_eq!(
+        content_type_for(Path::new("data.bin")),
+        "application/octet-stream"
+    );
+}
+
+#[test]
+fn test_resolve_static_path() {
+    let dir = TempDir::new().unwrap();
+    std::fs::create_dir(dir.path().join("docs")).unwrap();
+
+    assert_eq!(
+        resolve_static_path(dir.path(), "/"),
+        Some(dir.path().join("index.html"))
+    );
+    assert_eq!(
+        resolve_static_path(dir.path(), "/docs?x=1"),
+        Some(dir.path().join("docs").join("index.html"))
+    );
+    assert_eq!(
+        resolve_static_path(dir.path(), "/a%20b.css"),
+        Some(dir.path().join("a b.css"))
+    );
+    assert_eq!(resolve_static_path(dir.path(), "/../etc/passwd"), None);
+    assert_eq!(resolve_static_path(dir.path(), "/%2e%2e/etc/passwd"), None);
+    assert_eq!(resolve_static_path(dir.path(), "/a\\b"), None);
+}
+
+// ============================================================================
+// 反向代理路由
+// ============================================================================
+
+#[test]
+fn test_path_has_prefix() {
+    assert!(path_has_prefix("/app", "/app"));
+    assert!(path_has_prefix("/app/x", "/app/"));
+    assert!(path_has_prefix("/app?x=1", "/app"));
+    assert!(!path_has_prefix("/apple", "/app"));
+    assert!(path_has_prefix("/anything", "/"));
+}
+
+#[test]
+fn test_match_route_by_path_and_host() {
+    let routes = vec![
+        route("/app", Some("a.example.com"), "127.0.0.1:1"),
+        route("/app", None, "127.0.0.1:2"),
+    ];
+
+    let a = b"GET /app/x HTTP/1.1\r\nHost: a.example.com\r\n\r\n";
+    assert_eq!(match_route(&routes, a).unwrap().backend, "127.0.0.1:1");
+
+    let other = b"GET /app HTTP/1.1\r\nHost: b.example.com\r\n\r\n";
+    assert_eq!(match_route(&routes, other).unwrap().backend, "127.0.0.1:2");
+
+    assert!(match_route(&routes, b"GET / HTTP/1.1\r\n\r\n").is_none());
+}
+
+#[test]
+fn test_strip_request_prefix() {
+    assert_eq!(
+        strip_request_prefix(b"GET /app/x?y=1 HTTP/1.1\r\nHost: h\r\n\r\n", "/app"),
+        b"GET /x?y=1 HTTP/1.1\r\nHost: h\r\n\r\n".to_vec()
+    );
+    assert_eq!(
+        strip_request_prefix(b"GET /app HTTP/1.1\r\n\r\n", "/app/"),
+        b"GET / HTTP/1.1\r\n\r\n".to_vec()
+    );
+}
+
+#[tokio::test]
+async fn test_proxy_route_strips_prefix() {
+    let backend = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let backend_addr = backend.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut stream, _) = backend.accept().await.unwrap();
+        let mut buf = [0u8; 1024];
+        let n = stream.read(&mut buf).await.unwrap();
+        let reply: &[u8] = if buf[..n].starts_with(b"GET /status HTTP/1.1") {
+            b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nok"
+        } else {
+            b"HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\n\r\n"
+        };
+        stream.write_all(reply).await.unwrap();
+    });
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 1024];
+        let n = stream.read(&mut buf).await.unwrap();
+        let route = ProxyRoute {
+            strip_prefix: true,
+            ..route("/app", None, &backend_addr.to_string())
+        };
+        let _ = proxy_route(stream, &buf[..n], &route).await;
+    });
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client
+        .write_all(b"GET /app/status HTTP/1.1\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = Vec::new();
+    client.read_to_end(&mut response).await.unwrap();
+    assert!(response.ends_with(b"\r\n\r\nok"));
+}
+
+#[test]
+fn test_force_connection_close() {
+    assert_eq!(
+        force_connection_close(
+            b"GET /x HTTP/1.1\r\nHost: h\r\nconnection: keep-alive\r\nKeep-Alive: timeout=5\r\n\r\nbody"
+        ),
+        b"GET /x HTTP/1.1\r\nHost: h\r\nConnection: close\r\n\r\nbody".to_vec()
+    );
+    assert_eq!(
+        force_connection_close(b"GET / HTTP/1.1\r\n\r\n"),
+        b"GET / HTTP/1.1\r\nConnection: close\r\n\r\n".to_vec()
+    );
+    // 升级请求与不完整的请求头不改写
+    let upgrade: &[u8] = b"GET /ws HTTP/1.1\r\nConnection: Upgrade\r\nUpgrade: websocket\r\n\r\n";
+    assert_eq!(force_connection_close(upgrade), upgrade.to_vec());
+    assert_eq!(
+        force_connection_close(b"GET / HTTP/1.1\r\nHost"),
+        b"GET / HTTP/1.1\r\nHost".to_vec()
+    );
+}
+
+/// 同一连接上的后续请求不再经首个请求选中的路由发往同一后端
+#[tokio::test]
+async fn test_proxy_route_pipelined_requests() {
+    let backend = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let backend_addr = backend.local_addr().unwrap();
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (mut stream, _) = backend.accept().await.unwrap();
+        let mut buf = [0u8; 1024];
+        let n = stream.read(&mut buf).await.unwrap();
+        let received = buf[..n].to_vec();
+        // 遵守 Connection: close 的后端只响应首个请求；否则连同后续请求一并响应
+        let first = b"HTTP/1.1 200 OK\r\nContent-Length: 5\r\n\r\nfirst";
+        stream.write_all(first).await.unwrap();
+        let head_end = received.windows(4).position(|w| w == b"\r\n\r\n").unwrap();
+        let head = String::from_utf8_lossy(&received[..head_end]).to_string();
+        if !head.contains("Connection: close") {
+            let second = b"HTTP/1.1 200 OK\r\nContent-Length: 6\r\n\r\nsecond";
+            stream.write_all(second).await.unwrap();
+        }
+        tx.send(received).unwrap();
+    });
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 1024];
+        let n = stream.read(&mut buf).await.unwrap();
+        let route = ProxyRoute {
+            strip_prefix: true,
+            ..route("/app", None, &backend_addr.to_string())
+        };
+        let _ = proxy_route(stream, &buf[..n], &route).await;
+    });
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client
+        .write_all(
+            b"GET /app/x HTTP/1.1\r\nHost: h\r\nConnection: keep-alive\r\n\r\n\
+              GET /other HTTP/1.1\r\nHost: h\r\n\r\n",
+        )
+        .await
+        .unwrap();
+    let mut response = Vec::new();
+    client.read_to_end(&mut response).await.unwrap();
+    assert!(response.ends_with(b"\r\n\r\nfirst"));
+
+    let received = String::from_utf8(rx.await.unwrap()).unwrap();
+    assert!(received.starts_with("GET /x HTTP/1.1\r\n"), "{}", received);
+    assert!(received.contains("\r\nConnection: close\r\n"));
+    assert!(!received.contains("keep-alive"));
+}
+
+// ============================================================================
+// 配置
+// ============================================================================
+
+#[test]
+fn test_decoy_config_parse_and_validate() {
+    let config = Config::from_json(
+        r#"{
+            "server": {"listen": "0.0.0.0", "port": 443},
+            "users": [],
+            "decoy": {"mode": "redirect", "redirect_url": "https://example.com"}
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(config.decoy.mode, DecoyMode::Redirect);
+    assert!(config.decoy.validate().is_ok());
+
+    let default = DecoyConfig::default();
+    assert_eq!(default.mode, DecoyMode::Panel);
+    assert!(default.validate().is_ok());
+
+    let missing_root = DecoyConfig {
+        mode: DecoyMode::Static,
+        ..Default::default()
+    };
+    assert!(missing_root.validate().is_err());
+
+    let bad_status = DecoyConfig {
+        mode: DecoyMode::Reject,
+        status: Some(99),
+        ..Default::default()
+    };
+    assert!(bad_status.validate().is_err());
+
+    let bad_route = DecoyConfig {
+        routes: vec![route("app", None, "127.0.0.1:3000")],
+        ..Default::default()
+    };
+    assert!(bad_route.validate().is_err());
+}
+
+// ============================================================================
+// 端到端响应
+// ============================================================================
+
+#[tokio::test]
+async fn test_serve_static_site() {
+    let dir = TempDir::new().unwrap();
+    std::fs::write(dir.path().join("index.html"), "<h1>hello</h1>").unwrap();
+    std::fs::write(dir.path().join("404.html"), "missing").unwrap();
+    let config = DecoyConfig {
+        mode: DecoyMode::Static,
+        root: Some(dir.path().to_string_lossy().into_owned()),
+        ..Default::default()
+    };
+
+    let ok = roundtrip(config.clone(), b"GET / HTTP/1.1\r\nHost: x\r\n\r\n").await;
+    let ok = String::from_utf8(ok).unwrap();
+    assert!(ok.starts_with("HTTP/1.1 200 OK\r\n"));
+    assert!(ok.contains("text/html"));
+    assert!(ok.ends_with("<h1>hello</h1>"));
+
+    let missing = roundtrip(config.clone(), b"GET /nope HTTP/1.1\r\n\r\n").await;
+    let missing = String::from_utf8(missing).unwrap();
+    assert!(missing.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    assert!(missing.ends_with("missing"));
+
+    let head = roundtrip(config, b"HEAD / HTTP/1.1\r\n\r\n").await;
+    let head = String::from_utf8(head).unwrap();
+    assert!(head.contains("Content-Length: 14\r\n"));
+    assert!(head.ends_with("\r\n\r\n"));
+}
+
+#[tokio::test]
+async fn test_serve_reject_444_closes_silently() {
+    let config = DecoyConfig {
+        mode: DecoyMode::Reject,
+        status: Some(444),
+        ..Default::default()
+    };
+    let response = roundtrip(config, b"GET / HTTP/1.1\r\n\r\n").await;
+    assert!(response.is_empty());
+}
+
+#[tokio::test]
+async fn test_serve_proxy_forwards_request() {
+    let backend = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let backend_addr = backend.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut stream, _) = backend.accept().await.unwrap();
+        let mut buf = [0u8; 1024];
+        let n = stream.read(&mut buf).await.unwrap();
+        assert!(buf[..n].starts_with(b"GET /app HTTP/1.1"));
+        stream
+            .write_all(b"HTTP/1.1 200 OK\r\nContent-Length: 2\r\n\r\nhi")
+            .await
+            .unwrap();
+    });
+
+    let config = DecoyConfig {
+        mode: DecoyMode::Proxy,
+        backend: Some(backend_addr.to_string()),
+        ..Default::default()
+    };
+    let response = roundtrip(config, b"GET /app HTTP/1.1\r\n\r\n").await;
+    assert!(response.ends_with(b"\r\n\r\nhi"));
+}
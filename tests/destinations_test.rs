@@ -0,0 +1,387 @@
+//! 目标地址健康统计测试
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use vless_rust::api::{authorize, handle_admin_request, is_admin_request, AdminConfig};
+use vless_rust::blocklist::Blocklist;
+use vless_rust::config::{BlocklistConfig, Config, MonitoringConfig};
+use vless_rust::config_diff::ReloadPreview;
+use vless_rust::destinations::DestinationTracker;
+
+// ============================================================================
+// 统计与判定
+// ============================================================================
+
+#[test]
+fn test_healthy_destination_is_not_problem() {
+    let tracker = DestinationTracker::default();
+    for _ in 0..5 {
+        tracker.record_success("example.com:443", Duration::from_millis(40));
+    }
+
+    let reports = tracker.reports();
+    assert_eq!(reports.len(), 1);
+    assert_eq!(reports[0].samples, 5);
+    assert_eq!(reports[0].failures, 0);
+    assert_eq!(reports[0].avg_connect_ms, Some(40));
+    assert!(tracker.problems().is_empty());
+}
+
+#[test]
+fn test_slow_and_failing_destinations_are_problems() {
+    let tracker = DestinationTracker::default();
+    for _ in 0..3 {
+        tracker.record_success("slow.example:443", Duration::from_millis(1500));
+    }
+    tracker.record_success("flaky.example:443", Duration::from_millis(30));
+    tracker.record_failure("flaky.example:443", "connection refused");
+    tracker.record_failure("flaky.example:443", "connection refused");
+
+    let problems = tracker.problems();
+    assert_eq!(problems.len(), 2);
+    // 失败率高的排在前面
+    assert_eq!(problems[0].dest, "flaky.example:443");
+    assert_eq!(problems[0].failures, 2);
+    assert_eq!(
+        problems[0].last_error.as_deref(),
+        Some("connection refused")
+    );
+    assert_eq!(problems[0].avg_connect_ms, Some(30));
+    assert_eq!(problems[1].dest, "slow.example:443");
+    assert_eq!(problems[1].max_connect_ms, Some(1500));
+}
+
+#[test]
+fn test_too_few_samples_are_not_judged() {
+    let tracker = DestinationTracker::default();
+    tracker.record_failure("down.example:80", "timed out");
+    tracker.record_failure("down.example:80", "timed out");
+
+    let reports = tracker.reports();
+    assert_eq!(reports[0].failure_rate, 1.0);
+    assert_eq!(reports[0].avg_connect_ms, None);
+    assert!(tracker.problems().is_empty());
+}
+
+#[test]
+fn test_rolling_window_forgets_old_failures() {
+    let tracker = DestinationTracker::default();
+    for _ in 0..10 {
+        tracker.record_failure("recovered.example:443", "timed out");
+    }
+    assert_eq!(tracker.problems().len(), 1);
+
+    for _ in 0..50 {
+        tracker.record_success("recovered.example:443", Duration::from_millis(20));
+    }
+    let reports = tracker.reports();
+    assert_eq!(reports[0].samples, 50);
+    assert_eq!(reports[0].failures, 0);
+    assert!(tracker.problems().is_empty());
+}
+
+#[test]
+fn test_tracked_destinations_are_bounded() {
+    let tracker = DestinationTracker::default();
+    for i in 0..3000 {
+        tracker.record_success(&format!("host{}.example:443", i), Duration::from_millis(10));
+    }
+    assert_eq!(tracker.tracked_count(), 2048);
+}
+
+#[test]
+fn test_monitoring_limits_are_applied() {
+    let config = MonitoringConfig {
+        destination_window: 5,
+        max_destinations: 2,
+        destination_sample_every: 3,
+        ..Default::default()
+    };
+    let tracker = DestinationTracker::with_config(&config);
+    for i in 0..3 {
+        tracker.record_success(&format!("host{}.example:443", i), Duration::from_millis(10));
+    }
+    // 第 1 次成功被记录，第 2、3 次被采样跳过
+    assert_eq!(tracker.tracked_count(), 1);
+
+    for _ in 0..30 {
+        tracker.record_success("busy.example:443", Duration::from_millis(10));
+    }
+    for _ in 0..10 {
+        tracker.record_failure("busy.example:443", "timed out");
+    }
+    let busy = tracker
+        .reports()
+        .into_iter()
+        .find(|r| r.dest == "busy.example:443")
+        .unwrap();
+    // 失败不参与采样，窗口只保留最近 5 个样本
+    assert_eq!(busy.samples, 5);
+    assert_eq!(busy.failures, 5);
+    assert_eq!(tracker.tracked_count(), 2);
+}
+
+#[test]
+fn test_monitoring_config_validation() {
+    assert!(MonitoringConfig::default().validate().is_ok());
+
+    let config = Config::from_json(
+        r#"{"server": {"listen": "0.0.0.0", "port": 443}, "users": [],
+            "monitoring": {"max_destinations": 1000000}}"#,
+    )
+    .unwrap();
+    let err = config.validate().unwrap_err();
+    assert!(err.to_string().contains("monitoring.max_destinations"));
+
+    let config = MonitoringConfig {
+        destination_sample_every: 0,
+        ..Default::default()
+    };
+    assert!(config.validate().is_err());
+}
+
+// ============================================================================
+// 管理 API
+// ============================================================================
+
+#[test]
+fn test_admin_request_detection_and_authorization() {
+    let request =
+        b"GET /api/destinations HTTP/1.1\r\nHost: x\r\nAuthorization: Bearer s3cret\r\n\r\n";
+    assert!(is_admin_request(request));
+    assert!(!is_admin_request(
+        b"GET /?email=a HTTP/1.1\r\nHost: x\r\n\r\n"
+    ));
+
+    assert!(authorize(request, "s3cret"));
+    assert!(!authorize(request, "s3cret2"));
+    assert!(!authorize(request, "other!"));
+    assert!(!authorize(
+        b"GET /api/destinations HTTP/1.1\r\nHost: x\r\n\r\n",
+        "s3cret"
+    ));
+}
+
+#[test]
+fn test_api_token_config_parse() {
+    let config = Config::from_json(
+        r#"{
+            "server": {"listen": "0.0.0.0", "port": 443},
+            "users": [],
+            "api": {"token": "s3cret"}
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(config.api.token.as_deref(), Some("s3cret"));
+
+    let config =
+        Config::from_json(r#"{"server": {"listen": "0.0.0.0", "port": 443}, "users": []}"#)
+            .unwrap();
+    assert!(config.api.token.is_none());
+}
+
+async fn admin_roundtrip(config: AdminConfig, request: &'static [u8]) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 1024];
+        let n = stream.read(&mut buf).await.unwrap();
+        let _ = handle_admin_request(stream, &buf[..n], &config).await;
+    });
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client.write_all(request).await.unwrap();
+    let mut response = String::new();
+    client.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+/// 解析错误响应体（`{success, code, message, details}`）
+fn error_body(response: &str) -> serde_json::Value {
+    serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn test_admin_destinations_endpoint() {
+    let tracker = Arc::new(DestinationTracker::default());
+    for _ in 0..3 {
+        tracker.record_failure("down.example:443", "connection refused");
+    }
+    tracker.record_success("ok.example:443", Duration::from_millis(10));
+
+    let config = || AdminConfig {
+        token: "s3cret".to_string(),
+        destinations: Some(Arc::clone(&tracker)),
+        user_stats: None,
+        user_links: Vec::new(),
+        blocklist: None,
+        capture: None,
+        reload_preview: None,
+        overhead: Default::default(),
+        listener_tasks: Default::default(),
+        resolver: Default::default(),
+        route_stats: Default::default(),
+        gate: Default::default(),
+        maintenance: Default::default(),
+    };
+
+    let response = admin_roundtrip(
+        config(),
+        b"GET /api/destinations HTTP/1.1\r\nAuthorization: Bearer s3cret\r\n\r\n",
+    )
+    .await;
+    assert!(response.starts_with("HTTP/1.1 200"));
+    let body: serde_json::Value =
+        serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+    assert_eq!(body["tracked"], 2);
+    assert_eq!(body["problem_destinations"][0]["dest"], "down.example:443");
+    assert!(body.get("destinations").is_none());
+
+    let response = admin_roundtrip(
+        config(),
+        b"GET /api/destinations?all=1 HTTP/1.1\r\nAuthorization: Bearer s3cret\r\n\r\n",
+    )
+    .await;
+    let body: serde_json::Value =
+        serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+    assert_eq!(body["destinations"].as_array().unwrap().len(), 2);
+
+    let response = admin_roundtrip(config(), b"GET /api/destinations HTTP/1.1\r\n\r\n").await;
+    assert!(response.starts_with("HTTP/1.1 401"));
+    assert_eq!(error_body(&response)["code"], "unauthorized");
+
+    let response = admin_roundtrip(
+        config(),
+        b"GET /api/unknown HTTP/1.1\r\nAuthorization: Bearer s3cret\r\n\r\n",
+    )
+    .await;
+    assert!(response.starts_with("HTTP/1.1 404"));
+    assert_eq!(error_body(&response)["code"], "not_found");
+
+    let response = admin_roundtrip(
+        config(),
+        b"GET /api/users?sort=bogus HTTP/1.1\r\nAuthorization: Bearer s3cret\r\n\r\n",
+    )
+    .await;
+    assert!(response.starts_with("HTTP/1.1 400"));
+    let body = error_body(&response);
+    assert_eq!(body["code"], "invalid_parameter");
+    assert_eq!(body["message"], "Invalid sort");
+}
+
+#[tokio::test]
+async fn test_admin_version_and_reload_endpoints() {
+    let list = Arc::new(Blocklist::new(
+        &BlocklistConfig {
+            domains: vec!["ads.example".to_string()],
+            ..Default::default()
+        },
+        HashSet::new(),
+    ));
+    let config = || AdminConfig {
+        token: "s3cret".to_string(),
+        destinations: None,
+        user_stats: None,
+        user_links: Vec::new(),
+        blocklist: Some(Arc::clone(&list)),
+        capture: None,
+        reload_preview: None,
+        overhead: Default::default(),
+        listener_tasks: Default::default(),
+        resolver: Default::default(),
+        route_stats: Default::default(),
+        gate: Default::default(),
+        maintenance: Default::default(),
+    };
+
+    let response = admin_roundtrip(
+        config(),
+        b"GET /api/version HTTP/1.1\r\nAuthorization: Bearer s3cret\r\n\r\n",
+    )
+    .await;
+    let body: serde_json::Value =
+        serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+    assert!(body["version"].as_str().is_some());
+    assert_eq!(body["data"]["blocklist"]["version"], 1);
+    assert_eq!(body["data"]["blocklist"]["domains"], 1);
+
+    // 重新加载需要 POST
+    let response = admin_roundtrip(
+        config(),
+        b"GET /api/reload HTTP/1.1\r\nAuthorization: Bearer s3cret\r\n\r\n",
+    )
+    .await;
+    assert!(response.starts_with("HTTP/1.1 405"));
+    let body = error_body(&response);
+    assert_eq!(body["code"], "method_not_allowed");
+    assert_eq!(body["details"]["allowed"][0], "POST");
+
+    let response = admin_roundtrip(
+        config(),
+        b"POST /api/reload HTTP/1.1\r\nAuthorization: Bearer s3cret\r\n\r\n",
+    )
+    .await;
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert!(response.contains(r#""reloading":["blocklist"]"#));
+}
+
+#[tokio::test]
+async fn test_admin_reload_dry_run() {
+    let running = Config::from_json(
+        r#"{"server": {"listen": "0.0.0.0", "port": 443},
+            "users": [{"uuid": "11111111-1111-1111-1111-111111111111", "email": "alice"}]}"#,
+    )
+    .unwrap();
+    let file = tempfile::NamedTempFile::new().unwrap();
+    std::fs::write(
+        file.path(),
+        r#"{"server": {"listen": "0.0.0.0", "port": 8443}, "users": []}"#,
+    )
+    .unwrap();
+    let preview = Arc::new(ReloadPreview::new(file.path().to_str().unwrap(), running));
+    let config = || AdminConfig {
+        token: "s3cret".to_string(),
+        destinations: None,
+        user_stats: None,
+        user_links: Vec::new(),
+        blocklist: None,
+        capture: None,
+        reload_preview: Some(Arc::clone(&preview)),
+        overhead: Default::default(),
+        listener_tasks: Default::default(),
+        resolver: Default::default(),
+        route_stats: Default::default(),
+        gate: Default::default(),
+        maintenance: Default::default(),
+    };
+
+    let response = admin_roundtrip(
+        config(),
+        b"POST /api/reload?dry_run=true HTTP/1.1\r\nAuthorization: Bearer s3cret\r\n\r\n",
+    )
+    .await;
+    let body: serde_json::Value =
+        serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+    assert_eq!(body["dry_run"], true);
+    assert_eq!(body["restart_required"], true);
+    assert_eq!(body["diff"]["users_removed"][0]["email"], "alice");
+    assert_eq!(body["diff"]["changed"][0], "server.port");
+
+    // 新配置无效时返回 400，不做任何修改
+    std::fs::write(file.path(), "{ not json").unwrap();
+    let response = admin_roundtrip(
+        config(),
+        b"POST /api/reload?dry_run=1 HTTP/1.1\r\nAuthorization: Bearer s3cret\r\n\r\n",
+    )
+    .await;
+    assert!(response.starts_with("HTTP/1.1 400"));
+    let body = error_body(&response);
+    assert_eq!(body["success"], false);
+    assert_eq!(body["code"], "config_invalid");
+    assert!(body["message"].as_str().is_some());
+}
@@ -0,0 +1,94 @@
+//! 自检诊断模块测试
+
+use tempfile::TempDir;
+use tokio::net::TcpListener;
+use vless_rust::doctor::{
+    check_config, check_port, evaluate_clock_skew, evaluate_somaxconn, print_report, CheckStatus,
+};
+
+#[test]
+fn test_check_config_missing_file() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("missing.json");
+
+    let (result, config) = check_config(path.to_str().unwrap());
+
+    assert_eq!(result.status, CheckStatus::Fail);
+    assert!(result.fix.is_some());
+    assert!(config.is_none());
+}
+
+#[test]
+fn test_check_config_valid() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("config.json");
+    std::fs::write(
+        &path,
+        r#"{
+            "server": { "listen": "127.0.0.1", "port": 8443 },
+            "users": [{ "uuid": "550e8400-e29b-41d4-a716-446655440000", "email": "a@b.com" }]
+        }"#,
+    )
+    .unwrap();
+
+    let (result, config) = check_config(path.to_str().unwrap());
+
+    assert_eq!(result.status, CheckStatus::Pass);
+    assert_eq!(config.unwrap().server.port, 8443);
+}
+
+#[test]
+fn test_check_config_no_users_warns() {
+    let temp_dir = TempDir::new().unwrap();
+    let path = temp_dir.path().join("config.json");
+    std::fs::write(
+        &path,
+        r#"{ "server": { "listen": "127.0.0.1", "port": 8443 }, "users": [] }"#,
+    )
+    .unwrap();
+
+    let (result, _) = check_config(path.to_str().unwrap());
+
+    assert_eq!(result.status, CheckStatus::Warn);
+}
+
+#[tokio::test]
+async fn test_check_port_free() {
+    let result = check_port("127.0.0.1:0".parse().unwrap()).await;
+    assert_eq!(result.status, CheckStatus::Pass);
+}
+
+#[tokio::test]
+async fn test_check_port_in_use() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let result = check_port(addr).await;
+
+    assert_eq!(result.status, CheckStatus::Fail);
+    assert!(result.fix.unwrap().contains("already in use"));
+}
+
+#[test]
+fn test_evaluate_somaxconn() {
+    assert_eq!(evaluate_somaxconn(128).status, CheckStatus::Warn);
+    assert_eq!(evaluate_somaxconn(4096).status, CheckStatus::Pass);
+}
+
+#[test]
+fn test_evaluate_clock_skew() {
+    assert_eq!(evaluate_clock_skew(0).status, CheckStatus::Pass);
+    assert_eq!(evaluate_clock_skew(-300).status, CheckStatus::Warn);
+    assert_eq!(evaluate_clock_skew(300).status, CheckStatus::Warn);
+}
+
+#[test]
+fn test_print_report_exit_status() {
+    let pass = evaluate_somaxconn(4096);
+    let warn = evaluate_somaxconn(128);
+    assert!(print_report(&[pass.clone(), warn]));
+
+    let temp_dir = TempDir::new().unwrap();
+    let (fail, _) = check_config(temp_dir.path().join("x.json").to_str().unwrap());
+    assert!(!print_report(&[pass, fail]));
+}
@@ -0,0 +1,148 @@
+//! 事件总线测试
+
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::AsyncWriteExt;
+use tokio::net::{TcpListener, TcpStream};
+use uuid::Uuid;
+use vless_rust::blocklist::Blocklist;
+use vless_rust::config::{BlocklistConfig, PerformanceConfig};
+use vless_rust::events::{Event, EventBus};
+use vless_rust::session::SessionServices;
+use vless_rust::tcp::handle_tcp_connection;
+
+/// 构造 VLESS TCP 请求头（域名目标）
+fn vless_header(uuid: Uuid, domain: &str, port: u16) -> Vec<u8> {
+    let mut header = vec![0u8];
+    header.extend_from_slice(uuid.as_bytes());
+    header.push(0); // addons length
+    header.push(1); // TCP
+    header.extend_from_slice(&port.to_be_bytes());
+    header.push(2); // domain
+    header.push(domain.len() as u8);
+    header.extend_from_slice(domain.as_bytes());
+    header
+}
+
+/// 以给定用户与服务处理一次 VLESS 连接
+async fn run_connection(users: HashSet<Uuid>, services: SessionServices, header: Vec<u8>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    let server = tokio::spawn(async move {
+        let (stream, client_addr): (TcpStream, SocketAddr) = listener.accept().await.unwrap();
+        let _ = handle_tcp_connection(
+            stream,
+            client_addr,
+            PerformanceConfig::default(),
+            &users,
+            services,
+            |_| async { None },
+        )
+        .await;
+    });
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client.write_all(&header).await.unwrap();
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .unwrap()
+        .unwrap();
+}
+
+// ============================================================================
+// 总线
+// ============================================================================
+
+#[tokio::test]
+async fn test_event_bus_fans_out_to_all_subscribers() {
+    let bus = EventBus::new();
+    let mut first = bus.subscribe();
+    let mut second = bus.clone().subscribe();
+    assert_eq!(bus.subscriber_count(), 2);
+
+    bus.publish(Event::DestinationBlocked {
+        user: "alice".to_string(),
+        dest: "ads.example:443".to_string(),
+    });
+
+    for rx in [&mut first, &mut second] {
+        match rx.recv().await.unwrap() {
+            Event::DestinationBlocked { dest, .. } => assert_eq!(dest, "ads.example:443"),
+            other => panic!("unexpected event: {:?}", other),
+        }
+    }
+}
+
+#[test]
+fn test_publish_without_subscribers_is_noop() {
+    let bus = EventBus::default();
+    assert_eq!(bus.subscriber_count(), 0);
+    bus.publish(Event::DestinationBlocked {
+        user: "alice".to_string(),
+        dest: "ads.example:443".to_string(),
+    });
+}
+
+// ============================================================================
+// 代理路径发布的事件
+// ============================================================================
+
+#[tokio::test]
+async fn test_auth_failure_is_published() {
+    let bus = EventBus::new();
+    let mut rx = bus.subscribe();
+    let services = SessionServices {
+        events: bus.clone(),
+        ..Default::default()
+    };
+
+    let stranger = Uuid::new_v4();
+    run_connection(
+        HashSet::new(),
+        services,
+        vless_header(stranger, "example.com", 443),
+    )
+    .await;
+
+    match rx.recv().await.unwrap() {
+        Event::AuthFailed { uuid, .. } => assert_eq!(uuid, stranger),
+        other => panic!("unexpected event: {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn test_blocked_destination_is_published() {
+    let bus = EventBus::new();
+    let mut rx = bus.subscribe();
+    let blocklist = Blocklist::new(
+        &BlocklistConfig {
+            domains: vec!["ads.example".to_string()],
+            ..Default::default()
+        },
+        HashSet::new(),
+    );
+    let services = SessionServices {
+        blocklist: Some(Arc::new(blocklist)),
+        events: bus.clone(),
+        ..Default::default()
+    };
+
+    let user = Uuid::new_v4();
+    run_connection(
+        HashSet::from([user]),
+        services,
+        vless_header(user, "tracker.ads.example", 443),
+    )
+    .await;
+
+    match rx.recv().await.unwrap() {
+        Event::DestinationBlocked { user: label, dest } => {
+            assert_eq!(label, user.to_string());
+            assert_eq!(dest, "tracker.ads.example:443");
+        }
+        other => panic!("unexpected event: {:?}", other),
+    }
+}
@@ -0,0 +1,164 @@
+//! 握手失败回落测试
+
+use std::collections::HashSet;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use uuid::Uuid;
+use vless_rust::config::{Config, FallbackRule, PerformanceConfig};
+use vless_rust::fallback::Fallback;
+use vless_rust::session::SessionServices;
+use vless_rust::tcp::handle_tcp_connection;
+
+fn rule(dest: &str, path: Option<&str>) -> FallbackRule {
+    FallbackRule {
+        dest: dest.to_string(),
+        path: path.map(str::to_string),
+    }
+}
+
+/// 启动记录首段数据并回复固定内容的后端
+async fn spawn_backend(reply: &'static [u8]) -> (String, tokio::sync::oneshot::Receiver<Vec<u8>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap().to_string();
+    let (tx, rx) = tokio::sync::oneshot::channel();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = vec![0u8; 1024];
+        let n = stream.read(&mut buf).await.unwrap();
+        buf.truncate(n);
+        let _ = tx.send(buf);
+        stream.write_all(reply).await.unwrap();
+    });
+    (addr, rx)
+}
+
+// ============================================================================
+// 规则匹配
+// ============================================================================
+
+#[test]
+fn test_select_by_path_then_default() {
+    let fallback = Fallback::new(vec![
+        rule("127.0.0.1:8081", Some("/blog")),
+        rule("127.0.0.1:8080", None),
+    ]);
+    assert_eq!(
+        fallback.select(b"GET /blog/post HTTP/1.1\r\n\r\n"),
+        Some("127.0.0.1:8081")
+    );
+    assert_eq!(
+        fallback.select(b"GET /blogger HTTP/1.1\r\n\r\n"),
+        Some("127.0.0.1:8080")
+    );
+    assert_eq!(
+        fallback.select(b"\x16\x03\x01\x02\x00"),
+        Some("127.0.0.1:8080")
+    );
+
+    let path_only = Fallback::new(vec![rule("127.0.0.1:8081", Some("/blog"))]);
+    assert_eq!(path_only.select(b"\x00garbage"), None);
+}
+
+#[test]
+fn test_fallback_config() {
+    let config = Config::from_json(
+        r#"{"server": {"listen": "0.0.0.0", "port": 443}, "users": [],
+            "fallbacks": [{"dest": "127.0.0.1:8080"}, {"dest": "127.0.0.1:8081", "path": "/ws"}]}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        config.fallbacks,
+        vec![
+            rule("127.0.0.1:8080", None),
+            rule("127.0.0.1:8081", Some("/ws"))
+        ]
+    );
+    assert!(config.validate().is_ok());
+    assert!(!config.to_json().unwrap().contains("\"path\": null"));
+
+    let bad_dest = Config::from_json(
+        r#"{"server": {"listen": "0.0.0.0", "port": 443}, "users": [],
+            "fallbacks": [{"dest": "8080"}]}"#,
+    )
+    .unwrap();
+    let err = bad_dest.validate().unwrap_err().to_string();
+    assert!(err.contains("fallbacks[0].dest"), "{}", err);
+
+    let bad_path = Config::from_json(
+        r#"{"server": {"listen": "0.0.0.0", "port": 443}, "users": [],
+            "fallbacks": [{"dest": "127.0.0.1:80", "path": "ws"}]}"#,
+    )
+    .unwrap();
+    assert!(bad_path.validate().is_err());
+}
+
+// ============================================================================
+// 转发
+// ============================================================================
+
+#[tokio::test]
+async fn test_invalid_uuid_falls_back_with_consumed_bytes() {
+    let (dest, received) = spawn_backend(b"HTTP/1.1 200 OK\r\n\r\nhello").await;
+    let services = SessionServices {
+        fallback: Some(Arc::new(Fallback::new(vec![rule(&dest, None)]))),
+        ..Default::default()
+    };
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (stream, client_addr) = listener.accept().await.unwrap();
+        let users: HashSet<Uuid> = [Uuid::new_v4()].into_iter().collect();
+        let _ = handle_tcp_connection(
+            stream,
+            client_addr,
+            PerformanceConfig::default(),
+            &users,
+            services,
+            |_| async { None },
+        )
+        .await;
+    });
+
+    // 未知 UUID 的 VLESS 请求头
+    let mut probe = vec![0u8];
+    probe.extend_from_slice(Uuid::new_v4().as_bytes());
+    probe.extend_from_slice(&[0, 1, 0, 80, 1, 127, 0, 0, 1]);
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client.write_all(&probe).await.unwrap();
+    let mut response = Vec::new();
+    tokio::time::timeout(Duration::from_secs(5), client.read_to_end(&mut response))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(response, b"HTTP/1.1 200 OK\r\n\r\nhello");
+    assert_eq!(received.await.unwrap(), probe);
+}
+
+#[tokio::test]
+async fn test_unread_stream_is_forwarded() {
+    let (dest, received) = spawn_backend(b"pong").await;
+    let fallback = Fallback::new(vec![rule(&dest, None)]);
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        let mut head = [0u8; 4];
+        stream.peek(&mut head).await.unwrap();
+        let _ = fallback.serve(stream, &[]).await;
+    });
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client.write_all(b"ping").await.unwrap();
+    let mut response = Vec::new();
+    tokio::time::timeout(Duration::from_secs(5), client.read_to_end(&mut response))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(response, b"pong");
+    assert_eq!(received.await.unwrap(), b"ping");
+}
@@ -0,0 +1,234 @@
+//! 端口转发入站测试
+
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use vless_rust::config::{Config, ForwardConfig, ForwardNetwork, PerformanceConfig};
+use vless_rust::destinations::DestinationTracker;
+use vless_rust::events::{Event, EventBus};
+use vless_rust::forward::{parse_dest, Forwarder};
+use vless_rust::protocol::Address;
+use vless_rust::session::SessionServices;
+
+fn forward_config(dest: String, network: ForwardNetwork) -> ForwardConfig {
+    ForwardConfig {
+        name: Some("echo".to_string()),
+        listen: "127.0.0.1:0".to_string(),
+        dest,
+        network,
+    }
+}
+
+/// TCP 回显目标
+async fn tcp_echo() -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move {
+                let mut buf = [0u8; 64];
+                let n = stream.read(&mut buf).await.unwrap();
+                stream.write_all(&buf[..n]).await.unwrap();
+            });
+        }
+    });
+    addr.to_string()
+}
+
+/// UDP 回显目标
+async fn udp_echo() -> String {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let addr = socket.local_addr().unwrap();
+    tokio::spawn(async move {
+        let mut buf = [0u8; 64];
+        loop {
+            let (n, peer) = socket.recv_from(&mut buf).await.unwrap();
+            socket.send_to(&buf[..n], peer).await.unwrap();
+        }
+    });
+    addr.to_string()
+}
+
+// ============================================================================
+// 配置
+// ============================================================================
+
+#[test]
+fn test_forward_config_parse() {
+    let config = Config::from_json(
+        r#"{
+            "server": {"listen": "0.0.0.0", "port": 443},
+            "users": [],
+            "forwards": [
+                {"listen": "0.0.0.0:5432", "dest": "db.internal:5432"},
+                {"name": "dns", "listen": "0.0.0.0:53", "dest": "10.0.0.1:53", "network": "both"}
+            ]
+        }"#,
+    )
+    .unwrap();
+    assert_eq!(config.forwards[0].network, ForwardNetwork::Tcp);
+    assert_eq!(config.forwards[1].network, ForwardNetwork::Both);
+    assert!(config.forwards[1].network.tcp() && config.forwards[1].network.udp());
+    assert!(!ForwardNetwork::Udp.tcp());
+}
+
+#[test]
+fn test_parse_dest() {
+    let (address, port) = parse_dest("10.0.0.5:80").unwrap();
+    assert_eq!(address, Address::Ipv4("10.0.0.5".parse().unwrap()));
+    assert_eq!(port, 80);
+
+    let (address, port) = parse_dest("[::1]:8080").unwrap();
+    assert_eq!(address, Address::Ipv6("::1".parse().unwrap()));
+    assert_eq!(port, 8080);
+
+    let (address, _) = parse_dest("db.internal:5432").unwrap();
+    assert_eq!(address, Address::Domain("db.internal".into()));
+
+    assert!(parse_dest("db.internal").is_err());
+    assert!(parse_dest("db.internal:0").is_err());
+    assert!(parse_dest(":80").is_err());
+    assert!(parse_dest("::1:80").is_err());
+}
+
+#[tokio::test]
+async fn test_bind_rejects_invalid_listen() {
+    let mut config = forward_config("127.0.0.1:80".to_string(), ForwardNetwork::Tcp);
+    config.listen = "localhost:80".to_string();
+    let result = Forwarder::bind(
+        &config,
+        SessionServices::default(),
+        PerformanceConfig::default(),
+    )
+    .await;
+    assert!(result.is_err());
+}
+
+// ============================================================================
+// 转发
+// ============================================================================
+
+#[tokio::test]
+async fn test_tcp_forward_records_session() {
+    let bus = EventBus::new();
+    let mut rx = bus.subscribe();
+    let destinations = Arc::new(DestinationTracker::default());
+    let services = SessionServices {
+        events: bus.clone(),
+        destinations: Some(Arc::clone(&destinations)),
+        ..Default::default()
+    };
+
+    let dest = tcp_echo().await;
+    let forwarder = Forwarder::bind(
+        &forward_config(dest.clone(), ForwardNetwork::Tcp),
+        services,
+        PerformanceConfig::default(),
+    )
+    .await
+    .unwrap();
+    assert!(forwarder.udp_addr().is_none());
+    let addr = forwarder.tcp_addr().unwrap();
+    let uuid = forwarder.uuid();
+    forwarder.spawn();
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client.write_all(b"hello").await.unwrap();
+    let mut response = Vec::new();
+    tokio::time::timeout(Duration::from_secs(5), client.read_to_end(&mut response))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(response, b"hello");
+    drop(client);
+
+    let record = loop {
+        match tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap()
+        {
+            Event::SessionClosed(record) => break record,
+            _ => continue,
+        }
+    };
+    assert_eq!(record.uuid, uuid);
+    assert_eq!(record.user, "forward:echo");
+    assert_eq!(record.network, "tcp");
+    assert_eq!(record.dest, dest);
+    assert_eq!(record.bytes_up, 5);
+    assert_eq!(record.bytes_down, 5);
+    assert_eq!(destinations.tracked_count(), 1);
+}
+
+#[tokio::test]
+async fn test_udp_forward_round_trip() {
+    let dest = udp_echo().await;
+    let forwarder = Forwarder::bind(
+        &forward_config(dest, ForwardNetwork::Both),
+        SessionServices::default(),
+        PerformanceConfig::default(),
+    )
+    .await
+    .unwrap();
+    // 同一端口同时监听 TCP 与 UDP
+    let addr = forwarder.udp_addr().unwrap();
+    assert_eq!(forwarder.tcp_addr().unwrap(), addr);
+    forwarder.spawn();
+
+    let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let mut buf = [0u8; 64];
+    for payload in [&b"first"[..], &b"second"[..]] {
+        client.send_to(payload, addr).await.unwrap();
+        let (n, from) = tokio::time::timeout(Duration::from_secs(5), client.recv_from(&mut buf))
+            .await
+            .unwrap()
+            .unwrap();
+        assert_eq!(from, addr);
+        assert_eq!(&buf[..n], payload);
+    }
+}
+
+#[tokio::test]
+async fn test_udp_session_closes_when_idle() {
+    let bus = EventBus::new();
+    let mut rx = bus.subscribe();
+    let services = SessionServices {
+        events: bus.clone(),
+        ..Default::default()
+    };
+    let perf_config = PerformanceConfig {
+        udp_timeout: 1,
+        ..Default::default()
+    };
+
+    let dest = udp_echo().await;
+    let forwarder = Forwarder::bind(
+        &forward_config(dest, ForwardNetwork::Udp),
+        services,
+        perf_config,
+    )
+    .await
+    .unwrap();
+    let addr = forwarder.udp_addr().unwrap();
+    forwarder.spawn();
+
+    let client = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    client.send_to(b"ping", addr).await.unwrap();
+
+    let record = loop {
+        match tokio::time::timeout(Duration::from_secs(5), rx.recv())
+            .await
+            .unwrap()
+            .unwrap()
+        {
+            Event::SessionClosed(record) => break record,
+            _ => continue,
+        }
+    };
+    assert_eq!(record.network, "udp");
+    assert_eq!(record.bytes_up, 4);
+    assert_eq!(record.bytes_down, 4);
+}
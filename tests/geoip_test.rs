@@ -0,0 +1,268 @@
+//! GeoIP 测试：MaxMind DB 解析、国家查询、保留地址与路由规则中的 `geoip:` 条件
+
+use bytes::Bytes;
+use std::net::IpAddr;
+use uuid::Uuid;
+use vless_rust::config::Config;
+use vless_rust::geoip::{is_private, GeoIpDb};
+use vless_rust::protocol::Address;
+use vless_rust::router::{RouteAction, Router};
+
+#[derive(Clone, Copy)]
+enum Record {
+    Empty,
+    Node(usize),
+    Data(usize),
+}
+
+/// 构造测试用 MaxMind DB：IPv6 搜索树（IPv4 位于 `::/96`），记录长度 24 位
+struct MmdbBuilder {
+    nodes: Vec<[Record; 2]>,
+    data: Vec<u8>,
+    /// 第一个 `iso_code` 键的偏移，供指针引用
+    iso_code_key: Option<usize>,
+}
+
+fn push_string(out: &mut Vec<u8>, s: &str) {
+    out.push(0x40 | s.len() as u8);
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn push_map(out: &mut Vec<u8>, pairs: u8) {
+    out.push(0xe0 | pairs);
+}
+
+fn push_uint(out: &mut Vec<u8>, kind: u8, value: u32) {
+    let bytes = value.to_be_bytes();
+    let skip = bytes.iter().take_while(|&&b| b == 0).count();
+    out.push((kind << 5) | (4 - skip) as u8);
+    out.extend_from_slice(&bytes[skip..]);
+}
+
+impl MmdbBuilder {
+    fn new() -> Self {
+        Self {
+            nodes: vec![[Record::Empty; 2]],
+            data: Vec::new(),
+            iso_code_key: None,
+        }
+    }
+
+    /// 写入 `{"<key>": {"iso_code": "<code>"}}`，第二次起 `iso_code` 键使用指针
+    fn record(&mut self, key: &str, code: &str) -> usize {
+        let offset = self.data.len();
+        push_map(&mut self.data, 1);
+        push_string(&mut self.data, key);
+        push_map(&mut self.data, 1);
+        match self.iso_code_key {
+            Some(pointer) => self.data.extend_from_slice(&[0x20, pointer as u8]),
+            None => {
+                self.iso_code_key = Some(self.data.len());
+                push_string(&mut self.data, "iso_code");
+            }
+        }
+        push_string(&mut self.data, code);
+        offset
+    }
+
+    fn insert(&mut self, network: &str, data: usize) {
+        let (ip, prefix) = network.split_once('/').unwrap();
+        let prefix: usize = prefix.parse().unwrap();
+        let (bits, prefix) = match ip.parse::<IpAddr>().unwrap() {
+            IpAddr::V4(v4) => (u32::from(v4) as u128, prefix + 96),
+            IpAddr::V6(v6) => (u128::from(v6), prefix),
+        };
+        let mut node = 0;
+        for i in 0..prefix {
+            let bit = ((bits >> (127 - i)) & 1) as usize;
+            if i == prefix - 1 {
+                self.nodes[node][bit] = Record::Data(data);
+                break;
+            }
+            node = match self.nodes[node][bit] {
+                Record::Node(next) => next,
+                _ => {
+                    self.nodes.push([Record::Empty; 2]);
+                    let next = self.nodes.len() - 1;
+                    self.nodes[node][bit] = Record::Node(next);
+                    next
+                }
+            };
+        }
+    }
+
+    fn build(self) -> Vec<u8> {
+        let node_count = self.nodes.len();
+        let mut out = Vec::new();
+        for node in &self.nodes {
+            for record in node {
+                let value = match *record {
+                    Record::Empty => node_count,
+                    Record::Node(next) => next,
+                    Record::Data(offset) => node_count + 16 + offset,
+                };
+                out.extend_from_slice(&(value as u32).to_be_bytes()[1..]);
+            }
+        }
+        out.extend_from_slice(&[0u8; 16]);
+        out.extend_from_slice(&self.data);
+        out.extend_from_slice(b"\xAB\xCD\xEFMaxMind.com");
+        push_map(&mut out, 4);
+        push_string(&mut out, "node_count");
+        push_uint(&mut out, 6, node_count as u32);
+        push_string(&mut out, "record_size");
+        push_uint(&mut out, 5, 24);
+        push_string(&mut out, "ip_version");
+        push_uint(&mut out, 5, 6);
+        push_string(&mut out, "database_type");
+        push_string(&mut out, "Test-Country");
+        out
+    }
+}
+
+fn sample_db() -> Vec<u8> {
+    let mut builder = MmdbBuilder::new();
+    let cn = builder.record("country", "CN");
+    let us = builder.record("country", "US");
+    let de = builder.record("registered_country", "DE");
+    builder.insert("1.0.0.0/24", cn);
+    builder.insert("8.8.8.0/24", us);
+    builder.insert("5.5.0.0/16", de);
+    builder.insert("2400:da00::/32", cn);
+    builder.build()
+}
+
+fn ip(addr: &str) -> IpAddr {
+    addr.parse().unwrap()
+}
+
+// ============================================================================
+// 数据库解析
+// ============================================================================
+
+#[test]
+fn test_country_lookup() {
+    let db = GeoIpDb::from_bytes(sample_db()).unwrap();
+    assert_eq!(db.database_type(), "Test-Country");
+    assert_eq!(db.country(ip("1.0.0.7")).as_deref(), Some("cn"));
+    assert_eq!(db.country(ip("8.8.8.8")).as_deref(), Some("us"));
+    assert_eq!(db.country(ip("2400:da00::1")).as_deref(), Some("cn"));
+    // IPv4 映射地址按 IPv4 查询
+    assert_eq!(db.country(ip("::ffff:1.0.0.1")).as_deref(), Some("cn"));
+    // 只有注册国家的记录（键经指针引用）
+    assert_eq!(db.country(ip("5.5.5.5")).as_deref(), Some("de"));
+    assert_eq!(db.country(ip("9.9.9.9")), None);
+    assert_eq!(db.country(ip("2001:4860::8888")), None);
+
+    let record = db.lookup(ip("8.8.8.8")).unwrap().unwrap();
+    assert_eq!(record["country"]["iso_code"], "US");
+}
+
+#[test]
+fn test_invalid_database() {
+    let err = GeoIpDb::from_bytes(vec![0u8; 64]).unwrap_err();
+    assert!(err.to_string().contains("metadata marker"), "{}", err);
+
+    // 元数据声明的搜索树超出文件
+    let mut data = sample_db();
+    let marker = data
+        .windows(14)
+        .rposition(|w| w == b"\xAB\xCD\xEFMaxMind.com")
+        .unwrap();
+    data.drain(..marker - 40);
+    assert!(GeoIpDb::from_bytes(data).is_err());
+}
+
+#[test]
+fn test_private_addresses() {
+    for private in [
+        "10.1.2.3",
+        "172.16.0.1",
+        "192.168.1.1",
+        "127.0.0.1",
+        "169.254.1.1",
+        "100.64.0.1",
+        "0.0.0.0",
+        "224.0.0.1",
+        "198.18.0.1",
+        "::1",
+        "fd00::1",
+        "fe80::1",
+        "ff02::1",
+        "::ffff:192.168.0.1",
+    ] {
+        assert!(is_private(ip(private)), "{}", private);
+    }
+    for public in ["1.1.1.1", "8.8.8.8", "100.128.0.1", "2606:4700::1111"] {
+        assert!(!is_private(ip(public)), "{}", public);
+    }
+}
+
+// ============================================================================
+// 路由规则
+// ============================================================================
+
+fn config(routing: &str) -> Config {
+    Config::from_json(&format!(
+        r#"{{"server": {{"listen": "0.0.0.0", "port": 443}}, "users": [], "routing": {}}}"#,
+        routing
+    ))
+    .unwrap()
+}
+
+#[test]
+fn test_geoip_routing_rules() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("country.mmdb");
+    std::fs::write(&path, sample_db()).unwrap();
+    let config = config(&format!(
+        r#"{{"geoip_file": {}, "rules": [
+            {{"ip_cidr": ["geoip:private"], "action": "block"}},
+            {{"ip_cidr": ["geoip:CN"], "action": "direct"}}
+        ]}}"#,
+        serde_json::to_string(&path).unwrap()
+    ));
+    let router = Router::new(&config.routing, &config.outbounds).unwrap();
+    assert!(router.geoip().is_some());
+
+    let route = |addr: &str| {
+        let address = match ip(addr) {
+            IpAddr::V4(v4) => Address::Ipv4(v4),
+            IpAddr::V6(v6) => Address::Ipv6(v6),
+        };
+        router
+            .route(&Uuid::nil(), &address, 443)
+            .map(|(_, action)| action.clone())
+    };
+    assert!(matches!(route("192.168.1.10"), Some(RouteAction::Block)));
+    assert!(matches!(route("1.0.0.1"), Some(RouteAction::Direct)));
+    assert!(matches!(route("2400:da00::2"), Some(RouteAction::Direct)));
+    assert!(route("8.8.8.8").is_none());
+    // 域名目标不做 GeoIP 匹配
+    let domain = Address::Domain(Bytes::from_static(b"example.cn"));
+    assert!(router.route(&Uuid::nil(), &domain, 443).is_none());
+}
+
+#[test]
+fn test_geoip_config_errors() {
+    let err = config(r#"{"rules": [{"ip_cidr": ["geoip:cn"], "action": "block"}]}"#)
+        .validate()
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("geoip:cn requires routing.geoip_file"),
+        "{}",
+        err
+    );
+    // geoip:private 不需要数据库
+    assert!(
+        config(r#"{"rules": [{"ip_cidr": ["geoip:private"], "action": "block"}]}"#)
+            .validate()
+            .is_ok()
+    );
+
+    let err = config(r#"{"geoip_file": "/nonexistent/country.mmdb", "rules": []}"#)
+        .validate()
+        .unwrap_err();
+    assert!(err.to_string().contains("routing.geoip_file"), "{}", err);
+}
@@ -0,0 +1,351 @@
+//! 连接钩子测试：配置校验、命令模板、事件提取、频率限制与命令 / 回调执行
+
+use std::net::{IpAddr, SocketAddr};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use uuid::Uuid;
+use vless_rust::config::{Config, HookConfig, HookVerdict};
+use vless_rust::events::{Event, EventBus};
+use vless_rust::hooks::{CommandTemplate, HookEvent, Hooks, RateLimiter};
+
+fn parse(hooks: &str) -> Config {
+    Config::from_json(&format!(
+        r#"{{"server": {{"listen": "0.0.0.0", "port": 443}}, "users": [], "hooks": {}}}"#,
+        hooks
+    ))
+    .unwrap()
+}
+
+fn addr(text: &str) -> SocketAddr {
+    text.parse().unwrap()
+}
+
+fn strings(values: &[&str]) -> Vec<String> {
+    values.iter().map(|v| v.to_string()).collect()
+}
+
+fn hook_config(on: &[HookVerdict]) -> HookConfig {
+    serde_json::from_value::<HookConfig>(serde_json::json!({ "on": on })).unwrap()
+}
+
+// ============================================================================
+// 配置
+// ============================================================================
+
+#[test]
+fn test_hooks_config() {
+    let config = parse(r#"[{"exec": ["/usr/sbin/ipset", "add", "vless-ban", "{ip}"]}]"#);
+    let hook = &config.hooks[0];
+    assert_eq!(hook.on, vec![HookVerdict::AuthFailure]);
+    assert_eq!(hook.cooldown, 60);
+    assert_eq!(hook.max_per_minute, 60);
+    assert_eq!(hook.timeout, 10);
+    assert!(config.validate().is_ok());
+
+    let config = parse(
+        r#"[{"on": ["auth_success", "closed"], "webhook_url": "https://fw.example.com/hook",
+             "cooldown": 0, "max_per_minute": 0}]"#,
+    );
+    assert_eq!(
+        config.hooks[0].on,
+        vec![HookVerdict::AuthSuccess, HookVerdict::Closed]
+    );
+    assert!(config.validate().is_ok());
+    assert!(parse("[]").validate().is_ok());
+
+    let error = |hooks: &str| parse(hooks).validate().unwrap_err().to_string();
+    assert!(error(r#"[{}]"#).contains("hooks[0] requires exec or webhook_url"));
+    assert!(error(r#"[{"on": [], "exec": ["/bin/true"]}]"#).contains("hooks[0].on"));
+    assert!(error(r#"[{"exec": ["/bin/echo", "{host}"]}]"#).contains("unknown placeholder {host}"));
+    assert!(error(r#"[{"exec": ["/opt/{user}/ban"]}]"#).contains("hooks[0].exec"));
+    assert!(error(r#"[{"webhook_url": "fw.example.com"}]"#).contains("hooks[0].webhook_url"));
+    assert!(error(r#"[{"exec": ["/bin/true"], "timeout": 0}]"#).contains("hooks[0].timeout"));
+}
+
+// ============================================================================
+// 命令模板与事件
+// ============================================================================
+
+#[test]
+fn test_command_template_expansion() {
+    let event = HookEvent::new(
+        HookVerdict::AuthSuccess,
+        addr("203.0.113.7:50000"),
+        Uuid::nil(),
+        "alice; rm -rf / $(id) @example.com".to_string(),
+    );
+    let template = CommandTemplate::parse(&strings(&[
+        "/usr/local/bin/notify",
+        "--ip={ip}:{port}",
+        "{verdict}",
+        "{user}",
+        "{\"uuid\": \"{uuid}\"}",
+        "{}",
+        "{Upper}",
+    ]))
+    .unwrap();
+    assert_eq!(template.program(), "/usr/local/bin/notify");
+    // 占位符只在单个参数内替换，用户数据中的空格与 shell 元字符原样保留
+    assert_eq!(
+        template.args(&event),
+        strings(&[
+            "--ip=203.0.113.7:50000",
+            "auth_success",
+            "alice; rm -rf / $(id) @example.com",
+            "{\"uuid\": \"00000000-0000-0000-0000-000000000000\"}",
+            "{}",
+            "{Upper}",
+        ])
+    );
+
+    assert!(CommandTemplate::parse(&[]).is_err());
+    assert!(CommandTemplate::parse(&strings(&[" "])).is_err());
+    assert!(CommandTemplate::parse(&strings(&["{ip}"])).is_err());
+}
+
+#[test]
+fn test_hook_event_from_event() {
+    let uuid = Uuid::new_v4();
+    let opened = Event::ConnectionOpened {
+        client_addr: addr("[::ffff:198.51.100.9]:4000"),
+        uuid,
+        user: "alice@example.com".to_string(),
+        network: "tcp",
+        dest: "example.com:443".to_string(),
+    };
+    let event = HookEvent::from_event(&opened).unwrap();
+    assert_eq!(event.verdict, HookVerdict::AuthSuccess);
+    // IPv4 映射地址还原为 IPv4，便于直接加入 ipset
+    assert_eq!(event.ip, "198.51.100.9".parse::<IpAddr>().unwrap());
+    assert_eq!(event.port, 4000);
+    assert_eq!(event.user, "alice@example.com");
+    assert!(event.timestamp > 0);
+
+    let failed = Event::AuthFailed {
+        client_addr: addr("[2001:db8::1]:5000"),
+        uuid,
+    };
+    let event = HookEvent::from_event(&failed).unwrap();
+    assert_eq!(event.verdict, HookVerdict::AuthFailure);
+    assert_eq!(event.ip, "2001:db8::1".parse::<IpAddr>().unwrap());
+    assert_eq!(event.user, uuid.to_string());
+
+    let closed = Event::ConnectionClosed {
+        client_addr: addr("192.0.2.1:6000"),
+        uuid,
+        user: "alice@example.com".to_string(),
+    };
+    assert_eq!(
+        HookEvent::from_event(&closed).unwrap().verdict,
+        HookVerdict::Closed
+    );
+
+    let blocked = Event::DestinationBlocked {
+        user: "alice@example.com".to_string(),
+        dest: "ads.example.com:443".to_string(),
+    };
+    assert!(HookEvent::from_event(&blocked).is_none());
+
+    let json = serde_json::to_value(HookEvent::from_event(&failed).unwrap()).unwrap();
+    assert_eq!(json["verdict"], "auth_failure");
+    assert_eq!(json["ip"], "2001:db8::1");
+    assert_eq!(json["port"], 5000);
+}
+
+// ============================================================================
+// 频率限制
+// ============================================================================
+
+#[test]
+fn test_rate_limiter_cooldown() {
+    let mut limiter = RateLimiter::new(Duration::from_secs(60), 0);
+    let ip: IpAddr = "203.0.113.7".parse().unwrap();
+    let other: IpAddr = "203.0.113.8".parse().unwrap();
+    let start = Instant::now();
+
+    assert!(limiter.check(HookVerdict::AuthFailure, ip, start));
+    assert!(!limiter.check(
+        HookVerdict::AuthFailure,
+        ip,
+        start + Duration::from_secs(59)
+    ));
+    // 不同 IP 与不同判定各自冷却
+    assert!(limiter.check(HookVerdict::AuthFailure, other, start));
+    assert!(limiter.check(HookVerdict::AuthSuccess, ip, start));
+    assert!(limiter.check(
+        HookVerdict::AuthFailure,
+        ip,
+        start + Duration::from_secs(60)
+    ));
+
+    let mut unlimited = RateLimiter::new(Duration::ZERO, 0);
+    for _ in 0..100 {
+        assert!(unlimited.check(HookVerdict::AuthFailure, ip, start));
+    }
+}
+
+#[test]
+fn test_rate_limiter_max_per_minute() {
+    let mut limiter = RateLimiter::new(Duration::ZERO, 3);
+    let ip: IpAddr = "203.0.113.7".parse().unwrap();
+    let start = Instant::now();
+
+    for _ in 0..3 {
+        assert!(limiter.check(HookVerdict::AuthFailure, ip, start));
+    }
+    assert!(!limiter.check(
+        HookVerdict::AuthFailure,
+        ip,
+        start + Duration::from_secs(30)
+    ));
+    // 新的一分钟重新计数
+    assert!(limiter.check(
+        HookVerdict::AuthFailure,
+        ip,
+        start + Duration::from_secs(60)
+    ));
+}
+
+// ============================================================================
+// 执行
+// ============================================================================
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_hook_exec_without_shell_interpolation() {
+    let dir = tempfile::TempDir::new().unwrap();
+    let output = dir.path().join("hook.log");
+    let marker = dir.path().join("pwned");
+
+    let mut config = hook_config(&[HookVerdict::AuthSuccess, HookVerdict::AuthFailure]);
+    // sh 只解释固定脚本，事件数据作为位置参数传入
+    config.exec = strings(&[
+        "/bin/sh",
+        "-c",
+        r#"printf '%s|%s|%s\n' "$1" "$2" "$3" >> "$0""#,
+        output.to_str().unwrap(),
+        "{verdict}",
+        "{ip}",
+        "{user}",
+    ]);
+    let events = EventBus::new();
+    let hooks = Hooks::spawn(&[config], events.subscribe()).unwrap();
+
+    let user = format!("$(touch {})", marker.display());
+    events.publish(Event::ConnectionOpened {
+        client_addr: addr("203.0.113.7:50000"),
+        uuid: Uuid::nil(),
+        user: user.clone(),
+        network: "tcp",
+        dest: "example.com:443".to_string(),
+    });
+    // 冷却期内同一 IP 的重复失败只触发一次
+    for _ in 0..3 {
+        events.publish(Event::AuthFailed {
+            client_addr: addr("203.0.113.7:50001"),
+            uuid: Uuid::nil(),
+        });
+    }
+    events.publish(Event::ConnectionClosed {
+        client_addr: addr("203.0.113.7:50000"),
+        uuid: Uuid::nil(),
+        user,
+    });
+
+    let mut lines = Vec::new();
+    for _ in 0..100 {
+        let text = tokio::fs::read_to_string(&output).await.unwrap_or_default();
+        lines = text.lines().map(String::from).collect::<Vec<_>>();
+        if lines.len() >= 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    lines.sort();
+    assert_eq!(
+        lines,
+        vec![
+            format!("auth_failure|203.0.113.7|{}", Uuid::nil()),
+            format!("auth_success|203.0.113.7|$(touch {})", marker.display()),
+        ]
+    );
+    assert!(!marker.exists());
+    assert_eq!(hooks.triggered_count(), 2);
+    assert_eq!(hooks.suppressed_count(), 2);
+    assert_eq!(hooks.failed_count(), 0);
+}
+
+#[cfg(unix)]
+#[tokio::test]
+async fn test_hook_command_failure_and_timeout() {
+    let mut failing = hook_config(&[HookVerdict::AuthFailure]);
+    failing.exec = strings(&["/bin/sh", "-c", "exit 3"]);
+    let mut slow = hook_config(&[HookVerdict::AuthFailure]);
+    slow.exec = strings(&["/bin/sh", "-c", "sleep 5"]);
+    slow.timeout = 1;
+    let events = EventBus::new();
+    let hooks = Hooks::spawn(&[failing, slow], events.subscribe()).unwrap();
+
+    events.publish(Event::AuthFailed {
+        client_addr: addr("203.0.113.7:50000"),
+        uuid: Uuid::nil(),
+    });
+    for _ in 0..150 {
+        if hooks.failed_count() == 2 {
+            break;
+        }
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+    assert_eq!(hooks.triggered_count(), 2);
+    assert_eq!(hooks.failed_count(), 2);
+}
+
+#[tokio::test]
+async fn test_hook_webhook() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr_text = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut request = Vec::new();
+        let mut buf = [0u8; 4096];
+        // 读到请求体的结束括号为止
+        while !request.ends_with(b"}") {
+            let n = stream.read(&mut buf).await.unwrap();
+            if n == 0 {
+                break;
+            }
+            request.extend_from_slice(&buf[..n]);
+        }
+        stream
+            .write_all(b"HTTP/1.1 204 No Content\r\nConnection: close\r\n\r\n")
+            .await
+            .unwrap();
+        String::from_utf8(request).unwrap()
+    });
+
+    let mut config = hook_config(&[HookVerdict::AuthFailure]);
+    config.webhook_url = Some(format!("http://{}/hook", addr_text));
+    config.webhook_token = Some("t0ken".to_string());
+    let events = EventBus::new();
+    let _hooks = Hooks::spawn(&[config], events.subscribe()).unwrap();
+    let uuid = Uuid::new_v4();
+    events.publish(Event::AuthFailed {
+        client_addr: addr("198.51.100.9:4000"),
+        uuid,
+    });
+
+    let request = tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .unwrap()
+        .unwrap();
+    assert!(request.starts_with("POST /hook HTTP/1.1"));
+    assert!(request
+        .to_ascii_lowercase()
+        .contains("authorization: bearer t0ken"));
+    let body = request.split("\r\n\r\n").nth(1).unwrap();
+    let event: serde_json::Value = serde_json::from_str(body).unwrap();
+    assert_eq!(event["verdict"], "auth_failure");
+    assert_eq!(event["ip"], "198.51.100.9");
+    assert_eq!(event["uuid"], uuid.to_string());
+}
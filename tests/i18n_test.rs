@@ -0,0 +1,55 @@
+//! 界面语言测试
+
+use vless_rust::config::Config;
+use vless_rust::http::build_404_response;
+use vless_rust::i18n::{is_zh, set_language, Language};
+use vless_rust::tr;
+
+#[test]
+fn test_language_parse() {
+    assert_eq!(Language::parse("zh"), Some(Language::Zh));
+    assert_eq!(Language::parse("zh_CN.UTF-8"), Some(Language::Zh));
+    assert_eq!(Language::parse("en_US.UTF-8"), Some(Language::En));
+    assert_eq!(Language::parse("C.UTF-8"), None);
+}
+
+#[test]
+fn test_language_config() {
+    let config = Config::from_json(
+        r#"{"server": {"listen": "0.0.0.0", "port": 443}, "users": [], "language": "en"}"#,
+    )
+    .unwrap();
+    assert_eq!(config.language, Some(Language::En));
+    // 配置优先于系统区域
+    if std::env::var("VLESS_LANG").is_err() {
+        assert_eq!(Language::detect(Some(Language::Zh)), Language::Zh);
+    }
+
+    let config =
+        Config::from_json(r#"{"server": {"listen": "0.0.0.0", "port": 443}, "users": []}"#)
+            .unwrap();
+    assert_eq!(config.language, None);
+    assert!(!config.to_json().unwrap().contains("language"));
+}
+
+/// 当前语言为进程级状态，切换放在同一个测试中
+#[test]
+fn test_translated_output() {
+    assert!(!is_zh());
+    assert_eq!(tr!("共 {} 个", "{} total", 3), "3 total");
+    let response = String::from_utf8(build_404_response()).unwrap();
+    assert!(response.contains(r#""code":"not_found""#));
+    assert!(response.contains(r#""message":"Not Found""#));
+
+    set_language(Language::Zh);
+    assert!(is_zh());
+    assert_eq!(tr!("共 {} 个", "{} total", 3), "共 3 个");
+    let response = String::from_utf8(build_404_response()).unwrap();
+    // 状态行保持英文，错误信息按语言输出
+    assert!(response.starts_with("HTTP/1.1 404 Not Found\r\n"));
+    // 错误码不随语言变化
+    assert!(response.contains(r#""code":"not_found""#));
+    assert!(response.contains(r#""message":"未找到""#));
+
+    set_language(Language::En);
+}
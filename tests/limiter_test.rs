@@ -0,0 +1,77 @@
+//! 全局会话并发限制测试
+
+use std::sync::Arc;
+use std::time::Duration;
+use vless_rust::config::{Config, PerformanceConfig};
+use vless_rust::limiter::SessionLimiter;
+
+// ============================================================================
+// 许可与拒绝
+// ============================================================================
+
+#[tokio::test]
+async fn test_rejects_without_queue() {
+    let limiter = SessionLimiter::new(2, 0, Duration::from_secs(1));
+    let a = limiter.acquire().await.unwrap();
+    let _b = limiter.acquire().await.unwrap();
+    assert_eq!(limiter.stats().active, 2);
+
+    assert!(limiter.acquire().await.is_none());
+    assert_eq!(limiter.stats().rejected, 1);
+
+    drop(a);
+    assert!(limiter.acquire().await.is_some());
+    assert_eq!(limiter.stats().rejected, 1);
+}
+
+#[tokio::test]
+async fn test_queued_connection_gets_released_permit() {
+    let limiter = Arc::new(SessionLimiter::new(1, 1, Duration::from_secs(5)));
+    let held = limiter.acquire().await.unwrap();
+
+    let waiter = {
+        let limiter = Arc::clone(&limiter);
+        tokio::spawn(async move { limiter.acquire().await.is_some() })
+    };
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    assert_eq!(limiter.stats().waiting, 1);
+
+    // 队列已满的连接直接拒绝
+    assert!(limiter.acquire().await.is_none());
+
+    drop(held);
+    assert!(waiter.await.unwrap());
+    let stats = limiter.stats();
+    assert_eq!((stats.waiting, stats.rejected), (0, 1));
+}
+
+#[tokio::test]
+async fn test_queue_timeout_rejects() {
+    let limiter = SessionLimiter::new(1, 4, Duration::from_millis(50));
+    let _held = limiter.acquire().await.unwrap();
+    assert!(limiter.acquire().await.is_none());
+    let stats = limiter.stats();
+    assert_eq!((stats.active, stats.max, stats.waiting), (1, 1, 0));
+    assert_eq!(stats.rejected, 1);
+}
+
+// ============================================================================
+// 配置
+// ============================================================================
+
+#[test]
+fn test_session_limit_config() {
+    let defaults = PerformanceConfig::default();
+    assert_eq!(defaults.max_sessions, 0);
+    assert_eq!(defaults.session_queue, 0);
+    assert_eq!(defaults.session_queue_timeout_ms, 1000);
+
+    let config = Config::from_json(
+        r#"{"server": {"listen": "0.0.0.0", "port": 443}, "users": [],
+            "performance": {"max_sessions": 2000, "session_queue": 100}}"#,
+    )
+    .unwrap();
+    assert_eq!(config.performance.max_sessions, 2000);
+    assert_eq!(config.performance.session_queue, 100);
+    assert_eq!(config.performance.session_queue_timeout_ms, 1000);
+}
@@ -0,0 +1,257 @@
+//! 客户端模式测试：本地 SOCKS5 / HTTP 代理入站经 VLESS 出站转发
+
+use bytes::Bytes;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use uuid::Uuid;
+use vless_rust::config::{
+    Config, LocalProxyConfig, LocalProxyProtocol, OutboundConfig, PerformanceConfig,
+};
+use vless_rust::local_proxy::{rewrite_http_request, LocalProxy};
+use vless_rust::outbound::Outbound;
+use vless_rust::protocol::{Address, Command, VlessRequest, VlessResponse};
+
+/// 模拟上游 VLESS 服务器：校验请求头后回显数据，响应头随首个下行数据一起发送；
+/// 返回监听地址与收到的（目标地址，端口）
+async fn fake_upstream(uuid: Uuid) -> (String, tokio::sync::mpsc::Receiver<(Address, u16)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = tokio::sync::mpsc::channel(8);
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let tx = tx.clone();
+            tokio::spawn(async move {
+                let mut buf = vec![0u8; 4096];
+                let n = stream.read(&mut buf).await.unwrap();
+                let (request, mut payload) =
+                    VlessRequest::decode(Bytes::copy_from_slice(&buf[..n])).unwrap();
+                assert_eq!(request.uuid, uuid);
+                assert_eq!(request.command, Command::Tcp);
+                tx.send((request.address, request.port)).await.unwrap();
+                if payload.is_empty() {
+                    let n = stream.read(&mut buf).await.unwrap();
+                    payload = Bytes::copy_from_slice(&buf[..n]);
+                }
+                let mut reply = VlessResponse::new_with_version(request.version)
+                    .encode()
+                    .to_vec();
+                reply.extend_from_slice(&payload);
+                stream.write_all(&reply).await.unwrap();
+            });
+        }
+    });
+    (addr.to_string(), rx)
+}
+
+async fn start_proxy(
+    protocol: LocalProxyProtocol,
+) -> (String, tokio::sync::mpsc::Receiver<(Address, u16)>) {
+    let uuid = Uuid::new_v4();
+    let (server, rx) = fake_upstream(uuid).await;
+    let outbound = Outbound::from_config(&OutboundConfig {
+        tag: "upstream".to_string(),
+        protocol: Default::default(),
+        server,
+        uuid: uuid.to_string(),
+        username: None,
+        password: None,
+    })
+    .unwrap();
+    let config = LocalProxyConfig {
+        listen: "127.0.0.1:0".to_string(),
+        protocol,
+        outbound: None,
+    };
+    let proxy = LocalProxy::bind(&config, Arc::new(outbound), PerformanceConfig::default())
+        .await
+        .unwrap();
+    let addr = proxy.local_addr().unwrap().to_string();
+    proxy.spawn();
+    (addr, rx)
+}
+
+// ============================================================================
+// SOCKS5
+// ============================================================================
+
+#[tokio::test]
+async fn test_socks_connect_through_outbound() {
+    let (proxy, mut targets) = start_proxy(LocalProxyProtocol::Socks).await;
+    let mut client = TcpStream::connect(proxy).await.unwrap();
+
+    client.write_all(&[5, 1, 0]).await.unwrap();
+    let mut method = [0u8; 2];
+    client.read_exact(&mut method).await.unwrap();
+    assert_eq!(method, [5, 0]);
+
+    let mut request = vec![5, 1, 0, 3, 11];
+    request.extend_from_slice(b"example.com");
+    request.extend_from_slice(&443u16.to_be_bytes());
+    client.write_all(&request).await.unwrap();
+    let mut reply = [0u8; 10];
+    client.read_exact(&mut reply).await.unwrap();
+    assert_eq!(reply[1], 0);
+
+    client.write_all(b"ping").await.unwrap();
+    let mut echo = [0u8; 4];
+    client.read_exact(&mut echo).await.unwrap();
+    // 响应头已被剥离
+    assert_eq!(&echo, b"ping");
+
+    let (address, port) = targets.recv().await.unwrap();
+    assert_eq!(address, Address::Domain(Bytes::from_static(b"example.com")));
+    assert_eq!(port, 443);
+}
+
+#[tokio::test]
+async fn test_socks_rejects_unsupported_command() {
+    let (proxy, _targets) = start_proxy(LocalProxyProtocol::Socks).await;
+    let mut client = TcpStream::connect(proxy).await.unwrap();
+
+    client.write_all(&[5, 1, 0]).await.unwrap();
+    let mut method = [0u8; 2];
+    client.read_exact(&mut method).await.unwrap();
+
+    // BIND 命令
+    client
+        .write_all(&[5, 2, 0, 1, 127, 0, 0, 1, 0, 80])
+        .await
+        .unwrap();
+    let mut reply = [0u8; 10];
+    client.read_exact(&mut reply).await.unwrap();
+    assert_eq!(reply[1], 0x07);
+}
+
+// ============================================================================
+// HTTP 代理
+// ============================================================================
+
+#[tokio::test]
+async fn test_http_connect_through_outbound() {
+    let (proxy, mut targets) = start_proxy(LocalProxyProtocol::Http).await;
+    let mut client = TcpStream::connect(proxy).await.unwrap();
+
+    client
+        .write_all(b"CONNECT 10.0.0.5:8443 HTTP/1.1\r\nHost: 10.0.0.5:8443\r\n\r\n")
+        .await
+        .unwrap();
+    let expected = b"HTTP/1.1 200 Connection Established\r\n\r\n";
+    let mut status = vec![0u8; expected.len()];
+    client.read_exact(&mut status).await.unwrap();
+    assert_eq!(status, expected);
+
+    client.write_all(b"hello").await.unwrap();
+    let mut echo = [0u8; 5];
+    client.read_exact(&mut echo).await.unwrap();
+    assert_eq!(&echo, b"hello");
+
+    let (address, port) = targets.recv().await.unwrap();
+    assert_eq!(address, Address::Ipv4("10.0.0.5".parse().unwrap()));
+    assert_eq!(port, 8443);
+}
+
+#[tokio::test]
+async fn test_http_plain_request_is_rewritten() {
+    let (proxy, mut targets) = start_proxy(LocalProxyProtocol::Http).await;
+    let mut client = TcpStream::connect(proxy).await.unwrap();
+
+    client
+        .write_all(
+            b"GET http://example.com/index.html HTTP/1.1\r\nHost: example.com\r\nProxy-Connection: keep-alive\r\n\r\n",
+        )
+        .await
+        .unwrap();
+    // 上游回显改写后的请求
+    let expected = b"GET /index.html HTTP/1.1\r\nHost: example.com\r\n\r\n";
+    let mut echo = vec![0u8; expected.len()];
+    client.read_exact(&mut echo).await.unwrap();
+    assert_eq!(echo, expected);
+
+    let (address, port) = targets.recv().await.unwrap();
+    assert_eq!(address, Address::Domain(Bytes::from_static(b"example.com")));
+    assert_eq!(port, 80);
+}
+
+#[test]
+fn test_rewrite_http_request() {
+    let (dest, request) = rewrite_http_request(
+        b"POST http://api.local:8080 HTTP/1.1\r\nProxy-Authorization: x\r\n\r\n",
+    )
+    .unwrap();
+    assert_eq!(dest, "api.local:8080");
+    assert_eq!(request, b"POST / HTTP/1.1\r\n\r\n");
+
+    // 非绝对 URI
+    assert!(rewrite_http_request(b"GET /index.html HTTP/1.1\r\n\r\n").is_err());
+    assert!(rewrite_http_request(b"GET https://example.com/ HTTP/1.1\r\n\r\n").is_err());
+}
+
+// ============================================================================
+// 配置校验
+// ============================================================================
+
+fn client_config(extra: &str) -> Config {
+    Config::from_json(&format!(
+        r#"{{"server": {{"listen": "0.0.0.0", "port": 443}}, "users": []{}}}"#,
+        extra
+    ))
+    .unwrap()
+}
+
+#[test]
+fn test_outbounds_config() {
+    let uuid = Uuid::new_v4();
+    let config = client_config(&format!(
+        r#", "outbounds": [{{"tag": "a", "server": "example.com:443", "uuid": "{uuid}"}},
+                           {{"tag": "b", "server": "10.0.0.1:443", "uuid": "{uuid}"}}],
+            "local_proxies": [{{"listen": "127.0.0.1:1080", "protocol": "socks"}},
+                              {{"listen": "127.0.0.1:8080", "protocol": "http", "outbound": "b"}}]"#
+    ));
+    assert!(config.validate_outbounds().is_ok());
+    let socks = &config.local_proxies[0];
+    assert_eq!(socks.protocol, LocalProxyProtocol::Socks);
+    assert_eq!(socks.select_outbound(&config.outbounds).unwrap().tag, "a");
+    let http = &config.local_proxies[1];
+    assert_eq!(http.select_outbound(&config.outbounds).unwrap().tag, "b");
+}
+
+#[test]
+fn test_outbounds_config_errors() {
+    let uuid = Uuid::new_v4();
+    let cases = [
+        (
+            format!(
+                r#", "outbounds": [{{"tag": "a", "server": "x:1", "uuid": "{uuid}"}},
+                                   {{"tag": "a", "server": "y:1", "uuid": "{uuid}"}}]"#
+            ),
+            "duplicate tag",
+        ),
+        (
+            format!(
+                r#", "outbounds": [{{"tag": "a", "server": "example.com", "uuid": "{uuid}"}}]"#
+            ),
+            "outbounds[0].server",
+        ),
+        (
+            r#", "outbounds": [{"tag": "a", "server": "x:1", "uuid": "bad"}]"#.to_string(),
+            "outbounds[0].uuid",
+        ),
+        (
+            r#", "local_proxies": [{"listen": "127.0.0.1:1080", "protocol": "socks"}]"#.to_string(),
+            "requires at least one entry in outbounds",
+        ),
+        (
+            format!(
+                r#", "outbounds": [{{"tag": "a", "server": "x:1", "uuid": "{uuid}"}}],
+                    "local_proxies": [{{"listen": "127.0.0.1:1080", "protocol": "http", "outbound": "c"}}]"#
+            ),
+            "unknown outbound 'c'",
+        ),
+    ];
+    for (extra, expected) in cases {
+        let err = client_config(&extra).validate_outbounds().unwrap_err();
+        assert!(err.to_string().contains(expected), "{}: {}", expected, err);
+    }
+}
@@ -0,0 +1,164 @@
+//! 维护模式测试：配置校验、计划时间窗口、运行时开关、维护说明与关闭原因
+
+use chrono::{DateTime, Utc};
+use vless_rust::config::{Config, MaintenanceBehavior, MaintenanceConfig};
+use vless_rust::i18n::{set_language, Language};
+use vless_rust::maintenance::{Maintenance, MaintenanceMode};
+
+fn time(text: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(text)
+        .unwrap()
+        .with_timezone(&Utc)
+}
+
+fn maintenance(starts_at: Option<&str>, ends_at: Option<&str>, message: &str) -> Maintenance {
+    Maintenance::new(&MaintenanceConfig {
+        starts_at: starts_at.map(str::to_string),
+        ends_at: ends_at.map(str::to_string),
+        message: message.to_string(),
+        ..Default::default()
+    })
+    .unwrap()
+}
+
+// ============================================================================
+// 配置
+// ============================================================================
+
+#[test]
+fn test_maintenance_config() {
+    let parse = |maintenance: &str| {
+        Config::from_json(&format!(
+            r#"{{"server": {{"listen": "0.0.0.0", "port": 443}}, "users": [], "maintenance": {}}}"#,
+            maintenance
+        ))
+        .unwrap()
+    };
+
+    let config = parse("{}");
+    assert!(!config.maintenance.enabled);
+    assert_eq!(config.maintenance.behavior, MaintenanceBehavior::Close);
+    assert!(config.validate().is_ok());
+
+    let config = parse(
+        r#"{"starts_at": "2026-10-20T02:00:00+08:00", "ends_at": "2026-10-20T04:00:00+08:00",
+            "message": "Upgrading hardware", "behavior": "fallback"}"#,
+    );
+    assert_eq!(config.maintenance.behavior, MaintenanceBehavior::Fallback);
+    let (starts, ends) = config.maintenance.window().unwrap();
+    assert_eq!(starts, Some(time("2026-10-19T18:00:00Z")));
+    assert_eq!(ends, Some(time("2026-10-19T20:00:00Z")));
+    assert!(config.validate().is_ok());
+
+    let err = parse(r#"{"starts_at": "tomorrow"}"#)
+        .validate()
+        .unwrap_err();
+    assert!(err.to_string().contains("maintenance.starts_at"), "{}", err);
+    let err = parse(r#"{"starts_at": "2026-10-20T04:00:00Z", "ends_at": "2026-10-20T02:00:00Z"}"#)
+        .validate()
+        .unwrap_err();
+    assert!(err.to_string().contains("maintenance.ends_at"), "{}", err);
+}
+
+// ============================================================================
+// 计划时间窗口与运行时开关
+// ============================================================================
+
+#[test]
+fn test_maintenance_window() {
+    let window = maintenance(
+        Some("2026-10-20T02:00:00Z"),
+        Some("2026-10-20T04:00:00Z"),
+        "",
+    );
+    assert!(!window.is_active_at(time("2026-10-20T01:59:59Z")));
+    assert!(window.is_active_at(time("2026-10-20T02:00:00Z")));
+    assert!(window.is_active_at(time("2026-10-20T03:59:59Z")));
+    assert!(!window.is_active_at(time("2026-10-20T04:00:00Z")));
+
+    // 只设置开始时间时持续到手动关闭；只设置结束时间时从现在起生效
+    let open_ended = maintenance(Some("2026-10-20T02:00:00Z"), None, "");
+    assert!(open_ended.is_active_at(time("2030-01-01T00:00:00Z")));
+    let until = maintenance(None, Some("2026-10-20T04:00:00Z"), "");
+    assert!(until.is_active_at(time("2026-01-01T00:00:00Z")));
+    assert!(!until.is_active_at(time("2026-10-20T04:00:00Z")));
+
+    // 未设置时间窗口时不生效
+    let idle = Maintenance::default();
+    assert!(!idle.is_active());
+    assert!(idle.admit());
+    assert_eq!(idle.status().rejected, 0);
+}
+
+#[test]
+fn test_maintenance_mode_switch() {
+    let window = maintenance(
+        Some("2026-10-20T02:00:00Z"),
+        Some("2026-10-20T04:00:00Z"),
+        "",
+    );
+    let inside = time("2026-10-20T03:00:00Z");
+    assert_eq!(window.mode(), MaintenanceMode::Scheduled);
+
+    // 手动关闭时计划窗口不生效，手动开启时始终生效
+    assert!(window.set_mode(MaintenanceMode::Off));
+    assert!(!window.is_active_at(inside));
+    assert!(window.set_mode(MaintenanceMode::On));
+    assert!(!window.set_mode(MaintenanceMode::On));
+    assert!(window.is_active_at(time("2030-01-01T00:00:00Z")));
+    assert!(!window.admit());
+    assert!(!window.admit());
+
+    let status = serde_json::to_value(window.status()).unwrap();
+    assert_eq!(status["active"], true);
+    assert_eq!(status["mode"], "on");
+    assert_eq!(status["starts_at"], "2026-10-20T02:00:00+00:00");
+    assert_eq!(status["behavior"], "close");
+    assert_eq!(status["rejected"], 2);
+
+    assert!(window.set_mode(MaintenanceMode::Scheduled));
+    assert!(window.is_active_at(inside));
+
+    // 配置 enabled 时以手动开启状态启动
+    let enabled = Maintenance::new(&MaintenanceConfig {
+        enabled: true,
+        ..Default::default()
+    })
+    .unwrap();
+    assert_eq!(enabled.mode(), MaintenanceMode::On);
+    assert!(enabled.is_active());
+}
+
+// ============================================================================
+// 维护说明
+// ============================================================================
+
+#[test]
+fn test_maintenance_notice_and_close_reason() {
+    set_language(Language::En);
+    let window = maintenance(
+        Some("2026-10-20T02:00:00Z"),
+        Some("2026-10-20T04:00:00Z"),
+        "Upgrading hardware",
+    );
+
+    // 计划中的维护提前展示时间窗口
+    assert_eq!(
+        window.notice_at(time("2026-10-19T00:00:00Z")).unwrap(),
+        "Scheduled maintenance: 2026-10-20 02:00 UTC - 2026-10-20 04:00 UTC — Upgrading hardware"
+    );
+    assert_eq!(
+        window.notice_at(time("2026-10-20T03:00:00Z")).unwrap(),
+        "Under maintenance, expected to end at 2026-10-20 04:00 UTC — Upgrading hardware"
+    );
+    assert!(window.notice_at(time("2026-10-20T05:00:00Z")).is_none());
+    assert!(Maintenance::default().notice().is_none());
+
+    assert_eq!(window.close_reason(), "Upgrading hardware");
+    assert_eq!(Maintenance::default().close_reason(), "Under maintenance");
+    // 关闭原因按字符边界截断到 123 字节
+    let long = maintenance(None, None, &"维护".repeat(40));
+    let reason = long.close_reason();
+    assert!(reason.len() <= 123);
+    assert_eq!(reason, "维护".repeat(20) + "维");
+}
@@ -0,0 +1,311 @@
+//! Mux.Cool 多路复用测试
+
+use bytes::Bytes;
+use std::net::Ipv4Addr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, DuplexStream};
+use tokio::net::{TcpListener, UdpSocket};
+use uuid::Uuid;
+use vless_rust::config::PerformanceConfig;
+use vless_rust::mux::{handle_mux, MuxFrame, MuxNetwork, MuxTarget, SessionStatus};
+use vless_rust::protocol::{Address, Command, VlessRequest};
+use vless_rust::session::SessionServices;
+
+fn mux_request() -> VlessRequest {
+    VlessRequest {
+        version: 0,
+        uuid: Uuid::new_v4(),
+        addons_length: 0,
+        addons: Bytes::new(),
+        command: Command::Mux,
+        port: 0,
+        address: Address::Domain(Bytes::from_static(b"v1.mux.cool")),
+    }
+}
+
+fn new_frame(id: u16, network: MuxNetwork, port: u16, data: &'static [u8]) -> MuxFrame {
+    MuxFrame {
+        id,
+        status: SessionStatus::New,
+        target: Some(MuxTarget {
+            network,
+            address: Address::Ipv4(Ipv4Addr::LOCALHOST),
+            port,
+        }),
+        data: Some(Bytes::from_static(data)),
+    }
+}
+
+/// 启动 Mux 处理，返回客户端侧数据流
+fn start_mux() -> DuplexStream {
+    let (client, server) = tokio::io::duplex(64 * 1024);
+    tokio::spawn(async move {
+        let _ = handle_mux(
+            server,
+            "127.0.0.1:50000".parse().unwrap(),
+            mux_request(),
+            Bytes::new(),
+            PerformanceConfig::default(),
+            &SessionServices::default(),
+            None,
+            None,
+        )
+        .await;
+    });
+    client
+}
+
+async fn next_frame(client: &mut DuplexStream) -> MuxFrame {
+    tokio::time::timeout(Duration::from_secs(5), MuxFrame::read_from(client))
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap()
+}
+
+// ============================================================================
+// 帧编解码
+// ============================================================================
+
+#[tokio::test]
+async fn test_frame_roundtrip() {
+    let frames = vec![
+        MuxFrame {
+            id: 7,
+            status: SessionStatus::New,
+            target: Some(MuxTarget {
+                network: MuxNetwork::Tcp,
+                address: Address::Domain(Bytes::from_static(b"example.com")),
+                port: 443,
+            }),
+            data: Some(Bytes::from_static(b"hello")),
+        },
+        MuxFrame::keep(7, None, Bytes::from_static(b"world")),
+        MuxFrame::end(7),
+    ];
+    let mut encoded = Vec::new();
+    for frame in &frames {
+        encoded.extend_from_slice(&frame.encode().unwrap());
+    }
+
+    let mut reader = encoded.as_slice();
+    for frame in &frames {
+        assert_eq!(
+            MuxFrame::read_from(&mut reader).await.unwrap().as_ref(),
+            Some(frame)
+        );
+    }
+    assert!(MuxFrame::read_from(&mut reader).await.unwrap().is_none());
+}
+
+#[test]
+fn test_frame_layout() {
+    let frame = new_frame(1, MuxNetwork::Tcp, 80, b"GET");
+    assert_eq!(
+        frame.encode().unwrap().as_ref(),
+        &[0, 12, 0, 1, 1, 1, 1, 0, 80, 1, 127, 0, 0, 1, 0, 3, b'G', b'E', b'T']
+    );
+    assert_eq!(
+        MuxFrame::end(1).encode().unwrap().as_ref(),
+        &[0, 4, 0, 1, 3, 0]
+    );
+}
+
+#[tokio::test]
+async fn test_new_frame_without_target_is_rejected() {
+    let mut reader: &[u8] = &[0, 4, 0, 1, 1, 0];
+    assert!(MuxFrame::read_from(&mut reader).await.is_err());
+
+    let mut reader: &[u8] = &[0, 4, 0, 1, 9, 0];
+    assert!(MuxFrame::read_from(&mut reader).await.is_err());
+}
+
+// ============================================================================
+// 子连接转发
+// ============================================================================
+
+#[tokio::test]
+async fn test_tcp_sub_connections_are_demultiplexed() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        loop {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            tokio::spawn(async move {
+                let mut buf = [0u8; 1024];
+                let n = stream.read(&mut buf).await.unwrap();
+                let reply = [b"echo:", &buf[..n]].concat();
+                stream.write_all(&reply).await.unwrap();
+            });
+        }
+    });
+
+    let mut client = start_mux();
+    for (id, data) in [(1, b"one" as &'static [u8]), (2, b"two")] {
+        let frame = new_frame(id, MuxNetwork::Tcp, port, data);
+        client.write_all(&frame.encode().unwrap()).await.unwrap();
+    }
+
+    let mut replies = Vec::new();
+    let mut ended = Vec::new();
+    while ended.len() < 2 {
+        let frame = next_frame(&mut client).await;
+        match frame.status {
+            SessionStatus::Keep => replies.push((frame.id, frame.data.unwrap())),
+            SessionStatus::End => ended.push(frame.id),
+            status => panic!("unexpected status {:?}", status),
+        }
+    }
+    replies.sort_by_key(|(id, _)| *id);
+    assert_eq!(
+        replies,
+        vec![
+            (1, Bytes::from_static(b"echo:one")),
+            (2, Bytes::from_static(b"echo:two"))
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_udp_sub_connection_keeps_datagram_boundaries() {
+    let target = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let port = target.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let mut buf = [0u8; 1024];
+        loop {
+            let (n, src) = target.recv_from(&mut buf).await.unwrap();
+            target.send_to(&buf[..n], src).await.unwrap();
+        }
+    });
+
+    let mut client = start_mux();
+    let frame = new_frame(5, MuxNetwork::Udp, port, b"first");
+    client.write_all(&frame.encode().unwrap()).await.unwrap();
+    let second = MuxFrame::keep(5, None, Bytes::from_static(b"second"));
+    client.write_all(&second.encode().unwrap()).await.unwrap();
+
+    for expected in [&b"first"[..], b"second"] {
+        let frame = next_frame(&mut client).await;
+        assert_eq!(frame.id, 5);
+        assert_eq!(frame.status, SessionStatus::Keep);
+        assert_eq!(frame.data.unwrap(), expected);
+        let source = frame.target.unwrap();
+        assert_eq!(source.network, MuxNetwork::Udp);
+        assert_eq!(source.port, port);
+    }
+}
+
+#[tokio::test]
+async fn test_tcp_upload_waits_for_slow_target() {
+    const FRAMES: usize = 300;
+    const CHUNK: usize = 60_000;
+    // 目标延迟接受连接，之后读完全部上传数据再回复
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = vec![0u8; FRAMES * CHUNK];
+        stream.read_exact(&mut buf).await.unwrap();
+        stream.write_all(b"done").await.unwrap();
+    });
+
+    let mut client = start_mux();
+    let frame = new_frame(1, MuxNetwork::Tcp, port, b"");
+    client.write_all(&frame.encode().unwrap()).await.unwrap();
+    let (mut client, mut writer) = tokio::io::split(client);
+    tokio::spawn(async move {
+        let chunk = Bytes::from(vec![0u8; CHUNK]);
+        for _ in 0..FRAMES {
+            let frame = MuxFrame::keep(1, None, chunk.clone());
+            writer.write_all(&frame.encode().unwrap()).await.unwrap();
+        }
+    });
+
+    let frame = tokio::time::timeout(Duration::from_secs(5), MuxFrame::read_from(&mut client))
+        .await
+        .unwrap()
+        .unwrap()
+        .unwrap();
+    assert_eq!(frame.id, 1);
+    assert_eq!(frame.status, SessionStatus::Keep);
+    assert_eq!(frame.data.unwrap(), &b"done"[..]);
+}
+
+#[tokio::test]
+async fn test_udp_keep_with_other_target_is_ended() {
+    let target = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let port = target.local_addr().unwrap().port();
+    let other = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let other_port = other.local_addr().unwrap().port();
+
+    let mut client = start_mux();
+    let frame = new_frame(4, MuxNetwork::Udp, port, b"first");
+    client.write_all(&frame.encode().unwrap()).await.unwrap();
+    let mut buf = [0u8; 64];
+    let (n, _) = target.recv_from(&mut buf).await.unwrap();
+    assert_eq!(&buf[..n], b"first");
+
+    // 同一目标的 Keep 帧照常转发
+    let same = MuxTarget {
+        network: MuxNetwork::Udp,
+        address: Address::Ipv4(Ipv4Addr::LOCALHOST),
+        port,
+    };
+    let frame = MuxFrame::keep(4, Some(same), Bytes::from_static(b"second"));
+    client.write_all(&frame.encode().unwrap()).await.unwrap();
+    let (n, _) = target.recv_from(&mut buf).await.unwrap();
+    assert_eq!(&buf[..n], b"second");
+
+    let moved = MuxTarget {
+        network: MuxNetwork::Udp,
+        address: Address::Ipv4(Ipv4Addr::LOCALHOST),
+        port: other_port,
+    };
+    let frame = MuxFrame::keep(4, Some(moved), Bytes::from_static(b"elsewhere"));
+    client.write_all(&frame.encode().unwrap()).await.unwrap();
+    assert_eq!(next_frame(&mut client).await, MuxFrame::end(4));
+    assert!(
+        tokio::time::timeout(Duration::from_millis(200), other.recv_from(&mut buf))
+            .await
+            .is_err()
+    );
+    assert!(
+        tokio::time::timeout(Duration::from_millis(200), target.recv_from(&mut buf))
+            .await
+            .is_err()
+    );
+}
+
+#[tokio::test]
+async fn test_keep_for_unknown_session_is_ended() {
+    let mut client = start_mux();
+    let frame = MuxFrame::keep(9, None, Bytes::from_static(b"orphan"));
+    client.write_all(&frame.encode().unwrap()).await.unwrap();
+    assert_eq!(next_frame(&mut client).await, MuxFrame::end(9));
+}
+
+#[tokio::test]
+async fn test_udp_full_cone_reply_carries_actual_source() {
+    // 目标收到数据后由另一个 socket 回包（full-cone 场景）
+    let target = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let port = target.local_addr().unwrap().port();
+    let other = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let other_port = other.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let mut buf = [0u8; 1024];
+        let (n, src) = target.recv_from(&mut buf).await.unwrap();
+        other.send_to(&buf[..n], src).await.unwrap();
+    });
+
+    let mut client = start_mux();
+    let frame = new_frame(3, MuxNetwork::Udp, port, b"stun");
+    client.write_all(&frame.encode().unwrap()).await.unwrap();
+
+    let frame = next_frame(&mut client).await;
+    assert_eq!(frame.status, SessionStatus::Keep);
+    assert_eq!(frame.data.unwrap(), &b"stun"[..]);
+    let source = frame.target.unwrap();
+    assert_eq!(source.address, Address::Ipv4(Ipv4Addr::LOCALHOST));
+    assert_eq!(source.port, other_port);
+}
@@ -0,0 +1,365 @@
+//! 上游代理出站测试：目标连接经 SOCKS5 / HTTP 代理建立，UDP 会话经 SOCKS5 UDP 中继
+
+use bytes::Bytes;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream, UdpSocket};
+use tokio::sync::mpsc;
+use uuid::Uuid;
+use vless_rust::config::{Config, OutboundConfig, OutboundProtocol, PerformanceConfig};
+use vless_rust::outbound::Outbound;
+use vless_rust::protocol::{Address, Command, VlessRequest};
+use vless_rust::session::SessionServices;
+use vless_rust::socks;
+
+fn outbound(protocol: OutboundProtocol, server: String, auth: bool) -> Arc<Outbound> {
+    Arc::new(
+        Outbound::from_config(&OutboundConfig {
+            tag: "upstream".to_string(),
+            protocol,
+            server,
+            uuid: String::new(),
+            username: auth.then(|| "alice".to_string()),
+            password: auth.then(|| "s3cret".to_string()),
+        })
+        .unwrap(),
+    )
+}
+
+/// 建立连接后回显一次数据
+async fn echo_once(mut stream: TcpStream) {
+    let mut buf = [0u8; 64];
+    let n = stream.read(&mut buf).await.unwrap();
+    stream.write_all(&buf[..n]).await.unwrap();
+}
+
+/// 模拟 SOCKS5 代理：要求用户名密码认证，以 `reply` 应答 CONNECT，成功时回显
+async fn fake_socks(reply: u8) -> (String, mpsc::Receiver<(Address, u16)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = mpsc::channel(4);
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut greeting = [0u8; 4];
+        stream.read_exact(&mut greeting).await.unwrap();
+        assert_eq!(greeting, [5, 2, 0, 2]);
+        stream.write_all(&[5, 2]).await.unwrap();
+
+        let mut auth = vec![0u8; 2 + 5 + 1 + 6];
+        stream.read_exact(&mut auth).await.unwrap();
+        assert_eq!(&auth[2..7], b"alice");
+        assert_eq!(&auth[8..], b"s3cret");
+        stream.write_all(&[1, 0]).await.unwrap();
+
+        let mut request = [0u8; 4];
+        stream.read_exact(&mut request).await.unwrap();
+        assert_eq!(request[1], socks::CMD_CONNECT);
+        let target = socks::read_address(&mut stream, request[3])
+            .await
+            .unwrap()
+            .unwrap();
+        tx.send(target).await.unwrap();
+        stream
+            .write_all(&[5, reply, 0, 1, 127, 0, 0, 1, 0x1f, 0x90])
+            .await
+            .unwrap();
+        if reply == socks::REP_SUCCESS {
+            echo_once(stream).await;
+        }
+    });
+    (addr.to_string(), rx)
+}
+
+/// 模拟 HTTP 代理：返回收到的 CONNECT 请求头，以 `status` 应答，成功时回显
+async fn fake_http_proxy(status: &'static str) -> (String, mpsc::Receiver<String>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = mpsc::channel(4);
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut head = Vec::new();
+        while !head.ends_with(b"\r\n\r\n") {
+            head.push(stream.read_u8().await.unwrap());
+        }
+        tx.send(String::from_utf8(head).unwrap()).await.unwrap();
+        stream
+            .write_all(format!("HTTP/1.1 {}\r\nVia: test\r\n\r\n", status).as_bytes())
+            .await
+            .unwrap();
+        if status.starts_with('2') {
+            echo_once(stream).await;
+        }
+    });
+    (addr.to_string(), rx)
+}
+
+/// 模拟 SOCKS5 UDP 中继：无认证，应答未指定的绑定地址；回显数据报，
+/// 域名目标的回包以 `RESOLVED` 作为来源。返回代理地址与中继收到的目标
+async fn fake_socks_udp() -> (String, mpsc::Receiver<(Address, u16)>) {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let (tx, rx) = mpsc::channel(4);
+    tokio::spawn(async move {
+        let (mut control, _) = listener.accept().await.unwrap();
+        let mut greeting = [0u8; 3];
+        control.read_exact(&mut greeting).await.unwrap();
+        assert_eq!(greeting, [5, 1, 0]);
+        control.write_all(&[5, 0]).await.unwrap();
+
+        let mut request = [0u8; 4];
+        control.read_exact(&mut request).await.unwrap();
+        assert_eq!(request[1], socks::CMD_UDP_ASSOCIATE);
+        socks::read_address(&mut control, request[3])
+            .await
+            .unwrap()
+            .unwrap();
+        let relay = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+        let mut reply = vec![5, 0, 0];
+        socks::encode_address(
+            &Address::Ipv4("0.0.0.0".parse().unwrap()),
+            relay.local_addr().unwrap().port(),
+            &mut reply,
+        );
+        control.write_all(&reply).await.unwrap();
+
+        let mut buf = [0u8; 512];
+        loop {
+            let (n, client) = relay.recv_from(&mut buf).await.unwrap();
+            let (target, port, offset) = socks::decode_udp_header(&buf[..n]).unwrap();
+            tx.send((target.clone(), port)).await.unwrap();
+            let source = match target {
+                Address::Domain(_) => Address::Ipv4(RESOLVED),
+                ip => ip,
+            };
+            let mut packet = socks::encode_udp_header(&source, port);
+            packet.extend_from_slice(&buf[offset..n]);
+            relay.send_to(&packet, client).await.unwrap();
+        }
+    });
+    (addr.to_string(), rx)
+}
+
+const RESOLVED: Ipv4Addr = Ipv4Addr::new(93, 184, 216, 34);
+
+fn services_via(outbound: Arc<Outbound>) -> SessionServices {
+    SessionServices {
+        outbound: Some(outbound),
+        ..Default::default()
+    }
+}
+
+// ============================================================================
+// SOCKS5 上游
+// ============================================================================
+
+#[tokio::test]
+async fn test_connect_target_via_socks_upstream() {
+    let (server, mut targets) = fake_socks(socks::REP_SUCCESS).await;
+    let services = services_via(outbound(OutboundProtocol::Socks, server, true));
+
+    let domain = Address::Domain(Bytes::from_static(b"example.com"));
+    let (mut stream, _) = services
+        .connect_target(&domain, 443, &PerformanceConfig::default())
+        .await
+        .unwrap();
+    stream.write_all(b"ping").await.unwrap();
+    let mut echo = [0u8; 4];
+    stream.read_exact(&mut echo).await.unwrap();
+    assert_eq!(&echo, b"ping");
+
+    // 域名原样交给上游解析
+    assert_eq!(targets.recv().await.unwrap(), (domain, 443));
+}
+
+#[tokio::test]
+async fn test_socks_upstream_refusal_is_an_error() {
+    let (server, _targets) = fake_socks(0x05).await;
+    let services = services_via(outbound(OutboundProtocol::Socks, server, true));
+
+    let err = services
+        .connect_target(
+            &Address::Ipv4("10.0.0.1".parse().unwrap()),
+            22,
+            &PerformanceConfig::default(),
+        )
+        .await
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("reply 0x05"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_silent_upstream_times_out() {
+    // 上游从不 accept，连接停在内核队列中，握手得不到应答
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server = listener.local_addr().unwrap().to_string();
+    let target = Address::Ipv4(Ipv4Addr::new(10, 0, 0, 1));
+    let perf = PerformanceConfig::default();
+
+    let socks = outbound(OutboundProtocol::Socks, server.clone(), false);
+    let http = outbound(OutboundProtocol::Http, server, false);
+    let (socks, http) = tokio::join!(
+        socks.connect(&target, 22, b"", &perf),
+        http.connect(&target, 22, b"", &perf)
+    );
+    for result in [socks, http] {
+        let err = result.err().unwrap();
+        assert!(err.to_string().contains("timed out"), "{}", err);
+    }
+    drop(listener);
+}
+
+// ============================================================================
+// SOCKS5 UDP 中继
+// ============================================================================
+
+fn udp_request(address: Address, port: u16) -> VlessRequest {
+    VlessRequest::new(Uuid::nil(), Command::Udp, address, port)
+}
+
+#[tokio::test]
+async fn test_udp_session_via_socks_relay() {
+    let (server, mut targets) = fake_socks_udp().await;
+    let services = services_via(outbound(OutboundProtocol::Socks, server, false));
+    let domain = Address::Domain(Bytes::from_static(b"dns.example"));
+    let request = udp_request(domain.clone(), 53);
+    let route = services.route(&request);
+    assert!(services.udp_outbound(&request, &route).is_some());
+
+    let (session, timing) = services
+        .open_udp(&request, &route, &PerformanceConfig::default())
+        .await
+        .unwrap();
+    // 域名原样交给上游解析
+    assert_eq!(timing.resolved, None);
+    session.send(b"query").await.unwrap();
+    assert_eq!(targets.recv().await.unwrap(), (domain, 53));
+
+    // 回包剥离中继头部，来源为上游解析后的实际地址
+    let mut buf = [0u8; 64];
+    let (n, src) = session.recv_from(&mut buf).await.unwrap().unwrap();
+    assert_eq!(&buf[..n], b"query");
+    assert_eq!(src, SocketAddr::new(RESOLVED.into(), 53));
+    assert!(session.is_target(src));
+}
+
+#[tokio::test]
+async fn test_udp_outbound_selection() {
+    let request = udp_request(Address::Ipv4("1.1.1.1".parse().unwrap()), 53);
+    let perf = PerformanceConfig::default();
+
+    // HTTP 出站无法承载 UDP，回退直连
+    let http = outbound(OutboundProtocol::Http, "127.0.0.1:1".to_string(), false);
+    let err = http.udp_associate(&perf).await.err().unwrap();
+    assert!(err.to_string().contains("does not support UDP"), "{}", err);
+    assert!(services_via(http).udp_outbound(&request, &None).is_none());
+    assert!(SessionServices::default()
+        .udp_outbound(&request, &None)
+        .is_none());
+}
+
+#[test]
+fn test_socks_udp_header() {
+    let domain = Address::Domain(Bytes::from_static(b"example.com"));
+    let mut packet = socks::encode_udp_header(&domain, 53);
+    assert_eq!(&packet[..5], &[0, 0, 0, socks::ATYP_DOMAIN, 11]);
+    packet.extend_from_slice(b"data");
+    let (address, port, offset) = socks::decode_udp_header(&packet).unwrap();
+    assert_eq!((address, port), (domain, 53));
+    assert_eq!(&packet[offset..], b"data");
+
+    // 分片与截断的数据报
+    assert!(socks::decode_udp_header(&[0, 0, 1, 1, 1, 2, 3, 4, 0, 53]).is_err());
+    assert!(socks::decode_udp_header(&[0, 0, 0, 1, 1, 2]).is_err());
+    assert!(socks::decode_udp_header(&[0, 0, 0, 3, 5, b'a']).is_err());
+}
+
+// ============================================================================
+// HTTP 上游
+// ============================================================================
+
+#[tokio::test]
+async fn test_connect_target_via_http_upstream() {
+    let (server, mut heads) = fake_http_proxy("200 Connection established").await;
+    let services = services_via(outbound(OutboundProtocol::Http, server, true));
+
+    let target = Address::Ipv6("::1".parse().unwrap());
+    let (mut stream, _) = services
+        .connect_target(&target, 8443, &PerformanceConfig::default())
+        .await
+        .unwrap();
+    stream.write_all(b"hello").await.unwrap();
+    let mut echo = [0u8; 5];
+    stream.read_exact(&mut echo).await.unwrap();
+    assert_eq!(&echo, b"hello");
+
+    let head = heads.recv().await.unwrap();
+    assert!(head.starts_with("CONNECT [::1]:8443 HTTP/1.1\r\n"));
+    // base64("alice:s3cret")
+    assert!(head.contains("Proxy-Authorization: Basic YWxpY2U6czNjcmV0\r\n"));
+}
+
+#[tokio::test]
+async fn test_http_upstream_rejection_is_an_error() {
+    let (server, _heads) = fake_http_proxy("407 Proxy Authentication Required").await;
+    let services = services_via(outbound(OutboundProtocol::Http, server, false));
+
+    let err = services
+        .connect_target(
+            &Address::Domain(Bytes::from_static(b"example.com")),
+            80,
+            &PerformanceConfig::default(),
+        )
+        .await
+        .err()
+        .unwrap();
+    assert!(err.to_string().contains("407"), "{}", err);
+}
+
+// ============================================================================
+// 配置校验
+// ============================================================================
+
+fn config_with(extra: &str, server_outbound: &str) -> Config {
+    Config::from_json(&format!(
+        r#"{{"server": {{"listen": "0.0.0.0", "port": 443{}}}, "users": []{}}}"#,
+        server_outbound, extra
+    ))
+    .unwrap()
+}
+
+#[test]
+fn test_server_outbound_config() {
+    let outbounds = r#", "outbounds": [
+        {"tag": "hop", "protocol": "socks", "server": "10.0.0.2:1080"},
+        {"tag": "corp", "protocol": "http", "server": "proxy.corp:3128", "username": "u", "password": "p"},
+        {"tag": "vless", "server": "example.com:443", "uuid": "550e8400-e29b-41d4-a716-446655440000"}
+    ]"#;
+    let config = config_with(outbounds, r#", "outbound": "hop""#);
+    assert!(config.validate_outbounds().is_ok());
+    assert_eq!(config.outbounds[0].protocol, OutboundProtocol::Socks);
+    assert!(config.outbounds[0].uuid.is_empty());
+
+    let err = config_with(outbounds, r#", "outbound": "vless""#)
+        .validate_outbounds()
+        .unwrap_err();
+    assert!(err.to_string().contains("must be socks or http"), "{}", err);
+
+    let err = config_with(outbounds, r#", "outbound": "missing""#)
+        .validate_outbounds()
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("unknown outbound 'missing'"),
+        "{}",
+        err
+    );
+
+    let err = config_with(
+        r#", "outbounds": [{"tag": "hop", "protocol": "socks", "server": "x:1", "username": "u"}]"#,
+        "",
+    )
+    .validate_outbounds()
+    .unwrap_err();
+    assert!(err.to_string().contains("set together"), "{}", err);
+}
@@ -0,0 +1,113 @@
+//! 协议开销统计测试
+
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use uuid::Uuid;
+use vless_rust::config::PerformanceConfig;
+use vless_rust::overhead::{ws_frame_header_len, OverheadStats, Transport};
+use vless_rust::session::SessionServices;
+use vless_rust::tcp::handle_tcp_connection;
+
+// ============================================================================
+// 计数
+// ============================================================================
+
+#[test]
+fn test_ws_frame_header_len() {
+    assert_eq!(ws_frame_header_len(0, false), 2);
+    assert_eq!(ws_frame_header_len(125, false), 2);
+    assert_eq!(ws_frame_header_len(126, false), 4);
+    assert_eq!(ws_frame_header_len(65535, true), 8);
+    assert_eq!(ws_frame_header_len(65536, false), 10);
+    assert_eq!(ws_frame_header_len(65536, true), 14);
+}
+
+#[test]
+fn test_snapshot_per_transport() {
+    let stats = OverheadStats::default();
+    stats.record_overhead(Transport::Ws, 200, 150);
+    stats.record_session(Transport::Ws, (600, 1000), (30, 20));
+
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.len(), 2);
+    let tcp = &snapshot[0];
+    assert_eq!(tcp.transport, "tcp");
+    assert_eq!(tcp.sessions, 0);
+    assert_eq!(tcp.efficiency, 1.0);
+
+    let ws = &snapshot[1];
+    assert_eq!(ws.transport, "ws");
+    assert_eq!(ws.sessions, 1);
+    assert_eq!((ws.payload_up, ws.payload_down), (600, 1000));
+    assert_eq!((ws.overhead_up, ws.overhead_down), (230, 170));
+    assert_eq!(ws.efficiency, 0.8);
+}
+
+// ============================================================================
+// TCP 会话
+// ============================================================================
+
+#[tokio::test]
+async fn test_tcp_session_records_header_overhead() {
+    // 目标端：回显一次后关闭
+    let target = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let target_port = target.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (mut conn, _) = target.accept().await.unwrap();
+        let mut buf = [0u8; 64];
+        let n = conn.read(&mut buf).await.unwrap();
+        conn.write_all(&buf[..n]).await.unwrap();
+    });
+
+    let user = Uuid::new_v4();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let services = SessionServices::default();
+    let overhead = Arc::clone(&services.overhead);
+    let server = tokio::spawn(async move {
+        let (stream, client_addr): (TcpStream, SocketAddr) = listener.accept().await.unwrap();
+        let users = HashSet::from([user]);
+        let _ = handle_tcp_connection(
+            stream,
+            client_addr,
+            PerformanceConfig::default(),
+            &users,
+            services,
+            |_| async { None },
+        )
+        .await;
+    });
+
+    let mut request = vec![0u8];
+    request.extend_from_slice(user.as_bytes());
+    request.push(0); // addons length
+    request.push(1); // TCP
+    request.extend_from_slice(&target_port.to_be_bytes());
+    request.push(1); // IPv4
+    request.extend_from_slice(&[127, 0, 0, 1]);
+    let header_len = request.len() as u64;
+    request.extend_from_slice(b"ping");
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client.write_all(&request).await.unwrap();
+    let mut response = Vec::new();
+    tokio::time::timeout(Duration::from_secs(5), client.read_to_end(&mut response))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(&response[2..], b"ping");
+    drop(client);
+    tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .unwrap()
+        .unwrap();
+
+    let tcp = &overhead.snapshot()[0];
+    assert_eq!(tcp.sessions, 1);
+    assert_eq!((tcp.payload_up, tcp.payload_down), (4, 4));
+    assert_eq!((tcp.overhead_up, tcp.overhead_down), (header_len, 2));
+}
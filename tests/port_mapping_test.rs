@@ -0,0 +1,180 @@
+//! NAT-PMP 端口映射测试
+
+use std::net::{Ipv4Addr, SocketAddr};
+use tokio::net::UdpSocket;
+use vless_rust::config::{Config, PortMappingConfig};
+use vless_rust::port_mapping::{
+    encode_request, gateway_addr, parse_default_gateway, parse_response, request_mapping,
+    PortMapper, PortMapping, NAT_PMP_PORT,
+};
+
+/// 构造映射响应
+fn mapping_response(result: u16, internal: u16, external: u16, lifetime: u32) -> Vec<u8> {
+    let mut resp = vec![0u8, 130];
+    resp.extend_from_slice(&result.to_be_bytes());
+    resp.extend_from_slice(&1234u32.to_be_bytes()); // epoch
+    resp.extend_from_slice(&internal.to_be_bytes());
+    resp.extend_from_slice(&external.to_be_bytes());
+    resp.extend_from_slice(&lifetime.to_be_bytes());
+    resp
+}
+
+/// 本地模拟网关：把请求的外部端口加上 `offset` 后应答，任务结束时返回收到的请求
+async fn fake_gateway(
+    offset: u16,
+    count: usize,
+) -> (SocketAddr, tokio::task::JoinHandle<Vec<[u8; 12]>>) {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let addr = socket.local_addr().unwrap();
+    let handle = tokio::spawn(async move {
+        let mut requests = Vec::new();
+        let mut buf = [0u8; 64];
+        for _ in 0..count {
+            let (n, peer) = socket.recv_from(&mut buf).await.unwrap();
+            let request: [u8; 12] = buf[..n].try_into().unwrap();
+            let internal = u16::from_be_bytes([request[4], request[5]]);
+            let external = u16::from_be_bytes([request[6], request[7]]);
+            let lifetime = u32::from_be_bytes([request[8], request[9], request[10], request[11]]);
+            let external = if external == 0 { 0 } else { external + offset };
+            socket
+                .send_to(&mapping_response(0, internal, external, lifetime), peer)
+                .await
+                .unwrap();
+            requests.push(request);
+        }
+        requests
+    });
+    (addr, handle)
+}
+
+// ============================================================================
+// 编解码
+// ============================================================================
+
+#[test]
+fn test_encode_request() {
+    let request = encode_request(443, 8443, 3600);
+    assert_eq!(
+        request,
+        [0, 2, 0, 0, 0x01, 0xBB, 0x20, 0xFB, 0, 0, 0x0E, 0x10]
+    );
+}
+
+#[test]
+fn test_parse_response() {
+    let mapping = parse_response(&mapping_response(0, 443, 40443, 7200)).unwrap();
+    assert_eq!(
+        mapping,
+        PortMapping {
+            internal_port: 443,
+            external_port: 40443,
+            lifetime: 7200,
+        }
+    );
+
+    let err = parse_response(&mapping_response(2, 443, 0, 0)).unwrap_err();
+    assert!(err.to_string().contains("not authorized"));
+    assert!(parse_response(&[0, 130, 0]).is_err());
+
+    let mut udp = mapping_response(0, 443, 443, 60);
+    udp[1] = 129;
+    assert!(parse_response(&udp).is_err());
+}
+
+// ============================================================================
+// 网关地址
+// ============================================================================
+
+#[test]
+fn test_parse_default_gateway() {
+    let table = "Iface\tDestination\tGateway \tFlags\tRefCnt\tUse\tMetric\tMask\n\
+                 eth0\t0001A8C0\t00000000\t0001\t0\t0\t0\t00FFFFFF\n\
+                 eth0\t00000000\t0101A8C0\t0003\t0\t0\t100\t00000000\n";
+    assert_eq!(
+        parse_default_gateway(table),
+        Some(Ipv4Addr::new(192, 168, 1, 1))
+    );
+    assert_eq!(parse_default_gateway("Iface\tDestination\tGateway\n"), None);
+}
+
+#[test]
+fn test_gateway_addr_from_config() {
+    let config = PortMappingConfig {
+        gateway: Some("192.168.1.1".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(
+        gateway_addr(&config).unwrap(),
+        SocketAddr::new("192.168.1.1".parse().unwrap(), NAT_PMP_PORT)
+    );
+
+    let config = PortMappingConfig {
+        gateway: Some("10.0.0.1:15351".to_string()),
+        ..Default::default()
+    };
+    assert_eq!(gateway_addr(&config).unwrap().port(), 15351);
+
+    let config = PortMappingConfig {
+        gateway: Some("router.local".to_string()),
+        ..Default::default()
+    };
+    assert!(gateway_addr(&config).is_err());
+}
+
+#[test]
+fn test_port_mapping_config_parse() {
+    let config = Config::from_json(
+        r#"{
+            "server": {"listen": "0.0.0.0", "port": 443},
+            "users": [],
+            "port_mapping": {"enabled": true, "external_port": 8443}
+        }"#,
+    )
+    .unwrap();
+    assert!(config.port_mapping.enabled);
+    assert_eq!(config.port_mapping.external_port, Some(8443));
+    assert_eq!(config.port_mapping.lifetime, 3600);
+    assert!(config.port_mapping.gateway.is_none());
+}
+
+// ============================================================================
+// 请求映射
+// ============================================================================
+
+#[tokio::test]
+async fn test_request_mapping_against_gateway() {
+    let (gateway, handle) = fake_gateway(0, 1).await;
+    let mapping = request_mapping(gateway, 443, 443, 600).await.unwrap();
+    assert_eq!(mapping.external_port, 443);
+    assert_eq!(mapping.lifetime, 600);
+    assert_eq!(handle.await.unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn test_request_mapping_gives_up_without_gateway() {
+    // 绑定但不应答
+    let silent = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let result = request_mapping(silent.local_addr().unwrap(), 443, 443, 600).await;
+    assert!(result.is_err());
+}
+
+#[tokio::test]
+async fn test_port_mapper_start_and_release() {
+    let (gateway, handle) = fake_gateway(1000, 2).await;
+    let config = PortMappingConfig {
+        enabled: true,
+        gateway: Some(gateway.to_string()),
+        external_port: Some(8443),
+        lifetime: 600,
+    };
+
+    let mapper = PortMapper::start(&config, 443).await.unwrap();
+    // 网关分配的端口与请求不同时以网关为准
+    assert_eq!(mapper.external_port(), 9443);
+    mapper.release().await;
+
+    let requests = handle.await.unwrap();
+    assert_eq!(requests[0], encode_request(443, 8443, 600));
+    // 删除映射：外部端口与有效期均为 0
+    assert_eq!(requests[1], encode_request(443, 0, 0));
+}
@@ -1,11 +1,11 @@
 //! VLESS 协议模块集成测试
 
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use std::net::{Ipv4Addr, Ipv6Addr};
 use uuid::Uuid;
 use vless_rust::protocol::{
-    Address, AddressType, Command, VlessRequest, VlessResponse, VLESS_VERSION_BETA,
-    VLESS_VERSION_RELEASE,
+    encode_udp_packet, take_udp_packet, Address, AddressType, Command, VlessRequest, VlessResponse,
+    VLESS_VERSION_BETA, VLESS_VERSION_RELEASE,
 };
 
 // ============================================================================
@@ -260,3 +260,100 @@ fn test_address_to_socket_addr_domain_fails() {
 
     assert!(result.is_err());
 }
+
+// ============================================================================
+// UDP 包长度前缀测试
+// ============================================================================
+
+#[test]
+fn test_encode_udp_packet() {
+    assert_eq!(
+        encode_udp_packet(b"abc").as_ref(),
+        &[0, 3, b'a', b'b', b'c']
+    );
+    assert_eq!(encode_udp_packet(b"").as_ref(), &[0, 0]);
+}
+
+#[test]
+fn test_take_udp_packet_splits_stream() {
+    let mut buf = BytesMut::new();
+    buf.extend_from_slice(&encode_udp_packet(b"first"));
+    buf.extend_from_slice(&encode_udp_packet(b"second"));
+    buf.extend_from_slice(&[0]);
+
+    assert_eq!(take_udp_packet(&mut buf).unwrap(), "first");
+    assert_eq!(take_udp_packet(&mut buf).unwrap(), "second");
+    // 长度前缀不完整
+    assert!(take_udp_packet(&mut buf).is_none());
+
+    buf.extend_from_slice(&[4, b'q', b'u']);
+    // 载荷不完整，缓冲区保持不变
+    assert!(take_udp_packet(&mut buf).is_none());
+    assert_eq!(buf.len(), 4);
+
+    buf.extend_from_slice(b"ic");
+    assert_eq!(take_udp_packet(&mut buf).unwrap(), "quic");
+    assert!(buf.is_empty());
+}
+
+// ============================================================================
+// 客户端编码测试
+// ============================================================================
+
+#[test]
+fn test_vless_request_encode_roundtrip() {
+    let uuid = Uuid::new_v4();
+    let addresses = [
+        Address::Ipv4(Ipv4Addr::new(1, 2, 3, 4)),
+        Address::Domain(Bytes::from_static(b"example.com")),
+        Address::Ipv6(Ipv6Addr::LOCALHOST),
+    ];
+    for address in addresses {
+        let request = VlessRequest::new(uuid, Command::Tcp, address.clone(), 443);
+        let mut encoded = BytesMut::from(&request.encode().unwrap()[..]);
+        encoded.extend_from_slice(b"payload");
+
+        let (decoded, rest) = VlessRequest::decode(encoded.freeze()).unwrap();
+        assert_eq!(decoded.version, VLESS_VERSION_RELEASE);
+        assert_eq!(decoded.uuid, uuid);
+        assert_eq!(decoded.command, Command::Tcp);
+        assert_eq!(decoded.port, 443);
+        assert_eq!(decoded.address, address);
+        assert_eq!(rest, "payload");
+    }
+}
+
+#[test]
+fn test_vless_request_encode_rejects_long_domain() {
+    let max = Address::Domain(Bytes::from(vec![b'a'; 255]));
+    let request = VlessRequest::new(Uuid::new_v4(), Command::Tcp, max.clone(), 443);
+    let (decoded, _) = VlessRequest::decode(request.encode().unwrap()).unwrap();
+    assert_eq!(decoded.address, max);
+
+    let long = Address::Domain(Bytes::from(vec![b'a'; 256]));
+    let request = VlessRequest::new(Uuid::new_v4(), Command::Tcp, long, 443);
+    let err = request.encode().unwrap_err();
+    assert!(err.to_string().contains("too long"));
+}
+
+#[test]
+fn test_vless_response_decode() {
+    let (response, rest) =
+        VlessResponse::decode(Bytes::from_static(&[1, 2, 9, 9, b'h', b'i'])).unwrap();
+    assert_eq!(response.version, 1);
+    assert_eq!(response.addons.as_ref(), &[9, 9]);
+    assert_eq!(rest, "hi");
+
+    let encoded = VlessResponse::new_with_version(0).encode();
+    let (response, rest) = VlessResponse::decode(encoded).unwrap();
+    assert_eq!(response.version, 0);
+    assert!(rest.is_empty());
+}
+
+#[test]
+fn test_vless_response_decode_invalid() {
+    assert!(VlessResponse::decode(Bytes::from_static(&[1])).is_err());
+    assert!(VlessResponse::decode(Bytes::from_static(&[7, 0])).is_err());
+    // 附加数据不完整
+    assert!(VlessResponse::decode(Bytes::from_static(&[1, 3, 0])).is_err());
+}
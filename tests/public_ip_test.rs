@@ -1,6 +1,31 @@
 //! 公网 IP 获取模块测试
 
-use vless_rust::public_ip::{fetch_public_ip_with_timeout, PublicIp};
+use vless_rust::config::{ProtocolType, ServerSettings};
+use vless_rust::public_ip::{fetch_public_ip_with_timeout, resolve_public_host, PublicIp};
+
+fn settings(public_ip: Option<&str>, domain: Option<&str>, detect: bool) -> ServerSettings {
+    ServerSettings {
+        listen: "0.0.0.0".to_string(),
+        port: 443,
+        protocol: ProtocolType::Tcp,
+        ws_path: "/vless".to_string(),
+        ws_early_data: 0,
+        public_ip: public_ip.map(String::from),
+        domain: domain.map(String::from),
+        detect_public_ip: detect,
+        ws_host: None,
+        sni: None,
+        link_port: None,
+        links_file: None,
+        bind_retries: 5,
+        bind_retry_delay_ms: 500,
+        inbound_protocols: None,
+        strict_tls_only: false,
+        outbound: None,
+        block_private_destinations: true,
+        domain_strategy: Default::default(),
+    }
+}
 
 #[tokio::test]
 async fn test_fetch_public_ip_with_timeout() {
@@ -37,3 +62,33 @@ fn test_public_ip_structure() {
     assert_eq!(ip.ip, "1.2.3.4");
     assert_eq!(ip.source, "https://api.example.com/ip");
 }
+
+#[tokio::test]
+async fn test_resolve_public_host_domain_takes_priority() {
+    let settings = settings(Some("203.0.113.1"), Some("vpn.example.com"), true);
+    let ip = resolve_public_host(&settings, 1).await.unwrap();
+
+    assert_eq!(ip.ip, "vpn.example.com");
+    assert_eq!(ip.source, "server.domain");
+}
+
+#[tokio::test]
+async fn test_resolve_public_host_configured_ip() {
+    let settings = settings(Some("203.0.113.1"), None, true);
+    let ip = resolve_public_host(&settings, 1).await.unwrap();
+
+    assert_eq!(ip.ip, "203.0.113.1");
+    assert_eq!(ip.source, "server.public_ip");
+}
+
+#[tokio::test]
+async fn test_resolve_public_host_detection_disabled() {
+    use std::time::Instant;
+
+    let settings = settings(None, None, false);
+    let start = Instant::now();
+
+    assert!(resolve_public_host(&settings, 5).await.is_none());
+    // 关闭探测时不应发起任何网络请求
+    assert!(start.elapsed().as_millis() < 500);
+}
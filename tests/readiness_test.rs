@@ -0,0 +1,289 @@
+//! 绑定重试与就绪状态测试
+
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use vless_rust::api::{handle_readyz_request, is_readyz_request};
+use vless_rust::config::Config;
+use vless_rust::maintenance::{Maintenance, MaintenanceMode};
+use vless_rust::readiness::{
+    bind_with_retry, is_io_error, is_resource_exhausted, AcceptBackoff, AcceptGate, BindRetry,
+    Readiness,
+};
+
+// ============================================================================
+// 绑定重试
+// ============================================================================
+
+#[tokio::test]
+async fn test_bind_succeeds_after_port_is_released() {
+    let occupied = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = occupied.local_addr().unwrap();
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        drop(occupied);
+    });
+
+    let listener = bind_with_retry("test", &BindRetry::new(5, 50), || async {
+        Ok(TcpListener::bind(addr).await?)
+    })
+    .await
+    .unwrap();
+    assert_eq!(listener.local_addr().unwrap(), addr);
+}
+
+#[tokio::test]
+async fn test_bind_gives_up_after_retries() {
+    let occupied = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+    let addr = occupied.local_addr().unwrap();
+    let attempts = AtomicU32::new(0);
+
+    let err = bind_with_retry("forward:web", &BindRetry::new(2, 10), || async {
+        attempts.fetch_add(1, Ordering::Relaxed);
+        Ok(TcpListener::bind(addr).await?)
+    })
+    .await
+    .unwrap_err();
+    assert_eq!(attempts.load(Ordering::Relaxed), 3);
+    assert!(is_io_error(&err));
+    assert!(err
+        .to_string()
+        .contains("Failed to bind forward:web after 2 retries"));
+}
+
+#[tokio::test]
+async fn test_config_errors_are_not_retried() {
+    let attempts = AtomicU32::new(0);
+    let err = bind_with_retry("forward:bad", &BindRetry::new(5, 10), || async {
+        attempts.fetch_add(1, Ordering::Relaxed);
+        Err::<(), _>(anyhow::anyhow!("Invalid forward listen address: nope"))
+    })
+    .await
+    .unwrap_err();
+    assert_eq!(attempts.load(Ordering::Relaxed), 1);
+    assert!(!is_io_error(&err));
+}
+
+#[test]
+fn test_bind_retry_config_defaults() {
+    let config =
+        Config::from_json(r#"{"server": {"listen": "0.0.0.0", "port": 443}, "users": []}"#)
+            .unwrap();
+    assert_eq!(config.server.bind_retries, 5);
+    assert_eq!(config.server.bind_retry_delay_ms, 500);
+}
+
+// ============================================================================
+// accept 退避
+// ============================================================================
+
+#[cfg(unix)]
+#[test]
+fn test_accept_backoff_on_fd_exhaustion() {
+    let emfile = std::io::Error::from_raw_os_error(libc::EMFILE);
+    assert!(is_resource_exhausted(&emfile));
+
+    let mut backoff = AcceptBackoff::default();
+    assert_eq!(backoff.on_error(&emfile), Some(Duration::from_millis(5)));
+    assert_eq!(backoff.on_error(&emfile), Some(Duration::from_millis(10)));
+    for _ in 0..20 {
+        backoff.on_error(&emfile);
+    }
+    assert_eq!(backoff.on_error(&emfile), Some(Duration::from_secs(1)));
+    assert_eq!(backoff.on_success(), 23);
+
+    // 成功后暂停时间复位
+    assert_eq!(backoff.on_error(&emfile), Some(Duration::from_millis(5)));
+}
+
+#[test]
+fn test_transient_accept_errors_pause_only_when_repeated() {
+    let aborted = std::io::Error::from(std::io::ErrorKind::ConnectionAborted);
+    assert!(!is_resource_exhausted(&aborted));
+
+    let mut backoff = AcceptBackoff::default();
+    for _ in 0..7 {
+        assert_eq!(backoff.on_error(&aborted), None);
+    }
+    assert_eq!(backoff.on_error(&aborted), Some(Duration::from_millis(5)));
+    assert_eq!(backoff.streak(), 8);
+}
+
+// ============================================================================
+// /readyz
+// ============================================================================
+
+async fn readyz_roundtrip(readiness: Arc<Readiness>, request: &'static [u8]) -> String {
+    readyz_roundtrip_with_gate(readiness, None, None, request).await
+}
+
+async fn readyz_roundtrip_with_gate(
+    readiness: Arc<Readiness>,
+    gate: Option<Arc<AcceptGate>>,
+    maintenance: Option<Arc<Maintenance>>,
+    request: &'static [u8],
+) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 1024];
+        let n = stream.read(&mut buf).await.unwrap();
+        assert!(is_readyz_request(&buf[..n]));
+        let _ = handle_readyz_request(
+            stream,
+            &buf[..n],
+            "s3cret",
+            &readiness,
+            None,
+            None,
+            None,
+            gate.as_deref(),
+            maintenance.as_deref(),
+        )
+        .await;
+    });
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client.write_all(request).await.unwrap();
+    let mut response = String::new();
+    client.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+fn body(response: &str) -> serde_json::Value {
+    serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap()
+}
+
+#[tokio::test]
+async fn test_readyz_reports_degraded_inbounds() {
+    let readiness = Arc::new(Readiness::default());
+    readiness.set_ready("main", "0.0.0.0:443");
+    readiness.set_ready("forward:dns", "0.0.0.0:53");
+
+    let response = readyz_roundtrip(Arc::clone(&readiness), b"GET /readyz HTTP/1.1\r\n\r\n").await;
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert_eq!(body(&response)["status"], "ready");
+    assert!(body(&response).get("inbounds").is_none());
+
+    readiness.set_failed("forward:dns", "0.0.0.0:53", "Address already in use");
+    readiness.record_accept_failure();
+    assert!(readiness.is_degraded());
+    assert_eq!(readiness.inbounds().len(), 2);
+
+    // 无令牌只返回状态
+    let response = readyz_roundtrip(Arc::clone(&readiness), b"GET /readyz HTTP/1.1\r\n\r\n").await;
+    assert!(response.starts_with("HTTP/1.1 503"));
+    assert_eq!(body(&response)["status"], "degraded");
+    assert!(body(&response).get("inbounds").is_none());
+
+    let response = readyz_roundtrip(
+        readiness,
+        b"GET /readyz HTTP/1.1\r\nAuthorization: Bearer s3cret\r\n\r\n",
+    )
+    .await;
+    let inbounds = &body(&response)["inbounds"];
+    assert_eq!(inbounds[0]["name"], "main");
+    assert_eq!(inbounds[0]["ready"], true);
+    assert!(inbounds[0].get("error").is_none());
+    assert_eq!(inbounds[1]["name"], "forward:dns");
+    assert_eq!(inbounds[1]["error"], "Address already in use");
+    assert_eq!(body(&response)["accept_failures"], 1);
+}
+
+// ============================================================================
+// 暂停接受新会话
+// ============================================================================
+
+#[test]
+fn test_accept_gate() {
+    let gate = AcceptGate::default();
+    assert!(!gate.is_paused());
+    assert!(gate.admit());
+
+    assert!(gate.pause());
+    assert!(!gate.pause());
+    assert!(!gate.admit());
+    assert!(!gate.admit());
+    assert_eq!(gate.status().rejected, 2);
+
+    assert!(gate.resume());
+    assert!(!gate.resume());
+    assert!(gate.admit());
+
+    assert!(gate.toggle());
+    assert!(gate.is_paused());
+    assert!(!gate.toggle());
+    let json = serde_json::to_value(gate.status()).unwrap();
+    assert_eq!(json["paused"], false);
+    assert_eq!(json["rejected"], 2);
+}
+
+#[tokio::test]
+async fn test_readyz_reports_paused() {
+    let readiness = Arc::new(Readiness::default());
+    readiness.set_ready("main", "0.0.0.0:443");
+    let gate = Arc::new(AcceptGate::default());
+    gate.pause();
+
+    let response = readyz_roundtrip_with_gate(
+        Arc::clone(&readiness),
+        Some(Arc::clone(&gate)),
+        None,
+        b"GET /readyz HTTP/1.1\r\n\r\n",
+    )
+    .await;
+    assert!(response.starts_with("HTTP/1.1 503"));
+    assert_eq!(body(&response)["status"], "paused");
+
+    let response = readyz_roundtrip_with_gate(
+        Arc::clone(&readiness),
+        Some(Arc::clone(&gate)),
+        None,
+        b"GET /readyz HTTP/1.1\r\nAuthorization: Bearer s3cret\r\n\r\n",
+    )
+    .await;
+    assert_eq!(body(&response)["gate"]["paused"], true);
+
+    gate.resume();
+    let response =
+        readyz_roundtrip_with_gate(readiness, Some(gate), None, b"GET /readyz HTTP/1.1\r\n\r\n")
+            .await;
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert_eq!(body(&response)["status"], "ready");
+}
+
+#[tokio::test]
+async fn test_readyz_reports_maintenance() {
+    let readiness = Arc::new(Readiness::default());
+    readiness.set_ready("main", "0.0.0.0:443");
+    let gate = Arc::new(AcceptGate::default());
+    gate.pause();
+    let maintenance = Arc::new(Maintenance::default());
+    maintenance.set_mode(MaintenanceMode::On);
+
+    // 维护模式优先于暂停
+    let response = readyz_roundtrip_with_gate(
+        Arc::clone(&readiness),
+        Some(gate),
+        Some(Arc::clone(&maintenance)),
+        b"GET /readyz HTTP/1.1\r\nAuthorization: Bearer s3cret\r\n\r\n",
+    )
+    .await;
+    assert!(response.starts_with("HTTP/1.1 503"));
+    assert_eq!(body(&response)["status"], "maintenance");
+    assert_eq!(body(&response)["maintenance"]["mode"], "on");
+
+    maintenance.set_mode(MaintenanceMode::Off);
+    let response = readyz_roundtrip_with_gate(
+        readiness,
+        None,
+        Some(maintenance),
+        b"GET /readyz HTTP/1.1\r\n\r\n",
+    )
+    .await;
+    assert!(response.starts_with("HTTP/1.1 200"));
+}
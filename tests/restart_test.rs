@@ -0,0 +1,126 @@
+//! 定时重启测试：cron 解析、下一次触发时间、连接排空与配置校验
+
+use chrono::NaiveDateTime;
+use std::sync::Arc;
+use std::time::Duration;
+use vless_rust::config::Config;
+use vless_rust::restart::{drain, Schedule};
+use vless_rust::runtime_stats::ListenerTasks;
+
+fn time(text: &str) -> NaiveDateTime {
+    NaiveDateTime::parse_from_str(text, "%Y-%m-%d %H:%M:%S").unwrap()
+}
+
+fn next(expr: &str, after: &str) -> Option<NaiveDateTime> {
+    Schedule::parse(expr).unwrap().next_after(time(after))
+}
+
+// ============================================================================
+// 计划解析
+// ============================================================================
+
+#[test]
+fn test_next_after() {
+    let cases = [
+        // 每天 04:30，当天已过则为次日
+        ("30 4 * * *", "2026-03-01 04:29:59", "2026-03-01 04:30:00"),
+        ("30 4 * * *", "2026-03-01 04:30:00", "2026-03-02 04:30:00"),
+        // 步长与列表
+        ("*/15 * * * *", "2026-03-01 10:16:10", "2026-03-01 10:30:00"),
+        ("0 3,15 * * *", "2026-03-01 04:00:00", "2026-03-01 15:00:00"),
+        // 周日（0 与 7 等价），2026-03-01 是周日
+        ("0 5 * * 7", "2026-03-01 06:00:00", "2026-03-08 05:00:00"),
+        ("0 5 * * 1-5", "2026-03-06 06:00:00", "2026-03-09 05:00:00"),
+        // 日与周都受限时满足其一即可：每月 15 日或周一
+        ("0 0 15 * 1", "2026-03-10 00:00:00", "2026-03-15 00:00:00"),
+        // 跨年与闰日
+        ("0 0 1 1 *", "2026-06-01 00:00:00", "2027-01-01 00:00:00"),
+        ("0 0 29 2 *", "2026-03-01 00:00:00", "2028-02-29 00:00:00"),
+        ("@weekly", "2026-03-02 00:00:00", "2026-03-08 00:00:00"),
+        ("@hourly", "2026-03-01 23:59:00", "2026-03-02 00:00:00"),
+    ];
+    for (expr, after, expected) in cases {
+        assert_eq!(
+            next(expr, after),
+            Some(time(expected)),
+            "{} after {}",
+            expr,
+            after
+        );
+    }
+    // 永不触发
+    assert_eq!(next("0 0 30 2 *", "2026-01-01 00:00:00"), None);
+}
+
+#[test]
+fn test_invalid_schedule() {
+    let cases = [
+        ("0 4 * *", "expected 5 fields"),
+        ("60 4 * * *", "minute must be between 0 and 59"),
+        ("0 24 * * *", "hour must be between 0 and 23"),
+        ("0 0 0 * *", "day must be between 1 and 31"),
+        ("0 0 * 13 *", "month must be between 1 and 12"),
+        ("0 0 * * 8", "weekday must be between 0 and 7"),
+        ("*/0 * * * *", "invalid step"),
+        ("0 5-3 * * *", "invalid range"),
+        ("@yearly", "expected 5 fields"),
+    ];
+    for (expr, expected) in cases {
+        let err = Schedule::parse(expr).unwrap_err();
+        assert!(err.to_string().contains(expected), "{}: {}", expr, err);
+    }
+}
+
+#[test]
+fn test_restart_config() {
+    let config = |restart: &str| {
+        Config::from_json(&format!(
+            r#"{{"server": {{"listen": "0.0.0.0", "port": 443}}, "users": [], "restart": {}}}"#,
+            restart
+        ))
+        .unwrap()
+    };
+    let restart = config(r#"{"schedule": "30 4 * * *"}"#).restart;
+    assert_eq!(restart.max_active_connections, 0);
+    assert_eq!(restart.defer_minutes, 60);
+    assert_eq!(restart.drain_timeout, 30);
+    assert_eq!(restart.exit_code, 75);
+
+    let err = config(r#"{"schedule": "30 25 * * *"}"#)
+        .validate()
+        .unwrap_err();
+    assert!(err.to_string().starts_with("restart.schedule:"), "{}", err);
+    let err = config(r#"{"schedule": "@daily", "exit_code": 256}"#)
+        .validate()
+        .unwrap_err();
+    assert!(err.to_string().contains("restart.exit_code"), "{}", err);
+}
+
+// ============================================================================
+// 连接排空
+// ============================================================================
+
+#[tokio::test]
+async fn test_drain() {
+    let tasks = Arc::new(ListenerTasks::default());
+    let main = tasks.register("main");
+    let forward = tasks.register("forward 0.0.0.0:8080");
+
+    assert_eq!(drain(&tasks, Duration::from_secs(1)).await, 0);
+
+    let short = main.enter();
+    let long = forward.enter();
+    assert_eq!(tasks.active_total(), 2);
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        drop(short);
+    });
+    // 超时后返回仍在运行的任务数
+    assert_eq!(drain(&tasks, Duration::from_millis(500)).await, 1);
+
+    tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(100)).await;
+        drop(long);
+    });
+    assert_eq!(drain(&tasks, Duration::from_secs(5)).await, 0);
+}
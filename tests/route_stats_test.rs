@@ -0,0 +1,205 @@
+//! 路由流量统计测试：会话路由归属、拦截计数、快照汇总与会话记录输出
+
+use bytes::Bytes;
+use std::sync::Arc;
+use std::time::Instant;
+use uuid::Uuid;
+use vless_rust::address::ConnectTiming;
+use vless_rust::config::Config;
+use vless_rust::outbound::Outbound;
+use vless_rust::protocol::{Address, Command, VlessRequest};
+use vless_rust::route_stats::{RouteCounters, RouteStats, SessionRoute};
+use vless_rust::router::Router;
+use vless_rust::session::{SessionRecord, SessionServices};
+
+fn config(rules: &str) -> Config {
+    Config::from_json(&format!(
+        r#"{{"server": {{"listen": "0.0.0.0", "port": 443}}, "users": [],
+            "outbounds": [
+                {{"tag": "socks-hop", "protocol": "socks", "server": "127.0.0.1:1080"}},
+                {{"tag": "http-hop", "protocol": "http", "server": "127.0.0.1:8080"}}
+            ],
+            "routing": {{"rules": [{}]}}}}"#,
+        rules
+    ))
+    .unwrap()
+}
+
+fn services(rules: &str) -> SessionServices {
+    let config = config(rules);
+    SessionServices {
+        router: Some(Arc::new(
+            Router::new(&config.routing, &config.outbounds).unwrap(),
+        )),
+        ..Default::default()
+    }
+}
+
+fn request(command: Command, domain: &str) -> VlessRequest {
+    VlessRequest::new(
+        Uuid::nil(),
+        command,
+        Address::Domain(Bytes::copy_from_slice(domain.as_bytes())),
+        443,
+    )
+}
+
+fn counters(sessions: u64, bytes_up: u64, bytes_down: u64) -> RouteCounters {
+    RouteCounters {
+        sessions,
+        bytes_up,
+        bytes_down,
+    }
+}
+
+// ============================================================================
+// 路由归属
+// ============================================================================
+
+#[test]
+fn test_session_route() {
+    let mut services = services(
+        r#"{"domain_suffix": ["direct.test"], "action": "direct"},
+           {"domain_suffix": ["socks.test"], "action": "outbound:socks-hop"},
+           {"domain_suffix": ["http.test"], "action": "outbound:http-hop"},
+           {"domain_suffix": ["ads.test"], "action": "block"}"#,
+    );
+    let route = |services: &SessionServices, command, domain| {
+        let request = request(command, domain);
+        services.session_route(&request, &services.route(&request))
+    };
+
+    assert_eq!(
+        route(&services, Command::Tcp, "a.direct.test"),
+        SessionRoute::new("direct", Some(0))
+    );
+    assert_eq!(
+        route(&services, Command::Tcp, "socks.test"),
+        SessionRoute::new("socks-hop", Some(1))
+    );
+    assert_eq!(
+        route(&services, Command::Udp, "socks.test"),
+        SessionRoute::new("socks-hop", Some(1))
+    );
+    // HTTP 出站无法承载 UDP，按直连计
+    assert_eq!(
+        route(&services, Command::Tcp, "http.test"),
+        SessionRoute::new("http-hop", Some(2))
+    );
+    assert_eq!(
+        route(&services, Command::Udp, "http.test"),
+        SessionRoute::new("direct", Some(2))
+    );
+    assert_eq!(
+        route(&services, Command::Tcp, "ads.test"),
+        SessionRoute::new("block", Some(3))
+    );
+
+    // 未命中规则：未设置 server.outbound 时直连，否则为该出站
+    assert_eq!(
+        route(&services, Command::Tcp, "other.test"),
+        SessionRoute::new("direct", None)
+    );
+    let config = config("");
+    services.outbound = Some(Arc::new(
+        Outbound::from_config(&config.outbounds[0]).unwrap(),
+    ));
+    assert_eq!(
+        route(&services, Command::Tcp, "other.test"),
+        SessionRoute::new("socks-hop", None)
+    );
+}
+
+#[test]
+fn test_record_blocked_and_finished_sessions() {
+    let services = services(r#"{"domain_suffix": ["ads.test"], "action": "block"}"#);
+    services.record_blocked(&services.route(&request(Command::Tcp, "ads.test")));
+    // 拦截列表等其他原因拒绝的请求不关联规则
+    services.record_blocked(&services.route(&request(Command::Tcp, "tracker.test")));
+
+    let tcp = request(Command::Tcp, "video.test");
+    let mut record = SessionRecord::new(
+        tcp.uuid,
+        "alice".to_string(),
+        "tcp",
+        "video.test:443".to_string(),
+        &ConnectTiming::default(),
+        Instant::now(),
+    )
+    .with_route(services.session_route(&tcp, &services.route(&tcp)));
+    record.bytes_up = 100;
+    record.bytes_down = 5000;
+    services.finish_session(&mut record);
+
+    // 未设置路由的会话不计入
+    let mut untagged = SessionRecord::new(
+        Uuid::nil(),
+        "bob".to_string(),
+        "tcp",
+        "x.test:80".to_string(),
+        &ConnectTiming::default(),
+        Instant::now(),
+    );
+    untagged.bytes_up = 1;
+    services.finish_session(&mut untagged);
+
+    let snapshot = services.route_stats.snapshot();
+    assert_eq!(snapshot.routes.len(), 2);
+    assert_eq!(snapshot.routes[0].route, "direct");
+    assert_eq!(snapshot.routes[0].counters, counters(1, 100, 5000));
+    assert_eq!(snapshot.routes[1].route, "block");
+    assert_eq!(snapshot.routes[1].counters, counters(2, 0, 0));
+    assert_eq!(snapshot.rules.len(), 1);
+    assert_eq!(snapshot.rules[0].rule, 0);
+    assert_eq!(snapshot.rules[0].route, "block");
+    assert_eq!(snapshot.rules[0].counters, counters(1, 0, 0));
+
+    // 会话记录输出路由与规则字段
+    let json = serde_json::to_value(&record).unwrap();
+    assert_eq!(json["route"], "direct");
+    assert!(json["rule"].is_null());
+    let json = serde_json::to_value(&untagged).unwrap();
+    assert!(json.get("route").is_none());
+}
+
+// ============================================================================
+// 快照汇总
+// ============================================================================
+
+#[test]
+fn test_route_snapshot() {
+    let stats = RouteStats::default();
+    stats.record_session(&SessionRoute::new("hop", Some(2)), 10, 20);
+    stats.record_session(&SessionRoute::new("hop", None), 1000, 2000);
+    stats.record_session(&SessionRoute::new("direct", Some(0)), 5, 5);
+    stats.record_session(&SessionRoute::new("hop", Some(2)), 10, 20);
+
+    let snapshot = stats.snapshot();
+    // 按路由汇总（规则与默认路由合计），按总流量降序
+    let routes: Vec<(&str, RouteCounters)> = snapshot
+        .routes
+        .iter()
+        .map(|r| (r.route.as_str(), r.counters))
+        .collect();
+    assert_eq!(
+        routes,
+        vec![
+            ("hop", counters(3, 1020, 2040)),
+            ("direct", counters(1, 5, 5))
+        ]
+    );
+    let rules: Vec<(usize, &str, u64)> = snapshot
+        .rules
+        .iter()
+        .map(|r| (r.rule, r.route.as_str(), r.counters.sessions))
+        .collect();
+    assert_eq!(rules, vec![(0, "direct", 1), (2, "hop", 2)]);
+
+    let json = serde_json::to_value(&snapshot).unwrap();
+    assert_eq!(json["routes"][0]["route"], "hop");
+    assert_eq!(json["routes"][0]["bytes_down"], 2040);
+    assert_eq!(json["rules"][1]["rule"], 2);
+    assert_eq!(json["rules"][1]["sessions"], 2);
+
+    assert_eq!(RouteStats::default().snapshot().routes, vec![]);
+}
@@ -0,0 +1,331 @@
+//! 路由规则测试：条件匹配、规则顺序、动作与配置校验
+
+use bytes::Bytes;
+use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpListener;
+use uuid::Uuid;
+use vless_rust::config::{Config, DomainStrategy, PerformanceConfig};
+use vless_rust::protocol::{Address, Command, VlessRequest};
+use vless_rust::router::{RouteAction, Router};
+use vless_rust::session::SessionServices;
+
+const ALICE: &str = "550e8400-e29b-41d4-a716-446655440000";
+
+fn config(routing: &str, outbounds: &str) -> Config {
+    Config::from_json(&format!(
+        r#"{{"server": {{"listen": "0.0.0.0", "port": 443}}, "users": [],
+            "outbounds": [{}], "routing": {{"rules": [{}]}}}}"#,
+        outbounds, routing
+    ))
+    .unwrap()
+}
+
+fn router(rules: &str) -> Router {
+    let config = config(
+        rules,
+        r#"{"tag": "hop", "protocol": "socks", "server": "127.0.0.1:1080"}"#,
+    );
+    Router::new(&config.routing, &config.outbounds).unwrap()
+}
+
+fn domain(name: &str) -> Address {
+    Address::Domain(Bytes::copy_from_slice(name.as_bytes()))
+}
+
+fn ip(addr: &str) -> Address {
+    match addr.parse().unwrap() {
+        std::net::IpAddr::V4(v4) => Address::Ipv4(v4),
+        std::net::IpAddr::V6(v6) => Address::Ipv6(v6),
+    }
+}
+
+/// 命中的规则序号
+fn hit(router: &Router, address: &Address, port: u16) -> Option<usize> {
+    router
+        .route(&Uuid::nil(), address, port)
+        .map(|(index, _)| index)
+}
+
+// ============================================================================
+// 条件匹配
+// ============================================================================
+
+#[test]
+fn test_domain_conditions() {
+    let router = router(
+        r#"{"domain_suffix": ["example.com"], "action": "block"},
+           {"domain_keyword": ["tracker"], "action": "block"},
+           {"domain_regex": ["^ads?\\d*\\.(net|org)$"], "action": "block"}"#,
+    );
+    assert_eq!(hit(&router, &domain("example.com"), 443), Some(0));
+    assert_eq!(hit(&router, &domain("WWW.Example.COM."), 443), Some(0));
+    assert_eq!(hit(&router, &domain("notexample.com"), 443), None);
+    assert_eq!(hit(&router, &domain("cdn.tracker.io"), 443), Some(1));
+    assert_eq!(hit(&router, &domain("ad.net"), 443), Some(2));
+    assert_eq!(hit(&router, &domain("ads42.org"), 443), Some(2));
+    assert_eq!(hit(&router, &domain("ads.org.cn"), 443), None);
+    // 域名条件不匹配 IP 目标
+    assert_eq!(hit(&router, &ip("93.184.216.34"), 443), None);
+}
+
+#[test]
+fn test_ip_cidr_conditions() {
+    let router =
+        router(r#"{"ip_cidr": ["10.0.0.0/8", "192.168.1.1", "fd00::/8"], "action": "direct"}"#);
+    assert_eq!(hit(&router, &ip("10.20.30.40"), 80), Some(0));
+    assert_eq!(hit(&router, &ip("11.0.0.1"), 80), None);
+    assert_eq!(hit(&router, &ip("192.168.1.1"), 80), Some(0));
+    assert_eq!(hit(&router, &ip("192.168.1.2"), 80), None);
+    assert_eq!(hit(&router, &ip("fd12:3456::1"), 80), Some(0));
+    // IPv4 映射地址按 IPv4 匹配
+    assert_eq!(hit(&router, &ip("::ffff:10.1.2.3"), 80), Some(0));
+    // IP 网段不匹配域名目标
+    assert_eq!(hit(&router, &domain("10.0.0.1.nip.io"), 80), None);
+}
+
+#[test]
+fn test_port_and_user_conditions() {
+    let router = router(&format!(
+        r#"{{"port": "25, 465-587", "action": "block"}},
+           {{"users": ["{ALICE}"], "domain_suffix": ["internal.corp"], "action": "outbound:hop"}}"#
+    ));
+    assert_eq!(hit(&router, &domain("mail.example.com"), 25), Some(0));
+    assert_eq!(hit(&router, &domain("mail.example.com"), 500), Some(0));
+    assert_eq!(hit(&router, &domain("mail.example.com"), 588), None);
+
+    let alice = Uuid::parse_str(ALICE).unwrap();
+    let target = domain("git.internal.corp");
+    let (index, action) = router.route(&alice, &target, 443).unwrap();
+    assert_eq!(index, 1);
+    assert!(matches!(action, RouteAction::Outbound(o) if o.tag() == "hop"));
+    // 其他用户不命中
+    assert!(router.route(&Uuid::new_v4(), &target, 443).is_none());
+}
+
+#[test]
+fn test_first_matching_rule_wins() {
+    let router = router(
+        r#"{"domain_suffix": ["safe.example.com"], "action": "direct"},
+           {"domain_suffix": ["example.com"], "ip_cidr": ["1.1.1.1"], "action": "block"},
+           {"action": "outbound:hop"}"#,
+    );
+    let nil = Uuid::nil();
+    assert!(matches!(
+        router.route(&nil, &domain("safe.example.com"), 443),
+        Some((0, RouteAction::Direct))
+    ));
+    assert!(matches!(
+        router.route(&nil, &domain("www.example.com"), 443),
+        Some((1, RouteAction::Block))
+    ));
+    // 同一规则的域名与 IP 条件满足其一即可
+    assert!(matches!(
+        router.route(&nil, &ip("1.1.1.1"), 53),
+        Some((1, RouteAction::Block))
+    ));
+    // 没有条件的规则匹配所有目标
+    assert!(matches!(
+        router.route(&nil, &domain("other.org"), 443),
+        Some((2, RouteAction::Outbound(_)))
+    ));
+}
+
+#[test]
+fn test_domain_regex_syntax() {
+    let cases = [
+        ("^[a-z]+-\\d{2,3}\\.cdn\\.", "edge-42.cdn.example.com", true),
+        ("^[a-z]+-\\d{2,3}\\.cdn\\.", "edge-4.cdn.example.com", false),
+        (
+            "(?:^|\\.)google\\.(com|co\\.uk)$",
+            "mail.google.co.uk",
+            true,
+        ),
+        ("(?:^|\\.)google\\.(com|co\\.uk)$", "notgoogle.com", false),
+        ("[^.]+\\.example\\.com$", "a.example.com", true),
+        ("^example\\.com$", "www.example.com", false),
+        ("a.*b.*c", "xaybzc", true),
+        ("^(ab)+$", "ababab", true),
+        ("^(ab)+$", "ababa", false),
+        ("^(a*)*$", "aaaa", true),
+        ("^(a|ab)*c$", "abaabc", true),
+        ("^x{2,3}$", "xxxx", false),
+        ("^(\\w+\\.)?example\\.com$", "example.com", true),
+    ];
+    for (pattern, input, expected) in cases {
+        let rule = format!(
+            r#"{{"domain_regex": [{}], "action": "block"}}"#,
+            serde_json::to_string(pattern).unwrap()
+        );
+        let matched = hit(&router(&rule), &domain(input), 443).is_some();
+        assert_eq!(matched, expected, "{} on {}", pattern, input);
+    }
+}
+
+#[test]
+fn test_domain_regex_nested_quantifiers_linear() {
+    let router = router(r#"{"domain_regex": ["^(.*\\.)*example\\.com$"], "action": "block"}"#);
+    // 回溯实现在该输入上需要指数时间
+    let input = format!("{}x", "a.".repeat(120));
+    let started = std::time::Instant::now();
+    assert!(hit(&router, &domain(&input), 443).is_none());
+    assert!(hit(&router, &domain("a.b.example.com"), 443).is_some());
+    assert!(started.elapsed() < std::time::Duration::from_secs(1));
+}
+
+// ============================================================================
+// 配置校验
+// ============================================================================
+
+#[test]
+fn test_routing_config_errors() {
+    let outbounds = r#"{"tag": "hop", "protocol": "socks", "server": "127.0.0.1:1080"},
+        {"tag": "up", "server": "example.com:443", "uuid": "550e8400-e29b-41d4-a716-446655440000"}"#;
+    let cases = [
+        (
+            r#"{"action": "reject"}"#,
+            "routing.rules[0].action: unknown action",
+        ),
+        (r#"{"action": "outbound:none"}"#, "unknown outbound 'none'"),
+        (r#"{"action": "outbound:up"}"#, "must be socks or http"),
+        (
+            r#"{"action": "direct"}, {"domain_regex": ["(ads"], "action": "block"}"#,
+            "routing.rules[1].domain_regex: invalid regex '(ads'",
+        ),
+        (
+            r#"{"domain_regex": ["*.ads"], "action": "block"}"#,
+            "nothing to repeat",
+        ),
+        (
+            r#"{"domain_regex": ["(a{1000}){1000}"], "action": "block"}"#,
+            "pattern too large",
+        ),
+        (
+            r#"{"ip_cidr": ["10.0.0.0/33"], "action": "block"}"#,
+            "routing.rules[0].ip_cidr: invalid CIDR",
+        ),
+        (
+            r#"{"port": "80-20", "action": "block"}"#,
+            "routing.rules[0].port: invalid port list",
+        ),
+        (
+            r#"{"users": ["alice"], "action": "block"}"#,
+            "routing.rules[0].users: invalid UUID",
+        ),
+    ];
+    for (rules, expected) in cases {
+        let err = config(rules, outbounds).validate().unwrap_err();
+        assert!(err.to_string().contains(expected), "{}: {}", expected, err);
+    }
+    assert!(config(r#"{"action": "outbound:hop"}"#, outbounds)
+        .validate()
+        .is_ok());
+}
+
+// ============================================================================
+// 会话接入
+// ============================================================================
+
+fn request(address: Address, port: u16) -> VlessRequest {
+    VlessRequest::new(Uuid::parse_str(ALICE).unwrap(), Command::Tcp, address, port)
+}
+
+#[tokio::test]
+async fn test_session_routing_actions() {
+    let target = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = target.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (mut stream, _) = target.accept().await.unwrap();
+        stream.write_all(b"hi").await.unwrap();
+    });
+
+    let services = SessionServices {
+        router: Some(Arc::new(router(
+            r#"{"domain_suffix": ["blocked.test"], "action": "block"},
+               {"ip_cidr": ["127.0.0.0/8"], "action": "direct"}"#,
+        ))),
+        ..Default::default()
+    };
+
+    let blocked = request(domain("ads.blocked.test"), 443);
+    let route = services.route(&blocked);
+    let err = services.ensure_routable(&blocked, &route).unwrap_err();
+    assert!(err.to_string().contains("routing rule 0"), "{}", err);
+    assert!(services
+        .connect_request(&blocked, &route, &PerformanceConfig::default())
+        .await
+        .is_err());
+
+    let direct = request(ip("127.0.0.1"), port);
+    let route = services.route(&direct);
+    assert!(services.ensure_routable(&direct, &route).is_ok());
+    let (mut stream, _) = services
+        .connect_request(&direct, &route, &PerformanceConfig::default())
+        .await
+        .unwrap();
+    let mut greeting = [0u8; 2];
+    stream.read_exact(&mut greeting).await.unwrap();
+    assert_eq!(&greeting, b"hi");
+}
+
+// ============================================================================
+// 域名解析方式
+// ============================================================================
+
+#[test]
+fn test_domain_strategy_config() {
+    let parse = |strategy: &str| {
+        Config::from_json(&format!(
+            r#"{{"server": {{"listen": "0.0.0.0", "port": 443{}}}, "users": []}}"#,
+            strategy
+        ))
+    };
+    assert_eq!(
+        parse("").unwrap().server.domain_strategy,
+        DomainStrategy::AsIs
+    );
+    for (name, strategy) in [
+        ("AsIs", DomainStrategy::AsIs),
+        ("UseIP", DomainStrategy::UseIp),
+        ("UseIPv4", DomainStrategy::UseIpv4),
+        ("UseIPv6", DomainStrategy::UseIpv6),
+    ] {
+        let config = parse(&format!(r#", "domain_strategy": "{}""#, name)).unwrap();
+        assert_eq!(config.server.domain_strategy, strategy);
+    }
+    assert!(parse(r#", "domain_strategy": "useip""#).is_err());
+}
+
+#[tokio::test]
+async fn test_domain_strategy_before_routing() {
+    let mut services = SessionServices {
+        router: Some(Arc::new(router(
+            r#"{"ip_cidr": ["127.0.0.0/8"], "action": "block"}"#,
+        ))),
+        ..Default::default()
+    };
+
+    // AsIs：域名原样交给路由，IP 规则不匹配
+    let mut request = request(domain("localhost"), 80);
+    services.apply_domain_strategy(&mut request).await.unwrap();
+    assert_eq!(request.address, domain("localhost"));
+    assert!(services
+        .ensure_routable(&request, &services.route(&request))
+        .is_ok());
+
+    // UseIPv4：路由前解析为 IPv4，按 IP 规则匹配
+    services.domain_strategy = DomainStrategy::UseIpv4;
+    services.apply_domain_strategy(&mut request).await.unwrap();
+    assert_eq!(request.address, ip("127.0.0.1"));
+    assert!(services
+        .ensure_routable(&request, &services.route(&request))
+        .is_err());
+
+    // IP 目标不受影响
+    let mut literal = self::request(ip("10.0.0.1"), 22);
+    services.apply_domain_strategy(&mut literal).await.unwrap();
+    assert_eq!(literal.address, ip("10.0.0.1"));
+
+    let mut missing = self::request(domain("nonexistent.invalid"), 80);
+    assert!(services.apply_domain_strategy(&mut missing).await.is_err());
+}
@@ -0,0 +1,67 @@
+//! 运行时指标测试
+
+use std::sync::Arc;
+use vless_rust::runtime_stats::{snapshot, ListenerTasks};
+
+// ============================================================================
+// 入站任务计数
+// ============================================================================
+
+#[test]
+fn test_task_guard_counts_active_and_total() {
+    let tasks = ListenerTasks::default();
+    let counter = tasks.register("main");
+
+    let first = counter.enter();
+    let second = counter.enter();
+    assert_eq!(counter.active(), 2);
+    drop(first);
+    assert_eq!(counter.active(), 1);
+    drop(second);
+
+    let stats = tasks.snapshot();
+    assert_eq!(stats.len(), 1);
+    assert_eq!(stats[0].name, "main");
+    assert_eq!(stats[0].active, 0);
+    assert_eq!(stats[0].total, 2);
+}
+
+#[test]
+fn test_listeners_share_counter_by_name() {
+    let tasks = ListenerTasks::default();
+    let a = tasks.register("forward:db");
+    let b = tasks.register("forward:db");
+    assert!(Arc::ptr_eq(&a, &b));
+    tasks.register("main");
+
+    let names: Vec<_> = tasks.snapshot().into_iter().map(|s| s.name).collect();
+    assert_eq!(names, ["forward:db", "main"]);
+}
+
+// ============================================================================
+// 运行时快照
+// ============================================================================
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_runtime_snapshot() {
+    let tasks = ListenerTasks::default();
+    let counter = tasks.register("main");
+    let _task = counter.enter();
+    let (tx, rx) = tokio::sync::oneshot::channel::<()>();
+    let pending = tokio::spawn(async move {
+        let _ = rx.await;
+    });
+
+    let snapshot = snapshot(&tasks);
+    assert_eq!(snapshot.workers, 2);
+    assert_eq!(snapshot.worker_stats.len(), 2);
+    assert!(snapshot.alive_tasks >= 1);
+    assert_eq!(snapshot.listeners[0].active, 1);
+
+    let json = serde_json::to_value(&snapshot).unwrap();
+    assert!(json["global_queue_depth"].is_u64());
+    assert!(json["worker_stats"][0]["busy_ms"].is_u64());
+
+    tx.send(()).unwrap();
+    pending.await.unwrap();
+}
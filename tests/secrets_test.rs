@@ -0,0 +1,120 @@
+//! 配置加密测试
+
+use tempfile::TempDir;
+use vless_rust::config::Config;
+use vless_rust::secrets::{
+    decrypt_config, encrypt_config, encrypt_config_file, is_encrypted, ConfigKey,
+};
+
+const CONFIG: &str = r#"{
+    "server": {"listen": "0.0.0.0", "port": 443},
+    "users": [
+        {"uuid": "550e8400-e29b-41d4-a716-446655440000", "email": "alice@example.com"},
+        {"uuid": "6ba7b810-9dad-11d1-80b4-00c04fd430c8"}
+    ],
+    "api": {"token": "s3cret"}
+}"#;
+
+// ============================================================================
+// 密钥与单值加解密
+// ============================================================================
+
+#[test]
+fn test_key_roundtrip_and_validation() {
+    let key = ConfigKey::generate().unwrap();
+    let encoded = key.to_base64();
+    assert_eq!(
+        ConfigKey::from_base64(&encoded).unwrap().to_base64(),
+        encoded
+    );
+    assert_eq!(format!("{:?}", key), "ConfigKey(..)");
+
+    assert!(ConfigKey::from_base64("not base64!").is_err());
+    assert!(ConfigKey::from_base64("c2hvcnQ=").is_err());
+}
+
+#[test]
+fn test_encrypt_decrypt_value() {
+    let key = ConfigKey::generate().unwrap();
+    let sealed = key.encrypt_value("hello").unwrap();
+    assert!(is_encrypted(&sealed));
+    assert!(!sealed.contains("hello"));
+    assert_eq!(key.decrypt_value(&sealed).unwrap(), "hello");
+
+    // 随机 nonce：同一明文两次加密结果不同
+    assert_ne!(key.encrypt_value("hello").unwrap(), sealed);
+
+    let other = ConfigKey::generate().unwrap();
+    assert!(other.decrypt_value(&sealed).is_err());
+    assert!(key.decrypt_value("hello").is_err());
+    assert!(key.decrypt_value("enc:v1:AAAA").is_err());
+}
+
+// ============================================================================
+// 配置文件
+// ============================================================================
+
+#[test]
+fn test_encrypt_and_decrypt_config() {
+    let key = ConfigKey::generate().unwrap();
+    let mut config = Config::from_json(CONFIG).unwrap();
+
+    assert_eq!(encrypt_config(&mut config, None, &key).unwrap(), 3);
+    assert!(config.users.iter().all(|u| is_encrypted(&u.uuid)));
+    assert!(is_encrypted(config.api.token.as_deref().unwrap()));
+    // 邮箱保持明文（用于日志与链接别名）
+    assert_eq!(config.users[0].email.as_deref(), Some("alice@example.com"));
+
+    let mut loaded = Config::from_json(&config.to_json().unwrap()).unwrap();
+    assert!(decrypt_config(&mut loaded, None).is_err());
+    assert_eq!(decrypt_config(&mut loaded, Some(&key)).unwrap(), 3);
+    assert_eq!(loaded.users[0].uuid, "550e8400-e29b-41d4-a716-446655440000");
+    assert_eq!(loaded.api.token.as_deref(), Some("s3cret"));
+}
+
+#[test]
+fn test_plain_config_needs_no_key() {
+    let mut config = Config::from_json(CONFIG).unwrap();
+    assert_eq!(decrypt_config(&mut config, None).unwrap(), 0);
+}
+
+#[test]
+fn test_rotate_key() {
+    let old_key = ConfigKey::generate().unwrap();
+    let new_key = ConfigKey::generate().unwrap();
+    let mut config = Config::from_json(CONFIG).unwrap();
+    encrypt_config(&mut config, None, &old_key).unwrap();
+
+    // 旧密文需要旧密钥才能轮换
+    let mut without_old = config.clone();
+    assert!(encrypt_config(&mut without_old, None, &new_key).is_err());
+
+    encrypt_config(&mut config, Some(&old_key), &new_key).unwrap();
+    let mut rotated = config.clone();
+    assert!(decrypt_config(&mut rotated, Some(&old_key)).is_err());
+    decrypt_config(&mut config, Some(&new_key)).unwrap();
+    assert_eq!(config.users[1].uuid, "6ba7b810-9dad-11d1-80b4-00c04fd430c8");
+}
+
+#[test]
+fn test_encrypt_config_file_with_new_key_file() {
+    let dir = TempDir::new().unwrap();
+    let config_path = dir.path().join("config.json");
+    let key_path = dir.path().join("config.key");
+    let key = ConfigKey::generate().unwrap();
+    std::fs::write(&config_path, CONFIG).unwrap();
+    std::fs::write(&key_path, format!("{}\n", key.to_base64())).unwrap();
+
+    let count = encrypt_config_file(
+        config_path.to_str().unwrap(),
+        Some(key_path.to_str().unwrap()),
+    )
+    .unwrap();
+    assert_eq!(count, 3);
+
+    let written = std::fs::read_to_string(&config_path).unwrap();
+    assert!(!written.contains("550e8400"));
+    let mut config = Config::from_json(&written).unwrap();
+    decrypt_config(&mut config, Some(&key)).unwrap();
+    assert_eq!(config.users[0].uuid, "550e8400-e29b-41d4-a716-446655440000");
+}
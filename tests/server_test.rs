@@ -1,10 +1,19 @@
 //! 服务器模块集成测试
 
+use bytes::Bytes;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
 use uuid::Uuid;
-use vless_rust::config::ProtocolType;
-use vless_rust::server::ServerConfig;
+use vless_rust::api::{handle_admin_request, AdminConfig};
+use vless_rust::config::{Config, InboundProtocol, PerformanceConfig, ProtocolType};
+use vless_rust::maintenance::{Maintenance, MaintenanceMode};
+use vless_rust::protocol::{Address, Command, VlessRequest};
+use vless_rust::readiness::AcceptGate;
+use vless_rust::runtime_stats::ListenerTasks;
+use vless_rust::server::{classify_inbound, DestinationPolicy, ServerConfig};
+use vless_rust::session::SessionServices;
 
 // ============================================================================
 // 服务器配置创建测试
@@ -220,3 +229,428 @@ fn test_server_config_clone() {
     assert_eq!(cloned.users.len(), config.users.len());
     assert!(cloned.users.contains(&uuid));
 }
+
+// ============================================================================
+// 分享链接测试
+// ============================================================================
+
+fn config_with_users(protocol: ProtocolType) -> (ServerConfig, Uuid, Uuid) {
+    let addr: SocketAddr = "0.0.0.0:443".parse().unwrap();
+    let mut config = ServerConfig::new(
+        addr,
+        protocol,
+        "/vless".to_string(),
+        Some("vpn.example.com".to_string()),
+        443,
+    );
+    let (alice, bob) = (Uuid::new_v4(), Uuid::new_v4());
+    config.add_user_with_email(bob, Some("bob@example.com".to_string()));
+    config.add_user_with_email(alice, Some("alice@example.com".to_string()));
+    (config, alice, bob)
+}
+
+#[test]
+fn test_user_links_cover_all_users() {
+    let (config, alice, bob) = config_with_users(ProtocolType::Tcp);
+    let links = config.user_links();
+
+    assert_eq!(links.len(), 2);
+    assert_eq!(links[0].uuid, alice);
+    assert_eq!(links[1].uuid, bob);
+    assert!(links.iter().all(|l| l.transport == "tcp"));
+    assert!(links[1]
+        .vless
+        .starts_with(&format!("vless://{}@vpn.example.com:443?", bob)));
+    assert!(links[1].vless.ends_with("#bob%40example.com"));
+}
+
+#[test]
+fn test_user_links_websocket() {
+    let (config, _, _) = config_with_users(ProtocolType::WebSocket);
+    let links = config.user_links();
+    assert!(links.iter().all(|l| l.transport == "ws"));
+    assert!(links[0].vless.contains("path=%2Fvless"));
+}
+
+#[test]
+fn test_user_links_declare_ws_early_data() {
+    let (config, _, _) = config_with_users(ProtocolType::WebSocket);
+    let links = config.with_ws_early_data(2048).user_links();
+    assert!(links[0].vless.contains("path=%2Fvless%3Fed%3D2048"));
+}
+
+async fn admin_get(config: AdminConfig, path: &str) -> String {
+    admin_request(config, "GET", path).await
+}
+
+async fn admin_request(config: AdminConfig, method: &str, path: &str) -> String {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 1024];
+        let n = stream.read(&mut buf).await.unwrap();
+        let _ = handle_admin_request(stream, &buf[..n], &config).await;
+    });
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    let request = format!(
+        "{} {} HTTP/1.1\r\nAuthorization: Bearer s3cret\r\n\r\n",
+        method, path
+    );
+    client.write_all(request.as_bytes()).await.unwrap();
+    let mut response = String::new();
+    client.read_to_string(&mut response).await.unwrap();
+    response
+}
+
+#[tokio::test]
+async fn test_admin_user_url_endpoint() {
+    let (config, alice, _) = config_with_users(ProtocolType::Tcp);
+    let admin = || AdminConfig {
+        token: "s3cret".to_string(),
+        destinations: None,
+        user_stats: None,
+        user_links: config.user_links(),
+        blocklist: None,
+        capture: None,
+        reload_preview: None,
+        overhead: Default::default(),
+        listener_tasks: Default::default(),
+        resolver: Default::default(),
+        route_stats: Default::default(),
+        gate: Default::default(),
+        maintenance: Default::default(),
+    };
+
+    let response = admin_get(admin(), &format!("/api/users/{}/url", alice)).await;
+    assert!(response.starts_with("HTTP/1.1 200"));
+    let body: serde_json::Value =
+        serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+    assert_eq!(body["email"], "alice@example.com");
+    assert_eq!(body["transport"], "tcp");
+    assert!(body["vless"].as_str().unwrap().contains(&alice.to_string()));
+
+    let response = admin_get(admin(), &format!("/api/users/{}/url", Uuid::new_v4())).await;
+    assert!(response.starts_with("HTTP/1.1 404"));
+    let response = admin_get(admin(), "/api/users/not-a-uuid/url").await;
+    assert!(response.starts_with("HTTP/1.1 404"));
+}
+
+#[tokio::test]
+async fn test_admin_runtime_endpoint() {
+    let listener_tasks = Arc::new(ListenerTasks::default());
+    let _task = listener_tasks.register("main").enter();
+    let admin = AdminConfig {
+        token: "s3cret".to_string(),
+        destinations: None,
+        user_stats: None,
+        user_links: Vec::new(),
+        blocklist: None,
+        capture: None,
+        reload_preview: None,
+        overhead: Default::default(),
+        listener_tasks,
+        resolver: Default::default(),
+        route_stats: Default::default(),
+        gate: Default::default(),
+        maintenance: Default::default(),
+    };
+
+    let response = admin_get(admin, "/api/runtime").await;
+    assert!(response.starts_with("HTTP/1.1 200"));
+    let body: serde_json::Value =
+        serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+    assert!(body["workers"].as_u64().unwrap() >= 1);
+    assert_eq!(body["listeners"][0]["name"], "main");
+    assert_eq!(body["listeners"][0]["active"], 1);
+}
+
+#[tokio::test]
+async fn test_admin_stats_endpoint() {
+    let resolver = Arc::new(vless_rust::dns::Resolver::default());
+    resolver.lookup("localhost").await.unwrap();
+    resolver.lookup("localhost").await.unwrap();
+    let admin = AdminConfig {
+        token: "s3cret".to_string(),
+        destinations: None,
+        user_stats: None,
+        user_links: Vec::new(),
+        blocklist: None,
+        capture: None,
+        reload_preview: None,
+        overhead: Default::default(),
+        listener_tasks: Default::default(),
+        resolver,
+        route_stats: Default::default(),
+        gate: Default::default(),
+        maintenance: Default::default(),
+    };
+
+    let response = admin_get(admin, "/api/stats").await;
+    assert!(response.starts_with("HTTP/1.1 200"));
+    let body: serde_json::Value =
+        serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+    assert_eq!(body["resolver"]["entries"], 1);
+    assert_eq!(body["resolver"]["misses"], 1);
+    assert_eq!(body["resolver"]["hits"], 1);
+    assert_eq!(body["routes"], serde_json::json!([]));
+    assert_eq!(body["rules"], serde_json::json!([]));
+}
+
+#[tokio::test]
+async fn test_admin_pause_resume_endpoints() {
+    let gate = Arc::new(AcceptGate::default());
+    let admin = || AdminConfig {
+        token: "s3cret".to_string(),
+        destinations: None,
+        user_stats: None,
+        user_links: Vec::new(),
+        blocklist: None,
+        capture: None,
+        reload_preview: None,
+        overhead: Default::default(),
+        listener_tasks: Default::default(),
+        resolver: Default::default(),
+        route_stats: Default::default(),
+        gate: Arc::clone(&gate),
+        maintenance: Default::default(),
+    };
+    let body = |response: &str| -> serde_json::Value {
+        serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap()
+    };
+
+    let response = admin_get(admin(), "/api/pause").await;
+    assert!(response.starts_with("HTTP/1.1 405"));
+    assert!(!gate.is_paused());
+
+    let response = admin_request(admin(), "POST", "/api/pause").await;
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert_eq!(body(&response)["paused"], true);
+    assert_eq!(body(&response)["changed"], true);
+    assert!(gate.is_paused());
+    assert!(!gate.admit());
+
+    let response = admin_request(admin(), "POST", "/api/pause").await;
+    assert_eq!(body(&response)["changed"], false);
+    assert_eq!(body(&response)["rejected"], 1);
+
+    let response = admin_request(admin(), "POST", "/api/resume").await;
+    assert_eq!(body(&response)["paused"], false);
+    assert_eq!(body(&response)["changed"], true);
+    assert!(gate.admit());
+}
+
+#[tokio::test]
+async fn test_admin_maintenance_endpoint() {
+    let maintenance = Arc::new(Maintenance::default());
+    let admin = || AdminConfig {
+        token: "s3cret".to_string(),
+        destinations: None,
+        user_stats: None,
+        user_links: Vec::new(),
+        blocklist: None,
+        capture: None,
+        reload_preview: None,
+        overhead: Default::default(),
+        listener_tasks: Default::default(),
+        resolver: Default::default(),
+        route_stats: Default::default(),
+        gate: Default::default(),
+        maintenance: Arc::clone(&maintenance),
+    };
+    let body = |response: &str| -> serde_json::Value {
+        serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap()
+    };
+
+    let response = admin_get(admin(), "/api/maintenance").await;
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert_eq!(body(&response)["active"], false);
+    assert_eq!(body(&response)["mode"], "scheduled");
+
+    let response = admin_request(admin(), "POST", "/api/maintenance?mode=on").await;
+    assert!(response.starts_with("HTTP/1.1 200"));
+    assert_eq!(body(&response)["active"], true);
+    assert_eq!(body(&response)["changed"], true);
+    assert!(!maintenance.admit());
+
+    let response = admin_request(admin(), "POST", "/api/maintenance?mode=later").await;
+    assert!(response.starts_with("HTTP/1.1 400"));
+    assert_eq!(maintenance.mode(), MaintenanceMode::On);
+
+    let response = admin_request(admin(), "POST", "/api/maintenance?mode=off").await;
+    assert_eq!(body(&response)["active"], false);
+    assert_eq!(body(&response)["rejected"], 1);
+    assert!(maintenance.admit());
+}
+
+// ============================================================================
+// 入站协议识别
+// ============================================================================
+
+#[test]
+fn test_classify_inbound() {
+    let all = InboundProtocol::ALL;
+    let tls = [0x16, 0x03, 0x01, 0x02, 0x00];
+    let http = b"GET / HTTP/1.1\r\n";
+    let vless = [0x00, 0x11, 0x22];
+
+    let tcp = |data: &[u8], protocols: &[InboundProtocol]| {
+        classify_inbound(data, protocols, ProtocolType::Tcp)
+    };
+    assert_eq!(tcp(&tls, &all), Some(InboundProtocol::Tls));
+    assert_eq!(tcp(http, &all), Some(InboundProtocol::Http));
+    assert_eq!(tcp(&vless, &all), Some(InboundProtocol::Vless));
+    // 任意明文不再当作 VLESS
+    assert_eq!(tcp(b"\x05\x01\x00", &all), None);
+
+    // 未启用的协议不识别
+    assert_eq!(tcp(http, &[InboundProtocol::Vless]), None);
+    assert_eq!(tcp(&vless, &[InboundProtocol::Tls]), None);
+
+    // WS 模式下 VLESS 经 HTTP 升级到达，按配置顺序命中
+    let ws =
+        |protocols: &[InboundProtocol]| classify_inbound(http, protocols, ProtocolType::WebSocket);
+    assert_eq!(ws(&all), Some(InboundProtocol::Http));
+    assert_eq!(
+        ws(&[InboundProtocol::Vless, InboundProtocol::Http]),
+        Some(InboundProtocol::Vless)
+    );
+    assert_eq!(
+        classify_inbound(&vless, &all, ProtocolType::WebSocket),
+        None
+    );
+}
+
+#[test]
+fn test_inbound_protocol_config() {
+    let parse = |server_extra: &str, extra: &str| {
+        Config::from_json(&format!(
+            r#"{{"server": {{"listen": "0.0.0.0", "port": 443{}}}, "users": []{}}}"#,
+            server_extra, extra
+        ))
+        .unwrap()
+    };
+
+    let config = parse("", "");
+    assert_eq!(config.server.effective_protocols(), InboundProtocol::ALL);
+    assert!(config.validate().is_ok());
+
+    let config = parse(r#", "inbound_protocols": ["vless"]"#, "");
+    assert_eq!(
+        config.server.effective_protocols(),
+        [InboundProtocol::Vless]
+    );
+    assert!(config.validate().is_ok());
+
+    // 只接受 TLS 需要 SNI 分流
+    let config = parse(r#", "strict_tls_only": true"#, "");
+    assert_eq!(config.server.effective_protocols(), [InboundProtocol::Tls]);
+    let err = config.validate().unwrap_err();
+    assert!(err.to_string().contains("server.strict_tls_only"));
+    let config = parse(
+        r#", "strict_tls_only": true"#,
+        r#", "sni_proxy": {"backend": "127.0.0.1:8443"}"#,
+    );
+    assert!(config.validate().is_ok());
+
+    assert!(parse(r#", "inbound_protocols": []"#, "")
+        .validate()
+        .is_err());
+}
+
+// ============================================================================
+// 目标地址策略
+// ============================================================================
+
+fn vless_request(address: Address, port: u16) -> VlessRequest {
+    VlessRequest::new(Uuid::nil(), Command::Tcp, address, port)
+}
+
+#[test]
+fn test_destination_policy() {
+    let bind: SocketAddr = "0.0.0.0:443".parse().unwrap();
+    let policy = DestinationPolicy::new(bind, Some("203.0.113.7"));
+    let blocked = |addr: &str| policy.is_blocked(addr.parse().unwrap());
+    for private in ["10.0.0.1", "127.0.0.1", "169.254.169.254", "::1", "fe80::1"] {
+        assert!(blocked(private), "{}", private);
+    }
+    assert!(blocked("203.0.113.7"));
+    assert!(!blocked("1.1.1.1"));
+    assert!(!blocked("2606:4700::1111"));
+
+    // 指定监听地址时同样拒绝，IPv4 映射地址按 IPv4 比较
+    let bind: SocketAddr = "198.51.100.20:443".parse().unwrap();
+    let policy = DestinationPolicy::new(bind, Some("vpn.example.com"));
+    assert!(policy.is_blocked("::ffff:198.51.100.20".parse().unwrap()));
+    let err = policy
+        .check("198.51.100.20:22".parse().unwrap())
+        .unwrap_err();
+    assert!(
+        err.to_string()
+            .contains("blocked by private address policy: 198.51.100.20:22"),
+        "{}",
+        err
+    );
+
+    let config =
+        Config::from_json(r#"{"server": {"listen": "0.0.0.0", "port": 443}, "users": []}"#)
+            .unwrap();
+    assert!(config.server.block_private_destinations);
+}
+
+#[tokio::test]
+async fn test_session_destination_policy() {
+    let target = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = target.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        while let Ok((mut stream, _)) = target.accept().await {
+            let _ = stream.write_all(b"hi").await;
+        }
+    });
+    let perf = PerformanceConfig::default();
+    let bind: SocketAddr = "0.0.0.0:443".parse().unwrap();
+    let services = SessionServices {
+        destination_policy: Some(Arc::new(DestinationPolicy::new(bind, None))),
+        ..Default::default()
+    };
+
+    // IP 目标在握手后立即拒绝，域名目标在解析后、建连前拒绝
+    let loopback = vless_request(Address::Ipv4([127, 0, 0, 1].into()), port);
+    assert!(services.ensure_destination(&loopback).is_err());
+    let localhost = vless_request(Address::Domain(Bytes::from_static(b"localhost")), port);
+    assert!(services.ensure_destination(&localhost).is_ok());
+    for request in [&loopback, &localhost] {
+        let err = services
+            .connect_request(request, &None, &perf)
+            .await
+            .unwrap_err();
+        assert!(
+            err.to_string().contains("private address policy"),
+            "{}",
+            err
+        );
+    }
+    let err = services
+        .resolve_udp_target(&localhost.address, 53)
+        .await
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("private address policy"),
+        "{}",
+        err
+    );
+
+    // 运营者配置的转发目标不受限制
+    assert!(services
+        .connect_target(&loopback.address, port, &perf)
+        .await
+        .is_ok());
+    // 关闭策略后可以连接
+    let services = SessionServices::default();
+    assert!(services.ensure_destination(&loopback).is_ok());
+    assert!(services
+        .connect_request(&loopback, &None, &perf)
+        .await
+        .is_ok());
+}
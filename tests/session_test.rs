@@ -0,0 +1,137 @@
+//! 会话记录模块测试
+
+use bytes::Bytes;
+use std::collections::VecDeque;
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncRead, AsyncWriteExt, ReadBuf};
+use uuid::Uuid;
+use vless_rust::address::ConnectTiming;
+use vless_rust::protocol::Address;
+use vless_rust::session::{
+    copy_counted, copy_with_ttfb, format_destination, user_label, SessionRecord,
+};
+
+/// 依次返回各数据块，之后以连接重置结束
+struct ResetReader(VecDeque<&'static [u8]>);
+
+impl AsyncRead for ResetReader {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        _cx: &mut Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        match self.0.pop_front() {
+            Some(chunk) => {
+                buf.put_slice(chunk);
+                Poll::Ready(Ok(()))
+            }
+            None => Poll::Ready(Err(std::io::ErrorKind::ConnectionReset.into())),
+        }
+    }
+}
+
+#[test]
+fn test_format_destination() {
+    assert_eq!(
+        format_destination(&Address::Ipv4(Ipv4Addr::new(1, 2, 3, 4)), 80),
+        "1.2.3.4:80"
+    );
+    assert_eq!(
+        format_destination(&Address::Ipv6(Ipv6Addr::LOCALHOST), 443),
+        "[::1]:443"
+    );
+    assert_eq!(
+        format_destination(&Address::Domain(Bytes::from_static(b"example.com")), 443),
+        "example.com:443"
+    );
+}
+
+#[test]
+fn test_user_label_prefers_email() {
+    let uuid = Uuid::new_v4();
+    let email: Arc<str> = Arc::from("user@example.com");
+    assert_eq!(user_label(&uuid, Some(&email)), "user@example.com");
+    assert_eq!(user_label(&uuid, None), uuid.to_string());
+}
+
+#[test]
+fn test_session_record_from_timing() {
+    let timing = ConnectTiming {
+        resolved: Some("93.184.216.34:443".parse::<SocketAddr>().unwrap()),
+        dns: Duration::from_millis(12),
+        connect: Duration::from_millis(34),
+    };
+    let mut record = SessionRecord::new(
+        Uuid::nil(),
+        "user".to_string(),
+        "tcp",
+        "example.com:443".to_string(),
+        &timing,
+        Instant::now(),
+    );
+    record.set_ttfb(Some(Duration::from_millis(56)));
+    record.finish();
+
+    assert_eq!(record.resolved_ip.as_deref(), Some("93.184.216.34"));
+    assert_eq!(record.dns_ms, 12);
+    assert_eq!(record.connect_ms, 34);
+    assert_eq!(record.ttfb_ms, Some(56));
+
+    let json = serde_json::to_value(&record).unwrap();
+    assert_eq!(json["dest"], "example.com:443");
+    assert!(json.get("started").is_none());
+}
+
+#[tokio::test]
+async fn test_copy_with_ttfb_counts_bytes() {
+    let (mut source_writer, mut source_reader) = tokio::io::duplex(64);
+    let (mut sink_writer, _sink_reader) = tokio::io::duplex(1024);
+
+    let since = Instant::now();
+    let producer = tokio::spawn(async move {
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        source_writer.write_all(b"hello ").await.unwrap();
+        source_writer.write_all(b"world").await.unwrap();
+    });
+
+    let (ttfb, bytes, result) = copy_with_ttfb(&mut source_reader, &mut sink_writer, since).await;
+    producer.await.unwrap();
+    assert!(result.is_ok());
+
+    assert!(ttfb.unwrap() >= Duration::from_millis(20));
+    assert_eq!(bytes, 11);
+}
+
+#[tokio::test]
+async fn test_copy_with_ttfb_no_data() {
+    let (source_writer, mut source_reader) = tokio::io::duplex(64);
+    let (mut sink_writer, _sink_reader) = tokio::io::duplex(64);
+    drop(source_writer);
+
+    let (ttfb, bytes, _) =
+        copy_with_ttfb(&mut source_reader, &mut sink_writer, Instant::now()).await;
+    assert!(ttfb.is_none());
+    assert_eq!(bytes, 0);
+}
+
+#[tokio::test]
+async fn test_copy_counts_bytes_before_reset() {
+    let mut reader = ResetReader(VecDeque::from([&b"hello "[..], &b"world"[..]]));
+    let mut sink = Vec::new();
+    let (bytes, result) = copy_counted(&mut reader, &mut sink).await;
+    assert_eq!(bytes, 11);
+    assert_eq!(
+        result.unwrap_err().kind(),
+        std::io::ErrorKind::ConnectionReset
+    );
+
+    let mut reader = ResetReader(VecDeque::from([&b"hello "[..], &b"world"[..]]));
+    let (ttfb, bytes, result) = copy_with_ttfb(&mut reader, &mut sink, Instant::now()).await;
+    assert!(ttfb.is_some());
+    assert_eq!(bytes, 11);
+    assert!(result.is_err());
+}
@@ -0,0 +1,189 @@
+//! SNI 分流测试
+
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use vless_rust::config::{Config, SniProxyConfig, SniRoute};
+use vless_rust::sni_proxy::{is_tls_handshake, parse_sni, SniRouter};
+
+/// 构造带 server_name 扩展的最小 ClientHello 记录
+fn client_hello(sni: Option<&str>) -> Vec<u8> {
+    let mut extensions = Vec::new();
+    // 无关扩展（supported_versions）放在 SNI 之前，验证跳过逻辑
+    extensions.extend_from_slice(&[0x00, 0x2b, 0x00, 0x03, 0x02, 0x03, 0x04]);
+    if let Some(sni) = sni {
+        let name = sni.as_bytes();
+        let list_len = 3 + name.len();
+        extensions.extend_from_slice(&[0x00, 0x00]);
+        extensions.extend_from_slice(&((list_len + 2) as u16).to_be_bytes());
+        extensions.extend_from_slice(&(list_len as u16).to_be_bytes());
+        extensions.push(0);
+        extensions.extend_from_slice(&(name.len() as u16).to_be_bytes());
+        extensions.extend_from_slice(name);
+    }
+
+    let mut body = vec![0x03, 0x03];
+    body.extend_from_slice(&[0u8; 32]); // random
+    body.push(0); // session_id
+    body.extend_from_slice(&[0x00, 0x02, 0x13, 0x01]); // cipher_suites
+    body.extend_from_slice(&[0x01, 0x00]); // compression_methods
+    body.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+    body.extend_from_slice(&extensions);
+
+    let mut handshake = vec![0x01];
+    handshake.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+    handshake.extend_from_slice(&body);
+
+    let mut record = vec![0x16, 0x03, 0x01];
+    record.extend_from_slice(&(handshake.len() as u16).to_be_bytes());
+    record.extend_from_slice(&handshake);
+    record
+}
+
+fn router(backend: Option<&str>, routes: &[(&str, &str)]) -> SniRouter {
+    let config = SniProxyConfig {
+        backend: backend.map(String::from),
+        routes: routes
+            .iter()
+            .map(|(sni, backend)| SniRoute {
+                sni: sni.to_string(),
+                backend: backend.to_string(),
+            })
+            .collect(),
+    };
+    SniRouter::new(&config, vec!["proxy.example.com".to_string()])
+}
+
+// ============================================================================
+// ClientHello 解析
+// ============================================================================
+
+#[test]
+fn test_parse_sni() {
+    let hello = client_hello(Some("Blog.Example.com"));
+    assert!(is_tls_handshake(&hello));
+    assert_eq!(parse_sni(&hello).as_deref(), Some("blog.example.com"));
+    assert_eq!(parse_sni(&client_hello(None)), None);
+
+    // 数据不完整或不是 TLS 时返回 None
+    assert_eq!(parse_sni(&hello[..hello.len() - 4]), None);
+    assert!(!is_tls_handshake(b"GET / HTTP/1.1\r\n"));
+    assert_eq!(parse_sni(b"\x00\x11\x22"), None);
+}
+
+// ============================================================================
+// 路由
+// ============================================================================
+
+#[test]
+fn test_route_selection() {
+    let router = router(
+        Some("10.0.0.2:443"),
+        &[
+            ("*.internal.example", "10.0.0.3:443"),
+            ("api.example.com", "10.0.0.4:443"),
+        ],
+    );
+    assert_eq!(router.route(Some("www.example.com")), Some("10.0.0.2:443"));
+    assert_eq!(
+        router.route(Some("a.internal.example")),
+        Some("10.0.0.3:443")
+    );
+    assert_eq!(router.route(Some("internal.example")), Some("10.0.0.2:443"));
+    assert_eq!(router.route(Some("API.example.com")), Some("10.0.0.4:443"));
+    assert_eq!(router.route(None), Some("10.0.0.2:443"));
+    // 本服务域名不转发
+    assert_eq!(router.route(Some("proxy.example.com")), None);
+
+    let routes_only = self::router(None, &[("api.example.com", "10.0.0.4:443")]);
+    assert_eq!(routes_only.route(Some("www.example.com")), None);
+}
+
+#[test]
+fn test_config_validation() {
+    let config = Config::from_json(
+        r#"{
+            "server": {"listen": "0.0.0.0", "port": 443},
+            "users": [],
+            "sni_proxy": {
+                "backend": "10.0.0.2:443",
+                "routes": [{"sni": "*.example.com", "backend": "web.internal:8443"}]
+            }
+        }"#,
+    )
+    .unwrap();
+    assert!(config.sni_proxy.is_enabled());
+    assert!(config.validate().is_ok());
+    assert!(!SniProxyConfig::default().is_enabled());
+
+    let mut bad = config.clone();
+    bad.sni_proxy.backend = Some("10.0.0.2".to_string());
+    assert!(bad.validate().is_err());
+    let mut bad = config;
+    bad.sni_proxy.routes[0].sni = " ".to_string();
+    assert!(bad.validate().is_err());
+}
+
+// ============================================================================
+// 转发
+// ============================================================================
+
+#[tokio::test]
+async fn test_passthrough_forwards_client_hello() {
+    // 后端：读取完整 ClientHello 后回写固定响应
+    let backend = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let backend_addr = backend.local_addr().unwrap().to_string();
+    let hello = client_hello(Some("www.example.com"));
+    let expected = hello.clone();
+    tokio::spawn(async move {
+        let (mut conn, _) = backend.accept().await.unwrap();
+        let mut buf = vec![0u8; expected.len()];
+        conn.read_exact(&mut buf).await.unwrap();
+        assert_eq!(buf, expected);
+        conn.write_all(b"server-hello").await.unwrap();
+    });
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let router = router(Some(&backend_addr), &[]);
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        router.handle(stream).await.unwrap();
+    });
+
+    // 分两段发送，模拟跨分段的 ClientHello
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client.write_all(&hello[..20]).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    client.write_all(&hello[20..]).await.unwrap();
+
+    let mut response = Vec::new();
+    tokio::time::timeout(Duration::from_secs(5), client.read_to_end(&mut response))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(response, b"server-hello");
+}
+
+#[tokio::test]
+async fn test_own_domain_is_closed() {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let router = router(Some("127.0.0.1:9"), &[]);
+    tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        router.handle(stream).await.unwrap();
+    });
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client
+        .write_all(&client_hello(Some("proxy.example.com")))
+        .await
+        .unwrap();
+    // 未读取的 ClientHello 可能使关闭表现为 RST
+    let mut response = Vec::new();
+    let result = tokio::time::timeout(Duration::from_secs(5), client.read_to_end(&mut response))
+        .await
+        .unwrap();
+    assert!(result.is_err() || response.is_empty());
+}
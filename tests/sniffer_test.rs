@@ -0,0 +1,175 @@
+//! 流量嗅探测试：TLS SNI / HTTP Host 识别、排除列表、会话目标覆盖与配置
+
+use bytes::Bytes;
+use std::sync::Arc;
+use uuid::Uuid;
+use vless_rust::config::{Config, SniffProtocol, SniffingConfig};
+use vless_rust::protocol::{Address, Command, VlessRequest};
+use vless_rust::session::SessionServices;
+use vless_rust::sniffer::{sniff_http_host, Sniffer};
+
+/// 构造带 SNI 扩展的 TLS ClientHello 记录
+fn client_hello(sni: &str) -> Vec<u8> {
+    let name = sni.as_bytes();
+    let list_len = 3 + name.len();
+    let mut extensions = vec![0x00, 0x00];
+    extensions.extend_from_slice(&((list_len + 2) as u16).to_be_bytes());
+    extensions.extend_from_slice(&(list_len as u16).to_be_bytes());
+    extensions.push(0);
+    extensions.extend_from_slice(&(name.len() as u16).to_be_bytes());
+    extensions.extend_from_slice(name);
+
+    let mut body = vec![0x03, 0x03];
+    body.extend_from_slice(&[0u8; 32]);
+    body.push(0);
+    body.extend_from_slice(&[0x00, 0x02, 0x13, 0x01]);
+    body.extend_from_slice(&[0x01, 0x00]);
+    body.extend_from_slice(&(extensions.len() as u16).to_be_bytes());
+    body.extend_from_slice(&extensions);
+
+    let mut handshake = vec![0x01];
+    handshake.extend_from_slice(&(body.len() as u32).to_be_bytes()[1..]);
+    handshake.extend_from_slice(&body);
+
+    let mut record = vec![0x16, 0x03, 0x01];
+    record.extend_from_slice(&(handshake.len() as u16).to_be_bytes());
+    record.extend_from_slice(&handshake);
+    record
+}
+
+fn sniffer(protocols: &[SniffProtocol], excluded: &[&str]) -> Sniffer {
+    Sniffer::new(&SniffingConfig {
+        enabled: true,
+        dest_override: protocols.to_vec(),
+        domains_excluded: excluded.iter().map(|d| d.to_string()).collect(),
+    })
+}
+
+// ============================================================================
+// 域名识别
+// ============================================================================
+
+#[test]
+fn test_sniff_tls_and_http() {
+    let sniffer = sniffer(&[SniffProtocol::Tls, SniffProtocol::Http], &[]);
+    assert_eq!(
+        sniffer.sniff(&client_hello("WWW.Example.com")),
+        Some((SniffProtocol::Tls, "www.example.com".to_string()))
+    );
+    assert_eq!(
+        sniffer.sniff(b"GET / HTTP/1.1\r\nHost: api.example.com:8080\r\nAccept: */*\r\n\r\n"),
+        Some((SniffProtocol::Http, "api.example.com".to_string()))
+    );
+    // IP 形式的 SNI / Host 不作为域名
+    assert_eq!(sniffer.sniff(&client_hello("1.2.3.4")), None);
+    assert_eq!(
+        sniffer.sniff(b"GET / HTTP/1.1\r\nHost: 10.0.0.1\r\n\r\n"),
+        None
+    );
+    assert_eq!(sniffer.sniff(b"\x00\x01binary"), None);
+
+    // 只启用 TLS 时不识别 HTTP
+    let tls_only = self::sniffer(&[SniffProtocol::Tls], &[]);
+    assert_eq!(
+        tls_only.sniff(b"GET / HTTP/1.1\r\nHost: a.example\r\n\r\n"),
+        None
+    );
+}
+
+#[test]
+fn test_sniff_http_host() {
+    let host = |request: &[u8]| sniff_http_host(request);
+    assert_eq!(
+        host(b"POST /x HTTP/1.1\r\nhost: Example.org.\r\n\r\nbody").as_deref(),
+        Some("Example.org.")
+    );
+    assert_eq!(host(b"GET / HTTP/1.1\r\nHost: [::1]:80\r\n\r\n"), None);
+    assert_eq!(
+        host(b"CONNECT a.example:443 HTTP/1.1\r\nHost: a.example\r\n\r\n"),
+        None
+    );
+    assert_eq!(host(b"PRI * HTTP/2.0\r\n\r\nSM\r\n\r\n"), None);
+    // Host 在首包之后的正文中出现时不识别
+    assert_eq!(
+        host(b"GET / HTTP/1.1\r\nAccept: */*\r\n\r\nHost: body.example\r\n"),
+        None
+    );
+}
+
+#[test]
+fn test_sniff_excluded_domains() {
+    let sniffer = sniffer(&[SniffProtocol::Tls], &["Apple.com.", "push.example"]);
+    assert_eq!(sniffer.sniff(&client_hello("courier.push.apple.com")), None);
+    assert_eq!(sniffer.sniff(&client_hello("push.example")), None);
+    assert!(sniffer.sniff(&client_hello("notapple.com")).is_some());
+}
+
+// ============================================================================
+// 会话接入
+// ============================================================================
+
+fn request(command: Command) -> VlessRequest {
+    VlessRequest::new(
+        Uuid::nil(),
+        command,
+        Address::Ipv4("93.184.216.34".parse().unwrap()),
+        443,
+    )
+}
+
+#[test]
+fn test_sniff_destination() {
+    let hello = client_hello("video.example.com");
+    let services = SessionServices {
+        sniffer: Some(Arc::new(sniffer(&[SniffProtocol::Tls], &[]))),
+        ..Default::default()
+    };
+
+    let mut tcp = request(Command::Tcp);
+    services.sniff_destination(&mut tcp, &hello);
+    assert_eq!(
+        tcp.address,
+        Address::Domain(Bytes::from_static(b"video.example.com"))
+    );
+    assert_eq!(tcp.port, 443);
+
+    // UDP 请求与未识别的首包保持原目标
+    let mut udp = request(Command::Udp);
+    services.sniff_destination(&mut udp, &hello);
+    assert_eq!(udp.address, request(Command::Udp).address);
+    let mut empty = request(Command::Tcp);
+    services.sniff_destination(&mut empty, &[]);
+    assert_eq!(empty.address, request(Command::Tcp).address);
+
+    // 未启用嗅探时不变
+    let mut plain = request(Command::Tcp);
+    SessionServices::default().sniff_destination(&mut plain, &hello);
+    assert_eq!(plain.address, request(Command::Tcp).address);
+}
+
+#[test]
+fn test_sniffing_config() {
+    let parse = |sniffing: &str| {
+        Config::from_json(&format!(
+            r#"{{"server": {{"listen": "0.0.0.0", "port": 443}}, "users": [], "sniffing": {}}}"#,
+            sniffing
+        ))
+    };
+    let config = parse(r#"{"enabled": true}"#).unwrap();
+    assert_eq!(
+        config.sniffing.dest_override,
+        [SniffProtocol::Tls, SniffProtocol::Http]
+    );
+    assert!(config.validate().is_ok());
+
+    let err = parse(r#"{"enabled": true, "dest_override": []}"#)
+        .unwrap()
+        .validate()
+        .unwrap_err();
+    assert!(
+        err.to_string().contains("sniffing.dest_override"),
+        "{}",
+        err
+    );
+    assert!(parse(r#"{"dest_override": ["quic"]}"#).is_err());
+}
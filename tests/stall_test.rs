@@ -0,0 +1,132 @@
+//! 会话停滞检测测试
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncReadExt;
+use uuid::Uuid;
+use vless_rust::address::ConnectTiming;
+use vless_rust::session::SessionRecord;
+use vless_rust::stall::{watch, Activity, ActivityReader, StallStats};
+
+/// 等待条件成立（最多 3 秒）
+async fn wait_until(condition: impl Fn() -> bool) {
+    let deadline = Instant::now() + Duration::from_secs(3);
+    while !condition() {
+        assert!(Instant::now() < deadline, "condition not met in time");
+        tokio::time::sleep(Duration::from_millis(20)).await;
+    }
+}
+
+// ============================================================================
+// 活动记录
+// ============================================================================
+
+#[tokio::test]
+async fn test_activity_reader_touches_and_closes() {
+    let activity = Arc::new(Activity::default());
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    assert!(activity.idle_for() >= Duration::from_millis(100));
+
+    let mut reader = ActivityReader::new(&b"data"[..], Arc::clone(&activity));
+    let mut buf = [0u8; 16];
+    assert_eq!(reader.read(&mut buf).await.unwrap(), 4);
+    assert!(activity.idle_for() < Duration::from_millis(50));
+
+    // EOF 后停止停滞检测
+    let stats = Arc::new(StallStats::default());
+    assert_eq!(reader.read(&mut buf).await.unwrap(), 0);
+    let _watch = watch(
+        Arc::clone(&activity),
+        Duration::from_millis(50),
+        Arc::clone(&stats),
+        1,
+        "example.com:443".to_string(),
+    )
+    .unwrap();
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    assert_eq!(stats.snapshot().stalled_total, 0);
+}
+
+#[test]
+fn test_session_ids_are_unique() {
+    let record = |dest: &str| {
+        SessionRecord::new(
+            Uuid::nil(),
+            "user".to_string(),
+            "tcp",
+            dest.to_string(),
+            &ConnectTiming::default(),
+            Instant::now(),
+        )
+    };
+    let first = record("a:1");
+    let second = record("b:2");
+    assert!(first.id > 0);
+    assert_ne!(first.id, second.id);
+}
+
+// ============================================================================
+// 停滞检测
+// ============================================================================
+
+#[test]
+fn test_zero_timeout_disables_watch() {
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    runtime.block_on(async {
+        let watch = watch(
+            Arc::new(Activity::default()),
+            Duration::ZERO,
+            Arc::new(StallStats::default()),
+            1,
+            String::new(),
+        );
+        assert!(watch.is_none());
+    });
+}
+
+#[tokio::test]
+async fn test_stall_detected_and_recovered() {
+    let activity = Arc::new(Activity::default());
+    let stats = Arc::new(StallStats::default());
+    let _watch = watch(
+        Arc::clone(&activity),
+        Duration::from_millis(500),
+        Arc::clone(&stats),
+        7,
+        "example.com:443".to_string(),
+    )
+    .unwrap();
+
+    wait_until(|| stats.snapshot().stalled == 1).await;
+    assert_eq!(stats.snapshot().stalled_total, 1);
+
+    // 恢复传输
+    wait_until(|| {
+        activity.touch();
+        stats.snapshot().recovered_total == 1
+    })
+    .await;
+    assert_eq!(stats.snapshot().stalled, 0);
+
+    // 再次停滞计为新的一次
+    wait_until(|| stats.snapshot().stalled_total == 2).await;
+}
+
+#[tokio::test]
+async fn test_ending_stalled_session_clears_gauge() {
+    let stats = Arc::new(StallStats::default());
+    let watch = watch(
+        Arc::new(Activity::default()),
+        Duration::from_millis(100),
+        Arc::clone(&stats),
+        9,
+        "example.com:443".to_string(),
+    )
+    .unwrap();
+    wait_until(|| stats.snapshot().stalled == 1).await;
+
+    drop(watch);
+    wait_until(|| stats.snapshot().stalled == 0).await;
+    assert_eq!(stats.snapshot().stalled_total, 1);
+    assert_eq!(stats.snapshot().recovered_total, 0);
+}
@@ -0,0 +1,365 @@
+//! 用户流量统计测试
+
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use uuid::Uuid;
+use vless_rust::address::ConnectTiming;
+use vless_rust::api::{handle_admin_request, AdminConfig};
+use vless_rust::events::{Event, EventBus};
+use vless_rust::stats::{
+    load_state, query_users, SavedStats, SavedUser, SortKey, UserQuery, UserStats, UserSummary,
+};
+
+fn opened(uuid: Uuid) -> Event {
+    Event::ConnectionOpened {
+        client_addr: "127.0.0.1:1000".parse::<SocketAddr>().unwrap(),
+        uuid,
+        user: uuid.to_string(),
+        network: "tcp",
+        dest: "example.com:443".to_string(),
+    }
+}
+
+fn closed(uuid: Uuid) -> Event {
+    Event::ConnectionClosed {
+        client_addr: "127.0.0.1:1000".parse::<SocketAddr>().unwrap(),
+        uuid,
+        user: uuid.to_string(),
+    }
+}
+
+fn session_closed(uuid: Uuid, up: u64, down: u64) -> Event {
+    let mut record = vless_rust::session::SessionRecord::new(
+        uuid,
+        uuid.to_string(),
+        "tcp",
+        "example.com:443".to_string(),
+        &ConnectTiming::default(),
+        Instant::now(),
+    );
+    record.bytes_up = up;
+    record.bytes_down = down;
+    Event::SessionClosed(Arc::new(record))
+}
+
+fn summary(email: &str, total: u64, connections: u64, active: u64) -> UserSummary {
+    UserSummary {
+        uuid: Uuid::new_v4(),
+        email: Some(email.to_string()),
+        bytes_up: 0,
+        bytes_down: total,
+        connections,
+        active_connections: active,
+        last_seen_secs: None,
+    }
+}
+
+fn params(pairs: &[(&str, &str)]) -> HashMap<String, String> {
+    pairs
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect()
+}
+
+// ============================================================================
+// 计数
+// ============================================================================
+
+#[test]
+fn test_stats_apply_events() {
+    let stats = UserStats::new();
+    let alice = Uuid::new_v4();
+    let idle = Uuid::new_v4();
+    stats.register(alice, Some("alice@example.com".to_string()));
+    stats.register(idle, None);
+
+    stats.apply(&opened(alice));
+    stats.apply(&opened(alice));
+    stats.apply(&closed(alice));
+    stats.apply(&session_closed(alice, 100, 2000));
+
+    let snapshot = stats.snapshot();
+    assert_eq!(snapshot.len(), 2);
+    let a = snapshot.iter().find(|u| u.uuid == alice).unwrap();
+    assert_eq!(a.email.as_deref(), Some("alice@example.com"));
+    assert_eq!(a.connections, 2);
+    assert_eq!(a.active_connections, 1);
+    assert_eq!(a.total_bytes(), 2100);
+    assert!(a.last_seen_secs.is_some());
+
+    let i = snapshot.iter().find(|u| u.uuid == idle).unwrap();
+    assert_eq!(i.connections, 0);
+    assert!(i.last_seen_secs.is_none());
+}
+
+#[tokio::test]
+async fn test_stats_collector_subscribes_to_bus() {
+    let bus = EventBus::new();
+    let stats = Arc::new(UserStats::new());
+    stats.spawn_collector(bus.subscribe());
+
+    let user = Uuid::new_v4();
+    bus.publish(opened(user));
+    bus.publish(session_closed(user, 1, 2));
+    bus.publish(closed(user));
+
+    for _ in 0..50 {
+        let snapshot = stats.snapshot();
+        if snapshot.first().is_some_and(|u| u.total_bytes() == 3) {
+            assert_eq!(snapshot[0].active_connections, 0);
+            return;
+        }
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    panic!("collector did not apply events");
+}
+
+// ============================================================================
+// 持久化
+// ============================================================================
+
+fn unix_now() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs()
+}
+
+#[test]
+fn test_stats_survive_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("stats.json");
+    let alice = Uuid::new_v4();
+    let removed = Uuid::new_v4();
+
+    let before = UserStats::new();
+    before.register(alice, Some("alice".to_string()));
+    before.register(removed, None);
+    before.apply(&opened(alice));
+    before.apply(&session_closed(alice, 100, 2000));
+    before.apply(&opened(removed));
+    before.save(&path).unwrap();
+
+    // 重启后：配置中已删除的用户不恢复，活跃连接数不保存，新计数累加在恢复值之上
+    let after = UserStats::new();
+    after.register(alice, Some("alice".to_string()));
+    after.apply(&opened(alice));
+    let saved = load_state(&path).unwrap().unwrap();
+    assert!(saved.age_secs() < 5);
+    assert_eq!(after.restore(&saved), 1);
+
+    let snapshot = after.snapshot();
+    assert_eq!(snapshot.len(), 1);
+    assert_eq!(snapshot[0].connections, 2);
+    assert_eq!(snapshot[0].active_connections, 1);
+    assert_eq!(snapshot[0].total_bytes(), 2100);
+}
+
+#[test]
+fn test_restore_last_seen_across_gap() {
+    let idle = Uuid::new_v4();
+    let skewed = Uuid::new_v4();
+    let stats = UserStats::new();
+    stats.register(idle, None);
+    stats.register(skewed, None);
+
+    let now = unix_now();
+    let user = |uuid, last_seen| SavedUser {
+        uuid,
+        bytes_up: 0,
+        bytes_down: 0,
+        connections: 1,
+        last_seen: Some(last_seen),
+    };
+    stats.restore(&SavedStats {
+        version: 1,
+        saved_at: now - 3600,
+        // 停机一小时前最后活动；另一条记录晚于当前时间（时钟回拨）
+        users: vec![user(idle, now - 7200), user(skewed, now + 600)],
+    });
+
+    let snapshot = stats.snapshot();
+    let age = |uuid| {
+        snapshot
+            .iter()
+            .find(|u| u.uuid == uuid)
+            .unwrap()
+            .last_seen_secs
+            .unwrap()
+    };
+    assert!((7200..7205).contains(&age(idle)), "{}", age(idle));
+    assert_eq!(age(skewed), 0);
+}
+
+#[test]
+fn test_load_state_errors() {
+    let dir = tempfile::tempdir().unwrap();
+    let path = dir.path().join("stats.json");
+    assert!(load_state(&path).unwrap().is_none());
+
+    std::fs::write(&path, r#"{"version": 9, "saved_at": 0, "users": []}"#).unwrap();
+    let err = load_state(&path).unwrap_err();
+    assert!(err.to_string().contains("version 9"), "{}", err);
+
+    std::fs::write(&path, "not json").unwrap();
+    assert!(load_state(&path).is_err());
+}
+
+// ============================================================================
+// 查询
+// ============================================================================
+
+#[test]
+fn test_user_query_from_params() {
+    let query = UserQuery::from_params(&HashMap::new()).unwrap();
+    assert_eq!(query.page, 1);
+    assert_eq!(query.per_page, 50);
+    assert_eq!(query.sort, SortKey::Traffic);
+    assert!(query.descending);
+
+    let query = UserQuery::from_params(&params(&[
+        ("page", "3"),
+        ("per_page", "10000"),
+        ("sort", "email"),
+        ("search", " Alice "),
+        ("active", "1"),
+    ]))
+    .unwrap();
+    assert_eq!(query.page, 3);
+    assert_eq!(query.per_page, 500);
+    assert_eq!(query.sort, SortKey::Email);
+    assert!(!query.descending);
+    assert_eq!(query.search.as_deref(), Some("alice"));
+    assert!(query.active_only);
+
+    assert!(UserQuery::from_params(&params(&[("page", "0")])).is_err());
+    assert!(UserQuery::from_params(&params(&[("sort", "bogus")])).is_err());
+    assert!(UserQuery::from_params(&params(&[("order", "sideways")])).is_err());
+}
+
+#[test]
+fn test_query_users_sort_and_paginate() {
+    let users = vec![
+        summary("a@example.com", 10, 5, 0),
+        summary("b@example.com", 30, 1, 2),
+        summary("c@example.com", 20, 9, 1),
+    ];
+
+    let query = UserQuery {
+        per_page: 2,
+        ..Default::default()
+    };
+    let page = query_users(users.clone(), &query);
+    assert_eq!(page.total, 3);
+    let emails: Vec<_> = page
+        .users
+        .iter()
+        .map(|u| u.email.clone().unwrap())
+        .collect();
+    assert_eq!(emails, ["b@example.com", "c@example.com"]);
+
+    let page = query_users(
+        users.clone(),
+        &UserQuery {
+            page: 2,
+            ..query.clone()
+        },
+    );
+    assert_eq!(page.users.len(), 1);
+    assert_eq!(page.users[0].email.as_deref(), Some("a@example.com"));
+
+    let by_connections = UserQuery {
+        sort: SortKey::Connections,
+        ..Default::default()
+    };
+    let page = query_users(users, &by_connections);
+    assert_eq!(page.users[0].email.as_deref(), Some("c@example.com"));
+}
+
+#[test]
+fn test_query_users_search_and_active_filter() {
+    let users = vec![
+        summary("alice@example.com", 10, 5, 0),
+        summary("bob@example.com", 30, 1, 2),
+    ];
+    let bob_uuid = users[1].uuid.to_string();
+
+    let query = UserQuery {
+        search: Some("ALICE".to_ascii_lowercase()),
+        ..Default::default()
+    };
+    assert_eq!(query_users(users.clone(), &query).total, 1);
+
+    let query = UserQuery {
+        search: Some(bob_uuid[..8].to_string()),
+        ..Default::default()
+    };
+    let page = query_users(users.clone(), &query);
+    assert_eq!(page.users[0].email.as_deref(), Some("bob@example.com"));
+
+    let query = UserQuery {
+        active_only: true,
+        ..Default::default()
+    };
+    let page = query_users(users, &query);
+    assert_eq!(page.total, 1);
+    assert_eq!(page.users[0].active_connections, 2);
+}
+
+// ============================================================================
+// 管理 API
+// ============================================================================
+
+#[tokio::test]
+async fn test_admin_users_endpoint() {
+    let stats = Arc::new(UserStats::new());
+    for i in 0..5 {
+        stats.register(Uuid::new_v4(), Some(format!("user{}@example.com", i)));
+    }
+    let config = AdminConfig {
+        token: "s3cret".to_string(),
+        destinations: None,
+        user_stats: Some(stats),
+        user_links: Vec::new(),
+        blocklist: None,
+        capture: None,
+        reload_preview: None,
+        overhead: Default::default(),
+        listener_tasks: Default::default(),
+        resolver: Default::default(),
+        route_stats: Default::default(),
+        gate: Default::default(),
+        maintenance: Default::default(),
+    };
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let mut buf = [0u8; 1024];
+        let n = stream.read(&mut buf).await.unwrap();
+        let _ = handle_admin_request(stream, &buf[..n], &config).await;
+    });
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client
+        .write_all(
+            b"GET /api/users?sort=email&per_page=2&page=2 HTTP/1.1\r\nAuthorization: Bearer s3cret\r\n\r\n",
+        )
+        .await
+        .unwrap();
+    let mut response = String::new();
+    client.read_to_string(&mut response).await.unwrap();
+
+    assert!(response.starts_with("HTTP/1.1 200"));
+    let body: serde_json::Value =
+        serde_json::from_str(response.split("\r\n\r\n").nth(1).unwrap()).unwrap();
+    assert_eq!(body["total"], 5);
+    assert_eq!(body["page"], 2);
+    assert_eq!(body["users"][0]["email"], "user2@example.com");
+    assert_eq!(body["users"].as_array().unwrap().len(), 2);
+}
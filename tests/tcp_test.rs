@@ -4,7 +4,7 @@ use tokio::io::{AsyncReadExt, AsyncWriteExt};
 use tokio::net::TcpListener;
 use vless_rust::address::resolve_address;
 use vless_rust::config::PerformanceConfig;
-use vless_rust::socket::configure_tcp_socket;
+use vless_rust::socket::{bind_udp_socket, configure_tcp_socket, udp_bind_ip};
 
 // ============================================================================
 // TCP Socket 配置测试
@@ -152,6 +152,7 @@ fn test_performance_config_custom() {
         udp_recv_buffer: 128 * 1024,
         buffer_pool_size: 64,
         ws_header_buffer_size: 16 * 1024,
+        ..Default::default()
     };
 
     assert_eq!(config.buffer_size, 128 * 1024);
@@ -292,3 +293,279 @@ async fn test_resolve_invalid_domain_from_unit() {
     let result = resolve_address("invalid.invalid.invalid", 80).await;
     assert!(result.is_err());
 }
+
+// ============================================================================
+// UDP 中继绑定测试
+// ============================================================================
+
+#[test]
+fn test_udp_bind_ip_follows_target_family() {
+    let perf = PerformanceConfig::default();
+
+    let v4 = udp_bind_ip(&perf, "1.1.1.1:53".parse().unwrap()).unwrap();
+    let v6 = udp_bind_ip(&perf, "[2606:4700::1111]:53".parse().unwrap()).unwrap();
+
+    assert!(v4.is_ipv4() && v4.is_unspecified());
+    assert!(v6.is_ipv6() && v6.is_unspecified());
+}
+
+#[test]
+fn test_udp_bind_ip_invalid_address() {
+    let perf = PerformanceConfig {
+        udp_bind_address: Some("not-an-ip".to_string()),
+        ..Default::default()
+    };
+
+    assert!(udp_bind_ip(&perf, "1.1.1.1:53".parse().unwrap()).is_err());
+}
+
+#[tokio::test]
+async fn test_bind_udp_socket_configured_address() {
+    let perf = PerformanceConfig {
+        udp_bind_address: Some("127.0.0.1".to_string()),
+        ..Default::default()
+    };
+
+    let socket = bind_udp_socket(&perf, "1.1.1.1:53".parse().unwrap())
+        .await
+        .unwrap();
+
+    assert!(socket.local_addr().unwrap().ip().is_loopback());
+}
+
+#[tokio::test]
+async fn test_bind_udp_socket_port_range() {
+    // 先占用一个端口，再把范围限定为该端口 + 下一个端口
+    let occupied = tokio::net::UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let port = occupied.local_addr().unwrap().port();
+    let perf = PerformanceConfig {
+        udp_bind_address: Some("127.0.0.1".to_string()),
+        udp_port_range: Some([port, port.saturating_add(1)]),
+        ..Default::default()
+    };
+
+    match bind_udp_socket(&perf, "1.1.1.1:53".parse().unwrap()).await {
+        Ok(socket) => assert_eq!(socket.local_addr().unwrap().port(), port + 1),
+        // 下一个端口也可能恰好被占用
+        Err(e) => assert!(e.to_string().contains("No free UDP port")),
+    }
+}
+
+#[tokio::test]
+async fn test_bind_udp_socket_invalid_range() {
+    let perf = PerformanceConfig {
+        udp_port_range: Some([3000, 2000]),
+        ..Default::default()
+    };
+
+    assert!(bind_udp_socket(&perf, "1.1.1.1:53".parse().unwrap())
+        .await
+        .is_err());
+}
+
+// ============================================================================
+// Happy Eyeballs 建连
+// ============================================================================
+
+#[tokio::test]
+async fn test_happy_eyeballs_falls_back_to_next_address() {
+    use std::net::SocketAddr;
+    use std::time::Duration;
+    use vless_rust::address::happy_eyeballs;
+
+    let perf = PerformanceConfig::default();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let reachable = listener.local_addr().unwrap();
+    let closed = {
+        let socket = std::net::TcpListener::bind("127.0.0.1:0").unwrap();
+        socket.local_addr().unwrap()
+    };
+
+    // 依次尝试：前一个被拒绝后尝试下一个
+    let (_, addr) = happy_eyeballs(&[closed, reachable], Duration::ZERO, &perf)
+        .await
+        .unwrap();
+    assert_eq!(addr, reachable);
+
+    // 错开并发：不可达地址（TEST-NET-1）无应答时，到时即尝试下一个
+    let blackhole: SocketAddr = "192.0.2.1:9".parse().unwrap();
+    let (_, addr) = tokio::time::timeout(
+        Duration::from_secs(3),
+        happy_eyeballs(&[blackhole, reachable], Duration::from_millis(50), &perf),
+    )
+    .await
+    .unwrap()
+    .unwrap();
+    assert_eq!(addr, reachable);
+
+    let err = happy_eyeballs(&[closed], Duration::from_millis(50), &perf)
+        .await
+        .unwrap_err();
+    assert!(err.to_string().contains(&closed.to_string()), "{}", err);
+    assert!(happy_eyeballs(&[], Duration::ZERO, &perf).await.is_err());
+}
+
+// ============================================================================
+// MSS / 路径 MTU 选项测试
+// ============================================================================
+
+#[tokio::test]
+async fn test_tcp_mss_applies_to_accepted_and_outbound_connections() {
+    use socket2::SockRef;
+    use vless_rust::address::connect_target;
+    use vless_rust::protocol::Address;
+    use vless_rust::socket::apply_tcp_mtu_options;
+
+    let perf = PerformanceConfig {
+        tcp_mss: 1200,
+        ..Default::default()
+    };
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    apply_tcp_mtu_options(&SockRef::from(&listener), &perf);
+    let port = listener.local_addr().unwrap().port();
+
+    let (outbound, _) = connect_target(&Address::Ipv4(std::net::Ipv4Addr::LOCALHOST), port, &perf)
+        .await
+        .unwrap();
+    let (accepted, _) = listener.accept().await.unwrap();
+
+    // 回环接口 MTU 远大于 1200，生效的 MSS 由配置值决定（不超过该值）
+    let accepted_mss = SockRef::from(&accepted).mss().unwrap();
+    let outbound_mss = SockRef::from(&outbound).mss().unwrap();
+    assert!(accepted_mss > 0 && accepted_mss <= 1200, "{}", accepted_mss);
+    assert!(outbound_mss > 0 && outbound_mss <= 1200, "{}", outbound_mss);
+}
+
+#[cfg(target_os = "linux")]
+#[tokio::test]
+async fn test_pmtu_discovery_applies_to_udp_relay_socket() {
+    use std::os::fd::AsRawFd;
+    use vless_rust::config::PmtuDiscovery;
+
+    let perf = PerformanceConfig {
+        udp_bind_address: Some("127.0.0.1".to_string()),
+        pmtu_discovery: Some(PmtuDiscovery::Dont),
+        ..Default::default()
+    };
+    let socket = bind_udp_socket(&perf, "1.1.1.1:53".parse().unwrap())
+        .await
+        .unwrap();
+
+    let mut value: libc::c_int = -1;
+    let mut len = std::mem::size_of::<libc::c_int>() as libc::socklen_t;
+    let ret = unsafe {
+        libc::getsockopt(
+            socket.as_raw_fd(),
+            libc::IPPROTO_IP,
+            libc::IP_MTU_DISCOVER,
+            &mut value as *mut libc::c_int as *mut libc::c_void,
+            &mut len,
+        )
+    };
+    assert_eq!(ret, 0);
+    assert_eq!(value, libc::IP_PMTUDISC_DONT);
+}
+
+#[test]
+fn test_tcp_mss_validation() {
+    let valid = PerformanceConfig {
+        tcp_mss: 1360,
+        ..Default::default()
+    };
+    assert!(valid.validate().is_ok());
+    assert!(PerformanceConfig::default().validate().is_ok());
+
+    let too_small = PerformanceConfig {
+        tcp_mss: 40,
+        ..Default::default()
+    };
+    let err = too_small.validate().unwrap_err().to_string();
+    assert!(err.contains("performance.tcp_mss"), "{}", err);
+
+    let parsed: PerformanceConfig =
+        serde_json::from_str(r#"{"tcp_mss": 1360, "pmtu_discovery": "dont"}"#).unwrap();
+    assert_eq!(parsed.tcp_mss, 1360);
+    assert_eq!(
+        parsed.pmtu_discovery,
+        Some(vless_rust::config::PmtuDiscovery::Dont)
+    );
+}
+
+// ============================================================================
+// UDP over TCP 测试
+// ============================================================================
+
+#[tokio::test]
+async fn test_udp_command_frames_packets_with_length_prefix() {
+    use std::collections::HashSet;
+    use std::time::Duration;
+    use tokio::net::{TcpStream, UdpSocket};
+    use uuid::Uuid;
+    use vless_rust::protocol::{encode_udp_packet, take_udp_packet};
+    use vless_rust::session::SessionServices;
+    use vless_rust::tcp::handle_tcp_connection;
+
+    let target = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let target_port = target.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let mut buf = [0u8; 1024];
+        loop {
+            let (n, src) = target.recv_from(&mut buf).await.unwrap();
+            target.send_to(&buf[..n], src).await.unwrap();
+        }
+    });
+
+    let uuid = Uuid::new_v4();
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (stream, client_addr) = listener.accept().await.unwrap();
+        let users: HashSet<Uuid> = [uuid].into_iter().collect();
+        let _ = handle_tcp_connection(
+            stream,
+            client_addr,
+            PerformanceConfig::default(),
+            &users,
+            SessionServices::default(),
+            |_| async { None },
+        )
+        .await;
+    });
+
+    // 请求头与首个 UDP 包一并发送，第二个包拆成两段发送
+    let mut request = vec![0u8];
+    request.extend_from_slice(uuid.as_bytes());
+    request.extend_from_slice(&[0, 2]);
+    request.extend_from_slice(&target_port.to_be_bytes());
+    request.extend_from_slice(&[1, 127, 0, 0, 1]);
+    request.extend_from_slice(&encode_udp_packet(b"first"));
+
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client.write_all(&request).await.unwrap();
+    let second = encode_udp_packet(b"second");
+    client.write_all(&second[..1]).await.unwrap();
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    client.write_all(&second[1..]).await.unwrap();
+
+    // 响应头 [版本, 附加信息长度] 之后是带长度前缀的 UDP 包
+    let mut header = [0u8; 2];
+    tokio::time::timeout(Duration::from_secs(5), client.read_exact(&mut header))
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(header, [0, 0]);
+
+    let mut received = bytes::BytesMut::new();
+    let mut packets = Vec::new();
+    while packets.len() < 2 {
+        let n = tokio::time::timeout(Duration::from_secs(5), client.read_buf(&mut received))
+            .await
+            .unwrap()
+            .unwrap();
+        assert!(n > 0, "connection closed early");
+        while let Some(packet) = take_udp_packet(&mut received) {
+            packets.push(packet);
+        }
+    }
+    assert_eq!(packets, [&b"first"[..], b"second"]);
+}
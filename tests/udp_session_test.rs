@@ -0,0 +1,172 @@
+//! UDP 会话表测试
+
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use vless_rust::udp_session::{UdpSession, UdpSessionTable};
+
+async fn session(target: SocketAddr, full_cone: bool, idle: Duration) -> UdpSession {
+    let socket = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    UdpSession::new(socket, target, full_cone, idle)
+}
+
+fn addr(port: u16) -> SocketAddr {
+    SocketAddr::from(([127, 0, 0, 1], port))
+}
+
+// ============================================================================
+// 会话表
+// ============================================================================
+
+#[tokio::test]
+async fn test_table_enforces_max_sessions() {
+    let table = UdpSessionTable::new(2);
+    let idle = Duration::from_secs(30);
+    let first = table
+        .insert(addr(1), session(addr(53), true, idle).await)
+        .unwrap();
+    table
+        .insert(addr(2), session(addr(53), true, idle).await)
+        .unwrap();
+    assert!(!table.has_capacity());
+    assert!(table
+        .insert(addr(3), session(addr(53), true, idle).await)
+        .is_err());
+    // 同一键替换不受上限影响
+    let replaced = table
+        .insert(addr(1), session(addr(53), true, idle).await)
+        .unwrap();
+
+    // 旧会话结束时不会移除替换后的会话
+    table.remove(&addr(1), &first);
+    assert_eq!(table.stats().active, 2);
+    table.remove(&addr(1), &replaced);
+    assert_eq!(table.stats().active, 1);
+    assert!(table.has_capacity());
+    assert_eq!(table.stats().max, 2);
+}
+
+#[test]
+fn test_zero_max_is_unlimited() {
+    let table: UdpSessionTable<SocketAddr> = UdpSessionTable::new(0);
+    assert!(table.has_capacity());
+    assert_eq!(table.stats().max, 0);
+}
+
+// ============================================================================
+// 来源过滤与空闲超时
+// ============================================================================
+
+#[tokio::test]
+async fn test_full_cone_accepts_any_source() {
+    let full_cone = session(addr(53), true, Duration::from_secs(30)).await;
+    assert!(full_cone.accepts(addr(53)));
+    assert!(full_cone.accepts(addr(8053)));
+
+    let restricted = session(addr(53), false, Duration::from_secs(30)).await;
+    assert!(restricted.accepts(addr(53)));
+    assert!(!restricted.accepts(addr(8053)));
+}
+
+#[tokio::test]
+async fn test_restricted_session_drops_other_sources() {
+    let target = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let other = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let session = session(target.local_addr().unwrap(), false, Duration::from_secs(5)).await;
+    let local = session.socket().local_addr().unwrap();
+
+    other.send_to(b"stranger", local).await.unwrap();
+    target.send_to(b"reply", local).await.unwrap();
+
+    let mut buf = [0u8; 64];
+    let (n, src) = session.recv_from(&mut buf).await.unwrap().unwrap();
+    assert_eq!(&buf[..n], b"reply");
+    assert_eq!(src, target.local_addr().unwrap());
+}
+
+#[tokio::test]
+async fn test_idle_timeout_counts_activity_in_both_directions() {
+    let session = session(addr(53), true, Duration::from_millis(300)).await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
+    // 上行活动重置空闲计时
+    session.touch();
+    assert!(session.remaining().unwrap() > Duration::from_millis(200));
+
+    let started = std::time::Instant::now();
+    let mut buf = [0u8; 64];
+    assert!(session.recv_from(&mut buf).await.unwrap().is_none());
+    assert!(started.elapsed() >= Duration::from_millis(250));
+    assert!(session.remaining().is_none());
+}
+
+// ============================================================================
+// 重放窗口
+// ============================================================================
+
+#[tokio::test]
+async fn test_replay_window_drops_duplicate_datagrams() {
+    let target = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let table = UdpSessionTable::new(0);
+    let session = table
+        .insert(
+            addr(1),
+            session(target.local_addr().unwrap(), true, Duration::from_secs(5))
+                .await
+                .with_replay_window(2),
+        )
+        .unwrap();
+    let local = session.socket().local_addr().unwrap();
+
+    // a 的重复在窗口内被丢弃；c 把 a 挤出窗口后再次出现的 a 视为新数据报
+    for payload in [&b"a"[..], b"a", b"b", b"b", b"c", b"a"] {
+        target.send_to(payload, local).await.unwrap();
+    }
+    let mut buf = [0u8; 64];
+    let mut received = Vec::new();
+    for _ in 0..4 {
+        let (n, _) = session.recv_from(&mut buf).await.unwrap().unwrap();
+        received.push(buf[..n].to_vec());
+    }
+    assert_eq!(received, [b"a", b"b", b"c", b"a"]);
+    assert_eq!(session.replay_dropped(), 2);
+
+    // 会话关闭后计数仍保留在统计中
+    assert_eq!(table.stats().replay_dropped, 2);
+    table.remove(&addr(1), &session);
+    assert_eq!(table.stats().replay_dropped, 2);
+    assert_eq!(table.stats().active, 0);
+}
+
+#[test]
+fn test_replay_window_limit_validation() {
+    use vless_rust::config::{PerformanceConfig, MAX_UDP_REPLAY_WINDOW};
+
+    let max = PerformanceConfig {
+        udp_replay_window: MAX_UDP_REPLAY_WINDOW,
+        ..Default::default()
+    };
+    assert!(max.validate().is_ok());
+
+    let too_large = PerformanceConfig {
+        udp_replay_window: MAX_UDP_REPLAY_WINDOW + 1,
+        ..Default::default()
+    };
+    let err = too_large.validate().unwrap_err().to_string();
+    assert!(err.contains("performance.udp_replay_window"), "{}", err);
+}
+
+#[tokio::test]
+async fn test_replay_window_disabled_by_default() {
+    let target = UdpSocket::bind("127.0.0.1:0").await.unwrap();
+    let session = session(target.local_addr().unwrap(), true, Duration::from_secs(5)).await;
+    let local = session.socket().local_addr().unwrap();
+    target.send_to(b"dup", local).await.unwrap();
+    target.send_to(b"dup", local).await.unwrap();
+
+    let mut buf = [0u8; 64];
+    for _ in 0..2 {
+        let (n, _) = session.recv_from(&mut buf).await.unwrap().unwrap();
+        assert_eq!(&buf[..n], b"dup");
+    }
+    assert_eq!(session.replay_dropped(), 0);
+}
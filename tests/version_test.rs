@@ -0,0 +1,135 @@
+//! 启动输出测试
+
+use uuid::Uuid;
+use vless_rust::config::{BannerMode, Config, ProtocolType};
+use vless_rust::server::ServerConfig;
+use vless_rust::version::{banner_json, startup_report, ServerStatusInfo, VERSION_INFO};
+
+fn status() -> ServerStatusInfo {
+    ServerStatusInfo {
+        listen_addr: "0.0.0.0:443".to_string(),
+        protocol: ProtocolType::WebSocket,
+        user_count: 2,
+        buffer_size: 128 * 1024,
+        ws_path: Some("/ws".to_string()),
+        tcp_nodelay: true,
+        buffer_pool_size: 64,
+        tcp_recv_buffer: 0,
+        tcp_send_buffer: 0,
+        public_ip: Some("203.0.113.7".to_string()),
+    }
+}
+
+// ============================================================================
+// 配置
+// ============================================================================
+
+#[test]
+fn test_output_config_defaults() {
+    let config =
+        Config::from_json(r#"{"server": {"listen": "0.0.0.0", "port": 443}, "users": []}"#)
+            .unwrap();
+    assert_eq!(config.output.banner, BannerMode::Text);
+    assert!(config.output.show_links);
+
+    let config = Config::from_json(
+        r#"{"server": {"listen": "0.0.0.0", "port": 443}, "users": [],
+            "output": {"banner": "none", "show_links": false}}"#,
+    )
+    .unwrap();
+    assert_eq!(config.output.banner, BannerMode::None);
+    assert!(!config.output.show_links);
+}
+
+#[test]
+fn test_banner_mode_parse() {
+    assert_eq!(BannerMode::parse("json"), Some(BannerMode::Json));
+    assert_eq!(BannerMode::parse("NONE"), Some(BannerMode::None));
+    assert_eq!(BannerMode::parse("text"), Some(BannerMode::Text));
+    assert_eq!(BannerMode::parse("fancy"), None);
+}
+
+// ============================================================================
+// JSON 横幅
+// ============================================================================
+
+#[test]
+fn test_banner_json() {
+    let json = banner_json(&status());
+    assert_eq!(json["version"], VERSION_INFO.version);
+    assert_eq!(json["listen"], "0.0.0.0:443");
+    assert_eq!(json["protocol"], "WebSocket");
+    assert_eq!(json["ws_path"], "/ws");
+    assert_eq!(json["users"], 2);
+    assert_eq!(json["public_ip"], "203.0.113.7");
+    // 单行输出，便于逐行解析
+    assert!(!json.to_string().contains('\n'));
+}
+
+// ============================================================================
+// 启动信息报告
+// ============================================================================
+
+#[test]
+fn test_panel_url() {
+    let server = ServerConfig::new(
+        "0.0.0.0:8443".parse().unwrap(),
+        ProtocolType::Tcp,
+        "/".to_string(),
+        Some("203.0.113.7".to_string()),
+        8443,
+    );
+    assert_eq!(server.panel_url(), "http://203.0.113.7:8443/");
+
+    let server = ServerConfig::new(
+        "[::]:8443".parse().unwrap(),
+        ProtocolType::WebSocket,
+        "/ws".to_string(),
+        None,
+        8443,
+    );
+    assert_eq!(server.panel_url(), "http://[::]:8443/");
+
+    let server = server.with_fronting(
+        Some("cdn.example.com".to_string()),
+        Some("cdn.example.com".to_string()),
+        Some(443),
+    );
+    assert_eq!(server.panel_url(), "https://cdn.example.com:443/");
+}
+
+#[test]
+fn test_startup_report() {
+    let config = Config::from_json(
+        r#"{"server": {"listen": "0.0.0.0", "port": 443, "protocol": "ws", "ws_path": "/ws"},
+            "users": [{"uuid": "11111111-1111-1111-1111-111111111111", "email": "alice"}]}"#,
+    )
+    .unwrap();
+    let mut server = ServerConfig::new(
+        config.bind_addr().unwrap(),
+        config.server.protocol,
+        config.server.ws_path.clone(),
+        Some("vpn.example.com".to_string()),
+        443,
+    );
+    let uuid = Uuid::parse_str(&config.users[0].uuid).unwrap();
+    server.add_user_with_email(uuid, Some("alice".to_string()));
+
+    let report = startup_report(
+        &config,
+        vec!["0.0.0.0:443".to_string()],
+        &server.panel_url(),
+        &server.user_links(),
+    );
+    assert_eq!(report["version"], VERSION_INFO.version);
+    assert_eq!(report["listen"][0], "0.0.0.0:443");
+    assert_eq!(report["ws_path"], "/ws");
+    assert_eq!(report["panel_url"], "http://vpn.example.com:443/");
+    assert!(report["tls_fingerprint"].is_null());
+    assert_eq!(report["users"][0]["email"], "alice");
+    assert_eq!(report["users"][0]["transport"], "ws");
+    assert!(report["users"][0]["vless"]
+        .as_str()
+        .unwrap()
+        .starts_with("vless://11111111-1111-1111-1111-111111111111@vpn.example.com:443"));
+}
@@ -3,7 +3,9 @@
 use base64::engine::general_purpose::STANDARD;
 use base64::Engine;
 use uuid::Uuid;
-use vless_rust::vless_link::{generate_vless_links, VlessLinkConfig};
+use vless_rust::vless_link::{
+    format_links_file, generate_user_link, generate_vless_links, VlessLinkConfig,
+};
 
 #[test]
 fn test_generate_tcp_link() {
@@ -13,6 +15,8 @@ fn test_generate_tcp_link() {
         host: "1.2.3.4".to_string(),
         port: 443,
         ws_path: None,
+        ws_host: None,
+        sni: None,
         alias: "user@example.com".to_string(),
     };
 
@@ -32,6 +36,8 @@ fn test_generate_ws_link() {
         host: "1.2.3.4".to_string(),
         port: 443,
         ws_path: Some("/vless".to_string()),
+        ws_host: None,
+        sni: None,
         alias: "user@example.com".to_string(),
     };
 
@@ -51,6 +57,8 @@ fn test_vless_link_structure() {
         host: "example.com".to_string(),
         port: 8443,
         ws_path: Some("/ws".to_string()),
+        ws_host: None,
+        sni: None,
         alias: "test_user".to_string(),
     };
 
@@ -71,6 +79,8 @@ fn test_base64_encoding() {
         host: "localhost".to_string(),
         port: 443,
         ws_path: None,
+        ws_host: None,
+        sni: None,
         alias: "test".to_string(),
     };
 
@@ -89,6 +99,8 @@ fn test_alias_encoding() {
         host: "1.2.3.4".to_string(),
         port: 443,
         ws_path: None,
+        ws_host: None,
+        sni: None,
         alias: "user with spaces".to_string(),
     };
 
@@ -97,3 +109,58 @@ fn test_alias_encoding() {
     // 别名应该被 URL 编码
     assert!(links.tcp.vless.contains("user%20with%20spaces"));
 }
+
+#[test]
+fn test_generate_ws_link_with_fronting() {
+    let uuid = Uuid::parse_str("7fa8b8a5-e2d4-44dc-b3b4-0b72f04397d8").unwrap();
+    let config = VlessLinkConfig {
+        uuid,
+        host: "cdn.example.net".to_string(),
+        port: 443,
+        ws_path: Some("/vless".to_string()),
+        ws_host: Some("origin.example.com".to_string()),
+        sni: Some("front.example.com".to_string()),
+        alias: "user@example.com".to_string(),
+    };
+
+    let links = generate_vless_links(&config);
+    let ws = links.ws.unwrap().vless;
+
+    assert!(ws.starts_with("vless://7fa8b8a5-e2d4-44dc-b3b4-0b72f04397d8@cdn.example.net:443?"));
+    assert!(ws.contains("security=tls&sni=front.example.com"));
+    assert!(ws.contains("&host=origin.example.com"));
+    assert!(ws.contains("path=%2Fvless"));
+    // TCP 链接不受 CDN 参数影响
+    assert!(links.tcp.vless.contains("security=none"));
+}
+
+#[test]
+fn test_generate_user_link_follows_transport() {
+    let uuid = Uuid::parse_str("7fa8b8a5-e2d4-44dc-b3b4-0b72f04397d8").unwrap();
+    let mut config = VlessLinkConfig {
+        uuid,
+        host: "1.2.3.4".to_string(),
+        port: 443,
+        ws_path: None,
+        ws_host: None,
+        sni: None,
+        alias: "alice@example.com".to_string(),
+    };
+
+    let tcp = generate_user_link(&config, Some("alice@example.com".to_string()));
+    assert_eq!(tcp.transport, "tcp");
+    assert!(tcp.vless.contains("type=tcp"));
+    assert_eq!(STANDARD.decode(&tcp.base64).unwrap(), tcp.vless.as_bytes());
+
+    config.ws_path = Some("/ws".to_string());
+    let ws = generate_user_link(&config, None);
+    assert_eq!(ws.transport, "ws");
+    assert!(ws.vless.contains("type=ws"));
+
+    let file = format_links_file(&[tcp, ws]);
+    let lines: Vec<&str> = file.lines().collect();
+    assert_eq!(lines.len(), 4);
+    assert_eq!(lines[0], "# alice@example.com (tcp)");
+    assert_eq!(lines[2], "# 7fa8b8a5-e2d4-44dc-b3b4-0b72f04397d8 (ws)");
+    assert!(lines[3].starts_with("vless://"));
+}
@@ -0,0 +1,82 @@
+//! 配置向导用户导入测试
+
+use vless_rust::wizard::parse_user_import;
+
+const ALICE: &str = "11111111-1111-1111-1111-111111111111";
+const BOB: &str = "22222222-2222-2222-2222-222222222222";
+
+// ============================================================================
+// 格式识别
+// ============================================================================
+
+#[test]
+fn test_import_from_config_json() {
+    let content = format!(
+        r#"{{
+            "server": {{"listen": "0.0.0.0", "port": 443}},
+            "users": [
+                {{"uuid": "{}", "email": "alice"}},
+                {{"uuid": "{}", "blocklist": false}}
+            ]
+        }}"#,
+        ALICE, BOB
+    );
+    let users = parse_user_import(&content).unwrap();
+    assert_eq!(users.len(), 2);
+    assert_eq!(users[0].email.as_deref(), Some("alice"));
+    assert_eq!(users[1].email, None);
+    // 保留原有的拦截列表设置
+    assert!(!users[1].blocklist);
+}
+
+#[test]
+fn test_import_from_vless_links() {
+    let content = format!(
+        "vless://{}@vpn.example.com:443?encryption=none&type=ws&path=%2Fws#alice%40example.com\n\
+         \n\
+         vless://{}@1.2.3.4:8443?encryption=none&type=tcp\n",
+        ALICE, BOB
+    );
+    let users = parse_user_import(&content).unwrap();
+    assert_eq!(users.len(), 2);
+    assert_eq!(users[0].uuid, ALICE);
+    assert_eq!(users[0].email.as_deref(), Some("alice@example.com"));
+    assert_eq!(users[1].uuid, BOB);
+    assert_eq!(users[1].email, None);
+}
+
+#[test]
+fn test_import_from_csv() {
+    let content = format!(
+        "uuid,email\n# 迁移自旧服务器\n{},alice@example.com\n{}\n",
+        ALICE,
+        BOB.to_uppercase()
+    );
+    let users = parse_user_import(&content).unwrap();
+    assert_eq!(users.len(), 2);
+    assert_eq!(users[0].email.as_deref(), Some("alice@example.com"));
+    // UUID 统一为小写
+    assert_eq!(users[1].uuid, BOB);
+    assert!(users[1].blocklist);
+}
+
+// ============================================================================
+// 校验
+// ============================================================================
+
+#[test]
+fn test_import_rejects_invalid_uuid() {
+    let err = parse_user_import(&format!("{},alice\nnot-a-uuid,bob\n", ALICE)).unwrap_err();
+    assert!(err.to_string().contains("Line 2"));
+
+    assert!(parse_user_import("vless://broken@host:443#x").is_err());
+    assert!(parse_user_import("{ not json").is_err());
+}
+
+#[test]
+fn test_import_drops_duplicate_uuids() {
+    let content = format!("{},first\n{},second\n", ALICE, ALICE);
+    let users = parse_user_import(&content).unwrap();
+    assert_eq!(users.len(), 1);
+    assert_eq!(users[0].email.as_deref(), Some("first"));
+}
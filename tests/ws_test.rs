@@ -2,7 +2,7 @@
 
 use base64::{engine::general_purpose::STANDARD as BASE64, Engine as _};
 use sha1_smol::Sha1;
-use vless_rust::http::{extract_http_path, is_http_request, parse_http_request};
+use vless_rust::http::{extract_http_path, host_matches, is_http_request, parse_http_request};
 use vless_rust::ws::is_websocket_upgrade;
 
 /// WebSocket 升级检测测试
@@ -139,6 +139,7 @@ fn test_is_http_request_partial() {
 fn test_parse_http_request_with_params() {
     let data = b"GET /?email=user@example.com HTTP/1.1\r\nHost: localhost\r\n\r\n";
     let query = parse_http_request(data).unwrap();
+    assert_eq!(query.method, "GET");
     assert_eq!(query.path, "/");
     assert_eq!(query.params.get("email").unwrap(), "user@example.com");
 }
@@ -270,3 +271,267 @@ fn test_sha1_hash_incremental() {
 
     assert_eq!(digest_incremental, digest_single);
 }
+
+/// Host 请求头匹配测试（CDN 前置校验）
+#[test]
+fn test_host_matches() {
+    assert!(host_matches("origin.example.com", "origin.example.com"));
+    assert!(host_matches(
+        "Origin.Example.com:8080",
+        "origin.example.com"
+    ));
+    assert!(host_matches("origin.example.com.", "origin.example.com"));
+    assert!(host_matches("[::1]:443", "::1"));
+    assert!(!host_matches("1.2.3.4", "origin.example.com"));
+    assert!(!host_matches("", "origin.example.com"));
+}
+
+/// 空闲时服务端按配置间隔发送 Ping
+#[tokio::test]
+async fn test_ws_proxy_sends_keepalive_ping() {
+    use bytes::Bytes;
+    use futures_util::StreamExt;
+    use std::time::Duration;
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_tungstenite::tungstenite::Message;
+    use vless_rust::config::PerformanceConfig;
+    use vless_rust::protocol::{Address, Command, VlessRequest};
+    use vless_rust::session::SessionServices;
+    use vless_rust::ws::handle_ws_proxy;
+
+    // 目标端：接受连接后保持空闲
+    let target = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let target_port = target.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (_conn, _) = target.accept().await.unwrap();
+        tokio::time::sleep(Duration::from_secs(10)).await;
+    });
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (stream, client_addr) = listener.accept().await.unwrap();
+        let ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        let (sender, receiver) = ws.split();
+        let request = VlessRequest {
+            version: 0,
+            uuid: uuid::Uuid::new_v4(),
+            addons_length: 0,
+            addons: Bytes::new(),
+            command: Command::Tcp,
+            port: target_port,
+            address: Address::Ipv4(std::net::Ipv4Addr::LOCALHOST),
+        };
+        let perf = PerformanceConfig {
+            ws_ping_interval: 1,
+            ..Default::default()
+        };
+        let _ = handle_ws_proxy(
+            sender,
+            receiver,
+            request,
+            None,
+            Bytes::new(),
+            perf,
+            &SessionServices::default(),
+            None,
+            client_addr,
+        )
+        .await;
+    });
+
+    let stream = TcpStream::connect(server_addr).await.unwrap();
+    let (mut client, _) = tokio_tungstenite::client_async(format!("ws://{}/", server_addr), stream)
+        .await
+        .unwrap();
+
+    let message = tokio::time::timeout(Duration::from_secs(5), client.next())
+        .await
+        .expect("no keepalive ping within timeout")
+        .unwrap()
+        .unwrap();
+    assert!(matches!(message, Message::Ping(_)));
+}
+
+/// 开启下行合并后，间隔很短的小包合并为一帧发送
+#[tokio::test]
+async fn test_ws_proxy_coalesces_small_writes() {
+    use bytes::Bytes;
+    use futures_util::StreamExt;
+    use std::time::Duration;
+    use tokio::io::AsyncWriteExt;
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio_tungstenite::tungstenite::Message;
+    use vless_rust::config::PerformanceConfig;
+    use vless_rust::protocol::{Address, Command, VlessRequest};
+    use vless_rust::session::SessionServices;
+    use vless_rust::ws::handle_ws_proxy;
+
+    assert_eq!(PerformanceConfig::default().ws_coalesce_ms, 0);
+
+    // 目标端：连续写入 5 个 10 字节小包后保持连接
+    let target = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let target_port = target.local_addr().unwrap().port();
+    tokio::spawn(async move {
+        let (mut conn, _) = target.accept().await.unwrap();
+        conn.set_nodelay(true).unwrap();
+        for i in 0..5u8 {
+            conn.write_all(&[i; 10]).await.unwrap();
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+        tokio::time::sleep(Duration::from_secs(10)).await;
+    });
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let server_addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        let (stream, client_addr) = listener.accept().await.unwrap();
+        let ws = tokio_tungstenite::accept_async(stream).await.unwrap();
+        let (sender, receiver) = ws.split();
+        let request = VlessRequest {
+            version: 0,
+            uuid: uuid::Uuid::new_v4(),
+            addons_length: 0,
+            addons: Bytes::new(),
+            command: Command::Tcp,
+            port: target_port,
+            address: Address::Ipv4(std::net::Ipv4Addr::LOCALHOST),
+        };
+        let perf = PerformanceConfig {
+            ws_coalesce_ms: 500,
+            ws_ping_interval: 0,
+            ..Default::default()
+        };
+        let _ = handle_ws_proxy(
+            sender,
+            receiver,
+            request,
+            None,
+            Bytes::new(),
+            perf,
+            &SessionServices::default(),
+            None,
+            client_addr,
+        )
+        .await;
+    });
+
+    let stream = TcpStream::connect(server_addr).await.unwrap();
+    let (mut client, _) = tokio_tungstenite::client_async(format!("ws://{}/", server_addr), stream)
+        .await
+        .unwrap();
+
+    let message = tokio::time::timeout(Duration::from_secs(5), client.next())
+        .await
+        .expect("no coalesced frame within timeout")
+        .unwrap()
+        .unwrap();
+    let Message::Binary(data) = message else {
+        panic!("expected a binary frame, got {:?}", message);
+    };
+    assert_eq!(data.len(), 50);
+    assert_eq!(&data[40..], &[4u8; 10]);
+}
+
+// ============================================================================
+// 早期数据
+// ============================================================================
+
+#[test]
+fn test_ws_path_matches_early_data_query() {
+    use vless_rust::ws::ws_path_matches;
+
+    assert!(ws_path_matches("/vless", "/vless"));
+    assert!(ws_path_matches("/vless?ed=2048", "/vless"));
+    assert!(!ws_path_matches("/vless?x=1", "/vless"));
+    assert!(!ws_path_matches("/vless2", "/vless"));
+}
+
+#[test]
+fn test_decode_early_data() {
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use uuid::Uuid;
+    use vless_rust::ws::decode_early_data;
+
+    let data = [0u8, 0xfb, 0xff, 1, 2];
+    let encoded = URL_SAFE_NO_PAD.encode(data);
+    assert_eq!(
+        decode_early_data(&encoded, "/vless?ed=2048")
+            .unwrap()
+            .as_ref(),
+        data
+    );
+    assert_eq!(
+        decode_early_data(
+            &format!("{}==", URL_SAFE_NO_PAD.encode(b"abcd")),
+            "/vless?ed=2048"
+        )
+        .unwrap(),
+        &b"abcd"[..]
+    );
+    assert!(decode_early_data("chat, superchat", "/vless?ed=2048").is_none());
+    assert!(decode_early_data("", "/vless?ed=2048").is_none());
+
+    // 路径不带 ?ed= 时只接受以 VLESS 版本号与 UUID 开头的数据
+    assert!(decode_early_data("chat", "/vless").is_none());
+    assert!(decode_early_data(&encoded, "/vless").is_none());
+    let mut header = vec![1u8];
+    header.extend_from_slice(Uuid::new_v4().as_bytes());
+    header.push(0);
+    let encoded = URL_SAFE_NO_PAD.encode(&header);
+    assert_eq!(decode_early_data(&encoded, "/vless").unwrap(), header);
+    header[0] = 7;
+    let encoded = URL_SAFE_NO_PAD.encode(&header);
+    assert!(decode_early_data(&encoded, "/vless").is_none());
+}
+
+/// `Sec-WebSocket-Protocol` 携带的早期数据作为首条消息，且请求头被回显
+#[tokio::test]
+async fn test_ws_upgrade_with_early_data() {
+    use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+    use std::time::Duration;
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use vless_rust::config::PerformanceConfig;
+    use vless_rust::ws::{detect_ws_connection, WsConnectionResult};
+
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    let server = tokio::spawn(async move {
+        let (stream, _) = listener.accept().await.unwrap();
+        match detect_ws_connection(stream, "/vless", None, PerformanceConfig::default())
+            .await
+            .unwrap()
+        {
+            WsConnectionResult::UpgradeSuccess(_, first_message, _) => first_message,
+            WsConnectionResult::HttpRequest(..) => panic!("expected upgrade"),
+        }
+    });
+
+    let early = URL_SAFE_NO_PAD.encode(b"\x00vless-header");
+    let request = format!(
+        "GET /vless?ed=2048 HTTP/1.1\r\n\
+        Host: example.com\r\n\
+        Upgrade: websocket\r\n\
+        Connection: Upgrade\r\n\
+        Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\n\
+        Sec-WebSocket-Version: 13\r\n\
+        Sec-WebSocket-Protocol: {}\r\n\
+        \r\n",
+        early
+    );
+    let mut client = TcpStream::connect(addr).await.unwrap();
+    client.write_all(request.as_bytes()).await.unwrap();
+
+    let first_message = tokio::time::timeout(Duration::from_secs(5), server)
+        .await
+        .unwrap()
+        .unwrap();
+    assert_eq!(first_message.as_ref(), b"\x00vless-header");
+
+    let mut response = vec![0u8; 1024];
+    let n = client.read(&mut response).await.unwrap();
+    let response = String::from_utf8_lossy(&response[..n]);
+    assert!(response.starts_with("HTTP/1.1 101"));
+    assert!(response.contains(&format!("Sec-WebSocket-Protocol: {}\r\n", early)));
+}
@@ -0,0 +1,246 @@
+//! Xray 迁移导入测试：入站与用户转换、客户端 ID 映射、统计导入与文件生成
+
+use vless_rust::config::{Config, ProtocolType};
+use vless_rust::stats::load_state;
+use vless_rust::xray_import::{
+    client_uuid, import_config, import_files, import_stats, ImportOptions,
+};
+
+const ALICE: &str = "550e8400-e29b-41d4-a716-446655440000";
+const BOB: &str = "6ba7b810-9dad-11d1-80b4-00c04fd430c8";
+
+fn xray_config() -> String {
+    format!(
+        r#"{{
+        "log": {{"loglevel": "warning"}},
+        "inbounds": [
+            {{"tag": "api", "protocol": "dokodemo-door", "port": 10085, "listen": "127.0.0.1"}},
+            {{
+                "tag": "vless-ws",
+                "protocol": "vless",
+                "port": "8443",
+                "settings": {{
+                    "clients": [
+                        {{"id": "{ALICE}", "email": "Alice@example.com", "flow": "xtls-rprx-vision"}},
+                        {{"id": "{BOB}", "email": "bob@example.com"}},
+                        {{"id": "{ALICE}", "email": "alice-dup@example.com"}}
+                    ],
+                    "decryption": "none",
+                    "fallbacks": [
+                        {{"dest": 8080}},
+                        {{"path": "/blog", "dest": "127.0.0.1:8081", "xver": 1}},
+                        {{"dest": "/dev/shm/h2.sock"}}
+                    ]
+                }},
+                "streamSettings": {{
+                    "network": "ws",
+                    "security": "tls",
+                    "wsSettings": {{"path": "/ray", "headers": {{"Host": "cdn.example.com"}}}}
+                }}
+            }},
+            {{
+                "tag": "vless-tcp",
+                "protocol": "vless",
+                "port": 443,
+                "settings": {{"clients": [{{"id": "carol"}}], "decryption": "none"}}
+            }}
+        ]
+    }}"#
+    )
+}
+
+fn warned(warnings: &[String], text: &str) -> bool {
+    warnings.iter().any(|w| w.contains(text))
+}
+
+// ============================================================================
+// 配置转换
+// ============================================================================
+
+#[test]
+fn test_import_config() {
+    let imported = import_config(&xray_config()).unwrap();
+    let config = imported.config;
+    assert_eq!(imported.inbounds, 2);
+
+    // 监听与传输取自第一个 VLESS 入站
+    assert_eq!(config.server.listen, "0.0.0.0");
+    assert_eq!(config.server.port, 8443);
+    assert_eq!(config.server.protocol, ProtocolType::WebSocket);
+    assert_eq!(config.server.ws_path, "/ray");
+    assert_eq!(config.server.ws_host.as_deref(), Some("cdn.example.com"));
+
+    // 用户合并并按 UUID 去重，非 UUID 的 ID 按 Xray 规则映射
+    let users: Vec<(&str, Option<&str>)> = config
+        .users
+        .iter()
+        .map(|u| (u.uuid.as_str(), u.email.as_deref()))
+        .collect();
+    let carol = client_uuid("carol").unwrap().to_string();
+    assert_eq!(
+        users,
+        vec![
+            (ALICE, Some("Alice@example.com")),
+            (BOB, Some("bob@example.com")),
+            (carol.as_str(), None),
+        ]
+    );
+
+    // 端口与 host:port 回落可用，Unix socket 回落被跳过
+    let fallbacks: Vec<(&str, Option<&str>)> = config
+        .fallbacks
+        .iter()
+        .map(|f| (f.dest.as_str(), f.path.as_deref()))
+        .collect();
+    assert_eq!(
+        fallbacks,
+        vec![("127.0.0.1:8080", None), ("127.0.0.1:8081", Some("/blog"))]
+    );
+
+    let warnings = imported.warnings;
+    assert!(warned(
+        &warnings,
+        "Skipping inbound 'api' (protocol dokodemo-door)"
+    ));
+    assert!(warned(
+        &warnings,
+        "flow 'xtls-rprx-vision' is not supported"
+    ));
+    assert!(warned(&warnings, "security 'tls' is not supported"));
+    assert!(warned(&warnings, "duplicate client"));
+    assert!(warned(&warnings, "client id 'carol' mapped to UUID"));
+    assert!(warned(&warnings, "PROXY protocol (xver)"));
+    assert!(warned(
+        &warnings,
+        "fallback to \"/dev/shm/h2.sock\" skipped"
+    ));
+    assert!(warned(
+        &warnings,
+        "users of all 2 VLESS inbounds are merged"
+    ));
+}
+
+#[test]
+fn test_client_uuid_mapping() {
+    // Xray 文档中的映射示例
+    assert_eq!(
+        client_uuid("我爱🍉老师1314").unwrap().to_string(),
+        "5783a3e7-e373-51cd-8642-c83782b807c5"
+    );
+    assert_eq!(client_uuid(ALICE).unwrap().to_string(), ALICE);
+    assert!(client_uuid("").is_none());
+    assert!(client_uuid(&"x".repeat(31)).is_none());
+}
+
+#[test]
+fn test_import_config_errors() {
+    let cases = [
+        ("{", "Invalid Xray config"),
+        (r#"{"outbounds": []}"#, "no inbounds"),
+        (
+            r#"{"inbounds": [{"protocol": "vmess", "port": 443}]}"#,
+            "No VLESS inbound",
+        ),
+        (
+            r#"{"inbounds": [{"protocol": "vless", "port": 443, "settings": {"clients": []}}]}"#,
+            "No VLESS clients",
+        ),
+        (
+            r#"{"inbounds": [{"tag": "g", "protocol": "vless", "port": 443,
+                "settings": {"clients": [{"id": "a"}]}, "streamSettings": {"network": "grpc"}}]}"#,
+            "inbound 'g': transport 'grpc' is not supported",
+        ),
+        (
+            r#"{"inbounds": [{"protocol": "vless", "port": "1000-2000",
+                "settings": {"clients": [{"id": "a"}]}}]}"#,
+            "inbounds[0]: unsupported port \"1000-2000\"",
+        ),
+    ];
+    for (content, expected) in cases {
+        let err = import_config(content).unwrap_err();
+        assert!(err.to_string().contains(expected), "{}: {}", expected, err);
+    }
+}
+
+// ============================================================================
+// 统计导入
+// ============================================================================
+
+const STATS: &str = r#"{"stat": [
+    {"name": "user>>>alice@example.com>>>traffic>>>uplink", "value": "1000"},
+    {"name": "user>>>alice@example.com>>>traffic>>>downlink", "value": 5000},
+    {"name": "user>>>bob@example.com>>>traffic>>>uplink"},
+    {"name": "user>>>bob@example.com>>>traffic>>>downlink", "value": "42"},
+    {"name": "user>>>ghost@example.com>>>traffic>>>uplink", "value": "7"},
+    {"name": "inbound>>>vless-ws>>>traffic>>>uplink", "value": "99999"}
+]}"#;
+
+#[test]
+fn test_import_stats() {
+    let config = import_config(&xray_config()).unwrap().config;
+    let imported = import_stats(STATS, &config).unwrap();
+    let users: Vec<(String, u64, u64)> = imported
+        .stats
+        .users
+        .iter()
+        .map(|u| (u.uuid.to_string(), u.bytes_up, u.bytes_down))
+        .collect();
+    // 邮箱不区分大小写，缺少 value 的计数为 0
+    assert_eq!(
+        users,
+        vec![(ALICE.to_string(), 1000, 5000), (BOB.to_string(), 0, 42)]
+    );
+    assert!(warned(
+        &imported.warnings,
+        "Traffic of unknown user 'ghost@example.com' skipped"
+    ));
+    assert!(warned(&imported.warnings, "1 users have no email"));
+
+    assert!(import_stats(
+        r#"{"stat": [{"name": "user>>>a>>>traffic>>>uplink", "value": "x"}]}"#,
+        &config
+    )
+    .is_err());
+    assert!(import_stats(r#"{"stats": {}}"#, &config).is_err());
+}
+
+// ============================================================================
+// 文件生成
+// ============================================================================
+
+#[test]
+fn test_import_files() {
+    let dir = tempfile::tempdir().unwrap();
+    let xray = dir.path().join("xray.json");
+    let stats = dir.path().join("stats.json");
+    let output = dir.path().join("config.json");
+    std::fs::write(&xray, xray_config()).unwrap();
+    std::fs::write(&stats, STATS).unwrap();
+
+    let mut options = ImportOptions {
+        xray_config: xray.display().to_string(),
+        stats: Some(stats.display().to_string()),
+        output: output.display().to_string(),
+        ..Default::default()
+    };
+    let report = import_files(&options).unwrap();
+    assert_eq!(report.users, 3);
+    assert_eq!(report.inbounds, 2);
+
+    // 状态文件默认与配置同目录，并写入 monitoring.state_file
+    let state_file = dir.path().join("stats-state.json");
+    assert_eq!(report.stats, Some((2, state_file.display().to_string())));
+    let config = Config::from_json(&std::fs::read_to_string(&output).unwrap()).unwrap();
+    assert_eq!(
+        config.monitoring.state_file.as_deref(),
+        Some(state_file.to_str().unwrap())
+    );
+    let saved = load_state(&state_file).unwrap().unwrap();
+    assert_eq!(saved.users.len(), 2);
+
+    // 不覆盖已存在的文件，除非指定 force
+    let err = import_files(&options).unwrap_err();
+    assert!(err.to_string().contains("already exists"), "{}", err);
+    options.force = true;
+    assert!(import_files(&options).is_ok());
+}